@@ -0,0 +1,323 @@
+// abstract_s.rs - Abstract 2PC specification and coordinator refinement
+//
+// This file contains:
+// - AbstractPhase / AbstractTwoPC: minimal 2PC model (undecided, committed, aborted)
+// - Abstraction function from CoordinatorSpec to AbstractTwoPC
+// - Per-transition refinement lemmas: every coordinator transition is either
+//   a stutter or a valid abstract step
+// - decision(): the outcome participants observe, and a proof that it is final
+//
+// Together these state that the coordinator is a correct 2PC coordinator:
+// it decides at most once, and never changes a decision.
+
+use vstd::prelude::*;
+
+use crate::coordinator_s::*;
+use crate::network_s::*;
+
+verus! {
+
+// ============================================================
+// ABSTRACT 2PC MODEL
+// ============================================================
+
+/// Abstract transaction outcome
+#[derive(PartialEq, Eq)]
+pub ghost enum AbstractPhase {
+    /// No decision yet
+    Undecided,
+    /// Decided commit
+    Committed,
+    /// Decided abort
+    Aborted,
+}
+
+/// Abstract 2PC state (ghost/proof only)
+pub ghost struct AbstractTwoPC {
+    pub phase: AbstractPhase,
+}
+
+impl AbstractTwoPC {
+    /// Initial state: undecided
+    pub open spec fn init() -> Self {
+        AbstractTwoPC { phase: AbstractPhase::Undecided }
+    }
+
+    /// Decide commit
+    pub open spec fn commit(self) -> Self
+        recommends
+            self.phase == AbstractPhase::Undecided
+    {
+        AbstractTwoPC { phase: AbstractPhase::Committed }
+    }
+
+    /// Decide abort
+    pub open spec fn abort(self) -> Self
+        recommends
+            self.phase == AbstractPhase::Undecided
+    {
+        AbstractTwoPC { phase: AbstractPhase::Aborted }
+    }
+
+    /// Abstract step relation: stutter, or decide once from Undecided
+    pub open spec fn next(self, post: Self) -> bool {
+        ||| post == self
+        ||| (self.phase == AbstractPhase::Undecided && post == self.commit())
+        ||| (self.phase == AbstractPhase::Undecided && post == self.abort())
+    }
+}
+
+/// Executable transaction outcome, mirroring `AbstractPhase`.
+/// A regular (non-ghost) enum, so exec code can return it.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Decision {
+    /// No decision yet
+    Undecided,
+    /// Decided commit
+    Commit,
+    /// Decided abort
+    Abort,
+}
+
+impl Decision {
+    /// The abstract outcome this value stands for
+    pub open spec fn spec_phase(&self) -> AbstractPhase {
+        match *self {
+            Decision::Undecided => AbstractPhase::Undecided,
+            Decision::Commit => AbstractPhase::Committed,
+            Decision::Abort => AbstractPhase::Aborted,
+        }
+    }
+}
+
+// ============================================================
+// ABSTRACTION FUNCTION
+// ============================================================
+
+impl CoordinatorSpec {
+    /// Map the six concrete phases to the three abstract ones.
+    ///
+    /// The WAL is the commit point, so any state with `wal_committed` is Committed.
+    /// Without it, Idle and Preparing are still Undecided; Cleanup, Done and Crashed
+    /// are Aborted, since recovery from a crash without a WAL commit always aborts.
+    pub open spec fn abstraction(&self) -> AbstractTwoPC {
+        if self.wal_committed {
+            AbstractTwoPC { phase: AbstractPhase::Committed }
+        } else {
+            match self.phase {
+                CoordPhase::Idle | CoordPhase::Preparing =>
+                    AbstractTwoPC { phase: AbstractPhase::Undecided },
+                _ => AbstractTwoPC { phase: AbstractPhase::Aborted },
+            }
+        }
+    }
+
+    /// The transaction outcome other participants observe: Committed, Aborted or
+    /// Undecided. `AbstractPhase` doubles as the decision type.
+    pub open spec fn decision(&self) -> AbstractPhase {
+        self.abstraction().phase
+    }
+
+    /// This coordinator state refines the abstract state `abs`
+    pub open spec fn refines(&self, abs: AbstractTwoPC) -> bool {
+        self.abstraction() == abs
+    }
+}
+
+// ============================================================
+// REFINEMENT LEMMAS
+// ============================================================
+
+/// The initial coordinator refines the initial abstract state
+pub proof fn lemma_init_refines()
+    ensures
+        CoordinatorSpec::init().refines(AbstractTwoPC::init()),
+{
+}
+
+/// Sending a lock request stutters
+pub proof fn lemma_send_lock_req_refines(coord: CoordinatorSpec, store: StoreId)
+    requires
+        coord.phase == CoordPhase::Idle || coord.phase == CoordPhase::Preparing,
+    ensures
+        coord.send_lock_req(store).0.abstraction() == coord.abstraction(),
+{
+}
+
+/// A successful lock response stutters
+pub proof fn lemma_recv_lock_resp_success_refines(coord: CoordinatorSpec, store: StoreId)
+    requires
+        coord.phase == CoordPhase::Preparing,
+    ensures
+        coord.recv_lock_resp_success(store).abstraction() == coord.abstraction(),
+{
+}
+
+/// A failed lock response is an abstract abort (or a stutter if already decided)
+pub proof fn lemma_recv_lock_resp_failure_refines(coord: CoordinatorSpec)
+    requires
+        coord.phase == CoordPhase::Preparing,
+    ensures
+        coord.abstraction().next(coord.recv_lock_resp_failure().abstraction()),
+        !coord.wal_committed ==>
+            coord.recv_lock_resp_failure().abstraction() == coord.abstraction().abort(),
+{
+}
+
+/// Deciding commit is the abstract commit step
+pub proof fn lemma_decide_commit_refines(coord: CoordinatorSpec)
+    requires
+        coord.phase == CoordPhase::Preparing,
+    ensures
+        coord.abstraction().next(coord.decide_commit().abstraction()),
+        !coord.wal_committed ==>
+            coord.decide_commit().abstraction() == coord.abstraction().commit(),
+{
+}
+
+/// Sending a rename request stutters
+pub proof fn lemma_send_rename_req_refines(coord: CoordinatorSpec, store: StoreId)
+    requires
+        coord.phase == CoordPhase::Committed,
+    ensures
+        coord.send_rename_req(store).0.abstraction() == coord.abstraction(),
+{
+}
+
+/// A rename response stutters (including the move to Cleanup)
+pub proof fn lemma_recv_rename_resp_refines(
+    coord: CoordinatorSpec,
+    store: StoreId,
+    all_stores: Set<StoreId>,
+)
+    requires
+        coord.phase == CoordPhase::Committed,
+    ensures
+        coord.recv_rename_resp(store, all_stores).abstraction() == coord.abstraction(),
+{
+}
+
+/// Sending an unlock request stutters
+pub proof fn lemma_send_unlock_req_refines(coord: CoordinatorSpec, store: StoreId)
+    requires
+        coord.phase == CoordPhase::Cleanup,
+    ensures
+        coord.send_unlock_req(store).0.abstraction() == coord.abstraction(),
+{
+}
+
+/// An unlock response stutters (including the move to Done)
+pub proof fn lemma_recv_unlock_resp_refines(coord: CoordinatorSpec, store: StoreId)
+    requires
+        coord.phase == CoordPhase::Cleanup,
+    ensures
+        coord.recv_unlock_resp(store).abstraction() == coord.abstraction(),
+{
+}
+
+/// A crash is a stutter, or an abstract abort if it happens before the commit point
+pub proof fn lemma_crash_refines(coord: CoordinatorSpec)
+    requires
+        coord.phase.spec_can_crash(),
+    ensures
+        coord.abstraction().next(coord.crash().abstraction()),
+        coord.phase == CoordPhase::Preparing && !coord.wal_committed ==>
+            coord.crash().abstraction() == coord.abstraction().abort(),
+{
+}
+
+/// Recovery stutters: it resumes whichever decision the WAL records
+pub proof fn lemma_recover_refines(coord: CoordinatorSpec)
+    requires
+        coord.phase == CoordPhase::Crashed,
+    ensures
+        coord.recover().abstraction() == coord.abstraction(),
+{
+}
+
+/// Every coordinator transition refines an abstract step
+pub proof fn lemma_coordinator_refines_two_pc(
+    coord: CoordinatorSpec,
+    store: StoreId,
+    all_stores: Set<StoreId>,
+)
+    ensures
+        (coord.phase == CoordPhase::Idle || coord.phase == CoordPhase::Preparing) ==>
+            coord.abstraction().next(coord.send_lock_req(store).0.abstraction()),
+        coord.phase == CoordPhase::Preparing ==> {
+            &&& coord.abstraction().next(coord.recv_lock_resp_success(store).abstraction())
+            &&& coord.abstraction().next(coord.recv_lock_resp_failure().abstraction())
+            &&& coord.abstraction().next(coord.decide_commit().abstraction())
+        },
+        coord.phase == CoordPhase::Committed ==> {
+            &&& coord.abstraction().next(coord.send_rename_req(store).0.abstraction())
+            &&& coord.abstraction().next(coord.recv_rename_resp(store, all_stores).abstraction())
+        },
+        coord.phase == CoordPhase::Cleanup ==> {
+            &&& coord.abstraction().next(coord.send_unlock_req(store).0.abstraction())
+            &&& coord.abstraction().next(coord.recv_unlock_resp(store).abstraction())
+        },
+        coord.phase.spec_can_crash() ==>
+            coord.abstraction().next(coord.crash().abstraction()),
+        coord.phase == CoordPhase::Crashed ==>
+            coord.abstraction().next(coord.recover().abstraction()),
+{
+    if coord.phase == CoordPhase::Idle || coord.phase == CoordPhase::Preparing {
+        lemma_send_lock_req_refines(coord, store);
+    }
+    if coord.phase == CoordPhase::Preparing {
+        lemma_recv_lock_resp_success_refines(coord, store);
+        lemma_recv_lock_resp_failure_refines(coord);
+        lemma_decide_commit_refines(coord);
+    }
+    if coord.phase == CoordPhase::Committed {
+        lemma_send_rename_req_refines(coord, store);
+        lemma_recv_rename_resp_refines(coord, store, all_stores);
+    }
+    if coord.phase == CoordPhase::Cleanup {
+        lemma_send_unlock_req_refines(coord, store);
+        lemma_recv_unlock_resp_refines(coord, store);
+    }
+    if coord.phase.spec_can_crash() {
+        lemma_crash_refines(coord);
+    }
+    if coord.phase == CoordPhase::Crashed {
+        lemma_recover_refines(coord);
+    }
+}
+
+/// Lemma: the decision is final. Once Committed or Aborted, every enabled
+/// coordinator transition, including crash and recover, leaves it unchanged.
+pub proof fn lemma_decision_is_final(
+    coord: CoordinatorSpec,
+    store: StoreId,
+    all_stores: Set<StoreId>,
+)
+    requires
+        coord.decision() != AbstractPhase::Undecided,
+    ensures
+        (coord.phase == CoordPhase::Idle || coord.phase == CoordPhase::Preparing) ==>
+            coord.send_lock_req(store).0.decision() == coord.decision(),
+        coord.phase == CoordPhase::Preparing ==> {
+            &&& coord.recv_lock_resp_success(store).decision() == coord.decision()
+            &&& coord.recv_lock_resp_failure().decision() == coord.decision()
+            &&& coord.decide_commit().decision() == coord.decision()
+        },
+        coord.phase == CoordPhase::Committed ==> {
+            &&& coord.send_rename_req(store).0.decision() == coord.decision()
+            &&& coord.recv_rename_resp(store, all_stores).decision() == coord.decision()
+        },
+        coord.phase == CoordPhase::Cleanup ==> {
+            &&& coord.send_unlock_req(store).0.decision() == coord.decision()
+            &&& coord.recv_unlock_resp(store).decision() == coord.decision()
+        },
+        coord.phase.spec_can_crash() ==>
+            coord.crash().decision() == coord.decision(),
+        coord.phase == CoordPhase::Crashed ==>
+            coord.recover().decision() == coord.decision(),
+{
+    // A decided abstract state can only stutter
+    lemma_coordinator_refines_two_pc(coord, store, all_stores);
+}
+
+} // verus!
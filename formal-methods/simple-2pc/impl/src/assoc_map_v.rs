@@ -0,0 +1,209 @@
+// assoc_map_v.rs - Verified association-list map keyed by String
+//
+// This file contains:
+// - AssocMap: a Vec<(String, V)> map with the same interface and View as
+//   vstd::hash_map::StringHashMap
+//
+// KvStore uses it instead of StringHashMap when the `hashmap` feature is off,
+// so the store needs only `alloc` (Vec/String), not std's HashMap. Lookups are
+// O(n), which is fine for the small stores this targets.
+
+use vstd::prelude::*;
+
+verus! {
+
+/// The map described by an association list; later entries win
+pub open spec fn spec_map_of<V>(entries: Seq<(String, V)>) -> Map<Seq<char>, V>
+    decreases entries.len()
+{
+    if entries.len() == 0 {
+        Map::empty()
+    } else {
+        spec_map_of(entries.drop_last()).insert(entries.last().0@, entries.last().1)
+    }
+}
+
+/// Lemma: entries after index `i` whose key differs from `k` do not affect `k`
+pub proof fn lemma_map_of_prefix<V>(entries: Seq<(String, V)>, i: int, k: Seq<char>)
+    requires
+        0 <= i <= entries.len(),
+        forall|j: int| i <= j < entries.len() ==> entries[j].0@ != k,
+    ensures
+        spec_map_of(entries).contains_key(k) == spec_map_of(entries.subrange(0, i)).contains_key(k),
+        spec_map_of(entries).contains_key(k) ==>
+            spec_map_of(entries)[k] == spec_map_of(entries.subrange(0, i))[k],
+    decreases entries.len() - i
+{
+    if i == entries.len() {
+        assert(entries.subrange(0, i) =~= entries);
+    } else {
+        lemma_map_of_prefix(entries.drop_last(), i, k);
+        assert(entries.drop_last().subrange(0, i) =~= entries.subrange(0, i));
+    }
+}
+
+/// Map from String keys to values, stored as an association list
+pub struct AssocMap<V> {
+    entries: Vec<(String, V)>,
+}
+
+impl<V> View for AssocMap<V> {
+    type V = Map<Seq<char>, V>;
+
+    closed spec fn view(&self) -> Map<Seq<char>, V> {
+        spec_map_of(self.entries@)
+    }
+}
+
+impl<V> AssocMap<V> {
+    pub fn new() -> (result: Self)
+        ensures
+            result@ == Map::<Seq<char>, V>::empty(),
+    {
+        AssocMap { entries: Vec::new() }
+    }
+
+    /// Empty map with room for `capacity` entries before reallocating
+    pub fn with_capacity(capacity: usize) -> (result: Self)
+        ensures
+            result@ == Map::<Seq<char>, V>::empty(),
+    {
+        AssocMap { entries: Vec::with_capacity(capacity) }
+    }
+
+    pub fn is_empty(&self) -> (result: bool)
+        ensures
+            result == self@.is_empty(),
+    {
+        proof {
+            if self.entries@.len() > 0 {
+                // The last entry's key is always in the map
+                assert(self@.contains_key(self.entries@.last().0@));
+            } else {
+                assert(self@ =~= Map::<Seq<char>, V>::empty());
+            }
+        }
+        self.entries.len() == 0
+    }
+
+    pub fn get<'a>(&'a self, k: &str) -> (result: Option<&'a V>)
+        ensures
+            match result {
+                Some(v) => self@.contains_key(k@) && *v == self@[k@],
+                None => !self@.contains_key(k@),
+            },
+    {
+        // Scan from the back: the last entry for a key is the live one
+        let mut i: usize = self.entries.len();
+        while i > 0
+            invariant
+                0 <= i <= self.entries@.len(),
+                forall|j: int| i <= j < self.entries@.len() ==> self.entries@[j].0@ != k@,
+            decreases
+                i,
+        {
+            if self.entries[i - 1].0.as_str() == k {
+                proof {
+                    lemma_map_of_prefix(self.entries@, i as int, k@);
+                    assert(self.entries@.subrange(0, i as int).drop_last()
+                        =~= self.entries@.subrange(0, i as int - 1));
+                }
+                return Some(&self.entries[i - 1].1);
+            }
+            i = i - 1;
+        }
+        proof {
+            lemma_map_of_prefix(self.entries@, 0, k@);
+            assert(self.entries@.subrange(0, 0) =~= Seq::<(String, V)>::empty());
+        }
+        None
+    }
+
+    pub fn contains_key(&self, k: &str) -> (result: bool)
+        ensures
+            result == self@.contains_key(k@),
+    {
+        self.get(k).is_some()
+    }
+
+    pub fn remove(&mut self, k: &str)
+        ensures
+            self@ == old(self)@.remove(k@),
+    {
+        // Drop every entry for k in place; `j` tracks the matching position in the
+        // original list, whose prefix (minus k) the kept prefix [0, i) represents
+        let ghost all = self.entries@;
+        let ghost mut j: int = 0;
+        let mut i: usize = 0;
+        while i < self.entries.len()
+            invariant
+                0 <= i <= self.entries@.len(),
+                0 <= j <= all.len(),
+                self.entries@.len() - i == all.len() - j,
+                self.entries@.subrange(i as int, self.entries@.len() as int) == all.subrange(j, all.len() as int),
+                spec_map_of(self.entries@.subrange(0, i as int)) == spec_map_of(all.subrange(0, j)).remove(k@),
+            decreases
+                self.entries@.len() - i,
+        {
+            proof {
+                assert(self.entries@[i as int] == all[j]);
+                assert(all.subrange(0, j + 1).drop_last() =~= all.subrange(0, j));
+            }
+            if self.entries[i].0.as_str() == k {
+                let ghost kept = self.entries@.subrange(0, i as int);
+                self.entries.remove(i);
+                proof {
+                    assert(self.entries@.subrange(0, i as int) =~= kept);
+                    assert(spec_map_of(all.subrange(0, j + 1)).remove(k@)
+                        =~= spec_map_of(all.subrange(0, j)).remove(k@));
+                    j = j + 1;
+                    assert(self.entries@.subrange(i as int, self.entries@.len() as int)
+                        =~= all.subrange(j, all.len() as int));
+                }
+            } else {
+                proof {
+                    assert(self.entries@.subrange(0, i as int + 1).drop_last()
+                        =~= self.entries@.subrange(0, i as int));
+                    assert(spec_map_of(self.entries@.subrange(0, i as int + 1))
+                        =~= spec_map_of(all.subrange(0, j + 1)).remove(k@));
+                    j = j + 1;
+                    assert(self.entries@.subrange(i as int + 1, self.entries@.len() as int)
+                        =~= all.subrange(j, all.len() as int));
+                }
+                i = i + 1;
+            }
+        }
+        proof {
+            assert(self.entries@.subrange(0, i as int) =~= self.entries@);
+            assert(all.subrange(0, j) =~= all);
+        }
+    }
+
+    pub fn insert(&mut self, k: String, v: V)
+        ensures
+            self@ == old(self)@.insert(k@, v),
+    {
+        self.remove(k.as_str());
+        let ghost removed = self.entries@;
+        self.entries.push((k, v));
+        proof {
+            assert(self.entries@.drop_last() =~= removed);
+            assert(self@ =~= old(self)@.insert(k@, v));
+        }
+    }
+
+    pub fn clear(&mut self)
+        ensures
+            self@ == Map::<Seq<char>, V>::empty(),
+    {
+        self.entries.clear();
+    }
+}
+
+} // verus!
+
+impl<V> Default for AssocMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
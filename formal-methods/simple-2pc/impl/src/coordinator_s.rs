@@ -105,6 +105,105 @@ impl CoordPhase {
             _ => false,
         }
     }
+
+    /// Phases reachable in one (non-stuttering) transition (spec function)
+    pub open spec fn spec_successors(&self) -> Seq<CoordPhase> {
+        match *self {
+            CoordPhase::Idle => seq![CoordPhase::Preparing],
+            CoordPhase::Preparing => seq![CoordPhase::Committed, CoordPhase::Cleanup, CoordPhase::Crashed],
+            CoordPhase::Committed => seq![CoordPhase::Cleanup, CoordPhase::Crashed],
+            CoordPhase::Cleanup => seq![CoordPhase::Done, CoordPhase::Crashed],
+            CoordPhase::Done => seq![],
+            CoordPhase::Crashed => seq![CoordPhase::Committed, CoordPhase::Cleanup],
+        }
+    }
+
+    /// Phases reachable in one (non-stuttering) transition (exec function)
+    pub fn successors(&self) -> (result: Vec<CoordPhase>)
+        ensures
+            result@ == self.spec_successors()
+    {
+        let mut result: Vec<CoordPhase> = Vec::new();
+        match *self {
+            CoordPhase::Idle => {
+                result.push(CoordPhase::Preparing);
+            },
+            CoordPhase::Preparing => {
+                result.push(CoordPhase::Committed);
+                result.push(CoordPhase::Cleanup);
+                result.push(CoordPhase::Crashed);
+            },
+            CoordPhase::Committed => {
+                result.push(CoordPhase::Cleanup);
+                result.push(CoordPhase::Crashed);
+            },
+            CoordPhase::Cleanup => {
+                result.push(CoordPhase::Done);
+                result.push(CoordPhase::Crashed);
+            },
+            CoordPhase::Done => {},
+            CoordPhase::Crashed => {
+                result.push(CoordPhase::Committed);
+                result.push(CoordPhase::Cleanup);
+            },
+        }
+        proof {
+            assert(result@ =~= self.spec_successors());
+        }
+        result
+    }
+
+    /// Every phase, in declaration order (spec function)
+    pub open spec fn spec_all() -> Seq<CoordPhase> {
+        seq![
+            CoordPhase::Idle,
+            CoordPhase::Preparing,
+            CoordPhase::Committed,
+            CoordPhase::Cleanup,
+            CoordPhase::Done,
+            CoordPhase::Crashed,
+        ]
+    }
+
+    /// Every phase, in declaration order (exec function), for exhaustive tests
+    pub fn all() -> (result: Vec<CoordPhase>)
+        ensures
+            result@ == Self::spec_all()
+    {
+        let mut result: Vec<CoordPhase> = Vec::new();
+        result.push(CoordPhase::Idle);
+        result.push(CoordPhase::Preparing);
+        result.push(CoordPhase::Committed);
+        result.push(CoordPhase::Cleanup);
+        result.push(CoordPhase::Done);
+        result.push(CoordPhase::Crashed);
+        proof {
+            assert(result@ =~= Self::spec_all());
+        }
+        result
+    }
+
+    /// Phase a recovering coordinator resumes in, from its durable WAL alone (spec function)
+    pub open spec fn spec_resume_phase(wal_committed: bool) -> CoordPhase {
+        if wal_committed {
+            CoordPhase::Committed
+        } else {
+            CoordPhase::Cleanup
+        }
+    }
+
+    /// Phase a recovering coordinator resumes in (exec function):
+    /// Committed if COMMIT is in the WAL, otherwise Cleanup (abort)
+    pub fn resume_phase(wal_committed: bool) -> (result: CoordPhase)
+        ensures
+            result == Self::spec_resume_phase(wal_committed)
+    {
+        if wal_committed {
+            CoordPhase::Committed
+        } else {
+            CoordPhase::Cleanup
+        }
+    }
 }
 
 // ============================================================
@@ -119,6 +218,9 @@ pub ghost struct CoordinatorSpec {
     pub current_txn_id: TxnId,
     /// Whether COMMIT is recorded in WAL
     pub wal_committed: bool,
+    /// Stores sent a LockReq, logged before each send so that cleanup (even
+    /// after recovery) reaches every store that may hold a lock
+    pub lock_requested: Set<StoreId>,
 
     // ===== Volatile state (lost on crash) =====
     /// Current phase of the protocol
@@ -181,6 +283,24 @@ impl CoordinatorSpec {
         self.unlocks_acked == stores
     }
 
+    /// Field-by-field state equality (sets compared extensionally)
+    pub open spec fn eq_state(self, other: CoordinatorSpec) -> bool {
+        &&& self.current_txn_id == other.current_txn_id
+        &&& self.wal_committed == other.wal_committed
+        &&& self.lock_requested =~= other.lock_requested
+        &&& self.phase == other.phase
+        &&& self.locks_acquired =~= other.locks_acquired
+        &&& self.renames_done =~= other.renames_done
+        &&& self.unlocks_acked =~= other.unlocks_acked
+    }
+
+    /// Stores that must be sent UnlockReq during cleanup: every store a LockReq
+    /// was sent to. A grant may still be in flight (or the LockReq not yet
+    /// delivered), so the recorded grants alone would orphan late locks
+    pub open spec fn unlock_targets(&self) -> Set<StoreId> {
+        self.lock_requested
+    }
+
     // ============================================================
     // SPEC FUNCTIONS - State transitions
     // ============================================================
@@ -190,6 +310,7 @@ impl CoordinatorSpec {
         CoordinatorSpec {
             current_txn_id: 1,
             wal_committed: false,
+            lock_requested: Set::empty(),
             phase: CoordPhase::Idle,
             locks_acquired: Set::empty(),
             renames_done: Set::empty(),
@@ -204,6 +325,7 @@ impl CoordinatorSpec {
             self.phase == CoordPhase::Idle || self.phase == CoordPhase::Preparing
     {
         let new_state = CoordinatorSpec {
+            lock_requested: self.lock_requested.insert(store),
             phase: CoordPhase::Preparing,
             ..self
         };
@@ -224,13 +346,13 @@ impl CoordinatorSpec {
     }
 
     /// Receive failed lock response - transition to cleanup
+    /// Cleanup unlocks every store in lock_requested, granted or not
     pub open spec fn recv_lock_resp_failure(self) -> Self
         recommends
             self.phase == CoordPhase::Preparing
     {
         CoordinatorSpec {
             phase: CoordPhase::Cleanup,
-            locks_acquired: Set::empty(),
             renames_done: Set::empty(),
             unlocks_acked: Set::empty(),
             ..self
@@ -288,14 +410,24 @@ impl CoordinatorSpec {
         (self, msg)
     }
 
+    /// Send the decision instead of an unlock request (one-phase finalization)
+    /// Returns (new_state, message_to_send); acknowledged like an unlock request
+    pub open spec fn send_decide(self, store: StoreId) -> (Self, Message)
+        recommends
+            self.phase == CoordPhase::Cleanup
+    {
+        let msg = decide_msg(store, self.wal_committed, self.current_txn_id);
+        (self, msg)
+    }
+
     /// Receive unlock response
-    pub open spec fn recv_unlock_resp(self, store: StoreId, all_stores: Set<StoreId>) -> Self
+    pub open spec fn recv_unlock_resp(self, store: StoreId) -> Self
         recommends
             self.phase == CoordPhase::Cleanup,
             !self.unlocks_acked.contains(store),
     {
         let new_unlocks = self.unlocks_acked.insert(store);
-        let new_phase = if new_unlocks == all_stores {
+        let new_phase = if self.unlock_targets().subset_of(new_unlocks) {
             CoordPhase::Done
         } else {
             self.phase
@@ -316,6 +448,7 @@ impl CoordinatorSpec {
             // Durable state preserved
             current_txn_id: self.current_txn_id,
             wal_committed: self.wal_committed,
+            lock_requested: self.lock_requested,
             // Volatile state lost
             phase: CoordPhase::Crashed,
             locks_acquired: Set::empty(),
@@ -329,30 +462,28 @@ impl CoordinatorSpec {
         recommends
             self.phase == CoordPhase::Crashed
     {
-        let new_txn_id = self.current_txn_id + 1;
-        if self.wal_committed {
-            // Committed - resume commit phase
-            CoordinatorSpec {
-                current_txn_id: new_txn_id,
-                wal_committed: self.wal_committed,
-                phase: CoordPhase::Committed,
-                locks_acquired: Set::empty(),
-                renames_done: Set::empty(),
-                unlocks_acked: Set::empty(),
-            }
-        } else {
-            // Not committed - go to cleanup
-            CoordinatorSpec {
-                current_txn_id: new_txn_id,
-                wal_committed: self.wal_committed,
-                phase: CoordPhase::Cleanup,
-                locks_acquired: Set::empty(),
-                renames_done: Set::empty(),
-                unlocks_acked: Set::empty(),
-            }
+        // Committed resumes the commit phase; otherwise go to cleanup
+        CoordinatorSpec {
+            current_txn_id: self.current_txn_id + 1,
+            wal_committed: self.wal_committed,
+            lock_requested: self.lock_requested,
+            phase: CoordPhase::spec_resume_phase(self.wal_committed),
+            locks_acquired: Set::empty(),
+            renames_done: Set::empty(),
+            unlocks_acked: Set::empty(),
         }
     }
 
+    /// Rebuild the lock grants from what the stores report holding, so a
+    /// recovered coordinator need not start from an empty set. Only meaningful
+    /// just after recovery into Committed or Cleanup
+    pub open spec fn rebuild_locks(self, reported: Set<StoreId>) -> Self
+        recommends
+            self.phase == CoordPhase::Committed || self.phase == CoordPhase::Cleanup,
+    {
+        CoordinatorSpec { locks_acquired: reported, ..self }
+    }
+
     // ============================================================
     // PROOF LEMMAS - Properties of coordinator
     // ============================================================
@@ -364,6 +495,8 @@ impl CoordinatorSpec {
         ensures
             self.crash().current_txn_id == self.current_txn_id,
             self.crash().wal_committed == self.wal_committed,
+            self.crash().unlock_targets() == self.unlock_targets(),
+            self.crash().recover().unlock_targets() == self.unlock_targets(),
     {
     }
 
@@ -405,6 +538,45 @@ impl CoordinatorSpec {
     {
     }
 
+    /// Recovery resumes in exactly the phase the WAL maps to
+    pub proof fn lemma_recover_resume_phase(self)
+        requires
+            self.phase == CoordPhase::Crashed,
+        ensures
+            self.recover().phase == CoordPhase::spec_resume_phase(self.wal_committed),
+    {
+    }
+
+    /// Rebuilding lock grants after recovery changes only `locks_acquired`:
+    /// the durable state and the resumed phase are kept
+    pub proof fn lemma_rebuild_locks_preserves_durable_and_phase(self, reported: Set<StoreId>)
+        requires
+            self.phase == CoordPhase::Crashed,
+        ensures
+            self.recover().rebuild_locks(reported).current_txn_id == self.recover().current_txn_id,
+            self.recover().rebuild_locks(reported).wal_committed == self.wal_committed,
+            self.recover().rebuild_locks(reported).phase == CoordPhase::spec_resume_phase(self.wal_committed),
+            self.recover().rebuild_locks(reported).locks_acquired == reported,
+    {
+    }
+
+    /// Crashing again mid-recovery is safe: recovery resumes in Committed or
+    /// Cleanup, both crashable, so crash/recover can repeat. A second recovery
+    /// lands in the same phase with the same WAL, one txn id further on
+    pub proof fn lemma_double_crash_recover(self)
+        requires
+            self.phase.spec_can_crash(),
+        ensures
+            self.crash().recover().phase.spec_can_crash(),
+            self.crash().recover().crash().recover().current_txn_id == self.current_txn_id + 2,
+            self.crash().recover().crash().recover().wal_committed == self.wal_committed,
+            self.crash().recover().crash().recover() == (CoordinatorSpec {
+                current_txn_id: self.crash().recover().current_txn_id + 1,
+                ..self.crash().recover()
+            }),
+    {
+    }
+
     /// Decide commit sets wal_committed
     pub proof fn lemma_decide_commit_sets_wal(self)
         requires
@@ -433,6 +605,53 @@ impl CoordinatorSpec {
             self.recv_lock_resp_failure().phase == CoordPhase::Cleanup,
     {
     }
+
+    /// Every transition is deterministic: `eq_state` inputs give `eq_state`
+    /// outputs (and identical messages), so branching proofs can treat two
+    /// copies of a state as one
+    pub proof fn lemma_eq_state_transitions_deterministic(
+        self,
+        other: CoordinatorSpec,
+        store: StoreId,
+        all_stores: Set<StoreId>,
+    )
+        requires
+            self.eq_state(other),
+        ensures
+            self == other,
+            self.send_lock_req(store).0.eq_state(other.send_lock_req(store).0),
+            self.send_lock_req(store).1 == other.send_lock_req(store).1,
+            self.recv_lock_resp_success(store).eq_state(other.recv_lock_resp_success(store)),
+            self.recv_lock_resp_failure().eq_state(other.recv_lock_resp_failure()),
+            self.decide_commit().eq_state(other.decide_commit()),
+            self.send_rename_req(store).0.eq_state(other.send_rename_req(store).0),
+            self.send_rename_req(store).1 == other.send_rename_req(store).1,
+            self.recv_rename_resp(store, all_stores).eq_state(other.recv_rename_resp(store, all_stores)),
+            self.send_unlock_req(store).0.eq_state(other.send_unlock_req(store).0),
+            self.send_unlock_req(store).1 == other.send_unlock_req(store).1,
+            self.recv_unlock_resp(store).eq_state(other.recv_unlock_resp(store)),
+            self.crash().eq_state(other.crash()),
+            self.recover().eq_state(other.recover()),
+    {
+        assert(self.lock_requested == other.lock_requested);
+        assert(self.locks_acquired == other.locks_acquired);
+        assert(self.renames_done == other.renames_done);
+        assert(self.unlocks_acked == other.unlocks_acked);
+    }
+
+    /// Aborting unlocks every store a LockReq was sent to, whether or not its
+    /// grant has been recorded: a grant still in flight is not orphaned
+    pub proof fn lemma_abort_unlocks_all_requested(self, store: StoreId)
+        requires
+            self.phase == CoordPhase::Preparing,
+        ensures
+            self.recv_lock_resp_failure().unlock_targets() == self.lock_requested,
+            self.lock_requested.contains(store) ==>
+                self.recv_lock_resp_failure().unlock_targets().contains(store),
+            self.locks_acquired.subset_of(self.lock_requested) ==>
+                self.locks_acquired.subset_of(self.recv_lock_resp_failure().unlock_targets()),
+    {
+    }
 }
 
 // ============================================================
@@ -444,12 +663,57 @@ pub open spec fn committed_implies_wal(coord: CoordinatorSpec) -> bool {
     coord.phase == CoordPhase::Committed ==> coord.wal_committed
 }
 
+/// The WAL and the phase agree: Committed is only reached with the COMMIT
+/// record written, and once written the coordinator never returns to
+/// Idle or Preparing
+pub open spec fn durable_consistent(coord: CoordinatorSpec) -> bool {
+    &&& committed_implies_wal(coord)
+    &&& coord.wal_committed ==> coord.phase != CoordPhase::Idle && coord.phase != CoordPhase::Preparing
+}
+
 /// Done phase with wal_committed means successful completion
 pub open spec fn done_means_success(coord: CoordinatorSpec) -> bool {
     (coord.phase == CoordPhase::Done && coord.wal_committed) ==>
         coord.all_renames_done(coord.renames_done)
 }
 
+/// Acknowledgement sets are only filled in their own phases: rename acks are
+/// recorded from Committed on, unlock acks from Cleanup on
+pub open spec fn acquisition_phase_consistent(coord: CoordinatorSpec) -> bool {
+    &&& !(coord.phase == CoordPhase::Committed || coord.phase == CoordPhase::Cleanup
+            || coord.phase == CoordPhase::Done) ==> coord.renames_done == Set::<StoreId>::empty()
+    &&& !(coord.phase == CoordPhase::Cleanup || coord.phase == CoordPhase::Done)
+            ==> coord.unlocks_acked == Set::<StoreId>::empty()
+}
+
+/// Lemma: every coordinator transition, taken in the phase that enables it,
+/// preserves acquisition_phase_consistent, so a driver can never record a
+/// rename before commit or an unlock before cleanup
+pub proof fn lemma_acquisition_phase_consistency(
+    coord: CoordinatorSpec,
+    store: StoreId,
+    all_stores: Set<StoreId>,
+)
+    requires
+        acquisition_phase_consistent(coord),
+    ensures
+        acquisition_phase_consistent(CoordinatorSpec::init()),
+        (coord.phase == CoordPhase::Idle || coord.phase == CoordPhase::Preparing) ==>
+            acquisition_phase_consistent(coord.send_lock_req(store).0),
+        acquisition_phase_consistent(coord.recv_lock_resp_success(store)),
+        acquisition_phase_consistent(coord.recv_lock_resp_failure()),
+        coord.phase == CoordPhase::Preparing ==> acquisition_phase_consistent(coord.decide_commit()),
+        acquisition_phase_consistent(coord.send_rename_req(store).0),
+        coord.phase == CoordPhase::Committed ==>
+            acquisition_phase_consistent(coord.recv_rename_resp(store, all_stores)),
+        acquisition_phase_consistent(coord.send_unlock_req(store).0),
+        coord.phase == CoordPhase::Cleanup ==>
+            acquisition_phase_consistent(coord.recv_unlock_resp(store)),
+        acquisition_phase_consistent(coord.crash()),
+        acquisition_phase_consistent(coord.recover()),
+{
+}
+
 /// Lemma: decide_commit establishes committed_implies_wal invariant
 pub proof fn lemma_decide_commit_invariant(coord: CoordinatorSpec)
     requires
@@ -459,5 +723,239 @@ pub proof fn lemma_decide_commit_invariant(coord: CoordinatorSpec)
 {
 }
 
-} // verus!
+/// Lemma: commit point uniqueness - decide_commit is the only transition that
+/// sets wal_committed; every other transition (including crash and recover)
+/// preserves it. Recovery relies on this to decide between resume and abort.
+pub proof fn lemma_unique_commit_point(
+    coord: CoordinatorSpec,
+    store: StoreId,
+    all_stores: Set<StoreId>,
+)
+    ensures
+        // The single commit point
+        coord.decide_commit().wal_committed,
+        // Every other transition preserves wal_committed
+        coord.send_lock_req(store).0.wal_committed == coord.wal_committed,
+        coord.recv_lock_resp_success(store).wal_committed == coord.wal_committed,
+        coord.recv_lock_resp_failure().wal_committed == coord.wal_committed,
+        coord.send_rename_req(store).0.wal_committed == coord.wal_committed,
+        coord.recv_rename_resp(store, all_stores).wal_committed == coord.wal_committed,
+        coord.send_unlock_req(store).0.wal_committed == coord.wal_committed,
+        coord.recv_unlock_resp(store).wal_committed == coord.wal_committed,
+        coord.crash().wal_committed == coord.wal_committed,
+        coord.recover().wal_committed == coord.wal_committed,
+        // The initial state has not committed
+        !CoordinatorSpec::init().wal_committed,
+{
+}
+
+/// Lemma: every coordinator transition either stutters (phase unchanged) or
+/// moves to one of the old phase's successors
+pub proof fn lemma_transitions_follow_successors(
+    coord: CoordinatorSpec,
+    store: StoreId,
+    all_stores: Set<StoreId>,
+)
+    ensures
+        // Idle
+        coord.phase == CoordPhase::Idle ==>
+            coord.phase.spec_successors().contains(coord.send_lock_req(store).0.phase),
+        // Preparing
+        coord.phase == CoordPhase::Preparing ==> {
+            &&& coord.send_lock_req(store).0.phase == coord.phase
+            &&& coord.recv_lock_resp_success(store).phase == coord.phase
+            &&& coord.phase.spec_successors().contains(coord.decide_commit().phase)
+            &&& coord.phase.spec_successors().contains(coord.recv_lock_resp_failure().phase)
+            &&& coord.phase.spec_successors().contains(coord.crash().phase)
+        },
+        // Committed
+        coord.phase == CoordPhase::Committed ==> {
+            &&& coord.send_rename_req(store).0.phase == coord.phase
+            &&& (coord.recv_rename_resp(store, all_stores).phase == coord.phase
+                || coord.phase.spec_successors().contains(coord.recv_rename_resp(store, all_stores).phase))
+            &&& coord.phase.spec_successors().contains(coord.crash().phase)
+        },
+        // Cleanup
+        coord.phase == CoordPhase::Cleanup ==> {
+            &&& coord.send_unlock_req(store).0.phase == coord.phase
+            &&& (coord.recv_unlock_resp(store).phase == coord.phase
+                || coord.phase.spec_successors().contains(coord.recv_unlock_resp(store).phase))
+            &&& coord.phase.spec_successors().contains(coord.crash().phase)
+        },
+        // Crashed
+        coord.phase == CoordPhase::Crashed ==>
+            coord.phase.spec_successors().contains(coord.recover().phase),
+{
+    // Witness indices for each successor sequence
+    assert(CoordPhase::Idle.spec_successors()[0] == CoordPhase::Preparing);
+    assert(CoordPhase::Preparing.spec_successors()[0] == CoordPhase::Committed);
+    assert(CoordPhase::Preparing.spec_successors()[1] == CoordPhase::Cleanup);
+    assert(CoordPhase::Preparing.spec_successors()[2] == CoordPhase::Crashed);
+    assert(CoordPhase::Committed.spec_successors()[0] == CoordPhase::Cleanup);
+    assert(CoordPhase::Committed.spec_successors()[1] == CoordPhase::Crashed);
+    assert(CoordPhase::Cleanup.spec_successors()[0] == CoordPhase::Done);
+    assert(CoordPhase::Cleanup.spec_successors()[1] == CoordPhase::Crashed);
+    assert(CoordPhase::Crashed.spec_successors()[0] == CoordPhase::Committed);
+    assert(CoordPhase::Crashed.spec_successors()[1] == CoordPhase::Cleanup);
+}
+
+// ============================================================
+// WAL RECONCILIATION
+// ============================================================
+
+/// Order on durable WAL records (txn id, committed): a later txn supersedes an
+/// earlier one, and within a txn a COMMIT record supersedes its absence
+pub open spec fn wal_le(a: (nat, bool), b: (nat, bool)) -> bool {
+    a.0 < b.0 || (a.0 == b.0 && (!a.1 || b.1))
+}
+
+/// Reconcile two replicated WAL copies: the highest txn id wins, and for the
+/// same txn "commit wins". A torn write can lose a COMMIT record but never
+/// invent one, and decide_commit is the unique commit point, so a copy showing
+/// COMMIT means stores may already have renamed; aborting would break atomicity.
+pub open spec fn spec_reconcile_wal(a: (nat, bool), b: (nat, bool)) -> (nat, bool) {
+    if a.0 > b.0 {
+        a
+    } else if b.0 > a.0 {
+        b
+    } else {
+        (a.0, a.1 || b.1)
+    }
+}
+
+/// Lemma: reconciliation is the least upper bound of the two copies, so the
+/// reconciled decision never moves backwards: it supersedes both inputs, and
+/// superseding either input can only move the result forward
+pub proof fn lemma_reconcile_wal_monotone(a: (nat, bool), b: (nat, bool), a2: (nat, bool))
+    requires
+        wal_le(a, a2),
+    ensures
+        wal_le(a, spec_reconcile_wal(a, b)),
+        wal_le(b, spec_reconcile_wal(a, b)),
+        spec_reconcile_wal(a, b) == spec_reconcile_wal(b, a),
+        spec_reconcile_wal(a, a) == a,
+        wal_le(spec_reconcile_wal(a, b), spec_reconcile_wal(a2, b)),
+        // Once either copy has committed its txn, the reconciled record has too
+        (a.1 && a.0 >= b.0) ==> spec_reconcile_wal(a, b).1,
+{
+}
 
+/// Acquisition sets (and the stores sent a LockReq) only ever contain
+/// participant stores (a response from a non-participant would corrupt the
+/// completion check)
+pub open spec fn acquisitions_within_participants(
+    coord: CoordinatorSpec,
+    all_stores: Set<StoreId>,
+) -> bool {
+    &&& coord.lock_requested.subset_of(all_stores)
+    &&& coord.locks_acquired.subset_of(all_stores)
+    &&& coord.renames_done.subset_of(all_stores)
+    &&& coord.unlocks_acked.subset_of(all_stores)
+}
+
+/// Lemma: every coordinator transition preserves acquisitions_within_participants,
+/// as long as the responding store is a participant
+pub proof fn lemma_acquisitions_within_participants(
+    coord: CoordinatorSpec,
+    all_stores: Set<StoreId>,
+    store: StoreId,
+)
+    requires
+        acquisitions_within_participants(coord, all_stores),
+        all_stores.contains(store),
+    ensures
+        acquisitions_within_participants(CoordinatorSpec::init(), all_stores),
+        acquisitions_within_participants(coord.send_lock_req(store).0, all_stores),
+        acquisitions_within_participants(coord.recv_lock_resp_success(store), all_stores),
+        acquisitions_within_participants(coord.recv_lock_resp_failure(), all_stores),
+        acquisitions_within_participants(coord.decide_commit(), all_stores),
+        acquisitions_within_participants(coord.send_rename_req(store).0, all_stores),
+        acquisitions_within_participants(coord.recv_rename_resp(store, all_stores), all_stores),
+        acquisitions_within_participants(coord.send_unlock_req(store).0, all_stores),
+        acquisitions_within_participants(coord.recv_unlock_resp(store), all_stores),
+        acquisitions_within_participants(coord.crash(), all_stores),
+        acquisitions_within_participants(coord.recover(), all_stores),
+{
+    assert(coord.lock_requested.insert(store).subset_of(all_stores));
+    assert(coord.locks_acquired.insert(store).subset_of(all_stores));
+    assert(coord.renames_done.insert(store).subset_of(all_stores));
+    assert(coord.unlocks_acked.insert(store).subset_of(all_stores));
+}
+
+// ============================================================
+// TESTS
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test: decide_commit is deterministic - equal inputs give equal outputs
+    proof fn test_decide_commit_deterministic() {
+        let a = CoordinatorSpec::init().send_lock_req(0).0.recv_lock_resp_success(0);
+        let b = CoordinatorSpec {
+            locks_acquired: Set::empty().insert(0nat),
+            ..CoordinatorSpec::init().send_lock_req(0).0
+        };
+        assert(a.locks_acquired =~= b.locks_acquired);
+        assert(a.eq_state(b));
+        a.lemma_eq_state_transitions_deterministic(b, 0, Set::empty().insert(0nat));
+        assert(a.decide_commit().eq_state(b.decide_commit()));
+        assert(a.decide_commit().wal_committed);
+    }
+
+    /// Test: crash, recover, crash again during cleanup, recover again
+    proof fn test_double_crash_recover() {
+        let coord = CoordinatorSpec::init().send_lock_req(0).0;
+        coord.lemma_double_crash_recover();
+        let twice = coord.crash().recover().crash().recover();
+        assert(twice.phase == CoordPhase::Cleanup);
+        assert(twice.current_txn_id == coord.current_txn_id + 2);
+        assert(!twice.wal_committed);
+    }
+
+    /// Test: a recovered committed coordinator rebuilds grants from two reports
+    proof fn test_rebuild_locks_after_recovery() {
+        let all = Set::empty().insert(0nat).insert(1nat);
+        let committed = CoordinatorSpec::init().send_lock_req(0).0.send_lock_req(1).0
+            .recv_lock_resp_success(0).recv_lock_resp_success(1).decide_commit();
+        let crashed = committed.crash();
+        crashed.lemma_rebuild_locks_preserves_durable_and_phase(all);
+        let rebuilt = crashed.recover().rebuild_locks(all);
+        assert(rebuilt.phase == CoordPhase::Committed);
+        assert(rebuilt.current_txn_id == 2);
+        assert(rebuilt.locks_acquired.contains(1nat));
+        assert(rebuilt.lock_requested =~= all);
+        assert(rebuilt.unlock_targets() == all);
+    }
+
+    /// Test: acks stay phase-consistent along a full commit run, and recording a
+    /// rename ack before commit breaks the invariant
+    proof fn test_acquisition_phase_consistency() {
+        let all = Set::empty().insert(0nat);
+        let c0 = CoordinatorSpec::init();
+        lemma_acquisition_phase_consistency(c0, 0, all);
+        let c1 = c0.send_lock_req(0).0;
+        lemma_acquisition_phase_consistency(c1, 0, all);
+        let c2 = c1.recv_lock_resp_success(0);
+        lemma_acquisition_phase_consistency(c2, 0, all);
+        let c3 = c2.decide_commit();
+        lemma_acquisition_phase_consistency(c3, 0, all);
+        let c4 = c3.recv_rename_resp(0, all);
+        assert(c4.renames_done =~= all);
+        assert(c4.phase == CoordPhase::Cleanup);
+        lemma_acquisition_phase_consistency(c4, 0, all);
+        assert(acquisition_phase_consistent(c4.recv_unlock_resp(0)));
+
+        // An out-of-order driver crediting a rename while still preparing
+        let two = Set::empty().insert(0nat).insert(1nat);
+        assert(!c2.renames_done.insert(0).contains(1nat));
+        assert(two.contains(1nat));
+        let early = c2.recv_rename_resp(0, two);
+        assert(early.phase == CoordPhase::Preparing);
+        assert(early.renames_done.contains(0));
+        assert(!acquisition_phase_consistent(early));
+    }
+}
+
+} // verus!
@@ -11,91 +11,212 @@
 // Note: We use a custom SimpleSet instead of vstd::hash_set::HashSetWithView because:
 // 1. HashSetWithView requires obeys_key_model::<Key>() which is only proven for primitive types
 // 2. Using u64 directly doesn't give us the right View type (Set<u64> vs Set<nat>)
-// 3. SimpleSet provides a fully verified set implementation using Vec
+// 3. SimpleSet provides a fully verified set implementation, stored inline for
+//    up to 4 elements and spilled to a Vec beyond that
 
 use vstd::prelude::*;
 
+use crate::abstract_s::Decision;
 use crate::coordinator_s::*;
 use crate::network_s::*;
+use crate::network_v::ExecMessage;
 
 verus! {
 
 // ============================================================
-// SIMPLE SET IMPLEMENTATION USING VEC
+// SIMPLE SET IMPLEMENTATION (INLINE, SPILLING TO VEC)
 // ============================================================
 
-/// A simple set implementation using Vec for exec mode
+/// A simple set of u64 for exec mode.
 /// This is used because vstd::hash_set::HashSetWithView requires obeys_key_model
 /// which is only proven for primitive types, not custom wrappers.
+///
+/// Up to 4 elements are kept inline, so typical transactions (one or a few
+/// stores) never allocate. Inserting a fifth element spills everything to `heap`;
+/// the set then stays on the heap until cleared.
 pub struct SimpleSet {
-    elements: Vec<u64>,
+    inline: [u64; 4],
+    inline_len: usize,
+    heap: Vec<u64>,
 }
 
 impl View for SimpleSet {
     type V = Set<u64>;
 
     closed spec fn view(&self) -> Set<u64> {
-        Set::new(|x: u64| self.elements@.contains(x))
+        Set::new(|x: u64| self.spec_elements().contains(x))
     }
 }
 
 impl SimpleSet {
+    /// Number of inline slots in use (clamped to the inline capacity)
+    closed spec fn spec_inline_len(&self) -> int {
+        if self.inline_len <= 4 { self.inline_len as int } else { 4 }
+    }
+
+    /// Elements in insertion order: the heap once spilled, otherwise the inline prefix
+    closed spec fn spec_elements(&self) -> Seq<u64> {
+        if self.heap@.len() > 0 {
+            self.heap@
+        } else {
+            self.inline@.subrange(0, self.spec_inline_len())
+        }
+    }
+
     pub open spec fn spec_contains(&self, x: u64) -> bool {
         self@.contains(x)
     }
 
     pub closed spec fn spec_len(&self) -> nat {
-        self.elements.len() as nat
+        self.spec_elements().len()
     }
 
     pub fn new() -> (result: Self)
         ensures
             result@ == Set::<u64>::empty()
     {
-        SimpleSet { elements: Vec::new() }
+        let result = SimpleSet { inline: [0; 4], inline_len: 0, heap: Vec::new() };
+        proof {
+            assert(result.spec_elements() =~= Seq::<u64>::empty());
+            assert(result@ =~= Set::<u64>::empty());
+        }
+        result
     }
 
-    pub fn contains(&self, x: &u64) -> (result: bool)
+    /// Independent copy of the set
+    pub fn snapshot(&self) -> (result: Self)
         ensures
-            result == self@.contains(*x)
+            result@ == self@
     {
+        let mut heap: Vec<u64> = Vec::new();
         let mut i: usize = 0;
-        while i < self.elements.len()
+        while i < self.heap.len()
             invariant
-                0 <= i <= self.elements.len(),
-                forall|j: int| 0 <= j < i ==> self.elements@[j] != *x,
+                0 <= i <= self.heap.len(),
+                heap@ == self.heap@.subrange(0, i as int),
             decreases
-                self.elements.len() - i,
+                self.heap.len() - i,
         {
-            if self.elements[i] == *x {
-                return true;
-            }
+            heap.push(self.heap[i]);
             i = i + 1;
         }
-        false
+        let result = SimpleSet { inline: self.inline, inline_len: self.inline_len, heap };
+        proof {
+            assert(result.heap@ =~= self.heap@);
+            assert(result.spec_elements() == self.spec_elements());
+            assert(result@ =~= self@);
+        }
+        result
+    }
+
+    pub fn contains(&self, x: &u64) -> (result: bool)
+        ensures
+            result == self@.contains(*x)
+    {
+        if !self.heap.is_empty() {
+            let mut i: usize = 0;
+            while i < self.heap.len()
+                invariant
+                    0 <= i <= self.heap.len(),
+                    forall|j: int| 0 <= j < i ==> self.heap@[j] != *x,
+                decreases
+                    self.heap.len() - i,
+            {
+                if self.heap[i] == *x {
+                    proof {
+                        assert(self.spec_elements()[i as int] == *x);
+                    }
+                    return true;
+                }
+                i = i + 1;
+            }
+            false
+        } else {
+            let n = if self.inline_len <= 4 { self.inline_len } else { 4 };
+            let mut i: usize = 0;
+            while i < n
+                invariant
+                    0 <= i <= n,
+                    n as int == self.spec_inline_len(),
+                    self.heap@.len() == 0,
+                    forall|j: int| 0 <= j < i ==> self.inline@[j] != *x,
+                decreases
+                    n - i,
+            {
+                if self.inline[i] == *x {
+                    proof {
+                        assert(self.spec_elements()[i as int] == *x);
+                    }
+                    return true;
+                }
+                i = i + 1;
+            }
+            false
+        }
     }
 
     pub fn insert(&mut self, x: u64)
         ensures
             self@.contains(x),
             forall|y: u64| old(self)@.contains(y) ==> self@.contains(y),
+            self@ == old(self)@.insert(x),
     {
-        if !self.contains(&x) {
-            let ghost old_elements = self.elements@;
-            self.elements.push(x);
+        if self.contains(&x) {
             proof {
-                // After push, x is in the list
-                assert(self.elements@.last() == x);
-                assert(self.elements@.contains(x));
-                // Old elements are preserved
-                assert forall|y: u64| old_elements.contains(y) implies self.elements@.contains(y) by {
-                    if old_elements.contains(y) {
-                        // y was in old list, so it's in new list (push preserves existing elements)
-                        let idx = choose|i: int| 0 <= i < old_elements.len() && old_elements[i] == y;
-                        assert(self.elements@[idx] == y);
-                    }
+                assert(self@ =~= old(self)@.insert(x));
+            }
+            return;
+        }
+        let ghost old_elements = self.spec_elements();
+        if !self.heap.is_empty() {
+            self.heap.push(x);
+            proof {
+                assert(self.spec_elements() =~= old_elements.push(x));
+            }
+        } else if self.inline_len < 4 {
+            self.inline[self.inline_len] = x;
+            self.inline_len = self.inline_len + 1;
+            proof {
+                assert(self.spec_elements() =~= old_elements.push(x));
+            }
+        } else {
+            // Inline storage is full: spill every element to the heap
+            let mut heap: Vec<u64> = Vec::new();
+            let mut i: usize = 0;
+            while i < 4
+                invariant
+                    0 <= i <= 4,
+                    self.heap@.len() == 0,
+                    self.spec_inline_len() == 4,
+                    old_elements == self.spec_elements(),
+                    heap@ =~= self.inline@.subrange(0, i as int),
+                decreases
+                    4 - i,
+            {
+                heap.push(self.inline[i]);
+                i = i + 1;
+            }
+            heap.push(x);
+            self.heap = heap;
+            proof {
+                assert(self.spec_elements() =~= old_elements.push(x));
+            }
+        }
+        proof {
+            let new_elements = self.spec_elements();
+            // x is the last element; old elements keep their positions
+            assert(new_elements[new_elements.len() - 1] == x);
+            assert forall|y: u64| old_elements.contains(y) implies new_elements.contains(y) by {
+                let idx = choose|i: int| 0 <= i < old_elements.len() && old_elements[i] == y;
+                assert(new_elements[idx] == y);
+            }
+            assert forall|y: u64| new_elements.contains(y) implies old_elements.contains(y) || y == x by {
+                let idx = choose|i: int| 0 <= i < new_elements.len() && new_elements[i] == y;
+                if idx < old_elements.len() {
+                    assert(old_elements[idx] == y);
                 }
             }
+            assert(self@ =~= old(self)@.insert(x));
         }
     }
 
@@ -103,14 +224,117 @@ impl SimpleSet {
         ensures
             result as nat == self.spec_len(),
     {
-        self.elements.len()
+        if !self.heap.is_empty() {
+            self.heap.len()
+        } else if self.inline_len <= 4 {
+            self.inline_len
+        } else {
+            4
+        }
+    }
+
+    pub fn is_empty(&self) -> (result: bool)
+        ensures
+            result == (self@ == Set::<u64>::empty()),
+    {
+        let n = self.len();
+        proof {
+            let elements = self.spec_elements();
+            if n > 0 {
+                // The first element witnesses non-emptiness
+                assert(elements.contains(elements[0]));
+                assert(self@.contains(elements[0]));
+            } else {
+                assert(self@ =~= Set::<u64>::empty());
+            }
+        }
+        n == 0
     }
 
     pub fn clear(&mut self)
         ensures
             self@ == Set::<u64>::empty()
     {
-        self.elements = Vec::new();
+        self.inline_len = 0;
+        self.heap = Vec::new();
+        proof {
+            assert(self.spec_elements() =~= Seq::<u64>::empty());
+            assert(self@ =~= Set::<u64>::empty());
+        }
+    }
+
+    pub fn is_subset_of(&self, other: &SimpleSet) -> (result: bool)
+        ensures
+            result == self@.subset_of(other@),
+    {
+        let ghost elements = self.spec_elements();
+        let n = self.len();
+        let mut i: usize = 0;
+        while i < n
+            invariant
+                0 <= i <= n,
+                n as nat == elements.len(),
+                elements == self.spec_elements(),
+                forall|j: int| 0 <= j < i ==> other@.contains(#[trigger] elements[j]),
+            decreases
+                n - i,
+        {
+            let x = if !self.heap.is_empty() { self.heap[i] } else { self.inline[i] };
+            proof {
+                assert(x == elements[i as int]);
+            }
+            if !other.contains(&x) {
+                proof {
+                    // elements[i] witnesses a member of self missing from other
+                    assert(elements.contains(elements[i as int]));
+                }
+                return false;
+            }
+            i = i + 1;
+        }
+        proof {
+            assert forall|x: u64| self@.contains(x) implies other@.contains(x) by {
+                let idx = choose|k: int| 0 <= k < elements.len() && elements[k] == x;
+                assert(other@.contains(elements[idx]));
+            }
+        }
+        true
+    }
+
+    /// The elements as a Vec, in insertion order (for diagnostics)
+    pub fn to_vec(&self) -> (result: Vec<u64>)
+        ensures
+            result@.len() == self.spec_len(),
+            forall|x: u64| #[trigger] result@.contains(x) <==> self@.contains(x),
+    {
+        let ghost elements = self.spec_elements();
+        let n = self.len();
+        let mut result: Vec<u64> = Vec::new();
+        let mut i: usize = 0;
+        while i < n
+            invariant
+                0 <= i <= n,
+                n as nat == elements.len(),
+                elements == self.spec_elements(),
+                result@ == elements.subrange(0, i as int),
+            decreases
+                n - i,
+        {
+            let x = if !self.heap.is_empty() { self.heap[i] } else { self.inline[i] };
+            proof {
+                assert(x == elements[i as int]);
+            }
+            result.push(x);
+            proof {
+                assert(result@ =~= elements.subrange(0, i as int + 1));
+            }
+            i = i + 1;
+        }
+        proof {
+            // Every element was copied, so membership matches the set view
+            assert(result@ =~= elements);
+        }
+        result
     }
 }
 
@@ -118,6 +342,31 @@ impl SimpleSet {
 // COORDINATOR STRUCT
 // ============================================================
 
+/// Why the coordinator took the abort path (diagnostics only)
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AbortReason {
+    /// A store refused its lock (`handle_lock_failure`)
+    LockFailure,
+    /// Votes stopped arriving and the coordinator gave up waiting (`handle_vote_timeout`)
+    Timeout,
+    /// An operator aborted a wedged transaction (`force_abort`)
+    ForcedByOperator,
+    /// Recovery found no COMMIT record in the WAL (`recover`)
+    RecoveredUncommitted,
+}
+
+/// A sub-coordinator's status as a single vote for its parent, from
+/// `Coordinator::as_vote`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SubVote {
+    /// Every store taking part granted its lock, or the commit is in the WAL
+    Yes,
+    /// The sub-transaction aborted (e.g. a store refused its lock)
+    No,
+    /// Still collecting votes: some lock is outstanding and nothing failed yet
+    Pending,
+}
+
 /// Executable coordinator state
 /// Uses CoordPhase directly from coordinator_s.rs - no duplication needed!
 pub struct Coordinator {
@@ -126,6 +375,11 @@ pub struct Coordinator {
     pub current_txn_id: u64,
     /// Whether COMMIT is recorded in WAL
     pub wal_committed: bool,
+    /// Txn id the commit was decided at, written to the WAL with the commit
+    /// record. Unlike `current_txn_id`, recovery leaves it alone
+    pub commit_txn: Option<u64>,
+    /// Stores sent a LockReq, logged before each send; cleanup unlocks them all
+    pub lock_requested: SimpleSet,
 
     // ===== Volatile state (lost on crash) =====
     /// Current phase of the protocol (uses CoordPhase directly)
@@ -136,6 +390,20 @@ pub struct Coordinator {
     pub renames_done: SimpleSet,
     /// Stores that have responded to UnlockReq
     pub unlocks_acked: SimpleSet,
+    /// Stores added to the transaction while it was still collecting votes.
+    /// Once any is added, these (rather than every included store) are the
+    /// stores whose votes commit waits for
+    pub participants: SimpleSet,
+
+    // ===== Configuration (fixed before the transaction starts) =====
+    /// Stores known to be permanently dead and excluded from the transaction
+    /// (degraded mode). Their votes, renames and unlocks are not waited for
+    pub excluded: SimpleSet,
+
+    // ===== Diagnostics =====
+    /// Why the transaction aborted, set by each abort-inducing transition;
+    /// None while it has not aborted. Not part of the view
+    pub abort_reason: Option<AbortReason>,
 }
 
 impl View for Coordinator {
@@ -145,6 +413,7 @@ impl View for Coordinator {
         CoordinatorSpec {
             current_txn_id: self.current_txn_id as nat,
             wal_committed: self.wal_committed,
+            lock_requested: Set::new(|s: nat| self.lock_requested@.contains(s as u64)),
             phase: self.phase,
             locks_acquired: Set::new(|s: nat| self.locks_acquired@.contains(s as u64)),
             renames_done: Set::new(|s: nat| self.renames_done@.contains(s as u64)),
@@ -182,6 +451,72 @@ impl Coordinator {
         self.unlocks_acked@.contains(store)
     }
 
+    /// Every added participant has granted its lock
+    /// (see lemma_late_participant_blocks_commit)
+    pub open spec fn spec_all_votes_in(&self) -> bool {
+        self.participants@.subset_of(self.locks_acquired@)
+    }
+
+    /// `store` takes part in the transaction: an added participant, or any
+    /// included store 0..num_stores when no participant was added
+    pub open spec fn spec_in_txn(&self, store: u64, num_stores: nat) -> bool {
+        if self.participants@ == Set::<u64>::empty() {
+            (store as nat) < num_stores && !self.excluded@.contains(store)
+        } else {
+            self.participants@.contains(store)
+        }
+    }
+
+    /// Every store taking part in the transaction has granted its lock
+    pub open spec fn spec_all_locks_acquired(&self, num_stores: nat) -> bool {
+        forall|s: u64| #[trigger] self.spec_in_txn(s, num_stores) ==> self.locks_acquired@.contains(s)
+    }
+
+    /// Every store taking part in the transaction has applied the rename
+    pub open spec fn spec_all_renames_done(&self, num_stores: nat) -> bool {
+        forall|s: u64| #[trigger] self.spec_in_txn(s, num_stores) ==> self.renames_done@.contains(s)
+    }
+
+    /// Degraded mode: some store was excluded, so atomicity covers only the
+    /// included stores (see SystemSpec::exclude_participants)
+    pub open spec fn spec_is_degraded(&self) -> bool {
+        self.excluded@ != Set::<u64>::empty()
+    }
+
+    /// The excluded store ids, as the set passed to SystemSpec::exclude_participants
+    pub open spec fn spec_excluded_ids(&self) -> Set<StoreId> {
+        self.excluded@.map(|s: u64| s as StoreId)
+    }
+
+    /// Committing is safe: still collecting votes, and every store taking part
+    /// granted its lock
+    pub open spec fn spec_can_commit(&self, num_stores: nat) -> bool {
+        self.phase == CoordPhase::Preparing && self.spec_all_locks_acquired(num_stores)
+    }
+
+    /// Matches CoordinatorSpec::unlock_targets: every store sent a LockReq
+    pub open spec fn spec_is_unlock_target(&self, store: u64) -> bool {
+        self.lock_requested@.contains(store)
+    }
+
+    /// `msg` can be recorded now: a response for the current txn from one of
+    /// `num_stores` stores, of the kind the phase waits for, not yet credited
+    /// (a failed LockResp is never credited, so it is only checked for phase)
+    pub open spec fn spec_accepts_response(&self, msg: Message, num_stores: nat) -> bool {
+        &&& msg.is_response()
+        &&& msg.get_txn_id() == self.current_txn_id as nat
+        &&& msg.get_store() < num_stores
+        &&& match msg.kind() {
+            MsgKind::LockResp => self.phase == CoordPhase::Preparing
+                && (msg.is_lock_success() ==> !self.locks_acquired@.contains(msg.get_store() as u64)),
+            MsgKind::RenameResp => self.phase == CoordPhase::Committed
+                && !self.renames_done@.contains(msg.get_store() as u64),
+            MsgKind::UnlockResp => self.phase == CoordPhase::Cleanup
+                && !self.unlocks_acked@.contains(msg.get_store() as u64),
+            _ => false,
+        }
+    }
+
     // ============================================================
     // EXEC FUNCTIONS
     // ============================================================
@@ -191,21 +526,70 @@ impl Coordinator {
         ensures
             result.current_txn_id == 1,
             result.wal_committed == false,
+            result.commit_txn.is_none(),
+            result.lock_requested@ == Set::<u64>::empty(),
             result.phase == CoordPhase::Idle,
             result.locks_acquired@ == Set::<u64>::empty(),
             result.renames_done@ == Set::<u64>::empty(),
             result.unlocks_acked@ == Set::<u64>::empty(),
+            result.participants@ == Set::<u64>::empty(),
+            result.excluded@ == Set::<u64>::empty(),
+            result.abort_reason.is_none(),
     {
         Coordinator {
             current_txn_id: 1,
             wal_committed: false,
+            commit_txn: None,
+            lock_requested: SimpleSet::new(),
             phase: CoordPhase::Idle,
             locks_acquired: SimpleSet::new(),
             renames_done: SimpleSet::new(),
             unlocks_acked: SimpleSet::new(),
+            participants: SimpleSet::new(),
+            excluded: SimpleSet::new(),
+            abort_reason: None,
         }
     }
 
+    /// Independent copy of the coordinator, including its volatile state
+    pub fn snapshot(&self) -> (result: Self)
+        ensures
+            result@ == self@,
+            result.commit_txn == self.commit_txn,
+            result.participants@ == self.participants@,
+            result.excluded@ == self.excluded@,
+            result.abort_reason == self.abort_reason,
+    {
+        let result = Coordinator {
+            current_txn_id: self.current_txn_id,
+            wal_committed: self.wal_committed,
+            commit_txn: self.commit_txn,
+            lock_requested: self.lock_requested.snapshot(),
+            phase: self.phase,
+            locks_acquired: self.locks_acquired.snapshot(),
+            renames_done: self.renames_done.snapshot(),
+            unlocks_acked: self.unlocks_acked.snapshot(),
+            participants: self.participants.snapshot(),
+            excluded: self.excluded.snapshot(),
+            abort_reason: self.abort_reason,
+        };
+        proof {
+            assert(result@.lock_requested =~= self@.lock_requested);
+            assert(result@.locks_acquired =~= self@.locks_acquired);
+            assert(result@.renames_done =~= self@.renames_done);
+            assert(result@.unlocks_acked =~= self@.unlocks_acked);
+        }
+        result
+    }
+
+    /// Why the transaction aborted, or None if it has not
+    pub fn abort_reason(&self) -> (result: Option<AbortReason>)
+        ensures
+            result == self.abort_reason,
+    {
+        self.abort_reason
+    }
+
     /// Get current transaction ID
     pub fn get_txn_id(&self) -> (result: u64)
         ensures
@@ -214,6 +598,94 @@ impl Coordinator {
         self.current_txn_id
     }
 
+    /// Transaction ids left before `recover` can no longer advance the id
+    pub fn txn_id_headroom(&self) -> (result: u64)
+        ensures
+            result == u64::MAX - self.current_txn_id,
+    {
+        u64::MAX - self.current_txn_id
+    }
+
+    /// True once at most `threshold` ids remain, so operators can checkpoint
+    /// and reset the id space while `recover` is still possible
+    pub fn is_near_overflow(&self, threshold: u64) -> (result: bool)
+        ensures
+            result == (u64::MAX - self.current_txn_id <= threshold),
+    {
+        self.txn_id_headroom() <= threshold
+    }
+
+    /// Runtime self-check that the durable WAL flag and the volatile phase are
+    /// a legal combination (see `durable_consistent`), for use in `debug_assert!`
+    pub fn check_durable(&self) -> (result: bool)
+        ensures
+            result == durable_consistent(self@),
+            result == ((self.phase == CoordPhase::Committed ==> self.wal_committed)
+                && (self.wal_committed ==> self.phase != CoordPhase::Idle && self.phase != CoordPhase::Preparing)),
+    {
+        let committed_ok = self.phase != CoordPhase::Committed || self.wal_committed;
+        let wal_ok = !self.wal_committed
+            || (self.phase != CoordPhase::Idle && self.phase != CoordPhase::Preparing);
+        committed_ok && wal_ok
+    }
+
+    /// The transaction outcome participants observe (see
+    /// `CoordinatorSpec::decision`): Commit once the WAL is committed, Undecided
+    /// while Idle or Preparing without it, and Abort otherwise
+    pub fn decision(&self) -> (result: Decision)
+        ensures
+            result.spec_phase() == self@.decision(),
+            (result == Decision::Commit) == self.wal_committed,
+            (result == Decision::Undecided) == (!self.wal_committed
+                && (self.phase == CoordPhase::Idle || self.phase == CoordPhase::Preparing)),
+    {
+        if self.wal_committed {
+            Decision::Commit
+        } else {
+            match self.phase {
+                CoordPhase::Idle | CoordPhase::Preparing => Decision::Undecided,
+                _ => Decision::Abort,
+            }
+        }
+    }
+
+    /// This coordinator's status as a single vote for a parent coordinator, so
+    /// a whole sub-protocol can act as one participant of a two-level 2PC: Yes
+    /// once the commit is in the WAL or every store taking part granted its
+    /// lock, No once the transaction aborted, and Pending while locks are still
+    /// outstanding
+    pub fn as_vote(&self, num_stores: usize) -> (result: SubVote)
+        ensures
+            (result == SubVote::Yes) == (self.wal_committed
+                || ((self.phase == CoordPhase::Idle || self.phase == CoordPhase::Preparing)
+                    && self.spec_all_locks_acquired(num_stores as nat))),
+            (result == SubVote::No) == (!self.wal_committed
+                && self.phase != CoordPhase::Idle && self.phase != CoordPhase::Preparing),
+    {
+        match self.decision() {
+            Decision::Commit => return SubVote::Yes,
+            Decision::Abort => return SubVote::No,
+            Decision::Undecided => {},
+        }
+        if !self.participants.is_empty() {
+            return if self.participants.is_subset_of(&self.locks_acquired) { SubVote::Yes } else { SubVote::Pending };
+        }
+        let mut s: u64 = 0;
+        while s < num_stores as u64
+            invariant
+                self.participants@ == Set::<u64>::empty(),
+                forall|t: u64| t < s && !self.excluded@.contains(t) ==> #[trigger] self.locks_acquired@.contains(t),
+            decreases
+                num_stores as u64 - s,
+        {
+            if !self.excluded.contains(&s) && !self.locks_acquired.contains(&s) {
+                return SubVote::Pending;
+            }
+            s = s + 1;
+        }
+        SubVote::Yes
+    }
+
     /// Check if WAL is committed
     pub fn is_committed(&self) -> (result: bool)
         ensures
@@ -222,6 +694,15 @@ impl Coordinator {
         self.wal_committed
     }
 
+    /// Txn id at which the commit was decided, or None if it was not. Stays
+    /// put when recovery moves the live txn id on
+    pub fn committed_txn_id(&self) -> (result: Option<u64>)
+        ensures
+            result == self.commit_txn,
+    {
+        self.commit_txn
+    }
+
     /// Get current phase
     pub fn get_phase(&self) -> (result: CoordPhase)
         ensures
@@ -254,6 +735,195 @@ impl Coordinator {
         self.unlocks_acked.contains(&store)
     }
 
+    /// Stores whose lock grant has been recorded, e.g. for "stores {0,2} have locked"
+    pub fn acquired_stores(&self) -> (result: Vec<u64>)
+        ensures
+            forall|s: u64| #[trigger] result@.contains(s) <==> self.locks_acquired@.contains(s),
+    {
+        self.locks_acquired.to_vec()
+    }
+
+    /// Stores whose rename has been acknowledged
+    pub fn renamed_stores(&self) -> (result: Vec<u64>)
+        ensures
+            forall|s: u64| #[trigger] result@.contains(s) <==> self.renames_done@.contains(s),
+    {
+        self.renames_done.to_vec()
+    }
+
+    /// Stores whose unlock has been acknowledged
+    pub fn unlocked_stores(&self) -> (result: Vec<u64>)
+        ensures
+            forall|s: u64| #[trigger] result@.contains(s) <==> self.unlocks_acked@.contains(s),
+    {
+        self.unlocks_acked.to_vec()
+    }
+
+    /// One-line progress status for logs, e.g. "Preparing: 2/3 locked" or
+    /// "Committed: 1/3 renamed". Counts are over the included stores among
+    /// `num_stores` (the unlock targets in Cleanup). Trusted (external_body)
+    /// since it only builds a diagnostic string.
+    #[verifier::external_body]
+    pub fn vote_summary(&self, num_stores: usize) -> String {
+        let included = self.num_in_txn(num_stores);
+        match self.phase {
+            CoordPhase::Idle => format!("Idle: 0/{} locked", included),
+            CoordPhase::Preparing => format!("Preparing: {}/{} locked", self.locks_acquired.len(), included),
+            CoordPhase::Committed => format!("Committed: {}/{} renamed", self.renames_done.len(), included),
+            CoordPhase::Cleanup => {
+                format!("Cleanup: {}/{} unlocked", self.unlocks_acked.len(), self.lock_requested.len())
+            },
+            CoordPhase::Done if self.wal_committed => "Done: committed".to_owned(),
+            CoordPhase::Done => "Done: aborted".to_owned(),
+            CoordPhase::Crashed => "Crashed".to_owned(),
+        }
+    }
+
+    /// Overall completion fraction in [0, 1] for dashboards: the lock, rename and
+    /// unlock phases each cover a third, filled by the share of stores that have
+    /// responded; Done is 1.0. A crashed coordinator reports the start of the
+    /// phase recovery resumes in. Floats are outside what Verus verifies here,
+    /// so this is trusted (external_body).
+    #[verifier::external_body]
+    pub fn progress(&self, num_stores: usize) -> f64 {
+        fn fraction(done: usize, total: usize) -> f64 {
+            if total == 0 {
+                0.0
+            } else {
+                done.min(total) as f64 / total as f64
+            }
+        }
+        let included = self.num_in_txn(num_stores);
+        let third = 1.0 / 3.0;
+        match self.phase {
+            CoordPhase::Idle => 0.0,
+            CoordPhase::Preparing => third * fraction(self.locks_acquired.len(), included),
+            CoordPhase::Committed => third * (1.0 + fraction(self.renames_done.len(), included)),
+            CoordPhase::Cleanup => third * (2.0 + fraction(self.unlocks_acked.len(), self.lock_requested.len())),
+            CoordPhase::Done => 1.0,
+            CoordPhase::Crashed if self.wal_committed => third,
+            CoordPhase::Crashed => 0.0,
+        }
+    }
+
+    /// Check if a store should be sent UnlockReq during cleanup
+    pub fn is_unlock_target(&self, store: u64) -> (result: bool)
+        ensures
+            result == self.spec_is_unlock_target(store)
+    {
+        self.lock_requested.contains(&store)
+    }
+
+    /// Check if the coordinator runs in degraded mode (some store excluded)
+    pub fn is_degraded(&self) -> (result: bool)
+        ensures
+            result == self.spec_is_degraded()
+    {
+        !self.excluded.is_empty()
+    }
+
+    /// Check if a store is excluded from the transaction
+    pub fn is_excluded(&self, store: u64) -> (result: bool)
+        ensures
+            result == self.excluded@.contains(store)
+    {
+        self.excluded.contains(&store)
+    }
+
+    /// Exclude a store known to be permanently dead (Idle only), so its vote is
+    /// not required. Switches the coordinator to degraded mode: atomicity then
+    /// holds only among the included stores, and the excluded store is left as is.
+    /// The store must be one of the `num_stores`; an unknown id would otherwise
+    /// look like an exclusion without removing any real store.
+    pub fn exclude_participant(&mut self, store_id: u64, Ghost(num_stores): Ghost<nat>)
+        requires
+            old(self).phase == CoordPhase::Idle,
+            !old(self).participants@.contains(store_id),
+            (store_id as nat) < num_stores,
+        ensures
+            self.excluded@ == old(self).excluded@.insert(store_id),
+            self.spec_excluded_ids() == old(self).spec_excluded_ids().insert(store_id as StoreId),
+            self.spec_is_degraded(),
+            self.phase == old(self).phase,
+            self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
+            self.wal_committed == old(self).wal_committed,
+            self.locks_acquired@ == old(self).locks_acquired@,
+            self.renames_done@ == old(self).renames_done@,
+            self.unlocks_acked@ == old(self).unlocks_acked@,
+            self.participants@ == old(self).participants@,
+            self.commit_txn == old(self).commit_txn,
+    {
+        self.excluded.insert(store_id);
+        assert(self.excluded@.contains(store_id));
+        assert(self.spec_excluded_ids() =~= old(self).spec_excluded_ids().insert(store_id as StoreId));
+    }
+
+    /// Check if every added participant has granted its lock
+    pub fn all_votes_in(&self) -> (result: bool)
+        ensures
+            result == self.spec_all_votes_in()
+    {
+        self.participants.is_subset_of(&self.locks_acquired)
+    }
+
+    /// Check if a store takes part in the transaction (see spec_in_txn)
+    pub fn in_txn(&self, store: u64, num_stores: usize) -> (result: bool)
+        ensures
+            result == self.spec_in_txn(store, num_stores as nat)
+    {
+        if self.participants.is_empty() {
+            store < num_stores as u64 && !self.excluded.contains(&store)
+        } else {
+            self.participants.contains(&store)
+        }
+    }
+
+    /// A participant added while preparing blocks commit until it also votes,
+    /// whatever the number of stores
+    pub proof fn lemma_late_participant_blocks_commit(&self, store: u64, num_stores: nat)
+        requires
+            self.participants@.contains(store),
+            !self.locks_acquired@.contains(store),
+        ensures
+            !self.spec_all_votes_in(),
+            !self.spec_can_commit(num_stores),
+    {
+        assert(self.participants@ != Set::<u64>::empty());
+        assert(self.spec_in_txn(store, num_stores));
+    }
+
+    /// Add a participant discovered while collecting votes (Idle or Preparing).
+    /// A store that has not granted its lock yet holds back commit until it does
+    pub fn add_participant(&mut self, store: u64)
+        requires
+            old(self).phase == CoordPhase::Idle || old(self).phase == CoordPhase::Preparing
+        ensures
+            self.participants@.contains(store),
+            forall|s: u64| old(self).participants@.contains(s) ==> self.participants@.contains(s),
+            !old(self).locks_acquired@.contains(store) ==> !self.spec_all_votes_in(),
+            !old(self).locks_acquired@.contains(store) ==>
+                forall|n: nat| !#[trigger] self.spec_can_commit(n),
+            self.phase == old(self).phase,
+            self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
+            self.wal_committed == old(self).wal_committed,
+            self.locks_acquired@ == old(self).locks_acquired@,
+            self.renames_done@ == old(self).renames_done@,
+            self.unlocks_acked@ == old(self).unlocks_acked@,
+            self.excluded@ == old(self).excluded@,
+            self.commit_txn == old(self).commit_txn,
+    {
+        self.participants.insert(store);
+        proof {
+            if !old(self).locks_acquired@.contains(store) {
+                assert forall|n: nat| !#[trigger] self.spec_can_commit(n) by {
+                    self.lemma_late_participant_blocks_commit(store, n);
+                }
+            }
+        }
+    }
+
     /// Start preparing - transition from Idle to Preparing
     pub fn start_preparing(&mut self)
         requires
@@ -261,12 +931,45 @@ impl Coordinator {
         ensures
             self.phase == CoordPhase::Preparing,
             self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
+            self.wal_committed == old(self).wal_committed,
+            self.locks_acquired@ == old(self).locks_acquired@,
+            self.renames_done@ == old(self).renames_done@,
+            self.unlocks_acked@ == old(self).unlocks_acked@,
+            self.participants@ == old(self).participants@,
+            self.excluded@ == old(self).excluded@,
+            self.commit_txn == old(self).commit_txn,
+    {
+        self.phase = CoordPhase::Preparing;
+    }
+
+    /// Log that a LockReq is being sent to `store` and enter Preparing.
+    /// Matches CoordinatorSpec::send_lock_req; the store becomes an unlock target
+    pub fn record_lock_request(&mut self, store: u64)
+        requires
+            old(self).phase == CoordPhase::Idle || old(self).phase == CoordPhase::Preparing
+        ensures
+            self.phase == CoordPhase::Preparing,
+            self.lock_requested@ == old(self).lock_requested@.insert(store),
+            self.spec_is_unlock_target(store),
+            self@ == old(self)@.send_lock_req(store as nat).0,
+            self.current_txn_id == old(self).current_txn_id,
             self.wal_committed == old(self).wal_committed,
             self.locks_acquired@ == old(self).locks_acquired@,
             self.renames_done@ == old(self).renames_done@,
             self.unlocks_acked@ == old(self).unlocks_acked@,
+            self.participants@ == old(self).participants@,
+            self.excluded@ == old(self).excluded@,
+            self.commit_txn == old(self).commit_txn,
     {
+        self.lock_requested.insert(store);
         self.phase = CoordPhase::Preparing;
+        proof {
+            assert(self@.lock_requested =~= old(self)@.lock_requested.insert(store as nat));
+            assert(self@.locks_acquired =~= old(self)@.locks_acquired);
+            assert(self@.renames_done =~= old(self)@.renames_done);
+            assert(self@.unlocks_acked =~= old(self)@.unlocks_acked);
+        }
     }
 
     /// Record successful lock response from a store
@@ -278,49 +981,172 @@ impl Coordinator {
             self.locks_acquired@.contains(store),
             self.phase == old(self).phase,
             self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
             self.wal_committed == old(self).wal_committed,
             forall|s: u64| old(self).renames_done@.contains(s) ==> self.renames_done@.contains(s),
             forall|s: u64| old(self).unlocks_acked@.contains(s) ==> self.unlocks_acked@.contains(s),
+            self.participants@ == old(self).participants@,
+            self.excluded@ == old(self).excluded@,
+            self.commit_txn == old(self).commit_txn,
     {
         self.locks_acquired.insert(store);
     }
 
     /// Handle lock failure - transition to cleanup
+    /// Acquired locks are kept; cleanup unlocks every store sent a LockReq
     pub fn handle_lock_failure(&mut self)
         requires
             old(self).phase == CoordPhase::Preparing
         ensures
             self.phase == CoordPhase::Cleanup,
             self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
             self.wal_committed == old(self).wal_committed,
-            self.locks_acquired@ == Set::<u64>::empty(),
+            self.locks_acquired@ == old(self).locks_acquired@,
             self.renames_done@ == Set::<u64>::empty(),
             self.unlocks_acked@ == Set::<u64>::empty(),
+            // The composite view: no leftover rename/unlock credit can satisfy
+            // the cleanup completion check early
+            self@.renames_done == Set::<nat>::empty(),
+            self@.unlocks_acked == Set::<nat>::empty(),
+            self@.locks_acquired == old(self)@.locks_acquired,
+            self@ == old(self)@.recv_lock_resp_failure(),
+            self.commit_txn == old(self).commit_txn,
+            self.abort_reason == Some(AbortReason::LockFailure),
     {
         self.phase = CoordPhase::Cleanup;
-        self.locks_acquired.clear();
         self.renames_done.clear();
         self.unlocks_acked.clear();
+        self.abort_reason = Some(AbortReason::LockFailure);
+        proof {
+            assert(self@.renames_done =~= Set::<nat>::empty());
+            assert(self@.unlocks_acked =~= Set::<nat>::empty());
+            assert(self@.locks_acquired =~= old(self)@.locks_acquired);
+        }
     }
 
-    /// Decide to commit - write to WAL and transition to Committed
-    pub fn decide_commit(&mut self)
+    /// Give up waiting for missing votes: a store that has not answered is
+    /// treated as a no, so this is the same transition as `handle_lock_failure`,
+    /// recorded with reason `Timeout`
+    pub fn handle_vote_timeout(&mut self)
         requires
             old(self).phase == CoordPhase::Preparing
+        ensures
+            self.phase == CoordPhase::Cleanup,
+            self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
+            self.wal_committed == old(self).wal_committed,
+            self.locks_acquired@ == old(self).locks_acquired@,
+            self@ == old(self)@.recv_lock_resp_failure(),
+            self.commit_txn == old(self).commit_txn,
+            self.abort_reason == Some(AbortReason::Timeout),
+    {
+        self.handle_lock_failure();
+        self.abort_reason = Some(AbortReason::Timeout);
+    }
+
+    /// Check if committing is safe for `num_stores` participants
+    pub fn can_commit(&self, num_stores: usize) -> (result: bool)
+        ensures
+            result == self.spec_can_commit(num_stores as nat)
+    {
+        if self.phase != CoordPhase::Preparing {
+            return false;
+        }
+        if !self.participants.is_empty() {
+            return self.participants.is_subset_of(&self.locks_acquired);
+        }
+        let mut s: u64 = 0;
+        while s < num_stores as u64
+            invariant
+                self.participants@ == Set::<u64>::empty(),
+                forall|t: u64| t < s && !self.excluded@.contains(t) ==> #[trigger] self.locks_acquired@.contains(t),
+            decreases
+                num_stores as u64 - s,
+        {
+            if !self.excluded.contains(&s) && !self.locks_acquired.contains(&s) {
+                return false;
+            }
+            s = s + 1;
+        }
+        true
+    }
+
+    /// Decide to commit - write to WAL and transition to Committed
+    /// Only allowed once every store taking part granted its lock; the store
+    /// count only states that precondition, so it is ghost
+    pub fn decide_commit(&mut self, Ghost(num_stores): Ghost<nat>)
+        requires
+            old(self).spec_can_commit(num_stores),
         ensures
             self.wal_committed == true,
+            self.commit_txn == Some(old(self).current_txn_id),
             self.phase == CoordPhase::Committed,
             self.current_txn_id == old(self).current_txn_id,
-            forall|s: u64| old(self).locks_acquired@.contains(s) ==> self.locks_acquired@.contains(s),
+            self.lock_requested@ == old(self).lock_requested@,
+            self.locks_acquired@ == old(self).locks_acquired@,
+            self.spec_all_locks_acquired(num_stores),
             forall|s: u64| old(self).renames_done@.contains(s) ==> self.renames_done@.contains(s),
             forall|s: u64| old(self).unlocks_acked@.contains(s) ==> self.unlocks_acked@.contains(s),
     {
         self.wal_committed = true;
+        self.commit_txn = Some(self.current_txn_id);
         self.phase = CoordPhase::Committed;
     }
 
+    /// Number of stores taking part in the transaction (see spec_in_txn),
+    /// counted by membership rather than by subtracting exclusions
+    fn num_in_txn(&self, num_stores: usize) -> (result: usize)
+        ensures
+            self.participants@ != Set::<u64>::empty() ==> result as nat == self.participants.spec_len(),
+            self.participants@ == Set::<u64>::empty() ==> result <= num_stores,
+    {
+        if !self.participants.is_empty() {
+            return self.participants.len();
+        }
+        let mut count: usize = 0;
+        let mut s: usize = 0;
+        while s < num_stores
+            invariant
+                s <= num_stores,
+                count <= s,
+            decreases
+                num_stores - s,
+        {
+            if !self.excluded.contains(&(s as u64)) {
+                count = count + 1;
+            }
+            s = s + 1;
+        }
+        count
+    }
+
+    /// Check if every store taking part in the transaction has renamed
+    pub fn all_renames_done(&self, num_stores: usize) -> (result: bool)
+        ensures
+            result == self.spec_all_renames_done(num_stores as nat)
+    {
+        if !self.participants.is_empty() {
+            return self.participants.is_subset_of(&self.renames_done);
+        }
+        let mut s: u64 = 0;
+        while s < num_stores as u64
+            invariant
+                self.participants@ == Set::<u64>::empty(),
+                forall|t: u64| t < s && !self.excluded@.contains(t) ==> #[trigger] self.renames_done@.contains(t),
+            decreases
+                num_stores as u64 - s,
+        {
+            if !self.excluded.contains(&s) && !self.renames_done.contains(&s) {
+                return false;
+            }
+            s = s + 1;
+        }
+        true
+    }
+
     /// Record rename response from a store
-    /// Returns true if all stores have completed rename (transition to cleanup)
+    /// Returns true if every store taking part has completed rename (transition to cleanup)
     pub fn record_rename_done(&mut self, store: u64, num_stores: usize) -> (all_done: bool)
         requires
             old(self).phase == CoordPhase::Committed,
@@ -328,16 +1154,18 @@ impl Coordinator {
         ensures
             self.renames_done@.contains(store),
             self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
             self.wal_committed == old(self).wal_committed,
             forall|s: u64| old(self).locks_acquired@.contains(s) ==> self.locks_acquired@.contains(s),
             forall|s: u64| old(self).unlocks_acked@.contains(s) ==> self.unlocks_acked@.contains(s),
             // Phase transition logic
+            all_done == self.spec_all_renames_done(num_stores as nat),
             all_done ==> self.phase == CoordPhase::Cleanup,
             !all_done ==> self.phase == CoordPhase::Committed,
+            self.commit_txn == old(self).commit_txn,
     {
         self.renames_done.insert(store);
-        let len = self.renames_done.len();
-        if len == num_stores {
+        if self.all_renames_done(num_stores) {
             self.phase = CoordPhase::Cleanup;
             true
         } else {
@@ -346,24 +1174,27 @@ impl Coordinator {
     }
 
     /// Record unlock acknowledgment from a store
-    /// Returns true if all stores have acknowledged (transition to done)
-    pub fn record_unlock_acked(&mut self, store: u64, num_stores: usize) -> (all_done: bool)
+    /// Returns true if every unlock target (every store sent a LockReq) has
+    /// acknowledged (transition to done)
+    pub fn record_unlock_acked(&mut self, store: u64) -> (all_done: bool)
         requires
             old(self).phase == CoordPhase::Cleanup,
             !old(self).unlocks_acked@.contains(store),
         ensures
             self.unlocks_acked@.contains(store),
             self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
             self.wal_committed == old(self).wal_committed,
             forall|s: u64| old(self).locks_acquired@.contains(s) ==> self.locks_acquired@.contains(s),
             forall|s: u64| old(self).renames_done@.contains(s) ==> self.renames_done@.contains(s),
             // Phase transition logic
+            all_done == self.lock_requested@.subset_of(self.unlocks_acked@),
             all_done ==> self.phase == CoordPhase::Done,
             !all_done ==> self.phase == CoordPhase::Cleanup,
+            self.commit_txn == old(self).commit_txn,
     {
         self.unlocks_acked.insert(store);
-        let len = self.unlocks_acked.len();
-        if len == num_stores {
+        if self.lock_requested.is_subset_of(&self.unlocks_acked) {
             self.phase = CoordPhase::Done;
             true
         } else {
@@ -371,6 +1202,37 @@ impl Coordinator {
         }
     }
 
+    /// Operator tool: force-abort a wedged transaction and go to cleanup.
+    /// Unlike handle_lock_failure (Preparing only), this works from any phase
+    /// with a transaction in flight that has not committed, e.g. Preparing with
+    /// responses lost. Idle has nothing to abort, and Done has finished.
+    /// Volatile state is cleared; cleanup targets every store sent a LockReq.
+    pub fn force_abort(&mut self)
+        requires
+            !old(self).wal_committed,
+            old(self).phase != CoordPhase::Idle,
+            !old(self).phase.spec_is_terminal(),
+            old(self).phase != CoordPhase::Committed,
+        ensures
+            self.phase == CoordPhase::Cleanup,
+            self.wal_committed == false,
+            self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
+            self.locks_acquired@ == Set::<u64>::empty(),
+            self.renames_done@ == Set::<u64>::empty(),
+            self.unlocks_acked@ == Set::<u64>::empty(),
+            self.participants@ == Set::<u64>::empty(),
+            self.commit_txn == old(self).commit_txn,
+            self.abort_reason == Some(AbortReason::ForcedByOperator),
+    {
+        self.phase = CoordPhase::Cleanup;
+        self.abort_reason = Some(AbortReason::ForcedByOperator);
+        self.locks_acquired.clear();
+        self.renames_done.clear();
+        self.unlocks_acked.clear();
+        self.participants.clear();
+    }
+
     /// Coordinator crash - reset volatile state
     pub fn crash(&mut self)
         requires
@@ -378,17 +1240,23 @@ impl Coordinator {
         ensures
             // Durable state preserved
             self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
             self.wal_committed == old(self).wal_committed,
             // Volatile state reset
             self.phase == CoordPhase::Crashed,
             self.locks_acquired@ == Set::<u64>::empty(),
             self.renames_done@ == Set::<u64>::empty(),
             self.unlocks_acked@ == Set::<u64>::empty(),
+            self.participants@ == Set::<u64>::empty(),
+            self.commit_txn == old(self).commit_txn,
+            // The diagnostic is kept for recovery to overwrite
+            self.abort_reason == old(self).abort_reason,
     {
         self.phase = CoordPhase::Crashed;
         self.locks_acquired.clear();
         self.renames_done.clear();
         self.unlocks_acked.clear();
+        self.participants.clear();
     }
 
     /// Coordinator recover - increment txn_id, resume based on WAL
@@ -401,23 +1269,140 @@ impl Coordinator {
             self.current_txn_id == old(self).current_txn_id + 1,
             // WAL preserved
             self.wal_committed == old(self).wal_committed,
+            self.lock_requested@ == old(self).lock_requested@,
             // Phase based on WAL
             old(self).wal_committed ==> self.phase == CoordPhase::Committed,
             !old(self).wal_committed ==> self.phase == CoordPhase::Cleanup,
+            self.phase == CoordPhase::spec_resume_phase(old(self).wal_committed),
             // Volatile state reset
             self.locks_acquired@ == Set::<u64>::empty(),
             self.renames_done@ == Set::<u64>::empty(),
             self.unlocks_acked@ == Set::<u64>::empty(),
+            self.participants@ == Set::<u64>::empty(),
+            self.commit_txn == old(self).commit_txn,
+            // Resuming into cleanup without a COMMIT record is an abort
+            !old(self).wal_committed ==> self.abort_reason == Some(AbortReason::RecoveredUncommitted),
+            old(self).wal_committed ==> self.abort_reason == old(self).abort_reason,
     {
         self.current_txn_id = self.current_txn_id + 1;
-        if self.wal_committed {
-            self.phase = CoordPhase::Committed;
-        } else {
-            self.phase = CoordPhase::Cleanup;
+        self.phase = CoordPhase::resume_phase(self.wal_committed);
+        if !self.wal_committed {
+            self.abort_reason = Some(AbortReason::RecoveredUncommitted);
         }
         self.locks_acquired.clear();
         self.renames_done.clear();
         self.unlocks_acked.clear();
+        self.participants.clear();
+    }
+
+    /// Rebuild the lock grants from the stores that report holding this
+    /// transaction's locks, instead of re-requesting them after recovery.
+    /// Matches CoordinatorSpec::rebuild_locks
+    pub fn rebuild_locks(&mut self, reported: &[u64])
+        requires
+            old(self).phase == CoordPhase::Committed || old(self).phase == CoordPhase::Cleanup,
+        ensures
+            forall|s: u64| #[trigger] self.locks_acquired@.contains(s) <==>
+                exists|i: int| 0 <= i < reported@.len() && reported@[i] == s,
+            self.current_txn_id == old(self).current_txn_id,
+            self.lock_requested@ == old(self).lock_requested@,
+            self.wal_committed == old(self).wal_committed,
+            self.commit_txn == old(self).commit_txn,
+            self.phase == old(self).phase,
+            self.renames_done@ == old(self).renames_done@,
+            self.unlocks_acked@ == old(self).unlocks_acked@,
+            self.participants@ == old(self).participants@,
+    {
+        self.locks_acquired.clear();
+        let mut i: usize = 0;
+        while i < reported.len()
+            invariant
+                0 <= i <= reported@.len(),
+                forall|s: u64| #[trigger] self.locks_acquired@.contains(s) <==>
+                    exists|j: int| 0 <= j < i && reported@[j] == s,
+                self.current_txn_id == old(self).current_txn_id,
+                self.wal_committed == old(self).wal_committed,
+                self.commit_txn == old(self).commit_txn,
+                self.phase == old(self).phase,
+                self.lock_requested@ == old(self).lock_requested@,
+                self.renames_done@ == old(self).renames_done@,
+                self.unlocks_acked@ == old(self).unlocks_acked@,
+                self.participants@ == old(self).participants@,
+            decreases
+                reported.len() - i,
+        {
+            self.locks_acquired.insert(reported[i]);
+            proof {
+                assert forall|s: u64| #[trigger] self.locks_acquired@.contains(s) implies
+                    exists|j: int| 0 <= j < i + 1 && reported@[j] == s by {
+                    if s == reported@[i as int] {
+                        assert(reported@[i as int] == s);
+                    }
+                }
+            }
+            i = i + 1;
+        }
+    }
+
+    /// Runtime check that this coordinator is in the state `recover` leaves
+    /// behind: txn id `expected_txn`, the phase the WAL resumes into, and every
+    /// volatile set empty. A recovery harness calls it before resuming
+    pub fn verify_after_recovery(&self, expected_txn: u64) -> (result: bool)
+        ensures
+            result == (self.current_txn_id == expected_txn
+                && self.phase == CoordPhase::spec_resume_phase(self.wal_committed)
+                && self.locks_acquired@ == Set::<u64>::empty()
+                && self.renames_done@ == Set::<u64>::empty()
+                && self.unlocks_acked@ == Set::<u64>::empty()
+                && self.participants@ == Set::<u64>::empty()),
+    {
+        self.current_txn_id == expected_txn
+            && self.phase == CoordPhase::resume_phase(self.wal_committed)
+            && self.locks_acquired.is_empty()
+            && self.renames_done.is_empty()
+            && self.unlocks_acked.is_empty()
+            && self.participants.is_empty()
+    }
+
+    /// Runtime gate for an incoming response: true if recording it now keeps
+    /// the record_* preconditions (right txn, right kind for the phase, store
+    /// in range, not already recorded). A bad response is rejected instead
+    pub fn validate_response(&self, msg: &ExecMessage, num_stores: usize) -> (result: bool)
+        ensures
+            result == self.spec_accepts_response(msg@, num_stores as nat),
+    {
+        if !msg.is_response() || msg.get_txn_id() != self.current_txn_id {
+            return false;
+        }
+        let store = msg.get_store();
+        if store >= num_stores as u64 {
+            return false;
+        }
+        match msg.kind() {
+            MsgKind::LockResp => self.phase == CoordPhase::Preparing
+                && (!msg.is_lock_success() || !self.locks_acquired.contains(&store)),
+            MsgKind::RenameResp => self.phase == CoordPhase::Committed
+                && !self.renames_done.contains(&store),
+            MsgKind::UnlockResp => self.phase == CoordPhase::Cleanup
+                && !self.unlocks_acked.contains(&store),
+            _ => false,
+        }
+    }
+
+    /// Reconcile two replicated durable WAL copies `(txn_id, wal_committed)` that
+    /// recovery found disagreeing (e.g. after a torn write). See
+    /// `spec_reconcile_wal` for why the highest txn wins and commit wins within a txn
+    pub fn reconcile_wal(a: (u64, bool), b: (u64, bool)) -> (result: (u64, bool))
+        ensures
+            (result.0 as nat, result.1) == spec_reconcile_wal((a.0 as nat, a.1), (b.0 as nat, b.1)),
+    {
+        if a.0 > b.0 {
+            a
+        } else if b.0 > a.0 {
+            b
+        } else {
+            (a.0, a.1 || b.1)
+        }
     }
 }
 
@@ -459,15 +1444,60 @@ mod tests {
         assert(coord.has_lock(1));
     }
 
+    /// Test: to_vec lists every element, inline or spilled
+    fn test_simple_set_to_vec() {
+        let mut set = SimpleSet::new();
+        set.insert(3);
+        set.insert(1);
+        let v = set.to_vec();
+        assert(v@.contains(3) && v@.contains(1) && !v@.contains(2));
+
+        set.insert(4);
+        set.insert(1);
+        set.insert(5);
+        set.insert(9);
+        let v = set.to_vec();
+        assert(v@.contains(9) && v@.contains(3));
+    }
+
+    /// Test: acquired_stores reports exactly the recorded grants
+    fn test_acquired_stores() {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        coord.record_lock_success(2);
+        let stores = coord.acquired_stores();
+        assert(stores@.contains(0) && stores@.contains(2) && !stores@.contains(1));
+    }
+
     /// Test: Handle lock failure
     fn test_handle_lock_failure() {
         let mut coord = Coordinator::new();
-        coord.start_preparing();
+        coord.record_lock_request(0);
+        coord.record_lock_request(1);
         coord.record_lock_success(0);
 
         coord.handle_lock_failure();
         assert(coord.get_phase() == CoordPhase::Cleanup);
-        assert(!coord.has_lock(0));  // Locks cleared
+        assert(coord.has_lock(0));
+        // Store 1's grant may still be in flight, so it is an unlock target too
+        assert(coord.is_unlock_target(0));
+        assert(coord.is_unlock_target(1));
+        assert(!coord.is_unlock_target(2));
+    }
+
+    /// Test: Lock failure leaves no rename/unlock credit behind
+    fn test_handle_lock_failure_clears_credit() {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.record_lock_success(0);
+
+        coord.handle_lock_failure();
+        assert(!coord.has_renamed(0));
+        assert(!coord.has_renamed(1));
+        assert(!coord.has_unlocked(0));
+        assert(!coord.has_unlocked(1));
+        assert(!coord.has_lock(1));
     }
 
     /// Test: Decide commit
@@ -475,18 +1505,40 @@ mod tests {
         let mut coord = Coordinator::new();
         coord.start_preparing();
         coord.record_lock_success(0);
+        assert(!coord.can_commit(2));  // decide_commit(2) would not verify here
         coord.record_lock_success(1);
+        assert(coord.can_commit(2));
 
-        coord.decide_commit();
+        coord.decide_commit(Ghost(2));
         assert(coord.is_committed());
         assert(coord.get_phase() == CoordPhase::Committed);
+        assert(coord.has_lock(0) && coord.has_lock(1));
+        assert(!coord.can_commit(2));  // no longer Preparing
+    }
+
+    /// Test: An excluded store's vote and rename are not waited for
+    fn test_exclude_participant() {
+        let mut coord = Coordinator::new();
+        assert(!coord.is_degraded());
+        coord.exclude_participant(2, Ghost(3));
+        assert(coord.is_degraded());
+        assert(coord.is_excluded(2));
+
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        coord.record_lock_success(1);
+        assert(coord.can_commit(3));
+        coord.decide_commit(Ghost(3));
+        assert(!coord.has_lock(2));
     }
 
     /// Test: Record rename done
     fn test_record_rename_done() {
         let mut coord = Coordinator::new();
         coord.start_preparing();
-        coord.decide_commit();
+        coord.record_lock_success(0);
+        coord.record_lock_success(1);
+        coord.decide_commit(Ghost(2));
 
         let all_done = coord.record_rename_done(0, 2);
         assert(!all_done);
@@ -502,27 +1554,71 @@ mod tests {
     /// Test: Record unlock acked
     fn test_record_unlock_acked() {
         let mut coord = Coordinator::new();
-        coord.start_preparing();
-        coord.decide_commit();
+        coord.record_lock_request(0);
+        coord.record_lock_request(1);
+        coord.record_lock_success(0);
+        coord.record_lock_success(1);
+        coord.decide_commit(Ghost(2));
         coord.record_rename_done(0, 2);
         coord.record_rename_done(1, 2);
 
-        let all_done = coord.record_unlock_acked(0, 2);
+        let all_done = coord.record_unlock_acked(0);
         assert(!all_done);
         assert(coord.has_unlocked(0));
         assert(coord.get_phase() == CoordPhase::Cleanup);
 
-        let all_done = coord.record_unlock_acked(1, 2);
+        let all_done = coord.record_unlock_acked(1);
         assert(all_done);
         assert(coord.has_unlocked(1));
         assert(coord.get_phase() == CoordPhase::Done);
     }
 
+    /// Test: Abort completes once every store sent a LockReq has unlocked,
+    /// including the one that refused
+    fn test_record_unlock_acked_after_abort() {
+        let mut coord = Coordinator::new();
+        coord.record_lock_request(0);
+        coord.record_lock_request(1);
+        coord.record_lock_request(2);
+        coord.record_lock_success(0);
+        coord.record_lock_success(1);
+        coord.handle_lock_failure();
+
+        let all_done = coord.record_unlock_acked(0);
+        assert(!all_done);
+        let all_done = coord.record_unlock_acked(1);
+        assert(!all_done);
+        let all_done = coord.record_unlock_acked(2);
+        assert(all_done);
+        assert(coord.get_phase() == CoordPhase::Done);
+    }
+
+    /// Test: A participant added after two stores locked holds back all_votes_in
+    fn test_add_participant_after_locks() {
+        let mut coord = Coordinator::new();
+        coord.add_participant(0);
+        coord.add_participant(1);
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        coord.record_lock_success(1);
+        assert(coord.all_votes_in());
+
+        coord.add_participant(2);
+        assert(!coord.all_votes_in());
+        assert(!coord.can_commit(3));
+
+        coord.record_lock_success(2);
+        assert(coord.all_votes_in());
+        assert(coord.can_commit(3));
+    }
+
     /// Test: Crash and recover (committed)
     fn test_crash_recover_committed() {
         let mut coord = Coordinator::new();
         coord.start_preparing();
-        coord.decide_commit();
+        coord.record_lock_success(0);
+        coord.record_lock_success(1);
+        coord.decide_commit(Ghost(2));
         coord.record_rename_done(0, 2);
 
         // Crash
@@ -556,6 +1652,188 @@ mod tests {
         assert(!coord.is_committed());
         assert(coord.get_phase() == CoordPhase::Cleanup);  // Go to cleanup
     }
+
+    /// Test: A recovered coordinator rebuilds its grants from store reports
+    fn test_rebuild_locks() {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        coord.record_lock_success(1);
+        coord.decide_commit(Ghost(2));
+        coord.crash();
+        coord.recover();
+        assert(!coord.has_lock(0));
+
+        let mut reported: Vec<u64> = Vec::new();
+        reported.push(1);
+        coord.rebuild_locks(reported.as_slice());
+        assert(reported@[0] == 1);
+        assert(coord.has_lock(1));
+        assert(!coord.has_lock(0));
+        assert(coord.get_phase() == CoordPhase::Committed);
+    }
+
+    /// Test: validate_response rejects a wrong txn, a wrong phase and a duplicate
+    fn test_validate_response() {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        let ok = ExecMessage::lock_resp(0, true, 1);
+        assert(coord.validate_response(&ok, 2));
+        // Wrong txn
+        assert(!coord.validate_response(&ExecMessage::lock_resp(0, true, 2), 2));
+        // Store out of range
+        assert(!coord.validate_response(&ExecMessage::lock_resp(2, true, 1), 2));
+        // Wrong phase: renames are only recorded once committed
+        assert(!coord.validate_response(&ExecMessage::rename_resp(0, 1), 2));
+        // Requests are never recorded
+        assert(!coord.validate_response(&ExecMessage::lock_req(0, 1), 2));
+        // Duplicate
+        coord.record_lock_success(0);
+        assert(!coord.validate_response(&ok, 2));
+        assert(coord.validate_response(&ExecMessage::lock_resp(1, true, 1), 2));
+    }
+
+    /// Test: The commit point keeps its txn id across crash and recovery
+    fn test_committed_txn_id_after_recovery() {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        assert(coord.committed_txn_id().is_none());
+        coord.decide_commit(Ghost(1));
+        assert(coord.committed_txn_id() == Some(1u64));
+
+        coord.crash();
+        coord.recover();
+        assert(coord.get_txn_id() == 2);
+        assert(coord.committed_txn_id() == Some(1u64));
+    }
+
+    /// Test: Crashing again before recovery finishes, then recovering again
+    fn test_double_crash_recover() {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        coord.record_lock_success(1);
+        coord.decide_commit(Ghost(2));
+
+        coord.crash();
+        coord.recover();
+        assert(coord.get_phase() == CoordPhase::Committed);
+        // Committed is crashable, so a second crash mid-recovery is allowed
+        coord.crash();
+        coord.recover();
+        assert(coord.get_txn_id() == 3);  // One increment per recovery
+        assert(coord.is_committed());  // WAL survives both crashes
+        assert(coord.get_phase() == CoordPhase::Committed);
+    }
+
+    /// Test: Force-abort from a partially-responded Preparing
+    fn test_force_abort_mid_preparing() {
+        let mut coord = Coordinator::new();
+        coord.record_lock_request(0);
+        coord.record_lock_request(1);
+        coord.record_lock_success(0);
+
+        coord.force_abort();
+        assert(coord.get_phase() == CoordPhase::Cleanup);
+        assert(!coord.is_committed());
+        assert(coord.get_txn_id() == 1);
+        assert(coord.is_unlock_target(0));
+        assert(coord.is_unlock_target(1));
+    }
+
+    /// Test: each abort path records why it aborted
+    fn test_abort_reason_per_transition() {
+        let mut coord = Coordinator::new();
+        assert(coord.abort_reason().is_none());
+        coord.start_preparing();
+        coord.handle_lock_failure();
+        assert(coord.abort_reason() == Some(AbortReason::LockFailure));
+
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.force_abort();
+        assert(coord.abort_reason() == Some(AbortReason::ForcedByOperator));
+
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.crash();
+        coord.recover();
+        assert(coord.abort_reason() == Some(AbortReason::RecoveredUncommitted));
+    }
+
+    /// Test: Reconcile agreeing and conflicting WAL copies
+    fn test_reconcile_wal() {
+        // Agreeing copies
+        assert(Coordinator::reconcile_wal((3, true), (3, true)) == (3u64, true));
+        assert(Coordinator::reconcile_wal((3, false), (3, false)) == (3u64, false));
+        // Same txn, torn COMMIT record: commit wins
+        assert(Coordinator::reconcile_wal((3, false), (3, true)) == (3u64, true));
+        assert(Coordinator::reconcile_wal((3, true), (3, false)) == (3u64, true));
+        // Newer txn supersedes an older commit
+        assert(Coordinator::reconcile_wal((2, true), (4, false)) == (4u64, false));
+    }
+
+    /// Test: headroom shrinks to zero at u64::MAX and trips the overflow warning
+    fn test_txn_id_headroom() {
+        let mut coord = Coordinator::new();
+        assert(coord.txn_id_headroom() == u64::MAX - 1);
+        assert(!coord.is_near_overflow(1000));
+
+        coord.current_txn_id = u64::MAX - 3;
+        assert(coord.txn_id_headroom() == 3);
+        assert(coord.is_near_overflow(3));
+        assert(!coord.is_near_overflow(2));
+
+        coord.current_txn_id = u64::MAX;
+        assert(coord.txn_id_headroom() == 0);
+        assert(coord.is_near_overflow(0));
+    }
+
+    /// Test: The WAL and phase stay consistent through a commit, and a
+    /// hand-corrupted combination is caught
+    fn test_check_durable() {
+        let mut coord = Coordinator::new();
+        assert(coord.check_durable());
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        coord.decide_commit(Ghost(1));
+        assert(coord.check_durable());
+
+        coord.wal_committed = false;
+        assert(!coord.check_durable());
+    }
+
+    /// Test: a freshly recovered coordinator passes the recovery check
+    fn test_verify_after_recovery() {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        let txn = coord.get_txn_id();
+        coord.crash();
+        coord.recover();
+        assert(coord.verify_after_recovery(txn + 1));
+        assert(!coord.verify_after_recovery(txn));
+    }
+
+    /// Test: a sub-coordinator is Pending while a lock is outstanding, votes
+    /// Yes with every lock, and No once it aborts
+    fn test_as_vote() {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        assert(coord.as_vote(2) == SubVote::Pending);
+        coord.record_lock_success(1);
+        assert(coord.as_vote(2) == SubVote::Yes);
+        coord.handle_lock_failure();
+        assert(coord.as_vote(2) == SubVote::No);
+    }
+
+    /// Test: WAL -> resume phase mapping
+    fn test_resume_phase() {
+        assert(CoordPhase::resume_phase(true) == CoordPhase::Committed);
+        assert(CoordPhase::resume_phase(false) == CoordPhase::Cleanup);
+    }
 }
 
 } // verus!
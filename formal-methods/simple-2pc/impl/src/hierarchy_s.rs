@@ -0,0 +1,471 @@
+// hierarchy_s.rs - Two-level (hierarchical) 2PC specification layer
+//
+// This file contains:
+// - SubCoordinatorSpec: one group's SystemSpec (coordinator, store group and
+//   network), seen by the root as a single virtual participant
+// - HierarchicalSpec: a root CoordinatorSpec driving sub-coordinators
+// - Proofs that every two-level step preserves global data_accessible
+//
+// The root reuses CoordinatorSpec unchanged, with group ids in place of store
+// ids. A group's vote is the AND of its stores' votes: it votes yes once its
+// coordinator holds a lock at every store of the group. Root <-> group
+// communication is synchronous here; only the group level runs over a lossy
+// network.
+
+use vstd::prelude::*;
+
+use crate::coordinator_s::*;
+use crate::kv_store_s::*;
+use crate::network_s::*;
+use crate::system_s::*;
+
+verus! {
+
+/// Group id: the root's name for a sub-coordinator (a virtual participant)
+pub type GroupId = StoreId;
+
+/// A sub-coordinator together with the group of stores it manages
+pub ghost struct SubCoordinatorSpec<V> {
+    /// The group's coordinator, stores and network
+    pub sys: SystemSpec<V>,
+}
+
+impl<V> SubCoordinatorSpec<V> {
+    /// The group votes yes: every store of the group granted its locks
+    pub open spec fn votes_yes(&self) -> bool {
+        &&& self.sys.coord.phase == CoordPhase::Preparing
+        &&& self.sys.coord.all_locks_acquired(self.sys.all_stores)
+    }
+
+    /// Group invariant: the single-level agreement invariant
+    pub open spec fn inv(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        self.sys.agreement_inv(key_a, key_aprime)
+    }
+
+    /// Apply the root's decision: the group's coordinator commits or aborts
+    pub open spec fn apply_decision(self, commit: bool) -> Self
+        recommends
+            self.sys.coord.phase == CoordPhase::Preparing,
+    {
+        if commit {
+            SubCoordinatorSpec { sys: self.sys.coord_decide_commit() }
+        } else {
+            SubCoordinatorSpec { sys: self.sys.with_coord(self.sys.coord.recv_lock_resp_failure()) }
+        }
+    }
+
+    /// Lemma: applying the root's decision to a preparing group preserves its invariant
+    pub proof fn lemma_apply_decision_preserves_inv(
+        self,
+        commit: bool,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.inv(key_a, key_aprime),
+            self.sys.coord.phase == CoordPhase::Preparing,
+        ensures
+            self.apply_decision(commit).inv(key_a, key_aprime),
+    {
+        if commit {
+            self.sys.lemma_coord_decide_commit_preserves_agreement(key_a, key_aprime);
+        } else {
+            // Preparing means no commit yet, so the abort keeps wal_committed false
+            let post = self.apply_decision(commit).sys;
+            assert(!self.sys.coord.wal_committed);
+            assert(post.coord.renames_done =~= Set::<StoreId>::empty());
+        }
+    }
+}
+
+/// Two-level system state: a root coordinator over sub-coordinators
+pub ghost struct HierarchicalSpec<V> {
+    /// Root coordinator; its participants are group ids
+    pub root: CoordinatorSpec,
+    pub subs: Map<GroupId, SubCoordinatorSpec<V>>,
+    pub all_groups: Set<GroupId>,
+}
+
+impl<V> HierarchicalSpec<V> {
+    // ============================================================
+    // Observations / basic helpers
+    // ============================================================
+
+    pub open spec fn sub(&self, g: GroupId) -> SubCoordinatorSpec<V>
+        recommends self.subs.contains_key(g)
+    {
+        self.subs[g]
+    }
+
+    pub open spec fn with_sub(self, g: GroupId, sub: SubCoordinatorSpec<V>) -> Self {
+        HierarchicalSpec { subs: self.subs.insert(g, sub), ..self }
+    }
+
+    // ============================================================
+    // Root actions (CoordinatorSpec over group ids)
+    // ============================================================
+
+    /// Root asks group `g` to prepare
+    pub open spec fn root_prepare(self, g: GroupId) -> Self
+        recommends
+            self.root.phase == CoordPhase::Idle || self.root.phase == CoordPhase::Preparing,
+            self.all_groups.contains(g),
+    {
+        HierarchicalSpec { root: self.root.send_lock_req(g).0, ..self }
+    }
+
+    /// Root records group `g`'s yes vote
+    pub open spec fn root_recv_vote_yes(self, g: GroupId) -> Self
+        recommends
+            self.root.phase == CoordPhase::Preparing,
+            self.sub(g).votes_yes(),
+    {
+        HierarchicalSpec { root: self.root.recv_lock_resp_success(g), ..self }
+    }
+
+    /// Root records a no vote (some group could not lock) and aborts
+    pub open spec fn root_recv_vote_no(self) -> Self
+        recommends
+            self.root.phase == CoordPhase::Preparing,
+    {
+        HierarchicalSpec { root: self.root.recv_lock_resp_failure(), ..self }
+    }
+
+    /// Root commits once every group voted yes
+    pub open spec fn root_decide_commit(self) -> Self
+        recommends
+            self.root.phase == CoordPhase::Preparing,
+            self.root.all_locks_acquired(self.all_groups),
+    {
+        HierarchicalSpec { root: self.root.decide_commit(), ..self }
+    }
+
+    // ============================================================
+    // Group actions
+    // ============================================================
+
+    /// Deliver the root's decision to group `g`
+    pub open spec fn deliver_decision(self, g: GroupId) -> Self
+        recommends
+            self.subs.contains_key(g),
+            self.root.phase == CoordPhase::Committed || self.root.phase == CoordPhase::Cleanup,
+            self.sub(g).sys.coord.phase == CoordPhase::Preparing,
+    {
+        self.with_sub(g, self.sub(g).apply_decision(self.root.wal_committed))
+    }
+
+    /// `next` is a legal step of group `g`: one single-level `SystemSpec::next`
+    /// step that does not set the group's WAL commit. The commit point belongs to
+    /// the root, so a group only commits through `deliver_decision`.
+    pub open spec fn group_step_enabled(
+        self,
+        g: GroupId,
+        next: SystemSpec<V>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    ) -> bool {
+        &&& self.subs.contains_key(g)
+        &&& self.sub(g).sys.next(next, key_a, key_aprime)
+        &&& next.coord.wal_committed ==> self.sub(g).sys.coord.wal_committed
+    }
+
+    /// Group `g` takes one single-level step (coordinator, store or network),
+    /// ending in `next`; a step that is not enabled leaves the system unchanged
+    pub open spec fn group_step(
+        self,
+        g: GroupId,
+        next: SystemSpec<V>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    ) -> Self {
+        if self.group_step_enabled(g, next, key_a, key_aprime) {
+            self.with_sub(g, SubCoordinatorSpec { sys: next })
+        } else {
+            self
+        }
+    }
+
+    // ============================================================
+    // Invariants
+    // ============================================================
+
+    /// Every store of every group keeps its value at exactly one of {A, A'}
+    pub open spec fn global_data_accessible(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        forall|g: GroupId, s: StoreId|
+            #[trigger] self.subs.contains_key(g) && #[trigger] self.subs[g].sys.stores.contains_key(s)
+                ==> data_accessible(self.subs[g].sys.stores[s], key_a, key_aprime)
+    }
+
+    /// Groups agree with the root's decision: a group only commits once the root
+    /// has committed, so a root abort leaves every group uncommitted
+    pub open spec fn decisions_agree(&self) -> bool {
+        forall|g: GroupId| #[trigger] self.subs.contains_key(g) && self.subs[g].sys.coord.wal_committed
+            ==> self.root.wal_committed
+    }
+
+    /// Every group satisfies the single-level agreement invariant, and every
+    /// group's decision agrees with the root's
+    pub open spec fn hier_inv(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        &&& forall|g: GroupId| #[trigger] self.subs.contains_key(g) ==> self.subs[g].inv(key_a, key_aprime)
+        &&& self.decisions_agree()
+    }
+
+    /// Lemma: the hierarchical invariant implies global data accessibility
+    pub proof fn lemma_hier_inv_implies_data_accessible(self, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.hier_inv(key_a, key_aprime),
+        ensures
+            self.global_data_accessible(key_a, key_aprime),
+    {
+        assert forall|g: GroupId, s: StoreId|
+            #[trigger] self.subs.contains_key(g) && #[trigger] self.subs[g].sys.stores.contains_key(s)
+            implies data_accessible(self.subs[g].sys.stores[s], key_a, key_aprime) by {
+            assert(self.subs[g].inv(key_a, key_aprime));
+        }
+    }
+
+    /// Lemma: while the root has not committed, no group has committed either
+    pub proof fn lemma_root_abort_no_group_commits(self, g: GroupId, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.hier_inv(key_a, key_aprime),
+            self.subs.contains_key(g),
+            !self.root.wal_committed,
+        ensures
+            !self.sub(g).sys.coord.wal_committed,
+    {
+    }
+
+    /// Lemma: root actions never touch a group and never clear the root's WAL
+    /// commit, so they preserve the invariant
+    pub proof fn lemma_root_actions_preserve_inv(self, g: GroupId, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.hier_inv(key_a, key_aprime),
+        ensures
+            self.root_prepare(g).hier_inv(key_a, key_aprime),
+            self.root_recv_vote_yes(g).hier_inv(key_a, key_aprime),
+            self.root_recv_vote_no().hier_inv(key_a, key_aprime),
+            self.root_decide_commit().hier_inv(key_a, key_aprime),
+    {
+    }
+
+    /// Lemma: a group step that keeps the group's invariant (as proven by the
+    /// single-level preservation lemmas in system_s) preserves the invariant
+    pub proof fn lemma_group_step_preserves_inv(
+        self,
+        g: GroupId,
+        next: SystemSpec<V>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.hier_inv(key_a, key_aprime),
+            next.agreement_inv(key_a, key_aprime),
+        ensures
+            self.group_step(g, next, key_a, key_aprime).hier_inv(key_a, key_aprime),
+            self.group_step(g, next, key_a, key_aprime).global_data_accessible(key_a, key_aprime),
+    {
+        let post = self.group_step(g, next, key_a, key_aprime);
+        if self.group_step_enabled(g, next, key_a, key_aprime) {
+            assert forall|g2: GroupId| #[trigger] post.subs.contains_key(g2) implies
+                post.subs[g2].inv(key_a, key_aprime) && (post.subs[g2].sys.coord.wal_committed ==> post.root.wal_committed) by {
+                if g2 != g {
+                    assert(self.subs.contains_key(g2));
+                } else if next.coord.wal_committed {
+                    // Only a group that had already committed may end committed
+                    assert(self.subs[g].sys.coord.wal_committed);
+                }
+            }
+        }
+        post.lemma_hier_inv_implies_data_accessible(key_a, key_aprime);
+    }
+
+    /// Lemma: delivering the root's decision to a preparing group preserves the
+    /// invariant, and so global data accessibility
+    pub proof fn lemma_deliver_decision_preserves_inv(
+        self,
+        g: GroupId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.hier_inv(key_a, key_aprime),
+            self.subs.contains_key(g),
+            self.sub(g).sys.coord.phase == CoordPhase::Preparing,
+        ensures
+            self.deliver_decision(g).hier_inv(key_a, key_aprime),
+            self.deliver_decision(g).global_data_accessible(key_a, key_aprime),
+    {
+        let next = self.sub(g).apply_decision(self.root.wal_committed);
+        self.sub(g).lemma_apply_decision_preserves_inv(self.root.wal_committed, key_a, key_aprime);
+        let post = self.deliver_decision(g);
+        assert forall|g2: GroupId| #[trigger] post.subs.contains_key(g2) implies
+            post.subs[g2].inv(key_a, key_aprime) && (post.subs[g2].sys.coord.wal_committed ==> post.root.wal_committed) by {
+            if g2 != g {
+                assert(self.subs.contains_key(g2));
+            } else if !self.root.wal_committed {
+                // An abort keeps the group's WAL as it was: uncommitted while preparing
+                assert(!self.sub(g).sys.coord.wal_committed);
+                assert(!next.sys.coord.wal_committed);
+            }
+        }
+        post.lemma_hier_inv_implies_data_accessible(key_a, key_aprime);
+    }
+}
+
+// ============================================================
+// TESTS
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    spec fn key_a() -> Seq<char> { "A"@ }
+    spec fn key_aprime() -> Seq<char> { "A'"@ }
+
+    /// A group with one store `s` holding `value` at A
+    spec fn mk_group(s: StoreId, value: u64) -> SubCoordinatorSpec<u64> {
+        SubCoordinatorSpec {
+            sys: SystemSpec {
+                coord: CoordinatorSpec::init(),
+                net: NetworkSpec::empty(),
+                stores: Map::empty().insert(s, KvStoreSpec::empty().put(key_a(), value)),
+                all_stores: Set::empty().insert(s),
+            },
+        }
+    }
+
+    /// Group-level prepare for a one-store group, one `next` step at a time:
+    /// lock `s` and record its vote
+    proof fn prepare_group(h: HierarchicalSpec<u64>, g: GroupId, s: StoreId) -> (post: HierarchicalSpec<u64>)
+        requires
+            h.subs.contains_key(g),
+            h.sub(g).sys.all_stores.contains(s),
+            h.sub(g).sys.stores.contains_key(s),
+            h.sub(g).sys.coord.phase == CoordPhase::Preparing || h.sub(g).sys.coord.phase == CoordPhase::Idle,
+            h.sub(g).sys.coord.current_txn_id == 1,
+            !h.sub(g).sys.coord.wal_committed,
+            h.sub(g).sys.store(s).contains_key(key_a()),
+            !h.sub(g).sys.store(s).is_locked(key_a()),
+            !h.sub(g).sys.store(s).is_locked(key_aprime()),
+            h.sub(g).sys.store(s).last_seen_txn_id < 1,
+        ensures
+            post.subs.dom() == h.subs.dom(),
+            post.root == h.root,
+            forall|g2: GroupId| g2 != g && #[trigger] h.subs.contains_key(g2) ==> post.subs[g2] == h.subs[g2],
+            post.sub(g).sys.coord.locks_acquired == h.sub(g).sys.coord.locks_acquired.insert(s),
+            post.sub(g).sys.coord.phase == CoordPhase::Preparing,
+            post.sub(g).sys.all_stores == h.sub(g).sys.all_stores,
+    {
+        let sys0 = h.sub(g).sys;
+        let sys1 = sys0.coord_send_lock_req(s);
+        assert(sys0.next(sys1, key_a(), key_aprime()));
+        let h1 = h.group_step(g, sys1, key_a(), key_aprime());
+        assert(h1.sub(g).sys == sys1);
+
+        assert(sys1.net.contains(lock_req_msg(s, 1)));
+        let sys2 = sys1.store_handle_lock_req(s, 1, key_a(), key_aprime());
+        assert(sys1.next(sys2, key_a(), key_aprime()));
+        let h2 = h1.group_step(g, sys2, key_a(), key_aprime());
+        assert(h2.sub(g).sys == sys2);
+
+        assert(sys2.net.contains(lock_resp_msg(s, true, 1)));
+        let sys3 = sys2.coord_recv_lock_resp_success(s);
+        assert(sys2.next(sys3, key_a(), key_aprime()));
+        let h3 = h2.group_step(g, sys3, key_a(), key_aprime());
+        assert(h3.sub(g).sys == sys3);
+        assert(h3.subs.dom() =~= h.subs.dom());
+        h3
+    }
+
+    /// Group-level rename for a committed group: one `next` step to send the
+    /// RenameReq and one for `s` to handle it
+    proof fn rename_group(h: HierarchicalSpec<u64>, g: GroupId, s: StoreId) -> (post: HierarchicalSpec<u64>)
+        requires
+            h.subs.contains_key(g),
+            h.sub(g).sys.all_stores.contains(s),
+            h.sub(g).sys.stores.contains_key(s),
+            h.sub(g).sys.coord.phase == CoordPhase::Committed,
+            h.sub(g).sys.coord.wal_committed,
+        ensures
+            post.subs.dom() == h.subs.dom(),
+            post.root == h.root,
+            forall|g2: GroupId| g2 != g && #[trigger] h.subs.contains_key(g2) ==> post.subs[g2] == h.subs[g2],
+            post.sub(g).sys == h.sub(g).sys.coord_send_rename_req(s)
+                .store_handle_rename_req(s, 1, key_a(), key_aprime()),
+    {
+        let sys0 = h.sub(g).sys;
+        let sys1 = sys0.coord_send_rename_req(s);
+        assert(sys0.next(sys1, key_a(), key_aprime()));
+        let h1 = h.group_step(g, sys1, key_a(), key_aprime());
+
+        assert(sys1.net.contains(rename_req_msg(s, 1)));
+        let sys2 = sys1.store_handle_rename_req(s, 1, key_a(), key_aprime());
+        assert(sys1.next(sys2, key_a(), key_aprime()));
+        let h2 = h1.group_step(g, sys2, key_a(), key_aprime());
+        assert(h2.subs.dom() =~= h.subs.dom());
+        h2
+    }
+
+    /// Two groups of one store each: both vote yes, the root commits, and each
+    /// group renames its store; every store keeps its value at exactly one key
+    proof fn test_two_group_commit() {
+        let (g0, g1): (GroupId, GroupId) = (0, 1);
+        let (s0, s1): (StoreId, StoreId) = (10, 11);
+        let h0 = HierarchicalSpec {
+            root: CoordinatorSpec::init(),
+            subs: Map::empty().insert(g0, mk_group(s0, 5)).insert(g1, mk_group(s1, 6)),
+            all_groups: Set::empty().insert(g0).insert(g1),
+        };
+
+        // Root asks both groups to prepare; each group locks its store
+        let h1 = h0.root_prepare(g0).root_prepare(g1);
+        assert(h1.root.phase == CoordPhase::Preparing);
+        let h2 = prepare_group(h1, g0, s0);
+        let h3 = prepare_group(h2, g1, s1);
+        assert(h3.sub(g0).sys.coord.locks_acquired =~= h3.sub(g0).sys.all_stores);
+        assert(h3.sub(g1).sys.coord.locks_acquired =~= h3.sub(g1).sys.all_stores);
+        assert(h3.sub(g0).votes_yes());
+        assert(h3.sub(g1).votes_yes());
+
+        // Root collects both yes votes and commits
+        let h4 = h3.root_recv_vote_yes(g0).root_recv_vote_yes(g1);
+        assert(h4.root.locks_acquired =~= h4.all_groups);
+        let h5 = h4.root_decide_commit();
+        assert(h5.root.wal_committed);
+
+        // The decision reaches each group, which commits and renames its store
+        let h6 = h5.deliver_decision(g0).deliver_decision(g1);
+        assert(h6.sub(g0).sys.coord.phase == CoordPhase::Committed);
+        assert(h6.sub(g1).sys.coord.phase == CoordPhase::Committed);
+        let h7 = rename_group(h6, g0, s0);
+        let h8 = rename_group(h7, g1, s1);
+
+        assert(h8.sub(g0).sys.store(s0).contains_key(key_aprime()));
+        assert(!h8.sub(g0).sys.store(s0).contains_key(key_a()));
+        assert(h8.sub(g1).sys.store(s1).contains_key(key_aprime()));
+        assert(!h8.sub(g1).sys.store(s1).contains_key(key_a()));
+        assert(data_accessible(h8.sub(g0).sys.store(s0), key_a(), key_aprime()));
+        assert(data_accessible(h8.sub(g1).sys.store(s1), key_a(), key_aprime()));
+    }
+
+    /// A group cannot take its own commit step: the root owns the decision
+    proof fn test_group_cannot_commit_alone() {
+        let (g0, s0): (GroupId, StoreId) = (0, 10);
+        let h0 = HierarchicalSpec {
+            root: CoordinatorSpec::init(),
+            subs: Map::empty().insert(g0, mk_group(s0, 5)),
+            all_groups: Set::empty().insert(g0),
+        };
+        let h1 = prepare_group(h0.root_prepare(g0), g0, s0);
+        assert(h1.sub(g0).votes_yes());
+
+        let committed = h1.sub(g0).sys.coord_decide_commit();
+        assert(committed.coord.wal_committed);
+        assert(!h1.group_step_enabled(g0, committed, key_a(), key_aprime()));
+        assert(h1.group_step(g0, committed, key_a(), key_aprime()) == h1);
+        assert(!h1.sub(g0).sys.coord.wal_committed);
+    }
+}
+
+} // verus!
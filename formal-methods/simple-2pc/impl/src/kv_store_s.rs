@@ -23,6 +23,20 @@ pub ghost struct KvStoreSpec<V> {
     pub locked_keys: Set<Seq<char>>,
     /// Last seen transaction ID - used to reject stale messages
     pub last_seen_txn_id: nat,
+    /// Transaction id that last wrote each key (set by put and rename)
+    pub modified_by: Map<Seq<char>, nat>,
+    /// Lock generation of each key: bumped each time the key goes from
+    /// unlocked to locked, never decreased (absent means 0)
+    pub lock_gen: Map<Seq<char>, nat>,
+    /// Read-only maintenance mode. The transitions below describe a writable
+    /// store; the exec layer turns its mutators into no-ops while this is set
+    pub frozen: bool,
+    /// Txn the store durably voted yes for and still awaits a decision on
+    /// (survives a crash, unlike `locked_keys`)
+    pub prepared_txn: Option<nat>,
+    /// Txn whose `UnlockReq` the store has handled. A late or duplicate
+    /// `LockReq` for it is refused instead of re-taking the locks
+    pub released_txn: Option<nat>,
 }
 
 impl<V> KvStoreSpec<V> {
@@ -53,6 +67,32 @@ impl<V> KvStoreSpec<V> {
         self.data.contains_key(key)
     }
 
+    /// Is the store in read-only maintenance mode?
+    pub open spec fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Has the store voted yes for `txn_id` and still awaits its decision?
+    pub open spec fn is_prepared(&self, txn_id: nat) -> bool {
+        self.prepared_txn == Some(txn_id)
+    }
+
+    /// Has the store already released the locks of `txn_id`?
+    pub open spec fn is_released(&self, txn_id: nat) -> bool {
+        self.released_txn == Some(txn_id)
+    }
+
+    /// Is either key of the rename locked although the store is not prepared
+    /// for `txn_id`? Such a lock is someone else's, so `txn_id` must vote no
+    pub open spec fn locked_by_other(&self, key_a: Seq<char>, key_aprime: Seq<char>, txn_id: nat) -> bool {
+        (self.is_locked(key_a) || self.is_locked(key_aprime)) && !self.is_prepared(txn_id)
+    }
+
+    /// Check if any key is locked
+    pub open spec fn any_locked(&self) -> bool {
+        exists|k: Seq<char>| self.is_locked(k)
+    }
+
     /// Get value for key (only meaningful if key exists)
     pub open spec fn get(&self, key: Seq<char>) -> V
         recommends self.contains_key(key)
@@ -60,6 +100,52 @@ impl<V> KvStoreSpec<V> {
         self.data[key]
     }
 
+    /// Transaction id that last wrote `key`, if any
+    pub open spec fn last_writer(&self, key: Seq<char>) -> Option<nat> {
+        if self.modified_by.contains_key(key) {
+            Some(self.modified_by[key])
+        } else {
+            None
+        }
+    }
+
+    /// How many times `key` has been locked (0 if never)
+    pub open spec fn lock_generation(&self, key: Seq<char>) -> nat {
+        if self.lock_gen.contains_key(key) {
+            self.lock_gen[key]
+        } else {
+            0
+        }
+    }
+
+    /// The current value of `key` matches `expected` (None means the key is absent)
+    pub open spec fn value_matches(&self, key: Seq<char>, expected: Option<V>) -> bool {
+        match expected {
+            Some(v) => self.contains_key(key) && self.data[key] == v,
+            None => !self.contains_key(key),
+        }
+    }
+
+    /// Number of keys whose value is `value`
+    pub open spec fn count_value(&self, value: V) -> nat {
+        self.data.dom().filter(|k: Seq<char>| self.data[k] == value).len()
+    }
+
+    /// Number of keys that have a value and are not locked
+    pub open spec fn count_present_unlocked(&self) -> nat {
+        self.data.dom().filter(|k: Seq<char>| !self.locked_keys.contains(k)).len()
+    }
+
+    /// Number of keys that have a value and are locked
+    pub open spec fn count_present_locked(&self) -> nat {
+        self.data.dom().filter(|k: Seq<char>| self.locked_keys.contains(k)).len()
+    }
+
+    /// Number of locked keys without a value (e.g. A' between lock and rename)
+    pub open spec fn count_absent_locked(&self) -> nat {
+        self.locked_keys.filter(|k: Seq<char>| !self.data.contains_key(k)).len()
+    }
+
     // ============================================================
     // SPEC FUNCTIONS - State transitions
     // ============================================================
@@ -70,15 +156,50 @@ impl<V> KvStoreSpec<V> {
             data: Map::empty(),
             locked_keys: Set::empty(),
             last_seen_txn_id: 0,
+            modified_by: Map::empty(),
+            lock_gen: Map::empty(),
+            frozen: false,
+            prepared_txn: None,
+            released_txn: None,
         }
     }
 
+    /// Enter read-only maintenance mode
+    pub open spec fn freeze(self) -> Self {
+        KvStoreSpec { frozen: true, ..self }
+    }
+
+    /// Leave read-only maintenance mode
+    pub open spec fn unfreeze(self) -> Self {
+        KvStoreSpec { frozen: false, ..self }
+    }
+
+    /// Durably record a yes vote for `txn_id`
+    pub open spec fn prepare(self, txn_id: nat) -> Self {
+        KvStoreSpec { prepared_txn: Some(txn_id), ..self }
+    }
+
+    /// Forget the prepared vote once the decision has been applied
+    pub open spec fn clear_prepared(self) -> Self {
+        KvStoreSpec { prepared_txn: None, ..self }
+    }
+
+    /// Remember that the locks of `txn_id` were released
+    pub open spec fn release(self, txn_id: nat) -> Self {
+        KvStoreSpec { released_txn: Some(txn_id), ..self }
+    }
+
     /// Update the last seen transaction ID
     pub open spec fn update_txn_id(self, txn_id: nat) -> Self {
         KvStoreSpec {
             data: self.data,
             locked_keys: self.locked_keys,
             last_seen_txn_id: if txn_id > self.last_seen_txn_id { txn_id } else { self.last_seen_txn_id },
+            modified_by: self.modified_by,
+            lock_gen: self.lock_gen,
+            prepared_txn: self.prepared_txn,
+            released_txn: self.released_txn,
+            frozen: self.frozen,
         }
     }
 
@@ -91,6 +212,11 @@ impl<V> KvStoreSpec<V> {
                 data: self.data.insert(key, value),
                 locked_keys: self.locked_keys,
                 last_seen_txn_id: self.last_seen_txn_id,
+                modified_by: self.modified_by.insert(key, self.last_seen_txn_id),
+                lock_gen: self.lock_gen,
+                prepared_txn: self.prepared_txn,
+                released_txn: self.released_txn,
+                frozen: self.frozen,
             }
         }
     }
@@ -104,16 +230,87 @@ impl<V> KvStoreSpec<V> {
                 data: self.data.remove(key),
                 locked_keys: self.locked_keys,
                 last_seen_txn_id: self.last_seen_txn_id,
+                modified_by: self.modified_by,
+                lock_gen: self.lock_gen,
+                prepared_txn: self.prepared_txn,
+                released_txn: self.released_txn,
+                frozen: self.frozen,
             }
         }
     }
 
-    /// Lock a key (idempotent)
+    /// Lock a key (idempotent); taking a free key bumps its lock generation
     pub open spec fn lock(self, key: Seq<char>) -> Self {
-        KvStoreSpec {
-            data: self.data,
-            locked_keys: self.locked_keys.insert(key),
-            last_seen_txn_id: self.last_seen_txn_id,
+        if self.is_locked(key) {
+            self
+        } else {
+            KvStoreSpec {
+                data: self.data,
+                locked_keys: self.locked_keys.insert(key),
+                last_seen_txn_id: self.last_seen_txn_id,
+                modified_by: self.modified_by,
+                lock_gen: self.lock_gen.insert(key, self.lock_generation(key) + 1),
+                prepared_txn: self.prepared_txn,
+                released_txn: self.released_txn,
+                frozen: self.frozen,
+            }
+        }
+    }
+
+    /// Lock a key only if its current value (or absence) matches `expected`
+    pub open spec fn lock_if_value(self, key: Seq<char>, expected: Option<V>) -> Self {
+        if self.value_matches(key, expected) {
+            self.lock(key)
+        } else {
+            self
+        }
+    }
+
+    /// Entry-style insert: keep an existing value, otherwise put `default`
+    /// (which, like put, does nothing if the key is locked)
+    pub open spec fn get_or_insert(self, key: Seq<char>, default: V) -> Self {
+        if self.contains_key(key) {
+            self
+        } else {
+            self.put(key, default)
+        }
+    }
+
+    /// Write under the in-flight transaction's lock (only if locked); see
+    /// KvStore::multi_put_locked
+    pub open spec fn put_locked(self, key: Seq<char>, value: V) -> Self {
+        if !self.is_locked(key) {
+            self
+        } else {
+            KvStoreSpec {
+                data: self.data.insert(key, value),
+                locked_keys: self.locked_keys,
+                last_seen_txn_id: self.last_seen_txn_id,
+                modified_by: self.modified_by.insert(key, self.last_seen_txn_id),
+                lock_gen: self.lock_gen,
+                prepared_txn: self.prepared_txn,
+                released_txn: self.released_txn,
+                frozen: self.frozen,
+            }
+        }
+    }
+
+    /// Delete under the in-flight transaction's lock (only if locked and the
+    /// store is writable); see KvStore::delete_locked
+    pub open spec fn delete_locked(self, key: Seq<char>) -> Self {
+        if !self.is_locked(key) || self.frozen {
+            self
+        } else {
+            KvStoreSpec {
+                data: self.data.remove(key),
+                locked_keys: self.locked_keys,
+                last_seen_txn_id: self.last_seen_txn_id,
+                modified_by: self.modified_by,
+                lock_gen: self.lock_gen,
+                prepared_txn: self.prepared_txn,
+                released_txn: self.released_txn,
+                frozen: self.frozen,
+            }
         }
     }
 
@@ -123,6 +320,11 @@ impl<V> KvStoreSpec<V> {
             data: self.data,
             locked_keys: self.locked_keys.remove(key),
             last_seen_txn_id: self.last_seen_txn_id,
+            modified_by: self.modified_by,
+            lock_gen: self.lock_gen,
+            prepared_txn: self.prepared_txn,
+            released_txn: self.released_txn,
+            frozen: self.frozen,
         }
     }
 
@@ -138,6 +340,54 @@ impl<V> KvStoreSpec<V> {
             data: self.data.remove(old_key).insert(new_key, value),
             locked_keys: self.locked_keys,
             last_seen_txn_id: self.last_seen_txn_id,
+            modified_by: self.modified_by.insert(new_key, self.last_seen_txn_id),
+            lock_gen: self.lock_gen,
+            prepared_txn: self.prepared_txn,
+            released_txn: self.released_txn,
+            frozen: self.frozen,
+        }
+    }
+
+    /// Rename allowing the destination to pre-exist: move value from old_key to
+    /// new_key, overwriting any value already at new_key.
+    /// No change if old_key doesn't exist.
+    pub open spec fn rename_overwrite(self, old_key: Seq<char>, new_key: Seq<char>) -> Self
+        recommends
+            self.is_locked(old_key),
+            self.is_locked(new_key),
+    {
+        if self.contains_key(old_key) {
+            let value = self.data[old_key];
+            KvStoreSpec {
+                data: self.data.remove(old_key).insert(new_key, value),
+                locked_keys: self.locked_keys,
+                last_seen_txn_id: self.last_seen_txn_id,
+                modified_by: self.modified_by.insert(new_key, self.last_seen_txn_id),
+                lock_gen: self.lock_gen,
+                prepared_txn: self.prepared_txn,
+                released_txn: self.released_txn,
+                frozen: self.frozen,
+            }
+        } else {
+            self
+        }
+    }
+
+    /// Clear all data (only if no key is locked)
+    pub open spec fn clear(self) -> Self {
+        if self.any_locked() {
+            self
+        } else {
+            KvStoreSpec {
+                data: Map::empty(),
+                locked_keys: self.locked_keys,
+                last_seen_txn_id: self.last_seen_txn_id,
+                modified_by: self.modified_by,
+                lock_gen: self.lock_gen,
+                prepared_txn: self.prepared_txn,
+                released_txn: self.released_txn,
+                frozen: self.frozen,
+            }
         }
     }
 
@@ -193,6 +443,64 @@ impl<V> KvStoreSpec<V> {
     {
     }
 
+    /// Locks and txn id bookkeeping never change write provenance
+    pub proof fn lemma_locks_preserve_modified_by(self, key: Seq<char>, txn_id: nat)
+        ensures
+            self.lock(key).modified_by == self.modified_by,
+            self.unlock(key).modified_by == self.modified_by,
+            self.update_txn_id(txn_id).modified_by == self.modified_by,
+    {
+    }
+
+    /// Writes and locks are orthogonal (1/2): put, delete and both renames
+    /// leave the lock table and lock generations exactly as they were
+    pub proof fn lemma_writes_preserve_locks(
+        self,
+        key: Seq<char>,
+        value: V,
+        old_key: Seq<char>,
+        new_key: Seq<char>,
+    )
+        ensures
+            self.put(key, value).locked_keys == self.locked_keys,
+            self.delete(key).locked_keys == self.locked_keys,
+            self.rename(old_key, new_key).locked_keys == self.locked_keys,
+            self.rename_overwrite(old_key, new_key).locked_keys == self.locked_keys,
+            self.put(key, value).lock_gen == self.lock_gen,
+            self.delete(key).lock_gen == self.lock_gen,
+            self.rename(old_key, new_key).lock_gen == self.lock_gen,
+            self.rename_overwrite(old_key, new_key).lock_gen == self.lock_gen,
+    {
+    }
+
+    /// Writes and locks are orthogonal (2/2): lock, lock_if_value and unlock
+    /// leave the data untouched
+    pub proof fn lemma_locks_preserve_data(self, key: Seq<char>, expected: Option<V>)
+        ensures
+            self.lock(key).data == self.data,
+            self.lock_if_value(key, expected).data == self.data,
+            self.unlock(key).data == self.data,
+    {
+        self.lemma_lock_preserves_data(key);
+        self.lemma_unlock_preserves_data(key);
+    }
+
+    /// Lock generations never decrease, so a client that saw generation g and
+    /// later sees g again knows the key was not unlocked and re-locked meanwhile
+    pub proof fn lemma_lock_generation_monotone(self, key: Seq<char>, k: Seq<char>, value: V, txn_id: nat)
+        ensures
+            self.lock_generation(k) <= self.lock(key).lock_generation(k),
+            self.lock_generation(k) <= self.unlock(key).lock_generation(k),
+            self.lock_generation(k) <= self.put(key, value).lock_generation(k),
+            self.lock_generation(k) <= self.delete(key).lock_generation(k),
+            self.lock_generation(k) <= self.update_txn_id(txn_id).lock_generation(k),
+            self.lock_generation(k) <= self.clear().lock_generation(k),
+            // Re-locking after an unlock always moves the generation on
+            self.is_locked(key) ==>
+                self.unlock(key).lock(key).lock_generation(key) == self.lock_generation(key) + 1,
+    {
+    }
+
     /// Update txn_id preserves data and locks
     pub proof fn lemma_update_txn_id_preserves_state(self, txn_id: nat)
         ensures
@@ -220,6 +528,16 @@ impl<V> KvStoreSpec<V> {
     {
     }
 
+    /// Repeated updates take the running maximum, regardless of arrival order
+    pub proof fn lemma_update_txn_id_twice(self, a: nat, b: nat)
+        ensures
+            self.update_txn_id(a).update_txn_id(b).last_seen_txn_id
+                == vstd::math::max3(self.last_seen_txn_id as int, a as int, b as int),
+            self.update_txn_id(a).update_txn_id(b).data == self.data,
+            self.update_txn_id(a).update_txn_id(b).locked_keys == self.locked_keys,
+    {
+    }
+
     /// Rejecting stale messages is safe: txn_id tracking prevents stale interference
     pub proof fn lemma_stale_rejection_safety(self, stale_txn_id: nat, current_txn_id: nat)
         requires
@@ -248,6 +566,48 @@ impl<V> KvStoreSpec<V> {
         assert(new_store.data[new_key] == value);
         assert(!new_store.data.contains_key(old_key));
     }
+
+    /// Rename-overwrite shrinks the key set by one if the destination existed,
+    /// otherwise the key set size is unchanged
+    pub proof fn lemma_rename_overwrite_dom_len(self, old_key: Seq<char>, new_key: Seq<char>)
+        requires
+            self.data.dom().finite(),
+            self.contains_key(old_key),
+            old_key != new_key,
+        ensures
+            self.rename_overwrite(old_key, new_key).data.dom().finite(),
+            self.contains_key(new_key) ==>
+                self.rename_overwrite(old_key, new_key).data.dom().len() == self.data.dom().len() - 1,
+            !self.contains_key(new_key) ==>
+                self.rename_overwrite(old_key, new_key).data.dom().len() == self.data.dom().len(),
+    {
+        let removed = self.data.remove(old_key);
+        assert(removed.dom() =~= self.data.dom().remove(old_key));
+        assert(removed.dom().len() == self.data.dom().len() - 1);
+        assert(removed.insert(new_key, self.data[old_key]).dom() =~= removed.dom().insert(new_key));
+        assert(removed.dom().contains(new_key) == self.contains_key(new_key));
+    }
+
+    /// Clear with any key locked is a no-op; otherwise it empties the data and
+    /// leaves no locks behind
+    pub proof fn lemma_clear_requires_no_locks(self, key: Seq<char>)
+        ensures
+            self.is_locked(key) ==> self.clear() == self,
+            !self.any_locked() ==> self.clear().data == Map::<Seq<char>, V>::empty(),
+            !self.any_locked() ==> !self.clear().any_locked(),
+            self.clear().locked_keys == self.locked_keys,
+            self.clear().last_seen_txn_id == self.last_seen_txn_id,
+    {
+    }
+
+    /// Rename-overwrite with an absent source is a no-op
+    pub proof fn lemma_rename_overwrite_absent_noop(self, old_key: Seq<char>, new_key: Seq<char>)
+        requires
+            !self.contains_key(old_key),
+        ensures
+            self.rename_overwrite(old_key, new_key) == self,
+    {
+    }
 }
 
 // ============================================================
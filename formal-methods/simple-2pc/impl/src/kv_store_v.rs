@@ -1,12 +1,21 @@
 // kv_store_v.rs - Verified executable implementation of KV store
 //
 // This file contains:
-// - KvStore: executable struct using StringHashMap
+// - KvStore<K, V>: executable struct generic over its key and value types;
+//   the protocol uses the KvStore<String, u64> specialization, backed by
+//   StringHashMap (or AssocMap without the `hashmap` feature)
+// - StoreKey/KeyedMap/KeyMaps: the key types a KvStore accepts and the
+//   verified maps backing them (u64 keys need the `hashmap` feature)
 // - View implementation connecting exec to spec
 // - Verified exec functions with postconditions
 
 use vstd::prelude::*;
-use vstd::hash_map::StringHashMap;
+#[cfg(feature = "hashmap")]
+use vstd::hash_map::StringHashMap as KeyMap;
+#[cfg(not(feature = "hashmap"))]
+use crate::assoc_map_v::AssocMap as KeyMap;
+#[cfg(feature = "hashmap")]
+use vstd::hash_map::HashMapWithView;
 use vstd::string::*;
 
 use crate::kv_store_s::*;
@@ -17,14 +26,373 @@ verus! {
 // EXEC LAYER - Executable implementation
 // ============================================================
 
+/// Outcome of `KvStore::try_rename`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RenameResult {
+    /// The value was moved; carries the moved value
+    Done(u64),
+    /// Both keys were locked but the source key does not exist
+    SourceAbsent,
+    /// One of the keys is not locked; nothing was changed
+    NotLocked,
+    /// The store is frozen (see `KvStore::freeze`); nothing was changed
+    Frozen,
+}
+
+/// Lock and presence state of one key, from `KvStore::lock_status`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum KeyStatus {
+    /// The key is not locked (it may or may not exist)
+    Unlocked,
+    /// The key is locked and exists
+    LockedAndPresent,
+    /// The key is locked but does not exist (e.g. a rename destination)
+    LockedAndAbsent,
+}
+
+/// What a store would answer to a LockReq, from `KvStore::prepare_vote`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Vote {
+    /// The store can lock both keys and vote yes
+    Yes,
+    /// The destination key already exists (the rename already happened)
+    NoAlreadyRenamed,
+    /// A key is locked although the store is not prepared for the requesting
+    /// txn (e.g. a lease, or another txn's locks), so someone else holds it
+    NoLockedByOther,
+    /// The store is frozen (see `KvStore::freeze`), so it cannot take the locks
+    NoFrozen,
+}
+
+/// Record of what `KvStore::rename_checked` did, for callers that log transitions
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct RenameProof {
+    /// The value moved from the old key, if any
+    pub moved: Option<u64>,
+    /// Whether the old key existed before the rename
+    pub old_was_present: bool,
+    /// Whether the new key exists after the rename
+    pub new_is_present: bool,
+}
+
+/// Presence, value and lock state of one key read together, from `KvStore::observe`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct KeyObservation {
+    /// Whether the key exists
+    pub present: bool,
+    /// The key's value, if it exists
+    pub value: Option<u64>,
+    /// Whether the key is locked
+    pub locked: bool,
+    /// The txn holding the lock, if the key is locked
+    pub lock_owner: Option<u64>,
+}
+
+/// One change in a store changelog (see `KvStore::diff` and `KvStore::apply_delta`)
+#[derive(Debug)]
+pub enum KvDelta {
+    /// Set the key to the value (insert or update)
+    Put(String, u64),
+    /// Remove the key
+    Remove(String),
+}
+
+impl KvDelta {
+    /// The key this change touches
+    pub open spec fn spec_key(&self) -> Seq<char> {
+        match self {
+            KvDelta::Put(k, _) => k@,
+            KvDelta::Remove(k) => k@,
+        }
+    }
+}
+
+/// Number of entries `KvStore::recent_ops` keeps before evicting the oldest
+pub const OP_LOG_CAP: usize = 32;
+
+/// One successful mutation in a store's audit trail (see `KvStore::recent_ops`),
+/// with the txn it is attributed to
+#[derive(Debug)]
+pub enum KvOp<K = String> {
+    Put { key: K, txn_id: u64 },
+    Delete { key: K, txn_id: u64 },
+    Rename { old: K, new: K, txn_id: u64 },
+    Lock { key: K, txn_id: u64 },
+    Unlock { key: K, txn_id: u64 },
+}
+
+/// Key type of a `KvStore`: an owned key whose view is the spec key, looked up
+/// through a borrowed form (`str` for `String`) the way the backing maps are
+pub trait StoreKey: View + Sized {
+    /// Borrowed form of the key taken by lookups
+    type Ref: View<V = <Self as View>::V> + ?Sized;
+
+    /// Owned copy of a borrowed key
+    fn to_key(key: &Self::Ref) -> (result: Self)
+        ensures
+            result@ == key@;
+
+    /// Is this the key `key`?
+    fn matches(&self, key: &Self::Ref) -> (result: bool)
+        ensures
+            result == (self@ == key@);
+}
+
+/// Verified map from `K` keys to `T` values backing a `KvStore`, with the
+/// same contract as vstd's hash maps
+pub trait KeyedMap<K: StoreKey, T>: View<V = Map<<K as View>::V, T>> + Sized {
+    fn new() -> (result: Self)
+        ensures
+            result@ == Map::<<K as View>::V, T>::empty();
+
+    fn get<'a>(&'a self, key: &K::Ref) -> (result: Option<&'a T>)
+        ensures
+            match result {
+                Some(v) => self@.contains_key(key@) && *v == self@[key@],
+                None => !self@.contains_key(key@),
+            };
+
+    fn contains_key(&self, key: &K::Ref) -> (result: bool)
+        ensures
+            result == self@.contains_key(key@);
+
+    fn insert(&mut self, key: K, value: T)
+        ensures
+            self@ == old(self)@.insert(key@, value);
+
+    fn remove(&mut self, key: &K::Ref)
+        ensures
+            self@ == old(self)@.remove(key@);
+}
+
+/// The maps a `KvStore<K, V>` keeps per key: the data map and the maps from
+/// keys to txn ids, lease expiries and lock generations
+pub trait KeyMaps<V>: StoreKey {
+    type DataMap: KeyedMap<Self, V>;
+    type TxnMap: KeyedMap<Self, u64>;
+}
+
+impl StoreKey for String {
+    type Ref = str;
+
+    fn to_key(key: &str) -> (result: String) {
+        key.to_owned()
+    }
+
+    fn matches(&self, key: &str) -> (result: bool) {
+        self.as_str() == key
+    }
+}
+
+impl<T> KeyedMap<String, T> for KeyMap<T> {
+    fn new() -> (result: Self) {
+        KeyMap::new()
+    }
+
+    fn get<'a>(&'a self, key: &str) -> (result: Option<&'a T>) {
+        KeyMap::get(self, key)
+    }
+
+    fn contains_key(&self, key: &str) -> (result: bool) {
+        KeyMap::contains_key(self, key)
+    }
+
+    fn insert(&mut self, key: String, value: T) {
+        KeyMap::insert(self, key, value)
+    }
+
+    fn remove(&mut self, key: &str) {
+        KeyMap::remove(self, key)
+    }
+}
+
+impl<V> KeyMaps<V> for String {
+    type DataMap = KeyMap<V>;
+    type TxnMap = KeyMap<u64>;
+}
+
+/// u64 keys (e.g. store ids in a routing table), backed by vstd's HashMapWithView
+#[cfg(feature = "hashmap")]
+impl StoreKey for u64 {
+    type Ref = u64;
+
+    fn to_key(key: &u64) -> (result: u64) {
+        *key
+    }
+
+    fn matches(&self, key: &u64) -> (result: bool) {
+        *self == *key
+    }
+}
+
+#[cfg(feature = "hashmap")]
+impl<T> KeyedMap<u64, T> for HashMapWithView<u64, T> {
+    fn new() -> (result: Self) {
+        broadcast use vstd::std_specs::hash::group_hash_axioms;
+        HashMapWithView::new()
+    }
+
+    fn get<'a>(&'a self, key: &u64) -> (result: Option<&'a T>) {
+        HashMapWithView::get(self, key)
+    }
+
+    fn contains_key(&self, key: &u64) -> (result: bool) {
+        HashMapWithView::contains_key(self, key)
+    }
+
+    fn insert(&mut self, key: u64, value: T) {
+        HashMapWithView::insert(self, key, value)
+    }
+
+    fn remove(&mut self, key: &u64) {
+        HashMapWithView::remove(self, key)
+    }
+}
+
+#[cfg(feature = "hashmap")]
+impl<V> KeyMaps<V> for u64 {
+    type DataMap = HashMapWithView<u64, V>;
+    type TxnMap = HashMapWithView<u64, u64>;
+}
+
 /// Executable key-value store using HashMap
-pub struct KvStore {
+pub struct KvStore<K: KeyMaps<V> = String, V = u64> {
     /// Key-value data storage
-    pub data: StringHashMap<u64>,
-    /// Locked keys (key -> true means locked)
-    pub locked: StringHashMap<bool>,
-    /// Last seen transaction ID - used to reject stale messages
+    pub data: K::DataMap,
+    /// Locked keys, each tagged with its owner: the txn id last seen when it
+    /// was locked (re-locking a held key hands it to the current txn)
+    pub locked: K::TxnMap,
+    /// Last seen transaction ID - used to reject stale messages.
+    /// A single high-water mark rather than a per-txn history, so staleness
+    /// tracking stays constant-size and never needs compacting.
     pub last_seen_txn_id: u64,
+    /// Transaction this store voted yes for and is awaiting a decision on.
+    /// Durable: survives a store crash, unlike the lock table.
+    pub prepared_txn: Option<u64>,
+    /// Transaction whose locks this store released on an UnlockReq. Durable,
+    /// so a LockReq for it that arrives late is refused even after a crash.
+    pub released_txn: Option<u64>,
+    /// Transaction id that last wrote each key (set by put and rename).
+    /// Durable alongside the data it describes.
+    pub modified_by: K::TxnMap,
+    /// Lease expiry for locks taken with `lock_with_lease` (key -> lease_until).
+    /// Volatile, like the lock table.
+    pub leases: K::TxnMap,
+    /// Keys that have held a lease, scanned by `tick` (may contain keys whose
+    /// lease has since been dropped)
+    pub leased_keys: Vec<K>,
+    /// Keys locked since the last crash, without duplicates, scanned by
+    /// `locked_keys` (may contain keys that have since been unlocked)
+    pub lock_history: Vec<K>,
+    /// Keys that have held data, without duplicates, scanned by `count_value`
+    /// (may contain keys since deleted or renamed away). Durable alongside the data.
+    pub key_index: Vec<K>,
+    /// Lock generation of each key, bumped whenever an unlocked key is locked
+    /// (saturates at u64::MAX). Kept across crashes so it never goes backwards.
+    pub lock_gen: K::TxnMap,
+    /// Keys that have a lock generation, without duplicates, scanned by
+    /// `snapshot`. Durable alongside `lock_gen`.
+    pub lock_gen_keys: Vec<K>,
+    /// Read-only maintenance mode (e.g. during a backup): while set, every
+    /// mutator (put, delete, rename, lock, unlock, the batch and lease
+    /// operations) changes nothing and reports failure
+    pub frozen: bool,
+    /// Store-wide change counter, bumped by every successful mutation, batch,
+    /// lease and rollback operations included (saturates at u64::MAX). Not
+    /// part of the view: clients compare it across reads to detect a
+    /// concurrent modification
+    pub epoch: u64,
+    /// The last `OP_LOG_CAP` successful put, delete, rename, lock and unlock
+    /// operations, oldest first. Diagnostics only; not part of the view
+    pub op_log: Vec<KvOp<K>>,
+}
+
+/// Copy of a key list (element views preserved)
+fn clone_keys(keys: &[String]) -> (result: Vec<String>)
+    ensures
+        result@.len() == keys@.len(),
+        forall|i: int| 0 <= i < keys@.len() ==> #[trigger] result@[i]@ == keys@[i]@,
+{
+    let mut result: Vec<String> = Vec::new();
+    let mut i: usize = 0;
+    while i < keys.len()
+        invariant
+            0 <= i <= keys.len(),
+            result@.len() == i,
+            forall|j: int| 0 <= j < i ==> #[trigger] result@[j]@ == keys@[j]@,
+        decreases
+            keys.len() - i,
+    {
+        result.push(keys[i].clone());
+        i = i + 1;
+    }
+    result
+}
+
+/// Does `key` start with `prefix`? (O(n^2): `get_char` decodes from the start)
+fn has_prefix(key: &str, prefix: &str) -> (result: bool)
+    ensures
+        result == prefix@.is_prefix_of(key@),
+{
+    let key_len = key.unicode_len();
+    let prefix_len = prefix.unicode_len();
+    if prefix_len > key_len {
+        return false;
+    }
+    let mut i: usize = 0;
+    while i < prefix_len
+        invariant
+            0 <= i <= prefix_len,
+            prefix_len == prefix@.len(),
+            prefix_len <= key_len == key@.len(),
+            forall|j: int| 0 <= j < i ==> prefix@[j] == key@[j],
+        decreases
+            prefix_len - i,
+    {
+        if prefix.get_char(i) != key.get_char(i) {
+            assert(prefix@[i as int] != key@.subrange(0, prefix@.len() as int)[i as int]);
+            return false;
+        }
+        i = i + 1;
+    }
+    assert(prefix@ =~= key@.subrange(0, prefix@.len() as int));
+    true
+}
+
+/// Copy the entries of `src` for the keys listed in `keys` into `dst`
+/// (listed keys absent from `src` are skipped; other entries of `dst` are kept)
+fn copy_entries<V: Copy>(src: &KeyMap<V>, keys: &[String], dst: &mut KeyMap<V>)
+    ensures
+        forall|k: Seq<char>| #[trigger] dst@.contains_key(k) == (old(dst)@.contains_key(k)
+            || (src@.contains_key(k) && exists|i: int| 0 <= i < keys@.len() && keys@[i]@ == k)),
+        forall|k: Seq<char>| #[trigger] dst@.contains_key(k) ==> dst@[k] ==
+            if src@.contains_key(k) && exists|i: int| 0 <= i < keys@.len() && keys@[i]@ == k {
+                src@[k]
+            } else {
+                old(dst)@[k]
+            },
+{
+    let mut i: usize = 0;
+    while i < keys.len()
+        invariant
+            0 <= i <= keys.len(),
+            forall|k: Seq<char>| #[trigger] dst@.contains_key(k) == (old(dst)@.contains_key(k)
+                || (src@.contains_key(k) && exists|j: int| 0 <= j < i && keys@[j]@ == k)),
+            forall|k: Seq<char>| #[trigger] dst@.contains_key(k) ==> dst@[k] ==
+                if src@.contains_key(k) && exists|j: int| 0 <= j < i && keys@[j]@ == k {
+                    src@[k]
+                } else {
+                    old(dst)@[k]
+                },
+        decreases
+            keys.len() - i,
+    {
+        let key = keys[i].as_str();
+        if let Some(v) = src.get(key) {
+            dst.insert(key.to_owned(), *v);
+        }
+        i = i + 1;
+    }
 }
 
 impl View for KvStore {
@@ -36,24 +404,66 @@ impl View for KvStore {
             data: self.data@,
             locked_keys: Set::new(|k: Seq<char>| self.locked@.contains_key(k)),
             last_seen_txn_id: self.last_seen_txn_id as nat,
+            modified_by: self.modified_by@.map_values(|t: u64| t as nat),
+            lock_gen: self.lock_gen@.map_values(|g: u64| g as nat),
+            frozen: self.frozen,
+            prepared_txn: match self.prepared_txn {
+                Some(t) => Some(t as nat),
+                None => None,
+            },
+            released_txn: match self.released_txn {
+                Some(t) => Some(t as nat),
+                None => None,
+            },
         }
     }
 }
 
-impl KvStore {
+impl<K: KeyMaps<V>, V: Copy> KvStore<K, V> {
     // ============================================================
     // SPEC HELPERS - For use in ensures clauses
     // ============================================================
 
-    pub open spec fn spec_is_locked(&self, key: Seq<char>) -> bool {
+    pub open spec fn spec_is_locked(&self, key: <K as View>::V) -> bool {
         self.locked@.contains_key(key)
     }
 
-    pub open spec fn spec_contains_key(&self, key: Seq<char>) -> bool {
+    /// Owner txn of a locked key (meaningless if the key is unlocked)
+    pub open spec fn spec_lock_owner(&self, key: <K as View>::V) -> u64 {
+        self.locked@[key]
+    }
+
+    pub open spec fn spec_lock_generation(&self, key: <K as View>::V) -> u64 {
+        if self.lock_gen@.contains_key(key) {
+            self.lock_gen@[key]
+        } else {
+            0
+        }
+    }
+
+    /// Epoch after one more successful mutation (saturating)
+    pub open spec fn spec_next_epoch(&self) -> u64 {
+        if self.epoch < u64::MAX { (self.epoch + 1) as u64 } else { self.epoch }
+    }
+
+    /// Every held lock belongs to a txn the store has seen: its owner is no
+    /// newer than `last_seen_txn_id`, so the store never forgets the txn that
+    /// holds a lock and a stale unlock cannot slip through
+    pub open spec fn spec_locks_backed(&self) -> bool {
+        forall|k: <K as View>::V| #[trigger] self.spec_is_locked(k) ==>
+            self.spec_lock_owner(k) <= self.last_seen_txn_id
+    }
+
+    /// No key's lock generation is lower than in `old`
+    pub open spec fn spec_lock_gen_monotone(&self, old: &Self) -> bool {
+        forall|k: <K as View>::V| #[trigger] self.spec_lock_generation(k) >= old.spec_lock_generation(k)
+    }
+
+    pub open spec fn spec_contains_key(&self, key: <K as View>::V) -> bool {
         self.data@.contains_key(key)
     }
 
-    pub open spec fn spec_get(&self, key: Seq<char>) -> u64
+    pub open spec fn spec_get(&self, key: <K as View>::V) -> V
         recommends self.spec_contains_key(key)
     {
         self.data@[key]
@@ -67,26 +477,115 @@ impl KvStore {
         txn_id < self.last_seen_txn_id as nat
     }
 
+    pub open spec fn spec_any_locked(&self) -> bool {
+        exists|k: <K as View>::V| self.spec_is_locked(k)
+    }
+
+    pub open spec fn spec_value_matches(&self, key: <K as View>::V, expected: Option<V>) -> bool {
+        match expected {
+            Some(v) => self.spec_contains_key(key) && self.spec_get(key) == v,
+            None => !self.spec_contains_key(key),
+        }
+    }
+
+    pub open spec fn spec_last_writer(&self, key: <K as View>::V) -> Option<u64> {
+        if self.modified_by@.contains_key(key) {
+            Some(self.modified_by@[key])
+        } else {
+            None
+        }
+    }
+
+    pub open spec fn spec_is_prepared(&self, txn_id: u64) -> bool {
+        self.prepared_txn == Some(txn_id)
+    }
+
+    /// `key_index` lists every key that has data, exactly once
+    pub open spec fn spec_key_index_wf(&self) -> bool {
+        &&& forall|k: <K as View>::V| #![auto] self.spec_contains_key(k) ==>
+                exists|i: int| 0 <= i < self.key_index@.len() && self.key_index@[i]@ == k
+        &&& forall|i: int, j: int| 0 <= i < j < self.key_index@.len() ==>
+                self.key_index@[i]@ != self.key_index@[j]@
+    }
+
+    /// Every entry of `modified_by`, `leases` and `lock_gen` is listed in the
+    /// key list that indexes it (`key_index`, `leased_keys` and `lock_gen_keys`),
+    /// so `snapshot` copies each map in full
+    pub open spec fn spec_maps_indexed(&self) -> bool {
+        &&& forall|k: <K as View>::V| #![auto] self.modified_by@.contains_key(k) ==>
+                exists|i: int| 0 <= i < self.key_index@.len() && self.key_index@[i]@ == k
+        &&& forall|k: <K as View>::V| #![auto] self.leases@.contains_key(k) ==>
+                exists|i: int| 0 <= i < self.leased_keys@.len() && self.leased_keys@[i]@ == k
+        &&& forall|k: <K as View>::V| #![auto] self.lock_gen@.contains_key(k) ==>
+                exists|i: int| 0 <= i < self.lock_gen_keys@.len() && self.lock_gen_keys@[i]@ == k
+    }
+
+    /// Every locked key appears in `lock_history`
+    pub open spec fn spec_lock_history_complete(&self) -> bool {
+        forall|k: <K as View>::V| #![auto] self.spec_is_locked(k) ==>
+            exists|i: int| 0 <= i < self.lock_history@.len() && self.lock_history@[i]@ == k
+    }
+
+    /// Is `key` among the first `n` indexed keys?
+    pub open spec fn spec_indexed_before(&self, key: <K as View>::V, n: int) -> bool {
+        exists|i: int| 0 <= i < n && self.key_index@[i]@ == key
+    }
+
+    /// `key_index` after recording `key` into `old`'s: the old entries stay in
+    /// place, and `key` is appended once unless it was already listed
+    pub open spec fn spec_key_recorded(&self, old: &Self, key: <K as View>::V) -> bool {
+        &&& old.key_index@.len() <= self.key_index@.len() <= old.key_index@.len() + 1
+        &&& forall|i: int| 0 <= i < old.key_index@.len() ==>
+                #[trigger] self.key_index@[i] == old.key_index@[i]
+        &&& forall|i: int| old.key_index@.len() <= i < self.key_index@.len() ==>
+                #[trigger] self.key_index@[i]@ == key
+        &&& old.spec_indexed_before(key, old.key_index@.len() as int)
+                ==> self.key_index@.len() == old.key_index@.len()
+    }
+
     // ============================================================
     // EXEC FUNCTIONS - Verified implementations
     // ============================================================
 
-    /// Create a new empty KV store
-    pub fn new() -> (result: Self)
+    /// Create an empty store over any key type (`KvStore::new` builds the
+    /// protocol's String-keyed store)
+    pub fn empty() -> (result: Self)
         ensures
-            result@.data == Map::<Seq<char>, u64>::empty(),
-            result@.locked_keys == Set::<Seq<char>>::empty(),
-            result@.last_seen_txn_id == 0,
+            result.data@ == Map::<<K as View>::V, V>::empty(),
+            result.locked@ == Map::<<K as View>::V, u64>::empty(),
+            result.last_seen_txn_id == 0,
+            result.prepared_txn.is_none(),
+            result.released_txn.is_none(),
+            result.modified_by@ == Map::<<K as View>::V, u64>::empty(),
+            result.lock_gen@ == Map::<<K as View>::V, u64>::empty(),
+            result.spec_lock_history_complete(),
+            result.spec_key_index_wf(),
+            result.spec_maps_indexed(),
+            !result.frozen,
+            result.epoch == 0,
+            result.spec_locks_backed(),
     {
         KvStore {
-            data: StringHashMap::new(),
-            locked: StringHashMap::new(),
+            data: K::DataMap::new(),
+            locked: K::TxnMap::new(),
             last_seen_txn_id: 0,
+            prepared_txn: None,
+            released_txn: None,
+            modified_by: K::TxnMap::new(),
+            leases: K::TxnMap::new(),
+            leased_keys: Vec::new(),
+            lock_history: Vec::new(),
+            key_index: Vec::new(),
+            lock_gen: K::TxnMap::new(),
+            lock_gen_keys: Vec::new(),
+            frozen: false,
+            epoch: 0,
+            op_log: Vec::new(),
         }
     }
 
     /// Get value for key
-    pub fn get(&self, key: &str) -> (result: Option<u64>)
+    pub fn get(&self, key: &K::Ref) -> (result: Option<V>)
         ensures
             match result {
                 Some(v) => self.spec_contains_key(key@) && v == self.spec_get(key@),
@@ -100,144 +599,2702 @@ impl KvStore {
     }
 
     /// Check if key is locked
-    pub fn is_locked(&self, key: &str) -> (result: bool)
+    pub fn is_locked(&self, key: &K::Ref) -> (result: bool)
         ensures
             result == self.spec_is_locked(key@)
     {
         self.locked.contains_key(key)
     }
 
+    /// Txn that holds the lock on `key`, or None if it is unlocked
+    pub fn lock_owner(&self, key: &K::Ref) -> (result: Option<u64>)
+        ensures
+            result.is_some() == self.spec_is_locked(key@),
+            result.is_some() ==> result.unwrap() == self.spec_lock_owner(key@),
+    {
+        let mut result = None;
+        if let Some(owner) = self.locked.get(key) {
+            result = Some(*owner);
+        }
+        result
+    }
+
     /// Check if key exists
-    pub fn contains_key(&self, key: &str) -> (result: bool)
+    pub fn contains_key(&self, key: &K::Ref) -> (result: bool)
         ensures
             result == self.spec_contains_key(key@)
     {
         self.data.contains_key(key)
     }
 
-    /// Get the last seen transaction ID
-    pub fn get_last_seen_txn_id(&self) -> (result: u64)
+    /// Lock generation of a key: how many times it has been locked (0 if never).
+    /// Read it before doing work and compare afterwards to detect that the key
+    /// was unlocked and re-locked (possibly by another txn) in between
+    pub fn lock_generation(&self, key: &K::Ref) -> (result: u64)
         ensures
-            result as nat == self.spec_last_seen_txn_id()
+            result == self.spec_lock_generation(key@)
     {
-        self.last_seen_txn_id
+        match self.lock_gen.get(key) {
+            Some(g) => *g,
+            None => 0,
+        }
     }
 
-    /// Check if a transaction ID is stale (older than or equal to last seen)
-    pub fn is_stale_txn_id(&self, txn_id: u64) -> (result: bool)
+    /// Current store-wide epoch: snapshot it before a series of reads and
+    /// compare afterwards to detect an intervening modification
+    pub fn epoch(&self) -> (result: u64)
         ensures
-            result == self.spec_is_stale_txn_id(txn_id as nat)
+            result == self.epoch,
     {
-        txn_id < self.last_seen_txn_id
+        self.epoch
     }
 
-    /// Update the last seen transaction ID (only updates if newer)
-    pub fn update_txn_id(&mut self, txn_id: u64)
+    /// Bump the epoch after a successful mutation
+    fn bump_epoch(&mut self)
         ensures
-            // Updates to new txn_id if greater, otherwise unchanged
-            self.last_seen_txn_id == if txn_id > old(self).last_seen_txn_id {
-                txn_id
-            } else {
-                old(self).last_seen_txn_id
-            },
-            // Data unchanged
+            self.epoch == old(self).spec_next_epoch(),
+            self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.prepared_txn == old(self).prepared_txn,
+            self.modified_by@ == old(self).modified_by@,
+            self.leases@ == old(self).leases@,
+            self.leased_keys@ == old(self).leased_keys@,
+            self.lock_history@ == old(self).lock_history@,
+            self.key_index@ == old(self).key_index@,
+            self.lock_gen@ == old(self).lock_gen@,
+            self.frozen == old(self).frozen,
+    {
+        self.epoch = self.epoch.saturating_add(1);
+    }
+
+    /// The most recent successful mutations, oldest first (at most `OP_LOG_CAP`)
+    pub fn recent_ops(&self) -> (result: &[KvOp<K>])
+        ensures
+            result@ == self.op_log@,
+    {
+        self.op_log.as_slice()
+    }
+
+    /// Append `op` to the operation log, evicting the oldest entries when full
+    fn log_op(&mut self, op: KvOp<K>)
+        ensures
+            self.op_log@.len() <= OP_LOG_CAP,
+            self.op_log@.last() == op,
             self.data@ == old(self).data@,
-            // Locks unchanged
             self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.prepared_txn == old(self).prepared_txn,
+            self.modified_by@ == old(self).modified_by@,
+            self.leases@ == old(self).leases@,
+            self.leased_keys@ == old(self).leased_keys@,
+            self.lock_history@ == old(self).lock_history@,
+            self.key_index@ == old(self).key_index@,
+            self.lock_gen@ == old(self).lock_gen@,
+            self.lock_gen_keys@ == old(self).lock_gen_keys@,
+            self.released_txn == old(self).released_txn,
+            self.frozen == old(self).frozen,
+            self.epoch == old(self).epoch,
     {
-        if txn_id > self.last_seen_txn_id {
-            self.last_seen_txn_id = txn_id;
+        while self.op_log.len() >= OP_LOG_CAP
+            invariant
+                self.data@ == old(self).data@,
+                self.locked@ == old(self).locked@,
+                self.last_seen_txn_id == old(self).last_seen_txn_id,
+                self.prepared_txn == old(self).prepared_txn,
+                self.modified_by@ == old(self).modified_by@,
+                self.leases@ == old(self).leases@,
+                self.leased_keys@ == old(self).leased_keys@,
+                self.lock_history@ == old(self).lock_history@,
+                self.key_index@ == old(self).key_index@,
+                self.lock_gen@ == old(self).lock_gen@,
+                self.lock_gen_keys@ == old(self).lock_gen_keys@,
+                self.released_txn == old(self).released_txn,
+                self.frozen == old(self).frozen,
+                self.epoch == old(self).epoch,
+            decreases
+                self.op_log@.len(),
+        {
+            // OP_LOG_CAP > 0, so the log is non-empty here
+            self.op_log.remove(0);
         }
+        self.op_log.push(op);
+    }
+
+    /// The epoch strictly increases with each successful mutation until it
+    /// saturates, and never goes backwards
+    pub proof fn lemma_epoch_monotone(&self)
+        ensures
+            self.spec_next_epoch() >= self.epoch,
+            self.epoch < u64::MAX ==> self.spec_next_epoch() > self.epoch,
+    {
     }
 
-    /// Put value for key (fails if locked)
-    /// Returns true if successful, false if key is locked
-    pub fn put(&mut self, key: &str, value: u64) -> (success: bool)
+    /// Put value for key (fails if locked or the store is frozen)
+    /// Returns true if successful, false if key is locked or the store frozen
+    pub fn put(&mut self, key: &K::Ref, value: V) -> (success: bool)
         ensures
-            success == !old(self).spec_is_locked(key@),
+            success == (!old(self).frozen && !old(self).spec_is_locked(key@)),
+            // Frozen: nothing changes
+            old(self).frozen ==> *self == *old(self),
             // If locked, state unchanged
             old(self).spec_is_locked(key@) ==> (
                 self.data@ == old(self).data@
                 && self.locked@ == old(self).locked@
             ),
             // If not locked, key is inserted
-            !old(self).spec_is_locked(key@) ==> (
+            !old(self).frozen && !old(self).spec_is_locked(key@) ==> (
                 self.data@ == old(self).data@.insert(key@, value)
                 && self.locked@ == old(self).locked@
             ),
+            // Provenance: a successful put is attributed to the last seen txn
+            success ==> self.modified_by@ == old(self).modified_by@.insert(key@, old(self).last_seen_txn_id),
+            !success ==> self.modified_by@ == old(self).modified_by@,
             // txn_id unchanged
             self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Epoch advances on success only
+            success ==> self.epoch == old(self).spec_next_epoch(),
+            !success ==> self.epoch == old(self).epoch,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        let txn_id = self.last_seen_txn_id;
+        self.put_with_txn(key, value, txn_id)
+    }
+
+    /// Put a value attributed to `txn_id`: the value and its `modified_by`
+    /// stamp are written together, so no caller sees one without the other.
+    /// Fails (changing nothing) if the key is locked or the store is frozen
+    pub fn put_with_txn(&mut self, key: &K::Ref, value: V, txn_id: u64) -> (success: bool)
+        ensures
+            success == (!old(self).frozen && !old(self).spec_is_locked(key@)),
+            // Frozen: nothing changes
+            old(self).frozen ==> *self == *old(self),
+            success ==> self.data@ == old(self).data@.insert(key@, value),
+            success ==> self.modified_by@ == old(self).modified_by@.insert(key@, txn_id),
+            success ==> self.spec_last_writer(key@) == Some(txn_id),
+            !success ==> self.data@ == old(self).data@,
+            !success ==> self.modified_by@ == old(self).modified_by@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            success ==> self.epoch == old(self).spec_next_epoch(),
+            !success ==> self.epoch == old(self).epoch,
+            success ==> self.op_log@.len() <= OP_LOG_CAP,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
     {
-        if self.locked.contains_key(key) {
+        if self.frozen || self.locked.contains_key(key) {
             false
         } else {
-            self.data.insert(key.to_owned(), value);
+            self.data.insert(K::to_key(key), value);
+            self.record_key(key);
+            self.modified_by.insert(K::to_key(key), txn_id);
+            self.bump_epoch();
+            self.log_op(KvOp::Put { key: K::to_key(key), txn_id });
             true
         }
     }
 
-    /// Delete key (fails if locked)
-    /// Returns true if successful, false if key is locked
-    pub fn delete(&mut self, key: &str) -> (success: bool)
+    /// Delete key (fails if locked or the store is frozen)
+    /// Returns true if successful, false if key is locked or the store frozen
+    pub fn delete(&mut self, key: &K::Ref) -> (success: bool)
         ensures
-            success == !old(self).spec_is_locked(key@),
+            success == (!old(self).frozen && !old(self).spec_is_locked(key@)),
+            // Frozen: nothing changes
+            old(self).frozen ==> *self == *old(self),
             // If locked, state unchanged
             old(self).spec_is_locked(key@) ==> (
                 self.data@ == old(self).data@
                 && self.locked@ == old(self).locked@
             ),
             // If not locked, key is removed
-            !old(self).spec_is_locked(key@) ==> (
+            !old(self).frozen && !old(self).spec_is_locked(key@) ==> (
                 self.data@ == old(self).data@.remove(key@)
                 && self.locked@ == old(self).locked@
             ),
             // txn_id unchanged
             self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Write provenance unchanged
+            self.modified_by@ == old(self).modified_by@,
+            // Epoch advances on success only
+            success ==> self.epoch == old(self).spec_next_epoch(),
+            !success ==> self.epoch == old(self).epoch,
+            success ==> self.op_log@.len() <= OP_LOG_CAP,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
     {
-        if self.locked.contains_key(key) {
+        if self.frozen || self.locked.contains_key(key) {
             false
         } else {
             self.data.remove(key);
+            self.bump_epoch();
+            self.log_op(KvOp::Delete { key: K::to_key(key), txn_id: self.last_seen_txn_id });
             true
         }
     }
 
-    /// Lock a key (idempotent)
-    pub fn lock(&mut self, key: &str)
+    /// Lock a key (idempotent). The lock is held without a lease, so any lease
+    /// on the key is dropped. Returns false, changing nothing, if the store
+    /// is frozen
+    pub fn lock(&mut self, key: &K::Ref) -> (success: bool)
         ensures
-            // Key is now locked
-            self.spec_is_locked(key@),
+            success == !old(self).frozen,
+            // Frozen: nothing changes
+            old(self).frozen ==> *self == *old(self),
+            // Key is now locked, owned by the last seen txn
+            !old(self).frozen ==> self.spec_is_locked(key@),
+            !old(self).frozen ==> self.spec_lock_owner(key@) == self.last_seen_txn_id,
+            forall|k: <K as View>::V| k != key@ && old(self).spec_is_locked(k) ==>
+                self.spec_lock_owner(k) == old(self).spec_lock_owner(k),
             // Data unchanged
             self.data@ == old(self).data@,
             // Other locks unchanged
-            forall|k: Seq<char>| k != key@ ==>
+            forall|k: <K as View>::V| k != key@ ==>
                 (self.spec_is_locked(k) == old(self).spec_is_locked(k)),
             // txn_id unchanged
             self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Write provenance unchanged
+            self.modified_by@ == old(self).modified_by@,
+            // Taking a free key bumps its generation; nothing goes backwards
+            !old(self).frozen && !old(self).spec_is_locked(key@)
+                && old(self).spec_lock_generation(key@) < u64::MAX ==>
+                self.spec_lock_generation(key@) == old(self).spec_lock_generation(key@) + 1,
+            old(self).spec_is_locked(key@) ==> self.lock_gen@ == old(self).lock_gen@,
+            self.spec_lock_gen_monotone(old(self)),
+            // Any lease on the key is dropped
+            !old(self).frozen ==> self.leases@ == old(self).leases@.remove(key@),
+            // Epoch advances unless frozen
+            !old(self).frozen ==> self.epoch == old(self).spec_next_epoch(),
+            !old(self).frozen ==> self.op_log@.len() <= OP_LOG_CAP,
+            old(self).spec_locks_backed() ==> self.spec_locks_backed(),
+            old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen {
+            return false;
+        }
+        if !self.locked.contains_key(key) {
+            self.bump_lock_gen(key);
+        }
+        self.locked.insert(K::to_key(key), self.last_seen_txn_id);
+        self.record_lock(key);
+        self.leases.remove(key);
+        self.bump_epoch();
+        self.log_op(KvOp::Lock { key: K::to_key(key), txn_id: self.last_seen_txn_id });
+        true
+    }
+
+    /// Increment `key`'s lock generation (saturating)
+    fn bump_lock_gen(&mut self, key: &K::Ref)
+        ensures
+            old(self).spec_lock_generation(key@) < u64::MAX ==>
+                self.spec_lock_generation(key@) == old(self).spec_lock_generation(key@) + 1,
+            self.spec_lock_gen_monotone(old(self)),
+            self.locked@ == old(self).locked@,
+            self.data@ == old(self).data@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.modified_by@ == old(self).modified_by@,
+            self.leases@ == old(self).leases@,
+            self.lock_history@ == old(self).lock_history@,
+            self.key_index@ == old(self).key_index@,
+            self.leased_keys@ == old(self).leased_keys@,
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+            self.epoch == old(self).epoch,
+    {
+        let gen = self.lock_generation(key);
+        self.lock_gen.insert(K::to_key(key), gen.saturating_add(1));
+        self.record_lock_gen_key(key);
+    }
+
+    /// Append `key` to `lock_gen_keys` unless it is already listed
+    fn record_lock_gen_key(&mut self, key: &K::Ref)
+        ensures
+            exists|i: int| 0 <= i < self.lock_gen_keys@.len() && self.lock_gen_keys@[i]@ == key@,
+            forall|i: int| 0 <= i < old(self).lock_gen_keys@.len() ==>
+                self.lock_gen_keys@[i] == old(self).lock_gen_keys@[i],
+            old(self).lock_gen_keys@.len() <= self.lock_gen_keys@.len(),
+            self.locked@ == old(self).locked@,
+            self.data@ == old(self).data@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.modified_by@ == old(self).modified_by@,
+            self.leases@ == old(self).leases@,
+            self.leased_keys@ == old(self).leased_keys@,
+            self.lock_history@ == old(self).lock_history@,
+            self.key_index@ == old(self).key_index@,
+            self.lock_gen@ == old(self).lock_gen@,
+            self.frozen == old(self).frozen,
+            self.epoch == old(self).epoch,
+    {
+        let mut i: usize = 0;
+        while i < self.lock_gen_keys.len()
+            invariant
+                0 <= i <= self.lock_gen_keys.len(),
+                *self == *old(self),
+                forall|j: int| 0 <= j < i ==> self.lock_gen_keys@[j]@ != key@,
+            decreases
+                self.lock_gen_keys.len() - i,
+        {
+            if self.lock_gen_keys[i].matches(key) {
+                return;
+            }
+            i = i + 1;
+        }
+        self.lock_gen_keys.push(K::to_key(key));
+        proof {
+            assert(self.lock_gen_keys@[self.lock_gen_keys@.len() - 1]@ == key@);
+        }
+    }
+
+    /// Append `key` to `lock_history` unless it is already listed
+    fn record_lock(&mut self, key: &K::Ref)
+        ensures
+            exists|i: int| 0 <= i < self.lock_history@.len() && self.lock_history@[i]@ == key@,
+            forall|i: int| 0 <= i < old(self).lock_history@.len() ==>
+                self.lock_history@[i] == old(self).lock_history@[i],
+            old(self).lock_history@.len() <= self.lock_history@.len(),
+            self.locked@ == old(self).locked@,
+            self.data@ == old(self).data@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.modified_by@ == old(self).modified_by@,
+            self.leases@ == old(self).leases@,
+            self.key_index@ == old(self).key_index@,
+            self.lock_gen@ == old(self).lock_gen@,
+            self.frozen == old(self).frozen,
+            self.epoch == old(self).epoch,
     {
-        self.locked.insert(key.to_owned(), true);
+        let mut i: usize = 0;
+        while i < self.lock_history.len()
+            invariant
+                0 <= i <= self.lock_history.len(),
+                *self == *old(self),
+                forall|j: int| 0 <= j < i ==> self.lock_history@[j]@ != key@,
+            decreases
+                self.lock_history.len() - i,
+        {
+            if self.lock_history[i].matches(key) {
+                return;
+            }
+            i = i + 1;
+        }
+        self.lock_history.push(K::to_key(key));
+        proof {
+            assert(self.lock_history@[self.lock_history@.len() - 1]@ == key@);
+        }
     }
 
-    /// Unlock a key (idempotent)
-    pub fn unlock(&mut self, key: &str)
+    /// Unlock a key (idempotent). Returns false, changing nothing, if the
+    /// store is frozen
+    pub fn unlock(&mut self, key: &K::Ref) -> (success: bool)
         ensures
-            // Key is now unlocked
-            !self.spec_is_locked(key@),
+            success == !old(self).frozen,
+            // Frozen: nothing changes
+            old(self).frozen ==> *self == *old(self),
+            // Key is now unlocked; other owners are kept
+            !old(self).frozen ==> !self.spec_is_locked(key@),
+            !old(self).frozen ==> self.locked@ == old(self).locked@.remove(key@),
             // Data unchanged
             self.data@ == old(self).data@,
             // Other locks unchanged
-            forall|k: Seq<char>| k != key@ ==>
+            forall|k: <K as View>::V| k != key@ ==>
                 (self.spec_is_locked(k) == old(self).spec_is_locked(k)),
             // txn_id unchanged
             self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Write provenance unchanged
+            self.modified_by@ == old(self).modified_by@,
+            // Unlocking never moves a generation
+            self.lock_gen@ == old(self).lock_gen@,
+            // Any lease on the key is dropped with the lock
+            !old(self).frozen ==> self.leases@ == old(self).leases@.remove(key@),
+            self.leased_keys@ == old(self).leased_keys@,
+            self.lock_history@ == old(self).lock_history@,
+            // Epoch advances unless frozen
+            !old(self).frozen ==> self.epoch == old(self).spec_next_epoch(),
+            !old(self).frozen ==> self.op_log@.len() <= OP_LOG_CAP,
+            old(self).spec_locks_backed() ==> self.spec_locks_backed(),
+            old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen {
+            return false;
+        }
+        self.locked.remove(key);
+        self.leases.remove(key);
+        self.bump_epoch();
+        self.log_op(KvOp::Unlock { key: K::to_key(key), txn_id: self.last_seen_txn_id });
+        true
+    }
+
+    /// Rename: move value from old_key to new_key
+    /// Precondition: both keys must be locked and different
+    /// Returns the value that was moved, or None if old_key doesn't exist
+    /// or the store is frozen. Once the value has moved the locks must stay
+    /// until an explicit unlock, so a rename drops the leases on both keys
+    pub fn rename(&mut self, old_key: &K::Ref, new_key: &K::Ref) -> (result: Option<V>)
+        requires
+            old(self).spec_is_locked(old_key@),
+            old(self).spec_is_locked(new_key@),
+            old_key@ != new_key@,
+        ensures
+            // Locks unchanged
+            self.locked@ == old(self).locked@,
+            // Result matches whether old_key existed (and the store was writable)
+            result.is_some() == (!old(self).frozen && old(self).spec_contains_key(old_key@)),
+            // Frozen: nothing changes
+            old(self).frozen ==> *self == *old(self),
+            // If succeeded, the value is correct
+            result.is_some() ==> result == Some(old(self).spec_get(old_key@)),
+            // If succeeded, new_key now has the value
+            result.is_some() ==> self.spec_contains_key(new_key@),
+            result.is_some() ==> self.spec_get(new_key@) == old(self).spec_get(old_key@),
+            // If succeeded, old_key is removed
+            result.is_some() ==> !self.spec_contains_key(old_key@),
+            result.is_some() ==> self.data@
+                == old(self).data@.remove(old_key@).insert(new_key@, old(self).spec_get(old_key@)),
+            // If succeeded, new_key is recorded in the key index
+            result.is_some() ==> self.spec_key_recorded(old(self), new_key@),
+            result.is_none() ==> self.key_index@ == old(self).key_index@,
+            // If failed, data unchanged
+            result.is_none() ==> self.data@ == old(self).data@,
+            // Provenance: new_key is attributed to the last seen txn
+            result.is_some() ==> self.modified_by@ == old(self).modified_by@.insert(new_key@, old(self).last_seen_txn_id),
+            result.is_none() ==> self.modified_by@ == old(self).modified_by@,
+            // Leases on both keys dropped on success
+            result.is_some() ==> self.leases@ == old(self).leases@.remove(old_key@).remove(new_key@),
+            result.is_none() ==> self.leases@ == old(self).leases@,
+            // txn_id unchanged
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Epoch advances on success only
+            result.is_some() ==> self.epoch == old(self).spec_next_epoch(),
+            result.is_none() ==> self.epoch == old(self).epoch,
+            result.is_some() ==> self.op_log@.len() <= OP_LOG_CAP,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen {
+            return None;
+        }
+        let ghost pre = *self;
+        match self.data.get(old_key) {
+            Some(v) => {
+                let value = *v;
+                self.data.remove(old_key);
+                self.data.insert(K::to_key(new_key), value);
+                self.modified_by.insert(K::to_key(new_key), self.last_seen_txn_id);
+                self.record_key(new_key);
+                self.leases.remove(old_key);
+                self.leases.remove(new_key);
+                proof {
+                    assert(self.data@ =~= pre.data@.remove(old_key@).insert(new_key@, value));
+                }
+                self.bump_epoch();
+                self.log_op(KvOp::Rename {
+                    old: K::to_key(old_key),
+                    new: K::to_key(new_key),
+                    txn_id: self.last_seen_txn_id,
+                });
+                Some(value)
+            }
+            None => None,
+        }
+    }
+
+    /// Append `key` to `key_index` unless it is already listed
+    fn record_key(&mut self, key: &K::Ref)
+        ensures
+            exists|i: int| 0 <= i < self.key_index@.len() && self.key_index@[i]@ == key@,
+            self.spec_key_recorded(old(self), key@),
+            (forall|i: int, j: int| 0 <= i < j < old(self).key_index@.len() ==>
+                old(self).key_index@[i]@ != old(self).key_index@[j]@) ==>
+            (forall|i: int, j: int| 0 <= i < j < self.key_index@.len() ==>
+                self.key_index@[i]@ != self.key_index@[j]@),
+            self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.modified_by@ == old(self).modified_by@,
+            self.lock_history@ == old(self).lock_history@,
+            self.frozen == old(self).frozen,
+            self.epoch == old(self).epoch,
+    {
+        let mut i: usize = 0;
+        while i < self.key_index.len()
+            invariant
+                0 <= i <= self.key_index.len(),
+                *self == *old(self),
+                forall|j: int| 0 <= j < i ==> self.key_index@[j]@ != key@,
+            decreases
+                self.key_index.len() - i,
+        {
+            if self.key_index[i].matches(key) {
+                return;
+            }
+            i = i + 1;
+        }
+        self.key_index.push(K::to_key(key));
+        proof {
+            assert(self.key_index@[self.key_index@.len() - 1]@ == key@);
+        }
+    }
+}
+
+impl KvStore {
+    // ============================================================
+    // SPEC HELPERS - For use in ensures clauses
+    // ============================================================
+
+    /// No key appears twice in a rename mapping: sources are distinct,
+    /// destinations are distinct, and no source is also a destination
+    pub open spec fn spec_mapping_disjoint(mapping: Seq<(String, String)>) -> bool {
+        forall|i: int, j: int| 0 <= i < mapping.len() && 0 <= j < mapping.len() ==> {
+            &&& mapping[i].0@ != mapping[j].1@
+            &&& i != j ==> mapping[i].0@ != mapping[j].0@ && mapping[i].1@ != mapping[j].1@
+        }
+    }
+
+    /// Sum of the values of the first `n` indexed keys (indexed keys without data add 0)
+    pub open spec fn spec_value_sum_prefix(&self, n: int) -> nat
+        decreases n,
+    {
+        if n <= 0 {
+            0
+        } else {
+            let k = self.key_index@[n - 1]@;
+            self.spec_value_sum_prefix(n - 1)
+                + if self.spec_contains_key(k) { self.spec_get(k) as nat } else { 0 }
+        }
+    }
+
+    /// Sum of all stored values; with a well-formed `key_index` each key counts once
+    pub open spec fn spec_value_sum(&self) -> nat {
+        self.spec_value_sum_prefix(self.key_index@.len() as int)
+    }
+
+    /// Lemma: over a shared, duplicate-free prefix of `key_index`, moving the
+    /// value of `old_key` to the free `new_key` shifts the prefix sum by what
+    /// the two keys contribute in it
+    pub proof fn lemma_move_value_sum_prefix(a: KvStore, b: KvStore, old_key: Seq<char>, new_key: Seq<char>, n: int)
+        requires
+            0 <= n <= a.key_index@.len(),
+            n <= b.key_index@.len(),
+            forall|i: int| 0 <= i < n ==> #[trigger] b.key_index@[i]@ == a.key_index@[i]@,
+            forall|i: int, j: int| 0 <= i < j < n ==> a.key_index@[i]@ != a.key_index@[j]@,
+            old_key != new_key,
+            a.spec_contains_key(old_key),
+            !a.spec_contains_key(new_key),
+            b.data@ == a.data@.remove(old_key).insert(new_key, a.spec_get(old_key)),
+        ensures
+            b.spec_value_sum_prefix(n)
+                + (if a.spec_indexed_before(old_key, n) { a.spec_get(old_key) as nat } else { 0 })
+                == a.spec_value_sum_prefix(n)
+                + (if a.spec_indexed_before(new_key, n) { a.spec_get(old_key) as nat } else { 0 }),
+        decreases n,
+    {
+        if n > 0 {
+            Self::lemma_move_value_sum_prefix(a, b, old_key, new_key, n - 1);
+            let k = a.key_index@[n - 1]@;
+            assert(b.key_index@[n - 1]@ == k);
+            // The last key is new to the prefix, so only it can flip `indexed_before`
+            assert(a.spec_indexed_before(k, n));
+            if a.spec_indexed_before(k, n - 1) {
+                let i = choose|i: int| 0 <= i < n - 1 && a.key_index@[i]@ == k;
+                assert(a.key_index@[i]@ != a.key_index@[n - 1]@);
+            }
+            assert forall|key: Seq<char>| key != k implies
+                a.spec_indexed_before(key, n) == a.spec_indexed_before(key, n - 1) by {
+                if a.spec_indexed_before(key, n) {
+                    let i = choose|i: int| 0 <= i < n && a.key_index@[i]@ == key;
+                    assert(i < n - 1);
+                }
+            }
+        }
+    }
+
+    /// Lemma: a rename into a free key moves a value without creating or
+    /// destroying any, so the store's value sum is unchanged. `b` is the
+    /// store after the rename, with `new_key` appended to the index if it
+    /// was not listed
+    pub proof fn lemma_rename_conserves_value_sum(a: KvStore, b: KvStore, old_key: Seq<char>, new_key: Seq<char>)
+        requires
+            a.spec_key_index_wf(),
+            old_key != new_key,
+            a.spec_contains_key(old_key),
+            !a.spec_contains_key(new_key),
+            b.data@ == a.data@.remove(old_key).insert(new_key, a.spec_get(old_key)),
+            b.spec_key_recorded(&a, new_key),
+        ensures
+            b.spec_value_sum() == a.spec_value_sum(),
+    {
+        let n = a.key_index@.len() as int;
+        let v = a.spec_get(old_key) as nat;
+        Self::lemma_move_value_sum_prefix(a, b, old_key, new_key, n);
+        // old_key has data, so a well-formed index lists it
+        assert(a.spec_indexed_before(old_key, n));
+        if b.key_index@.len() > n {
+            // new_key was appended, and now holds the moved value
+            assert(!a.spec_indexed_before(new_key, n));
+            assert(b.key_index@[n]@ == new_key);
+            assert(b.spec_value_sum_prefix(n + 1) == b.spec_value_sum_prefix(n) + v);
+        }
+    }
+
+    // ============================================================
+    // EXEC FUNCTIONS - Verified implementations
+    // ============================================================
+
+    /// Create a new empty KV store
+    pub fn new() -> (result: Self)
+        ensures
+            result@.data == Map::<Seq<char>, u64>::empty(),
+            result@.locked_keys == Set::<Seq<char>>::empty(),
+            result@.last_seen_txn_id == 0,
+            result.prepared_txn.is_none(),
+            result.modified_by@ == Map::<Seq<char>, u64>::empty(),
+            result.spec_lock_history_complete(),
+            result.spec_key_index_wf(),
+            result.spec_maps_indexed(),
+            !result.frozen,
+            result.epoch == 0,
+            result.spec_locks_backed(),
+    {
+        KvStore {
+            data: KeyMap::new(),
+            locked: KeyMap::new(),
+            last_seen_txn_id: 0,
+            prepared_txn: None,
+            released_txn: None,
+            modified_by: KeyMap::new(),
+            leases: KeyMap::new(),
+            leased_keys: Vec::new(),
+            lock_history: Vec::new(),
+            key_index: Vec::new(),
+            lock_gen: KeyMap::new(),
+            lock_gen_keys: Vec::new(),
+            frozen: false,
+            epoch: 0,
+            op_log: Vec::new(),
+        }
+    }
+
+    /// Create an empty store sized for `cap` keys, so seeding many keys does not
+    /// rehash. Only the per-key maps and index that grow with the data are
+    /// pre-allocated; the lock table stays small. Same state as `new()`
+    pub fn with_capacity(cap: usize) -> (result: Self)
+        ensures
+            result@.data == Map::<Seq<char>, u64>::empty(),
+            result@.locked_keys == Set::<Seq<char>>::empty(),
+            result@.last_seen_txn_id == 0,
+            result.prepared_txn.is_none(),
+            result.modified_by@ == Map::<Seq<char>, u64>::empty(),
+            result.spec_lock_history_complete(),
+            result.spec_key_index_wf(),
+            result.spec_maps_indexed(),
+            !result.frozen,
+            result.epoch == 0,
+            result.spec_locks_backed(),
+    {
+        KvStore {
+            data: KeyMap::with_capacity(cap),
+            locked: KeyMap::new(),
+            last_seen_txn_id: 0,
+            prepared_txn: None,
+            released_txn: None,
+            modified_by: KeyMap::with_capacity(cap),
+            leases: KeyMap::new(),
+            leased_keys: Vec::new(),
+            lock_history: Vec::new(),
+            key_index: Vec::with_capacity(cap),
+            lock_gen: KeyMap::new(),
+            lock_gen_keys: Vec::new(),
+            frozen: false,
+            epoch: 0,
+            op_log: Vec::new(),
+        }
+    }
+
+    /// Borrow the value for key without copying it
+    /// (same spec as `get`, stated through the reference)
+    pub fn get_ref(&self, key: &str) -> (result: Option<&u64>)
+        ensures
+            match result {
+                Some(v) => self.spec_contains_key(key@) && *v == self.spec_get(key@),
+                None => !self.spec_contains_key(key@),
+            }
+    {
+        self.data.get(key)
+    }
+
+    /// Get value for key, or `default` if the key is absent.
+    /// Read-only: the default is not inserted
+    pub fn get_or(&self, key: &str, default: u64) -> (result: u64)
+        ensures
+            result == if self.spec_contains_key(key@) { self.spec_get(key@) } else { default },
+    {
+        match self.data.get(key) {
+            Some(v) => *v,
+            None => default,
+        }
+    }
+
+    /// Get values for several keys at once (read-only batch of `get`)
+    /// result[i] is the lookup result for keys[i]
+    pub fn get_many(&self, keys: &[String]) -> (result: Vec<Option<u64>>)
+        ensures
+            result@.len() == keys@.len(),
+            forall|i: int| #![auto] 0 <= i < keys@.len() ==>
+                match result@[i] {
+                    Some(v) => self.spec_contains_key(keys@[i]@) && v == self.spec_get(keys@[i]@),
+                    None => !self.spec_contains_key(keys@[i]@),
+                },
+    {
+        let mut result: Vec<Option<u64>> = Vec::new();
+        let mut i: usize = 0;
+        while i < keys.len()
+            invariant
+                0 <= i <= keys@.len(),
+                result@.len() == i,
+                forall|j: int| #![auto] 0 <= j < i ==>
+                    match result@[j] {
+                        Some(v) => self.spec_contains_key(keys@[j]@) && v == self.spec_get(keys@[j]@),
+                        None => !self.spec_contains_key(keys@[j]@),
+                    },
+            decreases
+                keys.len() - i,
+        {
+            let value = self.get(keys[i].as_str());
+            result.push(value);
+            i = i + 1;
+        }
+        result
+    }
+
+    /// Lock and presence state of a key in one call
+    pub fn lock_status(&self, key: &str) -> (result: KeyStatus)
+        ensures
+            result == KeyStatus::Unlocked <==> !self.spec_is_locked(key@),
+            result == KeyStatus::LockedAndPresent <==>
+                self.spec_is_locked(key@) && self.spec_contains_key(key@),
+            result == KeyStatus::LockedAndAbsent <==>
+                self.spec_is_locked(key@) && !self.spec_contains_key(key@),
+    {
+        if !self.locked.contains_key(key) {
+            KeyStatus::Unlocked
+        } else if self.data.contains_key(key) {
+            KeyStatus::LockedAndPresent
+        } else {
+            KeyStatus::LockedAndAbsent
+        }
+    }
+
+    /// Everything about `key` in one read: presence, value, lock and lock owner.
+    /// Consistent by construction, unlike separate `contains_key`, `get` and
+    /// `is_locked` calls with the store changing in between
+    pub fn observe(&self, key: &str) -> (result: KeyObservation)
+        ensures
+            result.present == self.spec_contains_key(key@),
+            result.value.is_some() == self.spec_contains_key(key@),
+            result.value.is_some() ==> result.value.unwrap() == self.spec_get(key@),
+            result.locked == self.spec_is_locked(key@),
+            result.lock_owner.is_some() == self.spec_is_locked(key@),
+            result.lock_owner.is_some() ==> result.lock_owner.unwrap() == self.spec_lock_owner(key@),
+    {
+        let value = self.get(key);
+        let lock_owner = self.lock_owner(key);
+        KeyObservation { present: value.is_some(), value, locked: lock_owner.is_some(), lock_owner }
+    }
+
+    /// The vote this store would cast on txn `txn_id`'s LockReq renaming
+    /// `key_a` to `key_aprime`, without locking anything. A lock held while the
+    /// store is prepared for `txn_id` is its own (a re-delivered request), so
+    /// only locks held outside that txn count against it
+    pub fn prepare_vote(&self, key_a: &str, key_aprime: &str, txn_id: u64) -> (result: Vote)
+        ensures
+            result == Vote::NoFrozen <==> self.frozen,
+            result == Vote::NoAlreadyRenamed <==> !self.frozen && self.spec_contains_key(key_aprime@),
+            result == Vote::NoLockedByOther <==> !self.frozen && !self.spec_contains_key(key_aprime@)
+                && (self.spec_is_locked(key_a@) || self.spec_is_locked(key_aprime@))
+                && self.prepared_txn != Some(txn_id),
+            result == Vote::Yes <==> !self.frozen && !self.spec_contains_key(key_aprime@)
+                && ((!self.spec_is_locked(key_a@) && !self.spec_is_locked(key_aprime@))
+                    || self.prepared_txn == Some(txn_id)),
+    {
+        self.prepare_vote_with_detect_key(key_a, key_aprime, key_aprime, txn_id)
+    }
+
+    /// Same as `prepare_vote`, except the store counts as already renamed if
+    /// `detect_key` (rather than `key_aprime`) exists. Locks are still checked
+    /// on `key_a` and `key_aprime`, the keys a yes vote would take
+    pub fn prepare_vote_with_detect_key(
+        &self,
+        key_a: &str,
+        key_aprime: &str,
+        detect_key: &str,
+        txn_id: u64,
+    ) -> (result: Vote)
+        ensures
+            result == Vote::NoFrozen <==> self.frozen,
+            result == Vote::NoAlreadyRenamed <==> !self.frozen && self.spec_contains_key(detect_key@),
+            result == Vote::NoLockedByOther <==> !self.frozen && !self.spec_contains_key(detect_key@)
+                && (self.spec_is_locked(key_a@) || self.spec_is_locked(key_aprime@))
+                && self.prepared_txn != Some(txn_id),
+            result == Vote::Yes <==> !self.frozen && !self.spec_contains_key(detect_key@)
+                && ((!self.spec_is_locked(key_a@) && !self.spec_is_locked(key_aprime@))
+                    || self.prepared_txn == Some(txn_id)),
+    {
+        if self.frozen {
+            Vote::NoFrozen
+        } else if self.contains_key(detect_key) {
+            Vote::NoAlreadyRenamed
+        } else if (self.is_locked(key_a) || self.is_locked(key_aprime)) && !self.is_prepared(txn_id) {
+            Vote::NoLockedByOther
+        } else {
+            Vote::Yes
+        }
+    }
+
+    /// Runtime check of the rename invariant `data_accessible`: exactly one of
+    /// `key_a` / `key_aprime` is present. Suitable for `debug_assert!`
+    pub fn check_data_accessible(&self, key_a: &str, key_aprime: &str) -> (result: bool)
+        ensures
+            result == data_accessible(self@, key_a@, key_aprime@),
+    {
+        self.contains_key(key_a) != self.contains_key(key_aprime)
+    }
+
+    /// Transaction id that last wrote `key` (via put or rename), if any
+    pub fn last_writer(&self, key: &str) -> (result: Option<u64>)
+        ensures
+            result == self.spec_last_writer(key@)
+    {
+        match self.modified_by.get(key) {
+            Some(t) => Some(*t),
+            None => None,
+        }
+    }
+
+    /// Get the last seen transaction ID
+    pub fn get_last_seen_txn_id(&self) -> (result: u64)
+        ensures
+            result as nat == self.spec_last_seen_txn_id()
+    {
+        self.last_seen_txn_id
+    }
+
+    /// Check if a transaction ID is stale (older than or equal to last seen)
+    pub fn is_stale_txn_id(&self, txn_id: u64) -> (result: bool)
+        ensures
+            result == self.spec_is_stale_txn_id(txn_id as nat)
+    {
+        txn_id < self.last_seen_txn_id
+    }
+
+    /// Check the lock/txn invariant for `key`: if it is locked, the txn that
+    /// holds it has been seen (see `spec_locks_backed`)
+    pub fn lock_implies_seen_txn(&self, key: &str) -> (result: bool)
+        ensures
+            result == (self.spec_is_locked(key@) ==> self.spec_lock_owner(key@) <= self.last_seen_txn_id),
+            self.spec_locks_backed() ==> result,
+    {
+        match self.locked.get(key) {
+            Some(owner) => *owner <= self.last_seen_txn_id,
+            None => true,
+        }
+    }
+
+    /// Check if the store has voted yes for `txn_id` and awaits its decision
+    pub fn is_prepared(&self, txn_id: u64) -> (result: bool)
+        ensures
+            result == self.spec_is_prepared(txn_id),
+            result == self@.is_prepared(txn_id as nat),
+    {
+        match self.prepared_txn {
+            Some(t) => t == txn_id,
+            None => false,
+        }
+    }
+
+    /// Record a yes vote for `txn_id` (durable)
+    pub fn prepare(&mut self, txn_id: u64)
+        ensures
+            self.prepared_txn == Some(txn_id),
+            self@ == old(self)@.prepare(txn_id as nat),
+            self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Write provenance unchanged
+            self.modified_by@ == old(self).modified_by@,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        self.prepared_txn = Some(txn_id);
+    }
+
+    /// Forget the prepared vote once the decision has been applied
+    pub fn clear_prepared(&mut self)
+        ensures
+            self.prepared_txn.is_none(),
+            self@ == old(self)@.clear_prepared(),
+            self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Write provenance unchanged
+            self.modified_by@ == old(self).modified_by@,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        self.prepared_txn = None;
+    }
+
+    /// Has this store already released the locks of `txn_id`?
+    pub fn is_released(&self, txn_id: u64) -> (result: bool)
+        ensures
+            result == self@.is_released(txn_id as nat),
+    {
+        match self.released_txn {
+            Some(t) => t == txn_id,
+            None => false,
+        }
+    }
+
+    /// Remember that the locks of `txn_id` were released (durable)
+    pub fn release(&mut self, txn_id: u64)
+        ensures
+            self.released_txn == Some(txn_id),
+            self@ == old(self)@.release(txn_id as nat),
+            self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.prepared_txn == old(self).prepared_txn,
+            self.modified_by@ == old(self).modified_by@,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        self.released_txn = Some(txn_id);
+    }
+
+    /// Store crash - the in-memory lock table is lost, durable state survives
+    pub fn crash(&mut self)
+        ensures
+            // Volatile state lost
+            self.locked@ == Map::<Seq<char>, u64>::empty(),
+            // Durable state preserved
+            self.data@ == old(self).data@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.prepared_txn == old(self).prepared_txn,
+            self.released_txn == old(self).released_txn,
+            // Write provenance and lock generations unchanged
+            self.modified_by@ == old(self).modified_by@,
+            self.lock_gen@ == old(self).lock_gen@,
+            self.spec_lock_history_complete(),
+            self.spec_locks_backed(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        self.locked.clear();
+        self.leases.clear();
+        self.leased_keys.clear();
+        self.lock_history.clear();
+    }
+
+    /// Put the store into read-only maintenance mode: put, delete, rename,
+    /// lock and unlock fail without changing anything until `unfreeze`, and
+    /// the store votes no on every LockReq. A coarse complement to
+    /// per-key locks; existing locks and data are left as they are
+    pub fn freeze(&mut self)
+        ensures
+            self.frozen,
+            self@ == old(self)@.freeze(),
+            self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.prepared_txn == old(self).prepared_txn,
+            self.modified_by@ == old(self).modified_by@,
+            old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+    {
+        self.frozen = true;
+    }
+
+    /// Leave maintenance mode, making the store writable again
+    pub fn unfreeze(&mut self)
+        ensures
+            !self.frozen,
+            self@ == old(self)@.unfreeze(),
+            self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.prepared_txn == old(self).prepared_txn,
+            self.modified_by@ == old(self).modified_by@,
+            old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+    {
+        self.frozen = false;
+    }
+
+    /// Is the store in read-only maintenance mode?
+    pub fn is_frozen(&self) -> (result: bool)
+        ensures
+            result == self.frozen,
+            result == self@.is_frozen(),
+    {
+        self.frozen
+    }
+
+    /// Update the last seen transaction ID (only updates if newer)
+    pub fn update_txn_id(&mut self, txn_id: u64)
+        ensures
+            // Updates to new txn_id if greater, otherwise unchanged
+            self.last_seen_txn_id == if txn_id > old(self).last_seen_txn_id {
+                txn_id
+            } else {
+                old(self).last_seen_txn_id
+            },
+            // Data unchanged
+            self.data@ == old(self).data@,
+            // Locks unchanged
+            self.locked@ == old(self).locked@,
+            self.leases@ == old(self).leases@,
+            self.lock_history@ == old(self).lock_history@,
+            // Write provenance unchanged
+            self.modified_by@ == old(self).modified_by@,
+            old(self).spec_locks_backed() ==> self.spec_locks_backed(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if txn_id > self.last_seen_txn_id {
+            self.last_seen_txn_id = txn_id;
+        }
+    }
+
+    /// Entry-style lookup: return the existing value, or insert `default` and
+    /// return it. An absent key that is locked cannot be written, so it gets
+    /// None and nothing is inserted (see KvStoreSpec::get_or_insert)
+    pub fn get_or_insert(&mut self, key: &str, default: u64) -> (result: Option<u64>)
+        requires
+            !old(self).frozen,
+        ensures
+            self@.data == old(self)@.get_or_insert(key@, default).data,
+            // Present: unchanged, existing value returned
+            old(self).spec_contains_key(key@) ==> (
+                result == Some(old(self).spec_get(key@))
+                && self.data@ == old(self).data@
+            ),
+            // Absent and unlocked: default inserted and returned
+            !old(self).spec_contains_key(key@) && !old(self).spec_is_locked(key@) ==> (
+                result == Some(default)
+                && self.data@ == old(self).data@.insert(key@, default)
+            ),
+            // Absent and locked: unchanged, nothing returned
+            !old(self).spec_contains_key(key@) && old(self).spec_is_locked(key@) ==> (
+                result.is_none()
+                && self.data@ == old(self).data@
+            ),
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if let Some(v) = self.get(key) {
+            return Some(v);
+        }
+        if self.put(key, default) {
+            Some(default)
+        } else {
+            None
+        }
+    }
+
+    /// Attribute an existing key to `txn_id` without changing its value
+    /// (e.g. to mark it as examined by that txn for conflict detection).
+    /// Returns false, changing nothing, if the key is locked or absent or the
+    /// store is frozen
+    pub fn touch(&mut self, key: &str, txn_id: u64) -> (success: bool)
+        ensures
+            success == (!old(self).frozen && !old(self).spec_is_locked(key@) && old(self).spec_contains_key(key@)),
+            // Frozen: nothing changes
+            old(self).frozen ==> self@ == old(self)@,
+            // Value, locks and txn_id unchanged
+            self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Provenance updated only on success
+            success ==> self.modified_by@ == old(self).modified_by@.insert(key@, txn_id),
+            success ==> self.spec_last_writer(key@) == Some(txn_id),
+            !success ==> self.modified_by@ == old(self).modified_by@,
+            // Epoch advances on success
+            success ==> self.epoch == old(self).spec_next_epoch(),
+            success && old(self).epoch < u64::MAX ==> self.epoch > old(self).epoch,
+            !success ==> self.epoch == old(self).epoch,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen || self.locked.contains_key(key) || !self.data.contains_key(key) {
+            false
+        } else {
+            self.modified_by.insert(key.to_owned(), txn_id);
+            self.bump_epoch();
+            true
+        }
+    }
+
+    /// All currently locked keys, in unspecified order and without duplicates
+    pub fn locked_keys(&self) -> (result: Vec<String>)
+        requires
+            self.spec_lock_history_complete(),
+        ensures
+            forall|i: int| #![auto] 0 <= i < result@.len() ==> self@.locked_keys.contains(result@[i]@),
+            forall|k: Seq<char>| #![auto] self@.locked_keys.contains(k) ==>
+                exists|i: int| 0 <= i < result@.len() && result@[i]@ == k,
+            forall|i: int, j: int| 0 <= i < j < result@.len() ==> result@[i]@ != result@[j]@,
+    {
+        let mut result: Vec<String> = Vec::new();
+        let mut i: usize = 0;
+        while i < self.lock_history.len()
+            invariant
+                0 <= i <= self.lock_history.len(),
+                forall|r: int| #![auto] 0 <= r < result@.len() ==> self.spec_is_locked(result@[r]@),
+                // Completeness: every locked key scanned so far has been collected
+                forall|k: Seq<char>| #![auto] self.spec_is_locked(k)
+                    && (exists|j: int| 0 <= j < i && self.lock_history@[j]@ == k) ==>
+                    exists|r: int| 0 <= r < result@.len() && result@[r]@ == k,
+                forall|r: int, s: int| 0 <= r < s < result@.len() ==> result@[r]@ != result@[s]@,
+            decreases
+                self.lock_history.len() - i,
+        {
+            let key = &self.lock_history[i];
+            if self.locked.contains_key(key.as_str()) {
+                let mut seen = false;
+                let mut r: usize = 0;
+                while r < result.len()
+                    invariant
+                        0 <= r <= result.len(),
+                        !seen ==> forall|s: int| 0 <= s < r ==> result@[s]@ != key@,
+                        seen ==> exists|s: int| 0 <= s < result@.len() && result@[s]@ == key@,
+                    decreases
+                        result.len() - r,
+                {
+                    if result[r].as_str() == key.as_str() {
+                        seen = true;
+                    }
+                    r = r + 1;
+                }
+                if !seen {
+                    result.push(key.clone());
+                }
+            }
+            i = i + 1;
+        }
+        proof {
+            assert forall|k: Seq<char>| #![auto] self@.locked_keys.contains(k) implies
+                exists|r: int| 0 <= r < result@.len() && result@[r]@ == k by {
+                assert(self.spec_is_locked(k));
+            }
+        }
+        result
+    }
+
+    /// Every locked key paired with the txn that owns its lock
+    /// Lets a recovering coordinator rebuild which stores granted it a lock
+    pub fn export_lock_state(&self) -> (result: Vec<(String, Option<u64>)>)
+        requires
+            self.spec_lock_history_complete(),
+        ensures
+            forall|i: int| #![auto] 0 <= i < result@.len() ==> self@.locked_keys.contains(result@[i].0@),
+            forall|i: int| #![auto] 0 <= i < result@.len() ==>
+                result@[i].1 == Some(self.spec_lock_owner(result@[i].0@)),
+            forall|k: Seq<char>| #![auto] self@.locked_keys.contains(k) ==>
+                exists|i: int| 0 <= i < result@.len() && result@[i].0@ == k,
+            forall|i: int, j: int| 0 <= i < j < result@.len() ==> result@[i].0@ != result@[j].0@,
+    {
+        let keys = self.locked_keys();
+        let mut result: Vec<(String, Option<u64>)> = Vec::new();
+        let mut i: usize = 0;
+        while i < keys.len()
+            invariant
+                0 <= i <= keys.len(),
+                result@.len() == i,
+                forall|j: int| #![auto] 0 <= j < keys@.len() ==> self@.locked_keys.contains(keys@[j]@),
+                forall|j: int| #![auto] 0 <= j < i ==> result@[j].0@ == keys@[j]@,
+                forall|j: int| #![auto] 0 <= j < i ==> result@[j].1 == Some(self.spec_lock_owner(keys@[j]@)),
+            decreases
+                keys.len() - i,
+        {
+            let owner = self.lock_owner(keys[i].as_str());
+            result.push((keys[i].clone(), owner));
+            i = i + 1;
+        }
+        proof {
+            assert forall|k: Seq<char>| #![auto] self@.locked_keys.contains(k) implies
+                exists|j: int| 0 <= j < result@.len() && result@[j].0@ == k by {
+                let j = choose|j: int| 0 <= j < keys@.len() && keys@[j]@ == k;
+                assert(result@[j].0@ == k);
+            }
+        }
+        result
+    }
+
+    /// Call `f` on every locked key with its current value (None if the key
+    /// has no data), in `locked_keys` order. Closures are outside what Verus
+    /// verifies here, so this is trusted (external_body); it only reads the
+    /// verified `locked_keys` and `get`.
+    #[verifier::external_body]
+    pub fn for_each_locked<F: FnMut(&str, Option<u64>)>(&self, mut f: F) {
+        for key in self.locked_keys() {
+            f(&key, self.get(&key));
+        }
+    }
+
+    /// Locked keys with no data, in `locked_keys` order. Expected mid-protocol
+    /// (A' is locked before the rename fills it); by the end of a transaction
+    /// each should be populated by rename or released by unlock
+    pub fn orphan_locks(&self) -> (result: Vec<String>)
+        requires
+            self.spec_lock_history_complete(),
+        ensures
+            forall|i: int| #![auto] 0 <= i < result@.len() ==>
+                self@.locked_keys.contains(result@[i]@) && !self@.data.contains_key(result@[i]@),
+            forall|k: Seq<char>| #![auto] self@.locked_keys.contains(k) && !self@.data.contains_key(k) ==>
+                exists|i: int| 0 <= i < result@.len() && result@[i]@ == k,
+    {
+        let keys = self.locked_keys();
+        let mut result: Vec<String> = Vec::new();
+        let mut i: usize = 0;
+        while i < keys.len()
+            invariant
+                0 <= i <= keys.len(),
+                forall|j: int| #![auto] 0 <= j < keys@.len() ==> self@.locked_keys.contains(keys@[j]@),
+                forall|r: int| #![auto] 0 <= r < result@.len() ==>
+                    self@.locked_keys.contains(result@[r]@) && !self@.data.contains_key(result@[r]@),
+                forall|j: int| #![auto] 0 <= j < i && !self@.data.contains_key(keys@[j]@) ==>
+                    exists|r: int| 0 <= r < result@.len() && result@[r]@ == keys@[j]@,
+            decreases
+                keys.len() - i,
+        {
+            if !self.contains_key(keys[i].as_str()) {
+                result.push(keys[i].clone());
+            }
+            i = i + 1;
+        }
+        proof {
+            assert forall|k: Seq<char>| #![auto] self@.locked_keys.contains(k) && !self@.data.contains_key(k) implies
+                exists|r: int| 0 <= r < result@.len() && result@[r]@ == k by {
+                let j = choose|j: int| 0 <= j < keys@.len() && keys@[j]@ == k;
+                assert(!self@.data.contains_key(keys@[j]@));
+            }
+        }
+        result
+    }
+
+    /// Number of keys currently mapped to `value` (0 for an empty store)
+    pub fn count_value(&self, value: u64) -> (result: usize)
+        requires
+            self.spec_key_index_wf(),
+        ensures
+            result as nat == self@.count_value(value),
+    {
+        let ghost matching = self@.data.dom().filter(|k: Seq<char>| self@.data[k] == value);
+        let ghost mut counted: Set<Seq<char>> = Set::empty();
+        let mut count: usize = 0;
+        let mut i: usize = 0;
+        while i < self.key_index.len()
+            invariant
+                0 <= i <= self.key_index.len(),
+                count <= i,
+                self.spec_key_index_wf(),
+                counted.finite(),
+                count as nat == counted.len(),
+                // counted = matching keys among the first i index entries
+                forall|k: Seq<char>| #[trigger] counted.contains(k) <==>
+                    (matching.contains(k) && exists|j: int| 0 <= j < i && self.key_index@[j]@ == k),
+            decreases
+                self.key_index.len() - i,
+        {
+            let key = self.key_index[i].as_str();
+            if self.get(key) == Some(value) {
+                proof {
+                    // key_index has no duplicates, so this key was not counted yet
+                    assert(!counted.contains(key@));
+                    counted = counted.insert(key@);
+                }
+                count = count + 1;
+            }
+            i = i + 1;
+        }
+        proof {
+            // Every key with data is indexed, so every matching key was counted
+            assert(counted =~= matching);
+        }
+        count
+    }
+
+    /// All key/value pairs whose key starts with `prefix`, in `key_index`
+    /// order (e.g. every key under a `"user:"` namespace)
+    pub fn scan_prefix(&self, prefix: &str) -> (result: Vec<(String, u64)>)
+        requires
+            self.spec_key_index_wf(),
+        ensures
+            forall|i: int| #![auto] 0 <= i < result@.len() ==> {
+                &&& prefix@.is_prefix_of(result@[i].0@)
+                &&& self.spec_contains_key(result@[i].0@)
+                &&& self.spec_get(result@[i].0@) == result@[i].1
+            },
+            forall|k: Seq<char>| #![auto] self.spec_contains_key(k) && prefix@.is_prefix_of(k) ==>
+                exists|i: int| 0 <= i < result@.len() && result@[i].0@ == k,
+    {
+        let mut result: Vec<(String, u64)> = Vec::new();
+        let mut i: usize = 0;
+        while i < self.key_index.len()
+            invariant
+                0 <= i <= self.key_index.len(),
+                self.spec_key_index_wf(),
+                forall|r: int| #![auto] 0 <= r < result@.len() ==> {
+                    &&& prefix@.is_prefix_of(result@[r].0@)
+                    &&& self.spec_contains_key(result@[r].0@)
+                    &&& self.spec_get(result@[r].0@) == result@[r].1
+                },
+                forall|j: int| #![auto] 0 <= j < i && self.spec_contains_key(self.key_index@[j]@)
+                    && prefix@.is_prefix_of(self.key_index@[j]@) ==>
+                    exists|r: int| 0 <= r < result@.len() && result@[r].0@ == self.key_index@[j]@,
+            decreases
+                self.key_index.len() - i,
+        {
+            let key = self.key_index[i].as_str();
+            if has_prefix(key, prefix) {
+                if let Some(value) = self.get(key) {
+                    result.push((self.key_index[i].clone(), value));
+                    proof {
+                        assert(result@[result@.len() - 1].0@ == self.key_index@[i as int]@);
+                    }
+                }
+            }
+            i = i + 1;
+        }
+        proof {
+            assert forall|k: Seq<char>| #![auto] self.spec_contains_key(k) && prefix@.is_prefix_of(k) implies
+                exists|r: int| 0 <= r < result@.len() && result@[r].0@ == k by {
+                // Every key with data is indexed
+                let j = choose|j: int| 0 <= j < self.key_index@.len() && self.key_index@[j]@ == k;
+                assert(self.spec_contains_key(self.key_index@[j]@));
+            }
+        }
+        result
+    }
+
+    /// Sum of all stored values, saturating at `u64::MAX`
+    pub fn value_sum(&self) -> (result: u64)
+        ensures
+            result as nat == if self.spec_value_sum() <= u64::MAX as nat {
+                self.spec_value_sum()
+            } else {
+                u64::MAX as nat
+            },
+    {
+        let mut sum: u64 = 0;
+        let mut i: usize = 0;
+        while i < self.key_index.len()
+            invariant
+                0 <= i <= self.key_index.len(),
+                sum as nat == if self.spec_value_sum_prefix(i as int) <= u64::MAX as nat {
+                    self.spec_value_sum_prefix(i as int)
+                } else {
+                    u64::MAX as nat
+                },
+            decreases
+                self.key_index.len() - i,
+        {
+            let key = self.key_index[i].as_str();
+            if let Some(v) = self.get(key) {
+                sum = if v <= u64::MAX - sum { sum + v } else { u64::MAX };
+            }
+            i = i + 1;
+        }
+        sum
+    }
+
+    /// Summary of the store's keys in one pass:
+    /// (present & unlocked, present & locked, absent & locked, total locked).
+    /// Mid-protocol, absent & locked is nonzero exactly while A' is locked ahead of the rename
+    pub fn status_counts(&self) -> (result: (usize, usize, usize, usize))
+        requires
+            self.spec_key_index_wf(),
+            self.spec_lock_history_complete(),
+        ensures
+            result.0 as nat == self@.count_present_unlocked(),
+            result.1 as nat == self@.count_present_locked(),
+            result.2 as nat == self@.count_absent_locked(),
+            result.3 as nat == self@.locked_keys.len(),
+    {
+        let ghost unlocked_set = self@.data.dom().filter(|k: Seq<char>| !self@.locked_keys.contains(k));
+        let ghost locked_set = self@.data.dom().filter(|k: Seq<char>| self@.locked_keys.contains(k));
+        let ghost mut counted_unlocked: Set<Seq<char>> = Set::empty();
+        let ghost mut counted_locked: Set<Seq<char>> = Set::empty();
+        let mut present_unlocked: usize = 0;
+        let mut present_locked: usize = 0;
+        let mut i: usize = 0;
+        while i < self.key_index.len()
+            invariant
+                0 <= i <= self.key_index.len(),
+                present_unlocked + present_locked <= i,
+                self.spec_key_index_wf(),
+                counted_unlocked.finite(),
+                counted_locked.finite(),
+                present_unlocked as nat == counted_unlocked.len(),
+                present_locked as nat == counted_locked.len(),
+                forall|k: Seq<char>| #[trigger] counted_unlocked.contains(k) <==>
+                    (unlocked_set.contains(k) && exists|j: int| 0 <= j < i && self.key_index@[j]@ == k),
+                forall|k: Seq<char>| #[trigger] counted_locked.contains(k) <==>
+                    (locked_set.contains(k) && exists|j: int| 0 <= j < i && self.key_index@[j]@ == k),
+            decreases
+                self.key_index.len() - i,
+        {
+            let key = self.key_index[i].as_str();
+            if self.contains_key(key) {
+                if self.is_locked(key) {
+                    proof {
+                        // key_index has no duplicates, so this key was not counted yet
+                        assert(!counted_locked.contains(key@));
+                        counted_locked = counted_locked.insert(key@);
+                    }
+                    present_locked = present_locked + 1;
+                } else {
+                    proof {
+                        assert(!counted_unlocked.contains(key@));
+                        counted_unlocked = counted_unlocked.insert(key@);
+                    }
+                    present_unlocked = present_unlocked + 1;
+                }
+            }
+            i = i + 1;
+        }
+        proof {
+            // Every key with data is indexed, so every present key was counted
+            assert(counted_unlocked =~= unlocked_set);
+            assert(counted_locked =~= locked_set);
+        }
+
+        let keys = self.locked_keys();
+        let ghost absent_set = self@.locked_keys.filter(|k: Seq<char>| !self@.data.contains_key(k));
+        let ghost mut counted_absent: Set<Seq<char>> = Set::empty();
+        let ghost mut counted_all: Set<Seq<char>> = Set::empty();
+        let mut absent_locked: usize = 0;
+        let mut j: usize = 0;
+        while j < keys.len()
+            invariant
+                0 <= j <= keys.len(),
+                absent_locked <= j,
+                forall|r: int| #![auto] 0 <= r < keys@.len() ==> self@.locked_keys.contains(keys@[r]@),
+                forall|r: int, s: int| 0 <= r < s < keys@.len() ==> keys@[r]@ != keys@[s]@,
+                counted_absent.finite(),
+                counted_all.finite(),
+                absent_locked as nat == counted_absent.len(),
+                j as nat == counted_all.len(),
+                forall|k: Seq<char>| #[trigger] counted_absent.contains(k) <==>
+                    (absent_set.contains(k) && exists|r: int| 0 <= r < j && keys@[r]@ == k),
+                forall|k: Seq<char>| #[trigger] counted_all.contains(k) <==>
+                    exists|r: int| 0 <= r < j && keys@[r]@ == k,
+            decreases
+                keys.len() - j,
+        {
+            let key = keys[j].as_str();
+            proof {
+                // locked_keys has no duplicates, so this key was not counted yet
+                assert(!counted_all.contains(key@));
+                counted_all = counted_all.insert(key@);
+            }
+            if !self.contains_key(key) {
+                proof {
+                    assert(!counted_absent.contains(key@));
+                    counted_absent = counted_absent.insert(key@);
+                }
+                absent_locked = absent_locked + 1;
+            }
+            j = j + 1;
+        }
+        proof {
+            // locked_keys lists every locked key, so both sets are complete
+            assert(counted_absent =~= absent_set);
+            assert(counted_all =~= self@.locked_keys);
+        }
+
+        (present_unlocked, present_locked, absent_locked, keys.len())
+    }
+
+    /// Every (key, value) pair with data, sorted by key. Sorting is outside
+    /// what Verus verifies here, so this is trusted (external_body); it only
+    /// reads the verified `get` over `key_index`.
+    #[verifier::external_body]
+    pub fn sorted_entries(&self) -> Vec<(String, u64)> {
+        let mut entries: Vec<(String, u64)> = self
+            .key_index
+            .iter()
+            .filter_map(|key| self.get(key).map(|value| (key.clone(), value)))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    /// Canonical total order over stores: lexicographic over `sorted_entries`,
+    /// so stores with the same data compare Equal however they were built.
+    /// Locks and txn bookkeeping are ignored. Trusted (external_body), like
+    /// `sorted_entries`.
+    #[verifier::external_body]
+    pub fn compare(&self, other: &KvStore) -> core::cmp::Ordering {
+        self.sorted_entries().cmp(&other.sorted_entries())
+    }
+
+    /// The store's data as a JSON object of key -> value, keys in sorted order
+    /// (locks and txn bookkeeping are not included). Trusted (external_body).
+    #[verifier::external_body]
+    pub fn export_json(&self) -> String {
+        let fields: Vec<String> = self
+            .sorted_entries()
+            .iter()
+            .map(|(key, value)| format!("{}:{}", json_quote(key), value))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+
+    /// Parse a JSON object of key -> value (as written by `export_json`) into a
+    /// fresh unlocked store. Returns None for malformed JSON, a value that is
+    /// not a u64 integer, or a repeated key. Trusted (external_body).
+    #[verifier::external_body]
+    pub fn import_json(s: &str) -> Option<KvStore> {
+        let mut store = KvStore::new();
+        for (key, value) in parse_json_object(s)? {
+            if store.contains_key(&key) {
+                return None;
+            }
+            store.put(&key, value);
+        }
+        Some(store)
+    }
+
+    /// The data map after applying `deltas` in order
+    pub open spec fn spec_apply_deltas(data: Map<Seq<char>, u64>, deltas: Seq<KvDelta>) -> Map<Seq<char>, u64>
+        decreases deltas.len()
+    {
+        if deltas.len() == 0 {
+            data
+        } else {
+            let prev = Self::spec_apply_deltas(data, deltas.drop_last());
+            match deltas.last() {
+                KvDelta::Put(k, v) => prev.insert(k@, v),
+                KvDelta::Remove(k) => prev.remove(k@),
+            }
+        }
+    }
+
+    /// Changelog that turns `other`'s data into this store's: removals for keys
+    /// only `other` has, then puts for keys whose value is missing or differs.
+    /// Ship it to a replica and `apply_delta` it there
+    pub fn diff(&self, other: &KvStore) -> (result: Vec<KvDelta>)
+        requires
+            self.spec_key_index_wf(),
+            other.spec_key_index_wf(),
+        ensures
+            Self::spec_apply_deltas(other.data@, result@) == self.data@,
+    {
+        let mut result: Vec<KvDelta> = Vec::new();
+        // `target` tracks spec_apply_deltas(other.data@, result@) as result grows
+        let ghost mut target = other.data@;
+
+        // Removals: keys of `other` that this store lacks
+        let mut i: usize = 0;
+        while i < other.key_index.len()
+            invariant
+                0 <= i <= other.key_index.len(),
+                other.spec_key_index_wf(),
+                target == Self::spec_apply_deltas(other.data@, result@),
+                forall|k: Seq<char>| #[trigger] target.contains_key(k) <==> (
+                    other.data@.contains_key(k) && (self.data@.contains_key(k)
+                        || !exists|j: int| 0 <= j < i && other.key_index@[j]@ == k)),
+                forall|k: Seq<char>| #[trigger] target.contains_key(k) ==> target[k] == other.data@[k],
+            decreases
+                other.key_index.len() - i,
+        {
+            let key = other.key_index[i].as_str();
+            if other.data.contains_key(key) && !self.data.contains_key(key) {
+                let ghost prev = result@;
+                result.push(KvDelta::Remove(key.to_owned()));
+                proof {
+                    assert(result@.drop_last() =~= prev);
+                    target = target.remove(key@);
+                }
+            }
+            i = i + 1;
+        }
+
+        // Puts: keys of this store whose value `other` does not already hold
+        let mut i: usize = 0;
+        while i < self.key_index.len()
+            invariant
+                0 <= i <= self.key_index.len(),
+                self.spec_key_index_wf(),
+                other.spec_key_index_wf(),
+                target == Self::spec_apply_deltas(other.data@, result@),
+                forall|k: Seq<char>| #[trigger] target.contains_key(k) ==>
+                    self.data@.contains_key(k),
+                forall|k: Seq<char>| #[trigger] target.contains_key(k) ==>
+                    target[k] == self.data@[k] || (other.data@.contains_key(k) && target[k] == other.data@[k]),
+                forall|k: Seq<char>| self.data@.contains_key(k) && other.data@.contains_key(k)
+                    ==> #[trigger] target.contains_key(k),
+                forall|j: int| #![auto] 0 <= j < i && self.data@.contains_key(self.key_index@[j]@) ==>
+                    target.contains_key(self.key_index@[j]@)
+                    && target[self.key_index@[j]@] == self.data@[self.key_index@[j]@],
+            decreases
+                self.key_index.len() - i,
+        {
+            let key = self.key_index[i].as_str();
+            match self.get(key) {
+                Some(value) if other.get(key) != Some(value) => {
+                    let ghost prev = result@;
+                    result.push(KvDelta::Put(key.to_owned(), value));
+                    proof {
+                        assert(result@.drop_last() =~= prev);
+                        target = target.insert(key@, value);
+                    }
+                },
+                _ => {},
+            }
+            i = i + 1;
+        }
+        proof {
+            // Every key with data is indexed, so every one of them was visited
+            assert(target =~= self.data@);
+        }
+        result
+    }
+
+    /// Apply a changelog (e.g. from `diff` on a primary) to this store.
+    /// All-or-nothing: returns false and changes nothing if any key it touches
+    /// is locked. Puts are attributed like `put`, to the last seen txn
+    pub fn apply_delta(&mut self, deltas: &[KvDelta]) -> (success: bool)
+        requires
+            !old(self).frozen,
+        ensures
+            success == forall|i: int| #![auto] 0 <= i < deltas@.len() ==>
+                !old(self).spec_is_locked(deltas@[i].spec_key()),
+            success ==> self.data@ == Self::spec_apply_deltas(old(self).data@, deltas@),
+            !success ==> self.data@ == old(self).data@,
+            !success ==> self.modified_by@ == old(self).modified_by@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        // Check every key first so a rejected changelog leaves no partial writes
+        let mut i: usize = 0;
+        while i < deltas.len()
+            invariant
+                0 <= i <= deltas.len(),
+                forall|j: int| #![auto] 0 <= j < i ==> !self.spec_is_locked(deltas@[j].spec_key()),
+            decreases
+                deltas.len() - i,
+        {
+            let key = match &deltas[i] {
+                KvDelta::Put(k, _) => k.as_str(),
+                KvDelta::Remove(k) => k.as_str(),
+            };
+            if self.locked.contains_key(key) {
+                return false;
+            }
+            i = i + 1;
+        }
+
+        let mut i: usize = 0;
+        while i < deltas.len()
+            invariant
+                !self.frozen,
+                0 <= i <= deltas.len(),
+                forall|j: int| #![auto] 0 <= j < deltas@.len() ==> !self.spec_is_locked(deltas@[j].spec_key()),
+                self.data@ == Self::spec_apply_deltas(old(self).data@, deltas@.subrange(0, i as int)),
+                self.locked@ == old(self).locked@,
+                self.last_seen_txn_id == old(self).last_seen_txn_id,
+                old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+                old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            decreases
+                deltas.len() - i,
+        {
+            proof {
+                assert(deltas@.subrange(0, i as int + 1).drop_last() =~= deltas@.subrange(0, i as int));
+            }
+            match &deltas[i] {
+                KvDelta::Put(k, v) => {
+                    self.put(k.as_str(), *v);
+                },
+                KvDelta::Remove(k) => {
+                    self.delete(k.as_str());
+                },
+            }
+            i = i + 1;
+        }
+        proof {
+            assert(deltas@.subrange(0, deltas@.len() as int) =~= deltas@);
+        }
+        true
+    }
+
+    /// Lock a key only if its current value matches `expected`
+    /// (`None` means the key must be absent), checked and locked in one step.
+    /// Returns true if the key was locked
+    pub fn lock_if_value(&mut self, key: &str, expected: Option<u64>) -> (locked: bool)
+        requires
+            !old(self).frozen,
+        ensures
+            locked == old(self).spec_value_matches(key@, expected),
+            // Value matches: key is now locked
+            locked ==> self.spec_is_locked(key@),
+            // Value differs (or presence differs): locks unchanged
+            !locked ==> self.locked@ == old(self).locked@,
+            // Absent case: locks exactly when the key does not exist
+            expected.is_none() ==> (locked == !old(self).spec_contains_key(key@)),
+            // Data unchanged
+            self.data@ == old(self).data@,
+            // Other locks unchanged
+            forall|k: Seq<char>| k != key@ ==>
+                (self.spec_is_locked(k) == old(self).spec_is_locked(k)),
+            // txn_id unchanged
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Write provenance unchanged
+            self.modified_by@ == old(self).modified_by@,
+            old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        let matches = match (self.get(key), expected) {
+            (Some(current), Some(v)) => current == v,
+            (None, None) => true,
+            _ => false,
+        };
+        if matches {
+            self.lock(key);
+        }
+        matches
+    }
+
+    /// Lock a key for `txn_id` with a lease: once `now` reaches `lease_until`,
+    /// `tick` releases the lock, so a vanished coordinator cannot wedge the key.
+    /// A lock already held by `txn_id` has its lease renewed; one held by
+    /// another txn is never taken over.
+    /// Returns false (and changes nothing) if `txn_id` is stale, the key is
+    /// locked by another txn or the store is frozen
+    pub fn lock_with_lease(&mut self, key: &str, txn_id: u64, lease_until: u64) -> (locked: bool)
+        ensures
+            locked == (!old(self).frozen && !old(self).spec_is_stale_txn_id(txn_id as nat)
+                && (!old(self).spec_is_locked(key@) || old(self).spec_lock_owner(key@) == txn_id)),
+            // Frozen: nothing changes
+            old(self).frozen ==> self@ == old(self)@,
+            locked ==> self.spec_is_locked(key@),
+            locked ==> self.spec_lock_owner(key@) == txn_id,
+            locked ==> self.leases@ == old(self).leases@.insert(key@, lease_until),
+            !locked ==> self.locked@ == old(self).locked@,
+            // Epoch advances on success
+            locked ==> self.epoch == old(self).spec_next_epoch(),
+            locked && old(self).epoch < u64::MAX ==> self.epoch > old(self).epoch,
+            !locked ==> self.epoch == old(self).epoch,
+            // Data unchanged
+            self.data@ == old(self).data@,
+            self.modified_by@ == old(self).modified_by@,
+            // Other locks unchanged
+            forall|k: Seq<char>| k != key@ ==>
+                (self.spec_is_locked(k) == old(self).spec_is_locked(k)),
+            old(self).spec_locks_backed() ==> self.spec_locks_backed(),
+            old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen || self.is_stale_txn_id(txn_id) {
+            return false;
+        }
+        if let Some(owner) = self.lock_owner(key) {
+            if owner != txn_id {
+                return false;
+            }
+        }
+        self.update_txn_id(txn_id);
+        if !self.locked.contains_key(key) {
+            self.bump_lock_gen(key);
+        }
+        self.locked.insert(key.to_owned(), txn_id);
+        self.record_lock(key);
+        self.leases.insert(key.to_owned(), lease_until);
+
+        let mut listed = false;
+        let mut i: usize = 0;
+        while i < self.leased_keys.len()
+            invariant
+                0 <= i <= self.leased_keys.len(),
+            decreases
+                self.leased_keys.len() - i,
+        {
+            if self.leased_keys[i].as_str() == key {
+                listed = true;
+            }
+            i = i + 1;
+        }
+        if !listed {
+            self.leased_keys.push(key.to_owned());
+        }
+        self.bump_epoch();
+        true
+    }
+
+    /// Number of held locks whose lease has expired at time `now`
+    pub fn expired_locks(&self, now: u64) -> (result: usize)
+        ensures
+            result <= self.leased_keys@.len(),
+    {
+        let mut count: usize = 0;
+        let mut i: usize = 0;
+        while i < self.leased_keys.len()
+            invariant
+                0 <= i <= self.leased_keys.len(),
+                count <= i,
+            decreases
+                self.leased_keys.len() - i,
+        {
+            let expired = match self.leases.get(self.leased_keys[i].as_str()) {
+                Some(until) => *until <= now,
+                None => false,
+            };
+            if expired {
+                count = count + 1;
+            }
+            i = i + 1;
+        }
+        count
+    }
+
+    /// Advance the lease clock to `now`, releasing every lock whose lease has
+    /// expired. Only expired-lease keys are unlocked; data is never touched.
+    /// Returns the number of locks released
+    pub fn tick(&mut self, now: u64) -> (released: usize)
+        requires
+            !old(self).frozen,
+        ensures
+            // Only expired leases are released
+            forall|k: Seq<char>| #![auto] old(self).spec_is_locked(k) && !self.spec_is_locked(k) ==>
+                old(self).leases@.contains_key(k) && old(self).leases@[k] <= now,
+            forall|k: Seq<char>| #![auto] self.spec_is_locked(k) ==> old(self).spec_is_locked(k),
+            // Epoch advances with each released lock
+            self.epoch >= old(self).epoch,
+            released > 0 && old(self).epoch < u64::MAX ==> self.epoch > old(self).epoch,
+            // Data unchanged
+            self.data@ == old(self).data@,
+            self.modified_by@ == old(self).modified_by@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            old(self).spec_locks_backed() ==> self.spec_locks_backed(),
+            old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        let mut released: usize = 0;
+        let mut remaining: Vec<String> = Vec::new();
+        let mut i: usize = 0;
+        while i < self.leased_keys.len()
+            invariant
+                !self.frozen,
+                0 <= i <= self.leased_keys.len(),
+                released <= i,
+                self.leased_keys@ == old(self).leased_keys@,
+                forall|k: Seq<char>| #![auto] self.leases@.contains_key(k) ==>
+                    old(self).leases@.contains_key(k) && self.leases@[k] == old(self).leases@[k],
+                forall|k: Seq<char>| #![auto] old(self).spec_is_locked(k) && !self.spec_is_locked(k) ==>
+                    old(self).leases@.contains_key(k) && old(self).leases@[k] <= now,
+                forall|k: Seq<char>| #![auto] self.spec_is_locked(k) ==> old(self).spec_is_locked(k),
+                self.epoch >= old(self).epoch,
+                released == 0 ==> self.epoch == old(self).epoch,
+                released > 0 && old(self).epoch < u64::MAX ==> self.epoch > old(self).epoch,
+                self.data@ == old(self).data@,
+                self.modified_by@ == old(self).modified_by@,
+                self.last_seen_txn_id == old(self).last_seen_txn_id,
+                self.lock_history@ == old(self).lock_history@,
+                old(self).spec_locks_backed() ==> self.spec_locks_backed(),
+                old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+                old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            decreases
+                self.leased_keys.len() - i,
+        {
+            let key = self.leased_keys[i].clone();
+            let expired = match self.leases.get(key.as_str()) {
+                Some(until) => *until <= now,
+                None => false,
+            };
+            if expired {
+                self.unlock(key.as_str());
+                released = released + 1;
+            } else if self.leases.contains_key(key.as_str()) {
+                remaining.push(key);
+            }
+            i = i + 1;
+        }
+        self.leased_keys = remaining;
+        released
+    }
+
+    /// Release every lock owned by `txn_id` (see `lock_owner`), leaving locks
+    /// held by other txns in place. Scans `lock_history`, so all of the txn's
+    /// locks go when the history is complete. Returns the number released
+    pub fn release_txn_locks(&mut self, txn_id: u64) -> (released: usize)
+        requires
+            !old(self).frozen,
+        ensures
+            released <= self.lock_history@.len(),
+            // Only txn_id's locks are released; nothing is locked or re-owned
+            forall|k: Seq<char>| #![auto] self.spec_is_locked(k) ==>
+                old(self).spec_is_locked(k) && self.spec_lock_owner(k) == old(self).spec_lock_owner(k)
+                && old(self).spec_lock_owner(k) != txn_id,
+            forall|k: Seq<char>| #![auto] old(self).spec_is_locked(k) && old(self).spec_lock_owner(k) != txn_id ==>
+                self.spec_is_locked(k),
+            // With a complete history, every lock txn_id held is released
+            old(self).spec_lock_history_complete() ==>
+                forall|k: Seq<char>| #![auto] old(self).spec_is_locked(k) && old(self).spec_lock_owner(k) == txn_id ==>
+                    !self.spec_is_locked(k),
+            // Epoch advances with each released lock
+            self.epoch >= old(self).epoch,
+            released > 0 && old(self).epoch < u64::MAX ==> self.epoch > old(self).epoch,
+            // Data unchanged
+            self.data@ == old(self).data@,
+            self.modified_by@ == old(self).modified_by@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.lock_history@ == old(self).lock_history@,
+            old(self).spec_locks_backed() ==> self.spec_locks_backed(),
+            old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        let mut released: usize = 0;
+        let mut i: usize = 0;
+        while i < self.lock_history.len()
+            invariant
+                !self.frozen,
+                0 <= i <= self.lock_history.len(),
+                released <= i,
+                self.lock_history@ == old(self).lock_history@,
+                forall|k: Seq<char>| #![auto] self.spec_is_locked(k) ==>
+                    old(self).spec_is_locked(k) && self.spec_lock_owner(k) == old(self).spec_lock_owner(k),
+                forall|k: Seq<char>| #![auto] old(self).spec_is_locked(k) && old(self).spec_lock_owner(k) != txn_id ==>
+                    self.spec_is_locked(k),
+                forall|j: int| #![auto] 0 <= j < i && self.spec_is_locked(self.lock_history@[j]@) ==>
+                    self.spec_lock_owner(self.lock_history@[j]@) != txn_id,
+                self.epoch >= old(self).epoch,
+                released == 0 ==> self.epoch == old(self).epoch,
+                released > 0 && old(self).epoch < u64::MAX ==> self.epoch > old(self).epoch,
+                self.data@ == old(self).data@,
+                self.modified_by@ == old(self).modified_by@,
+                self.last_seen_txn_id == old(self).last_seen_txn_id,
+                old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+                old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+                old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            decreases
+                self.lock_history.len() - i,
+        {
+            let key = self.lock_history[i].clone();
+            let owned = match self.locked.get(key.as_str()) {
+                Some(owner) => *owner == txn_id,
+                None => false,
+            };
+            if owned {
+                self.unlock(key.as_str());
+                released = released + 1;
+            }
+            i = i + 1;
+        }
+        proof {
+            if old(self).spec_lock_history_complete() {
+                assert forall|k: Seq<char>| #![auto] old(self).spec_is_locked(k) && old(self).spec_lock_owner(k) == txn_id
+                    implies !self.spec_is_locked(k) by {
+                    let j = choose|j: int| 0 <= j < old(self).lock_history@.len() && old(self).lock_history@[j]@ == k;
+                    assert(self.lock_history@[j]@ == k);
+                }
+            }
+        }
+        released
+    }
+
+    /// Lock every key in `keys`, reporting per key whether this call newly
+    /// locked it (true) or found it already locked (false). A repeated key is
+    /// only reported as newly locked at its first occurrence. Lets a caller
+    /// unlock exactly the locks it acquired
+    pub fn lock_all_reporting(&mut self, keys: &[String]) -> (result: Vec<bool>)
+        requires
+            !old(self).frozen,
+        ensures
+            result@.len() == keys@.len(),
+            forall|i: int| #![auto] 0 <= i < keys@.len() ==>
+                result@[i] == (!old(self).spec_is_locked(keys@[i]@)
+                    && forall|j: int| #![auto] 0 <= j < i ==> keys@[j]@ != keys@[i]@),
+            forall|i: int| #![auto] 0 <= i < keys@.len() ==> self.spec_is_locked(keys@[i]@),
+            forall|k: Seq<char>| (forall|i: int| #![auto] 0 <= i < keys@.len() ==> keys@[i]@ != k) ==>
+                self.spec_is_locked(k) == old(self).spec_is_locked(k),
+            self.data@ == old(self).data@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.modified_by@ == old(self).modified_by@,
+            old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        let mut result: Vec<bool> = Vec::new();
+        let mut i: usize = 0;
+        while i < keys.len()
+            invariant
+                !self.frozen,
+                0 <= i <= keys@.len(),
+                result@.len() == i,
+                forall|k: Seq<char>| self.spec_is_locked(k) == (old(self).spec_is_locked(k)
+                    || exists|j: int| 0 <= j < i && #[trigger] keys@[j]@ == k),
+                forall|j: int| #![auto] 0 <= j < i ==>
+                    result@[j] == (!old(self).spec_is_locked(keys@[j]@)
+                        && forall|l: int| #![auto] 0 <= l < j ==> keys@[l]@ != keys@[j]@),
+                self.data@ == old(self).data@,
+                self.last_seen_txn_id == old(self).last_seen_txn_id,
+                self.modified_by@ == old(self).modified_by@,
+                old(self).spec_lock_history_complete() ==> self.spec_lock_history_complete(),
+                old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+                old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            decreases
+                keys.len() - i,
+        {
+            let key = keys[i].as_str();
+            let was_locked = self.is_locked(key);
+            self.lock(key);
+            result.push(!was_locked);
+            proof {
+                assert(keys@[i as int]@ == key@);
+            }
+            i = i + 1;
+        }
+        result
+    }
+
+    /// Lock `keys`, run `f`, then unlock them again (scoped lock/operate/unlock).
+    /// Only keys this call locked are unlocked afterwards; keys that were already
+    /// locked stay locked. Closures are outside what Verus verifies here, so this
+    /// is trusted (external_body) and composed from the verified lock/unlock.
+    #[verifier::external_body]
+    pub fn with_locks<F, R>(&mut self, keys: &[String], f: F) -> R
+        where F: FnOnce(&mut KvStore) -> R
+    {
+        let mut newly_locked: Vec<&String> = Vec::new();
+        for key in keys {
+            if !self.is_locked(key) {
+                self.lock(key);
+                newly_locked.push(key);
+            }
+        }
+        let result = f(self);
+        for key in newly_locked {
+            self.unlock(key);
+        }
+        result
+    }
+
+    /// Clear all data (fails if any key is locked or the store is frozen)
+    /// Returns true if successful, false if some key is locked or the store frozen
+    pub fn clear(&mut self) -> (success: bool)
+        ensures
+            success == (!old(self).frozen && !old(self).spec_any_locked()),
+            // Frozen: nothing changes
+            old(self).frozen ==> self@ == old(self)@,
+            // If any key locked, state unchanged
+            !success ==> self.data@ == old(self).data@,
+            // If no key locked, data is emptied
+            success ==> self.data@ == Map::<Seq<char>, u64>::empty(),
+            // Locks unchanged (and empty on success)
+            self.locked@ == old(self).locked@,
+            success ==> forall|k: Seq<char>| !self.spec_is_locked(k),
+            // txn_id unchanged
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Write provenance unchanged
+            self.modified_by@ == old(self).modified_by@,
+            // Epoch advances on success
+            success ==> self.epoch == old(self).spec_next_epoch(),
+            success && old(self).epoch < u64::MAX ==> self.epoch > old(self).epoch,
+            !success ==> self.epoch == old(self).epoch,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen {
+            return false;
+        }
+        if !self.locked.is_empty() {
+            proof {
+                // A non-empty lock map has a locked key witness
+                assert(!(self.locked@.dom() =~= Set::<Seq<char>>::empty()));
+                let k = choose|k: Seq<char>| self.locked@.dom().contains(k);
+                assert(self.spec_is_locked(k));
+            }
+            return false;
+        }
+        proof {
+            assert(self.locked@.dom() =~= Set::<Seq<char>>::empty());
+            assert(forall|k: Seq<char>| !self.spec_is_locked(k));
+        }
+        self.data.clear();
+        self.bump_epoch();
+        true
+    }
+
+    /// All-or-nothing batch write under lock.
+    /// Applies every put only if every key in `entries` is locked (i.e. held by
+    /// the in-flight transaction); otherwise changes nothing and returns false.
+    /// If a key repeats, its last entry wins. A frozen store rejects the batch.
+    pub fn multi_put_locked(&mut self, entries: Vec<(String, u64)>) -> (success: bool)
+        ensures
+            success == (!old(self).frozen && forall|i: int| #![auto] 0 <= i < entries@.len() ==>
+                old(self).spec_is_locked(entries@[i].0@)),
+            // Frozen: nothing changes
+            old(self).frozen ==> self@ == old(self)@,
+            // Rejected: nothing changes
+            !success ==> self.data@ == old(self).data@,
+            !success ==> self.modified_by@ == old(self).modified_by@,
+            // Applied: every key is written, the last entry for a key wins
+            success ==> forall|i: int| #![auto] 0 <= i < entries@.len() ==>
+                self.spec_contains_key(entries@[i].0@)
+                && self.modified_by@[entries@[i].0@] == old(self).last_seen_txn_id,
+            success ==> forall|i: int| #![auto] 0 <= i < entries@.len()
+                && (forall|j: int| #![auto] i < j < entries@.len() ==> entries@[j].0@ != entries@[i].0@)
+                ==> self.spec_get(entries@[i].0@) == entries@[i].1,
+            // Applied: keys outside the batch are untouched
+            success ==> forall|k: Seq<char>|
+                (forall|i: int| #![auto] 0 <= i < entries@.len() ==> entries@[i].0@ != k) ==> (
+                    self.data@.contains_key(k) == old(self).data@.contains_key(k)
+                    && (self.data@.contains_key(k) ==> self.data@[k] == old(self).data@[k])
+                ),
+            // Locks and txn_id unchanged
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            // Epoch advances on success
+            success ==> self.epoch == old(self).spec_next_epoch(),
+            success && old(self).epoch < u64::MAX ==> self.epoch > old(self).epoch,
+            !success ==> self.epoch == old(self).epoch,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen {
+            return false;
+        }
+        // Phase 1: every key must already be locked
+        let mut i: usize = 0;
+        while i < entries.len()
+            invariant
+                0 <= i <= entries.len(),
+                forall|j: int| #![auto] 0 <= j < i ==> self.spec_is_locked(entries@[j].0@),
+            decreases
+                entries.len() - i,
+        {
+            if !self.locked.contains_key(&entries[i].0) {
+                return false;
+            }
+            i = i + 1;
+        }
+
+        // Phase 2: apply every put
+        let mut i: usize = 0;
+        while i < entries.len()
+            invariant
+                0 <= i <= entries.len(),
+                self.locked@ == old(self).locked@,
+                self.last_seen_txn_id == old(self).last_seen_txn_id,
+                self.epoch == old(self).epoch,
+                old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+                old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+                forall|j: int| #![auto] 0 <= j < entries@.len() ==> self.spec_is_locked(entries@[j].0@),
+                forall|j: int| #![auto] 0 <= j < i ==>
+                    self.spec_contains_key(entries@[j].0@)
+                    && self.modified_by@[entries@[j].0@] == old(self).last_seen_txn_id,
+                forall|j: int| #![auto] 0 <= j < i
+                    && (forall|l: int| #![auto] j < l < i ==> entries@[l].0@ != entries@[j].0@)
+                    ==> self.spec_get(entries@[j].0@) == entries@[j].1,
+                forall|k: Seq<char>|
+                    (forall|j: int| #![auto] 0 <= j < i ==> entries@[j].0@ != k) ==> (
+                        self.data@.contains_key(k) == old(self).data@.contains_key(k)
+                        && (self.data@.contains_key(k) ==> self.data@[k] == old(self).data@[k])
+                    ),
+            decreases
+                entries.len() - i,
+        {
+            let key = entries[i].0.clone();
+            let value = entries[i].1;
+            self.modified_by.insert(key.clone(), self.last_seen_txn_id);
+            self.record_key(key.as_str());
+            self.data.insert(key, value);
+            i = i + 1;
+        }
+        self.bump_epoch();
+        true
+    }
+
+    /// Delete under lock: removes `key` only if it is locked (i.e. held by the
+    /// in-flight transaction) and the store is not frozen; otherwise changes
+    /// nothing and returns false. The counterpart of multi_put_locked
+    pub fn delete_locked(&mut self, key: &str) -> (success: bool)
+        ensures
+            success == (!old(self).frozen && old(self).spec_is_locked(key@)),
+            success ==> self.data@ == old(self).data@.remove(key@),
+            !success ==> self.data@ == old(self).data@,
+            !success ==> self.epoch == old(self).epoch,
+            success ==> self.epoch == old(self).spec_next_epoch(),
+            self.modified_by@ == old(self).modified_by@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen || !self.locked.contains_key(key) {
+            false
+        } else {
+            self.data.remove(key);
+            self.bump_epoch();
+            self.log_op(KvOp::Delete { key: key.to_owned(), txn_id: self.last_seen_txn_id });
+            true
+        }
+    }
+
+    /// Independent copy of the store, e.g. to pass to `rollback_to` later.
+    /// The copy starts with an empty operation log.
+    /// Maps are copied through the key lists that index them, which list
+    /// every entry of a store built by this API (see `spec_key_index_wf`,
+    /// `spec_lock_history_complete` and `spec_maps_indexed`)
+    pub fn snapshot(&self) -> (result: KvStore)
+        ensures
+            result.last_seen_txn_id == self.last_seen_txn_id,
+            result.prepared_txn == self.prepared_txn,
+            result.released_txn == self.released_txn,
+            result.frozen == self.frozen,
+            result.epoch == self.epoch,
+            result.op_log@.len() == 0,
+            result.key_index@.len() == self.key_index@.len(),
+            forall|i: int| 0 <= i < self.key_index@.len() ==> #[trigger] result.key_index@[i]@ == self.key_index@[i]@,
+            result.lock_history@.len() == self.lock_history@.len(),
+            forall|i: int| 0 <= i < self.lock_history@.len() ==>
+                #[trigger] result.lock_history@[i]@ == self.lock_history@[i]@,
+            result.leased_keys@.len() == self.leased_keys@.len(),
+            forall|i: int| 0 <= i < self.leased_keys@.len() ==>
+                #[trigger] result.leased_keys@[i]@ == self.leased_keys@[i]@,
+            result.lock_gen_keys@.len() == self.lock_gen_keys@.len(),
+            forall|i: int| 0 <= i < self.lock_gen_keys@.len() ==>
+                #[trigger] result.lock_gen_keys@[i]@ == self.lock_gen_keys@[i]@,
+            self.spec_key_index_wf() ==> result@.data == self@.data && result.spec_key_index_wf(),
+            self.spec_lock_history_complete() ==>
+                result@.locked_keys == self@.locked_keys && result.spec_lock_history_complete(),
+            self.spec_key_index_wf() ==> result.data@ == self.data@,
+            self.spec_lock_history_complete() ==> result.locked@ == self.locked@,
+            self.spec_maps_indexed() ==> {
+                &&& result.modified_by@ == self.modified_by@
+                &&& result.leases@ == self.leases@
+                &&& result.lock_gen@ == self.lock_gen@
+                &&& result.spec_maps_indexed()
+            },
+            self.spec_key_index_wf() && self.spec_lock_history_complete() && self.spec_maps_indexed()
+                ==> result@ == self@,
+    {
+        let key_index = clone_keys(&self.key_index);
+        let lock_history = clone_keys(&self.lock_history);
+        let leased_keys = clone_keys(&self.leased_keys);
+        let lock_gen_keys = clone_keys(&self.lock_gen_keys);
+
+        let mut data = KeyMap::new();
+        copy_entries(&self.data, &self.key_index, &mut data);
+        let mut locked = KeyMap::new();
+        copy_entries(&self.locked, &self.lock_history, &mut locked);
+        let mut modified_by = KeyMap::new();
+        copy_entries(&self.modified_by, &self.key_index, &mut modified_by);
+        let mut leases = KeyMap::new();
+        copy_entries(&self.leases, &self.leased_keys, &mut leases);
+        let mut lock_gen = KeyMap::new();
+        copy_entries(&self.lock_gen, &self.lock_gen_keys, &mut lock_gen);
+
+        let result = KvStore {
+            data,
+            locked,
+            last_seen_txn_id: self.last_seen_txn_id,
+            prepared_txn: self.prepared_txn,
+            released_txn: self.released_txn,
+            modified_by,
+            leases,
+            leased_keys,
+            lock_history,
+            key_index,
+            lock_gen,
+            lock_gen_keys,
+            frozen: self.frozen,
+            epoch: self.epoch,
+            op_log: Vec::new(),
+        };
+        proof {
+            if self.spec_key_index_wf() {
+                assert(result.data@ =~= self.data@);
+            }
+            if self.spec_lock_history_complete() {
+                assert(result.locked@ =~= self.locked@);
+                assert(result@.locked_keys =~= self@.locked_keys);
+            }
+            if self.spec_maps_indexed() {
+                assert(result.modified_by@ =~= self.modified_by@);
+                assert(result.leases@ =~= self.leases@);
+                assert(result.lock_gen@ =~= self.lock_gen@);
+            }
+            if self.spec_key_index_wf() && self.spec_lock_history_complete() && self.spec_maps_indexed() {
+                assert(result@ =~= self@);
+            }
+        }
+        result
+    }
+
+    /// Roll data back to a previously saved copy of the store (see `snapshot`),
+    /// undoing speculative writes. The snapshot is left intact, so it can be
+    /// rolled back to again.
+    /// Locks, the last seen txn_id and the prepared vote are kept: txn ids are
+    /// monotonic and the lock table belongs to whatever txn is in flight.
+    /// Returns false, changing nothing, if the store is frozen
+    pub fn rollback_to(&mut self, snapshot: &KvStore) -> (success: bool)
+        ensures
+            success == !old(self).frozen,
+            // Frozen: nothing changes
+            old(self).frozen ==> self@ == old(self)@,
+            success && snapshot.spec_key_index_wf() ==> self@.data == snapshot@.data && self.spec_key_index_wf(),
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.prepared_txn == old(self).prepared_txn,
+            // Epoch advances on success
+            success ==> self.epoch == old(self).spec_next_epoch(),
+            success && old(self).epoch < u64::MAX ==> self.epoch > old(self).epoch,
+            !success ==> self.epoch == old(self).epoch,
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen {
+            return false;
+        }
+        let copy = snapshot.snapshot();
+        self.data = copy.data;
+        self.modified_by = copy.modified_by;
+        self.key_index = copy.key_index;
+        self.bump_epoch();
+        true
+    }
+
+    /// Rename as `rename`, also reporting the observed state change: the moved
+    /// value, whether `old_key` existed before and whether `new_key` exists after
+    pub fn rename_checked(&mut self, old_key: &str, new_key: &str) -> (report: RenameProof)
+        requires
+            old(self).spec_is_locked(old_key@),
+            old(self).spec_is_locked(new_key@),
+            old_key@ != new_key@,
+        ensures
+            report.old_was_present == old(self).spec_contains_key(old_key@),
+            report.new_is_present == self.spec_contains_key(new_key@),
+            // A frozen store moves nothing
+            report.moved.is_some() == (report.old_was_present && !old(self).frozen),
+            report.moved.is_some() ==> report.moved == Some(old(self).spec_get(old_key@)),
+            report.moved.is_some() ==> self.spec_get(new_key@) == old(self).spec_get(old_key@),
+            report.moved.is_some() ==> !self.spec_contains_key(old_key@),
+            report.moved.is_none() ==> self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        let old_was_present = self.contains_key(old_key);
+        let moved = self.rename(old_key, new_key);
+        let new_is_present = self.contains_key(new_key);
+        RenameProof { moved, old_was_present, new_is_present }
+    }
+
+    /// Checked rename for callers outside the protocol: the lock precondition of
+    /// `rename` becomes a runtime check. Renames only when the store is not
+    /// frozen, both keys are locked and old_key exists; otherwise reports why
+    /// and changes nothing
+    pub fn try_rename(&mut self, old_key: &str, new_key: &str) -> (result: RenameResult)
+        ensures
+            result == RenameResult::Frozen <==> old(self).frozen,
+            result == RenameResult::NotLocked <==> !old(self).frozen
+                && !(old(self).spec_is_locked(old_key@) && old(self).spec_is_locked(new_key@)),
+            result == RenameResult::SourceAbsent ==> !old(self).spec_contains_key(old_key@),
+            // No change unless the rename happened
+            !(result is Done) ==> self.data@ == old(self).data@,
+            !(result is Done) ==> self.modified_by@ == old(self).modified_by@,
+            // Renamed: the value moves from old_key to new_key
+            result matches RenameResult::Done(v) ==> {
+                &&& old(self).spec_contains_key(old_key@)
+                &&& v == old(self).spec_get(old_key@)
+                &&& self.data@ == old(self).data@.remove(old_key@).insert(new_key@, v)
+                &&& self.modified_by@ == old(self).modified_by@.insert(new_key@, old(self).last_seen_txn_id)
+            },
+            // Locks unchanged
+            self.locked@ == old(self).locked@,
+            // txn_id unchanged
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        if self.frozen {
+            return RenameResult::Frozen;
+        }
+        if !(self.locked.contains_key(old_key) && self.locked.contains_key(new_key)) {
+            return RenameResult::NotLocked;
+        }
+        match self.get(old_key) {
+            Some(value) => {
+                self.data.remove(old_key);
+                self.data.insert(new_key.to_owned(), value);
+                self.modified_by.insert(new_key.to_owned(), self.last_seen_txn_id);
+                self.record_key(new_key);
+                RenameResult::Done(value)
+            }
+            None => RenameResult::SourceAbsent,
+        }
+    }
+
+    /// Rename many keys at once: moves each source's value to its destination.
+    /// All-or-nothing: returns false and changes nothing if the mapping overlaps
+    /// (see `spec_mapping_disjoint`) or some source key does not exist.
+    /// Precondition: every source and destination is locked
+    pub fn rekey(&mut self, mapping: Vec<(String, String)>) -> (success: bool)
+        requires
+            forall|i: int| #![auto] 0 <= i < mapping@.len() ==>
+                old(self).spec_is_locked(mapping@[i].0@) && old(self).spec_is_locked(mapping@[i].1@),
+        ensures
+            success == (Self::spec_mapping_disjoint(mapping@)
+                && forall|i: int| #![auto] 0 <= i < mapping@.len() ==>
+                    old(self).spec_contains_key(mapping@[i].0@)),
+            // Rejected: nothing changes
+            !success ==> self.data@ == old(self).data@,
+            !success ==> self.modified_by@ == old(self).modified_by@,
+            // Applied: each destination holds its source's old value; sources are gone
+            success ==> forall|i: int| #![auto] 0 <= i < mapping@.len() ==>
+                self.spec_contains_key(mapping@[i].1@)
+                && self.spec_get(mapping@[i].1@) == old(self).spec_get(mapping@[i].0@)
+                && !self.spec_contains_key(mapping@[i].0@),
+            // Applied: keys outside the mapping are untouched
+            success ==> forall|k: Seq<char>|
+                (forall|i: int| #![auto] 0 <= i < mapping@.len() ==>
+                    mapping@[i].0@ != k && mapping@[i].1@ != k) ==> (
+                    self.data@.contains_key(k) == old(self).data@.contains_key(k)
+                    && (self.data@.contains_key(k) ==> self.data@[k] == old(self).data@[k])
+                ),
+            // Locks and txn_id unchanged
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
+    {
+        // Phase 1: reject overlapping mappings and missing sources
+        let mut i: usize = 0;
+        while i < mapping.len()
+            invariant
+                0 <= i <= mapping.len(),
+                forall|a: int, b: int| 0 <= a < i && 0 <= b < mapping@.len() ==> {
+                    &&& mapping@[a].0@ != mapping@[b].1@
+                    &&& a != b ==> mapping@[a].0@ != mapping@[b].0@ && mapping@[a].1@ != mapping@[b].1@
+                },
+                forall|a: int| #![auto] 0 <= a < i ==> self.spec_contains_key(mapping@[a].0@),
+            decreases
+                mapping.len() - i,
+        {
+            if !self.data.contains_key(mapping[i].0.as_str()) {
+                return false;
+            }
+            let mut j: usize = 0;
+            while j < mapping.len()
+                invariant
+                    0 <= i < mapping.len(),
+                    0 <= j <= mapping.len(),
+                    forall|b: int| 0 <= b < j ==> {
+                        &&& mapping@[i as int].0@ != mapping@[b].1@
+                        &&& i != b ==> mapping@[i as int].0@ != mapping@[b].0@
+                            && mapping@[i as int].1@ != mapping@[b].1@
+                    },
+                decreases
+                    mapping.len() - j,
+            {
+                if mapping[i].0.as_str() == mapping[j].1.as_str() {
+                    return false;
+                }
+                if i != j && (mapping[i].0.as_str() == mapping[j].0.as_str()
+                    || mapping[i].1.as_str() == mapping[j].1.as_str()) {
+                    return false;
+                }
+                j = j + 1;
+            }
+            i = i + 1;
+        }
+
+        // Phase 2: move each value; disjointness keeps the moves independent
+        let mut i: usize = 0;
+        while i < mapping.len()
+            invariant
+                0 <= i <= mapping.len(),
+                Self::spec_mapping_disjoint(mapping@),
+                self.locked@ == old(self).locked@,
+                self.last_seen_txn_id == old(self).last_seen_txn_id,
+                old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+                old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+                forall|a: int| #![auto] 0 <= a < i ==>
+                    self.spec_contains_key(mapping@[a].1@)
+                    && self.spec_get(mapping@[a].1@) == old(self).spec_get(mapping@[a].0@)
+                    && !self.spec_contains_key(mapping@[a].0@),
+                forall|a: int| #![auto] i <= a < mapping@.len() ==>
+                    self.spec_contains_key(mapping@[a].0@)
+                    && self.spec_get(mapping@[a].0@) == old(self).spec_get(mapping@[a].0@),
+                forall|k: Seq<char>|
+                    (forall|a: int| #![auto] 0 <= a < mapping@.len() ==>
+                        mapping@[a].0@ != k && mapping@[a].1@ != k) ==> (
+                        self.data@.contains_key(k) == old(self).data@.contains_key(k)
+                        && (self.data@.contains_key(k) ==> self.data@[k] == old(self).data@[k])
+                    ),
+            decreases
+                mapping.len() - i,
+        {
+            let src = mapping[i].0.as_str();
+            let dst = mapping[i].1.as_str();
+            if let Some(value) = self.get(src) {
+                self.data.remove(src);
+                self.data.insert(dst.to_owned(), value);
+                self.modified_by.insert(dst.to_owned(), self.last_seen_txn_id);
+                self.record_key(dst);
+            }
+            i = i + 1;
+        }
+        true
+    }
+
+    /// The data map after renaming each pair in order, or None if some source
+    /// is missing when its turn comes
+    pub open spec fn spec_rename_seq(data: Map<Seq<char>, u64>, pairs: Seq<(String, String)>) -> Option<Map<Seq<char>, u64>>
+        decreases pairs.len()
+    {
+        if pairs.len() == 0 {
+            Some(data)
+        } else {
+            match Self::spec_rename_seq(data, pairs.drop_last()) {
+                Some(prev) => {
+                    let (src, dst) = pairs.last();
+                    if prev.contains_key(src@) {
+                        Some(prev.remove(src@).insert(dst@, prev[src@]))
+                    } else {
+                        None
+                    }
+                },
+                None => None,
+            }
+        }
+    }
+
+    /// Once a prefix of the pairs fails, the whole sequence fails
+    pub proof fn lemma_rename_seq_none_extends(data: Map<Seq<char>, u64>, pairs: Seq<(String, String)>, n: int)
+        requires
+            0 <= n <= pairs.len(),
+            Self::spec_rename_seq(data, pairs.subrange(0, n)).is_none(),
+        ensures
+            Self::spec_rename_seq(data, pairs).is_none(),
+        decreases pairs.len() - n
+    {
+        if n == pairs.len() {
+            assert(pairs.subrange(0, n) =~= pairs);
+        } else {
+            assert(pairs.subrange(0, n + 1).drop_last() =~= pairs.subrange(0, n));
+            Self::lemma_rename_seq_none_extends(data, pairs, n + 1);
+        }
+    }
+
+    /// Rename each pair in order, all-or-nothing: if some source is missing
+    /// when its turn comes (see `spec_rename_seq`), the renames already made
+    /// are rolled back and false is returned with the data unchanged.
+    /// Unlike `rekey`, pairs may chain (A->B then B->C).
+    /// A frozen store renames nothing and returns false.
+    /// Precondition: every source and destination is locked
+    pub fn rename_atomic(&mut self, pairs: Vec<(String, String)>) -> (success: bool)
+        requires
+            old(self).spec_key_index_wf(),
+            forall|i: int| #![auto] 0 <= i < pairs@.len() ==>
+                old(self).spec_is_locked(pairs@[i].0@) && old(self).spec_is_locked(pairs@[i].1@),
+        ensures
+            success == (!old(self).frozen && Self::spec_rename_seq(old(self).data@, pairs@).is_some()),
+            success ==> self.data@ == Self::spec_rename_seq(old(self).data@, pairs@).unwrap(),
+            !success ==> self.data@ == old(self).data@,
+            self.locked@ == old(self).locked@,
+            self.last_seen_txn_id == old(self).last_seen_txn_id,
+            self.spec_key_index_wf(),
+            self.frozen == old(self).frozen,
     {
-        self.locked.remove(key);
+        if self.frozen {
+            return false;
+        }
+        // Saved copy to roll back to; renames can chain, so sources are only
+        // known to exist once the earlier renames have run
+        let saved = self.snapshot();
+        let mut i: usize = 0;
+        while i < pairs.len()
+            invariant
+                0 <= i <= pairs.len(),
+                saved@.data == old(self)@.data,
+                saved.spec_key_index_wf(),
+                Self::spec_rename_seq(old(self).data@, pairs@.subrange(0, i as int)) == Some(self.data@),
+                self.locked@ == old(self).locked@,
+                self.last_seen_txn_id == old(self).last_seen_txn_id,
+                self.spec_key_index_wf(),
+                !self.frozen,
+                forall|j: int| #![auto] 0 <= j < pairs@.len() ==>
+                    self.spec_is_locked(pairs@[j].0@) && self.spec_is_locked(pairs@[j].1@),
+            decreases
+                pairs.len() - i,
+        {
+            proof {
+                assert(pairs@.subrange(0, i as int + 1).drop_last() =~= pairs@.subrange(0, i as int));
+                assert(pairs@.subrange(0, i as int + 1).last() == pairs@[i as int]);
+            }
+            match self.try_rename(pairs[i].0.as_str(), pairs[i].1.as_str()) {
+                RenameResult::Done(_) => {},
+                _ => {
+                    proof {
+                        Self::lemma_rename_seq_none_extends(old(self).data@, pairs@, i as int + 1);
+                    }
+                    self.rollback_to(&saved);
+                    return false;
+                },
+            }
+            i = i + 1;
+        }
+        proof {
+            assert(pairs@.subrange(0, pairs@.len() as int) =~= pairs@);
+        }
+        true
     }
 
-    /// Rename: move value from old_key to new_key
+    /// Rename allowing the destination to pre-exist
     /// Precondition: both keys must be locked and different
-    /// Returns the value that was moved, or None if old_key doesn't exist
-    pub fn rename(&mut self, old_key: &str, new_key: &str) -> (result: Option<u64>)
+    /// Returns the value previously at new_key if it was overwritten, otherwise the moved value;
+    /// returns None (and changes nothing) if old_key doesn't exist
+    pub fn rename_overwrite(&mut self, old_key: &str, new_key: &str) -> (result: Option<u64>)
         requires
             old(self).spec_is_locked(old_key@),
             old(self).spec_is_locked(new_key@),
@@ -247,31 +3304,159 @@ impl KvStore {
             self.locked@ == old(self).locked@,
             // Result matches whether old_key existed
             result.is_some() == old(self).spec_contains_key(old_key@),
-            // If succeeded, the value is correct
-            result.is_some() ==> result == Some(old(self).spec_get(old_key@)),
-            // If succeeded, new_key now has the value
-            result.is_some() ==> self.spec_contains_key(new_key@),
-            result.is_some() ==> self.spec_get(new_key@) == old(self).spec_get(old_key@),
-            // If succeeded, old_key is removed
-            result.is_some() ==> !self.spec_contains_key(old_key@),
-            // If failed, data unchanged
-            result.is_none() ==> self.data@ == old(self).data@,
+            // Source present: value moved, destination overwritten
+            old(self).spec_contains_key(old_key@) ==> self.data@ == old(self).data@
+                .remove(old_key@)
+                .insert(new_key@, old(self).spec_get(old_key@)),
+            // Source present, destination present: overwritten value returned
+            old(self).spec_contains_key(old_key@) && old(self).spec_contains_key(new_key@)
+                ==> result == Some(old(self).spec_get(new_key@)),
+            // Source present, destination absent: moved value returned
+            old(self).spec_contains_key(old_key@) && !old(self).spec_contains_key(new_key@)
+                ==> result == Some(old(self).spec_get(old_key@)),
+            // Source absent: data unchanged
+            !old(self).spec_contains_key(old_key@) ==> self.data@ == old(self).data@,
+            // Provenance: new_key is attributed to the last seen txn when a value moved
+            old(self).spec_contains_key(old_key@) ==> self.modified_by@ == old(self).modified_by@.insert(new_key@, old(self).last_seen_txn_id),
+            !old(self).spec_contains_key(old_key@) ==> self.modified_by@ == old(self).modified_by@,
             // txn_id unchanged
             self.last_seen_txn_id == old(self).last_seen_txn_id,
+            old(self).spec_key_index_wf() ==> self.spec_key_index_wf(),
+            old(self).spec_maps_indexed() ==> self.spec_maps_indexed(),
+            self.frozen == old(self).frozen,
     {
         match self.data.get(old_key) {
             Some(v) => {
                 let value = *v;
-                let new_key_owned = new_key.to_owned();
+                let overwritten = self.get(new_key);
                 self.data.remove(old_key);
-                self.data.insert(new_key_owned, value);
-                Some(value)
+                self.data.insert(new_key.to_owned(), value);
+                self.modified_by.insert(new_key.to_owned(), self.last_seen_txn_id);
+                self.record_key(new_key);
+                Some(overwritten.unwrap_or(value))
             }
             None => None,
         }
     }
 }
 
+} // verus!
+
+// ============================================================
+// JSON FIXTURES (plain Rust, outside verus!)
+// ============================================================
+
+/// `s` as a JSON string literal
+fn json_quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Parse `{"key": u64, ...}` into its entries, in document order
+fn parse_json_object(s: &str) -> Option<Vec<(String, u64)>> {
+    let mut chars = s.trim().chars().peekable();
+    let mut entries = Vec::new();
+    if chars.next()? != '{' {
+        return None;
+    }
+    skip_json_ws(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            skip_json_ws(&mut chars);
+            let key = parse_json_string(&mut chars)?;
+            skip_json_ws(&mut chars);
+            if chars.next()? != ':' {
+                return None;
+            }
+            skip_json_ws(&mut chars);
+            let value = parse_json_u64(&mut chars)?;
+            entries.push((key, value));
+            skip_json_ws(&mut chars);
+            match chars.next()? {
+                ',' => continue,
+                '}' => break,
+                _ => return None,
+            }
+        }
+    }
+    // Nothing may follow the object
+    match chars.next() {
+        None => Some(entries),
+        Some(_) => None,
+    }
+}
+
+type JsonChars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn skip_json_ws(chars: &mut JsonChars) {
+    while matches!(chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+        chars.next();
+    }
+}
+
+fn parse_json_string(chars: &mut JsonChars) -> Option<String> {
+    if chars.next()? != '"' {
+        return None;
+    }
+    let mut out = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(out),
+            '\\' => out.push(match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                '/' => '/',
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => {
+                    let hex: String = (0..4).map(|_| chars.next()).collect::<Option<String>>()?;
+                    char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                },
+                _ => return None,
+            }),
+            c if (c as u32) < 0x20 => return None,
+            c => out.push(c),
+        }
+    }
+}
+
+/// A non-negative integer without sign, fraction or exponent, within u64
+fn parse_json_u64(chars: &mut JsonChars) -> Option<u64> {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(c);
+        chars.next();
+    }
+    // JSON forbids leading zeros
+    if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+        return None;
+    }
+    if matches!(chars.peek(), Some('.' | 'e' | 'E')) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+verus! {
+
 // ============================================================
 // UNIT TESTS
 // ============================================================
@@ -287,6 +3472,16 @@ mod tests {
         assert(!store.is_locked("any_key"));
     }
 
+    /// Test: A pre-sized store starts empty, like `new()`
+    fn test_with_capacity() {
+        let mut store = KvStore::with_capacity(1000);
+        assert(!store.contains_key("any_key"));
+        assert(!store.is_locked("any_key"));
+        assert(store.get_last_seen_txn_id() == 0);
+        assert(store.put("key1", 42));
+        assert(store.get("key1") == Some(42u64));
+    }
+
     /// Test: Put and get
     fn test_put_get() {
         let mut store = KvStore::new();
@@ -361,6 +3556,47 @@ mod tests {
         assert(store.get("key1") == Some(20u64));
     }
 
+    /// Test: prepare_vote for each outcome, without touching the store
+    fn test_prepare_vote() {
+        let mut store = KvStore::new();
+        store.put("A", 42);
+        assert(store.prepare_vote("A", "B", 1) == Vote::Yes);
+        assert(!store.is_locked("A"));
+
+        // Held outside any txn
+        store.lock("A");
+        assert(store.prepare_vote("A", "B", 1) == Vote::NoLockedByOther);
+
+        // Held by the store's own prepared txn: a re-delivered request
+        store.prepare(1);
+        assert(store.prepare_vote("A", "B", 1) == Vote::Yes);
+
+        // Prepared for another txn, whose locks these are
+        assert(store.prepare_vote("A", "B", 2) == Vote::NoLockedByOther);
+
+        store.unlock("A");
+        store.clear_prepared();
+        store.put("B", 42);
+        assert(store.prepare_vote("A", "B", 1) == Vote::NoAlreadyRenamed);
+    }
+
+    /// Test: data_accessible holds before and after a rename, not with both keys
+    fn test_check_data_accessible() {
+        let mut store = KvStore::new();
+        store.put("A", 123);
+        assert(store.check_data_accessible("A", "B"));
+
+        store.lock("A");
+        store.lock("B");
+        store.rename("A", "B");
+        assert(store.check_data_accessible("A", "B"));
+
+        // Corrupted: both keys present
+        store.unlock("A");
+        store.put("A", 7);
+        assert(!store.check_data_accessible("A", "B"));
+    }
+
     /// Test: Rename moves value
     fn test_rename() {
         let mut store = KvStore::new();
@@ -399,6 +3635,124 @@ mod tests {
         assert(result.is_none());
     }
 
+    /// Test: Rename overwrites an existing destination
+    fn test_rename_overwrite() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        store.put("B", 2);
+        store.lock("A");
+        store.lock("B");
+
+        // Destination pre-exists: overwritten value is returned
+        let result = store.rename_overwrite("A", "B");
+        assert(result == Some(2u64));
+        assert(!store.contains_key("A"));
+        assert(store.get("B") == Some(1u64));
+    }
+
+    /// Test: Rename-overwrite with a missing source changes nothing
+    fn test_rename_overwrite_missing_source() {
+        let mut store = KvStore::new();
+        store.put("B", 2);
+        store.lock("A");
+        store.lock("B");
+
+        let result = store.rename_overwrite("A", "B");
+        assert(result.is_none());
+        assert(store.get("B") == Some(2u64));
+    }
+
+    /// Test: Clear empties an unlocked store
+    fn test_clear() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        store.put("B", 2);
+
+        assert(store.clear());
+        assert(!store.contains_key("A"));
+        assert(!store.contains_key("B"));
+    }
+
+    /// Test: Clear refuses while any key is locked
+    fn test_clear_locked() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        store.lock("B");
+
+        assert(!store.clear());
+        assert(store.get("A") == Some(1u64));
+        assert(store.is_locked("B"));
+    }
+
+    /// Test: get_many returns one lookup per key, in order
+    fn test_get_many() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        store.put("C", 3);
+
+        let mut keys: Vec<String> = Vec::new();
+        keys.push("A".to_owned());
+        keys.push("B".to_owned());
+        keys.push("C".to_owned());
+
+        let values = store.get_many(keys.as_slice());
+        assert(values@.len() == 3);
+        assert(values@[0] == Some(1u64));
+        assert(values@[1].is_none());
+        assert(values@[2] == Some(3u64));
+    }
+
+    /// Test: lock_all_reporting reports only the locks it newly took
+    fn test_lock_all_reporting() {
+        let mut store = KvStore::new();
+        store.lock("A");
+
+        let mut keys: Vec<String> = Vec::new();
+        keys.push("A".to_owned());
+        keys.push("B".to_owned());
+        keys.push("B".to_owned());
+
+        let fresh = store.lock_all_reporting(keys.as_slice());
+        assert(fresh@.len() == 3);
+        assert(!fresh@[0]);
+        assert(fresh@[1]);
+        assert(!fresh@[2]);
+        assert(store.is_locked("A"));
+        assert(store.is_locked("B"));
+    }
+
+    /// Test: release_txn_locks frees one txn's locks and keeps the other's
+    fn test_release_txn_locks() {
+        let mut store = KvStore::new();
+        store.update_txn_id(1);
+        store.lock("A");
+        store.lock("B");
+        store.update_txn_id(2);
+        store.lock("C");
+
+        store.release_txn_locks(1);
+        assert(!store.is_locked("A"));
+        assert(!store.is_locked("B"));
+        assert(store.is_locked("C"));
+    }
+
+    /// Test: Conditional lock only locks when the value matches
+    fn test_lock_if_value() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+
+        // Mismatch: wrong value, or expected absent but present
+        assert(!store.lock_if_value("A", Some(2)));
+        assert(!store.lock_if_value("A", None));
+        assert(!store.is_locked("A"));
+
+        // Match on value, and on absence
+        assert(store.lock_if_value("A", Some(1)));
+        assert(store.is_locked("A"));
+        assert(store.lock_if_value("A'", None));
+        assert(store.is_locked("A'"));
+    }
+
     /// Test: Multiple keys independent
     fn test_multiple_keys() {
         let mut store = KvStore::new();
@@ -467,6 +3821,15 @@ mod tests {
         assert(store.get_last_seen_txn_id() == 15);
     }
 
+    /// Test: Out-of-order updates end at the highest txn_id
+    fn test_update_txn_id_non_monotonic() {
+        let mut store = KvStore::new();
+        store.update_txn_id(5);
+        store.update_txn_id(3);
+        store.update_txn_id(8);
+        assert(store.get_last_seen_txn_id() == 8);
+    }
+
     /// Test: txn_id preserved during put
     fn test_txn_id_preserved_put() {
         let mut store = KvStore::new();
@@ -501,6 +3864,314 @@ mod tests {
         assert(store.get_last_seen_txn_id() == 42);
     }
 
+    /// Test: Lock is backed by the txn that took it
+    fn test_lock_implies_seen_txn() {
+        let mut store = KvStore::new();
+        store.update_txn_id(3);
+        store.lock("A");
+
+        assert(store.spec_lock_owner("A"@) == 3);
+        assert(store.spec_locks_backed());
+        assert(store.lock_implies_seen_txn("A"));
+        // Raising the txn id keeps the held lock backed
+        store.update_txn_id(7);
+        assert(store.spec_locks_backed());
+        assert(store.lock_implies_seen_txn("A"));
+        // Unlocked keys trivially satisfy the invariant
+        assert(store.lock_implies_seen_txn("B"));
+    }
+
+    /// Test: Prepared vote survives a crash, locks do not
+    fn test_prepared_survives_crash() {
+        let mut store = KvStore::new();
+        assert(!store.is_prepared(1));
+
+        store.lock("A");
+        store.prepare(1);
+        assert(store.is_prepared(1));
+        assert(!store.is_prepared(2));
+
+        store.crash();
+        assert(!store.is_locked("A"));
+        assert(store.is_prepared(1));
+
+        store.clear_prepared();
+        assert(!store.is_prepared(1));
+    }
+
+    /// Test: Last writer follows put and rename, not locks or reads
+    fn test_last_writer() {
+        let mut store = KvStore::new();
+        assert(store.last_writer("A").is_none());
+
+        store.update_txn_id(3);
+        store.put("A", 1);
+        assert(store.last_writer("A") == Some(3u64));
+
+        store.update_txn_id(5);
+        store.lock("A");
+        store.lock("B");
+        assert(store.last_writer("A") == Some(3u64));
+
+        store.rename("A", "B");
+        assert(store.last_writer("B") == Some(5u64));
+    }
+
+    /// Test: A snapshot keeps the data it copied when the original changes
+    fn test_snapshot() {
+        let mut store = KvStore::new();
+        store.update_txn_id(3);
+        store.put("A", 1);
+
+        let copy = store.snapshot();
+        store.put("A", 2);
+        store.put("B", 3);
+        assert(copy.get("A") == Some(1u64));
+        assert(!copy.contains_key("B"));
+        assert(copy.get_last_seen_txn_id() == 3);
+    }
+
+    /// Test: Rollback restores saved data but keeps txn_id and locks
+    fn test_rollback_to() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        let saved = store.snapshot();
+
+        store.update_txn_id(4);
+        store.put("A", 2);
+        store.put("B", 3);
+        store.lock("C");
+
+        assert(store.rollback_to(&saved));
+        assert(store.get("A") == Some(1u64));
+        assert(!store.contains_key("B"));
+        assert(store.is_locked("C"));
+        assert(store.get_last_seen_txn_id() == 4);
+    }
+
+    /// Test: Atomic batch write is rejected if any key is unlocked
+    fn test_multi_put_locked() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        store.lock("A");
+        store.lock("B");
+
+        let mut entries: Vec<(String, u64)> = Vec::new();
+        entries.push(("A".to_string(), 10));
+        entries.push(("C".to_string(), 30));
+        assert(!store.multi_put_locked(entries));
+        assert(store.get("A") == Some(1u64));
+        assert(!store.contains_key("C"));
+
+        let mut entries: Vec<(String, u64)> = Vec::new();
+        entries.push(("A".to_string(), 10));
+        entries.push(("B".to_string(), 20));
+        assert(store.multi_put_locked(entries));
+        assert(store.get("A") == Some(10u64));
+        assert(store.get("B") == Some(20u64));
+    }
+
+    /// Test: rename_checked reports the transition it performed
+    fn test_rename_checked() {
+        let mut store = KvStore::new();
+        store.put("A", 7);
+        store.lock("A");
+        store.lock("B");
+        let report = store.rename_checked("A", "B");
+        assert(report == RenameProof { moved: Some(7u64), old_was_present: true, new_is_present: true });
+        assert(store.get("B") == Some(7u64));
+
+        // Nothing left to move
+        let report = store.rename_checked("A", "B");
+        assert(report == RenameProof { moved: None, old_was_present: false, new_is_present: true });
+    }
+
+    /// Test: put_with_txn writes the value and its txn stamp together
+    fn test_put_with_txn() {
+        let mut store = KvStore::new();
+        assert(store.put_with_txn("A", 5, 7));
+        assert(store.get("A") == Some(5u64));
+        assert(store.last_writer("A") == Some(7u64));
+
+        store.lock("A");
+        assert(!store.put_with_txn("A", 6, 8));
+        assert(store.get("A") == Some(5u64));
+        assert(store.last_writer("A") == Some(7u64));
+    }
+
+    /// Test: re-locking after an unlock bumps the lock generation
+    fn test_lock_generation() {
+        let mut store = KvStore::new();
+        assert(store.lock_generation("A") == 0);
+        store.lock("A");
+        assert(store.lock_generation("A") == 1);
+        // Locking an already-locked key is idempotent
+        store.lock("A");
+        assert(store.lock_generation("A") == 1);
+        store.unlock("A");
+        store.lock("A");
+        assert(store.is_locked("A"));
+        assert(store.lock_generation("A") == 2);
+    }
+
+    /// Test: applying a diff makes the replica match the primary
+    fn test_diff_apply_delta() {
+        let mut primary = KvStore::new();
+        primary.put("A", 1);
+        primary.put("B", 2);
+        let mut replica = KvStore::new();
+        replica.put("B", 5);
+        replica.put("C", 3);
+
+        let deltas = primary.diff(&replica);
+        assert(replica.apply_delta(&deltas));
+        assert(replica.data@ == primary.data@);
+    }
+
+    /// Test: touch re-attributes a key without changing its value
+    fn test_touch() {
+        let mut store = KvStore::new();
+        store.put("A", 10);
+        assert(store.touch("A", 5));
+        assert(store.get("A") == Some(10u64));
+        assert(store.last_writer("A") == Some(5u64));
+        assert(!store.touch("B", 5));
+    }
+
+    /// Test: lock_status distinguishes unlocked, locked-present and locked-absent
+    fn test_lock_status() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        assert(store.lock_status("A") == KeyStatus::Unlocked);
+        store.lock("A");
+        store.lock("A'");
+        assert(store.lock_status("A") == KeyStatus::LockedAndPresent);
+        assert(store.lock_status("A'") == KeyStatus::LockedAndAbsent);
+    }
+
+    /// Test: observe reports a locked, present key in one read
+    fn test_observe() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        store.lock("A");
+        let obs = store.observe("A");
+        assert(obs.present && obs.locked);
+        assert(obs.value == Some(1u64));
+        assert(obs.lock_owner == Some(0u64));
+    }
+
+    /// Test: exported lock state pairs each locked key with its owner
+    fn test_export_lock_state() {
+        let mut store = KvStore::new();
+        store.lock_with_lease("A", 3, 10);
+        assert(store.spec_is_locked("A"@));
+        let exported = store.export_lock_state();
+        assert(exported@.len() > 0);
+    }
+
+    /// Test: rekey moves every mapped value, or nothing on overlap
+    fn test_rekey() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        store.lock("A");
+        store.lock("B");
+
+        let mut mapping: Vec<(String, String)> = Vec::new();
+        mapping.push(("A".to_string(), "B".to_string()));
+        mapping.push(("B".to_string(), "A".to_string()));
+        assert(!store.rekey(mapping));
+        assert(store.get("A") == Some(1u64));
+
+        let mut mapping: Vec<(String, String)> = Vec::new();
+        mapping.push(("A".to_string(), "B".to_string()));
+        assert(store.rekey(mapping));
+        assert(store.get("B") == Some(1u64));
+        assert(!store.contains_key("A"));
+    }
+
+    /// Test: Chained renames apply in order; a missing source rejects the batch
+    fn test_rename_atomic() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        store.lock("A");
+        store.lock("B");
+        store.lock("C");
+
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        pairs.push(("A".to_string(), "B".to_string()));
+        pairs.push(("B".to_string(), "C".to_string()));
+        assert(store.rename_atomic(pairs));
+        assert(store.get("C") == Some(1u64));
+        assert(!store.contains_key("A"));
+        assert(!store.contains_key("B"));
+    }
+
+    /// Test: An expired lease is released by tick, data untouched
+    fn test_lease_expiry() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        assert(store.lock_with_lease("A", 1, 10));
+        assert(!store.put("A", 2));
+
+        store.tick(5);
+        assert(store.is_locked("A"));
+
+        store.tick(10);
+        assert(!store.is_locked("A"));
+        assert(store.get("A") == Some(1u64));
+        assert(store.put("A", 2));
+    }
+
+    /// Test: get_ref borrows the same value get returns
+    fn test_get_ref() {
+        let mut store = KvStore::new();
+        store.put("A", 42);
+        assert(store.get_ref("A") == Some(&42u64));
+        assert(store.get_ref("B").is_none());
+    }
+
+    /// Test: get_or falls back to the default only for absent keys
+    fn test_get_or() {
+        let mut store = KvStore::new();
+        store.put("A", 42);
+        assert(store.get_or("A", 7) == 42);
+        assert(store.get_or("B", 7) == 7);
+        assert(!store.contains_key("B"));
+    }
+
+    /// Test: get_or_insert on present, absent, and locked-absent keys
+    fn test_get_or_insert() {
+        let mut store = KvStore::new();
+        store.put("A", 42);
+        assert(store.get_or_insert("A", 7) == Some(42u64));
+        assert(store.get("A") == Some(42u64));
+
+        assert(store.get_or_insert("B", 7) == Some(7u64));
+        assert(store.get("B") == Some(7u64));
+
+        store.lock("C");
+        assert(store.get_or_insert("C", 7).is_none());
+        assert(!store.contains_key("C"));
+    }
+
+    /// Test: try_rename reports each outcome
+    fn test_try_rename() {
+        let mut store = KvStore::new();
+        store.put("A", 7);
+
+        assert(store.try_rename("A", "A'") == RenameResult::NotLocked);
+        assert(store.get("A") == Some(7u64));
+
+        store.lock("A");
+        store.lock("A'");
+        store.lock("B");
+        assert(store.try_rename("B", "A'") == RenameResult::SourceAbsent);
+
+        assert(store.try_rename("A", "A'") == RenameResult::Done(7));
+        assert(!store.contains_key("A"));
+        assert(store.get("A'") == Some(7u64));
+    }
+
     /// Test: Stale message rejection scenario
     fn test_stale_message_rejection_scenario() {
         let mut store = KvStore::new();
@@ -521,6 +4192,85 @@ mod tests {
         // New message with txn_id 2 is not stale
         assert(!store.is_stale_txn_id(3));
     }
+
+    /// Test: scan_prefix returns only the keys under the prefix
+    fn test_scan_prefix() {
+        let mut store = KvStore::new();
+        store.put("user:a", 1);
+        store.put("order:b", 2);
+        let users = store.scan_prefix("user:");
+        assert(forall|i: int| #![auto] 0 <= i < users@.len() ==>
+            "user:"@.is_prefix_of(users@[i].0@) && store.spec_get(users@[i].0@) == users@[i].1);
+        assert(store.spec_contains_key("user:a"@));
+    }
+
+    /// Test: a frozen store rejects writes and lock changes until unfrozen
+    fn test_freeze_blocks_mutators() {
+        let mut store = KvStore::new();
+        store.put("A", 1);
+        store.lock("B");
+
+        store.freeze();
+        assert(store.is_frozen());
+        assert(!store.put("A", 2));
+        assert(!store.delete("A"));
+        assert(!store.lock("A"));
+        assert(!store.is_locked("A"));
+        assert(!store.unlock("B"));
+        assert(store.is_locked("B"));
+        assert(store.prepare_vote("A", "A'", 1) == Vote::NoFrozen);
+        assert(store.get("A") == Some(1u64));
+
+        store.unfreeze();
+        assert(!store.is_frozen());
+        assert(store.put("A", 2));
+        store.unlock("B");
+        assert(!store.is_locked("B"));
+    }
+
+    /// Test: the epoch advances on a put but not on a get
+    fn test_epoch_advances_on_put_not_get() {
+        let mut store = KvStore::new();
+        assert(store.epoch() == 0);
+        let ok = store.put("A", 1);
+        assert(ok);
+        let e = store.epoch();
+        assert(e == 1);
+        let v = store.get("A");
+        assert(v == Some(1u64));
+        assert(store.epoch() == e);
+    }
+
+    /// Test: the operation log stays within its capacity
+    fn test_recent_ops_bounded() {
+        let mut store = KvStore::new();
+        let ok = store.put("A", 1);
+        assert(ok);
+        store.lock("A");
+        let ops = store.recent_ops();
+        assert(ops@.len() <= OP_LOG_CAP);
+    }
+
+    /// Test: a routing table keyed by u64 store ids supports the same
+    /// lock-then-rename flow as the protocol's String-keyed store
+    #[cfg(feature = "hashmap")]
+    fn test_u64_keys_lock_and_rename() {
+        let mut routes = KvStore::<u64, u64>::empty();
+        let ok = routes.put(&1, 100);
+        assert(ok);
+        routes.lock(&1);
+        routes.lock(&2);
+        let blocked = routes.put(&1, 5);
+        assert(!blocked);
+        let moved = routes.rename(&1, &2);
+        assert(moved == Some(100u64));
+        assert(!routes.spec_contains_key(1));
+        assert(routes.spec_get(2) == 100);
+        routes.unlock(&2);
+        let deleted = routes.delete(&2);
+        assert(deleted);
+        assert(!routes.spec_contains_key(2));
+    }
 }
 
 } // verus!
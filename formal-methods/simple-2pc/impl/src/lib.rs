@@ -4,31 +4,47 @@
 //
 // Structure:
 // - kv_store_s: KV store specification layer (ghost types, lemmas)
-// - kv_store_v: Verified executable KV store implementation
+// - kv_store_v: Verified executable KV store implementation, generic over the
+//   key type (the protocol uses KvStore<String, u64>)
+// - assoc_map_v: Verified Vec-backed String map, used by kv_store_v when the
+//   `hashmap` feature is disabled
 // - network_s: Network and message specification layer (ghost)
-// - network_v: Verified executable network implementation (mocked with Vec)
+// - network_v: Verified executable network implementation (mocked with Vec),
+//   plus the plain WireMessage bridge for external serialization and the
+//   NetworkModel fault policies installed on an ExecSystem
 // - coordinator_s: Coordinator specification layer (ghost types, lemmas)
 // - coordinator_v: Verified executable coordinator implementation
 // - system_s: System specification layer (ghost composition)
 // - system_v: Verified executable system driver
+// - hierarchy_s: Two-level 2PC specification (root over sub-coordinators)
+// - abstract_s: Abstract 2PC model and coordinator refinement proofs
+// - trace_v: Trace recording and replay wrapper around ExecSystem (testing tooling)
 
 use vstd::prelude::*;
 
 pub mod kv_store_s;
 pub mod kv_store_v;
+pub mod assoc_map_v;
 pub mod network_s;
 pub mod network_v;
 pub mod coordinator_s;
 pub mod coordinator_v;
 pub mod system_s;
 pub mod system_v;
+pub mod hierarchy_s;
+pub mod abstract_s;
+pub mod trace_v;
 
 // Re-export main types for convenience
 pub use kv_store_s::KvStoreSpec;
-pub use kv_store_v::KvStore;
-pub use network_s::{Message, NetworkSpec, StoreId};
-pub use network_v::{ExecMessage, ExecNetwork};
+pub use kv_store_v::{KeyObservation, KeyStatus, KeyMaps, KeyedMap, KvDelta, KvOp, KvStore, RenameProof, RenameResult, StoreKey, Vote, OP_LOG_CAP};
+pub use assoc_map_v::AssocMap;
+pub use network_s::{Message, MsgKind, NetworkSpec, StoreId};
+pub use network_v::{DuplicatingUniform, ExecMessage, ExecNetwork, LossyUniform, NetOp, NetworkModel, Reliable, WireError, WireMessage, MAX_MSG_BYTES, NET_LOG_CAP};
 pub use coordinator_s::{CoordPhase, CoordinatorSpec};
-pub use coordinator_v::Coordinator;
-pub use system_s::SystemSpec;
-pub use system_v::ExecSystem;
+pub use coordinator_v::{AbortReason, Coordinator, SubVote};
+pub use system_s::{RecordedSystem, SystemAction, SystemSpec};
+pub use system_v::{client_rename, DeadlockKind, ExecSystem, NetModelSlot, ProtocolConfig, ProtocolError, QuiescenceError, SentRequests};
+pub use hierarchy_s::{HierarchicalSpec, SubCoordinatorSpec};
+pub use abstract_s::{AbstractPhase, AbstractTwoPC, Decision};
+pub use trace_v::{format_trace, replay, run_twice_identical, TraceAction, TraceEvent, TracedSystem};
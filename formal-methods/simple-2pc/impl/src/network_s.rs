@@ -39,6 +39,13 @@ pub type TxnId = nat;
 /// - RenameRespMsg(s, txnId): Confirmation of rename completion
 /// - UnlockReqMsg(s, txnId): Request to release locks at store s
 /// - UnlockRespMsg(s, txnId): Confirmation of unlock completion
+/// - DecideMsg(s, commit, txnId): One-phase finalization carrying the decision;
+///   sent in cleanup in place of an UnlockReq, it lets a no-op participant
+///   release the txn's locks and answer UnlockResp (not in the TLA+ spec)
+/// - MigrateVoteMsg(s, txnId, v): A migration source's yes vote, reporting the
+///   value `v` it will hand over (not in the TLA+ spec)
+/// - MigrateApplyMsg(s, txnId, v): Request for a migration destination to
+///   insert `v`; answered with RenameRespMsg (not in the TLA+ spec)
 ///
 /// All messages include txnId to prevent stale messages from old transactions
 /// being processed after coordinator crash/recovery.
@@ -50,9 +57,80 @@ pub ghost enum Message {
     RenameResp { store: StoreId, txn_id: TxnId },
     UnlockReq { store: StoreId, txn_id: TxnId },
     UnlockResp { store: StoreId, txn_id: TxnId },
+    Decide { store: StoreId, commit: bool, txn_id: TxnId },
+    MigrateVote { store: StoreId, txn_id: TxnId, value: u64 },
+    MigrateApply { store: StoreId, txn_id: TxnId, value: u64 },
+}
+
+/// Message kind: the Message variant without its payload
+///
+/// Like CoordPhase, a regular (non-ghost) enum usable in both spec and exec contexts.
+/// Discriminants index per-kind tables such as ExecNetwork::kind_histogram.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MsgKind {
+    LockReq,
+    LockResp,
+    RenameReq,
+    RenameResp,
+    UnlockReq,
+    UnlockResp,
+    Decide,
+    MigrateVote,
+    MigrateApply,
+}
+
+impl MsgKind {
+    /// Position of this kind in a per-kind table (spec function)
+    pub open spec fn spec_index(&self) -> int {
+        match *self {
+            MsgKind::LockReq => 0,
+            MsgKind::LockResp => 1,
+            MsgKind::RenameReq => 2,
+            MsgKind::RenameResp => 3,
+            MsgKind::UnlockReq => 4,
+            MsgKind::UnlockResp => 5,
+            MsgKind::Decide => 6,
+            MsgKind::MigrateVote => 7,
+            MsgKind::MigrateApply => 8,
+        }
+    }
+
+    /// Position of this kind in a per-kind table (exec function)
+    pub fn index(&self) -> (result: usize)
+        ensures
+            result as int == self.spec_index(),
+            result < 9,
+    {
+        match *self {
+            MsgKind::LockReq => 0,
+            MsgKind::LockResp => 1,
+            MsgKind::RenameReq => 2,
+            MsgKind::RenameResp => 3,
+            MsgKind::UnlockReq => 4,
+            MsgKind::UnlockResp => 5,
+            MsgKind::Decide => 6,
+            MsgKind::MigrateVote => 7,
+            MsgKind::MigrateApply => 8,
+        }
+    }
 }
 
 impl Message {
+    /// Get the kind of this message
+    pub open spec fn kind(&self) -> MsgKind {
+        match *self {
+            Message::LockReq { .. } => MsgKind::LockReq,
+            Message::LockResp { .. } => MsgKind::LockResp,
+            Message::RenameReq { .. } => MsgKind::RenameReq,
+            Message::RenameResp { .. } => MsgKind::RenameResp,
+            Message::UnlockReq { .. } => MsgKind::UnlockReq,
+            Message::UnlockResp { .. } => MsgKind::UnlockResp,
+            Message::Decide { .. } => MsgKind::Decide,
+            Message::MigrateVote { .. } => MsgKind::MigrateVote,
+            Message::MigrateApply { .. } => MsgKind::MigrateApply,
+        }
+    }
+
     /// Get the store this message is associated with
     pub open spec fn get_store(&self) -> StoreId {
         match *self {
@@ -62,6 +140,9 @@ impl Message {
             Message::RenameResp { store, .. } => store,
             Message::UnlockReq { store, .. } => store,
             Message::UnlockResp { store, .. } => store,
+            Message::Decide { store, .. } => store,
+            Message::MigrateVote { store, .. } => store,
+            Message::MigrateApply { store, .. } => store,
         }
     }
 
@@ -74,6 +155,25 @@ impl Message {
             Message::RenameResp { txn_id, .. } => txn_id,
             Message::UnlockReq { txn_id, .. } => txn_id,
             Message::UnlockResp { txn_id, .. } => txn_id,
+            Message::Decide { txn_id, .. } => txn_id,
+            Message::MigrateVote { txn_id, .. } => txn_id,
+            Message::MigrateApply { txn_id, .. } => txn_id,
+        }
+    }
+
+    /// Same message with its transaction ID replaced
+    pub open spec fn with_txn(&self, new_txn: TxnId) -> Message {
+        match *self {
+            Message::LockReq { store, .. } => Message::LockReq { store, txn_id: new_txn },
+            Message::LockResp { store, success, .. } =>
+                Message::LockResp { store, success, txn_id: new_txn },
+            Message::RenameReq { store, .. } => Message::RenameReq { store, txn_id: new_txn },
+            Message::RenameResp { store, .. } => Message::RenameResp { store, txn_id: new_txn },
+            Message::UnlockReq { store, .. } => Message::UnlockReq { store, txn_id: new_txn },
+            Message::UnlockResp { store, .. } => Message::UnlockResp { store, txn_id: new_txn },
+            Message::Decide { store, commit, .. } => Message::Decide { store, commit, txn_id: new_txn },
+            Message::MigrateVote { store, value, .. } => Message::MigrateVote { store, txn_id: new_txn, value },
+            Message::MigrateApply { store, value, .. } => Message::MigrateApply { store, txn_id: new_txn, value },
         }
     }
 
@@ -83,6 +183,8 @@ impl Message {
             Message::LockReq { .. } => true,
             Message::RenameReq { .. } => true,
             Message::UnlockReq { .. } => true,
+            Message::Decide { .. } => true,
+            Message::MigrateApply { .. } => true,
             _ => false,
         }
     }
@@ -93,6 +195,7 @@ impl Message {
             Message::LockResp { .. } => true,
             Message::RenameResp { .. } => true,
             Message::UnlockResp { .. } => true,
+            Message::MigrateVote { .. } => true,
             _ => false,
         }
     }
@@ -112,6 +215,58 @@ impl Message {
             _ => false,
         }
     }
+
+    /// The boolean payload: LockResp's `success` or Decide's `commit`, false otherwise
+    pub open spec fn flag(&self) -> bool {
+        match *self {
+            Message::LockResp { success, .. } => success,
+            Message::Decide { commit, .. } => commit,
+            _ => false,
+        }
+    }
+
+    /// The migrated value carried by MigrateVote and MigrateApply, 0 otherwise
+    pub open spec fn value(&self) -> u64 {
+        match *self {
+            Message::MigrateVote { value, .. } => value,
+            Message::MigrateApply { value, .. } => value,
+            _ => 0,
+        }
+    }
+}
+
+/// The message with kind index `tag` (see MsgKind::spec_index) and the given
+/// payload, or None if `tag` names no kind. `flag` is ignored by kinds
+/// without a boolean field, `value` by kinds that carry no value
+pub open spec fn message_from_tag(tag: int, store: StoreId, txn_id: TxnId, flag: bool, value: u64) -> Option<Message> {
+    if tag == 0 {
+        Some(Message::LockReq { store, txn_id })
+    } else if tag == 1 {
+        Some(Message::LockResp { store, success: flag, txn_id })
+    } else if tag == 2 {
+        Some(Message::RenameReq { store, txn_id })
+    } else if tag == 3 {
+        Some(Message::RenameResp { store, txn_id })
+    } else if tag == 4 {
+        Some(Message::UnlockReq { store, txn_id })
+    } else if tag == 5 {
+        Some(Message::UnlockResp { store, txn_id })
+    } else if tag == 6 {
+        Some(Message::Decide { store, commit: flag, txn_id })
+    } else if tag == 7 {
+        Some(Message::MigrateVote { store, txn_id, value })
+    } else if tag == 8 {
+        Some(Message::MigrateApply { store, txn_id, value })
+    } else {
+        None
+    }
+}
+
+/// Lemma: a message is rebuilt exactly from its kind index and payload
+pub proof fn lemma_message_from_tag_round_trip(m: Message)
+    ensures
+        message_from_tag(m.kind().spec_index(), m.get_store(), m.get_txn_id(), m.flag(), m.value()) == Some(m),
+{
 }
 
 // ============================================================
@@ -148,6 +303,21 @@ pub open spec fn unlock_resp_msg(store: StoreId, txn_id: TxnId) -> Message {
     Message::UnlockResp { store, txn_id }
 }
 
+/// Create a decide message carrying the final decision (`commit` or abort)
+pub open spec fn decide_msg(store: StoreId, commit: bool, txn_id: TxnId) -> Message {
+    Message::Decide { store, commit, txn_id }
+}
+
+/// Create a migration vote message reporting the value src hands over
+pub open spec fn migrate_vote_msg(store: StoreId, txn_id: TxnId, value: u64) -> Message {
+    Message::MigrateVote { store, txn_id, value }
+}
+
+/// Create a migration apply message telling dst which value to insert
+pub open spec fn migrate_apply_msg(store: StoreId, txn_id: TxnId, value: u64) -> Message {
+    Message::MigrateApply { store, txn_id, value }
+}
+
 // ============================================================
 // NETWORK SPEC
 // ============================================================
@@ -159,9 +329,13 @@ pub open spec fn unlock_resp_msg(store: StoreId, txn_id: TxnId) -> Message {
 /// - Messages can be duplicated (send adds one copy)
 /// - Messages can be lost (lose removes one copy, others remain)
 /// - Multiple copies of the same message can exist independently
+/// - `sent` remembers every message ever put on the network, so causal
+///   properties can refer to requests that have since been consumed
 pub ghost struct NetworkSpec {
     /// Multiset of messages currently in the network
     pub messages: Multiset<Message>,
+    /// Every message ever sent or duplicated (history: loss does not remove it)
+    pub sent: Set<Message>,
 }
 
 impl NetworkSpec {
@@ -179,6 +353,11 @@ impl NetworkSpec {
         self.messages.count(msg)
     }
 
+    /// Total number of in-flight messages, counting every copy
+    pub open spec fn total(&self) -> nat {
+        self.messages.len()
+    }
+
     /// Check if network is empty
     pub open spec fn is_empty(&self) -> bool {
         self.messages =~= Multiset::empty()
@@ -219,6 +398,39 @@ impl NetworkSpec {
         self.contains(unlock_resp_msg(store, txn_id))
     }
 
+    /// Check if there's a decide message (either decision) for a store with specific txn_id
+    pub open spec fn has_decide(&self, store: StoreId, txn_id: TxnId) -> bool {
+        self.contains(decide_msg(store, true, txn_id)) || self.contains(decide_msg(store, false, txn_id))
+    }
+
+    // ============================================================
+    // SPEC FUNCTIONS - Causal predicates
+    // ============================================================
+
+    /// Every in-flight message was sent at some point
+    pub open spec fn in_flight_sent(&self) -> bool {
+        forall|m: Message| #[trigger] self.contains(m) ==> self.sent.contains(m)
+    }
+
+    /// A LockResp for (store, txn_id) is only in flight if a LockReq for
+    /// (store, txn_id) was sent at some point (it may since have been consumed)
+    pub open spec fn response_implies_request(&self, store: StoreId, txn_id: TxnId) -> bool {
+        (self.has_lock_resp_success(store, txn_id) || self.has_lock_resp_failure(store, txn_id))
+            ==> self.sent.contains(lock_req_msg(store, txn_id))
+    }
+
+    /// No spontaneous messages: everything in flight was sent, and every
+    /// LockResp answers a LockReq that was sent
+    pub open spec fn causally_consistent(&self) -> bool {
+        &&& self.in_flight_sent()
+        &&& forall|store: StoreId, txn_id: TxnId| #[trigger] self.response_implies_request(store, txn_id)
+    }
+
+    /// Sending `msg` keeps causality: it is not a LockResp, or its LockReq was sent
+    pub open spec fn may_send(&self, msg: Message) -> bool {
+        msg.kind() == MsgKind::LockResp ==> self.sent.contains(lock_req_msg(msg.get_store(), msg.get_txn_id()))
+    }
+
     // ============================================================
     // SPEC FUNCTIONS - State transitions
     // ============================================================
@@ -227,6 +439,7 @@ impl NetworkSpec {
     pub open spec fn empty() -> Self {
         NetworkSpec {
             messages: Multiset::empty(),
+            sent: Set::empty(),
         }
     }
 
@@ -235,6 +448,18 @@ impl NetworkSpec {
     pub open spec fn send(self, msg: Message) -> Self {
         NetworkSpec {
             messages: self.messages.insert(msg),
+            sent: self.sent.insert(msg),
+        }
+    }
+
+    /// Send a batch of messages, in order (folds `send` over `msgs`)
+    pub open spec fn send_all(self, msgs: Seq<Message>) -> Self
+        decreases msgs.len()
+    {
+        if msgs.len() == 0 {
+            self
+        } else {
+            self.send_all(msgs.drop_last()).send(msgs.last())
         }
     }
 
@@ -243,6 +468,7 @@ impl NetworkSpec {
     pub open spec fn lose(self, msg: Message) -> Self {
         NetworkSpec {
             messages: self.messages.remove(msg),
+            sent: self.sent,
         }
     }
 
@@ -253,6 +479,15 @@ impl NetworkSpec {
     {
         NetworkSpec {
             messages: self.messages.insert(msg),
+            sent: self.sent.insert(msg),
+        }
+    }
+
+    /// Keep only the messages addressed to (or sent by) store `s`
+    pub open spec fn restrict_to_store(self, s: StoreId) -> Self {
+        NetworkSpec {
+            messages: self.messages.filter(|m: Message| m.get_store() == s),
+            sent: self.sent.filter(|m: Message| m.get_store() == s),
         }
     }
 
@@ -276,6 +511,18 @@ impl NetworkSpec {
     {
     }
 
+    /// Sending a batch adds one copy of `msg` per occurrence in the batch
+    pub proof fn lemma_send_all_count(self, msgs: Seq<Message>, msg: Message)
+        ensures
+            self.send_all(msgs).count(msg) == self.count(msg) + msgs.filter(|x: Message| x == msg).len()
+        decreases msgs.len()
+    {
+        reveal(Seq::filter);
+        if msgs.len() > 0 {
+            self.lemma_send_all_count(msgs.drop_last(), msg);
+        }
+    }
+
     /// Send then check: after send, message is in network
     pub proof fn lemma_send_contains(self, msg: Message)
         ensures
@@ -319,6 +566,16 @@ impl NetworkSpec {
     {
     }
 
+    /// Send and lose of distinct messages commute
+    pub proof fn lemma_send_lose_commute(self, msg: Message, other: Message)
+        requires
+            msg != other
+        ensures
+            self.send(msg).lose(other) == self.lose(other).send(msg)
+    {
+        assert(self.send(msg).lose(other).messages =~= self.lose(other).send(msg).messages);
+    }
+
     /// Lose preserves other messages
     pub proof fn lemma_lose_preserves_others(self, msg: Message, other: Message)
         requires
@@ -328,6 +585,72 @@ impl NetworkSpec {
     {
     }
 
+    /// Restricting to a store keeps exactly that store's copies
+    pub proof fn lemma_restrict_to_store_count(self, s: StoreId, msg: Message)
+        ensures
+            self.restrict_to_store(s).count(msg) == if msg.get_store() == s { self.count(msg) } else { 0 },
+    {
+    }
+
+    /// Per-store independence: sending, losing or duplicating a message for one
+    /// store leaves every other store's view of the network unchanged
+    pub proof fn lemma_other_store_ops_preserve_restrict(self, msg: Message, s: StoreId)
+        requires
+            msg.get_store() != s,
+        ensures
+            self.send(msg).restrict_to_store(s) == self.restrict_to_store(s),
+            self.lose(msg).restrict_to_store(s) == self.restrict_to_store(s),
+            self.duplicate(msg).restrict_to_store(s) == self.restrict_to_store(s),
+    {
+        assert(self.send(msg).restrict_to_store(s).messages =~= self.restrict_to_store(s).messages);
+        assert(self.lose(msg).restrict_to_store(s).messages =~= self.restrict_to_store(s).messages);
+        assert(self.duplicate(msg).restrict_to_store(s).messages =~= self.restrict_to_store(s).messages);
+        assert(self.send(msg).restrict_to_store(s).sent =~= self.restrict_to_store(s).sent);
+        assert(self.duplicate(msg).restrict_to_store(s).sent =~= self.restrict_to_store(s).sent);
+    }
+
+    /// Send adds one message to the total
+    pub proof fn lemma_send_total(self, msg: Message)
+        ensures
+            self.send(msg).total() == self.total() + 1
+    {
+    }
+
+    /// Lose removes one message from the total (if present)
+    pub proof fn lemma_lose_total(self, msg: Message)
+        requires
+            self.contains(msg)
+        ensures
+            self.lose(msg).total() == self.total() - 1
+    {
+    }
+
+    /// Losing an absent message leaves the total unchanged
+    pub proof fn lemma_lose_absent_total(self, msg: Message)
+        requires
+            !self.contains(msg)
+        ensures
+            self.lose(msg).total() == self.total()
+    {
+        assert(self.lose(msg).messages =~= self.messages);
+    }
+
+    /// Duplicate adds one message to the total
+    pub proof fn lemma_duplicate_total(self, msg: Message)
+        requires
+            self.contains(msg)
+        ensures
+            self.duplicate(msg).total() == self.total() + 1
+    {
+    }
+
+    /// The empty network has no messages in flight
+    pub proof fn lemma_empty_total()
+        ensures
+            Self::empty().total() == 0
+    {
+    }
+
     /// Empty network contains no messages
     pub proof fn lemma_empty_contains_nothing(msg: Message)
         ensures
@@ -353,6 +676,40 @@ impl NetworkSpec {
     {
     }
 
+    /// Duplication only copies a message already in flight, so it introduces
+    /// nothing new: the same messages are in flight (one more copy of `msg`),
+    /// the send history is unchanged if `msg` was recorded, and every
+    /// invariant over in-flight messages or causality carries over
+    pub proof fn lemma_duplicate_preserves_invariants(self, msg: Message)
+        requires
+            self.contains(msg),
+        ensures
+            forall|m: Message| #[trigger] self.duplicate(msg).contains(m) == self.contains(m),
+            forall|m: Message| m != msg ==> #[trigger] self.duplicate(msg).count(m) == self.count(m),
+            self.in_flight_sent() ==> self.duplicate(msg).sent == self.sent,
+            self.in_flight_sent() ==> self.duplicate(msg).in_flight_sent(),
+            forall|store: StoreId, txn_id: TxnId| #[trigger] self.response_implies_request(store, txn_id)
+                ==> self.duplicate(msg).response_implies_request(store, txn_id),
+            self.causally_consistent() ==> self.duplicate(msg).causally_consistent(),
+    {
+        let next = self.duplicate(msg);
+        assert forall|m: Message| #[trigger] next.contains(m) == self.contains(m) by {
+            if m != msg {
+                self.lemma_send_preserves_others(msg, m);
+            }
+        }
+        assert forall|m: Message| m != msg implies #[trigger] next.count(m) == self.count(m) by {
+            self.lemma_send_preserves_others(msg, m);
+        }
+        if self.in_flight_sent() {
+            assert(self.sent.contains(msg));
+            assert(next.sent =~= self.sent);
+        }
+        if self.causally_consistent() {
+            self.lemma_lose_preserves_causally_consistent(msg);
+        }
+    }
+
     /// Send twice creates two copies
     pub proof fn lemma_send_twice_creates_two(msg: Message)
         ensures
@@ -368,6 +725,81 @@ impl NetworkSpec {
     {
     }
 
+    /// The empty network is causally consistent
+    pub proof fn lemma_empty_causally_consistent()
+        ensures
+            Self::empty().causally_consistent()
+    {
+        assert forall|m: Message| #[trigger] Self::empty().contains(m) implies Self::empty().sent.contains(m) by {
+            Self::lemma_empty_contains_nothing(m);
+        }
+        assert forall|store: StoreId, txn_id: TxnId| #[trigger] Self::empty().response_implies_request(store, txn_id) by {
+            Self::lemma_empty_contains_nothing(lock_resp_msg(store, true, txn_id));
+            Self::lemma_empty_contains_nothing(lock_resp_msg(store, false, txn_id));
+        }
+    }
+
+    /// Sending a message that `may_send` allows preserves causal consistency
+    pub proof fn lemma_send_preserves_causally_consistent(self, msg: Message)
+        requires
+            self.causally_consistent(),
+            self.may_send(msg),
+        ensures
+            self.send(msg).causally_consistent()
+    {
+        let next = self.send(msg);
+        assert forall|m: Message| #[trigger] next.contains(m) implies next.sent.contains(m) by {
+            if m != msg {
+                self.lemma_send_preserves_others(msg, m);
+            }
+        }
+        assert forall|store: StoreId, txn_id: TxnId| #[trigger] next.response_implies_request(store, txn_id) by {
+            let (yes, no) = (lock_resp_msg(store, true, txn_id), lock_resp_msg(store, false, txn_id));
+            if yes != msg {
+                self.lemma_send_preserves_others(msg, yes);
+            }
+            if no != msg {
+                self.lemma_send_preserves_others(msg, no);
+            }
+            assert(self.response_implies_request(store, txn_id));
+        }
+    }
+
+    /// Losing any message, or duplicating an in-flight one, preserves causal consistency
+    pub proof fn lemma_lose_preserves_causally_consistent(self, msg: Message)
+        requires
+            self.causally_consistent(),
+        ensures
+            self.lose(msg).causally_consistent(),
+            self.contains(msg) ==> self.duplicate(msg).causally_consistent(),
+    {
+        let next = self.lose(msg);
+        assert forall|m: Message| #[trigger] next.contains(m) implies next.sent.contains(m) by {
+            if m != msg {
+                self.lemma_lose_preserves_others(msg, m);
+            }
+        }
+        assert forall|store: StoreId, txn_id: TxnId| #[trigger] next.response_implies_request(store, txn_id) by {
+            let (yes, no) = (lock_resp_msg(store, true, txn_id), lock_resp_msg(store, false, txn_id));
+            if yes != msg {
+                self.lemma_lose_preserves_others(msg, yes);
+            }
+            if no != msg {
+                self.lemma_lose_preserves_others(msg, no);
+            }
+            assert(self.response_implies_request(store, txn_id));
+        }
+        if self.contains(msg) {
+            // A duplicated LockResp is already in flight, so its LockReq was sent
+            if let Message::LockResp { store, success, txn_id } = msg {
+                assert(msg == lock_resp_msg(store, success, txn_id));
+                assert(self.response_implies_request(store, txn_id));
+            }
+            self.lemma_send_preserves_causally_consistent(msg);
+            assert(self.duplicate(msg) == self.send(msg));
+        }
+    }
+
     /// Different message types are distinct (with same store and txn_id)
     pub proof fn lemma_message_types_distinct(store: StoreId, txn_id: TxnId)
         ensures
@@ -380,6 +812,13 @@ impl NetworkSpec {
             lock_resp_msg(store, true, txn_id) != lock_resp_msg(store, false, txn_id),
             rename_req_msg(store, txn_id) != rename_resp_msg(store, txn_id),
             unlock_req_msg(store, txn_id) != unlock_resp_msg(store, txn_id),
+            decide_msg(store, true, txn_id) != decide_msg(store, false, txn_id),
+            forall|commit: bool| #![auto] decide_msg(store, commit, txn_id) != lock_req_msg(store, txn_id)
+                && decide_msg(store, commit, txn_id) != lock_resp_msg(store, commit, txn_id)
+                && decide_msg(store, commit, txn_id) != rename_req_msg(store, txn_id)
+                && decide_msg(store, commit, txn_id) != rename_resp_msg(store, txn_id)
+                && decide_msg(store, commit, txn_id) != unlock_req_msg(store, txn_id)
+                && decide_msg(store, commit, txn_id) != unlock_resp_msg(store, txn_id),
     {
     }
 
@@ -394,6 +833,7 @@ impl NetworkSpec {
             rename_resp_msg(s1, txn_id) != rename_resp_msg(s2, txn_id),
             unlock_req_msg(s1, txn_id) != unlock_req_msg(s2, txn_id),
             unlock_resp_msg(s1, txn_id) != unlock_resp_msg(s2, txn_id),
+            forall|commit: bool| #![auto] decide_msg(s1, commit, txn_id) != decide_msg(s2, commit, txn_id),
     {
     }
 
@@ -408,6 +848,7 @@ impl NetworkSpec {
             rename_resp_msg(store, t1) != rename_resp_msg(store, t2),
             unlock_req_msg(store, t1) != unlock_req_msg(store, t2),
             unlock_resp_msg(store, t1) != unlock_resp_msg(store, t2),
+            forall|commit: bool| #![auto] decide_msg(store, commit, t1) != decide_msg(store, commit, t2),
     {
     }
 }
@@ -423,6 +864,35 @@ mod tests {
     // Default txn_id for tests
     spec fn default_txn_id() -> TxnId { 1 }
 
+    /// Test: total tracks every send, duplicate and loss
+    proof fn test_total_tracks_operations() {
+        let txn_id = default_txn_id();
+        let req = lock_req_msg(0, txn_id);
+        let resp = lock_resp_msg(0, true, txn_id);
+        NetworkSpec::lemma_empty_total();
+        let net0 = NetworkSpec::empty();
+
+        net0.lemma_send_total(req);
+        let net1 = net0.send(req);
+        assert(net1.total() == 1);
+
+        net1.lemma_send_total(resp);
+        let net2 = net1.send(resp);
+        assert(net2.total() == 2);
+
+        net2.lemma_duplicate_total(req);
+        let net3 = net2.duplicate(req);
+        assert(net3.total() == 3);
+
+        net3.lemma_lose_total(resp);
+        let net4 = net3.lose(resp);
+        assert(net4.total() == 2);
+
+        // resp is gone now, so losing it again changes nothing
+        net4.lemma_lose_absent_total(resp);
+        assert(net4.lose(resp).total() == 2);
+    }
+
     /// Test: Empty network contains no messages
     proof fn test_empty_network() {
         let net = NetworkSpec::empty();
@@ -521,6 +991,25 @@ mod tests {
         assert(net2.contains(msg2));
     }
 
+    /// Test: Handling store 1's messages leaves store 2's restriction alone
+    proof fn test_restrict_to_store_two_stores() {
+        let txn_id = default_txn_id();
+        let req1 = lock_req_msg(1, txn_id);
+        let req2 = lock_req_msg(2, txn_id);
+        let net = NetworkSpec::empty().send(req1).send(req2);
+
+        net.lemma_restrict_to_store_count(2, req2);
+        net.lemma_restrict_to_store_count(2, req1);
+        assert(net.restrict_to_store(2).contains(req2));
+        assert(!net.restrict_to_store(2).contains(req1));
+
+        // Store 1 consumes its request and answers
+        let resp1 = lock_resp_msg(1, true, txn_id);
+        net.lemma_other_store_ops_preserve_restrict(req1, 2);
+        net.lose(req1).lemma_other_store_ops_preserve_restrict(resp1, 2);
+        assert(net.lose(req1).send(resp1).restrict_to_store(2) == net.restrict_to_store(2));
+    }
+
     /// Test: Message type predicates
     proof fn test_message_predicates() {
         let txn_id = default_txn_id();
@@ -554,6 +1043,11 @@ mod tests {
 
         assert(!lock_resp_fail.is_lock_success());
         assert(lock_resp_fail.is_lock_failure());
+
+        // A decide message is a request from the coordinator
+        assert(decide_msg(1, true, txn_id).is_request());
+        assert(!decide_msg(1, false, txn_id).is_response());
+        assert(!decide_msg(1, true, txn_id).is_lock_success());
     }
 
     /// Test: Store accessor
@@ -565,6 +1059,7 @@ mod tests {
         assert(rename_resp_msg(2, txn_id).get_store() == 2);
         assert(unlock_req_msg(9, txn_id).get_store() == 9);
         assert(unlock_resp_msg(4, txn_id).get_store() == 4);
+        assert(decide_msg(6, true, txn_id).get_store() == 6);
     }
 
     /// Test: TxnId accessor
@@ -576,6 +1071,7 @@ mod tests {
         assert(rename_resp_msg(2, txn_id).get_txn_id() == 42);
         assert(unlock_req_msg(9, txn_id).get_txn_id() == 42);
         assert(unlock_resp_msg(4, txn_id).get_txn_id() == 42);
+        assert(decide_msg(6, false, txn_id).get_txn_id() == 42);
     }
 
     /// Test: Convenience methods
@@ -614,6 +1110,27 @@ mod tests {
         assert(net2.count(msg) == 2);
     }
 
+    /// Test: Duplicating a LockResp keeps the network causally consistent
+    proof fn test_duplicate_preserves_invariants() {
+        let txn_id = default_txn_id();
+        let req = lock_req_msg(1, txn_id);
+        let resp = lock_resp_msg(1, true, txn_id);
+        NetworkSpec::lemma_empty_causally_consistent();
+        let net0 = NetworkSpec::empty();
+        assert(net0.may_send(req));
+        net0.lemma_send_preserves_causally_consistent(req);
+        let net1 = net0.send(req);
+        assert(net1.may_send(resp));
+        net1.lemma_send_preserves_causally_consistent(resp);
+        let net2 = net1.send(resp);
+        assert(net2.contains(resp));
+
+        net2.lemma_duplicate_preserves_invariants(resp);
+        assert(net2.duplicate(resp).causally_consistent());
+        assert(net2.duplicate(resp).sent == net2.sent);
+        assert(net2.duplicate(resp).contains(req));
+    }
+
     /// Test: Different message types are distinct
     proof fn test_message_distinctness() {
         let txn_id = default_txn_id();
@@ -623,6 +1140,8 @@ mod tests {
         assert(lock_req_msg(1, txn_id) != rename_req_msg(1, txn_id));
         assert(lock_resp_msg(1, true, txn_id) != lock_resp_msg(1, false, txn_id));
         assert(unlock_req_msg(1, txn_id) != unlock_resp_msg(1, txn_id));
+        assert(decide_msg(1, true, txn_id) != rename_req_msg(1, txn_id));
+        assert(decide_msg(1, true, txn_id) != decide_msg(1, false, txn_id));
     }
 
     /// Test: Messages for different stores are distinct
@@ -635,6 +1154,19 @@ mod tests {
         assert(unlock_resp_msg(1, txn_id) != unlock_resp_msg(2, txn_id));
     }
 
+    /// Test: Sending a LockReq and losing a RenameResp commute
+    proof fn test_send_lose_commute() {
+        let txn_id = default_txn_id();
+        let lock_req = lock_req_msg(1, txn_id);
+        let rename_resp = rename_resp_msg(2, txn_id);
+        let net = NetworkSpec::empty().send(rename_resp);
+
+        net.lemma_send_lose_commute(lock_req, rename_resp);
+        assert(net.send(lock_req).lose(rename_resp) == net.lose(rename_resp).send(lock_req));
+        assert(net.lose(rename_resp).send(lock_req).count(lock_req) == 1);
+        assert(net.lose(rename_resp).send(lock_req).count(rename_resp) == 0);
+    }
+
     /// Test: Realistic duplication scenario
     /// Network duplicates a message, one copy is lost, receiver still gets it
     proof fn test_duplication_then_loss() {
@@ -657,6 +1189,42 @@ mod tests {
         assert(net3.contains(msg));
     }
 
+    /// Test: Sending a batch of two distinct messages
+    proof fn test_send_all_two() {
+        let txn_id = default_txn_id();
+        let a = lock_req_msg(1, txn_id);
+        let b = lock_req_msg(2, txn_id);
+        let msgs = seq![a, b];
+
+        NetworkSpec::empty().lemma_send_all_count(msgs, a);
+        NetworkSpec::empty().lemma_send_all_count(msgs, b);
+        reveal_with_fuel(Seq::filter, 3);
+        assert(msgs.drop_last() =~= seq![a]);
+        assert(seq![a].drop_last() =~= Seq::<Message>::empty());
+
+        let net = NetworkSpec::empty().send_all(msgs);
+        assert(net.count(a) == 1);
+        assert(net.count(b) == 1);
+    }
+
+    /// Test: A batch containing the same message twice creates two copies
+    proof fn test_send_all_duplicates() {
+        let txn_id = default_txn_id();
+        let a = lock_req_msg(1, txn_id);
+        let b = lock_req_msg(2, txn_id);
+        let msgs = seq![a, a, b];
+
+        NetworkSpec::empty().lemma_send_all_count(msgs, a);
+        reveal_with_fuel(Seq::filter, 4);
+        assert(msgs.drop_last() =~= seq![a, a]);
+        assert(seq![a, a].drop_last() =~= seq![a]);
+        assert(seq![a].drop_last() =~= Seq::<Message>::empty());
+
+        let net = NetworkSpec::empty().send_all(msgs);
+        assert(net.count(a) == 2);
+        assert(net.count(b) == 1);
+    }
+
     /// Test: Messages with different txn_ids are distinct
     proof fn test_different_txn_ids() {
         let txn1: TxnId = 1;
@@ -690,6 +1258,32 @@ mod tests {
         assert(net.count(old_lock_req) == 1);
         assert(net.count(new_lock_req) == 1);
     }
+
+    /// Test: A LockResp stays causally justified after its LockReq is consumed,
+    /// while a spontaneous LockResp is not
+    proof fn test_response_implies_request() {
+        let txn_id = default_txn_id();
+        let req = lock_req_msg(0, txn_id);
+        let resp = lock_resp_msg(0, true, txn_id);
+        NetworkSpec::lemma_message_types_distinct(0, txn_id);
+
+        NetworkSpec::lemma_empty_causally_consistent();
+        let net0 = NetworkSpec::empty();
+        net0.lemma_send_preserves_causally_consistent(req);
+        let net1 = net0.send(req);
+        net1.lemma_lose_preserves_causally_consistent(req);
+        let net2 = net1.lose(req);
+        net1.lemma_lose_removes_last(req);
+        assert(!net2.contains(req));
+        net2.lemma_send_preserves_causally_consistent(resp);
+        let net3 = net2.send(resp);
+        assert(net3.causally_consistent());
+        assert(net3.response_implies_request(0, txn_id));
+
+        let spontaneous = NetworkSpec::empty().send(resp);
+        assert(spontaneous.contains(resp));
+        assert(!spontaneous.response_implies_request(0, txn_id));
+    }
 }
 
 } // verus!
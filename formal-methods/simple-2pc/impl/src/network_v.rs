@@ -8,6 +8,7 @@
 
 use vstd::prelude::*;
 
+use crate::coordinator_s::CoordPhase;
 use crate::network_s::*;
 
 verus! {
@@ -16,8 +17,12 @@ verus! {
 // EXECUTABLE MESSAGE TYPE
 // ============================================================
 
+/// Largest `ExecMessage::byte_size` of any message
+pub const MAX_MSG_BYTES: usize = 25;
+
 /// Executable message type - mirrors the ghost Message enum
 /// Uses u64 for StoreId and TxnId to match exec types
+#[derive(Debug)]
 pub enum ExecMessage {
     LockReq { store: u64, txn_id: u64 },
     LockResp { store: u64, success: bool, txn_id: u64 },
@@ -25,6 +30,9 @@ pub enum ExecMessage {
     RenameResp { store: u64, txn_id: u64 },
     UnlockReq { store: u64, txn_id: u64 },
     UnlockResp { store: u64, txn_id: u64 },
+    Decide { store: u64, commit: bool, txn_id: u64 },
+    MigrateVote { store: u64, txn_id: u64, value: u64 },
+    MigrateApply { store: u64, txn_id: u64, value: u64 },
 }
 
 impl ExecMessage {
@@ -46,6 +54,12 @@ impl ExecMessage {
              ExecMessage::UnlockReq { store: s2, txn_id: t2 }) => *s1 == *s2 && *t1 == *t2,
             (ExecMessage::UnlockResp { store: s1, txn_id: t1 },
              ExecMessage::UnlockResp { store: s2, txn_id: t2 }) => *s1 == *s2 && *t1 == *t2,
+            (ExecMessage::Decide { store: s1, commit: c1, txn_id: t1 },
+             ExecMessage::Decide { store: s2, commit: c2, txn_id: t2 }) => *s1 == *s2 && *c1 == *c2 && *t1 == *t2,
+            (ExecMessage::MigrateVote { store: s1, txn_id: t1, value: v1 },
+             ExecMessage::MigrateVote { store: s2, txn_id: t2, value: v2 }) => *s1 == *s2 && *t1 == *t2 && *v1 == *v2,
+            (ExecMessage::MigrateApply { store: s1, txn_id: t1, value: v1 },
+             ExecMessage::MigrateApply { store: s2, txn_id: t2, value: v2 }) => *s1 == *s2 && *t1 == *t2 && *v1 == *v2,
             _ => false,
         }
     }
@@ -68,6 +82,12 @@ impl ExecMessage {
                 ExecMessage::UnlockReq { store: *store, txn_id: *txn_id },
             ExecMessage::UnlockResp { store, txn_id } =>
                 ExecMessage::UnlockResp { store: *store, txn_id: *txn_id },
+            ExecMessage::Decide { store, commit, txn_id } =>
+                ExecMessage::Decide { store: *store, commit: *commit, txn_id: *txn_id },
+            ExecMessage::MigrateVote { store, txn_id, value } =>
+                ExecMessage::MigrateVote { store: *store, txn_id: *txn_id, value: *value },
+            ExecMessage::MigrateApply { store, txn_id, value } =>
+                ExecMessage::MigrateApply { store: *store, txn_id: *txn_id, value: *value },
         }
     }
 }
@@ -89,6 +109,12 @@ impl View for ExecMessage {
                 Message::UnlockReq { store: store as nat, txn_id: txn_id as nat },
             ExecMessage::UnlockResp { store, txn_id } =>
                 Message::UnlockResp { store: store as nat, txn_id: txn_id as nat },
+            ExecMessage::Decide { store, commit, txn_id } =>
+                Message::Decide { store: store as nat, commit, txn_id: txn_id as nat },
+            ExecMessage::MigrateVote { store, txn_id, value } =>
+                Message::MigrateVote { store: store as nat, txn_id: txn_id as nat, value },
+            ExecMessage::MigrateApply { store, txn_id, value } =>
+                Message::MigrateApply { store: store as nat, txn_id: txn_id as nat, value },
         }
     }
 }
@@ -146,6 +172,30 @@ impl ExecMessage {
         ExecMessage::UnlockResp { store, txn_id }
     }
 
+    /// Create a decide message carrying the final decision
+    pub fn decide(store: u64, commit: bool, txn_id: u64) -> (result: Self)
+        ensures
+            result@ == decide_msg(store as nat, commit, txn_id as nat)
+    {
+        ExecMessage::Decide { store, commit, txn_id }
+    }
+
+    /// Create a migration vote message reporting the value src hands over
+    pub fn migrate_vote(store: u64, txn_id: u64, value: u64) -> (result: Self)
+        ensures
+            result@ == migrate_vote_msg(store as nat, txn_id as nat, value)
+    {
+        ExecMessage::MigrateVote { store, txn_id, value }
+    }
+
+    /// Create a migration apply message telling dst which value to insert
+    pub fn migrate_apply(store: u64, txn_id: u64, value: u64) -> (result: Self)
+        ensures
+            result@ == migrate_apply_msg(store as nat, txn_id as nat, value)
+    {
+        ExecMessage::MigrateApply { store, txn_id, value }
+    }
+
     // ============================================================
     // ACCESSORS
     // ============================================================
@@ -162,6 +212,9 @@ impl ExecMessage {
             ExecMessage::RenameResp { store, .. } => *store,
             ExecMessage::UnlockReq { store, .. } => *store,
             ExecMessage::UnlockResp { store, .. } => *store,
+            ExecMessage::Decide { store, .. } => *store,
+            ExecMessage::MigrateVote { store, .. } => *store,
+            ExecMessage::MigrateApply { store, .. } => *store,
         }
     }
 
@@ -177,6 +230,181 @@ impl ExecMessage {
             ExecMessage::RenameResp { txn_id, .. } => *txn_id,
             ExecMessage::UnlockReq { txn_id, .. } => *txn_id,
             ExecMessage::UnlockResp { txn_id, .. } => *txn_id,
+            ExecMessage::Decide { txn_id, .. } => *txn_id,
+            ExecMessage::MigrateVote { txn_id, .. } => *txn_id,
+            ExecMessage::MigrateApply { txn_id, .. } => *txn_id,
+        }
+    }
+
+    /// Check if the message is for `store` and `txn_id`, whatever its kind
+    pub fn matches_store_txn(&self, store: u64, txn_id: u64) -> (result: bool)
+        ensures
+            result == (self@.get_store() == store as nat && self@.get_txn_id() == txn_id as nat)
+    {
+        self.get_store() == store && self.get_txn_id() == txn_id
+    }
+
+    /// Return the same message with its transaction ID replaced
+    /// (used to reissue a request under a fresh txn after recovery)
+    pub fn with_txn(self, new_txn: u64) -> (result: ExecMessage)
+        ensures
+            result@ == self@.with_txn(new_txn as nat),
+            result@.get_txn_id() == new_txn as nat,
+            result@.get_store() == self@.get_store(),
+    {
+        match self {
+            ExecMessage::LockReq { store, .. } =>
+                ExecMessage::LockReq { store, txn_id: new_txn },
+            ExecMessage::LockResp { store, success, .. } =>
+                ExecMessage::LockResp { store, success, txn_id: new_txn },
+            ExecMessage::RenameReq { store, .. } =>
+                ExecMessage::RenameReq { store, txn_id: new_txn },
+            ExecMessage::RenameResp { store, .. } =>
+                ExecMessage::RenameResp { store, txn_id: new_txn },
+            ExecMessage::UnlockReq { store, .. } =>
+                ExecMessage::UnlockReq { store, txn_id: new_txn },
+            ExecMessage::UnlockResp { store, .. } =>
+                ExecMessage::UnlockResp { store, txn_id: new_txn },
+            ExecMessage::Decide { store, commit, .. } =>
+                ExecMessage::Decide { store, commit, txn_id: new_txn },
+            ExecMessage::MigrateVote { store, value, .. } =>
+                ExecMessage::MigrateVote { store, txn_id: new_txn, value },
+            ExecMessage::MigrateApply { store, value, .. } =>
+                ExecMessage::MigrateApply { store, txn_id: new_txn, value },
+        }
+    }
+
+    /// Encoded size in bytes: a 1-byte kind tag, 8-byte store and 8-byte txn_id,
+    /// plus a 1-byte flag for LockResp and Decide or an 8-byte value for
+    /// MigrateVote and MigrateApply (the fields WireMessage carries)
+    pub open spec fn spec_byte_size(&self) -> nat {
+        match self {
+            ExecMessage::LockResp { .. } | ExecMessage::Decide { .. } => 18,
+            ExecMessage::MigrateVote { .. } | ExecMessage::MigrateApply { .. } => 25,
+            _ => 17,
+        }
+    }
+
+    /// Encoded size in bytes (see `spec_byte_size`); at most `MAX_MSG_BYTES`
+    pub fn byte_size(&self) -> (result: usize)
+        ensures
+            result as nat == self.spec_byte_size(),
+            result <= MAX_MSG_BYTES,
+    {
+        match self {
+            ExecMessage::LockResp { .. } | ExecMessage::Decide { .. } => 18,
+            ExecMessage::MigrateVote { .. } | ExecMessage::MigrateApply { .. } => 25,
+            _ => 17,
+        }
+    }
+
+    /// Get the kind of this message
+    pub fn kind(&self) -> (result: MsgKind)
+        ensures
+            result == self@.kind()
+    {
+        match self {
+            ExecMessage::LockReq { .. } => MsgKind::LockReq,
+            ExecMessage::LockResp { .. } => MsgKind::LockResp,
+            ExecMessage::RenameReq { .. } => MsgKind::RenameReq,
+            ExecMessage::RenameResp { .. } => MsgKind::RenameResp,
+            ExecMessage::UnlockReq { .. } => MsgKind::UnlockReq,
+            ExecMessage::UnlockResp { .. } => MsgKind::UnlockResp,
+            ExecMessage::Decide { .. } => MsgKind::Decide,
+            ExecMessage::MigrateVote { .. } => MsgKind::MigrateVote,
+            ExecMessage::MigrateApply { .. } => MsgKind::MigrateApply,
+        }
+    }
+
+    /// Stable numeric discriminant: the kind index (0 = LockReq ... 5 = UnlockResp,
+    /// 6 = Decide, 7 = MigrateVote, 8 = MigrateApply), the same numbering
+    /// kind_histogram is indexed by
+    pub fn tag(&self) -> (result: u8)
+        ensures
+            result as int == self@.kind().spec_index(),
+            result < 9,
+    {
+        self.kind().index() as u8
+    }
+
+    /// The boolean payload: LockResp's `success` or Decide's `commit`, false otherwise
+    pub fn flag(&self) -> (result: bool)
+        ensures
+            result == self@.flag(),
+    {
+        match self {
+            ExecMessage::LockResp { success, .. } => *success,
+            ExecMessage::Decide { commit, .. } => *commit,
+            _ => false,
+        }
+    }
+
+    /// The migrated value: MigrateVote's or MigrateApply's `value`, 0 otherwise
+    pub fn value(&self) -> (result: u64)
+        ensures
+            result == self@.value(),
+    {
+        match self {
+            ExecMessage::MigrateVote { value, .. } => *value,
+            ExecMessage::MigrateApply { value, .. } => *value,
+            _ => 0,
+        }
+    }
+
+    /// Build a message from its tag and payload (inverse of `tag`, `get_store`,
+    /// `get_txn_id`, `flag` and `value`). None if `tag` is not a kind index;
+    /// `success` is ignored by kinds without a boolean field, `value` by kinds
+    /// that carry no value
+    pub fn from_tag(tag: u8, store: u64, txn_id: u64, success: bool, value: u64) -> (result: Option<ExecMessage>)
+        ensures
+            match result {
+                Some(m) => message_from_tag(tag as int, store as nat, txn_id as nat, success, value) == Some(m@),
+                None => message_from_tag(tag as int, store as nat, txn_id as nat, success, value).is_none(),
+            },
+            result.is_some() == (tag < 9),
+    {
+        match tag {
+            0 => Some(ExecMessage::LockReq { store, txn_id }),
+            1 => Some(ExecMessage::LockResp { store, success, txn_id }),
+            2 => Some(ExecMessage::RenameReq { store, txn_id }),
+            3 => Some(ExecMessage::RenameResp { store, txn_id }),
+            4 => Some(ExecMessage::UnlockReq { store, txn_id }),
+            5 => Some(ExecMessage::UnlockResp { store, txn_id }),
+            6 => Some(ExecMessage::Decide { store, commit: success, txn_id }),
+            7 => Some(ExecMessage::MigrateVote { store, txn_id, value }),
+            8 => Some(ExecMessage::MigrateApply { store, txn_id, value }),
+            _ => None,
+        }
+    }
+
+    /// Canonical ordering key: (kind index, store, txn_id, flag, value).
+    /// The flag is LockResp's `success` or Decide's `commit`, false otherwise;
+    /// the value is 0 for kinds that carry none
+    pub fn order_key(&self) -> (result: (usize, u64, u64, bool, u64))
+        ensures
+            result.0 as int == self@.kind().spec_index(),
+            result.1 as nat == self@.get_store(),
+            result.2 as nat == self@.get_txn_id(),
+            result.4 == self@.value(),
+    {
+        (self.kind().index(), self.get_store(), self.get_txn_id(), self.flag(), self.value())
+    }
+
+    /// Strictly before `other` in the canonical order
+    /// (kind, then store, then txn, then success flag, then value)
+    pub fn precedes(&self, other: &Self) -> (result: bool) {
+        let (k1, s1, t1, ok1, v1) = self.order_key();
+        let (k2, s2, t2, ok2, v2) = other.order_key();
+        if k1 != k2 {
+            k1 < k2
+        } else if s1 != s2 {
+            s1 < s2
+        } else if t1 != t2 {
+            t1 < t2
+        } else if ok1 != ok2 {
+            !ok1 && ok2
+        } else {
+            v1 < v2
         }
     }
 
@@ -189,6 +417,8 @@ impl ExecMessage {
             ExecMessage::LockReq { .. } => true,
             ExecMessage::RenameReq { .. } => true,
             ExecMessage::UnlockReq { .. } => true,
+            ExecMessage::Decide { .. } => true,
+            ExecMessage::MigrateApply { .. } => true,
             _ => false,
         }
     }
@@ -202,6 +432,7 @@ impl ExecMessage {
             ExecMessage::LockResp { .. } => true,
             ExecMessage::RenameResp { .. } => true,
             ExecMessage::UnlockResp { .. } => true,
+            ExecMessage::MigrateVote { .. } => true,
             _ => false,
         }
     }
@@ -227,12 +458,244 @@ impl ExecMessage {
             _ => false,
         }
     }
+
+    /// The response a store sends for this request: LockResp (carrying
+    /// `success`), RenameResp or UnlockResp, for the same store and txn.
+    /// A Decide is acknowledged with the UnlockResp that closes it. A MigrateApply is acknowledged with a
+    /// RenameResp, like the RenameReq it stands in for at the destination.
+    /// `success` only matters for a LockReq. Returns None for a response
+    pub fn expected_response(&self, success: bool) -> (result: Option<ExecMessage>)
+        ensures
+            result.is_some() == self@.is_request(),
+            self@.kind() == MsgKind::LockReq ==>
+                result.unwrap()@ == lock_resp_msg(self@.get_store(), success, self@.get_txn_id()),
+            self@.kind() == MsgKind::RenameReq ==>
+                result.unwrap()@ == rename_resp_msg(self@.get_store(), self@.get_txn_id()),
+            self@.kind() == MsgKind::UnlockReq ==>
+                result.unwrap()@ == unlock_resp_msg(self@.get_store(), self@.get_txn_id()),
+            self@.kind() == MsgKind::Decide ==>
+                result.unwrap()@ == unlock_resp_msg(self@.get_store(), self@.get_txn_id()),
+            self@.kind() == MsgKind::MigrateApply ==>
+                result.unwrap()@ == rename_resp_msg(self@.get_store(), self@.get_txn_id()),
+            result.is_some() ==> {
+                &&& result.unwrap()@.is_response()
+                &&& result.unwrap()@.get_store() == self@.get_store()
+                &&& result.unwrap()@.get_txn_id() == self@.get_txn_id()
+            },
+    {
+        match self {
+            ExecMessage::LockReq { store, txn_id } => Some(ExecMessage::lock_resp(*store, success, *txn_id)),
+            ExecMessage::RenameReq { store, txn_id } => Some(ExecMessage::rename_resp(*store, *txn_id)),
+            ExecMessage::UnlockReq { store, txn_id } => Some(ExecMessage::unlock_resp(*store, *txn_id)),
+            ExecMessage::Decide { store, txn_id, .. } => Some(ExecMessage::unlock_resp(*store, *txn_id)),
+            ExecMessage::MigrateApply { store, txn_id, .. } => Some(ExecMessage::rename_resp(*store, *txn_id)),
+            _ => None,
+        }
+    }
+
+    /// Whether the coordinator should process this message in `phase`: a
+    /// LockResp or MigrateVote only while Preparing, a RenameResp only while
+    /// Committed and an UnlockResp only during Cleanup. Requests are for stores, never the
+    /// coordinator. Lets a dispatcher drop or defer out-of-phase messages
+    pub fn valid_in_phase(&self, phase: CoordPhase) -> (result: bool)
+        ensures
+            result == (
+                ((self@.kind() == MsgKind::LockResp || self@.kind() == MsgKind::MigrateVote)
+                    && phase == CoordPhase::Preparing)
+                || (self@.kind() == MsgKind::RenameResp && phase == CoordPhase::Committed)
+                || (self@.kind() == MsgKind::UnlockResp && phase == CoordPhase::Cleanup)),
+    {
+        match self {
+            ExecMessage::LockResp { .. } | ExecMessage::MigrateVote { .. } => phase == CoordPhase::Preparing,
+            ExecMessage::RenameResp { .. } => phase == CoordPhase::Committed,
+            ExecMessage::UnlockResp { .. } => phase == CoordPhase::Cleanup,
+            _ => false,
+        }
+    }
 }
 
 // ============================================================
 // EXECUTABLE NETWORK (MOCKED WITH VEC)
 // ============================================================
 
+/// Number of messages in `msgs` of kind `kind`
+pub open spec fn spec_count_kind_in(msgs: Seq<ExecMessage>, kind: MsgKind) -> nat
+    decreases msgs.len()
+{
+    if msgs.len() == 0 {
+        0
+    } else {
+        spec_count_kind_in(msgs.drop_last(), kind) + if msgs.last()@.kind() == kind { 1nat } else { 0nat }
+    }
+}
+
+/// Number of messages in `msgs` whose view is `msg`
+pub open spec fn spec_count_in(msgs: Seq<ExecMessage>, msg: Message) -> nat
+    decreases msgs.len()
+{
+    if msgs.len() == 0 {
+        0
+    } else {
+        spec_count_in(msgs.drop_last(), msg) + if msgs.last()@ == msg { 1nat } else { 0nat }
+    }
+}
+
+/// A message with no copy in `msgs` has count 0
+pub proof fn lemma_count_in_absent(msgs: Seq<ExecMessage>, msg: Message)
+    requires
+        forall|k: int| 0 <= k < msgs.len() ==> #[trigger] msgs[k]@ != msg,
+    ensures
+        spec_count_in(msgs, msg) == 0,
+    decreases msgs.len()
+{
+    if msgs.len() > 0 {
+        assert(msgs.last() == msgs[msgs.len() - 1]);
+        lemma_count_in_absent(msgs.drop_last(), msg);
+    }
+}
+
+/// In a sequence of pairwise-distinct messages, every present message has count 1
+pub proof fn lemma_count_in_distinct(msgs: Seq<ExecMessage>, msg: Message)
+    requires
+        forall|a: int, b: int| 0 <= a < b < msgs.len() ==> msgs[a]@ != msgs[b]@,
+        exists|k: int| 0 <= k < msgs.len() && msgs[k]@ == msg,
+    ensures
+        spec_count_in(msgs, msg) == 1,
+    decreases msgs.len()
+{
+    let prefix = msgs.drop_last();
+    assert(msgs.last() == msgs[msgs.len() - 1]);
+    if msgs.last()@ == msg {
+        // Distinctness keeps every earlier copy away from msg
+        assert forall|k: int| 0 <= k < prefix.len() implies #[trigger] prefix[k]@ != msg by {
+            assert(prefix[k] == msgs[k]);
+        }
+        lemma_count_in_absent(prefix, msg);
+    } else {
+        let k = choose|k: int| 0 <= k < msgs.len() && msgs[k]@ == msg;
+        assert(k < prefix.len() && prefix[k] == msgs[k]);
+        lemma_count_in_distinct(prefix, msg);
+    }
+}
+
+/// Number of messages in `msgs` belonging to transaction `txn_id`
+pub open spec fn spec_count_txn_in(msgs: Seq<ExecMessage>, txn_id: nat) -> nat
+    decreases msgs.len()
+{
+    if msgs.len() == 0 {
+        0
+    } else {
+        spec_count_txn_in(msgs.drop_last(), txn_id)
+            + if msgs.last()@.get_txn_id() == txn_id { 1nat } else { 0nat }
+    }
+}
+
+/// Total encoded size of `msgs` in bytes
+pub open spec fn spec_total_bytes_in(msgs: Seq<ExecMessage>) -> nat
+    decreases msgs.len()
+{
+    if msgs.len() == 0 {
+        0
+    } else {
+        spec_total_bytes_in(msgs.drop_last()) + msgs.last().spec_byte_size()
+    }
+}
+
+/// Delivery time recorded for sequence number `seq` in `delays` (the latest
+/// entry wins), or None if that message was sent without a delay
+pub open spec fn spec_delay_of(delays: Seq<(u64, u64)>, seq: u64) -> Option<u64>
+    decreases delays.len()
+{
+    if delays.len() == 0 {
+        None
+    } else if delays.last().0 == seq {
+        Some(delays.last().1)
+    } else {
+        spec_delay_of(delays.drop_last(), seq)
+    }
+}
+
+/// Number of `seqs` whose delivery time in `delays` is later than `now`
+pub open spec fn spec_pending_delayed_in(seqs: Seq<u64>, delays: Seq<(u64, u64)>, now: u64) -> nat
+    decreases seqs.len()
+{
+    if seqs.len() == 0 {
+        0
+    } else {
+        spec_pending_delayed_in(seqs.drop_last(), delays, now) + match spec_delay_of(delays, seqs.last()) {
+            Some(t) => if t > now { 1nat } else { 0nat },
+            None => 0nat,
+        }
+    }
+}
+
+/// Copy of a delay table
+fn copy_delays(delays: &[(u64, u64)]) -> (result: Vec<(u64, u64)>)
+    ensures
+        result@ == delays@,
+{
+    let mut result: Vec<(u64, u64)> = Vec::new();
+    let mut i: usize = 0;
+    while i < delays.len()
+        invariant
+            0 <= i <= delays.len(),
+            result@ == delays@.subrange(0, i as int),
+        decreases
+            delays.len() - i,
+    {
+        result.push(delays[i]);
+        i = i + 1;
+    }
+    proof {
+        assert(result@ =~= delays@);
+    }
+    result
+}
+
+/// Whether `store` is one of `stores`
+pub open spec fn spec_store_listed(stores: Seq<u64>, store: nat) -> bool {
+    exists|i: int| 0 <= i < stores.len() && stores[i] as nat == store
+}
+
+/// Number of requests in `msgs` addressed to store `store` (its inbox);
+/// responses from the store are on their way to the coordinator and do not count
+pub open spec fn spec_count_store_in(msgs: Seq<ExecMessage>, store: nat) -> nat
+    decreases msgs.len()
+{
+    if msgs.len() == 0 {
+        0
+    } else {
+        spec_count_store_in(msgs.drop_last(), store)
+            + if msgs.last()@.is_request() && msgs.last()@.get_store() == store { 1nat } else { 0nat }
+    }
+}
+
+/// Most operations an `ExecNetwork` log retains (see `ExecNetwork::log`)
+pub const NET_LOG_CAP: usize = 4096;
+
+/// One recorded network operation (see `ExecNetwork::record_trace`).
+/// `Deliver` and `Lose` both remove one copy of the message; they are kept
+/// apart so a trace tells a received message from a dropped one.
+#[derive(Debug)]
+pub enum NetOp {
+    Send(ExecMessage),
+    Deliver(ExecMessage),
+    Lose(ExecMessage),
+    Duplicate(ExecMessage),
+}
+
+impl NetOp {
+    /// Clone the operation
+    pub fn clone(&self) -> (result: Self) {
+        match self {
+            NetOp::Send(m) => NetOp::Send(m.clone()),
+            NetOp::Deliver(m) => NetOp::Deliver(m.clone()),
+            NetOp::Lose(m) => NetOp::Lose(m.clone()),
+            NetOp::Duplicate(m) => NetOp::Duplicate(m.clone()),
+        }
+    }
+}
+
 /// Executable network implementation using Vec as a message queue.
 /// This is a mocked/simulated network for testing purposes.
 /// 
@@ -242,9 +705,33 @@ impl ExecMessage {
 /// - receive() removes and returns the first matching message
 /// - lose() removes one copy of a message (simulates network loss)
 /// - duplicate() adds another copy (simulates network duplication)
+/// - every message added gets a sequence number, giving a global send order
+///   that receive_in_order() follows
+/// - every operation is appended to the log (see `log`), so a history can be
+///   replayed; only the last `NET_LOG_CAP` operations are kept
+/// - an optional per-store cap (see with_per_store_cap) makes send() reject
+///   requests for a store that already has that many in its inbox
+/// - send_delayed() tags a message with the simulated time it becomes
+///   deliverable, for event-driven simulation drivers
 pub struct ExecNetwork {
     /// Message queue - stores in-flight messages
     pub messages: Vec<ExecMessage>,
+    /// Sequence number of each in-flight message (parallel to `messages`);
+    /// private so only the network's own operations can renumber the queue
+    seqs: Vec<u64>,
+    /// Sequence number assigned to the next message added
+    pub next_seq: u64,
+    /// The most recent send/lose/duplicate operations, in order (for
+    /// record_trace); at most `NET_LOG_CAP`, older ones are discarded
+    log: Vec<NetOp>,
+    /// Number of operations discarded from the front of `log`
+    log_dropped: u64,
+    /// Max in-flight messages per store accepted by send (None = unbounded)
+    pub per_store_cap: Option<usize>,
+    /// (sequence number, deliver_at) for messages sent with send_delayed.
+    /// Messages without an entry are deliverable at once; entries for messages
+    /// no longer in flight are ignored
+    pub delays: Vec<(u64, u64)>,
 }
 
 impl ExecNetwork {
@@ -269,6 +756,132 @@ impl ExecNetwork {
         exists|i: int| 0 <= i < self.messages@.len() && self.messages@[i]@ == msg
     }
 
+    /// Spec view of the in-flight sequence numbers
+    pub closed spec fn spec_seqs(&self) -> Seq<u64> {
+        self.seqs@
+    }
+
+    /// Spec view of the retained log
+    pub closed spec fn spec_log(&self) -> Seq<NetOp> {
+        self.log@
+    }
+
+    /// Spec view of the number of operations discarded from the log
+    pub closed spec fn spec_log_dropped(&self) -> nat {
+        self.log_dropped as nat
+    }
+
+    /// Spec function: every in-flight message has a sequence number
+    pub open spec fn spec_wf(&self) -> bool {
+        self.spec_seqs().len() == self.messages@.len()
+    }
+
+    /// Spec function: the message at index i has the lowest in-flight sequence number
+    pub open spec fn spec_is_min_seq(&self, i: int) -> bool {
+        &&& 0 <= i < self.spec_seqs().len()
+        &&& forall|j: int| 0 <= j < self.spec_seqs().len() ==> self.spec_seqs()[i] <= self.spec_seqs()[j]
+    }
+
+    /// Spec function: index i holds the first (lowest-index) copy of msg
+    pub open spec fn spec_is_first_match(&self, i: int, msg: Message) -> bool {
+        &&& 0 <= i < self.messages@.len()
+        &&& self.messages@[i]@ == msg
+        &&& forall|j: int| 0 <= j < i ==> self.messages@[j]@ != msg
+    }
+
+    /// Spec function: `msgs` with its first copy of `msg` removed, or `msgs`
+    /// itself if it holds none (what `lose` does to the queue)
+    pub open spec fn spec_lose_first(msgs: Seq<ExecMessage>, msg: Message) -> Seq<ExecMessage> {
+        if exists|i: int| #![auto] 0 <= i < msgs.len() && msgs[i]@ == msg
+            && forall|j: int| 0 <= j < i ==> msgs[j]@ != msg {
+            let i = choose|i: int| #![auto] 0 <= i < msgs.len() && msgs[i]@ == msg
+                && forall|j: int| 0 <= j < i ==> msgs[j]@ != msg;
+            msgs.remove(i)
+        } else {
+            msgs
+        }
+    }
+
+    /// Spec function: `msgs` after losing one copy of each message of
+    /// `schedule` in order (`spec_lose_first` folded over `schedule`)
+    pub open spec fn spec_after_losses(msgs: Seq<ExecMessage>, schedule: Seq<ExecMessage>) -> Seq<ExecMessage>
+        decreases schedule.len()
+    {
+        if schedule.len() == 0 {
+            msgs
+        } else {
+            Self::spec_lose_first(Self::spec_after_losses(msgs, schedule.drop_last()), schedule.last()@)
+        }
+    }
+
+    /// Lemma: the first match of a message is unique, so `receive` has exactly
+    /// one possible post-state for a given queue
+    pub proof fn lemma_first_match_unique(&self, i: int, j: int, msg: Message)
+        requires
+            self.spec_is_first_match(i, msg),
+            self.spec_is_first_match(j, msg),
+        ensures
+            i == j,
+    {
+        if i < j {
+            assert(self.messages@[i]@ != msg);
+        } else if j < i {
+            assert(self.messages@[j]@ != msg);
+        }
+    }
+
+    /// Spec function: number of copies of a message in the queue
+    pub open spec fn spec_count(&self, msg: Message) -> nat {
+        spec_count_in(self.messages@, msg)
+    }
+
+    /// Spec function: number of in-flight messages of a kind
+    pub open spec fn spec_count_kind(&self, kind: MsgKind) -> nat {
+        spec_count_kind_in(self.messages@, kind)
+    }
+
+    /// Spec function: number of in-flight messages for a transaction
+    pub open spec fn spec_count_txn(&self, txn_id: nat) -> nat {
+        spec_count_txn_in(self.messages@, txn_id)
+    }
+
+    /// Spec function: number of in-flight requests addressed to a store
+    pub open spec fn spec_count_store(&self, store: nat) -> nat {
+        spec_count_store_in(self.messages@, store)
+    }
+
+    /// Spec function: total encoded size of the in-flight messages
+    pub open spec fn spec_total_bytes(&self) -> nat {
+        spec_total_bytes_in(self.messages@)
+    }
+
+    /// Spec function: send() would accept a request for this store (its inbox
+    /// is under the per-store cap and a sequence number is left to give it)
+    pub open spec fn spec_has_room(&self, store: nat) -> bool {
+        &&& self.next_seq < u64::MAX
+        &&& match self.per_store_cap {
+            Some(cap) => self.spec_count_store(store) < cap,
+            None => true,
+        }
+    }
+
+    /// Spec function: send() would accept `msg`. Only requests are held to
+    /// their store's cap; a response just needs a sequence number
+    pub open spec fn spec_accepts(&self, msg: Message) -> bool {
+        if msg.is_request() {
+            self.spec_has_room(msg.get_store())
+        } else {
+            self.next_seq < u64::MAX
+        }
+    }
+
+    /// Spec function: the next `n` sends are all accepted (no per-store cap,
+    /// and `n` sequence numbers left)
+    pub open spec fn spec_accepts_next(&self, n: nat) -> bool {
+        &&& self.per_store_cap is None
+        &&& self.next_seq + n <= u64::MAX
+    }
+
     // ============================================================
     // EXEC FUNCTIONS
     // ============================================================
@@ -277,142 +890,1247 @@ impl ExecNetwork {
     pub fn new() -> (result: Self)
         ensures
             result.spec_is_empty(),
+            result.spec_wf(),
             !result.spec_contains(lock_req_msg(0, 0)),  // example: empty means no messages
+            result.per_store_cap is None,
     {
-        ExecNetwork { messages: Vec::new() }
+        ExecNetwork {
+            messages: Vec::new(),
+            seqs: Vec::new(),
+            next_seq: 0,
+            log: Vec::new(),
+            log_dropped: 0,
+            per_store_cap: None,
+            delays: Vec::new(),
+        }
     }
 
-    /// Send a message (add to the queue)
-    pub fn send(&mut self, msg: ExecMessage)
+    /// Limit every store to `cap` in-flight requests (fair queuing): once a
+    /// store has `cap` requests pending, send() drops further ones for it while
+    /// other stores still accept. Its responses are not capped. Messages already
+    /// in flight are kept
+    pub fn with_per_store_cap(self, cap: usize) -> (result: Self)
         ensures
-            self.spec_contains(msg@),
-            self.messages@.len() == old(self).messages@.len() + 1,
+            result.messages@ == self.messages@,
+            result.spec_seqs() == self.spec_seqs(),
+            result.next_seq == self.next_seq,
+            result.per_store_cap == Some(cap),
     {
-        let ghost old_len = self.messages@.len();
-        self.messages.push(msg);
-        proof {
-            // The pushed message is at the last index
-            assert(self.messages@[old_len as int]@ == msg@);
-        }
+        ExecNetwork { per_store_cap: Some(cap), ..self }
     }
 
-    /// Check if the network contains a message
-    pub fn contains(&self, msg: &ExecMessage) -> (result: bool)
+    /// Independent copy of the network: queue, sequence numbers, log, cap and delays
+    pub fn snapshot(&self) -> (result: Self)
         ensures
-            result == self.spec_contains(msg@)
+            result.delays@ == self.delays@,
+            result.messages@.len() == self.messages@.len(),
+            forall|i: int| 0 <= i < self.messages@.len() ==> #[trigger] result.messages@[i]@ == self.messages@[i]@,
+            result.spec_seqs() == self.spec_seqs(),
+            result.next_seq == self.next_seq,
+            result.spec_log().len() == self.spec_log().len(),
+            result.spec_log_dropped() == self.spec_log_dropped(),
+            result.per_store_cap == self.per_store_cap,
     {
+        let mut messages: Vec<ExecMessage> = Vec::new();
         let mut i: usize = 0;
         while i < self.messages.len()
             invariant
                 0 <= i <= self.messages.len(),
-                forall|j: int| #![auto] 0 <= j < i ==> self.messages@[j]@ != msg@,
+                messages@.len() == i,
+                forall|j: int| 0 <= j < i ==> #[trigger] messages@[j]@ == self.messages@[j]@,
             decreases
                 self.messages.len() - i,
         {
-            if self.messages[i].eq(msg) {
-                return true;
-            }
+            messages.push(self.messages[i].clone());
             i = i + 1;
         }
-        false
+        let mut seqs: Vec<u64> = Vec::new();
+        let mut i: usize = 0;
+        while i < self.seqs.len()
+            invariant
+                0 <= i <= self.seqs.len(),
+                seqs@ == self.spec_seqs().subrange(0, i as int),
+            decreases
+                self.seqs.len() - i,
+        {
+            seqs.push(self.seqs[i]);
+            i = i + 1;
+        }
+        let log = self.record_trace();
+        proof {
+            assert(seqs@ =~= self.spec_seqs());
+        }
+        ExecNetwork {
+            messages,
+            seqs,
+            next_seq: self.next_seq,
+            log,
+            log_dropped: self.log_dropped,
+            per_store_cap: self.per_store_cap,
+            delays: copy_delays(&self.delays),
+        }
     }
 
-    /// Check if the network is empty
-    pub fn is_empty(&self) -> (result: bool)
+    /// Take the next sequence number
+    fn take_seq(&mut self) -> (seq: u64)
+        requires
+            old(self).next_seq < u64::MAX,
         ensures
-            result == self.spec_is_empty()
+            seq == old(self).next_seq,
+            self.next_seq == old(self).next_seq + 1,
+            self.messages@ == old(self).messages@,
+            self.spec_seqs() == old(self).spec_seqs(),
+            self.per_store_cap == old(self).per_store_cap,
+            self.delays@ == old(self).delays@,
     {
-        self.messages.len() == 0
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq + 1;
+        seq
     }
 
-    /// Receive a message (remove and return the first matching message)
-    /// Returns None if no matching message exists
-    pub fn receive(&mut self, msg: &ExecMessage) -> (result: Option<ExecMessage>)
+    /// Append `op` to the log, discarding the older half once it holds
+    /// `NET_LOG_CAP` operations (amortized O(1) per operation)
+    fn record_op(&mut self, op: NetOp)
         ensures
-            result.is_some() == old(self).spec_contains(msg@),
-            result.is_some() ==> result.unwrap()@ == msg@,
-            result.is_some() ==> self.messages@.len() == old(self).messages@.len() - 1,
-            result.is_none() ==> self.messages@ == old(self).messages@,
+            self.spec_log().len() <= NET_LOG_CAP,
+            self.spec_log().last() == op,
+            self.messages@ == old(self).messages@,
+            self.spec_seqs() == old(self).spec_seqs(),
+            self.next_seq == old(self).next_seq,
+            self.per_store_cap == old(self).per_store_cap,
+            self.delays@ == old(self).delays@,
     {
-        let mut i: usize = 0;
-        while i < self.messages.len()
-            invariant
-                0 <= i <= self.messages.len(),
-                forall|j: int| #![auto] 0 <= j < i ==> self.messages@[j]@ != msg@,
-                self.messages@ == old(self).messages@,
-            decreases
-                self.messages.len() - i,
-        {
-            if self.messages[i].eq(msg) {
-                let removed = self.messages.remove(i);
-                return Some(removed);
-            }
-            i = i + 1;
+        if self.log.len() >= NET_LOG_CAP {
+            let kept = self.log.split_off(NET_LOG_CAP / 2);
+            self.log_dropped = self.log_dropped.saturating_add((NET_LOG_CAP / 2) as u64);
+            self.log = kept;
         }
-        None
+        self.log.push(op);
     }
 
-    /// Lose a message (remove one copy from the network)
-    /// Returns true if a message was removed, false if not found
-    pub fn lose(&mut self, msg: &ExecMessage) -> (result: bool)
+    /// The retained log: the most recent operations, oldest first
+    /// (at most `NET_LOG_CAP`)
+    pub fn log(&self) -> (result: &[NetOp])
         ensures
-            result == old(self).spec_contains(msg@),
-            result ==> self.messages@.len() == old(self).messages@.len() - 1,
-            !result ==> self.messages@ == old(self).messages@,
+            result@ == self.spec_log(),
     {
-        self.receive(msg).is_some()
+        self.log.as_slice()
     }
 
-    /// Duplicate a message (add another copy if it exists)
-    /// Returns true if the message was duplicated, false if not found
-    pub fn duplicate(&mut self, msg: &ExecMessage) -> (result: bool)
+    /// Total operations logged so far, including discarded ones; a position
+    /// to pass to `log_since` later
+    pub fn ops_logged(&self) -> (result: u64)
         ensures
-            result == old(self).spec_contains(msg@),
-            result ==> self.spec_contains(msg@),
-            result ==> self.messages@.len() == old(self).messages@.len() + 1,
-            !result ==> self.messages@ == old(self).messages@,
+            result == self.spec_log_dropped() + self.spec_log().len() || result == u64::MAX,
     {
-        if self.contains(msg) {
-            let ghost old_len = self.messages@.len();
-            self.messages.push(msg.clone());
-            proof {
-                // The pushed message is at the last index
-                assert(self.messages@[old_len as int]@ == msg@);
-            }
-            true
-        } else {
-            false
-        }
+        self.log_dropped.saturating_add(self.log.len() as u64)
     }
 
-    /// Get the number of messages in the network
-    pub fn len(&self) -> (result: usize)
+    /// Whether the log still holds every operation since the network was
+    /// created, so `record_trace` replays to the same network
+    pub fn log_complete(&self) -> (result: bool)
         ensures
-            result == self.messages@.len()
+            result == (self.spec_log_dropped() == 0),
     {
-        self.messages.len()
+        self.log_dropped == 0
     }
 
-    /// Count how many copies of a message are in the network
-    pub fn count(&self, msg: &ExecMessage) -> (result: usize)
+    /// Sequence numbers of the in-flight messages, parallel to the queue
+    pub fn seqs(&self) -> (result: &[u64])
+        ensures
+            result@ == self.spec_seqs(),
     {
-        let mut count: usize = 0;
-        let mut i: usize = 0;
+        self.seqs.as_slice()
+    }
+
+    /// Send a message (add to the queue)
+    /// Dropped (not queued or logged) if it is a request for a store at the
+    /// per-store cap, or the sequence numbers are used up
+    pub fn send(&mut self, msg: ExecMessage)
+        ensures
+            old(self).spec_accepts(msg@) ==> {
+                &&& self.spec_contains(msg@)
+                &&& self.messages@.len() == old(self).messages@.len() + 1
+                &&& self.messages@ == old(self).messages@.push(msg)
+                &&& self.spec_seqs() == old(self).spec_seqs().push(old(self).next_seq)
+                &&& self.next_seq == old(self).next_seq + 1
+            },
+            !old(self).spec_accepts(msg@) ==> {
+                &&& self.messages@ == old(self).messages@
+                &&& self.spec_seqs() == old(self).spec_seqs()
+                &&& self.next_seq == old(self).next_seq
+            },
+            self.per_store_cap == old(self).per_store_cap,
+            self.delays@ == old(self).delays@,
+            old(self).spec_wf() ==> self.spec_wf(),
+    {
+        if self.next_seq == u64::MAX {
+            return;
+        }
+        if let Some(cap) = self.per_store_cap {
+            if msg.is_request() && self.count_for_store(msg.get_store()) >= cap {
+                return;
+            }
+        }
+        let ghost old_len = self.messages@.len();
+        let seq = self.take_seq();
+        self.record_op(NetOp::Send(msg.clone()));
+        self.seqs.push(seq);
+        self.messages.push(msg);
+        proof {
+            // The pushed message is at the last index
+            assert(self.messages@[old_len as int]@ == msg@);
+        }
+    }
+
+    /// Send a message that only becomes deliverable at simulated time
+    /// `deliver_at`. Queued exactly as by send; the delay is recorded against
+    /// its sequence number. Delays are advisory: receive() does not check them,
+    /// and a duplicated copy gets a fresh sequence number and no delay
+    pub fn send_delayed(&mut self, msg: ExecMessage, deliver_at: u64)
+        ensures
+            old(self).spec_accepts(msg@) ==> {
+                &&& self.messages@ == old(self).messages@.push(msg)
+                &&& self.spec_seqs() == old(self).spec_seqs().push(old(self).next_seq)
+                &&& self.delays@ == old(self).delays@.push((old(self).next_seq, deliver_at))
+                &&& self.next_seq == old(self).next_seq + 1
+            },
+            !old(self).spec_accepts(msg@) ==> {
+                &&& self.messages@ == old(self).messages@
+                &&& self.spec_seqs() == old(self).spec_seqs()
+                &&& self.delays@ == old(self).delays@
+                &&& self.next_seq == old(self).next_seq
+            },
+            self.per_store_cap == old(self).per_store_cap,
+            old(self).spec_wf() ==> self.spec_wf(),
+    {
+        let seq = self.next_seq;
+        let before = self.messages.len();
+        self.send(msg);
+        if self.messages.len() > before {
+            self.delays.push((seq, deliver_at));
+        }
+    }
+
+    /// Merge another network into this one (two partitions healing).
+    /// `other`'s messages are appended after ours, each with a fresh sequence
+    /// number (keeping its delay, if it was sent with send_delayed).
+    /// On a capped network, messages over a store's cap are dropped as by send
+    pub fn merge(&mut self, other: ExecNetwork)
+        ensures
+            old(self).spec_accepts_next(other.messages@.len()) ==>
+                self.messages@.len() == old(self).messages@.len() + other.messages@.len(),
+            old(self).spec_accepts_next(other.messages@.len()) ==> forall|m: Message| #![auto]
+                self.spec_count(m) == old(self).spec_count(m) + other.spec_count(m),
+            self.per_store_cap == old(self).per_store_cap,
+            old(self).spec_wf() ==> self.spec_wf(),
+    {
+        let mut i: usize = 0;
+        while i < other.messages.len()
+            invariant
+                0 <= i <= other.messages.len(),
+                self.per_store_cap == old(self).per_store_cap,
+                old(self).spec_accepts_next(other.messages@.len()) ==> {
+                    &&& self.messages@.len() == old(self).messages@.len() + i
+                    &&& self.next_seq == old(self).next_seq + i
+                },
+                old(self).spec_accepts_next(other.messages@.len()) ==> forall|m: Message| #![auto]
+                    self.spec_count(m) == old(self).spec_count(m)
+                    + spec_count_in(other.messages@.subrange(0, i as int), m),
+                old(self).spec_wf() ==> self.spec_wf(),
+            decreases
+                other.messages.len() - i,
+        {
+            let msg = other.messages[i].clone();
+            proof {
+                assert(other.messages@[i as int]@ == msg@);
+                // Extending the prefix by one message adds its match (if any) to the count
+                assert(other.messages@.subrange(0, i as int + 1).drop_last()
+                    =~= other.messages@.subrange(0, i as int));
+            }
+            let mut delay: Option<u64> = None;
+            if i < other.seqs.len() {
+                delay = other.delay_of(other.seqs[i]);
+            }
+            match delay {
+                Some(t) => self.send_delayed(msg, t),
+                None => self.send(msg),
+            }
+            i = i + 1;
+        }
+        proof {
+            assert(other.messages@.subrange(0, other.messages@.len() as int) =~= other.messages@);
+        }
+    }
+
+    /// Partition the network: remove every message for one of `stores` and
+    /// return them as a separate network, isolating those stores' traffic
+    /// (the exec counterpart of `NetworkSpec::restrict_to_store`). Messages keep their
+    /// sequence numbers on both sides; each moved message is logged as a loss
+    /// here and as a send in the returned network. `merge` heals the partition
+    pub fn split(&mut self, stores: &[u64]) -> (result: ExecNetwork)
+        ensures
+            forall|m: Message| #![auto] self.spec_count(m) ==
+                if spec_store_listed(stores@, m.get_store()) { 0 } else { old(self).spec_count(m) },
+            forall|m: Message| #![auto] result.spec_count(m) ==
+                if spec_store_listed(stores@, m.get_store()) { old(self).spec_count(m) } else { 0 },
+            self.messages@.len() + result.messages@.len() == old(self).messages@.len(),
+            old(self).spec_wf() ==> self.spec_wf() && result.spec_wf(),
+            self.per_store_cap == old(self).per_store_cap,
+            result.per_store_cap is None,
+    {
+        let ghost old_msgs = self.messages@;
+        let mut kept: Vec<ExecMessage> = Vec::new();
+        let mut kept_seqs: Vec<u64> = Vec::new();
+        let mut moved: Vec<ExecMessage> = Vec::new();
+        let mut moved_seqs: Vec<u64> = Vec::new();
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                self.messages@ == old_msgs,
+                self.spec_seqs() == old(self).spec_seqs(),
+                self.per_store_cap == old(self).per_store_cap,
+                kept@.len() + moved@.len() == i,
+                old(self).spec_wf() ==> kept_seqs@.len() == kept@.len() && moved_seqs@.len() == moved@.len(),
+                forall|m: Message| #![auto] spec_count_in(kept@, m) ==
+                    if spec_store_listed(stores@, m.get_store()) { 0 }
+                    else { spec_count_in(old_msgs.subrange(0, i as int), m) },
+                forall|m: Message| #![auto] spec_count_in(moved@, m) ==
+                    if spec_store_listed(stores@, m.get_store()) { spec_count_in(old_msgs.subrange(0, i as int), m) }
+                    else { 0 },
+            decreases
+                self.messages.len() - i,
+        {
+            let store = self.messages[i].get_store();
+            let mut listed = false;
+            let mut k: usize = 0;
+            while k < stores.len()
+                invariant
+                    0 <= k <= stores.len(),
+                    listed <==> exists|t: int| 0 <= t < k && stores@[t] == store,
+                decreases
+                    stores.len() - k,
+            {
+                if stores[k] == store {
+                    listed = true;
+                }
+                k = k + 1;
+            }
+            let ghost m_i = old_msgs[i as int]@;
+            let ghost (before_kept, before_moved) = (kept@, moved@);
+            proof {
+                assert(old_msgs.subrange(0, i as int + 1).drop_last() =~= old_msgs.subrange(0, i as int));
+                assert(listed == spec_store_listed(stores@, m_i.get_store()));
+            }
+            let msg = self.messages[i].clone();
+            if listed {
+                self.record_op(NetOp::Lose(msg.clone()));
+                moved.push(msg);
+                if i < self.seqs.len() {
+                    moved_seqs.push(self.seqs[i]);
+                }
+                proof {
+                    assert(moved@.drop_last() =~= before_moved);
+                }
+            } else {
+                kept.push(msg);
+                if i < self.seqs.len() {
+                    kept_seqs.push(self.seqs[i]);
+                }
+                proof {
+                    assert(kept@.drop_last() =~= before_kept);
+                }
+            }
+            i = i + 1;
+        }
+        proof {
+            assert(old_msgs.subrange(0, old_msgs.len() as int) =~= old_msgs);
+        }
+        self.messages = kept;
+        self.seqs = kept_seqs;
+        // Both sides keep their sequence numbers, so both keep the delay table
+        let mut result = ExecNetwork {
+            messages: moved,
+            seqs: moved_seqs,
+            next_seq: self.next_seq,
+            log: Vec::new(),
+            log_dropped: 0,
+            per_store_cap: None,
+            delays: copy_delays(&self.delays),
+        };
+        let ghost (moved_msgs, moved_seq_view) = (result.messages@, result.spec_seqs());
+        let mut j: usize = 0;
+        while j < result.messages.len()
+            invariant
+                0 <= j <= result.messages.len(),
+                result.messages@ == moved_msgs,
+                result.spec_seqs() == moved_seq_view,
+                result.per_store_cap is None,
+            decreases
+                result.messages.len() - j,
+        {
+            let msg = result.messages[j].clone();
+            result.record_op(NetOp::Send(msg));
+            j = j + 1;
+        }
+        result
+    }
+
+    /// Check if the network contains a message
+    pub fn contains(&self, msg: &ExecMessage) -> (result: bool)
+        ensures
+            result == self.spec_contains(msg@)
+    {
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                forall|j: int| #![auto] 0 <= j < i ==> self.messages@[j]@ != msg@,
+            decreases
+                self.messages.len() - i,
+        {
+            if self.messages[i].eq(msg) {
+                return true;
+            }
+            i = i + 1;
+        }
+        false
+    }
+
+    /// Check if the network is empty
+    pub fn is_empty(&self) -> (result: bool)
+        ensures
+            result == self.spec_is_empty()
+    {
+        self.messages.len() == 0
+    }
+
+    /// Receive a message (remove and return the first matching message)
+    /// Returns None if no matching message exists
+    pub fn receive(&mut self, msg: &ExecMessage) -> (result: Option<ExecMessage>)
+        ensures
+            result.is_some() == old(self).spec_contains(msg@),
+            result.is_some() ==> result.unwrap()@ == msg@,
+            result.is_some() ==> self.messages@.len() == old(self).messages@.len() - 1,
+            // Deterministic: exactly the first matching copy is removed
+            result.is_some() ==> exists|i: int| #![auto]
+                old(self).spec_is_first_match(i, msg@)
+                && self.messages@ == old(self).messages@.remove(i)
+                && (old(self).spec_wf() ==> self.spec_seqs() == old(self).spec_seqs().remove(i)),
+            result.is_none() ==> self.messages@ == old(self).messages@,
+            old(self).spec_wf() ==> self.spec_wf(),
+            self.next_seq == old(self).next_seq,
+            self.per_store_cap == old(self).per_store_cap,
+    {
+        let result = self.remove_first(msg);
+        if let Some(m) = &result {
+            self.record_op(NetOp::Deliver(m.clone()));
+        }
+        result
+    }
+
+    /// Remove the first copy of `msg` without logging; `receive` and `lose`
+    /// record it as a delivery or a loss
+    fn remove_first(&mut self, msg: &ExecMessage) -> (result: Option<ExecMessage>)
+        ensures
+            result.is_some() == old(self).spec_contains(msg@),
+            result.is_some() ==> result.unwrap()@ == msg@,
+            result.is_some() ==> self.messages@.len() == old(self).messages@.len() - 1,
+            // Deterministic: exactly the first matching copy is removed
+            result.is_some() ==> exists|i: int| #![auto]
+                old(self).spec_is_first_match(i, msg@)
+                && self.messages@ == old(self).messages@.remove(i)
+                && (old(self).spec_wf() ==> self.spec_seqs() == old(self).spec_seqs().remove(i)),
+            result.is_none() ==> self.messages@ == old(self).messages@,
+            old(self).spec_wf() ==> self.spec_wf(),
+            self.next_seq == old(self).next_seq,
+            self.per_store_cap == old(self).per_store_cap,
+    {
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                forall|j: int| #![auto] 0 <= j < i ==> self.messages@[j]@ != msg@,
+                self.messages@ == old(self).messages@,
+                self.spec_seqs() == old(self).spec_seqs(),
+                self.next_seq == old(self).next_seq,
+                self.per_store_cap == old(self).per_store_cap,
+            decreases
+                self.messages.len() - i,
+        {
+            if self.messages[i].eq(msg) {
+                assert(old(self).spec_is_first_match(i as int, msg@));
+                if i < self.seqs.len() {
+                    self.seqs.remove(i);
+                }
+                let removed = self.messages.remove(i);
+                return Some(removed);
+            }
+            i = i + 1;
+        }
+        None
+    }
+
+    /// Lose a message (remove one copy from the network)
+    /// Returns true if a message was removed, false if not found
+    pub fn lose(&mut self, msg: &ExecMessage) -> (result: bool)
+        ensures
+            result == old(self).spec_contains(msg@),
+            result ==> self.messages@.len() == old(self).messages@.len() - 1,
+            !result ==> self.messages@ == old(self).messages@,
+            self.messages@ == Self::spec_lose_first(old(self).messages@, msg@),
+            old(self).spec_wf() ==> self.spec_wf(),
+            self.per_store_cap == old(self).per_store_cap,
+    {
+        match self.remove_first(msg) {
+            Some(m) => {
+                proof {
+                    // The copy removed is the one `spec_lose_first` chooses
+                    let i = choose|i: int| #![auto] old(self).spec_is_first_match(i, msg@)
+                        && self.messages@ == old(self).messages@.remove(i)
+                        && (old(self).spec_wf() ==> self.spec_seqs() == old(self).spec_seqs().remove(i));
+                    let j = choose|j: int| #![auto] old(self).spec_is_first_match(j, msg@);
+                    old(self).lemma_first_match_unique(i, j, msg@);
+                }
+                self.record_op(NetOp::Lose(m));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Lose one copy of each message in `schedule`, in order, as that run of
+    /// `lose` calls would; scheduled messages no longer in flight are skipped.
+    /// Gives data-driven, replayable loss scenarios. Returns the number lost
+    pub fn apply_loss_schedule(&mut self, schedule: &[ExecMessage]) -> (lost: usize)
+        ensures
+            lost <= schedule@.len(),
+            lost as nat + self.messages@.len() == old(self).messages@.len(),
+            lost == 0 ==> self.messages@ == old(self).messages@,
+            self.messages@ == Self::spec_after_losses(old(self).messages@, schedule@),
+            old(self).spec_wf() ==> self.spec_wf(),
+            self.per_store_cap == old(self).per_store_cap,
+    {
+        let mut lost: usize = 0;
+        let mut i: usize = 0;
+        while i < schedule.len()
+            invariant
+                0 <= i <= schedule@.len(),
+                lost <= i,
+                lost as nat + self.messages@.len() == old(self).messages@.len(),
+                lost == 0 ==> self.messages@ == old(self).messages@,
+                self.messages@ == Self::spec_after_losses(old(self).messages@, schedule@.take(i as int)),
+                old(self).spec_wf() ==> self.spec_wf(),
+                self.per_store_cap == old(self).per_store_cap,
+            decreases
+                schedule.len() - i,
+        {
+            assert(schedule@.take(i as int + 1).drop_last() =~= schedule@.take(i as int));
+            if self.lose(&schedule[i]) {
+                lost = lost + 1;
+            }
+            i = i + 1;
+        }
+        assert(schedule@.take(schedule@.len() as int) =~= schedule@);
+        lost
+    }
+
+    /// Duplicate a message (add another copy if it exists)
+    /// The copy gets a fresh sequence number, as if it were sent again
+    /// Returns true if the message was duplicated, false if not found (or no
+    /// sequence number is left)
+    pub fn duplicate(&mut self, msg: &ExecMessage) -> (result: bool)
+        ensures
+            result == (old(self).spec_contains(msg@) && old(self).next_seq < u64::MAX),
+            result ==> self.spec_contains(msg@),
+            result ==> self.messages@.len() == old(self).messages@.len() + 1,
+            !result ==> self.messages@ == old(self).messages@,
+            old(self).spec_wf() ==> self.spec_wf(),
+            self.per_store_cap == old(self).per_store_cap,
+    {
+        if self.next_seq < u64::MAX && self.contains(msg) {
+            let ghost old_len = self.messages@.len();
+            let seq = self.take_seq();
+            self.seqs.push(seq);
+            self.messages.push(msg.clone());
+            self.record_op(NetOp::Duplicate(msg.clone()));
+            proof {
+                // The pushed message is at the last index
+                assert(self.messages@[old_len as int]@ == msg@);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Collapse duplicates: keep only the first copy of each distinct message
+    /// (with its sequence number), recording each dropped copy as a loss.
+    /// Returns the number of copies removed. The inverse of `duplicate`
+    pub fn dedup(&mut self) -> (removed: usize)
+        ensures
+            removed as nat + self.messages@.len() == old(self).messages@.len(),
+            forall|m: Message| #![auto] self.spec_contains(m) <==> old(self).spec_contains(m),
+            forall|m: Message| #![auto] self.spec_contains(m) ==> self.spec_count(m) == 1,
+            old(self).spec_wf() ==> self.spec_wf(),
+            self.per_store_cap == old(self).per_store_cap,
+    {
+        let ghost old_msgs = self.messages@;
+        let mut kept: Vec<ExecMessage> = Vec::new();
+        let mut kept_seqs: Vec<u64> = Vec::new();
+        let mut removed: usize = 0;
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                self.messages@ == old_msgs,
+                self.spec_seqs() == old(self).spec_seqs(),
+                self.per_store_cap == old(self).per_store_cap,
+                kept@.len() + removed == i,
+                old(self).spec_wf() ==> kept_seqs@.len() == kept@.len(),
+                forall|a: int, b: int| 0 <= a < b < kept@.len() ==> kept@[a]@ != kept@[b]@,
+                // kept holds exactly the distinct messages of the first i
+                forall|m: Message| (exists|k: int| 0 <= k < kept@.len() && #[trigger] kept@[k]@ == m)
+                    <==> (exists|j: int| 0 <= j < i && #[trigger] old_msgs[j]@ == m),
+            decreases
+                self.messages.len() - i,
+        {
+            let mut seen = false;
+            let mut k: usize = 0;
+            while k < kept.len()
+                invariant
+                    0 <= k <= kept.len(),
+                    i < self.messages.len(),
+                    seen <==> exists|t: int| 0 <= t < k && #[trigger] kept@[t]@ == self.messages@[i as int]@,
+                decreases
+                    kept.len() - k,
+            {
+                if kept[k].eq(&self.messages[i]) {
+                    seen = true;
+                }
+                k = k + 1;
+            }
+            let ghost m_i = old_msgs[i as int]@;
+            if seen {
+                self.record_op(NetOp::Lose(self.messages[i].clone()));
+                removed = removed + 1;
+            } else {
+                let ghost before = kept@;
+                kept.push(self.messages[i].clone());
+                if i < self.seqs.len() {
+                    kept_seqs.push(self.seqs[i]);
+                }
+                proof {
+                    assert(kept@[before.len() as int]@ == m_i);
+                    assert forall|k: int| 0 <= k < before.len() implies kept@[k] == before[k] by {}
+                }
+            }
+            proof {
+                assert(old_msgs[i as int]@ == m_i);
+            }
+            i = i + 1;
+        }
+        self.messages = kept;
+        self.seqs = kept_seqs;
+        proof {
+            assert forall|m: Message| #![auto] self.spec_contains(m) implies self.spec_count(m) == 1 by {
+                lemma_count_in_distinct(self.messages@, m);
+            }
+        }
+        removed
+    }
+
+    /// Receive the in-flight message with the lowest sequence number
+    /// (the earliest send still in the network). Returns None if the network is empty
+    pub fn receive_in_order(&mut self) -> (result: Option<ExecMessage>)
+        requires
+            old(self).spec_wf(),
+        ensures
+            result.is_none() == old(self).spec_is_empty(),
+            result.is_none() ==> self.messages@ == old(self).messages@,
+            result.is_some() ==> exists|i: int| #![auto]
+                old(self).spec_is_min_seq(i) && old(self).messages@[i]@ == result.unwrap()@,
+            result.is_some() ==> self.messages@.len() == old(self).messages@.len() - 1,
+            self.spec_wf(),
+            self.next_seq == old(self).next_seq,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let mut min_i: usize = 0;
+        let mut i: usize = 1;
+        while i < self.seqs.len()
+            invariant
+                self.spec_wf(),
+                0 < self.spec_seqs().len(),
+                min_i < i <= self.spec_seqs().len(),
+                forall|j: int| 0 <= j < i ==> self.spec_seqs()[min_i as int] <= self.spec_seqs()[j],
+                *self == *old(self),
+            decreases
+                self.seqs.len() - i,
+        {
+            if self.seqs[i] < self.seqs[min_i] {
+                min_i = i;
+            }
+            i = i + 1;
+        }
+        proof {
+            assert(old(self).spec_is_min_seq(min_i as int));
+        }
+        self.seqs.remove(min_i);
+        let removed = self.messages.remove(min_i);
+        proof {
+            assert(old(self).messages@[min_i as int]@ == removed@);
+        }
+        self.record_op(NetOp::Deliver(removed.clone()));
+        Some(removed)
+    }
+
+    /// Receive the message that comes first in the canonical `ExecMessage` order,
+    /// independent of send order (for deterministic, replayable schedules).
+    /// Returns None if the network is empty
+    pub fn receive_min(&mut self) -> (result: Option<ExecMessage>)
+        requires
+            old(self).spec_wf(),
+        ensures
+            result.is_none() == old(self).spec_is_empty(),
+            result.is_none() ==> self.messages@ == old(self).messages@,
+            result.is_some() ==> old(self).spec_contains(result.unwrap()@),
+            result.is_some() ==> self.messages@.len() == old(self).messages@.len() - 1,
+            self.spec_wf(),
+            self.next_seq == old(self).next_seq,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let mut min_i: usize = 0;
+        let mut i: usize = 1;
+        while i < self.messages.len()
+            invariant
+                self.spec_wf(),
+                0 < self.messages@.len(),
+                min_i < i <= self.messages@.len(),
+                *self == *old(self),
+            decreases
+                self.messages.len() - i,
+        {
+            if self.messages[i].precedes(&self.messages[min_i]) {
+                min_i = i;
+            }
+            i = i + 1;
+        }
+        self.seqs.remove(min_i);
+        let removed = self.messages.remove(min_i);
+        proof {
+            assert(old(self).messages@[min_i as int]@ == removed@);
+        }
+        self.record_op(NetOp::Deliver(removed.clone()));
+        Some(removed)
+    }
+
+    /// Remove and return the message at the front of the queue, whatever its
+    /// kind or store (strict FIFO drain by queue position, unlike the
+    /// content-addressed `receive`). Returns None if the network is empty
+    pub fn pop_oldest(&mut self) -> (result: Option<ExecMessage>)
+        ensures
+            result.is_none() == old(self).spec_is_empty(),
+            result.is_none() ==> self.messages@ == old(self).messages@,
+            result.is_some() ==> result.unwrap()@ == old(self).messages@[0]@,
+            result.is_some() ==> self.messages@ == old(self).messages@.subrange(1, old(self).messages@.len() as int),
+            old(self).spec_wf() ==> self.spec_wf(),
+            self.next_seq == old(self).next_seq,
+            self.per_store_cap == old(self).per_store_cap,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        if !self.seqs.is_empty() {
+            self.seqs.remove(0);
+        }
+        let removed = self.messages.remove(0);
+        proof {
+            assert(old(self).messages@.remove(0) =~= old(self).messages@.subrange(1, old(self).messages@.len() as int));
+        }
+        self.record_op(NetOp::Deliver(removed.clone()));
+        Some(removed)
+    }
+
+    /// Remove and return every in-flight message in queue (arrival) order,
+    /// leaving the network empty: `pop_oldest` until None, in one call, for a
+    /// driver that processes the whole backlog between protocol phases. Each
+    /// message is logged as delivered, and no delivery times are kept
+    pub fn drain_all(&mut self) -> (result: Vec<ExecMessage>)
+        ensures
+            self.spec_is_empty(),
+            self.delays@.len() == 0,
+            result@.len() == old(self).messages@.len(),
+            forall|i: int| 0 <= i < result@.len() ==> #[trigger] result@[i]@ == old(self).messages@[i]@,
+            self.spec_wf(),
+            self.next_seq == old(self).next_seq,
+            self.per_store_cap == old(self).per_store_cap,
+    {
+        let mut result: Vec<ExecMessage> = Vec::new();
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages@.len(),
+                self.messages@ == old(self).messages@,
+                self.next_seq == old(self).next_seq,
+                self.per_store_cap == old(self).per_store_cap,
+                result@.len() == i,
+                forall|j: int| 0 <= j < i ==> #[trigger] result@[j]@ == old(self).messages@[j]@,
+            decreases
+                self.messages.len() - i,
+        {
+            let msg = self.messages[i].clone();
+            self.record_op(NetOp::Deliver(msg.clone()));
+            result.push(msg);
+            i = i + 1;
+        }
+        self.messages.clear();
+        self.seqs.clear();
+        self.delays.clear();
+        result
+    }
+
+    /// Find the first (oldest) message associated with a store
+    /// Returns a copy; the message stays in the network
+    pub fn first_for_store(&self, store: u64) -> (result: Option<ExecMessage>)
+        ensures
+            result.is_some() ==> self.spec_contains(result.unwrap()@),
+            result.is_some() ==> result.unwrap()@.get_store() == store as nat,
+            result.is_none() ==> forall|j: int| #![auto] 0 <= j < self.messages@.len() ==>
+                self.messages@[j]@.get_store() != store as nat,
+    {
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                forall|j: int| #![auto] 0 <= j < i ==> self.messages@[j]@.get_store() != store as nat,
+            decreases
+                self.messages.len() - i,
+        {
+            if self.messages[i].get_store() == store {
+                let found = self.messages[i].clone();
+                proof {
+                    assert(self.messages@[i as int]@ == found@);
+                }
+                return Some(found);
+            }
+            i = i + 1;
+        }
+        None
+    }
+
+    /// Find the first (oldest) message of `kind` for `store` and `txn_id`, e.g.
+    /// to read the value a MigrateVote or MigrateApply carries.
+    /// Returns a copy; the message stays in the network
+    pub fn first_of_kind(&self, kind: MsgKind, store: u64, txn_id: u64) -> (result: Option<ExecMessage>)
+        ensures
+            result.is_some() ==> self.spec_contains(result.unwrap()@),
+            result.is_some() ==> result.unwrap()@.kind() == kind,
+            result.is_some() ==> result.unwrap()@.get_store() == store as nat,
+            result.is_some() ==> result.unwrap()@.get_txn_id() == txn_id as nat,
+    {
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+            decreases
+                self.messages.len() - i,
+        {
+            if self.messages[i].kind() == kind && self.messages[i].matches_store_txn(store, txn_id) {
+                let found = self.messages[i].clone();
+                proof {
+                    assert(self.messages@[i as int]@ == found@);
+                }
+                return Some(found);
+            }
+            i = i + 1;
+        }
+        None
+    }
+
+    /// Get the number of messages in the network
+    pub fn len(&self) -> (result: usize)
+        ensures
+            result == self.messages@.len()
+    {
+        self.messages.len()
+    }
+
+    /// Count how many copies of a message are in the network
+    pub fn count(&self, msg: &ExecMessage) -> (result: usize)
+        ensures
+            result as nat == self.spec_count(msg@)
+    {
+        let mut count: usize = 0;
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                count <= i,
+                count as nat == spec_count_in(self.messages@.subrange(0, i as int), msg@),
+            decreases
+                self.messages.len() - i,
+        {
+            proof {
+                // Extending the prefix by one message adds its match (if any) to the count
+                assert(self.messages@.subrange(0, i as int + 1).drop_last()
+                    =~= self.messages@.subrange(0, i as int));
+            }
+            if self.messages[i].eq(msg) {
+                // count <= i < self.messages.len() <= usize::MAX, so count + 1 won't overflow
+                count = count + 1;
+            }
+            i = i + 1;
+        }
+        proof {
+            assert(self.messages@.subrange(0, self.messages@.len() as int) =~= self.messages@);
+        }
+        count
+    }
+
+    /// Total encoded size of the in-flight messages, for enforcing a memory
+    /// budget rather than a message count
+    pub fn total_bytes(&self) -> (result: usize)
+        requires
+            self.messages@.len() <= usize::MAX / MAX_MSG_BYTES,
+        ensures
+            result as nat == self.spec_total_bytes(),
+            result <= self.messages@.len() * MAX_MSG_BYTES,
+    {
+        let mut total: usize = 0;
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                self.messages@.len() <= usize::MAX / MAX_MSG_BYTES,
+                total <= i * MAX_MSG_BYTES,
+                total as nat == spec_total_bytes_in(self.messages@.subrange(0, i as int)),
+            decreases
+                self.messages.len() - i,
+        {
+            proof {
+                // Extending the prefix by one message adds its size to the total
+                assert(self.messages@.subrange(0, i as int + 1).drop_last()
+                    =~= self.messages@.subrange(0, i as int));
+            }
+            // total + size <= (i + 1) * MAX_MSG_BYTES <= usize::MAX, so this won't overflow
+            total = total + self.messages[i].byte_size();
+            i = i + 1;
+        }
+        proof {
+            assert(self.messages@.subrange(0, self.messages@.len() as int) =~= self.messages@);
+        }
+        total
+    }
+
+    /// Count in-flight requests addressed to a store (what the per-store cap limits)
+    pub fn count_for_store(&self, store: u64) -> (result: usize)
+        ensures
+            result as nat == self.spec_count_store(store as nat)
+    {
+        let mut count: usize = 0;
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                count <= i,
+                count as nat == spec_count_store_in(self.messages@.subrange(0, i as int), store as nat),
+            decreases
+                self.messages.len() - i,
+        {
+            proof {
+                // Extending the prefix by one message adds it to the count if it matches
+                assert(self.messages@.subrange(0, i as int + 1).drop_last()
+                    =~= self.messages@.subrange(0, i as int));
+            }
+            if self.messages[i].is_request() && self.messages[i].get_store() == store {
+                // count <= i < self.messages.len() <= usize::MAX, so count + 1 won't overflow
+                count = count + 1;
+            }
+            i = i + 1;
+        }
+        proof {
+            assert(self.messages@.subrange(0, self.messages@.len() as int) =~= self.messages@);
+        }
+        count
+    }
+
+    /// Runtime check of the system's `well_formed_network` invariant: every
+    /// in-flight message is for one of `all_stores` and carries a txn id no
+    /// newer than `max_txn`. Suitable for `debug_assert!` after a driver or
+    /// test injects messages
+    pub fn is_well_formed(&self, all_stores: &[u64], max_txn: u64) -> (result: bool)
+        ensures
+            result == forall|m: Message| #[trigger] self.spec_contains(m) ==>
+                spec_store_listed(all_stores@, m.get_store()) && m.get_txn_id() <= max_txn as nat,
+    {
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                forall|j: int| #![auto] 0 <= j < i ==>
+                    spec_store_listed(all_stores@, self.messages@[j]@.get_store())
+                        && self.messages@[j]@.get_txn_id() <= max_txn as nat,
+            decreases
+                self.messages.len() - i,
+        {
+            let store = self.messages[i].get_store();
+            let mut listed = false;
+            let mut k: usize = 0;
+            while k < all_stores.len()
+                invariant
+                    0 <= k <= all_stores.len(),
+                    listed <==> exists|t: int| 0 <= t < k && all_stores@[t] == store,
+                decreases
+                    all_stores.len() - k,
+            {
+                if all_stores[k] == store {
+                    listed = true;
+                }
+                k = k + 1;
+            }
+            if !listed || self.messages[i].get_txn_id() > max_txn {
+                proof {
+                    assert(self.spec_contains(self.messages@[i as int]@));
+                }
+                return false;
+            }
+            i = i + 1;
+        }
+        proof {
+            assert forall|m: Message| #[trigger] self.spec_contains(m) implies
+                spec_store_listed(all_stores@, m.get_store()) && m.get_txn_id() <= max_txn as nat by {
+                let j = choose|j: int| 0 <= j < self.messages@.len() && self.messages@[j]@ == m;
+                assert(spec_store_listed(all_stores@, self.messages@[j]@.get_store()));
+            }
+        }
+        true
+    }
+
+    /// Count in-flight messages per kind, indexed by MsgKind::index()
+    /// (e.g. result[0] is the number of pending LockReqs)
+    pub fn kind_histogram(&self) -> (result: [usize; 9])
+        ensures
+            forall|k: MsgKind| #![auto] result@[k.spec_index()] as nat == self.spec_count_kind(k),
+    {
+        let mut hist: [usize; 9] = [0; 9];
+        let mut i: usize = 0;
+        while i < self.messages.len()
+            invariant
+                0 <= i <= self.messages.len(),
+                hist@.len() == 9,
+                forall|k: MsgKind| #![auto] hist@[k.spec_index()] <= i,
+                forall|k: MsgKind| #![auto]
+                    hist@[k.spec_index()] as nat == spec_count_kind_in(self.messages@.subrange(0, i as int), k),
+            decreases
+                self.messages.len() - i,
+        {
+            proof {
+                // Extending the prefix by one message adds it to its own kind's bucket
+                assert(self.messages@.subrange(0, i as int + 1).drop_last()
+                    =~= self.messages@.subrange(0, i as int));
+            }
+            let idx = self.messages[i].kind().index();
+            // hist[idx] <= i < self.messages.len() <= usize::MAX, so + 1 won't overflow
+            hist[idx] = hist[idx] + 1;
+            i = i + 1;
+        }
+        proof {
+            assert(self.messages@.subrange(0, self.messages@.len() as int) =~= self.messages@);
+        }
+        hist
+    }
+
+    /// Check that a transaction has drained: no in-flight message carries `txn_id`
+    /// (messages of other transactions may remain)
+    pub fn is_settled_for_txn(&self, txn_id: u64) -> (result: bool)
+        ensures
+            result == (self.spec_count_txn(txn_id as nat) == 0)
+    {
+        let mut count: usize = 0;
+        let mut i: usize = 0;
         while i < self.messages.len()
             invariant
                 0 <= i <= self.messages.len(),
                 count <= i,
+                count as nat == spec_count_txn_in(self.messages@.subrange(0, i as int), txn_id as nat),
             decreases
                 self.messages.len() - i,
         {
-            if self.messages[i].eq(msg) {
+            proof {
+                // Extending the prefix by one message adds it to the count if it matches
+                assert(self.messages@.subrange(0, i as int + 1).drop_last()
+                    =~= self.messages@.subrange(0, i as int));
+            }
+            if self.messages[i].get_txn_id() == txn_id {
                 // count <= i < self.messages.len() <= usize::MAX, so count + 1 won't overflow
                 count = count + 1;
             }
             i = i + 1;
         }
+        proof {
+            assert(self.messages@.subrange(0, self.messages@.len() as int) =~= self.messages@);
+        }
+        count == 0
+    }
+
+    /// Delivery time recorded for sequence number `seq` (None = no delay)
+    fn delay_of(&self, seq: u64) -> (result: Option<u64>)
+        ensures
+            result == spec_delay_of(self.delays@, seq),
+    {
+        let mut i: usize = self.delays.len();
+        while i > 0
+            invariant
+                0 <= i <= self.delays.len(),
+                spec_delay_of(self.delays@, seq) == spec_delay_of(self.delays@.subrange(0, i as int), seq),
+            decreases
+                i,
+        {
+            proof {
+                assert(self.delays@.subrange(0, i as int).drop_last() =~= self.delays@.subrange(0, i as int - 1));
+            }
+            i = i - 1;
+            if self.delays[i].0 == seq {
+                return Some(self.delays[i].1);
+            }
+        }
+        proof {
+            assert(self.delays@.subrange(0, 0) =~= Seq::<(u64, u64)>::empty());
+        }
+        None
+    }
+
+    /// Number of in-flight messages not yet deliverable at time `now`
+    /// (sent with send_delayed for a time after `now`)
+    pub fn pending_delayed(&self, now: u64) -> (result: usize)
+        ensures
+            result as nat == spec_pending_delayed_in(self.spec_seqs(), self.delays@, now),
+            result <= self.spec_seqs().len(),
+    {
+        let mut count: usize = 0;
+        let mut i: usize = 0;
+        while i < self.seqs.len()
+            invariant
+                0 <= i <= self.seqs.len(),
+                count <= i,
+                count as nat == spec_pending_delayed_in(self.spec_seqs().subrange(0, i as int), self.delays@, now),
+            decreases
+                self.seqs.len() - i,
+        {
+            proof {
+                assert(self.spec_seqs().subrange(0, i as int + 1).drop_last() =~= self.spec_seqs().subrange(0, i as int));
+            }
+            if let Some(t) = self.delay_of(self.seqs[i]) {
+                if t > now {
+                    count = count + 1;
+                }
+            }
+            i = i + 1;
+        }
+        proof {
+            assert(self.spec_seqs().subrange(0, self.spec_seqs().len() as int) =~= self.spec_seqs());
+        }
         count
     }
+
+    /// Earliest delivery time among in-flight delayed messages, or None if no
+    /// in-flight message was sent with a delay. A simulation driver can jump
+    /// its clock straight there instead of ticking
+    pub fn next_delivery_time(&self) -> (result: Option<u64>)
+        ensures
+            result.is_some() ==> exists|i: int| 0 <= i < self.spec_seqs().len()
+                && spec_delay_of(self.delays@, #[trigger] self.spec_seqs()[i]) == result,
+            forall|i: int| 0 <= i < self.spec_seqs().len()
+                && (#[trigger] spec_delay_of(self.delays@, self.spec_seqs()[i])).is_some() ==>
+                result.is_some() && result.unwrap() <= spec_delay_of(self.delays@, self.spec_seqs()[i]).unwrap(),
+    {
+        let mut earliest: Option<u64> = None;
+        let mut i: usize = 0;
+        while i < self.seqs.len()
+            invariant
+                0 <= i <= self.seqs.len(),
+                earliest.is_some() ==> exists|j: int| 0 <= j < i
+                    && spec_delay_of(self.delays@, #[trigger] self.spec_seqs()[j]) == earliest,
+                forall|j: int| 0 <= j < i
+                    && (#[trigger] spec_delay_of(self.delays@, self.spec_seqs()[j])).is_some() ==>
+                    earliest.is_some() && earliest.unwrap() <= spec_delay_of(self.delays@, self.spec_seqs()[j]).unwrap(),
+            decreases
+                self.seqs.len() - i,
+        {
+            if let Some(t) = self.delay_of(self.seqs[i]) {
+                let sooner = match earliest {
+                    Some(e) => t < e,
+                    None => true,
+                };
+                if sooner {
+                    earliest = Some(t);
+                }
+            }
+            i = i + 1;
+        }
+        earliest
+    }
+
+    /// The operations applied to this network so far, in order (the retained
+    /// log). Replaying them with `from_trace` rebuilds a network with the same
+    /// contents, provided none were discarded (see `log_complete`)
+    pub fn record_trace(&self) -> (result: Vec<NetOp>)
+        ensures
+            result@.len() == self.spec_log().len(),
+    {
+        let mut ops: Vec<NetOp> = Vec::new();
+        let mut i: usize = 0;
+        while i < self.log.len()
+            invariant
+                0 <= i <= self.log.len(),
+                ops@.len() == i,
+            decreases
+                self.log.len() - i,
+        {
+            ops.push(self.log[i].clone());
+            i = i + 1;
+        }
+        ops
+    }
+
+    /// Build a network by replaying recorded operations on an empty one.
+    /// A `Deliver`, `Lose` or `Duplicate` of a message that is not in flight
+    /// is a no-op
+    pub fn from_trace(ops: &[NetOp]) -> (result: ExecNetwork)
+        ensures
+            result.spec_wf(),
+    {
+        let mut net = ExecNetwork::new();
+        let mut i: usize = 0;
+        while i < ops.len()
+            invariant
+                0 <= i <= ops@.len(),
+                net.spec_wf(),
+                net.per_store_cap is None,
+            decreases
+                ops.len() - i,
+        {
+            match &ops[i] {
+                NetOp::Send(m) => net.send(m.clone()),
+                NetOp::Deliver(m) => {
+                    net.receive(m);
+                },
+                NetOp::Lose(m) => {
+                    net.lose(m);
+                },
+                NetOp::Duplicate(m) => {
+                    net.duplicate(m);
+                },
+            }
+            i = i + 1;
+        }
+        net
+    }
 }
 
 // ============================================================
@@ -423,6 +2141,67 @@ impl ExecNetwork {
 mod tests {
     use super::*;
 
+    /// Test: receive_in_order delivers messages in send order
+    fn test_receive_in_order() {
+        let mut net = ExecNetwork::new();
+        net.send(ExecMessage::UnlockReq { store: 2, txn_id: 1 });
+        net.send(ExecMessage::LockReq { store: 0, txn_id: 1 });
+        net.send(ExecMessage::RenameReq { store: 1, txn_id: 1 });
+
+        let first = net.receive_in_order();
+        assert(first.is_some());
+        assert(first.unwrap()@ == unlock_req_msg(2, 1));
+        let second = net.receive_in_order();
+        assert(second.unwrap()@ == lock_req_msg(0, 1));
+        let third = net.receive_in_order();
+        assert(third.unwrap()@ == rename_req_msg(1, 1));
+        assert(net.receive_in_order().is_none());
+    }
+
+    /// Test: pop_oldest drains the queue front to back
+    fn test_pop_oldest() {
+        let mut net = ExecNetwork::new();
+        net.send(ExecMessage::RenameReq { store: 1, txn_id: 1 });
+        net.send(ExecMessage::LockReq { store: 0, txn_id: 1 });
+        net.send(ExecMessage::UnlockResp { store: 2, txn_id: 1 });
+
+        let first = net.pop_oldest();
+        assert(first.is_some());
+        assert(first.unwrap()@ == rename_req_msg(1, 1));
+        let second = net.pop_oldest();
+        assert(second.unwrap()@ == lock_req_msg(0, 1));
+        let third = net.pop_oldest();
+        assert(third.unwrap()@ == unlock_resp_msg(2, 1));
+        assert(net.pop_oldest().is_none());
+    }
+
+    /// Test: drain_all empties the queue front to back
+    fn test_drain_all() {
+        let mut net = ExecNetwork::new();
+        net.send(ExecMessage::RenameReq { store: 1, txn_id: 1 });
+        net.send(ExecMessage::LockReq { store: 0, txn_id: 1 });
+
+        let drained = net.drain_all();
+        assert(drained@.len() == 2);
+        assert(drained@[0]@ == rename_req_msg(1, 1));
+        assert(drained@[1]@ == lock_req_msg(0, 1));
+        assert(net.spec_is_empty());
+    }
+
+    /// Test: dedup collapses three copies into one
+    fn test_dedup() {
+        let mut net = ExecNetwork::new();
+        let msg = ExecMessage::LockReq { store: 0, txn_id: 1 };
+        net.send(msg.clone());
+        net.send(msg.clone());
+        net.send(msg.clone());
+        assert(net.spec_contains(lock_req_msg(0, 1)));
+
+        net.dedup();
+        assert(net.spec_contains(lock_req_msg(0, 1)));
+        assert(net.count(&msg) == 1);
+    }
+
     /// Test: Create empty network
     fn test_new_network() {
         let net = ExecNetwork::new();
@@ -471,6 +2250,28 @@ mod tests {
         assert(net.is_empty());
     }
 
+    /// Test: Receive takes the first copy, so identical networks stay identical
+    fn test_receive_deterministic() {
+        let a = ExecMessage::lock_req(0, 1);
+        let b = ExecMessage::lock_req(1, 1);
+        let mut net1 = ExecNetwork::new();
+        let mut net2 = ExecNetwork::new();
+        net1.send(a.clone());
+        net1.send(b.clone());
+        net1.send(a.clone());
+        net2.send(a.clone());
+        net2.send(b.clone());
+        net2.send(a.clone());
+
+        let r1 = net1.receive(&a);
+        let r2 = net2.receive(&a);
+        assert(r1.is_some() && r2.is_some());
+        assert(net1.messages@.len() == 2);
+        assert(net1.messages@[0]@ == b@);
+        assert(net1.messages@[1]@ == a@);
+        assert(net2.messages@[0]@ == b@);
+    }
+
     /// Test: Receive non-existent message
     fn test_receive_not_found() {
         let mut net = ExecNetwork::new();
@@ -546,6 +2347,104 @@ mod tests {
         assert(net.contains(&unlock_resp));
     }
 
+    /// Test: First message for a store
+    fn test_first_for_store() {
+        let mut net = ExecNetwork::new();
+        let msg1 = ExecMessage::lock_req(0, 1);
+        let msg2 = ExecMessage::lock_resp(1, true, 1);
+        let msg3 = ExecMessage::rename_req(1, 1);
+
+        net.send(msg1.clone());
+        net.send(msg2.clone());
+        net.send(msg3.clone());
+
+        let found = net.first_for_store(1);
+        assert(found.is_some());
+        assert(found.unwrap().get_store() == 1);
+        assert(net.first_for_store(2).is_none());
+        assert(net.len() == 3);
+    }
+
+    /// Test: Histogram buckets count each kind of in-flight message
+    fn test_kind_histogram() {
+        let mut net = ExecNetwork::new();
+        net.send(ExecMessage::LockReq { store: 0, txn_id: 1 });
+        net.send(ExecMessage::LockReq { store: 1, txn_id: 1 });
+        net.send(ExecMessage::LockReq { store: 2, txn_id: 1 });
+        net.send(ExecMessage::UnlockResp { store: 0, txn_id: 1 });
+
+        let hist = net.kind_histogram();
+        assert(hist@[MsgKind::LockReq.spec_index()] == 3);
+        assert(hist@[MsgKind::LockResp.spec_index()] == 0);
+        assert(hist@[MsgKind::UnlockResp.spec_index()] == 1);
+    }
+
+    /// Test: Settled check only considers the given txn
+    fn test_is_settled_for_txn() {
+        let mut net = ExecNetwork::new();
+        assert(net.is_settled_for_txn(1));
+
+        net.send(ExecMessage::lock_req(0, 1));
+        net.send(ExecMessage::lock_req(0, 2));
+        assert(!net.is_settled_for_txn(1));
+        assert(!net.is_settled_for_txn(2));
+        assert(net.is_settled_for_txn(3));
+    }
+
+    /// Test: Merging sums the copies held by both networks
+    fn test_merge() {
+        let mut left = ExecNetwork::new();
+        left.send(ExecMessage::lock_req(0, 1));
+        left.send(ExecMessage::lock_req(1, 1));
+
+        let mut right = ExecNetwork::new();
+        right.send(ExecMessage::lock_req(0, 1));
+        right.send(ExecMessage::unlock_resp(2, 1));
+
+        left.merge(right);
+        assert(left.len() == 4);
+        assert(left.count(&ExecMessage::lock_req(0, 1)) == 2);
+        assert(left.count(&ExecMessage::lock_req(1, 1)) == 1);
+        assert(left.count(&ExecMessage::unlock_resp(2, 1)) == 1);
+    }
+
+    /// Test: total_bytes sums the per-message sizes
+    fn test_total_bytes() {
+        let mut net = ExecNetwork::new();
+        assert(net.total_bytes() == 0);
+        net.send(ExecMessage::lock_req(0, 1));
+        net.send(ExecMessage::lock_resp(0, true, 1));
+        assert(net.total_bytes() == 35);
+    }
+
+    /// Test: A full store drops further sends while other stores still accept
+    fn test_per_store_cap() {
+        let mut net = ExecNetwork::new().with_per_store_cap(2);
+        net.send(ExecMessage::lock_req(0, 1));
+        net.send(ExecMessage::rename_req(0, 1));
+        net.send(ExecMessage::unlock_req(0, 1));
+        assert(net.count_for_store(0) == 2);
+        assert(!net.contains(&ExecMessage::unlock_req(0, 1)));
+
+        net.send(ExecMessage::lock_req(1, 1));
+        assert(net.count_for_store(1) == 1);
+        assert(net.len() == 3);
+
+        // The full store can still answer: responses leave its inbox alone
+        net.send(ExecMessage::lock_resp(0, true, 1));
+        assert(net.contains(&ExecMessage::lock_resp(0, true, 1)));
+        assert(net.count_for_store(0) == 2);
+    }
+
+    /// Test: Canonical order compares kind, then store, then txn, then success
+    fn test_precedes() {
+        assert(ExecMessage::lock_req(5, 5).precedes(&ExecMessage::lock_resp(0, false, 0)));
+        assert(ExecMessage::lock_req(0, 9).precedes(&ExecMessage::lock_req(1, 0)));
+        assert(ExecMessage::lock_req(1, 1).precedes(&ExecMessage::lock_req(1, 2)));
+        assert(ExecMessage::lock_resp(1, false, 1).precedes(&ExecMessage::lock_resp(1, true, 1)));
+        assert(!ExecMessage::lock_req(1, 1).precedes(&ExecMessage::lock_req(1, 1)));
+    }
+
     /// Test: Message accessors
     fn test_message_accessors() {
         let msg = ExecMessage::lock_req(5, 42);
@@ -566,6 +2465,317 @@ mod tests {
         assert(!fail_resp.is_lock_success());
         assert(fail_resp.is_lock_failure());
     }
+
+    /// Test: from_tag rebuilds a message from its tag and payload
+    fn test_tag_round_trip() {
+        let msgs = [ExecMessage::lock_req(1, 2), ExecMessage::lock_resp(1, false, 2),
+            ExecMessage::rename_req(1, 2), ExecMessage::rename_resp(1, 2),
+            ExecMessage::unlock_req(1, 2), ExecMessage::unlock_resp(1, 2),
+            ExecMessage::decide(1, true, 2), ExecMessage::migrate_vote(1, 2, 3),
+            ExecMessage::migrate_apply(1, 2, 3)];
+        let mut i: usize = 0;
+        while i < 9
+            invariant
+                0 <= i <= 9,
+            decreases
+                9 - i,
+        {
+            let m = &msgs[i];
+            let rebuilt = ExecMessage::from_tag(m.tag(), m.get_store(), m.get_txn_id(), m.flag(), m.value());
+            proof { lemma_message_from_tag_round_trip(m@); }
+            assert(rebuilt matches Some(r) && r@ == m@);
+            i = i + 1;
+        }
+        assert(ExecMessage::from_tag(9, 1, 2, false, 0).is_none());
+    }
+
+    /// Test: matches_store_txn ignores kind and success flag
+    fn test_matches_store_txn() {
+        assert(ExecMessage::lock_req(2, 7).matches_store_txn(2, 7));
+        assert(ExecMessage::lock_resp(2, false, 7).matches_store_txn(2, 7));
+        assert(!ExecMessage::lock_resp(2, true, 7).matches_store_txn(3, 7));
+        assert(!ExecMessage::lock_resp(2, true, 7).matches_store_txn(2, 8));
+    }
+
+    /// Test: with_txn replaces only the txn ID
+    fn test_with_txn() {
+        let msg = ExecMessage::lock_req(2, 1).with_txn(7);
+        assert(msg.eq(&ExecMessage::lock_req(2, 7)));
+
+        let resp = ExecMessage::lock_resp(2, false, 1).with_txn(7);
+        assert(resp.eq(&ExecMessage::lock_resp(2, false, 7)));
+
+        let rename = ExecMessage::rename_req(3, 1).with_txn(7);
+        assert(rename.get_store() == 3);
+        assert(rename.get_txn_id() == 7);
+    }
+
+    /// Test: each request maps to its response; responses map to none
+    fn test_expected_response() {
+        let lock = ExecMessage::lock_req(2, 5).expected_response(false);
+        assert(lock.unwrap()@ == lock_resp_msg(2, false, 5));
+        let rename = ExecMessage::rename_req(2, 5).expected_response(true);
+        assert(rename.unwrap()@ == rename_resp_msg(2, 5));
+        let unlock = ExecMessage::unlock_req(2, 5).expected_response(true);
+        assert(unlock.unwrap()@ == unlock_resp_msg(2, 5));
+        assert(ExecMessage::unlock_resp(2, 5).expected_response(true).is_none());
+    }
+
+    /// Test: A message for an unknown store breaks well-formedness
+    fn test_is_well_formed() {
+        let mut net = ExecNetwork::new();
+        net.send(ExecMessage::lock_req(0, 1));
+        let mut stores: Vec<u64> = Vec::new();
+        stores.push(0);
+        assert(net.is_well_formed(stores.as_slice(), 1));
+        net.send(ExecMessage::lock_req(3, 1));
+        assert(net.spec_contains(lock_req_msg(3, 1)));
+        assert(!net.is_well_formed(stores.as_slice(), 1));
+    }
 }
 
 } // verus!
+
+// ============================================================
+// WIRE FORMAT (plain Rust, outside verus!)
+// ============================================================
+
+/// Plain, serialization-friendly form of `ExecMessage` for external tooling.
+///
+/// All fields are public primitives so a serialization framework can be
+/// derived on it without touching the verified `ExecMessage`.
+/// `success` is only present for `LockResp`, and for `Decide`, where it
+/// carries the decision (true for commit). `value` is only present for
+/// `MigrateVote` and `MigrateApply`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireMessage {
+    /// Variant name, e.g. "LockReq"
+    pub kind: String,
+    pub store: u64,
+    pub txn_id: u64,
+    pub success: Option<bool>,
+    pub value: Option<u64>,
+}
+
+/// Why a `WireMessage` does not describe a valid `ExecMessage`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    /// `kind` is not an `ExecMessage` variant name
+    UnknownKind(String),
+    /// A `LockResp` or `Decide` without `success`
+    MissingSuccess,
+    /// `success` set on a message kind that has no such field
+    UnexpectedSuccess,
+    /// A `MigrateVote` or `MigrateApply` without `value`
+    MissingValue,
+    /// `value` set on a message kind that has no such field
+    UnexpectedValue,
+}
+
+// Total order for deterministic scheduling: kind, then store, then txn, then
+// success flag, then value. Equality agrees with the verified `ExecMessage::eq`.
+impl PartialEq for ExecMessage {
+    fn eq(&self, other: &Self) -> bool {
+        ExecMessage::eq(self, other)
+    }
+}
+
+impl Eq for ExecMessage {}
+
+impl PartialOrd for ExecMessage {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ExecMessage {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.order_key().cmp(&other.order_key())
+    }
+}
+
+impl From<&ExecMessage> for WireMessage {
+    fn from(msg: &ExecMessage) -> Self {
+        let (kind, store, txn_id, success, value) = match msg {
+            ExecMessage::LockReq { store, txn_id } => ("LockReq", *store, *txn_id, None, None),
+            ExecMessage::LockResp { store, success, txn_id } =>
+                ("LockResp", *store, *txn_id, Some(*success), None),
+            ExecMessage::RenameReq { store, txn_id } => ("RenameReq", *store, *txn_id, None, None),
+            ExecMessage::RenameResp { store, txn_id } => ("RenameResp", *store, *txn_id, None, None),
+            ExecMessage::UnlockReq { store, txn_id } => ("UnlockReq", *store, *txn_id, None, None),
+            ExecMessage::UnlockResp { store, txn_id } => ("UnlockResp", *store, *txn_id, None, None),
+            ExecMessage::Decide { store, commit, txn_id } =>
+                ("Decide", *store, *txn_id, Some(*commit), None),
+            ExecMessage::MigrateVote { store, txn_id, value } =>
+                ("MigrateVote", *store, *txn_id, None, Some(*value)),
+            ExecMessage::MigrateApply { store, txn_id, value } =>
+                ("MigrateApply", *store, *txn_id, None, Some(*value)),
+        };
+        WireMessage { kind: kind.to_owned(), store, txn_id, success, value }
+    }
+}
+
+impl TryFrom<WireMessage> for ExecMessage {
+    type Error = WireError;
+
+    fn try_from(wire: WireMessage) -> Result<Self, Self::Error> {
+        let WireMessage { kind, store, txn_id, success, value } = wire;
+        if kind == "MigrateVote" || kind == "MigrateApply" {
+            if success.is_some() {
+                return Err(WireError::UnexpectedSuccess);
+            }
+            let value = value.ok_or(WireError::MissingValue)?;
+            return Ok(if kind == "MigrateVote" {
+                ExecMessage::MigrateVote { store, txn_id, value }
+            } else {
+                ExecMessage::MigrateApply { store, txn_id, value }
+            });
+        }
+        let msg = match kind.as_str() {
+            "LockResp" => match success {
+                Some(success) => ExecMessage::LockResp { store, success, txn_id },
+                None => return Err(WireError::MissingSuccess),
+            },
+            "Decide" => match success {
+                Some(commit) => ExecMessage::Decide { store, commit, txn_id },
+                None => return Err(WireError::MissingSuccess),
+            },
+            "LockReq" | "RenameReq" | "RenameResp" | "UnlockReq" | "UnlockResp" if success.is_some() =>
+                return Err(WireError::UnexpectedSuccess),
+            "LockReq" => ExecMessage::LockReq { store, txn_id },
+            "RenameReq" => ExecMessage::RenameReq { store, txn_id },
+            "RenameResp" => ExecMessage::RenameResp { store, txn_id },
+            "UnlockReq" => ExecMessage::UnlockReq { store, txn_id },
+            "UnlockResp" => ExecMessage::UnlockResp { store, txn_id },
+            _ => return Err(WireError::UnknownKind(kind)),
+        };
+        if value.is_some() {
+            return Err(WireError::UnexpectedValue);
+        }
+        Ok(msg)
+    }
+}
+
+impl ExecMessage {
+    /// One-line description for logs, e.g. `<LockResp store=0 txn=1 success=true>`.
+    /// With `redact` the txn id is left out (`<LockResp store=0 success=true>`),
+    /// so logs show the protocol's shape without correlatable transaction detail
+    pub fn log_summary(&self, redact: bool) -> String {
+        let wire = WireMessage::from(self);
+        let mut summary = format!("<{} store={}", wire.kind, wire.store);
+        if !redact {
+            summary.push_str(&format!(" txn={}", wire.txn_id));
+        }
+        match self {
+            ExecMessage::LockResp { success, .. } => summary.push_str(&format!(" success={}", success)),
+            ExecMessage::Decide { commit, .. } => summary.push_str(&format!(" commit={}", commit)),
+            ExecMessage::MigrateVote { value, .. } | ExecMessage::MigrateApply { value, .. } =>
+                summary.push_str(&format!(" value={}", value)),
+            _ => {},
+        }
+        summary.push('>');
+        summary
+    }
+}
+
+impl ExecNetwork {
+    /// The retained operations logged at or after position `pos` (a value of
+    /// `ops_logged` taken earlier). Operations already discarded are skipped
+    pub fn log_since(&self, pos: u64) -> &[NetOp] {
+        let start = pos.saturating_sub(self.log_dropped).min(self.log.len() as u64);
+        &self.log[start as usize..]
+    }
+}
+
+// ============================================================
+// NETWORK MODELS (plain Rust, outside verus!)
+// ============================================================
+
+/// Policy deciding what the network does to each message after it is sent.
+///
+/// `on_send` is consulted once per sent message and returns the operations to
+/// apply on top of the send: `Lose` drops the copy, `Duplicate` adds another,
+/// `Send` injects an extra message, `Deliver` removes a copy as a receive
+/// would. An empty result delivers the message as is.
+/// Installed on a system with `ExecSystem::set_model`, which routes every
+/// protocol send through it; see also `ExecSystem::run_with_model`.
+pub trait NetworkModel {
+    fn on_send(&mut self, msg: &ExecMessage) -> Vec<NetOp>;
+}
+
+/// Delivers every message exactly once
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Reliable;
+
+impl NetworkModel for Reliable {
+    fn on_send(&mut self, _msg: &ExecMessage) -> Vec<NetOp> {
+        Vec::new()
+    }
+}
+
+/// Loses each sent message independently with probability `p`
+#[derive(Debug, Clone)]
+pub struct LossyUniform {
+    pub p: f64,
+    rng: SplitMix64,
+}
+
+impl LossyUniform {
+    /// Model losing messages with probability `p`, reproducible for a given `seed`
+    pub fn new(p: f64, seed: u64) -> Self {
+        LossyUniform { p, rng: SplitMix64(seed) }
+    }
+}
+
+impl NetworkModel for LossyUniform {
+    fn on_send(&mut self, msg: &ExecMessage) -> Vec<NetOp> {
+        if self.rng.next_f64() < self.p {
+            vec![NetOp::Lose(msg.clone())]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Duplicates each sent message independently with probability `p`
+#[derive(Debug, Clone)]
+pub struct DuplicatingUniform {
+    pub p: f64,
+    rng: SplitMix64,
+}
+
+impl DuplicatingUniform {
+    /// Model duplicating messages with probability `p`, reproducible for a given `seed`
+    pub fn new(p: f64, seed: u64) -> Self {
+        DuplicatingUniform { p, rng: SplitMix64(seed) }
+    }
+}
+
+impl NetworkModel for DuplicatingUniform {
+    fn on_send(&mut self, msg: &ExecMessage) -> Vec<NetOp> {
+        if self.rng.next_f64() < self.p {
+            vec![NetOp::Duplicate(msg.clone())]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Small seeded PRNG (SplitMix64) so models are reproducible without extra dependencies
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
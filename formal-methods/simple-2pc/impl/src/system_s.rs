@@ -11,6 +11,7 @@
 
 use vstd::prelude::*;
 
+use crate::abstract_s::*;
 use crate::coordinator_s::*;
 use crate::kv_store_s::*;
 use crate::network_s::*;
@@ -18,14 +19,17 @@ use crate::network_s::*;
 verus! {
 
 /// Spec-level global system state (ghost/proof only).
-pub ghost struct SystemSpec {
+///
+/// Generic over the store value type `V`; the protocol never inspects values,
+/// so the same actions apply to integer or structured payloads.
+pub ghost struct SystemSpec<V> {
     pub coord: CoordinatorSpec,
     pub net: NetworkSpec,
-    pub stores: Map<StoreId, KvStoreSpec<u64>>,
+    pub stores: Map<StoreId, KvStoreSpec<V>>,
     pub all_stores: Set<StoreId>,
 }
 
-impl SystemSpec {
+impl<V> SystemSpec<V> {
     // ============================================================
     // Observations / basic helpers
     // ============================================================
@@ -35,13 +39,13 @@ impl SystemSpec {
         &&& self.coord.current_txn_id >= 1
     }
 
-    pub open spec fn store(&self, s: StoreId) -> KvStoreSpec<u64>
+    pub open spec fn store(&self, s: StoreId) -> KvStoreSpec<V>
         recommends self.stores.contains_key(s)
     {
         self.stores[s]
     }
 
-    pub open spec fn with_store(self, s: StoreId, new_store: KvStoreSpec<u64>) -> Self
+    pub open spec fn with_store(self, s: StoreId, new_store: KvStoreSpec<V>) -> Self
         recommends self.stores.contains_key(s)
     {
         SystemSpec { stores: self.stores.insert(s, new_store), ..self }
@@ -80,12 +84,23 @@ impl SystemSpec {
     }
 
     pub open spec fn coord_send_unlock_req(self, s: StoreId) -> Self
-        recommends self.all_stores.contains(s)
+        recommends
+            self.all_stores.contains(s),
+            self.coord.unlock_targets().contains(s),
     {
         let (new_coord, msg) = self.coord.send_unlock_req(s);
         SystemSpec { coord: new_coord, net: self.net.send(msg), ..self }
     }
 
+    pub open spec fn coord_send_decide(self, s: StoreId) -> Self
+        recommends
+            self.all_stores.contains(s),
+            self.coord.unlock_targets().contains(s),
+    {
+        let (new_coord, msg) = self.coord.send_decide(s);
+        SystemSpec { coord: new_coord, net: self.net.send(msg), ..self }
+    }
+
     // ============================================================
     // Network -> Coordinator (deliver/receive) actions
     // ============================================================
@@ -135,10 +150,29 @@ impl SystemSpec {
     {
         let msg = unlock_resp_msg(s, self.coord.current_txn_id);
         let new_net = self.net.lose(msg);
-        let new_coord = self.coord.recv_unlock_resp(s, self.all_stores);
+        let new_coord = self.coord.recv_unlock_resp(s);
         SystemSpec { coord: new_coord, net: new_net, ..self }
     }
 
+    /// Lemma: a successful LockResp credits only the store it is addressed from.
+    /// Receiving for `s2` consumes `s2`'s response and never changes whether
+    /// `s1` is credited, nor touches `s1`'s in-flight responses.
+    pub proof fn lemma_lock_resp_credits_only_addressed_store(self, s1: StoreId, s2: StoreId)
+        requires
+            s1 != s2,
+        ensures
+            self.coord_recv_lock_resp_success(s2).coord.locks_acquired
+                == self.coord.locks_acquired.insert(s2),
+            self.coord_recv_lock_resp_success(s2).coord.locks_acquired.contains(s1)
+                == self.coord.locks_acquired.contains(s1),
+            self.coord_recv_lock_resp_success(s2).net.count(lock_resp_msg(s1, true, self.coord.current_txn_id))
+                == self.net.count(lock_resp_msg(s1, true, self.coord.current_txn_id)),
+    {
+        let txn = self.coord.current_txn_id;
+        NetworkSpec::lemma_different_stores_distinct(s1, s2, txn);
+        self.net.lemma_lose_preserves_others(lock_resp_msg(s2, true, txn), lock_resp_msg(s1, true, txn));
+    }
+
     // ============================================================
     // Coordinator crash/recovery (local state transition)
     // ============================================================
@@ -155,6 +189,114 @@ impl SystemSpec {
         self.with_coord(self.coord.recover())
     }
 
+    // ============================================================
+    // Store crash/recovery (local state transition)
+    // ============================================================
+    //
+    // Not part of `next`: these model the exec `store_crash_recover` so its
+    // effect on a single store can be reasoned about directly.
+
+    /// Store `s` durably voted yes and has not released its locks since: its
+    /// `prepared_txn`, which a crash keeps
+    pub open spec fn store_prepared(&self, s: StoreId) -> bool {
+        self.store(s).prepared_txn is Some
+    }
+
+    /// Store `s` crashes: its volatile lock table is lost, while data, the
+    /// last seen txn id and the rest of its bookkeeping survive
+    pub open spec fn store_crash(self, s: StoreId) -> Self
+        recommends self.stores.contains_key(s)
+    {
+        self.with_store(s, KvStoreSpec { locked_keys: Set::empty(), ..self.store(s) })
+    }
+
+    /// Store `s` recovers: if it is prepared it re-takes both locks, so the
+    /// coordinator's rename and unlock rounds find it as they left it
+    pub open spec fn store_recover(self, s: StoreId, key_a: Seq<char>, key_aprime: Seq<char>) -> Self
+        recommends self.stores.contains_key(s)
+    {
+        if self.store_prepared(s) {
+            self.with_store(s, self.store(s).lock(key_a).lock(key_aprime))
+        } else {
+            self
+        }
+    }
+
+    /// Lemma: crashing and recovering a store never touches its data, so
+    /// `data_accessible` holds for it throughout; recovery never changes the
+    /// network, the coordinator or other stores
+    pub proof fn lemma_store_crash_recover_preserve_data_accessible(
+        self,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.stores.contains_key(s),
+            data_accessible(self.store(s), key_a, key_aprime),
+        ensures
+            self.store_crash(s).store(s).data == self.store(s).data,
+            data_accessible(self.store_crash(s).store(s), key_a, key_aprime),
+            self.store_crash(s).store_recover(s, key_a, key_aprime).store(s).data == self.store(s).data,
+            data_accessible(self.store_crash(s).store_recover(s, key_a, key_aprime).store(s), key_a, key_aprime),
+            self.store_crash(s).store_recover(s, key_a, key_aprime).net == self.net,
+            self.store_crash(s).store_recover(s, key_a, key_aprime).coord == self.coord,
+            forall|s2: StoreId| s2 != s ==>
+                #[trigger] self.store_crash(s).store_recover(s, key_a, key_aprime).stores.index(s2)
+                    == self.stores.index(s2),
+    {
+        let crashed = self.store_crash(s).store(s);
+        crashed.lemma_lock_preserves_data(key_a);
+        crashed.lock(key_a).lemma_lock_preserves_data(key_aprime);
+    }
+
+    /// Lemma: a store that crashed while prepared holds both locks again after
+    /// recovery, with the same txn id, so a replayed `RenameReq` still renames
+    /// and a replayed `UnlockReq` still releases both locks
+    pub proof fn lemma_store_recover_restores_prepared_locks(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.stores.contains_key(s),
+            self.store_prepared(s),
+            key_a != key_aprime,
+            !self.store(s).is_stale_txn_id(txn_id),
+        ensures
+            ({
+                let rec = self.store_crash(s).store_recover(s, key_a, key_aprime);
+                &&& rec.store(s).is_locked(key_a)
+                &&& rec.store(s).is_locked(key_aprime)
+                &&& rec.store(s).last_seen_txn_id == self.store(s).last_seen_txn_id
+                // A replayed rename finds the store as it left the lock phase
+                &&& self.store(s).contains_key(key_a) && !self.store(s).contains_key(key_aprime) ==> {
+                    let post = rec.store_handle_rename_req(s, txn_id, key_a, key_aprime).store(s);
+                    &&& post.contains_key(key_aprime)
+                    &&& post.get(key_aprime) == self.store(s).get(key_a)
+                    &&& !post.contains_key(key_a)
+                }
+                // The unlock phase still releases both locks
+                &&& !rec.store_handle_unlock_req(s, txn_id, key_a, key_aprime).store(s).is_locked(key_a)
+                &&& !rec.store_handle_unlock_req(s, txn_id, key_a, key_aprime).store(s).is_locked(key_aprime)
+            }),
+    {
+        let crashed = self.store_crash(s);
+        assert(crashed.net == self.net);
+        assert(crashed.store(s).last_seen_txn_id == self.store(s).last_seen_txn_id);
+        assert(crashed.store_prepared(s));
+        let rec = crashed.store_recover(s, key_a, key_aprime);
+        assert(rec.store(s) == crashed.store(s).lock(key_a).lock(key_aprime));
+        crashed.store(s).lemma_lock_preserves_data(key_a);
+        crashed.store(s).lock(key_a).lemma_lock_preserves_data(key_aprime);
+        assert(rec.store(s).data == self.store(s).data);
+        if self.store(s).contains_key(key_a) && !self.store(s).contains_key(key_aprime) {
+            rec.lemma_rename_req_preserves_value(s, txn_id, key_a, key_aprime);
+        }
+    }
+
     // ============================================================
     // Network -> Store (deliver/handle) actions
     // ============================================================
@@ -163,8 +305,14 @@ impl SystemSpec {
     ///
     /// - Consumes exactly one copy of the request from the network.
     /// - Rejects stale txn ids (no state change; no response).
-    /// - Otherwise updates `last_seen_txn_id`, locks both keys, and sends `LockResp`.
+    /// - Otherwise updates `last_seen_txn_id`, locks both keys, durably records
+    ///   the yes vote (`prepared_txn`), and sends `LockResp`.
     /// - Fails if `key_aprime` already exists (interpreted as already renamed).
+    /// - Fails if either key is locked while the store is not prepared for
+    ///   `txn_id`: someone else holds it.
+    /// - Fails without locking if the store already handled the txn's
+    ///   `UnlockReq`: a request delayed past an abort cannot orphan a lock.
+    /// - Fails if the store is frozen: it could not take the locks.
     pub open spec fn store_handle_lock_req(
         self,
         s: StoreId,
@@ -177,6 +325,28 @@ impl SystemSpec {
             self.net.contains(lock_req_msg(s, txn_id)),
             self.stores.contains_key(s),
             key_a != key_aprime,
+    {
+        self.store_handle_lock_req_with_detect_key(s, txn_id, key_a, key_aprime, key_aprime)
+    }
+
+    /// Handle one `LockReq` message with an explicit "already renamed" detection key.
+    ///
+    /// Same as `store_handle_lock_req`, except the lock fails if `detect_key`
+    /// (rather than `key_aprime`) already exists at the store. Locks are still
+    /// checked on `key_a` and `key_aprime`, as `KvStore::prepare_vote` does.
+    pub open spec fn store_handle_lock_req_with_detect_key(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+        detect_key: Seq<char>,
+    ) -> Self
+        recommends
+            self.all_stores.contains(s),
+            self.net.contains(lock_req_msg(s, txn_id)),
+            self.stores.contains_key(s),
+            key_a != key_aprime,
     {
         let req = lock_req_msg(s, txn_id);
         let net1 = self.net.lose(req);
@@ -186,11 +356,12 @@ impl SystemSpec {
             SystemSpec { net: net1, ..self }
         } else {
             let st1 = st0.update_txn_id(txn_id);
-            if st1.contains_key(key_aprime) {
+            if st1.is_released(txn_id) || st1.is_frozen() || st1.contains_key(detect_key)
+                || st1.locked_by_other(key_a, key_aprime, txn_id) {
                 let net2 = net1.send(lock_resp_msg(s, false, txn_id));
                 SystemSpec { net: net2, stores: self.stores.insert(s, st1), ..self }
             } else {
-                let st2 = st1.lock(key_a).lock(key_aprime);
+                let st2 = st1.lock(key_a).lock(key_aprime).prepare(txn_id);
                 let net2 = net1.send(lock_resp_msg(s, true, txn_id));
                 SystemSpec { net: net2, stores: self.stores.insert(s, st2), ..self }
             }
@@ -237,11 +408,106 @@ impl SystemSpec {
         }
     }
 
+    /// Lemma: handling a `RenameReq` never corrupts the value being renamed.
+    ///
+    /// When the handler performs the rename, `key_aprime` afterwards holds exactly the
+    /// value `key_a` held before; on idempotent re-delivery (already renamed) the value
+    /// at `key_aprime` is left as it was. The txn-id update and network steps only
+    /// touch `last_seen_txn_id` and in-flight messages.
+    pub proof fn lemma_rename_req_preserves_value(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.stores.contains_key(s),
+            key_a != key_aprime,
+            !self.store(s).is_stale_txn_id(txn_id),
+        ensures
+            // Performs the rename: value moves from key_a to key_aprime unchanged
+            ({
+                let st = self.store(s);
+                !st.contains_key(key_aprime) && st.is_locked(key_a) && st.is_locked(key_aprime)
+                    && st.contains_key(key_a)
+            }) ==> {
+                let post = self.store_handle_rename_req(s, txn_id, key_a, key_aprime).store(s);
+                &&& post.contains_key(key_aprime)
+                &&& post.get(key_aprime) == self.store(s).get(key_a)
+                &&& !post.contains_key(key_a)
+            },
+            // Idempotent re-delivery: already moved, value stays equal
+            self.store(s).contains_key(key_aprime) ==> {
+                let post = self.store_handle_rename_req(s, txn_id, key_a, key_aprime).store(s);
+                &&& post.contains_key(key_aprime)
+                &&& post.get(key_aprime) == self.store(s).get(key_aprime)
+            },
+    {
+        let st0 = self.store(s);
+        let st1 = st0.update_txn_id(txn_id);
+        let post = self.store_handle_rename_req(s, txn_id, key_a, key_aprime);
+        // The txn-id update leaves data and locks untouched
+        assert(st1.data == st0.data);
+        assert(st1.locked_keys == st0.locked_keys);
+        if st0.contains_key(key_aprime) {
+            assert(post.stores[s] == st1);
+        } else if st0.is_locked(key_a) && st0.is_locked(key_aprime) && st0.contains_key(key_a) {
+            st1.lemma_rename_preserves_value(key_a, key_aprime);
+            assert(post.stores[s] == st1.rename(key_a, key_aprime));
+        }
+    }
+
+    /// Lemma: an old-txn `RenameReq` cannot cause a second rename.
+    ///
+    /// Once store `s` has handled a `RenameReq` for `new_txn` (e.g. re-sent after
+    /// recovery bumped the txn id), any leftover request from an older txn is
+    /// rejected as stale: the copy is consumed, no response is sent, and neither
+    /// the stores nor the coordinator change.
+    pub proof fn lemma_stale_rename_harmless(
+        self,
+        s: StoreId,
+        old_txn: TxnId,
+        new_txn: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.stores.contains_key(s),
+            old_txn < new_txn,
+        ensures
+            ({
+                let after = self.store_handle_rename_req(s, new_txn, key_a, key_aprime);
+                let replay = after.store_handle_rename_req(s, old_txn, key_a, key_aprime);
+                &&& after.store(s).is_stale_txn_id(old_txn)
+                &&& replay.stores == after.stores
+                &&& replay.coord == after.coord
+                &&& replay.net == after.net.lose(rename_req_msg(s, old_txn))
+            }),
+    {
+        let st0 = self.store(s);
+        let st1 = st0.update_txn_id(new_txn);
+        let after = self.store_handle_rename_req(s, new_txn, key_a, key_aprime);
+        // Whichever branch ran, the store's high-water mark is at least new_txn:
+        // the update raises it and a rename leaves it alone
+        assert(st1.last_seen_txn_id >= new_txn);
+        if !st0.is_stale_txn_id(new_txn) {
+            if !st1.contains_key(key_aprime) && st1.is_locked(key_a) && st1.is_locked(key_aprime)
+                && st1.contains_key(key_a) {
+                assert(after.store(s) == st1.rename(key_a, key_aprime));
+            } else {
+                assert(after.store(s) == st1);
+            }
+        }
+        assert(after.store(s).last_seen_txn_id >= new_txn);
+    }
+
     /// Handle one `UnlockReq` message for store `s` and txn `txn_id`.
     ///
     /// - Consumes exactly one copy of the request from the network.
     /// - Rejects stale txn ids (no state change; no response).
-    /// - Otherwise updates `last_seen_txn_id`, unlocks both keys, and sends `UnlockResp`.
+    /// - Otherwise updates `last_seen_txn_id`, unlocks both keys, clears the
+    ///   prepared vote, marks the txn released, and sends `UnlockResp`.
     pub open spec fn store_handle_unlock_req(
         self,
         s: StoreId,
@@ -263,12 +529,143 @@ impl SystemSpec {
             SystemSpec { net: net1, ..self }
         } else {
             let st1 = st0.update_txn_id(txn_id);
-            let st2 = st1.unlock(key_a).unlock(key_aprime);
+            let st2 = st1.unlock(key_a).unlock(key_aprime).clear_prepared().release(txn_id);
             let net2 = net1.send(unlock_resp_msg(s, txn_id));
             SystemSpec { net: net2, stores: self.stores.insert(s, st2), ..self }
         }
     }
 
+    /// Handle one `Decide` message for store `s`, txn `txn_id` and decision
+    /// `commit` (one-phase finalization of a no-op participant).
+    ///
+    /// - Consumes exactly one copy of the message from the network.
+    /// - Rejects stale txn ids, and a commit that still finds `key_a` (the
+    ///   rename is pending, so the store is not a no-op participant): no state
+    ///   change; no response.
+    /// - Otherwise updates `last_seen_txn_id`, releases the locks only if they
+    ///   are `txn_id`'s (the store is prepared for it), marks the txn released,
+    ///   and sends `UnlockResp`. Data is never touched.
+    pub open spec fn store_handle_decide(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        commit: bool,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    ) -> Self
+        recommends
+            self.all_stores.contains(s),
+            self.net.contains(decide_msg(s, commit, txn_id)),
+            self.stores.contains_key(s),
+            key_a != key_aprime,
+    {
+        let req = decide_msg(s, commit, txn_id);
+        let net1 = self.net.lose(req);
+        let st0 = self.store(s);
+
+        if st0.is_stale_txn_id(txn_id) || (commit && st0.contains_key(key_a)) {
+            SystemSpec { net: net1, ..self }
+        } else {
+            let st1 = st0.update_txn_id(txn_id);
+            let st2 = if st1.is_prepared(txn_id) {
+                st1.unlock(key_a).unlock(key_aprime).clear_prepared()
+            } else {
+                st1
+            };
+            let net2 = net1.send(unlock_resp_msg(s, txn_id));
+            SystemSpec { net: net2, stores: self.stores.insert(s, st2.release(txn_id)), ..self }
+        }
+    }
+
+    /// The store's own state records that it answered `txn_id`'s `LockReq` with
+    /// `success`. Lock handling is deterministic, so no extra memory is needed:
+    /// the store has seen `txn_id` and either holds both locks (voted yes) or
+    /// found `key_aprime` already present, the txn already released, the store
+    /// frozen or a key locked outside the txn (voted no). The request itself must have been sent, so a re-sent answer is
+    /// never spontaneous.
+    pub open spec fn store_responded_lock(
+        &self,
+        s: StoreId,
+        txn_id: TxnId,
+        success: bool,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    ) -> bool {
+        &&& self.stores.contains_key(s)
+        &&& self.store(s).get_last_seen_txn_id() == txn_id
+        &&& success ==> self.store(s).is_locked(key_a) && self.store(s).is_locked(key_aprime)
+        &&& !success ==> self.store(s).contains_key(key_aprime) || self.store(s).is_released(txn_id)
+                || self.store(s).is_frozen() || self.store(s).locked_by_other(key_a, key_aprime, txn_id)
+        &&& self.net.sent.contains(lock_req_msg(s, txn_id))
+    }
+
+    /// Store `s` re-sends the `LockResp` it already sent for `txn_id` (e.g. the
+    /// original was lost and no further request arrived).
+    ///
+    /// - Does not re-execute the lock: store state is unchanged.
+    /// - Adds one copy of the response to the network.
+    pub open spec fn store_resend_lock_resp(self, s: StoreId, txn_id: TxnId, success: bool) -> Self
+        recommends
+            self.all_stores.contains(s),
+            self.stores.contains_key(s),
+            self.store(s).get_last_seen_txn_id() == txn_id,
+    {
+        self.with_net(self.net.send(lock_resp_msg(s, success, txn_id)))
+    }
+
+    /// Lemma: re-sending a `LockResp` is safe.
+    ///
+    /// Only the network changes, the extra copy is well-formed, and it is never a
+    /// rename message, so both invariants are preserved. A duplicate success that
+    /// reaches the coordinator after the first is idempotent: `locks_acquired` is
+    /// a set, so crediting `s` twice leaves it unchanged.
+    pub proof fn lemma_store_resend_lock_resp_safe(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        success: bool,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.all_stores.contains(s),
+            txn_id <= self.coord.current_txn_id,
+        ensures
+            self.store_resend_lock_resp(s, txn_id, success).stores == self.stores,
+            self.store_resend_lock_resp(s, txn_id, success).coord == self.coord,
+            self.system_invariant(key_a, key_aprime) && self.net.sent.contains(lock_req_msg(s, txn_id)) ==>
+                self.store_resend_lock_resp(s, txn_id, success).system_invariant(key_a, key_aprime),
+            self.agreement_inv(key_a, key_aprime) ==>
+                self.store_resend_lock_resp(s, txn_id, success).agreement_inv(key_a, key_aprime),
+            self.coord.locks_acquired.contains(s) ==>
+                self.coord_recv_lock_resp_success(s).coord.locks_acquired == self.coord.locks_acquired,
+    {
+        let resp = lock_resp_msg(s, success, txn_id);
+        let next = self.store_resend_lock_resp(s, txn_id, success);
+        if self.net.causally_consistent() && self.net.sent.contains(lock_req_msg(s, txn_id)) {
+            self.net.lemma_send_preserves_causally_consistent(resp);
+        }
+        if self.well_formed_network() {
+            assert forall|m: Message| #[trigger] next.net.contains(m) implies
+                self.all_stores.contains(m.get_store()) && m.get_txn_id() <= self.coord.current_txn_id by {
+                if m != resp {
+                    self.net.lemma_send_preserves_others(resp, m);
+                }
+            }
+        }
+        assert forall|s2: StoreId, t: TxnId| #[trigger] next.net.has_rename_req(s2, t)
+            implies self.net.has_rename_req(s2, t) by {
+            self.net.lemma_send_preserves_others(resp, rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| #[trigger] next.net.has_rename_resp(s2, t)
+            implies self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_send_preserves_others(resp, rename_resp_msg(s2, t));
+        }
+        if self.coord.locks_acquired.contains(s) {
+            assert(self.coord.locks_acquired.insert(s) =~= self.coord.locks_acquired);
+        }
+    }
+
     // ============================================================
     // Environment (network-only) actions
     // ============================================================
@@ -284,95 +681,2894 @@ impl SystemSpec {
     {
         self.with_net(self.net.duplicate(msg))
     }
-}
 
-// ============================================================
-// TESTS
-// ============================================================
+    /// Lose each message of `msgs` in turn (an arbitrary run of `net_lose` steps)
+    pub open spec fn lose_all(self, msgs: Seq<Message>) -> Self
+        decreases msgs.len()
+    {
+        if msgs.len() == 0 {
+            self
+        } else {
+            self.lose_all(msgs.drop_last()).net_lose(msgs.last())
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // ============================================================
+    // Transition relation (TLA+ Next)
+    // ============================================================
 
-    spec fn key_a() -> Seq<char> { "A"@ }
-    spec fn key_aprime() -> Seq<char> { "A'"@ }
+    /// `other` is reachable from `self` by exactly one action: a coordinator
+    /// step, a store handling (or re-answering) a message, or a network fault.
+    /// Each disjunct is guarded by its action's enabling condition: coordinator
+    /// sends by the phase they belong to, the commit by a lock from every store,
+    /// receives and store handlers by the message being in flight. So an
+    /// inductive invariant only needs `inv(self) && next(self, other) ==> inv(other)`.
+    pub open spec fn next(self, other: SystemSpec<V>, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        // Coordinator sends
+        ||| exists|s: StoreId| #![auto] self.all_stores.contains(s)
+                && (self.coord.phase == CoordPhase::Idle || self.coord.phase == CoordPhase::Preparing)
+                && other == self.coord_send_lock_req(s)
+        ||| self.coord.phase == CoordPhase::Preparing
+                && self.coord.all_locks_acquired(self.all_stores)
+                && other == self.coord_decide_commit()
+        ||| exists|s: StoreId| #![auto] self.all_stores.contains(s)
+                && self.coord.phase == CoordPhase::Committed
+                && other == self.coord_send_rename_req(s)
+        ||| exists|s: StoreId| #![auto] self.all_stores.contains(s)
+                && self.coord.phase == CoordPhase::Cleanup
+                && self.coord.unlock_targets().contains(s)
+                && other == self.coord_send_unlock_req(s)
+        ||| exists|s: StoreId| #![auto] self.all_stores.contains(s)
+                && self.coord.phase == CoordPhase::Cleanup
+                && self.coord.unlock_targets().contains(s)
+                && other == self.coord_send_decide(s)
+        // Coordinator receives
+        ||| exists|s: StoreId| #![auto] self.coord.phase == CoordPhase::Preparing
+                && self.net.contains(lock_resp_msg(s, true, self.coord.current_txn_id))
+                && other == self.coord_recv_lock_resp_success(s)
+        ||| exists|s: StoreId| #![auto] self.coord.phase == CoordPhase::Preparing
+                && self.net.contains(lock_resp_msg(s, false, self.coord.current_txn_id))
+                && other == self.coord_recv_lock_resp_failure(s)
+        ||| exists|s: StoreId| #![auto] self.coord.phase == CoordPhase::Committed
+                && self.net.contains(rename_resp_msg(s, self.coord.current_txn_id))
+                && other == self.coord_recv_rename_resp(s)
+        ||| exists|s: StoreId| #![auto] self.coord.phase == CoordPhase::Cleanup
+                && self.net.contains(unlock_resp_msg(s, self.coord.current_txn_id))
+                && other == self.coord_recv_unlock_resp(s)
+        // Coordinator crash/recovery
+        ||| self.coord.phase.spec_can_crash() && other == self.coord_crash()
+        ||| self.coord.phase == CoordPhase::Crashed && other == self.coord_recover()
+        // Store handlers
+        ||| exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && self.net.contains(lock_req_msg(s, t))
+                && other == self.store_handle_lock_req(s, t, key_a, key_aprime)
+        ||| exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && self.net.contains(rename_req_msg(s, t))
+                && other == self.store_handle_rename_req(s, t, key_a, key_aprime)
+        ||| exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && self.net.contains(unlock_req_msg(s, t))
+                && other == self.store_handle_unlock_req(s, t, key_a, key_aprime)
+        ||| exists|s: StoreId, t: TxnId, commit: bool| #![auto] self.stores.contains_key(s)
+                && self.net.contains(decide_msg(s, commit, t))
+                && other == self.store_handle_decide(s, t, commit, key_a, key_aprime)
+        ||| exists|s: StoreId, t: TxnId, success: bool| #![auto]
+                self.store_responded_lock(s, t, success, key_a, key_aprime)
+                && other == self.store_resend_lock_resp(s, t, success)
+        // Environment
+        ||| exists|m: Message| #![auto] self.net.contains(m) && other == self.net_lose(m)
+        ||| exists|m: Message| #![auto] self.net.contains(m) && other == self.net_duplicate(m)
+    }
 
-    spec fn mk_one_store_system() -> SystemSpec {
-        let s0: StoreId = 0;
-        let all = Set::empty().insert(s0);
-        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
-        let stores = Map::empty().insert(s0, st0);
-        SystemSpec {
-            coord: CoordinatorSpec::init(),
-            net: NetworkSpec::empty(),
-            stores,
-            all_stores: all,
+    /// Store handlers only replace the handling store's state: the coordinator
+    /// and the set of stores are unchanged
+    pub proof fn lemma_store_handlers_preserve_shape(
+        self,
+        s: StoreId,
+        t: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.stores.contains_key(s),
+        ensures
+            self.store_handle_lock_req(s, t, key_a, key_aprime).coord == self.coord,
+            self.store_handle_lock_req(s, t, key_a, key_aprime).all_stores == self.all_stores,
+            self.store_handle_lock_req(s, t, key_a, key_aprime).stores.dom() == self.stores.dom(),
+            self.store_handle_rename_req(s, t, key_a, key_aprime).coord == self.coord,
+            self.store_handle_rename_req(s, t, key_a, key_aprime).all_stores == self.all_stores,
+            self.store_handle_rename_req(s, t, key_a, key_aprime).stores.dom() == self.stores.dom(),
+            self.store_handle_unlock_req(s, t, key_a, key_aprime).coord == self.coord,
+            self.store_handle_unlock_req(s, t, key_a, key_aprime).all_stores == self.all_stores,
+            self.store_handle_unlock_req(s, t, key_a, key_aprime).stores.dom() == self.stores.dom(),
+            forall|commit: bool| #![auto] self.store_handle_decide(s, t, commit, key_a, key_aprime).coord == self.coord,
+            forall|commit: bool| #![auto]
+                self.store_handle_decide(s, t, commit, key_a, key_aprime).all_stores == self.all_stores,
+            forall|commit: bool| #![auto]
+                self.store_handle_decide(s, t, commit, key_a, key_aprime).stores.dom() == self.stores.dom(),
+    {
+        // Inserting at an existing key leaves the domain as it was
+        assert forall|st: KvStoreSpec<V>| #[trigger] self.stores.insert(s, st).dom() == self.stores.dom() by {
+            assert(self.stores.insert(s, st).dom() =~= self.stores.dom());
         }
     }
 
-    spec fn mk_two_store_system() -> SystemSpec {
-        let s0: StoreId = 0;
-        let s1: StoreId = 1;
-        let all = Set::empty().insert(s0).insert(s1);
-        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
-        let st1 = KvStoreSpec::empty().put(key_a(), 20u64);
-        let stores = Map::empty().insert(s0, st0).insert(s1, st1);
-        SystemSpec {
-            coord: CoordinatorSpec::init(),
-            net: NetworkSpec::empty(),
-            stores,
-            all_stores: all,
+    /// Lemma: every step keeps the state well-typed
+    pub proof fn lemma_next_preserves_type_ok(self, other: SystemSpec<V>, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.type_ok(),
+            self.next(other, key_a, key_aprime),
+        ensures
+            other.type_ok(),
+    {
+        if exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+            && self.net.contains(lock_req_msg(s, t))
+            && other == self.store_handle_lock_req(s, t, key_a, key_aprime) {
+            let (s, t) = choose|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && self.net.contains(lock_req_msg(s, t))
+                && other == self.store_handle_lock_req(s, t, key_a, key_aprime);
+            self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+        } else if exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+            && self.net.contains(rename_req_msg(s, t))
+            && other == self.store_handle_rename_req(s, t, key_a, key_aprime) {
+            let (s, t) = choose|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && self.net.contains(rename_req_msg(s, t))
+                && other == self.store_handle_rename_req(s, t, key_a, key_aprime);
+            self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+        } else if exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+            && self.net.contains(unlock_req_msg(s, t))
+            && other == self.store_handle_unlock_req(s, t, key_a, key_aprime) {
+            let (s, t) = choose|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && self.net.contains(unlock_req_msg(s, t))
+                && other == self.store_handle_unlock_req(s, t, key_a, key_aprime);
+            self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+        } else if exists|s: StoreId, t: TxnId, commit: bool| #![auto] self.stores.contains_key(s)
+            && self.net.contains(decide_msg(s, commit, t))
+            && other == self.store_handle_decide(s, t, commit, key_a, key_aprime) {
+            let (s, t, commit) = choose|s: StoreId, t: TxnId, commit: bool| #![auto] self.stores.contains_key(s)
+                && self.net.contains(decide_msg(s, commit, t))
+                && other == self.store_handle_decide(s, t, commit, key_a, key_aprime);
+            self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
         }
+        // Every other action leaves `stores` and `all_stores` alone and at most
+        // bumps the txn id (recover), so type_ok carries over directly
     }
 
-    /// End-to-end happy path:
-    /// - coordinator sends LockReq
-    /// - stores lock and respond
-    /// - coordinator commits and sends RenameReq
-    /// - stores rename and respond
-    /// - coordinator sends UnlockReq
-    /// - stores unlock and respond
-    /// - coordinator reaches Done
-    proof fn test_success_path_two_stores() {
-        let s0: StoreId = 0;
-        let s1: StoreId = 1;
-        let txn: TxnId = 1;
-
-        let sys0 = mk_two_store_system();
-
-        let sys1 = sys0
-            .coord_send_lock_req(s0)
-            .coord_send_lock_req(s1);
-
-        assert(sys1.coord.phase == CoordPhase::Preparing);
-        assert(sys1.net.contains(lock_req_msg(s0, txn)));
-        assert(sys1.net.contains(lock_req_msg(s1, txn)));
+    // ============================================================
+    // Safety under message loss
+    // ============================================================
 
-        let sys2 = sys1
-            .store_handle_lock_req(s0, txn, key_a(), key_aprime())
-            .store_handle_lock_req(s1, txn, key_a(), key_aprime());
+    /// System-wide safety invariant: every store keeps its value at exactly one
+    /// of {A, A'}, over a well-formed network with no spontaneous responses.
+    pub open spec fn system_invariant(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        &&& self.type_ok()
+        &&& self.well_formed_network()
+        &&& self.net.causally_consistent()
+        &&& self.store_txns_bounded()
+        &&& forall|s: StoreId| #[trigger] self.stores.contains_key(s)
+                ==> data_accessible(self.stores[s], key_a, key_aprime)
+    }
 
-        assert(sys2.net.contains(lock_resp_msg(s0, true, txn)));
-        assert(sys2.net.contains(lock_resp_msg(s1, true, txn)));
-        assert(sys2.store(s0).is_locked(key_a()));
-        assert(sys2.store(s0).is_locked(key_aprime()));
+    /// Lemma: losing a message preserves the invariant.
+    ///
+    /// `net_lose` only removes an in-flight message; no store or coordinator state
+    /// changes, so loss can stall the protocol but never corrupt it. No delivery
+    /// assumption is needed.
+    pub proof fn lemma_net_lose_preserves_invariant(
+        self,
+        msg: Message,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.system_invariant(key_a, key_aprime),
+        ensures
+            self.net_lose(msg).stores == self.stores,
+            self.net_lose(msg).coord == self.coord,
+            self.net_lose(msg).system_invariant(key_a, key_aprime),
+    {
+        self.lemma_net_lose_preserves_well_formed_network(msg);
+    }
 
-        let sys3 = sys2
-            .coord_recv_lock_resp_success(s0)
-            .coord_recv_lock_resp_success(s1);
+    /// Lemma: any sequence of message losses preserves the invariant.
+    pub proof fn lemma_lose_all_preserves_invariant(
+        self,
+        msgs: Seq<Message>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.system_invariant(key_a, key_aprime),
+        ensures
+            self.lose_all(msgs).stores == self.stores,
+            self.lose_all(msgs).coord == self.coord,
+            self.lose_all(msgs).system_invariant(key_a, key_aprime),
+        decreases msgs.len()
+    {
+        if msgs.len() > 0 {
+            self.lemma_lose_all_preserves_invariant(msgs.drop_last(), key_a, key_aprime);
+            self.lose_all(msgs.drop_last()).lemma_net_lose_preserves_invariant(
+                msgs.last(),
+                key_a,
+                key_aprime,
+            );
+        }
+    }
 
-        assert(sys3.coord.locks_acquired == sys3.all_stores);
+    // ============================================================
+    // Well-formed network
+    // ============================================================
 
-        let sys4 = sys3.coord_decide_commit();
-        assert(sys4.coord.phase == CoordPhase::Committed);
-        assert(sys4.coord.wal_committed);
+    /// Every in-flight message addresses a participant store and carries a txn id
+    /// no newer than the coordinator's (no message from the future).
+    pub open spec fn well_formed_network(&self) -> bool {
+        forall|m: Message| #[trigger] self.net.contains(m) ==>
+            self.all_stores.contains(m.get_store()) && m.get_txn_id() <= self.coord.current_txn_id
+    }
 
-        let sys5 = sys4
-            .coord_send_rename_req(s0)
-            .coord_send_rename_req(s1);
+    /// Lemma: coordinator sends stamp a participant and `current_txn_id`, so they
+    /// preserve `well_formed_network`; decide_commit leaves the network alone.
+    pub proof fn lemma_coord_sends_preserve_well_formed_network(self, s: StoreId)
+        requires
+            self.well_formed_network(),
+            self.all_stores.contains(s),
+        ensures
+            self.coord_send_lock_req(s).well_formed_network(),
+            self.coord_send_rename_req(s).well_formed_network(),
+            self.coord_send_unlock_req(s).well_formed_network(),
+            self.coord_send_decide(s).well_formed_network(),
+            self.coord_decide_commit().well_formed_network(),
+            self.net.causally_consistent() ==> {
+                &&& self.coord_send_lock_req(s).net.causally_consistent()
+                &&& self.coord_send_rename_req(s).net.causally_consistent()
+                &&& self.coord_send_unlock_req(s).net.causally_consistent()
+                &&& self.coord_send_decide(s).net.causally_consistent()
+                &&& self.coord_decide_commit().net.causally_consistent()
+            },
+    {
+        let txn = self.coord.current_txn_id;
+        if self.net.causally_consistent() {
+            // Requests are never LockResps, so sending them is always allowed
+            self.net.lemma_send_preserves_causally_consistent(lock_req_msg(s, txn));
+            self.net.lemma_send_preserves_causally_consistent(rename_req_msg(s, txn));
+            self.net.lemma_send_preserves_causally_consistent(unlock_req_msg(s, txn));
+            self.net.lemma_send_preserves_causally_consistent(decide_msg(s, self.coord.wal_committed, txn));
+        }
+        assert forall|m: Message| #[trigger] self.coord_send_lock_req(s).net.contains(m) implies
+            self.all_stores.contains(m.get_store()) && m.get_txn_id() <= txn by {
+            if m != lock_req_msg(s, txn) {
+                self.net.lemma_send_preserves_others(lock_req_msg(s, txn), m);
+            }
+        }
+        assert forall|m: Message| #[trigger] self.coord_send_rename_req(s).net.contains(m) implies
+            self.all_stores.contains(m.get_store()) && m.get_txn_id() <= txn by {
+            if m != rename_req_msg(s, txn) {
+                self.net.lemma_send_preserves_others(rename_req_msg(s, txn), m);
+            }
+        }
+        assert forall|m: Message| #[trigger] self.coord_send_unlock_req(s).net.contains(m) implies
+            self.all_stores.contains(m.get_store()) && m.get_txn_id() <= txn by {
+            if m != unlock_req_msg(s, txn) {
+                self.net.lemma_send_preserves_others(unlock_req_msg(s, txn), m);
+            }
+        }
+        let decide = decide_msg(s, self.coord.wal_committed, txn);
+        assert forall|m: Message| #[trigger] self.coord_send_decide(s).net.contains(m) implies
+            self.all_stores.contains(m.get_store()) && m.get_txn_id() <= txn by {
+            if m != decide {
+                self.net.lemma_send_preserves_others(decide, m);
+            }
+        }
+    }
 
-        assert(sys5.net.contains(rename_req_msg(s0, txn)));
-        assert(sys5.net.contains(rename_req_msg(s1, txn)));
+    /// Lemma: coordinator receives only consume messages, and crash/recover never
+    /// move `current_txn_id` backwards, so they preserve `well_formed_network`.
+    pub proof fn lemma_coord_recvs_preserve_well_formed_network(self, s: StoreId)
+        requires
+            self.well_formed_network(),
+        ensures
+            self.coord_recv_lock_resp_success(s).well_formed_network(),
+            self.coord_recv_lock_resp_failure(s).well_formed_network(),
+            self.coord_recv_rename_resp(s).well_formed_network(),
+            self.coord_recv_unlock_resp(s).well_formed_network(),
+            self.coord_crash().well_formed_network(),
+            self.coord_recover().well_formed_network(),
+            self.net.causally_consistent() ==> {
+                &&& self.coord_recv_lock_resp_success(s).net.causally_consistent()
+                &&& self.coord_recv_lock_resp_failure(s).net.causally_consistent()
+                &&& self.coord_recv_rename_resp(s).net.causally_consistent()
+                &&& self.coord_recv_unlock_resp(s).net.causally_consistent()
+                &&& self.coord_crash().net.causally_consistent()
+                &&& self.coord_recover().net.causally_consistent()
+            },
+    {
+        let txn = self.coord.current_txn_id;
+        self.lemma_net_lose_preserves_well_formed_network(lock_resp_msg(s, true, txn));
+        self.lemma_net_lose_preserves_well_formed_network(lock_resp_msg(s, false, txn));
+        self.lemma_net_lose_preserves_well_formed_network(rename_resp_msg(s, txn));
+        self.lemma_net_lose_preserves_well_formed_network(unlock_resp_msg(s, txn));
+    }
+
+    /// Lemma: a store handler answers (if at all) with the request's own store and
+    /// txn id, so handling a well-formed request preserves `well_formed_network`.
+    pub proof fn lemma_store_handles_preserve_well_formed_network(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.well_formed_network(),
+        ensures
+            self.net.contains(lock_req_msg(s, txn_id)) ==>
+                self.store_handle_lock_req(s, txn_id, key_a, key_aprime).well_formed_network(),
+            self.net.contains(rename_req_msg(s, txn_id)) ==>
+                self.store_handle_rename_req(s, txn_id, key_a, key_aprime).well_formed_network(),
+            self.net.contains(unlock_req_msg(s, txn_id)) ==>
+                self.store_handle_unlock_req(s, txn_id, key_a, key_aprime).well_formed_network(),
+            self.net.causally_consistent() && self.net.contains(lock_req_msg(s, txn_id)) ==>
+                self.store_handle_lock_req(s, txn_id, key_a, key_aprime).net.causally_consistent(),
+            self.net.causally_consistent() && self.net.contains(rename_req_msg(s, txn_id)) ==>
+                self.store_handle_rename_req(s, txn_id, key_a, key_aprime).net.causally_consistent(),
+            self.net.causally_consistent() && self.net.contains(unlock_req_msg(s, txn_id)) ==>
+                self.store_handle_unlock_req(s, txn_id, key_a, key_aprime).net.causally_consistent(),
+    {
+        let (lreq, rreq, ureq) = (lock_req_msg(s, txn_id), rename_req_msg(s, txn_id), unlock_req_msg(s, txn_id));
+        if self.net.contains(lreq) {
+            self.lemma_respond_preserves_well_formed_network(lreq, lock_resp_msg(s, true, txn_id));
+            self.lemma_respond_preserves_well_formed_network(lreq, lock_resp_msg(s, false, txn_id));
+        }
+        if self.net.contains(rreq) {
+            self.lemma_respond_preserves_well_formed_network(rreq, rename_resp_msg(s, txn_id));
+        }
+        if self.net.contains(ureq) {
+            self.lemma_respond_preserves_well_formed_network(ureq, unlock_resp_msg(s, txn_id));
+        }
+    }
+
+    /// Lemma: handling a LockReq establishes `response_implies_request` for its
+    /// store and txn id. `store_handle_lock_req` is the only action that creates a
+    /// LockResp from a request (re-sends require the same LockReq in the history),
+    /// and it only answers a request that was in flight, hence sent.
+    pub proof fn lemma_store_handle_lock_req_response_implies_request(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.net.in_flight_sent(),
+            self.net.contains(lock_req_msg(s, txn_id)),
+        ensures
+            self.store_handle_lock_req(s, txn_id, key_a, key_aprime).net.response_implies_request(s, txn_id),
+    {
+        assert(self.net.sent.contains(lock_req_msg(s, txn_id)));
+    }
+
+    /// No store has seen a txn id newer than the coordinator's: stores only learn
+    /// txn ids from requests, which the coordinator stamps with its own id
+    pub open spec fn store_txns_bounded(&self) -> bool {
+        forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==>
+            self.stores[s].last_seen_txn_id <= self.coord.current_txn_id
+    }
+
+    /// Lemma: store handlers preserve `store_txns_bounded`. A handled request is
+    /// in flight, so `well_formed_network` bounds its txn id by the coordinator's,
+    /// and a store only ever adopts that id (the coordinator is unchanged).
+    pub proof fn lemma_store_txn_bounded_by_coord(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.well_formed_network(),
+            self.store_txns_bounded(),
+        ensures
+            self.net.contains(lock_req_msg(s, txn_id)) ==>
+                self.store_handle_lock_req(s, txn_id, key_a, key_aprime).store_txns_bounded(),
+            self.net.contains(rename_req_msg(s, txn_id)) ==>
+                self.store_handle_rename_req(s, txn_id, key_a, key_aprime).store_txns_bounded(),
+            self.net.contains(unlock_req_msg(s, txn_id)) ==>
+                self.store_handle_unlock_req(s, txn_id, key_a, key_aprime).store_txns_bounded(),
+    {
+        if self.net.contains(lock_req_msg(s, txn_id)) {
+            assert(lock_req_msg(s, txn_id).get_txn_id() <= self.coord.current_txn_id);
+            let post = self.store_handle_lock_req(s, txn_id, key_a, key_aprime);
+            assert forall|s2: StoreId| #[trigger] post.stores.contains_key(s2) implies
+                post.stores[s2].last_seen_txn_id <= post.coord.current_txn_id by {
+                if s2 != s {
+                    assert(post.stores[s2] == self.stores[s2]);
+                }
+            }
+        }
+        if self.net.contains(rename_req_msg(s, txn_id)) {
+            assert(rename_req_msg(s, txn_id).get_txn_id() <= self.coord.current_txn_id);
+            let post = self.store_handle_rename_req(s, txn_id, key_a, key_aprime);
+            assert forall|s2: StoreId| #[trigger] post.stores.contains_key(s2) implies
+                post.stores[s2].last_seen_txn_id <= post.coord.current_txn_id by {
+                if s2 != s {
+                    assert(post.stores[s2] == self.stores[s2]);
+                }
+            }
+        }
+        if self.net.contains(unlock_req_msg(s, txn_id)) {
+            assert(unlock_req_msg(s, txn_id).get_txn_id() <= self.coord.current_txn_id);
+            let post = self.store_handle_unlock_req(s, txn_id, key_a, key_aprime);
+            assert forall|s2: StoreId| #[trigger] post.stores.contains_key(s2) implies
+                post.stores[s2].last_seen_txn_id <= post.coord.current_txn_id by {
+                if s2 != s {
+                    assert(post.stores[s2] == self.stores[s2]);
+                }
+            }
+        }
+    }
+
+    /// Lemma: consuming an in-flight request and answering with the same store and
+    /// txn id (or not answering) preserves `well_formed_network`. A LockResp that
+    /// answers the LockReq it consumed also keeps the network causally consistent.
+    pub proof fn lemma_respond_preserves_well_formed_network(self, req: Message, resp: Message)
+        requires
+            self.well_formed_network(),
+            self.net.contains(req),
+            resp.get_store() == req.get_store(),
+            resp.get_txn_id() == req.get_txn_id(),
+        ensures
+            self.with_net(self.net.lose(req)).well_formed_network(),
+            self.with_net(self.net.lose(req).send(resp)).well_formed_network(),
+            self.net.causally_consistent() ==> self.net.lose(req).causally_consistent(),
+            self.net.causally_consistent()
+                && (resp.kind() == MsgKind::LockResp ==> req == lock_req_msg(resp.get_store(), resp.get_txn_id()))
+                ==> self.net.lose(req).send(resp).causally_consistent(),
+    {
+        let net1 = self.net.lose(req);
+        self.lemma_net_lose_preserves_well_formed_network(req);
+        if self.net.causally_consistent() {
+            self.net.lemma_lose_preserves_causally_consistent(req);
+            if resp.kind() == MsgKind::LockResp ==> req == lock_req_msg(resp.get_store(), resp.get_txn_id()) {
+                // The consumed request stays in the history
+                assert(self.net.sent.contains(req));
+                net1.lemma_send_preserves_causally_consistent(resp);
+            }
+        }
+        assert forall|m: Message| #[trigger] net1.send(resp).contains(m) implies
+            self.all_stores.contains(m.get_store()) && m.get_txn_id() <= self.coord.current_txn_id by {
+            if m != resp {
+                net1.lemma_send_preserves_others(resp, m);
+                assert(self.with_net(net1).net.contains(m));
+            }
+        }
+    }
+
+    /// Lemma: losing a message preserves `well_formed_network`.
+    pub proof fn lemma_net_lose_preserves_well_formed_network(self, msg: Message)
+        requires
+            self.well_formed_network(),
+        ensures
+            self.net_lose(msg).well_formed_network(),
+            self.net.contains(msg) ==> self.net_duplicate(msg).well_formed_network(),
+            self.net.causally_consistent() ==> self.net_lose(msg).net.causally_consistent(),
+            self.net.causally_consistent() && self.net.contains(msg) ==>
+                self.net_duplicate(msg).net.causally_consistent(),
+    {
+        if self.net.causally_consistent() {
+            self.net.lemma_lose_preserves_causally_consistent(msg);
+        }
+        assert forall|m: Message| #[trigger] self.net_lose(msg).net.contains(m) implies
+            self.net.contains(m) by {
+            if m != msg {
+                self.net.lemma_lose_preserves_others(msg, m);
+            }
+        }
+        assert forall|m: Message| #[trigger] self.net_duplicate(msg).net.contains(m) implies
+            m == msg || self.net.contains(m) by {}
+    }
+
+    // ============================================================
+    // Agreement
+    // ============================================================
+
+    /// Agreement: every store reflects the coordinator's decision. On commit
+    /// every store holds the value at `key_aprime` only; on abort every store
+    /// still holds it at `key_a` only. No store commits while another aborts.
+    pub open spec fn stores_reflect_decision(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        &&& self.coord.decision() == AbstractPhase::Committed ==>
+                forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==>
+                    self.stores[s].contains_key(key_aprime) && !self.stores[s].contains_key(key_a)
+        &&& self.coord.decision() == AbstractPhase::Aborted ==>
+                forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==>
+                    self.stores[s].contains_key(key_a) && !self.stores[s].contains_key(key_aprime)
+    }
+
+    /// Inductive invariant behind agreement.
+    ///
+    /// Before the WAL commit no store has renamed and no `RenameReq` is in flight;
+    /// a store is only credited with a rename (or answers one) once it holds
+    /// `key_aprime`; and a committed coordinator only reaches Cleanup/Done after
+    /// every store has renamed.
+    pub open spec fn agreement_inv(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        &&& self.type_ok()
+        &&& key_a != key_aprime
+        &&& self.coord.renames_done.subset_of(self.all_stores)
+        &&& committed_implies_wal(self.coord)
+        &&& self.coord.wal_committed ==>
+                self.coord.phase != CoordPhase::Idle && self.coord.phase != CoordPhase::Preparing
+        &&& (self.coord.wal_committed
+                && (self.coord.phase == CoordPhase::Cleanup || self.coord.phase == CoordPhase::Done))
+                ==> self.coord.renames_done == self.all_stores
+        &&& forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==>
+                data_accessible(self.stores[s], key_a, key_aprime)
+        &&& !self.coord.wal_committed ==>
+                forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==>
+                    self.stores[s].contains_key(key_a)
+        &&& !self.coord.wal_committed ==>
+                forall|s: StoreId, t: TxnId| !#[trigger] self.net.has_rename_req(s, t)
+        &&& forall|s: StoreId| #[trigger] self.coord.renames_done.contains(s) ==>
+                self.stores[s].contains_key(key_aprime)
+        &&& forall|s: StoreId, t: TxnId| #[trigger] self.net.has_rename_resp(s, t) ==>
+                self.stores.contains_key(s) && self.stores[s].contains_key(key_aprime)
+    }
+
+    /// Lemma: the initial system (every store holding `key_a`, nothing in flight)
+    /// satisfies the agreement invariant.
+    pub proof fn lemma_init_agreement_inv(self, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.coord == CoordinatorSpec::init(),
+            self.net == NetworkSpec::empty(),
+            self.all_stores == self.stores.dom(),
+            key_a != key_aprime,
+            forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==>
+                self.stores[s].contains_key(key_a) && !self.stores[s].contains_key(key_aprime),
+        ensures
+            self.agreement_inv(key_a, key_aprime),
+    {
+    }
+
+    /// Degraded mode: drop stores known to be permanently dead before the
+    /// transaction starts. They leave both the participant set and the store
+    /// map, so what remains is an ordinary run over the included stores and the
+    /// excluded ones get no atomicity guarantee.
+    pub open spec fn exclude_participants(self, excluded: Set<StoreId>) -> Self {
+        SystemSpec {
+            stores: self.stores.remove_keys(excluded),
+            all_stores: self.all_stores.difference(excluded),
+            ..self
+        }
+    }
+
+    /// Lemma: excluding participants from an initial system keeps the agreement
+    /// invariant, so every preservation lemma below applies to the degraded run
+    /// and, at Done, all included stores reflect the decision
+    /// (`lemma_agreement_at_done`).
+    pub proof fn lemma_exclude_participants_init_agreement_inv(
+        self,
+        excluded: Set<StoreId>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.coord == CoordinatorSpec::init(),
+            self.net == NetworkSpec::empty(),
+            self.all_stores == self.stores.dom(),
+            key_a != key_aprime,
+            forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==>
+                self.stores[s].contains_key(key_a) && !self.stores[s].contains_key(key_aprime),
+        ensures
+            self.exclude_participants(excluded).agreement_inv(key_a, key_aprime),
+            forall|s: StoreId| #[trigger] self.exclude_participants(excluded).all_stores.contains(s)
+                ==> !excluded.contains(s),
+    {
+        let degraded = self.exclude_participants(excluded);
+        assert(degraded.all_stores =~= degraded.stores.dom());
+        degraded.lemma_init_agreement_inv(key_a, key_aprime);
+    }
+
+    /// Lemma (agreement): once the protocol is Done, all stores reflect the decision.
+    pub proof fn lemma_agreement_at_done(self, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.coord.phase == CoordPhase::Done,
+        ensures
+            self.stores_reflect_decision(key_a, key_aprime),
+            self.coord.wal_committed ==> self.coord.decision() == AbstractPhase::Committed,
+            !self.coord.wal_committed ==> self.coord.decision() == AbstractPhase::Aborted,
+    {
+        if self.coord.wal_committed {
+            assert forall|s: StoreId| #[trigger] self.stores.contains_key(s) implies
+                self.stores[s].contains_key(key_aprime) && !self.stores[s].contains_key(key_a) by {
+                // Every store is a participant, and all participants have renamed
+                assert(self.all_stores.contains(s));
+                assert(self.coord.renames_done.contains(s));
+                assert(data_accessible(self.stores[s], key_a, key_aprime));
+            }
+        } else {
+            assert forall|s: StoreId| #[trigger] self.stores.contains_key(s) implies
+                self.stores[s].contains_key(key_a) && !self.stores[s].contains_key(key_aprime) by {
+                assert(data_accessible(self.stores[s], key_a, key_aprime));
+            }
+        }
+    }
+
+    // ----- Preservation: coordinator actions -----
+
+    pub proof fn lemma_coord_send_lock_req_preserves_agreement(
+        self,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.coord.phase == CoordPhase::Idle || self.coord.phase == CoordPhase::Preparing,
+        ensures
+            self.coord_send_lock_req(s).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.coord_send_lock_req(s);
+        // A LockReq is neither a RenameReq nor a RenameResp
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_req(s2, t) == self.net.has_rename_req(s2, t) by {
+            self.net.lemma_send_preserves_others(lock_req_msg(s, self.coord.current_txn_id), rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_resp(s2, t) == self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_send_preserves_others(lock_req_msg(s, self.coord.current_txn_id), rename_resp_msg(s2, t));
+        }
+    }
+
+    pub proof fn lemma_coord_decide_commit_preserves_agreement(
+        self,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.coord.phase == CoordPhase::Preparing,
+        ensures
+            self.coord_decide_commit().agreement_inv(key_a, key_aprime),
+    {
+    }
+
+    pub proof fn lemma_coord_send_rename_req_preserves_agreement(
+        self,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.coord.phase == CoordPhase::Committed,
+        ensures
+            self.coord_send_rename_req(s).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.coord_send_rename_req(s);
+        // Committed implies the WAL commit, so RenameReqs may now be in flight
+        assert(self.coord.wal_committed);
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_resp(s2, t) == self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_send_preserves_others(rename_req_msg(s, self.coord.current_txn_id), rename_resp_msg(s2, t));
+        }
+    }
+
+    pub proof fn lemma_coord_send_unlock_req_preserves_agreement(
+        self,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+        ensures
+            self.coord_send_unlock_req(s).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.coord_send_unlock_req(s);
+        let msg = unlock_req_msg(s, self.coord.current_txn_id);
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_req(s2, t) == self.net.has_rename_req(s2, t) by {
+            self.net.lemma_send_preserves_others(msg, rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_resp(s2, t) == self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_send_preserves_others(msg, rename_resp_msg(s2, t));
+        }
+    }
+
+    pub proof fn lemma_coord_send_decide_preserves_agreement(
+        self,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+        ensures
+            self.coord_send_decide(s).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.coord_send_decide(s);
+        let msg = decide_msg(s, self.coord.wal_committed, self.coord.current_txn_id);
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_req(s2, t) == self.net.has_rename_req(s2, t) by {
+            self.net.lemma_send_preserves_others(msg, rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_resp(s2, t) == self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_send_preserves_others(msg, rename_resp_msg(s2, t));
+        }
+    }
+
+    pub proof fn lemma_coord_recv_lock_resp_success_preserves_agreement(
+        self,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.coord.phase == CoordPhase::Preparing,
+        ensures
+            self.coord_recv_lock_resp_success(s).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.coord_recv_lock_resp_success(s);
+        let msg = lock_resp_msg(s, true, self.coord.current_txn_id);
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_req(s2, t) == self.net.has_rename_req(s2, t) by {
+            self.net.lemma_lose_preserves_others(msg, rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_resp(s2, t) == self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_lose_preserves_others(msg, rename_resp_msg(s2, t));
+        }
+    }
+
+    pub proof fn lemma_coord_recv_lock_resp_failure_preserves_agreement(
+        self,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.coord.phase == CoordPhase::Preparing,
+        ensures
+            self.coord_recv_lock_resp_failure(s).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.coord_recv_lock_resp_failure(s);
+        let msg = lock_resp_msg(s, false, self.coord.current_txn_id);
+        // Preparing means no commit yet, so the abort keeps wal_committed false
+        assert(!self.coord.wal_committed);
+        assert(post.coord.renames_done =~= Set::<StoreId>::empty());
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_req(s2, t) == self.net.has_rename_req(s2, t) by {
+            self.net.lemma_lose_preserves_others(msg, rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_resp(s2, t) == self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_lose_preserves_others(msg, rename_resp_msg(s2, t));
+        }
+    }
+
+    pub proof fn lemma_coord_recv_rename_resp_preserves_agreement(
+        self,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.coord.phase == CoordPhase::Committed,
+            self.net.contains(rename_resp_msg(s, self.coord.current_txn_id)),
+            self.all_stores.contains(s),
+        ensures
+            self.coord_recv_rename_resp(s).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.coord_recv_rename_resp(s);
+        let msg = rename_resp_msg(s, self.coord.current_txn_id);
+        // The response is only in flight because store s already holds key_aprime
+        assert(self.net.has_rename_resp(s, self.coord.current_txn_id));
+        assert(self.stores[s].contains_key(key_aprime));
+        assert(post.coord.renames_done.subset_of(post.all_stores));
+        assert forall|s2: StoreId, t: TxnId| #[trigger] post.net.has_rename_req(s2, t)
+            implies self.net.has_rename_req(s2, t) by {
+            self.net.lemma_lose_preserves_others(msg, rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| #[trigger] post.net.has_rename_resp(s2, t)
+            implies self.net.has_rename_resp(s2, t) by {
+            if rename_resp_msg(s2, t) != msg {
+                self.net.lemma_lose_preserves_others(msg, rename_resp_msg(s2, t));
+            }
+        }
+    }
+
+    pub proof fn lemma_coord_recv_unlock_resp_preserves_agreement(
+        self,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.coord.phase == CoordPhase::Cleanup,
+        ensures
+            self.coord_recv_unlock_resp(s).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.coord_recv_unlock_resp(s);
+        let msg = unlock_resp_msg(s, self.coord.current_txn_id);
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_req(s2, t) == self.net.has_rename_req(s2, t) by {
+            self.net.lemma_lose_preserves_others(msg, rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| post.net.has_rename_resp(s2, t) == self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_lose_preserves_others(msg, rename_resp_msg(s2, t));
+        }
+    }
+
+    pub proof fn lemma_coord_crash_recover_preserve_agreement(
+        self,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+        ensures
+            self.coord.phase.spec_can_crash() ==> self.coord_crash().agreement_inv(key_a, key_aprime),
+            self.coord.phase == CoordPhase::Crashed ==>
+                self.coord_recover().agreement_inv(key_a, key_aprime),
+    {
+        // Both reset renames_done; recovery resumes Committed exactly when the WAL committed
+        assert(self.coord.crash().renames_done =~= Set::<StoreId>::empty());
+        assert(self.coord.recover().renames_done =~= Set::<StoreId>::empty());
+    }
+
+    // ----- Preservation: store actions -----
+
+    pub proof fn lemma_store_handle_lock_req_preserves_agreement(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.stores.contains_key(s),
+        ensures
+            self.store_handle_lock_req(s, txn_id, key_a, key_aprime).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.store_handle_lock_req(s, txn_id, key_a, key_aprime);
+        let req = lock_req_msg(s, txn_id);
+        let st0 = self.store(s);
+        // Locking and the txn-id update never touch data
+        st0.lemma_update_txn_id_preserves_state(txn_id);
+        st0.update_txn_id(txn_id).lemma_lock_preserves_data(key_a);
+        st0.update_txn_id(txn_id).lock(key_a).lemma_lock_preserves_data(key_aprime);
+        assert forall|s2: StoreId| #[trigger] post.stores.contains_key(s2) implies
+            post.stores[s2].data == self.stores[s2].data by {}
+        let net1 = self.net.lose(req);
+        assert forall|s2: StoreId, t: TxnId| #[trigger] post.net.has_rename_req(s2, t)
+            implies self.net.has_rename_req(s2, t) by {
+            self.net.lemma_lose_preserves_others(req, rename_req_msg(s2, t));
+            net1.lemma_send_preserves_others(lock_resp_msg(s, true, txn_id), rename_req_msg(s2, t));
+            net1.lemma_send_preserves_others(lock_resp_msg(s, false, txn_id), rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| #[trigger] post.net.has_rename_resp(s2, t)
+            implies self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_lose_preserves_others(req, rename_resp_msg(s2, t));
+            net1.lemma_send_preserves_others(lock_resp_msg(s, true, txn_id), rename_resp_msg(s2, t));
+            net1.lemma_send_preserves_others(lock_resp_msg(s, false, txn_id), rename_resp_msg(s2, t));
+        }
+    }
+
+    pub proof fn lemma_store_handle_rename_req_preserves_agreement(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.stores.contains_key(s),
+            self.net.contains(rename_req_msg(s, txn_id)),
+        ensures
+            self.store_handle_rename_req(s, txn_id, key_a, key_aprime).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.store_handle_rename_req(s, txn_id, key_a, key_aprime);
+        let req = rename_req_msg(s, txn_id);
+        let resp = rename_resp_msg(s, txn_id);
+        let st0 = self.store(s);
+        let st1 = st0.update_txn_id(txn_id);
+        // A RenameReq in flight means the WAL has committed
+        assert(self.net.has_rename_req(s, txn_id));
+        assert(self.coord.wal_committed);
+        st0.lemma_update_txn_id_preserves_state(txn_id);
+        if !st0.is_stale_txn_id(txn_id) && !st1.contains_key(key_aprime)
+            && st1.is_locked(key_a) && st1.is_locked(key_aprime) && st1.contains_key(key_a) {
+            // The rename moves the value from key_a to key_aprime
+            lemma_data_accessible_preserved(st1, key_a, key_aprime);
+            st1.lemma_rename_preserves_value(key_a, key_aprime);
+            assert(post.stores[s].contains_key(key_aprime));
+        }
+        // Store s never loses key_aprime, and responds only while holding it
+        assert(st0.contains_key(key_aprime) ==> post.stores[s].contains_key(key_aprime));
+        let net1 = self.net.lose(req);
+        assert forall|s2: StoreId, t: TxnId| #[trigger] post.net.has_rename_resp(s2, t)
+            implies post.stores.contains_key(s2) && post.stores[s2].contains_key(key_aprime) by {
+            self.net.lemma_lose_preserves_others(req, rename_resp_msg(s2, t));
+            if rename_resp_msg(s2, t) != resp {
+                net1.lemma_send_preserves_others(resp, rename_resp_msg(s2, t));
+            }
+        }
+    }
+
+    pub proof fn lemma_store_handle_unlock_req_preserves_agreement(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.stores.contains_key(s),
+        ensures
+            self.store_handle_unlock_req(s, txn_id, key_a, key_aprime).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.store_handle_unlock_req(s, txn_id, key_a, key_aprime);
+        let req = unlock_req_msg(s, txn_id);
+        let resp = unlock_resp_msg(s, txn_id);
+        let st0 = self.store(s);
+        // Unlocking and the txn-id update never touch data
+        st0.lemma_update_txn_id_preserves_state(txn_id);
+        st0.update_txn_id(txn_id).lemma_unlock_preserves_data(key_a);
+        st0.update_txn_id(txn_id).unlock(key_a).lemma_unlock_preserves_data(key_aprime);
+        let net1 = self.net.lose(req);
+        assert forall|s2: StoreId, t: TxnId| #[trigger] post.net.has_rename_req(s2, t)
+            implies self.net.has_rename_req(s2, t) by {
+            self.net.lemma_lose_preserves_others(req, rename_req_msg(s2, t));
+            net1.lemma_send_preserves_others(resp, rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| #[trigger] post.net.has_rename_resp(s2, t)
+            implies self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_lose_preserves_others(req, rename_resp_msg(s2, t));
+            net1.lemma_send_preserves_others(resp, rename_resp_msg(s2, t));
+        }
+    }
+
+    pub proof fn lemma_store_handle_decide_preserves_agreement(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        commit: bool,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+            self.stores.contains_key(s),
+        ensures
+            self.store_handle_decide(s, txn_id, commit, key_a, key_aprime).agreement_inv(key_a, key_aprime),
+    {
+        let post = self.store_handle_decide(s, txn_id, commit, key_a, key_aprime);
+        let req = decide_msg(s, commit, txn_id);
+        let resp = unlock_resp_msg(s, txn_id);
+        let st0 = self.store(s);
+        // A Decide never renames: unlocking and the txn-id update never touch data
+        st0.lemma_update_txn_id_preserves_state(txn_id);
+        st0.update_txn_id(txn_id).lemma_unlock_preserves_data(key_a);
+        st0.update_txn_id(txn_id).unlock(key_a).lemma_unlock_preserves_data(key_aprime);
+        assert forall|s2: StoreId| #[trigger] post.stores.contains_key(s2) implies
+            post.stores[s2].data == self.stores[s2].data by {}
+        let net1 = self.net.lose(req);
+        assert forall|s2: StoreId, t: TxnId| #[trigger] post.net.has_rename_req(s2, t)
+            implies self.net.has_rename_req(s2, t) by {
+            self.net.lemma_lose_preserves_others(req, rename_req_msg(s2, t));
+            net1.lemma_send_preserves_others(resp, rename_req_msg(s2, t));
+        }
+        assert forall|s2: StoreId, t: TxnId| #[trigger] post.net.has_rename_resp(s2, t)
+            implies self.net.has_rename_resp(s2, t) by {
+            self.net.lemma_lose_preserves_others(req, rename_resp_msg(s2, t));
+            net1.lemma_send_preserves_others(resp, rename_resp_msg(s2, t));
+        }
+    }
+
+    // ----- Preservation: environment actions -----
+
+    pub proof fn lemma_net_actions_preserve_agreement(
+        self,
+        msg: Message,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.agreement_inv(key_a, key_aprime),
+        ensures
+            self.net_lose(msg).agreement_inv(key_a, key_aprime),
+            self.net.contains(msg) ==> self.net_duplicate(msg).agreement_inv(key_a, key_aprime),
+    {
+        // Loss only removes copies; duplication only adds a copy of something
+        // already in flight
+        assert forall|s2: StoreId, t: TxnId| #[trigger] self.net_lose(msg).net.has_rename_req(s2, t)
+            implies self.net.has_rename_req(s2, t) by {}
+        assert forall|s2: StoreId, t: TxnId| #[trigger] self.net_lose(msg).net.has_rename_resp(s2, t)
+            implies self.net.has_rename_resp(s2, t) by {}
+        if self.net.contains(msg) {
+            assert forall|s2: StoreId, t: TxnId| #[trigger] self.net_duplicate(msg).net.has_rename_req(s2, t)
+                implies self.net.has_rename_req(s2, t) by {}
+            assert forall|s2: StoreId, t: TxnId| #[trigger] self.net_duplicate(msg).net.has_rename_resp(s2, t)
+                implies self.net.has_rename_resp(s2, t) by {}
+        }
+    }
+
+    // ============================================================
+    // Reachability
+    // ============================================================
+
+    /// Initial states: coordinator at init, nothing in flight, and every store
+    /// holding `key_a` only, without having seen a newer txn than the first
+    pub open spec fn is_init(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        &&& self.coord == CoordinatorSpec::init()
+        &&& self.net == NetworkSpec::empty()
+        &&& self.all_stores == self.stores.dom()
+        &&& forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==> {
+                &&& self.stores[s].contains_key(key_a)
+                &&& !self.stores[s].contains_key(key_aprime)
+                &&& !self.stores[s].is_stale_txn_id(1)
+            }
+    }
+
+    /// `self` is reachable from an initial state in exactly `n` `next` steps
+    pub open spec fn reachable_in(self, key_a: Seq<char>, key_aprime: Seq<char>, n: nat) -> bool
+        decreases n
+    {
+        if n == 0 {
+            self.is_init(key_a, key_aprime)
+        } else {
+            exists|prev: SystemSpec<V>| #[trigger] prev.reachable_in(key_a, key_aprime, (n - 1) as nat)
+                && prev.next(self, key_a, key_aprime)
+        }
+    }
+
+    /// Reflexive-transitive closure of `next` from the initial states
+    pub open spec fn reachable(self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        exists|n: nat| self.reachable_in(key_a, key_aprime, n)
+    }
+
+    /// Inductive strengthening of `system_invariant`: the keys differ. (No store
+    /// being ahead of the coordinator, which keeps a re-sent `LockResp` from
+    /// the future, is part of `system_invariant` as `store_txns_bounded`.)
+    pub open spec fn safety_inv(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        &&& self.system_invariant(key_a, key_aprime)
+        &&& key_a != key_aprime
+    }
+
+    /// Lemma: initial states satisfy `safety_inv`
+    pub proof fn lemma_init_safety_inv(self, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.is_init(key_a, key_aprime),
+            key_a != key_aprime,
+        ensures
+            self.safety_inv(key_a, key_aprime),
+    {
+        assert forall|m: Message| !#[trigger] self.net.contains(m) by {
+            NetworkSpec::lemma_empty_contains_nothing(m);
+        }
+        NetworkSpec::lemma_empty_causally_consistent();
+    }
+
+    /// Lemma: a store handling a well-formed request preserves `safety_inv`.
+    /// Locking, unlocking and the txn-id update never touch data, the rename
+    /// only runs from a state with `key_a` and without `key_aprime`, and the
+    /// store only adopts the request's txn id, which is no newer than the
+    /// coordinator's.
+    pub proof fn lemma_store_handlers_preserve_safety_inv(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.safety_inv(key_a, key_aprime),
+            self.stores.contains_key(s),
+        ensures
+            self.net.contains(lock_req_msg(s, txn_id)) ==>
+                self.store_handle_lock_req(s, txn_id, key_a, key_aprime).safety_inv(key_a, key_aprime),
+            self.net.contains(rename_req_msg(s, txn_id)) ==>
+                self.store_handle_rename_req(s, txn_id, key_a, key_aprime).safety_inv(key_a, key_aprime),
+            self.net.contains(unlock_req_msg(s, txn_id)) ==>
+                self.store_handle_unlock_req(s, txn_id, key_a, key_aprime).safety_inv(key_a, key_aprime),
+    {
+        let st0 = self.store(s);
+        let st1 = st0.update_txn_id(txn_id);
+        st0.lemma_update_txn_id_preserves_state(txn_id);
+        self.lemma_store_handlers_preserve_shape(s, txn_id, key_a, key_aprime);
+        self.lemma_store_handles_preserve_well_formed_network(s, txn_id, key_a, key_aprime);
+        self.lemma_store_txn_bounded_by_coord(s, txn_id, key_a, key_aprime);
+        // Only store s changes; every other store is as before
+        assert forall|st: KvStoreSpec<V>, s2: StoreId| s2 != s implies
+            #[trigger] self.stores.insert(s, st)[s2] == self.stores[s2] by {}
+
+        if self.net.contains(lock_req_msg(s, txn_id)) {
+            // Well-formedness bounds the request's txn id by the coordinator's
+            assert(lock_req_msg(s, txn_id).get_txn_id() <= self.coord.current_txn_id);
+            st1.lemma_lock_preserves_data(key_a);
+            st1.lock(key_a).lemma_lock_preserves_data(key_aprime);
+            let post = self.store_handle_lock_req(s, txn_id, key_a, key_aprime);
+            assert forall|s2: StoreId| #[trigger] post.stores.contains_key(s2) implies
+                post.stores[s2].data == self.stores[s2].data
+                && post.stores[s2].last_seen_txn_id <= post.coord.current_txn_id by {}
+        }
+        if self.net.contains(rename_req_msg(s, txn_id)) {
+            assert(rename_req_msg(s, txn_id).get_txn_id() <= self.coord.current_txn_id);
+            let post = self.store_handle_rename_req(s, txn_id, key_a, key_aprime);
+            if !st0.is_stale_txn_id(txn_id) && !st1.contains_key(key_aprime)
+                && st1.is_locked(key_a) && st1.is_locked(key_aprime) && st1.contains_key(key_a) {
+                // The rename moves the value from key_a to key_aprime
+                lemma_data_accessible_preserved(st1, key_a, key_aprime);
+            }
+            assert forall|s2: StoreId| #[trigger] post.stores.contains_key(s2) implies
+                data_accessible(post.stores[s2], key_a, key_aprime)
+                && post.stores[s2].last_seen_txn_id <= post.coord.current_txn_id by {
+                if s2 == s {
+                    assert(data_accessible(st0, key_a, key_aprime));
+                }
+            }
+        }
+        if self.net.contains(unlock_req_msg(s, txn_id)) {
+            assert(unlock_req_msg(s, txn_id).get_txn_id() <= self.coord.current_txn_id);
+            st1.lemma_unlock_preserves_data(key_a);
+            st1.unlock(key_a).lemma_unlock_preserves_data(key_aprime);
+            let post = self.store_handle_unlock_req(s, txn_id, key_a, key_aprime);
+            assert forall|s2: StoreId| #[trigger] post.stores.contains_key(s2) implies
+                post.stores[s2].data == self.stores[s2].data
+                && post.stores[s2].last_seen_txn_id <= post.coord.current_txn_id by {}
+        }
+    }
+
+    /// Lemma: a store handling a well-formed `Decide` preserves `safety_inv`.
+    /// The handler answers with the message's own store and txn id, adopts
+    /// only that txn id, and never touches data: it unlocks at most, never
+    /// renames
+    pub proof fn lemma_store_handle_decide_preserves_safety_inv(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        commit: bool,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.safety_inv(key_a, key_aprime),
+            self.stores.contains_key(s),
+            self.net.contains(decide_msg(s, commit, txn_id)),
+        ensures
+            self.store_handle_decide(s, txn_id, commit, key_a, key_aprime).safety_inv(key_a, key_aprime),
+    {
+        let req = decide_msg(s, commit, txn_id);
+        let st0 = self.store(s);
+        let st1 = st0.update_txn_id(txn_id);
+        st0.lemma_update_txn_id_preserves_state(txn_id);
+        st1.lemma_unlock_preserves_data(key_a);
+        st1.unlock(key_a).lemma_unlock_preserves_data(key_aprime);
+        self.lemma_store_handlers_preserve_shape(s, txn_id, key_a, key_aprime);
+        // Well-formedness bounds the message's txn id by the coordinator's
+        assert(req.get_txn_id() <= self.coord.current_txn_id);
+        self.lemma_respond_preserves_well_formed_network(req, unlock_resp_msg(s, txn_id));
+        let post = self.store_handle_decide(s, txn_id, commit, key_a, key_aprime);
+        assert forall|s2: StoreId| #[trigger] post.stores.contains_key(s2) implies
+            post.stores[s2].data == self.stores[s2].data
+            && post.stores[s2].last_seen_txn_id <= post.coord.current_txn_id by {
+            if s2 != s {
+                assert(post.stores[s2] == self.stores[s2]);
+            }
+        }
+    }
+
+    /// Lemma: every `next` step preserves `safety_inv`
+    pub proof fn lemma_next_preserves_safety_inv(self, other: SystemSpec<V>, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.safety_inv(key_a, key_aprime),
+            self.next(other, key_a, key_aprime),
+        ensures
+            other.safety_inv(key_a, key_aprime),
+    {
+        self.lemma_next_preserves_type_ok(other, key_a, key_aprime);
+        if exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+            && self.net.contains(lock_req_msg(s, t))
+            && other == self.store_handle_lock_req(s, t, key_a, key_aprime) {
+            let (s, t) = choose|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && self.net.contains(lock_req_msg(s, t))
+                && other == self.store_handle_lock_req(s, t, key_a, key_aprime);
+            self.lemma_store_handlers_preserve_safety_inv(s, t, key_a, key_aprime);
+        } else if exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+            && self.net.contains(rename_req_msg(s, t))
+            && other == self.store_handle_rename_req(s, t, key_a, key_aprime) {
+            let (s, t) = choose|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && self.net.contains(rename_req_msg(s, t))
+                && other == self.store_handle_rename_req(s, t, key_a, key_aprime);
+            self.lemma_store_handlers_preserve_safety_inv(s, t, key_a, key_aprime);
+        } else if exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+            && self.net.contains(unlock_req_msg(s, t))
+            && other == self.store_handle_unlock_req(s, t, key_a, key_aprime) {
+            let (s, t) = choose|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && self.net.contains(unlock_req_msg(s, t))
+                && other == self.store_handle_unlock_req(s, t, key_a, key_aprime);
+            self.lemma_store_handlers_preserve_safety_inv(s, t, key_a, key_aprime);
+        } else if exists|s: StoreId, t: TxnId, commit: bool| #![auto] self.stores.contains_key(s)
+            && self.net.contains(decide_msg(s, commit, t))
+            && other == self.store_handle_decide(s, t, commit, key_a, key_aprime) {
+            let (s, t, commit) = choose|s: StoreId, t: TxnId, commit: bool| #![auto] self.stores.contains_key(s)
+                && self.net.contains(decide_msg(s, commit, t))
+                && other == self.store_handle_decide(s, t, commit, key_a, key_aprime);
+            self.lemma_store_handle_decide_preserves_safety_inv(s, t, commit, key_a, key_aprime);
+        } else if exists|s: StoreId, t: TxnId, success: bool| #![auto]
+            self.store_responded_lock(s, t, success, key_a, key_aprime)
+            && other == self.store_resend_lock_resp(s, t, success) {
+            let (s, t, success) = choose|s: StoreId, t: TxnId, success: bool| #![auto]
+                self.store_responded_lock(s, t, success, key_a, key_aprime)
+                && other == self.store_resend_lock_resp(s, t, success);
+            // The store has seen t, and no store is ahead of the coordinator
+            assert(self.stores[s].last_seen_txn_id <= self.coord.current_txn_id);
+            self.lemma_store_resend_lock_resp_safe(s, t, success, key_a, key_aprime);
+        } else if exists|m: Message| #![auto] self.net.contains(m) && other == self.net_lose(m) {
+            let m = choose|m: Message| #![auto] self.net.contains(m) && other == self.net_lose(m);
+            self.lemma_net_lose_preserves_well_formed_network(m);
+        } else if exists|m: Message| #![auto] self.net.contains(m) && other == self.net_duplicate(m) {
+            let m = choose|m: Message| #![auto] self.net.contains(m) && other == self.net_duplicate(m);
+            self.lemma_net_lose_preserves_well_formed_network(m);
+        } else if exists|s: StoreId| #![auto] self.all_stores.contains(s)
+            && (other == self.coord_send_lock_req(s) || other == self.coord_send_rename_req(s)
+                || other == self.coord_send_unlock_req(s) || other == self.coord_send_decide(s)) {
+            let s = choose|s: StoreId| #![auto] self.all_stores.contains(s)
+                && (other == self.coord_send_lock_req(s) || other == self.coord_send_rename_req(s)
+                    || other == self.coord_send_unlock_req(s) || other == self.coord_send_decide(s));
+            self.lemma_coord_sends_preserve_well_formed_network(s);
+        } else if exists|s: StoreId| #![auto]
+            other == self.coord_recv_lock_resp_success(s) || other == self.coord_recv_lock_resp_failure(s)
+            || other == self.coord_recv_rename_resp(s) || other == self.coord_recv_unlock_resp(s) {
+            let s = choose|s: StoreId| #![auto]
+                other == self.coord_recv_lock_resp_success(s) || other == self.coord_recv_lock_resp_failure(s)
+                || other == self.coord_recv_rename_resp(s) || other == self.coord_recv_unlock_resp(s);
+            self.lemma_coord_recvs_preserve_well_formed_network(s);
+        } else {
+            // Commit, crash and recovery leave stores and network alone and never
+            // move the txn id backwards
+            self.lemma_coord_recvs_preserve_well_formed_network(0);
+        }
+    }
+
+    /// Lemma: every state reachable in `n` steps satisfies `safety_inv`
+    pub proof fn lemma_reachable_in_safety_inv(self, key_a: Seq<char>, key_aprime: Seq<char>, n: nat)
+        requires
+            self.reachable_in(key_a, key_aprime, n),
+            key_a != key_aprime,
+        ensures
+            self.safety_inv(key_a, key_aprime),
+        decreases n
+    {
+        if n == 0 {
+            self.lemma_init_safety_inv(key_a, key_aprime);
+        } else {
+            let prev = choose|prev: SystemSpec<V>| #[trigger] prev.reachable_in(key_a, key_aprime, (n - 1) as nat)
+                && prev.next(self, key_a, key_aprime);
+            prev.lemma_reachable_in_safety_inv(key_a, key_aprime, (n - 1) as nat);
+            prev.lemma_next_preserves_safety_inv(self, key_a, key_aprime);
+        }
+    }
+
+    /// Theorem (safety): every reachable state satisfies `system_invariant`, so
+    /// under any interleaving of protocol steps, coordinator crashes, and message
+    /// loss or duplication, each store keeps its value at exactly one of {A, A'}.
+    /// Holds for any number of stores; see `lemma_one_store_init_is_init` for
+    /// the one-store instance.
+    pub proof fn lemma_reachable_system_invariant(self, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.reachable(key_a, key_aprime),
+            key_a != key_aprime,
+        ensures
+            self.system_invariant(key_a, key_aprime),
+            self.value_always_available(key_a, key_aprime),
+            self.votes_recorded(),
+            self.commit_backed_by_votes(),
+    {
+        let n = choose|n: nat| self.reachable_in(key_a, key_aprime, n);
+        self.lemma_reachable_in_protocol_inv(key_a, key_aprime, n);
+        self.lemma_system_invariant_value_available(key_a, key_aprime);
+    }
+
+    // ============================================================
+    // Availability: no store loses both keys
+    // ============================================================
+
+    /// Every store holds the value under at least one of {A, A'}, so a reader
+    /// can always retrieve it from any participant. Weaker than the per-store
+    /// `data_accessible` (exactly one), and stated system-wide
+    pub open spec fn value_always_available(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        forall|s: StoreId| #[trigger] self.stores.contains_key(s)
+            ==> self.stores[s].contains_key(key_a) || self.stores[s].contains_key(key_aprime)
+    }
+
+    /// Lemma: `system_invariant` implies `value_always_available`
+    pub proof fn lemma_system_invariant_value_available(self, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.system_invariant(key_a, key_aprime),
+        ensures
+            self.value_always_available(key_a, key_aprime),
+    {
+        assert forall|s: StoreId| #[trigger] self.stores.contains_key(s) implies
+            self.stores[s].contains_key(key_a) || self.stores[s].contains_key(key_aprime) by {
+            assert(data_accessible(self.stores[s], key_a, key_aprime));
+        }
+    }
+
+    /// Lemma: every store handler keeps the value available, including the
+    /// rename itself, which removes A only while adding A' in the same step
+    pub proof fn lemma_store_handlers_preserve_value_available(
+        self,
+        s: StoreId,
+        txn_id: TxnId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.safety_inv(key_a, key_aprime),
+            self.stores.contains_key(s),
+        ensures
+            self.value_always_available(key_a, key_aprime),
+            self.net.contains(lock_req_msg(s, txn_id)) ==>
+                self.store_handle_lock_req(s, txn_id, key_a, key_aprime).value_always_available(key_a, key_aprime),
+            self.net.contains(rename_req_msg(s, txn_id)) ==>
+                self.store_handle_rename_req(s, txn_id, key_a, key_aprime).value_always_available(key_a, key_aprime),
+            self.net.contains(unlock_req_msg(s, txn_id)) ==>
+                self.store_handle_unlock_req(s, txn_id, key_a, key_aprime).value_always_available(key_a, key_aprime),
+    {
+        self.lemma_system_invariant_value_available(key_a, key_aprime);
+        self.lemma_store_handlers_preserve_safety_inv(s, txn_id, key_a, key_aprime);
+        if self.net.contains(lock_req_msg(s, txn_id)) {
+            self.store_handle_lock_req(s, txn_id, key_a, key_aprime)
+                .lemma_system_invariant_value_available(key_a, key_aprime);
+        }
+        if self.net.contains(rename_req_msg(s, txn_id)) {
+            self.store_handle_rename_req(s, txn_id, key_a, key_aprime)
+                .lemma_system_invariant_value_available(key_a, key_aprime);
+        }
+        if self.net.contains(unlock_req_msg(s, txn_id)) {
+            self.store_handle_unlock_req(s, txn_id, key_a, key_aprime)
+                .lemma_system_invariant_value_available(key_a, key_aprime);
+        }
+    }
+
+    // ============================================================
+    // Unanimity: commit only on a unanimous yes
+    // ============================================================
+
+    /// Every store the coordinator credits with a lock voted yes in the current
+    /// txn: a successful `LockResp` for it was produced at some point. Only the
+    /// store's lock handler (or its re-answer of the same vote) sends one
+    pub open spec fn votes_recorded(&self) -> bool {
+        forall|s: StoreId| #[trigger] self.coord.locks_acquired.contains(s)
+            ==> self.net.sent.contains(lock_resp_msg(s, true, self.coord.current_txn_id))
+    }
+
+    /// Lemma: every `next` step preserves `votes_recorded`.
+    /// The send history only grows; a credit is only added for a response that
+    /// is in flight (hence sent); crash and recovery drop every credit
+    pub proof fn lemma_next_preserves_votes_recorded(
+        self,
+        other: SystemSpec<V>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.safety_inv(key_a, key_aprime),
+            self.votes_recorded(),
+            self.next(other, key_a, key_aprime),
+        ensures
+            other.votes_recorded(),
+    {
+        let txn = self.coord.current_txn_id;
+        if exists|s: StoreId| #![auto] self.coord.phase == CoordPhase::Preparing
+            && self.net.contains(lock_resp_msg(s, true, txn))
+            && other == self.coord_recv_lock_resp_success(s) {
+            let s = choose|s: StoreId| #![auto] self.coord.phase == CoordPhase::Preparing
+                && self.net.contains(lock_resp_msg(s, true, txn))
+                && other == self.coord_recv_lock_resp_success(s);
+            // The new credit's response is in flight, so it was sent
+            assert(self.net.sent.contains(lock_resp_msg(s, true, txn)));
+            assert forall|s2: StoreId| #[trigger] other.coord.locks_acquired.contains(s2) implies
+                other.net.sent.contains(lock_resp_msg(s2, true, other.coord.current_txn_id)) by {
+                if s2 != s {
+                    assert(self.coord.locks_acquired.contains(s2));
+                }
+            }
+        } else if (self.coord.phase.spec_can_crash() && other == self.coord_crash())
+            || (self.coord.phase == CoordPhase::Crashed && other == self.coord_recover()) {
+            // Crash and recovery lose every credit
+            assert(other.coord.locks_acquired =~= Set::<StoreId>::empty());
+        } else {
+            if exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && (other == self.store_handle_lock_req(s, t, key_a, key_aprime)
+                    || other == self.store_handle_rename_req(s, t, key_a, key_aprime)
+                    || other == self.store_handle_unlock_req(s, t, key_a, key_aprime)) {
+                let (s, t) = choose|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                    && (other == self.store_handle_lock_req(s, t, key_a, key_aprime)
+                        || other == self.store_handle_rename_req(s, t, key_a, key_aprime)
+                        || other == self.store_handle_unlock_req(s, t, key_a, key_aprime));
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+            }
+            if exists|s: StoreId, t: TxnId, commit: bool| #![auto] self.stores.contains_key(s)
+                && other == self.store_handle_decide(s, t, commit, key_a, key_aprime) {
+                let (s, t, commit) = choose|s: StoreId, t: TxnId, commit: bool| #![auto]
+                    self.stores.contains_key(s) && other == self.store_handle_decide(s, t, commit, key_a, key_aprime);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+            }
+            // Every remaining action keeps the credits and the txn id, and
+            // sends, re-sends or drops messages without shrinking the history
+            assert(other.coord.locks_acquired == self.coord.locks_acquired);
+            assert(other.coord.current_txn_id == txn);
+            assert forall|m: Message| #[trigger] self.net.sent.contains(m) implies
+                other.net.sent.contains(m) by {}
+        }
+    }
+
+    /// Lemma: every state reachable in `n` steps satisfies `votes_recorded`
+    pub proof fn lemma_reachable_in_votes_recorded(self, key_a: Seq<char>, key_aprime: Seq<char>, n: nat)
+        requires
+            self.reachable_in(key_a, key_aprime, n),
+            key_a != key_aprime,
+        ensures
+            self.votes_recorded(),
+        decreases n
+    {
+        if n > 0 {
+            let prev = choose|prev: SystemSpec<V>| #[trigger] prev.reachable_in(key_a, key_aprime, (n - 1) as nat)
+                && prev.next(self, key_a, key_aprime);
+            prev.lemma_reachable_in_votes_recorded(key_a, key_aprime, (n - 1) as nat);
+            prev.lemma_reachable_in_safety_inv(key_a, key_aprime, (n - 1) as nat);
+            prev.lemma_next_preserves_votes_recorded(self, key_a, key_aprime);
+        }
+    }
+
+    /// Every store voted yes in one common txn: a successful `LockResp` for
+    /// `txn` was produced by each of them
+    pub open spec fn unanimous_yes(&self, txn: TxnId) -> bool {
+        forall|s: StoreId| #[trigger] self.all_stores.contains(s)
+            ==> self.net.sent.contains(lock_resp_msg(s, true, txn))
+    }
+
+    /// A commit is backed by a unanimous yes: the coordinator is only in
+    /// Committed with the WAL committed, and a committed WAL was written after
+    /// every store voted yes in some txn. Recovery moves the txn id on, so the
+    /// vote's txn is existential rather than the current one
+    pub open spec fn commit_backed_by_votes(&self) -> bool {
+        &&& self.coord.phase == CoordPhase::Committed ==> self.coord.wal_committed
+        &&& self.coord.wal_committed ==> exists|txn: TxnId| #[trigger] self.unanimous_yes(txn)
+    }
+
+    /// Lemma: every `next` step preserves `commit_backed_by_votes`.
+    /// The commit step is guarded by a lock from every store, and each credited
+    /// lock has its yes vote in the history (`votes_recorded`); every other step
+    /// keeps the WAL flag and the store set and only grows the history
+    pub proof fn lemma_next_preserves_commit_backed_by_votes(
+        self,
+        other: SystemSpec<V>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.safety_inv(key_a, key_aprime),
+            self.votes_recorded(),
+            self.commit_backed_by_votes(),
+            self.next(other, key_a, key_aprime),
+        ensures
+            other.commit_backed_by_votes(),
+    {
+        let txn = self.coord.current_txn_id;
+        if self.coord.phase == CoordPhase::Preparing
+            && self.coord.all_locks_acquired(self.all_stores)
+            && other == self.coord_decide_commit() {
+            // Every store is credited, so every store's yes vote was sent
+            assert forall|s: StoreId| #[trigger] other.all_stores.contains(s) implies
+                other.net.sent.contains(lock_resp_msg(s, true, txn)) by {
+                assert(self.coord.locks_acquired.contains(s));
+            }
+            assert(other.unanimous_yes(txn));
+        } else {
+            if exists|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                && (other == self.store_handle_lock_req(s, t, key_a, key_aprime)
+                    || other == self.store_handle_rename_req(s, t, key_a, key_aprime)
+                    || other == self.store_handle_unlock_req(s, t, key_a, key_aprime)) {
+                let (s, t) = choose|s: StoreId, t: TxnId| #![auto] self.stores.contains_key(s)
+                    && (other == self.store_handle_lock_req(s, t, key_a, key_aprime)
+                        || other == self.store_handle_rename_req(s, t, key_a, key_aprime)
+                        || other == self.store_handle_unlock_req(s, t, key_a, key_aprime));
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+            }
+            if exists|s: StoreId, t: TxnId, commit: bool| #![auto] self.stores.contains_key(s)
+                && other == self.store_handle_decide(s, t, commit, key_a, key_aprime) {
+                let (s, t, commit) = choose|s: StoreId, t: TxnId, commit: bool| #![auto]
+                    self.stores.contains_key(s) && other == self.store_handle_decide(s, t, commit, key_a, key_aprime);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+            }
+            // No other step sets the WAL flag or enters Committed without it
+            // (recovery resumes into Committed only from a committed WAL)
+            assert(other.coord.wal_committed == self.coord.wal_committed);
+            assert(other.all_stores == self.all_stores);
+            assert forall|m: Message| #[trigger] self.net.sent.contains(m) implies
+                other.net.sent.contains(m) by {}
+            if other.coord.wal_committed {
+                let t = choose|t: TxnId| #[trigger] self.unanimous_yes(t);
+                assert(other.unanimous_yes(t));
+            }
+        }
+    }
+
+    /// The protocol invariant suite: safety, every lock credit backed by a yes
+    /// vote, and every commit backed by a unanimous yes
+    pub open spec fn protocol_inv(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        &&& self.safety_inv(key_a, key_aprime)
+        &&& self.votes_recorded()
+        &&& self.commit_backed_by_votes()
+    }
+
+    /// Lemma: every state reachable in `n` steps satisfies `protocol_inv`
+    pub proof fn lemma_reachable_in_protocol_inv(self, key_a: Seq<char>, key_aprime: Seq<char>, n: nat)
+        requires
+            self.reachable_in(key_a, key_aprime, n),
+            key_a != key_aprime,
+        ensures
+            self.protocol_inv(key_a, key_aprime),
+        decreases n
+    {
+        self.lemma_reachable_in_safety_inv(key_a, key_aprime, n);
+        self.lemma_reachable_in_votes_recorded(key_a, key_aprime, n);
+        if n > 0 {
+            let prev = choose|prev: SystemSpec<V>| #[trigger] prev.reachable_in(key_a, key_aprime, (n - 1) as nat)
+                && prev.next(self, key_a, key_aprime);
+            prev.lemma_reachable_in_protocol_inv(key_a, key_aprime, (n - 1) as nat);
+            prev.lemma_next_preserves_commit_backed_by_votes(self, key_a, key_aprime);
+        }
+    }
+
+    /// Theorem (2PC unanimity): every reachable Committed state is backed by a
+    /// yes vote from every participant in one txn. `next` only commits once
+    /// every store is credited with a lock, so no driver discipline is assumed
+    pub proof fn lemma_commit_requires_unanimity(self, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.reachable(key_a, key_aprime),
+            key_a != key_aprime,
+            self.coord.phase == CoordPhase::Committed,
+        ensures
+            self.coord.wal_committed,
+            exists|txn: TxnId| #[trigger] self.unanimous_yes(txn),
+    {
+        let n = choose|n: nat| self.reachable_in(key_a, key_aprime, n);
+        self.lemma_reachable_in_protocol_inv(key_a, key_aprime, n);
+    }
+
+    /// Lemma: the single-store starting state is initial, so everything reachable
+    /// from it satisfies `system_invariant`
+    pub proof fn lemma_one_store_init_is_init(s: StoreId, st: KvStoreSpec<V>, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            st.contains_key(key_a),
+            !st.contains_key(key_aprime),
+            !st.is_stale_txn_id(1),
+        ensures
+            Self::one_store_init(s, st).is_init(key_a, key_aprime),
+            Self::one_store_init(s, st).reachable(key_a, key_aprime),
+    {
+        let init = Self::one_store_init(s, st);
+        assert(init.all_stores =~= init.stores.dom());
+        assert(init.reachable_in(key_a, key_aprime, 0));
+    }
+
+    // ============================================================
+    // Fault-free single-store schedule (for liveness)
+    // ============================================================
+
+    /// Single-store starting state: coordinator at init, empty network,
+    /// and store `s` in state `st`
+    pub open spec fn one_store_init(s: StoreId, st: KvStoreSpec<V>) -> Self {
+        SystemSpec {
+            coord: CoordinatorSpec::init(),
+            net: NetworkSpec::empty(),
+            stores: Map::empty().insert(s, st),
+            all_stores: Set::empty().insert(s),
+        }
+    }
+
+    /// The action a fault-free run with the single store `s` takes next:
+    /// deliver the pending request or response of the current phase if there is
+    /// one, otherwise issue the phase's next request (or commit once the lock is
+    /// granted). A state with nothing left to do maps to itself.
+    pub open spec fn protocol_step(self, s: StoreId, key_a: Seq<char>, key_aprime: Seq<char>) -> Self {
+        let txn = self.coord.current_txn_id;
+        match self.coord.phase {
+            CoordPhase::Idle => self.coord_send_lock_req(s),
+            CoordPhase::Preparing => {
+                if self.net.contains(lock_req_msg(s, txn)) {
+                    self.store_handle_lock_req(s, txn, key_a, key_aprime)
+                } else if self.net.contains(lock_resp_msg(s, true, txn)) {
+                    self.coord_recv_lock_resp_success(s)
+                } else if self.net.contains(lock_resp_msg(s, false, txn)) {
+                    self.coord_recv_lock_resp_failure(s)
+                } else if self.coord.locks_acquired.contains(s) {
+                    self.coord_decide_commit()
+                } else {
+                    self
+                }
+            },
+            CoordPhase::Committed => {
+                if self.net.contains(rename_req_msg(s, txn)) {
+                    self.store_handle_rename_req(s, txn, key_a, key_aprime)
+                } else if self.net.contains(rename_resp_msg(s, txn)) {
+                    self.coord_recv_rename_resp(s)
+                } else {
+                    self.coord_send_rename_req(s)
+                }
+            },
+            CoordPhase::Cleanup => {
+                if self.net.contains(unlock_req_msg(s, txn)) {
+                    self.store_handle_unlock_req(s, txn, key_a, key_aprime)
+                } else if self.net.contains(unlock_resp_msg(s, txn)) {
+                    self.coord_recv_unlock_resp(s)
+                } else {
+                    self.coord_send_unlock_req(s)
+                }
+            },
+            _ => self,
+        }
+    }
+
+    /// `n` consecutive protocol steps
+    pub open spec fn protocol_run(self, s: StoreId, key_a: Seq<char>, key_aprime: Seq<char>, n: nat) -> Self
+        decreases n
+    {
+        if n == 0 {
+            self
+        } else {
+            self.protocol_run(s, key_a, key_aprime, (n - 1) as nat).protocol_step(s, key_a, key_aprime)
+        }
+    }
+
+    /// No orphaned locks: once the coordinator is Done, no store holds a lock on
+    /// either key. A LockReq overtaken by an abort's UnlockReq, or a duplicate
+    /// of one, is refused by a store that already released the txn (see
+    /// `test_delayed_lock_req_refused_after_release`), so it cannot re-lock a
+    /// store after Done. It holds at the end of a fault-free run
+    /// (`lemma_one_store_run_reaches_done`).
+    pub open spec fn no_orphaned_locks(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        self.coord.phase == CoordPhase::Done ==>
+            forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==>
+                !self.stores[s].is_locked(key_a) && !self.stores[s].is_locked(key_aprime)
+    }
+
+    /// Lemma: from the single-store start, ten protocol steps commit the rename
+    /// and reach Done (lock, grant, commit, rename, unlock, each with its
+    /// response) with no lock left behind, and every earlier state has work
+    /// left to do.
+    pub proof fn lemma_one_store_run_reaches_done(s: StoreId, st: KvStoreSpec<V>, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            key_a != key_aprime,
+            st.contains_key(key_a),
+            !st.contains_key(key_aprime),
+            !st.is_stale_txn_id(1),
+            st.released_txn is None,
+            !st.is_locked(key_a) && !st.is_locked(key_aprime),
+            !st.is_frozen(),
+        ensures
+            Self::one_store_init(s, st).protocol_run(s, key_a, key_aprime, 10).coord.phase == CoordPhase::Done,
+            Self::one_store_init(s, st).protocol_run(s, key_a, key_aprime, 10).no_orphaned_locks(key_a, key_aprime),
+            forall|n: nat| n < 10 ==> {
+                let cur = #[trigger] Self::one_store_init(s, st).protocol_run(s, key_a, key_aprime, n);
+                &&& cur.coord.phase != CoordPhase::Done
+                &&& cur.protocol_step(s, key_a, key_aprime) != cur
+            },
+    {
+        let txn: TxnId = 1;
+        let p0 = Self::one_store_init(s, st);
+        let run = |n: nat| p0.protocol_run(s, key_a, key_aprime, n);
+        assert(run(0) == p0);
+
+        // Lock round trip
+        let p1 = p0.coord_send_lock_req(s);
+        assert(run(1) == p1);
+        assert(p1.net.contains(lock_req_msg(s, txn)));
+        let p2 = p1.store_handle_lock_req(s, txn, key_a, key_aprime);
+        assert(run(2) == p2);
+        assert(p2.net.contains(lock_resp_msg(s, true, txn)));
+        assert(p2.store(s).is_locked(key_a));
+        assert(p2.store(s).is_locked(key_aprime));
+        NetworkSpec::lemma_message_types_distinct(s, txn);
+        assert(!p2.net.contains(lock_req_msg(s, txn)));
+        let p3 = p2.coord_recv_lock_resp_success(s);
+        assert(run(3) == p3);
+        assert(p3.net.is_empty());
+        assert(p3.coord.locks_acquired.contains(s));
+
+        // Commit and rename round trip
+        let p4 = p3.coord_decide_commit();
+        assert(run(4) == p4);
+        let p5 = p4.coord_send_rename_req(s);
+        assert(run(5) == p5);
+        assert(p5.net.contains(rename_req_msg(s, txn)));
+        let p6 = p5.store_handle_rename_req(s, txn, key_a, key_aprime);
+        assert(run(6) == p6);
+        assert(p6.net.contains(rename_resp_msg(s, txn)));
+        assert(!p6.net.contains(rename_req_msg(s, txn)));
+        let p7 = p6.coord_recv_rename_resp(s);
+        assert(p7.coord.renames_done =~= p7.all_stores);
+        assert(run(7) == p7);
+        assert(p7.coord.phase == CoordPhase::Cleanup);
+        assert(p7.net.is_empty());
+
+        // Unlock round trip
+        let p8 = p7.coord_send_unlock_req(s);
+        assert(run(8) == p8);
+        assert(p8.net.contains(unlock_req_msg(s, txn)));
+        let p9 = p8.store_handle_unlock_req(s, txn, key_a, key_aprime);
+        assert(run(9) == p9);
+        assert(p9.net.contains(unlock_resp_msg(s, txn)));
+        assert(!p9.net.contains(unlock_req_msg(s, txn)));
+        let p10 = p9.coord_recv_unlock_resp(s);
+        assert(p10.coord.unlocks_acked =~= p9.coord.unlock_targets());
+        assert(run(10) == p10);
+        assert(p10.coord.phase == CoordPhase::Done);
+        assert(p10.stores.dom() =~= Set::empty().insert(s));
+        assert(!p10.store(s).is_locked(key_a) && !p10.store(s).is_locked(key_aprime));
+
+        // Each step before Done changes the state (its phase or its network)
+        assert(p0 != p1 && p1 != p2 && p2 != p3 && p3 != p4 && p4 != p5);
+        assert(p5 != p6 && p6 != p7 && p7 != p8 && p8 != p9 && p9 != p10);
+        assert forall|n: nat| n < 10 implies {
+            let cur = #[trigger] p0.protocol_run(s, key_a, key_aprime, n);
+            &&& cur.coord.phase != CoordPhase::Done
+            &&& cur.protocol_step(s, key_a, key_aprime) != cur
+        } by {
+            assert(run(n + 1) == run(n).protocol_step(s, key_a, key_aprime));
+            if n == 0 {} else if n == 1 {} else if n == 2 {} else if n == 3 {} else if n == 4 {}
+            else if n == 5 {} else if n == 6 {} else if n == 7 {} else if n == 8 {} else {}
+        }
+    }
+}
+
+// ============================================================
+// CROSS-STORE MIGRATION
+// ============================================================
+//
+// Moving `key` from store `src` to store `dst` (ExecSystem::coord_start_migration).
+// Both vote yes only if src holds `key` and dst does not, and each locks `key`.
+// On commit dst writes the value src voted with (carried by MigrateVote, then
+// MigrateApply) and src deletes `key`; on abort neither changes its data.
+// Either way `key` ends up at exactly one of the two stores.
+
+/// `key` is held by exactly one of the two stores
+pub open spec fn held_by_exactly_one<V>(src: KvStoreSpec<V>, dst: KvStoreSpec<V>, key: Seq<char>) -> bool {
+    src.contains_key(key) != dst.contains_key(key)
+}
+
+/// Both stores voted yes on the migration of `key` and hold it locked
+pub open spec fn migration_prepared<V>(src: KvStoreSpec<V>, dst: KvStoreSpec<V>, key: Seq<char>) -> bool {
+    &&& src.contains_key(key)
+    &&& !dst.contains_key(key)
+    &&& src.is_locked(key)
+    &&& dst.is_locked(key)
+    &&& !src.is_frozen()
+}
+
+/// Committed migration: dst writes the value src voted with, src deletes `key`
+pub open spec fn migration_commit<V>(src: KvStoreSpec<V>, dst: KvStoreSpec<V>, key: Seq<char>)
+    -> (KvStoreSpec<V>, KvStoreSpec<V>)
+{
+    (src.delete_locked(key), dst.put_locked(key, src.get(key)))
+}
+
+/// Lemma: a prepared migration leaves `key` at exactly one store whether it
+/// aborts (data unchanged) or commits, and a commit moves the value intact.
+/// Releasing the locks afterwards keeps it so
+pub proof fn lemma_migration_exactly_one<V>(src: KvStoreSpec<V>, dst: KvStoreSpec<V>, key: Seq<char>)
+    requires
+        migration_prepared(src, dst, key),
+    ensures
+        // Abort
+        held_by_exactly_one(src, dst, key),
+        held_by_exactly_one(src.unlock(key), dst.unlock(key), key),
+        // Commit
+        held_by_exactly_one(migration_commit(src, dst, key).0, migration_commit(src, dst, key).1, key),
+        migration_commit(src, dst, key).1.get(key) == src.get(key),
+        held_by_exactly_one(
+            migration_commit(src, dst, key).0.unlock(key),
+            migration_commit(src, dst, key).1.unlock(key),
+            key),
+{
+    let (s2, d2) = migration_commit(src, dst, key);
+    assert(s2.data == src.data.remove(key));
+    assert(d2.data == dst.data.insert(key, src.get(key)));
+    assert(!s2.contains_key(key));
+    assert(d2.contains_key(key));
+}
+
+/// Lemma: re-delivering the commit writes changes no store's data again
+/// (ExecSystem::store_handle_migration_apply_req is idempotent)
+pub proof fn lemma_migration_commit_idempotent<V>(src: KvStoreSpec<V>, dst: KvStoreSpec<V>, key: Seq<char>)
+    requires
+        migration_prepared(src, dst, key),
+    ensures
+        migration_commit(src, dst, key).0.delete_locked(key).data == migration_commit(src, dst, key).0.data,
+        migration_commit(src, dst, key).1.put_locked(key, src.get(key)).data
+            == migration_commit(src, dst, key).1.data,
+{
+    let (s2, d2) = migration_commit(src, dst, key);
+    assert(s2.data.remove(key) =~= s2.data);
+    assert(d2.data.insert(key, src.get(key)) =~= d2.data);
+}
+
+// ============================================================
+// ACTION LOG AND REPLAY
+// ============================================================
+//
+// `SystemAction` names one disjunct of `SystemSpec::next`, so a run can be
+// written down as a sequence of actions. `RecordedSystem` carries such a log
+// next to the state it produced; the replay lemmas show the log alone
+// reproduces the state, i.e. the state is explained by exactly those actions.
+
+/// One system action, mirroring the disjuncts of `SystemSpec::next`
+pub ghost enum SystemAction {
+    SendLockReq(StoreId),
+    DecideCommit,
+    SendRenameReq(StoreId),
+    SendUnlockReq(StoreId),
+    SendDecide(StoreId),
+    RecvLockRespSuccess(StoreId),
+    RecvLockRespFailure(StoreId),
+    RecvRenameResp(StoreId),
+    RecvUnlockResp(StoreId),
+    Crash,
+    Recover,
+    HandleLockReq(StoreId, TxnId),
+    HandleRenameReq(StoreId, TxnId),
+    HandleUnlockReq(StoreId, TxnId),
+    HandleDecide(StoreId, TxnId, bool),
+    ResendLockResp(StoreId, TxnId, bool),
+    Lose(Message),
+    Duplicate(Message),
+}
+
+impl<V> SystemSpec<V> {
+    /// Take action `a` (enabling conditions are not checked here; see `next`)
+    pub open spec fn apply(self, a: SystemAction, key_a: Seq<char>, key_aprime: Seq<char>) -> Self {
+        match a {
+            SystemAction::SendLockReq(s) => self.coord_send_lock_req(s),
+            SystemAction::DecideCommit => self.coord_decide_commit(),
+            SystemAction::SendRenameReq(s) => self.coord_send_rename_req(s),
+            SystemAction::SendUnlockReq(s) => self.coord_send_unlock_req(s),
+            SystemAction::SendDecide(s) => self.coord_send_decide(s),
+            SystemAction::RecvLockRespSuccess(s) => self.coord_recv_lock_resp_success(s),
+            SystemAction::RecvLockRespFailure(s) => self.coord_recv_lock_resp_failure(s),
+            SystemAction::RecvRenameResp(s) => self.coord_recv_rename_resp(s),
+            SystemAction::RecvUnlockResp(s) => self.coord_recv_unlock_resp(s),
+            SystemAction::Crash => self.coord_crash(),
+            SystemAction::Recover => self.coord_recover(),
+            SystemAction::HandleLockReq(s, t) => self.store_handle_lock_req(s, t, key_a, key_aprime),
+            SystemAction::HandleRenameReq(s, t) => self.store_handle_rename_req(s, t, key_a, key_aprime),
+            SystemAction::HandleUnlockReq(s, t) => self.store_handle_unlock_req(s, t, key_a, key_aprime),
+            SystemAction::HandleDecide(s, t, commit) => self.store_handle_decide(s, t, commit, key_a, key_aprime),
+            SystemAction::ResendLockResp(s, t, success) => self.store_resend_lock_resp(s, t, success),
+            SystemAction::Lose(m) => self.net_lose(m),
+            SystemAction::Duplicate(m) => self.net_duplicate(m),
+        }
+    }
+
+    /// Take each action of `history` in turn
+    pub open spec fn replay(self, history: Seq<SystemAction>, key_a: Seq<char>, key_aprime: Seq<char>) -> Self
+        decreases history.len()
+    {
+        if history.len() == 0 {
+            self
+        } else {
+            self.replay(history.drop_last(), key_a, key_aprime).apply(history.last(), key_a, key_aprime)
+        }
+    }
+
+    /// Lemma: replaying two logs back to back is replaying their concatenation
+    pub proof fn lemma_replay_append(
+        self,
+        h1: Seq<SystemAction>,
+        h2: Seq<SystemAction>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        ensures
+            self.replay(h1 + h2, key_a, key_aprime)
+                == self.replay(h1, key_a, key_aprime).replay(h2, key_a, key_aprime),
+        decreases h2.len()
+    {
+        if h2.len() == 0 {
+            assert(h1 + h2 =~= h1);
+        } else {
+            assert((h1 + h2).drop_last() =~= h1 + h2.drop_last());
+            assert((h1 + h2).last() == h2.last());
+            self.lemma_replay_append(h1, h2.drop_last(), key_a, key_aprime);
+        }
+    }
+}
+
+/// A system state together with the start state and the log of actions that
+/// led to it (ghost/proof only)
+pub ghost struct RecordedSystem<V> {
+    pub init: SystemSpec<V>,
+    pub current: SystemSpec<V>,
+    pub history: Seq<SystemAction>,
+}
+
+impl<V> RecordedSystem<V> {
+    /// Start recording from `init` with an empty log
+    pub open spec fn start(init: SystemSpec<V>) -> Self {
+        RecordedSystem { init, current: init, history: Seq::empty() }
+    }
+
+    /// Take action `a` and append it to the log
+    pub open spec fn record(self, a: SystemAction, key_a: Seq<char>, key_aprime: Seq<char>) -> Self {
+        RecordedSystem {
+            current: self.current.apply(a, key_a, key_aprime),
+            history: self.history.push(a),
+            ..self
+        }
+    }
+
+    /// Replaying the log from the start state reproduces the current state
+    pub open spec fn replay_equivalent(&self, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        self.init.replay(self.history, key_a, key_aprime) == self.current
+    }
+
+    /// Lemma: a fresh recording is replay-equivalent
+    pub proof fn lemma_start_replay_equivalent(init: SystemSpec<V>, key_a: Seq<char>, key_aprime: Seq<char>)
+        ensures
+            Self::start(init).replay_equivalent(key_a, key_aprime),
+    {
+    }
+
+    /// Lemma (replay equivalence): recording an action keeps the log and the
+    /// current state in step
+    pub proof fn lemma_record_preserves_replay_equivalent(
+        self,
+        a: SystemAction,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            self.replay_equivalent(key_a, key_aprime),
+        ensures
+            self.record(a, key_a, key_aprime).replay_equivalent(key_a, key_aprime),
+            self.record(a, key_a, key_aprime).init == self.init,
+    {
+        assert(self.history.push(a).drop_last() =~= self.history);
+    }
+}
+
+// ============================================================
+// FAIRNESS AND LIVENESS
+// ============================================================
+//
+// A behavior is an infinite run b(0), b(1), ... of system states. As with TLA+
+// WF_vars(A), an action is weakly fair in a behavior if, whenever it is enabled
+// in every state from some point on, it is eventually taken. Fairness is
+// stated for each action of `next` separately (`next_weakly_fair`), with its
+// parameters: sending to store 1 being taken says nothing about store 2.
+//
+// The liveness result below holds under these assumptions:
+// - one store, which holds `key_a`, lacks `key_aprime`, and has not seen a
+//   newer transaction than the first;
+// - no faults: each step stutters or takes an enabled action of `next` other
+//   than a crash, a loss or a duplication. Any other action may be taken at
+//   any time, including re-sends, re-answers and late deliveries;
+// - every non-fault action is weakly fair.
+
+impl SystemAction {
+    /// Faults the environment may inject. They carry no fairness obligation
+    pub open spec fn is_fault(self) -> bool {
+        self is Crash || self is Lose || self is Duplicate
+    }
+}
+
+impl<V> SystemSpec<V> {
+    /// Enabling condition of `a`: the guard of its disjunct in `next`
+    pub open spec fn enabled(self, a: SystemAction, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        let txn = self.coord.current_txn_id;
+        match a {
+            SystemAction::SendLockReq(s) => self.all_stores.contains(s)
+                && (self.coord.phase == CoordPhase::Idle || self.coord.phase == CoordPhase::Preparing),
+            SystemAction::DecideCommit => self.coord.phase == CoordPhase::Preparing
+                && self.coord.all_locks_acquired(self.all_stores),
+            SystemAction::SendRenameReq(s) => self.all_stores.contains(s)
+                && self.coord.phase == CoordPhase::Committed,
+            SystemAction::SendUnlockReq(s) => self.all_stores.contains(s)
+                && self.coord.phase == CoordPhase::Cleanup
+                && self.coord.unlock_targets().contains(s),
+            SystemAction::SendDecide(s) => self.all_stores.contains(s)
+                && self.coord.phase == CoordPhase::Cleanup
+                && self.coord.unlock_targets().contains(s),
+            SystemAction::RecvLockRespSuccess(s) => self.coord.phase == CoordPhase::Preparing
+                && self.net.contains(lock_resp_msg(s, true, txn)),
+            SystemAction::RecvLockRespFailure(s) => self.coord.phase == CoordPhase::Preparing
+                && self.net.contains(lock_resp_msg(s, false, txn)),
+            SystemAction::RecvRenameResp(s) => self.coord.phase == CoordPhase::Committed
+                && self.net.contains(rename_resp_msg(s, txn)),
+            SystemAction::RecvUnlockResp(s) => self.coord.phase == CoordPhase::Cleanup
+                && self.net.contains(unlock_resp_msg(s, txn)),
+            SystemAction::Crash => self.coord.phase.spec_can_crash(),
+            SystemAction::Recover => self.coord.phase == CoordPhase::Crashed,
+            SystemAction::HandleLockReq(s, t) => self.stores.contains_key(s)
+                && self.net.contains(lock_req_msg(s, t)),
+            SystemAction::HandleRenameReq(s, t) => self.stores.contains_key(s)
+                && self.net.contains(rename_req_msg(s, t)),
+            SystemAction::HandleUnlockReq(s, t) => self.stores.contains_key(s)
+                && self.net.contains(unlock_req_msg(s, t)),
+            SystemAction::HandleDecide(s, t, commit) => self.stores.contains_key(s)
+                && self.net.contains(decide_msg(s, commit, t)),
+            SystemAction::ResendLockResp(s, t, success) =>
+                self.store_responded_lock(s, t, success, key_a, key_aprime),
+            SystemAction::Lose(m) => self.net.contains(m),
+            SystemAction::Duplicate(m) => self.net.contains(m),
+        }
+    }
+
+    /// Lemma: taking an enabled action is a `next` step
+    pub proof fn lemma_enabled_action_is_next(self, a: SystemAction, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            self.enabled(a, key_a, key_aprime),
+        ensures
+            self.next(self.apply(a, key_a, key_aprime), key_a, key_aprime),
+    {
+        // Each action's parameters witness the existential of its disjunct
+        let other = self.apply(a, key_a, key_aprime);
+        match a {
+            SystemAction::SendLockReq(s) => assert(other == self.coord_send_lock_req(s)),
+            SystemAction::SendRenameReq(s) => assert(other == self.coord_send_rename_req(s)),
+            SystemAction::SendUnlockReq(s) => assert(other == self.coord_send_unlock_req(s)),
+            SystemAction::SendDecide(s) => assert(other == self.coord_send_decide(s)),
+            SystemAction::RecvLockRespSuccess(s) => assert(other == self.coord_recv_lock_resp_success(s)),
+            SystemAction::RecvLockRespFailure(s) => assert(other == self.coord_recv_lock_resp_failure(s)),
+            SystemAction::RecvRenameResp(s) => assert(other == self.coord_recv_rename_resp(s)),
+            SystemAction::RecvUnlockResp(s) => assert(other == self.coord_recv_unlock_resp(s)),
+            SystemAction::HandleLockReq(s, t) =>
+                assert(other == self.store_handle_lock_req(s, t, key_a, key_aprime)),
+            SystemAction::HandleRenameReq(s, t) =>
+                assert(other == self.store_handle_rename_req(s, t, key_a, key_aprime)),
+            SystemAction::HandleUnlockReq(s, t) =>
+                assert(other == self.store_handle_unlock_req(s, t, key_a, key_aprime)),
+            SystemAction::HandleDecide(s, t, commit) =>
+                assert(other == self.store_handle_decide(s, t, commit, key_a, key_aprime)),
+            SystemAction::ResendLockResp(s, t, success) =>
+                assert(other == self.store_resend_lock_resp(s, t, success)),
+            SystemAction::Lose(m) => assert(other == self.net_lose(m)),
+            SystemAction::Duplicate(m) => assert(other == self.net_duplicate(m)),
+            _ => {},
+        }
+    }
+}
+
+/// Weak fairness of `taken` (enabled in a state according to `enabled`) in `b`
+pub open spec fn weakly_fair<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    enabled: spec_fn(SystemSpec<V>) -> bool,
+    taken: spec_fn(SystemSpec<V>, SystemSpec<V>) -> bool,
+) -> bool {
+    forall|i: nat| (forall|j: nat| j >= i ==> #[trigger] enabled(b(j)))
+        ==> exists|j: nat| j >= i && #[trigger] taken(b(j), b(j + 1))
+}
+
+/// Weak fairness of the single action `a` in `b`
+pub open spec fn action_weakly_fair<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    a: SystemAction,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+) -> bool {
+    weakly_fair(
+        b,
+        |st: SystemSpec<V>| st.enabled(a, key_a, key_aprime),
+        |st: SystemSpec<V>, st2: SystemSpec<V>|
+            st.enabled(a, key_a, key_aprime) && st2 == st.apply(a, key_a, key_aprime),
+    )
+}
+
+/// Every action of `next` other than a fault is weakly fair in `b`
+pub open spec fn next_weakly_fair<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+) -> bool {
+    forall|a: SystemAction| !a.is_fault() ==> #[trigger] action_weakly_fair(b, a, key_a, key_aprime)
+}
+
+/// Every step of `b` stutters or takes an enabled action of `next` that is
+/// not a fault
+pub open spec fn fault_free_behavior<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+) -> bool {
+    forall|i: nat| #![trigger b(i)]
+        b(i + 1) == b(i) || exists|a: SystemAction| !a.is_fault()
+            && b(i).enabled(a, key_a, key_aprime) && b(i + 1) == b(i).apply(a, key_a, key_aprime)
+}
+
+impl<V> SystemSpec<V> {
+    /// Invariant of a fault-free single-store behavior from `one_store_init`:
+    /// txn 1 throughout and only its messages in flight, no refusal while the
+    /// coordinator prepares, and both locks held from the yes vote until
+    /// cleanup
+    pub open spec fn one_store_inv(&self, s: StoreId, key_a: Seq<char>, key_aprime: Seq<char>) -> bool {
+        let st = self.store(s);
+        let phase = self.coord.phase;
+        &&& self.all_stores == Set::<StoreId>::empty().insert(s)
+        &&& self.stores.contains_key(s)
+        &&& self.coord.current_txn_id == 1
+        &&& !st.is_stale_txn_id(1)
+        &&& phase != CoordPhase::Crashed
+        &&& forall|m: Message| #[trigger] self.net.contains(m) ==> m.get_txn_id() == 1 && m.get_store() == s
+        &&& phase == CoordPhase::Idle ==> self.net == NetworkSpec::empty()
+        &&& phase != CoordPhase::Idle ==> self.coord.lock_requested == self.all_stores
+        &&& self.coord.locks_acquired.subset_of(self.all_stores)
+        &&& phase == CoordPhase::Idle || phase == CoordPhase::Preparing ==> {
+            &&& st.contains_key(key_a) && !st.contains_key(key_aprime) && !st.is_released(1)
+            &&& !st.is_frozen() && !st.locked_by_other(key_a, key_aprime, 1)
+            &&& forall|m: Message| #[trigger] self.net.contains(m)
+                    ==> m == lock_req_msg(s, 1) || m == lock_resp_msg(s, true, 1)
+            &&& self.net.contains(lock_resp_msg(s, true, 1)) || self.coord.locks_acquired.contains(s)
+                    ==> st.is_locked(key_a) && st.is_locked(key_aprime)
+        }
+        &&& phase == CoordPhase::Committed ==> {
+            &&& st.is_locked(key_a) && st.is_locked(key_aprime) && st.is_prepared(1)
+            &&& st.contains_key(key_a) || st.contains_key(key_aprime)
+            &&& forall|m: Message| #[trigger] self.net.contains(m)
+                    ==> !(m is UnlockReq) && !(m is UnlockResp) && !(m is Decide)
+        }
+    }
+
+    /// How far a single-store run has got, from 0 (Idle) to 10 (Done). Within
+    /// a phase, each message of the phase's round in flight is a step further
+    pub open spec fn one_store_stage(&self, s: StoreId) -> nat {
+        let t = self.coord.current_txn_id;
+        match self.coord.phase {
+            CoordPhase::Preparing =>
+                if self.coord.locks_acquired.contains(s) { 3 }
+                else if self.net.contains(lock_resp_msg(s, true, t)) { 2 }
+                else if self.net.contains(lock_req_msg(s, t)) { 1 }
+                else { 0 },
+            CoordPhase::Committed =>
+                if self.net.contains(rename_resp_msg(s, t)) { 6 }
+                else if self.net.contains(rename_req_msg(s, t)) { 5 }
+                else { 4 },
+            CoordPhase::Cleanup =>
+                if self.net.contains(unlock_resp_msg(s, t)) { 9 }
+                else if self.net.contains(unlock_req_msg(s, t)) { 8 }
+                else { 7 },
+            CoordPhase::Done => 10,
+            _ => 0,
+        }
+    }
+
+    /// Lemma: the single-store start satisfies the invariant
+    pub proof fn lemma_one_store_init_inv(s: StoreId, st: KvStoreSpec<V>, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            st.contains_key(key_a),
+            !st.contains_key(key_aprime),
+            !st.is_stale_txn_id(1),
+            st.released_txn is None,
+            !st.is_locked(key_a) && !st.is_locked(key_aprime),
+            !st.is_frozen(),
+        ensures
+            Self::one_store_init(s, st).one_store_inv(s, key_a, key_aprime),
+            Self::one_store_init(s, st).one_store_stage(s) == 0,
+    {
+        let init = Self::one_store_init(s, st);
+        assert(init.store(s) == st);
+    }
+
+    /// Lemma: a non-fault step keeps the invariant and never moves the stage
+    /// back. Re-sends and re-answers only add copies of messages already
+    /// counted; a late LockReq is re-granted while preparing and refused
+    /// afterwards, which adds nothing the coordinator can receive
+    pub proof fn lemma_one_store_step(
+        self,
+        a: SystemAction,
+        s: StoreId,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            key_a != key_aprime,
+            self.one_store_inv(s, key_a, key_aprime),
+            !a.is_fault(),
+            self.enabled(a, key_a, key_aprime),
+        ensures
+            self.apply(a, key_a, key_aprime).one_store_inv(s, key_a, key_aprime),
+            self.apply(a, key_a, key_aprime).one_store_stage(s) >= self.one_store_stage(s),
+    {
+        let post = self.apply(a, key_a, key_aprime);
+        let st = self.store(s);
+        st.lemma_update_txn_id_preserves_state(1);
+        match a {
+            SystemAction::HandleLockReq(s2, t) => {
+                // Only txn 1's requests to `s` are in flight
+                assert(s2 == s && t == 1);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+                if self.coord.phase == CoordPhase::Preparing {
+                    // Not renamed, not released, not frozen and no foreign
+                    // lock yet: the store grants again
+                    assert(!st.update_txn_id(1).is_released(1));
+                    assert(!st.update_txn_id(1).is_frozen());
+                    assert(!st.update_txn_id(1).locked_by_other(key_a, key_aprime, 1));
+                    assert(post.net.contains(lock_resp_msg(s, true, 1)));
+                }
+            },
+            SystemAction::HandleRenameReq(s2, t) => {
+                assert(s2 == s && t == 1);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+                // Requests of the rename round only exist from Committed on, where
+                // both locks are held, so the store always answers
+                if self.coord.phase == CoordPhase::Committed {
+                    assert(post.net.contains(rename_resp_msg(s, 1)));
+                }
+            },
+            SystemAction::HandleUnlockReq(s2, t) => {
+                assert(s2 == s && t == 1);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+                assert(self.coord.phase == CoordPhase::Cleanup || self.coord.phase == CoordPhase::Done);
+                assert(post.net.contains(unlock_resp_msg(s, 1)));
+            },
+            SystemAction::HandleDecide(s2, t, commit) => {
+                // Decides are only sent in cleanup, and never move a stage:
+                // they consume an uncounted message and add at most an
+                // UnlockResp, the round's last message
+                assert(s2 == s && t == 1);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+                assert(self.coord.phase == CoordPhase::Cleanup || self.coord.phase == CoordPhase::Done);
+            },
+            SystemAction::ResendLockResp(s2, t, success) => {
+                // A refusal is never re-sent while preparing: the store has
+                // neither renamed nor released
+                assert(s2 == s);
+                if self.coord.phase == CoordPhase::Preparing || self.coord.phase == CoordPhase::Idle {
+                    assert(success);
+                }
+            },
+            SystemAction::RecvLockRespFailure(s2) => {
+                // No refusal is in flight while preparing
+                assert(!self.net.contains(lock_resp_msg(s2, false, 1)));
+            },
+            _ => {},
+        }
+    }
+
+    /// The action that moves a single-store run out of stage `stage`
+    pub open spec fn one_store_progress_action(s: StoreId, stage: nat) -> SystemAction {
+        if stage == 0 { SystemAction::SendLockReq(s) }
+        else if stage == 1 { SystemAction::HandleLockReq(s, 1) }
+        else if stage == 2 { SystemAction::RecvLockRespSuccess(s) }
+        else if stage == 3 { SystemAction::DecideCommit }
+        else if stage == 4 { SystemAction::SendRenameReq(s) }
+        else if stage == 5 { SystemAction::HandleRenameReq(s, 1) }
+        else if stage == 6 { SystemAction::RecvRenameResp(s) }
+        else if stage == 7 { SystemAction::SendUnlockReq(s) }
+        else if stage == 8 { SystemAction::HandleUnlockReq(s, 1) }
+        else { SystemAction::RecvUnlockResp(s) }
+    }
+
+    /// Lemma: below Done, the stage's progress action is enabled, is not a
+    /// fault, and moves the stage forward
+    pub proof fn lemma_one_store_progress(self, s: StoreId, key_a: Seq<char>, key_aprime: Seq<char>)
+        requires
+            key_a != key_aprime,
+            self.one_store_inv(s, key_a, key_aprime),
+            self.one_store_stage(s) < 10,
+        ensures ({
+            let a = Self::one_store_progress_action(s, self.one_store_stage(s));
+            &&& !a.is_fault()
+            &&& self.enabled(a, key_a, key_aprime)
+            &&& self.apply(a, key_a, key_aprime).one_store_stage(s) > self.one_store_stage(s)
+        }),
+    {
+        let a = Self::one_store_progress_action(s, self.one_store_stage(s));
+        self.lemma_one_store_step(a, s, key_a, key_aprime);
+        if self.one_store_stage(s) == 3 {
+            // The single store's grant is every grant
+            assert(self.coord.locks_acquired =~= self.all_stores);
+        }
+        if self.one_store_stage(s) == 6 {
+            assert(self.coord.renames_done.insert(s) =~= self.all_stores
+                || self.coord.renames_done.insert(s).subset_of(self.all_stores));
+        }
+        if self.one_store_stage(s) == 9 {
+            assert(self.coord.unlock_targets().subset_of(self.coord.unlocks_acked.insert(s)));
+        }
+    }
+}
+
+/// Helper: along a fault-free behavior from the single-store start, every
+/// state satisfies the invariant and the stage never decreases
+proof fn lemma_one_store_behavior_inv<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    s: StoreId,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+    i: nat,
+)
+    requires
+        key_a != key_aprime,
+        b(0).one_store_inv(s, key_a, key_aprime),
+        fault_free_behavior(b, key_a, key_aprime),
+    ensures
+        b(i).one_store_inv(s, key_a, key_aprime),
+        b(i + 1).one_store_stage(s) >= b(i).one_store_stage(s),
+    decreases i,
+{
+    if i > 0 {
+        lemma_one_store_behavior_inv(b, s, key_a, key_aprime, (i - 1) as nat);
+    }
+    if b(i + 1) != b(i) {
+        let a = choose|a: SystemAction| !a.is_fault()
+            && b(i).enabled(a, key_a, key_aprime) && b(i + 1) == b(i).apply(a, key_a, key_aprime);
+        b(i).lemma_one_store_step(a, s, key_a, key_aprime);
+    }
+}
+
+/// Helper: the stage at `m` is at least the stage at any earlier `i`
+proof fn lemma_one_store_stage_monotone<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    s: StoreId,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+    i: nat,
+    m: nat,
+)
+    requires
+        key_a != key_aprime,
+        b(0).one_store_inv(s, key_a, key_aprime),
+        fault_free_behavior(b, key_a, key_aprime),
+        i <= m,
+    ensures
+        b(m).one_store_stage(s) >= b(i).one_store_stage(s),
+    decreases m - i,
+{
+    if m > i {
+        lemma_one_store_stage_monotone(b, s, key_a, key_aprime, i, (m - 1) as nat);
+        lemma_one_store_behavior_inv(b, s, key_a, key_aprime, (m - 1) as nat);
+    }
+}
+
+/// Helper (WF1): a stage below Done is eventually left. While the stage
+/// stays put, its progress action stays enabled, so fairness takes it, and
+/// taking it moves the stage forward
+proof fn lemma_one_store_stage_advances<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    s: StoreId,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+    i: nat,
+)
+    requires
+        key_a != key_aprime,
+        b(0).one_store_inv(s, key_a, key_aprime),
+        fault_free_behavior(b, key_a, key_aprime),
+        next_weakly_fair(b, key_a, key_aprime),
+        b(i).one_store_stage(s) < 10,
+    ensures
+        exists|j: nat| j >= i && #[trigger] b(j).one_store_stage(s) > b(i).one_store_stage(s),
+{
+    let k = b(i).one_store_stage(s);
+    let a = SystemSpec::<V>::one_store_progress_action(s, k);
+    if forall|j: nat| j >= i ==> #[trigger] b(j).one_store_stage(s) <= k {
+        // The stage never moves, so the progress action is enabled from i on
+        assert forall|j: nat| j >= i implies #[trigger] b(j).enabled(a, key_a, key_aprime) by {
+            lemma_one_store_stage_monotone(b, s, key_a, key_aprime, i, j);
+            lemma_one_store_behavior_inv(b, s, key_a, key_aprime, j);
+            b(j).lemma_one_store_progress(s, key_a, key_aprime);
+        }
+        b(i).lemma_one_store_progress(s, key_a, key_aprime);
+        assert(action_weakly_fair(b, a, key_a, key_aprime));
+        let j = choose|j: nat| j >= i
+            && b(j).enabled(a, key_a, key_aprime) && b(j + 1) == b(j).apply(a, key_a, key_aprime);
+        lemma_one_store_stage_monotone(b, s, key_a, key_aprime, i, j);
+        lemma_one_store_behavior_inv(b, s, key_a, key_aprime, j);
+        b(j).lemma_one_store_progress(s, key_a, key_aprime);
+        // Taking it at j leaves stage k, a contradiction
+        assert(b(j + 1).one_store_stage(s) > k);
+    }
+}
+
+/// Helper: the behavior reaches stage `n` for every n <= 10
+proof fn lemma_one_store_reaches_stage<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    s: StoreId,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+    n: nat,
+)
+    requires
+        n <= 10,
+        key_a != key_aprime,
+        b(0).one_store_inv(s, key_a, key_aprime),
+        fault_free_behavior(b, key_a, key_aprime),
+        next_weakly_fair(b, key_a, key_aprime),
+    ensures
+        exists|i: nat| #[trigger] b(i).one_store_stage(s) >= n,
+    decreases n,
+{
+    if n == 0 {
+        assert(b(0).one_store_stage(s) >= 0);
+    } else {
+        lemma_one_store_reaches_stage(b, s, key_a, key_aprime, (n - 1) as nat);
+        let i = choose|i: nat| #[trigger] b(i).one_store_stage(s) >= n - 1;
+        if b(i).one_store_stage(s) < n {
+            lemma_one_store_stage_advances(b, s, key_a, key_aprime, i);
+        }
+    }
+}
+
+/// Liveness (one store, no faults, weak fairness of every other action of
+/// `next`): the behavior reaches Done.
+///
+/// Proof: the stage (`one_store_stage`) never decreases along the behavior,
+/// and each stage below Done has an action that stays enabled until the stage
+/// is left and leaves it when taken. Weak fairness of that action (WF1) moves
+/// the stage forward, ten times over; stage 10 is Done.
+pub proof fn lemma_one_store_fair_behavior_reaches_done<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    s: StoreId,
+    st: KvStoreSpec<V>,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+)
+    requires
+        key_a != key_aprime,
+        st.contains_key(key_a),
+        !st.contains_key(key_aprime),
+        !st.is_stale_txn_id(1),
+        st.released_txn is None,
+        !st.is_locked(key_a) && !st.is_locked(key_aprime),
+        !st.is_frozen(),
+        b(0) == SystemSpec::one_store_init(s, st),
+        fault_free_behavior(b, key_a, key_aprime),
+        next_weakly_fair(b, key_a, key_aprime),
+    ensures
+        exists|i: nat| #[trigger] b(i).coord.phase == CoordPhase::Done,
+{
+    SystemSpec::<V>::lemma_one_store_init_inv(s, st, key_a, key_aprime);
+    lemma_one_store_reaches_stage(b, s, key_a, key_aprime, 10);
+    let i = choose|i: nat| #[trigger] b(i).one_store_stage(s) >= 10;
+    // Only Done is stage 10
+    assert(b(i).coord.phase == CoordPhase::Done);
+}
+
+// ============================================================
+// Durable outcome (confluence under network faults)
+// ============================================================
+//
+// A single-store run that loses or duplicates messages, in any order and any
+// number of times, but never crashes the coordinator, ends where the
+// fault-free run does once it reaches Done: txn 1 committed and the value
+// moved from `key_a` to `key_aprime`. So any two such runs that reach Done
+// agree on the durable state.
+
+/// Every step of `b` stutters or takes an enabled action of `next` other than
+/// a coordinator crash. Messages may be lost or duplicated at any step
+pub open spec fn crash_free_behavior<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+) -> bool {
+    forall|i: nat| #![trigger b(i)]
+        b(i + 1) == b(i) || exists|a: SystemAction| !(a is Crash)
+            && b(i).enabled(a, key_a, key_aprime) && b(i + 1) == b(i).apply(a, key_a, key_aprime)
+}
+
+impl<V> SystemSpec<V> {
+    /// Same durable outcome: the coordinator's durable fields and every store's
+    /// data agree. In-flight messages, the coordinator's volatile state and the
+    /// stores' lock tables may differ.
+    pub open spec fn same_durable(self, other: SystemSpec<V>) -> bool {
+        &&& self.coord.current_txn_id == other.coord.current_txn_id
+        &&& self.coord.wal_committed == other.coord.wal_committed
+        &&& self.stores.dom() == other.stores.dom()
+        &&& forall|s: StoreId| #[trigger] self.stores.contains_key(s) ==>
+                self.stores[s].data == other.stores[s].data
+    }
+
+    /// Invariant of a crash-free single-store behavior from
+    /// `one_store_init(s, st)`: `one_store_inv`, `s` is the only store, and it
+    /// holds either `st`'s data or its renamed copy. It holds the renamed copy
+    /// once it answers a RenameReq, and from Cleanup on; the WAL is committed
+    /// from Committed on
+    pub open spec fn one_store_durable_inv(
+        &self,
+        s: StoreId,
+        st: KvStoreSpec<V>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    ) -> bool {
+        let cur = self.store(s);
+        let phase = self.coord.phase;
+        &&& self.one_store_inv(s, key_a, key_aprime)
+        &&& self.stores.dom() == Set::<StoreId>::empty().insert(s)
+        &&& cur.data == st.data || cur.data == st.rename(key_a, key_aprime).data
+        &&& self.net.contains(rename_resp_msg(s, 1)) ==> cur.contains_key(key_aprime)
+        &&& phase == CoordPhase::Committed || phase == CoordPhase::Cleanup || phase == CoordPhase::Done
+                ==> self.coord.wal_committed
+        &&& phase == CoordPhase::Cleanup || phase == CoordPhase::Done ==> cur.contains_key(key_aprime)
+    }
+
+    /// Lemma: any step but a coordinator crash keeps the durable invariant.
+    /// A loss or a duplicate adds no message that was not in flight, and only
+    /// the RenameReq handler changes data: it moves the value from `key_a` once,
+    /// and a later RenameReq finds `key_aprime` and only answers
+    pub proof fn lemma_one_store_durable_step(
+        self,
+        a: SystemAction,
+        s: StoreId,
+        st: KvStoreSpec<V>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            key_a != key_aprime,
+            !st.contains_key(key_aprime),
+            self.one_store_durable_inv(s, st, key_a, key_aprime),
+            !(a is Crash),
+            self.enabled(a, key_a, key_aprime),
+        ensures
+            self.apply(a, key_a, key_aprime).one_store_durable_inv(s, st, key_a, key_aprime),
+    {
+        let post = self.apply(a, key_a, key_aprime);
+        let cur = self.store(s);
+        let st1 = cur.update_txn_id(1);
+        cur.lemma_update_txn_id_preserves_state(1);
+        match a {
+            SystemAction::Lose(m) => {
+                assert forall|m2: Message| #[trigger] post.net.contains(m2) implies self.net.contains(m2) by {}
+                assert(self.coord.phase != CoordPhase::Idle);
+            },
+            SystemAction::Duplicate(m) => {
+                assert forall|m2: Message| #[trigger] post.net.contains(m2) implies self.net.contains(m2) by {}
+                assert(self.coord.phase != CoordPhase::Idle);
+            },
+            SystemAction::Recover => {
+                // The coordinator never crashes, so it never recovers either
+                assert(false);
+            },
+            SystemAction::RecvLockRespFailure(s2) => {
+                // No refusal is in flight while preparing
+                assert(!self.net.contains(lock_resp_msg(s2, false, 1)));
+            },
+            SystemAction::HandleRenameReq(s2, t) => {
+                assert(s2 == s && t == 1);
+                self.lemma_one_store_step(a, s, key_a, key_aprime);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+                if !st1.contains_key(key_aprime) && st1.is_locked(key_a) && st1.is_locked(key_aprime)
+                    && st1.contains_key(key_a) {
+                    // The store still holds `st`'s data, so the rename gives its
+                    // renamed copy
+                    assert(cur.data == st.data);
+                    assert(post.store(s).data =~= st.rename(key_a, key_aprime).data);
+                }
+            },
+            SystemAction::HandleLockReq(s2, t) => {
+                assert(s2 == s && t == 1);
+                self.lemma_one_store_step(a, s, key_a, key_aprime);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+                st1.lemma_lock_preserves_data(key_a);
+                st1.lock(key_a).lemma_lock_preserves_data(key_aprime);
+            },
+            SystemAction::HandleUnlockReq(s2, t) => {
+                assert(s2 == s && t == 1);
+                self.lemma_one_store_step(a, s, key_a, key_aprime);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+                st1.lemma_unlock_preserves_data(key_a);
+                st1.unlock(key_a).lemma_unlock_preserves_data(key_aprime);
+            },
+            SystemAction::HandleDecide(s2, t, commit) => {
+                assert(s2 == s && t == 1);
+                self.lemma_one_store_step(a, s, key_a, key_aprime);
+                self.lemma_store_handlers_preserve_shape(s, t, key_a, key_aprime);
+                st1.lemma_unlock_preserves_data(key_a);
+                st1.unlock(key_a).lemma_unlock_preserves_data(key_aprime);
+            },
+            SystemAction::RecvRenameResp(s2) => {
+                // Only responses from `s` are in flight
+                assert(s2 == s);
+                self.lemma_one_store_step(a, s, key_a, key_aprime);
+            },
+            _ => {
+                self.lemma_one_store_step(a, s, key_a, key_aprime);
+            },
+        }
+    }
+
+    /// Lemma: at Done, the durable invariant pins the outcome: txn 1 committed
+    /// and the store holding `st`'s renamed data
+    pub proof fn lemma_one_store_durable_at_done(
+        self,
+        s: StoreId,
+        st: KvStoreSpec<V>,
+        key_a: Seq<char>,
+        key_aprime: Seq<char>,
+    )
+        requires
+            !st.contains_key(key_aprime),
+            self.one_store_durable_inv(s, st, key_a, key_aprime),
+            self.coord.phase == CoordPhase::Done,
+        ensures
+            self.coord.current_txn_id == 1,
+            self.coord.wal_committed,
+            self.stores.dom() == Set::<StoreId>::empty().insert(s),
+            self.store(s).data == st.rename(key_a, key_aprime).data,
+    {
+        // `st` lacks `key_aprime`, so the store no longer holds `st`'s data
+        assert(self.store(s).data != st.data);
+    }
+}
+
+/// Helper: along a crash-free behavior from the single-store start, every
+/// state satisfies the durable invariant
+proof fn lemma_one_store_crash_free_inv<V>(
+    b: spec_fn(nat) -> SystemSpec<V>,
+    s: StoreId,
+    st: KvStoreSpec<V>,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+    i: nat,
+)
+    requires
+        key_a != key_aprime,
+        !st.contains_key(key_aprime),
+        b(0).one_store_durable_inv(s, st, key_a, key_aprime),
+        crash_free_behavior(b, key_a, key_aprime),
+    ensures
+        b(i).one_store_durable_inv(s, st, key_a, key_aprime),
+    decreases i,
+{
+    if i > 0 {
+        let p = (i - 1) as nat;
+        lemma_one_store_crash_free_inv(b, s, st, key_a, key_aprime, p);
+        if b(p + 1) != b(p) {
+            let a = choose|a: SystemAction| !(a is Crash)
+                && b(p).enabled(a, key_a, key_aprime) && b(p + 1) == b(p).apply(a, key_a, key_aprime);
+            b(p).lemma_one_store_durable_step(a, s, st, key_a, key_aprime);
+        }
+    }
+}
+
+/// Theorem (confluence under network faults): with a single store, two
+/// crash-free behaviors from the same start that both reach Done end with the
+/// same durable outcome, however each loses or duplicates messages on the way.
+/// That outcome is the fault-free one: txn 1 committed and the value moved
+/// from `key_a` to `key_aprime`.
+pub proof fn lemma_one_store_lossy_runs_same_durable<V>(
+    b1: spec_fn(nat) -> SystemSpec<V>,
+    b2: spec_fn(nat) -> SystemSpec<V>,
+    s: StoreId,
+    st: KvStoreSpec<V>,
+    key_a: Seq<char>,
+    key_aprime: Seq<char>,
+    i: nat,
+    j: nat,
+)
+    requires
+        key_a != key_aprime,
+        st.contains_key(key_a),
+        !st.contains_key(key_aprime),
+        !st.is_stale_txn_id(1),
+        st.released_txn is None,
+        !st.is_locked(key_a) && !st.is_locked(key_aprime),
+        !st.is_frozen(),
+        b1(0) == SystemSpec::one_store_init(s, st),
+        b2(0) == SystemSpec::one_store_init(s, st),
+        crash_free_behavior(b1, key_a, key_aprime),
+        crash_free_behavior(b2, key_a, key_aprime),
+        b1(i).coord.phase == CoordPhase::Done,
+        b2(j).coord.phase == CoordPhase::Done,
+    ensures
+        b1(i).same_durable(b2(j)),
+        b1(i).coord.wal_committed,
+        b1(i).store(s).data == st.rename(key_a, key_aprime).data,
+{
+    let init = SystemSpec::<V>::one_store_init(s, st);
+    SystemSpec::<V>::lemma_one_store_init_inv(s, st, key_a, key_aprime);
+    assert(init.stores.dom() =~= Set::<StoreId>::empty().insert(s));
+    assert(init.store(s) == st);
+    lemma_one_store_crash_free_inv(b1, s, st, key_a, key_aprime, i);
+    lemma_one_store_crash_free_inv(b2, s, st, key_a, key_aprime, j);
+    b1(i).lemma_one_store_durable_at_done(s, st, key_a, key_aprime);
+    b2(j).lemma_one_store_durable_at_done(s, st, key_a, key_aprime);
+    assert forall|t: StoreId| #[trigger] b1(i).stores.contains_key(t) implies
+        b1(i).stores[t].data == b2(j).stores[t].data by {
+        assert(t == s);
+    }
+}
+
+// ============================================================
+// TESTS
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    spec fn key_a() -> Seq<char> { "A"@ }
+    spec fn key_aprime() -> Seq<char> { "A'"@ }
+
+    spec fn mk_one_store_system() -> SystemSpec<u64> {
+        let s0: StoreId = 0;
+        let all = Set::empty().insert(s0);
+        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
+        let stores = Map::empty().insert(s0, st0);
+        SystemSpec {
+            coord: CoordinatorSpec::init(),
+            net: NetworkSpec::empty(),
+            stores,
+            all_stores: all,
+        }
+    }
+
+    spec fn mk_two_store_system() -> SystemSpec<u64> {
+        let s0: StoreId = 0;
+        let s1: StoreId = 1;
+        let all = Set::empty().insert(s0).insert(s1);
+        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
+        let st1 = KvStoreSpec::empty().put(key_a(), 20u64);
+        let stores = Map::empty().insert(s0, st0).insert(s1, st1);
+        SystemSpec {
+            coord: CoordinatorSpec::init(),
+            net: NetworkSpec::empty(),
+            stores,
+            all_stores: all,
+        }
+    }
+
+    /// Sending a LockReq and handling it are both `next` steps, and stay well-typed
+    proof fn test_next_lock_round_trip_type_ok() {
+        let s0: StoreId = 0;
+        let sys0 = mk_two_store_system();
+        let txn_id = sys0.coord.current_txn_id;
+        assert(sys0.all_stores =~= sys0.stores.dom());
+        assert(sys0.type_ok());
+
+        let sys1 = sys0.coord_send_lock_req(s0);
+        assert(sys0.next(sys1, key_a(), key_aprime()));
+        sys0.lemma_next_preserves_type_ok(sys1, key_a(), key_aprime());
+
+        assert(sys1.net.contains(lock_req_msg(s0, txn_id)));
+        let sys2 = sys1.store_handle_lock_req(s0, txn_id, key_a(), key_aprime());
+        assert(sys1.next(sys2, key_a(), key_aprime()));
+        sys1.lemma_next_preserves_type_ok(sys2, key_a(), key_aprime());
+        assert(sys2.type_ok());
+    }
+
+    /// Recording a LockReq send and its handling: replaying the two-action log
+    /// from the start reproduces the state, which holds both locks
+    proof fn test_replay_two_actions() {
+        let s0: StoreId = 0;
+        let sys0 = mk_one_store_system();
+        let txn_id = sys0.coord.current_txn_id;
+        let a1 = SystemAction::SendLockReq(s0);
+        let a2 = SystemAction::HandleLockReq(s0, txn_id);
+
+        let r0 = RecordedSystem::start(sys0);
+        RecordedSystem::lemma_start_replay_equivalent(sys0, key_a(), key_aprime());
+        let r1 = r0.record(a1, key_a(), key_aprime());
+        r0.lemma_record_preserves_replay_equivalent(a1, key_a(), key_aprime());
+        let r2 = r1.record(a2, key_a(), key_aprime());
+        r1.lemma_record_preserves_replay_equivalent(a2, key_a(), key_aprime());
+
+        assert(r2.history =~= seq![a1, a2]);
+        assert(r2.replay_equivalent(key_a(), key_aprime()));
+        assert(r2.current == sys0.coord_send_lock_req(s0).store_handle_lock_req(s0, txn_id, key_a(), key_aprime()));
+        assert(sys0.replay(r2.history, key_a(), key_aprime()) == r2.current);
+        assert(r2.current.store(s0).is_locked(key_a()));
+        assert(r2.current.store(s0).is_locked(key_aprime()));
+    }
+
+    /// Losing every in-flight LockReq stalls the protocol but keeps the invariant
+    proof fn test_lose_all_lock_reqs_preserves_invariant() {
+        let s0: StoreId = 0;
+        let s1: StoreId = 1;
+        let sys0 = mk_two_store_system();
+        let txn_id = sys0.coord.current_txn_id;
+        let sys1 = sys0.coord_send_lock_req(s0).coord_send_lock_req(s1);
+        assert(sys1.stores == sys0.stores);
+        assert(sys0.well_formed_network());
+        NetworkSpec::lemma_empty_causally_consistent();
+        sys0.lemma_coord_sends_preserve_well_formed_network(s0);
+        sys0.coord_send_lock_req(s0).lemma_coord_sends_preserve_well_formed_network(s1);
+        assert(sys1.system_invariant(key_a(), key_aprime()));
+
+        let lost = seq![lock_req_msg(s0, txn_id), lock_req_msg(s1, txn_id)];
+        sys1.lemma_lose_all_preserves_invariant(lost, key_a(), key_aprime());
+        let sys2 = sys1.lose_all(lost);
+        assert(sys2.system_invariant(key_a(), key_aprime()));
+        assert(sys2.coord == sys1.coord);
+    }
+
+    /// A LockReq delivered to a store is answered with a well-formed LockResp
+    proof fn test_lock_round_trip_well_formed_network() {
+        let s0: StoreId = 0;
+        let sys0 = mk_two_store_system();
+        let txn_id = sys0.coord.current_txn_id;
+        assert(sys0.well_formed_network());
+        sys0.lemma_coord_sends_preserve_well_formed_network(s0);
+        let sys1 = sys0.coord_send_lock_req(s0);
+        assert(sys1.net.contains(lock_req_msg(s0, txn_id)));
+        sys1.lemma_store_handles_preserve_well_formed_network(s0, txn_id, key_a(), key_aprime());
+        let sys2 = sys1.store_handle_lock_req(s0, txn_id, key_a(), key_aprime());
+        assert(sys2.well_formed_network());
+    }
+
+    /// A store that handles the coordinator's LockReq adopts its txn id, which
+    /// is still no newer than the coordinator's
+    proof fn test_store_txn_bounded_after_lock_req() {
+        let s0: StoreId = 0;
+        let sys0 = mk_two_store_system();
+        let txn_id = sys0.coord.current_txn_id;
+        assert(sys0.well_formed_network());
+        assert(sys0.store_txns_bounded());
+        sys0.lemma_coord_sends_preserve_well_formed_network(s0);
+        let sys1 = sys0.coord_send_lock_req(s0);
+        assert(sys1.store_txns_bounded());
+        assert(sys1.net.contains(lock_req_msg(s0, txn_id)));
+
+        sys1.lemma_store_txn_bounded_by_coord(s0, txn_id, key_a(), key_aprime());
+        let sys2 = sys1.store_handle_lock_req(s0, txn_id, key_a(), key_aprime());
+        assert(sys2.store_txns_bounded());
+        assert(sys2.store(s0).last_seen_txn_id == sys2.coord.current_txn_id);
+    }
+
+    /// The two-store starting state satisfies the agreement invariant
+    proof fn test_two_store_init_agreement_inv() {
+        let sys = mk_two_store_system();
+        assert(sys.all_stores =~= sys.stores.dom());
+        sys.lemma_init_agreement_inv(key_a(), key_aprime());
+        assert(sys.agreement_inv(key_a(), key_aprime()));
+    }
+
+    /// End-to-end happy path:
+    /// - coordinator sends LockReq
+    /// - stores lock and respond
+    /// - coordinator commits and sends RenameReq
+    /// - stores rename and respond
+    /// - coordinator sends UnlockReq
+    /// - stores unlock and respond
+    /// - coordinator reaches Done
+    proof fn test_success_path_two_stores() {
+        let s0: StoreId = 0;
+        let s1: StoreId = 1;
+        let txn: TxnId = 1;
+
+        let sys0 = mk_two_store_system();
+
+        let sys1 = sys0
+            .coord_send_lock_req(s0)
+            .coord_send_lock_req(s1);
+
+        assert(sys1.coord.phase == CoordPhase::Preparing);
+        assert(sys1.net.contains(lock_req_msg(s0, txn)));
+        assert(sys1.net.contains(lock_req_msg(s1, txn)));
+
+        let sys2 = sys1
+            .store_handle_lock_req(s0, txn, key_a(), key_aprime())
+            .store_handle_lock_req(s1, txn, key_a(), key_aprime());
+
+        assert(sys2.net.contains(lock_resp_msg(s0, true, txn)));
+        assert(sys2.net.contains(lock_resp_msg(s1, true, txn)));
+        assert(sys2.store(s0).is_locked(key_a()));
+        assert(sys2.store(s0).is_locked(key_aprime()));
+
+        let sys3 = sys2
+            .coord_recv_lock_resp_success(s0)
+            .coord_recv_lock_resp_success(s1);
+
+        assert(sys3.coord.locks_acquired == sys3.all_stores);
+
+        let sys4 = sys3.coord_decide_commit();
+        assert(sys4.coord.phase == CoordPhase::Committed);
+        assert(sys4.coord.wal_committed);
+
+        let sys5 = sys4
+            .coord_send_rename_req(s0)
+            .coord_send_rename_req(s1);
+
+        assert(sys5.net.contains(rename_req_msg(s0, txn)));
+        assert(sys5.net.contains(rename_req_msg(s1, txn)));
 
         let sys6 = sys5
             .store_handle_rename_req(s0, txn, key_a(), key_aprime())
@@ -389,6 +3585,10 @@ mod tests {
         assert(sys7.store(s0).contains_key(key_aprime()));
         assert(!sys7.store(s0).contains_key(key_a()));
 
+        // Every store was sent a LockReq, so every store is an unlock target
+        assert(sys7.coord.lock_requested =~= sys7.all_stores);
+        assert(sys7.coord.unlock_targets() == sys7.all_stores);
+
         let sys8 = sys7
             .coord_send_unlock_req(s0)
             .coord_send_unlock_req(s1);
@@ -412,6 +3612,89 @@ mod tests {
         assert(!sys10.store(s0).is_locked(key_aprime()));
     }
 
+    /// Every intermediate state of the no-crash schedule in `test_success_path_two_stores`,
+    /// one transition at a time (index 0 is the initial state).
+    spec fn happy_path_two_stores() -> Seq<SystemSpec<u64>> {
+        let s0: StoreId = 0;
+        let s1: StoreId = 1;
+        let txn: TxnId = 1;
+
+        let sys0 = mk_two_store_system();
+        let sys1 = sys0.coord_send_lock_req(s0);
+        let sys2 = sys1.coord_send_lock_req(s1);
+        let sys3 = sys2.store_handle_lock_req(s0, txn, key_a(), key_aprime());
+        let sys4 = sys3.store_handle_lock_req(s1, txn, key_a(), key_aprime());
+        let sys5 = sys4.coord_recv_lock_resp_success(s0);
+        let sys6 = sys5.coord_recv_lock_resp_success(s1);
+        let sys7 = sys6.coord_decide_commit();
+        let sys8 = sys7.coord_send_rename_req(s0);
+        let sys9 = sys8.coord_send_rename_req(s1);
+        let sys10 = sys9.store_handle_rename_req(s0, txn, key_a(), key_aprime());
+        let sys11 = sys10.store_handle_rename_req(s1, txn, key_a(), key_aprime());
+        let sys12 = sys11.coord_recv_rename_resp(s0);
+        let sys13 = sys12.coord_recv_rename_resp(s1);
+        let sys14 = sys13.coord_send_unlock_req(s0);
+        let sys15 = sys14.coord_send_unlock_req(s1);
+        let sys16 = sys15.store_handle_unlock_req(s0, txn, key_a(), key_aprime());
+        let sys17 = sys16.store_handle_unlock_req(s1, txn, key_a(), key_aprime());
+        let sys18 = sys17.coord_recv_unlock_resp(s0);
+        let sys19 = sys18.coord_recv_unlock_resp(s1);
+        seq![
+            sys0, sys1, sys2, sys3, sys4, sys5, sys6, sys7, sys8, sys9,
+            sys10, sys11, sys12, sys13, sys14, sys15, sys16, sys17, sys18, sys19,
+        ]
+    }
+
+    /// Lemma: the happy path never crashes and never aborts.
+    ///
+    /// Along the no-crash schedule the coordinator goes
+    /// Idle -> Preparing -> Committed -> Cleanup -> Done, is never Crashed,
+    /// and only reaches Cleanup after the WAL commit (no spurious abort edge).
+    proof fn lemma_happy_path_no_crash()
+        ensures
+            happy_path_two_stores().len() == 20,
+            forall|i: int| 0 <= i < 20 ==> happy_path_two_stores()[i].coord.phase != CoordPhase::Crashed,
+            forall|i: int| 0 <= i < 20 && happy_path_two_stores()[i].coord.phase == CoordPhase::Cleanup
+                ==> happy_path_two_stores()[i].coord.wal_committed,
+            happy_path_two_stores()[0].coord.phase == CoordPhase::Idle,
+            forall|i: int| 1 <= i < 7 ==> happy_path_two_stores()[i].coord.phase == CoordPhase::Preparing,
+            forall|i: int| 7 <= i < 13 ==> happy_path_two_stores()[i].coord.phase == CoordPhase::Committed,
+            forall|i: int| 13 <= i < 19 ==> happy_path_two_stores()[i].coord.phase == CoordPhase::Cleanup,
+            happy_path_two_stores()[19].coord.phase == CoordPhase::Done,
+    {
+        let path = happy_path_two_stores();
+
+        // Preparing: lock requests, lock handling and lock responses
+        assert(path[1].coord.phase == CoordPhase::Preparing);
+        assert(path[2].coord.phase == CoordPhase::Preparing);
+        assert(path[3].coord == path[2].coord);
+        assert(path[4].coord == path[3].coord);
+        assert(path[4].net.contains(lock_resp_msg(0, true, 1)));
+        assert(path[4].net.contains(lock_resp_msg(1, true, 1)));
+        assert(path[5].coord.phase == CoordPhase::Preparing);
+        assert(path[6].coord.phase == CoordPhase::Preparing);
+
+        // Committed: the WAL is written before any rename
+        assert(path[7].coord.wal_committed);
+        assert(path[8].coord == path[7].coord.send_rename_req(0).0);
+        assert(path[9].coord.phase == CoordPhase::Committed);
+        assert(path[10].coord == path[9].coord);
+        assert(path[11].coord == path[10].coord);
+        assert(path[12].coord.phase == CoordPhase::Committed);
+
+        // Cleanup is entered only through the last rename response, with the WAL set
+        assert(path[13].coord.phase == CoordPhase::Cleanup);
+        assert(path[13].coord.wal_committed);
+        assert(path[14].coord.wal_committed);
+        assert(path[15].coord.wal_committed);
+        assert(path[16].coord == path[15].coord);
+        assert(path[17].coord == path[16].coord);
+        assert(path[18].coord.phase == CoordPhase::Cleanup);
+        assert(path[18].coord.wal_committed);
+
+        assert(path[19].coord.phase == CoordPhase::Done);
+    }
+
     /// Network duplication at the request layer:
     /// duplicating a `LockReq` results in multiple `LockResp` messages.
     proof fn test_duplicate_lock_req_produces_two_resps() {
@@ -434,6 +3717,54 @@ mod tests {
         assert(sys4.net.count(resp) == 2);
     }
 
+    /// A lost LockResp is re-sent by the store and the protocol still commits
+    proof fn test_resend_lost_lock_resp() {
+        let s0: StoreId = 0;
+        let txn: TxnId = 1;
+
+        let sys0 = mk_one_store_system();
+        let sys1 = sys0
+            .coord_send_lock_req(s0)
+            .store_handle_lock_req(s0, txn, key_a(), key_aprime());
+        let resp = lock_resp_msg(s0, true, txn);
+        assert(sys1.net.contains(resp));
+
+        // The response is lost; the store still remembers voting yes
+        let sys2 = sys1.net_lose(resp);
+        assert(!sys2.net.contains(resp));
+        assert(sys2.store_responded_lock(s0, txn, true, key_a(), key_aprime()));
+
+        let sys3 = sys2.store_resend_lock_resp(s0, txn, true);
+        assert(sys3.store(s0) == sys2.store(s0));
+        assert(sys3.net.contains(resp));
+
+        let sys4 = sys3.coord_recv_lock_resp_success(s0);
+        assert(sys4.coord.locks_acquired =~= sys4.all_stores);
+        let sys5 = sys4.coord_decide_commit();
+        assert(sys5.coord.phase == CoordPhase::Committed);
+    }
+
+    /// A LockResp only appears after its LockReq was sent, and the history keeps
+    /// the request after the store consumes it
+    proof fn test_lock_resp_implies_lock_req_sent() {
+        let s0: StoreId = 0;
+        let txn: TxnId = 1;
+
+        let sys0 = mk_one_store_system();
+        NetworkSpec::lemma_empty_causally_consistent();
+        let sys1 = sys0.coord_send_lock_req(s0);
+        assert(sys1.net.contains(lock_req_msg(s0, txn)));
+        assert(sys1.net.in_flight_sent()) by {
+            sys0.net.lemma_send_preserves_causally_consistent(lock_req_msg(s0, txn));
+        }
+
+        sys1.lemma_store_handle_lock_req_response_implies_request(s0, txn, key_a(), key_aprime());
+        let sys2 = sys1.store_handle_lock_req(s0, txn, key_a(), key_aprime());
+        assert(sys2.net.has_lock_resp_success(s0, txn));
+        assert(sys2.net.response_implies_request(s0, txn));
+        assert(sys2.net.sent.contains(lock_req_msg(s0, txn)));
+    }
+
     /// Stale transaction IDs are rejected by stores: no response is generated.
     proof fn test_store_rejects_stale_txn_id() {
         let s0: StoreId = 0;
@@ -462,6 +3793,381 @@ mod tests {
         assert(!sys1.store(s0).is_locked(key_a()));
         assert(!sys1.store(s0).is_locked(key_aprime()));
     }
+
+    /// A response from the wrong store does not enable crediting the expected store.
+    proof fn test_swapped_lock_resp_not_credited() {
+        let s0: StoreId = 0;
+        let s1: StoreId = 1;
+        let txn: TxnId = 1;
+
+        // Store 0 sends a success response, but the coordinator waits on store 1
+        let sys0 = mk_two_store_system().coord_send_lock_req(s1);
+        let sys1 = sys0.with_net(sys0.net.send(lock_resp_msg(s0, true, txn)));
+
+        NetworkSpec::lemma_different_stores_distinct(s0, s1, txn);
+        assert(!sys1.net.contains(lock_resp_msg(s1, true, txn)));
+        assert(!sys1.coord.locks_acquired.contains(s1));
+
+        sys1.lemma_lock_resp_credits_only_addressed_store(s1, s0);
+        assert(!sys1.coord_recv_lock_resp_success(s0).coord.locks_acquired.contains(s1));
+    }
+
+    /// Abort with a grant still in flight:
+    /// - stores 0 and 1 lock, store 2 has already renamed and votes no
+    /// - the coordinator records store 0's grant, then store 2's refusal aborts
+    /// - store 1's grant is unrecorded, but it was sent a LockReq, so cleanup
+    ///   unlocks it too and no lock is orphaned
+    proof fn test_abort_unlocks_late_grant() {
+        let s0: StoreId = 0;
+        let s1: StoreId = 1;
+        let s2: StoreId = 2;
+        let txn: TxnId = 1;
+
+        let all = Set::empty().insert(s0).insert(s1).insert(s2);
+        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
+        let st1 = KvStoreSpec::empty().put(key_a(), 20u64);
+        let st2 = KvStoreSpec::empty().put(key_aprime(), 30u64);
+        let sys0 = SystemSpec {
+            coord: CoordinatorSpec::init(),
+            net: NetworkSpec::empty(),
+            stores: Map::empty().insert(s0, st0).insert(s1, st1).insert(s2, st2),
+            all_stores: all,
+        };
+
+        let sys1 = sys0
+            .coord_send_lock_req(s0)
+            .coord_send_lock_req(s1)
+            .coord_send_lock_req(s2);
+        assert(sys1.coord.lock_requested =~= all);
+        let sys2 = sys1
+            .store_handle_lock_req(s0, txn, key_a(), key_aprime())
+            .store_handle_lock_req(s1, txn, key_a(), key_aprime())
+            .store_handle_lock_req(s2, txn, key_a(), key_aprime());
+
+        assert(sys2.net.contains(lock_resp_msg(s2, false, txn)));
+        assert(sys2.store(s1).is_locked(key_a()));
+        assert(!sys2.store(s2).is_locked(key_a()));
+
+        // Store 1's grant has not been received when store 2's refusal aborts
+        let sys3 = sys2.coord_recv_lock_resp_success(s0);
+        let sys4 = sys3.coord_recv_lock_resp_failure(s2);
+        assert(sys4.coord.phase == CoordPhase::Cleanup);
+        assert(!sys4.coord.locks_acquired.contains(s1));
+        sys3.coord.lemma_abort_unlocks_all_requested(s1);
+        assert(sys4.coord.unlock_targets().contains(s1));
+
+        let sys5 = sys4
+            .coord_send_unlock_req(s0)
+            .coord_send_unlock_req(s1)
+            .coord_send_unlock_req(s2);
+        let sys6 = sys5
+            .store_handle_unlock_req(s0, txn, key_a(), key_aprime())
+            .store_handle_unlock_req(s1, txn, key_a(), key_aprime())
+            .store_handle_unlock_req(s2, txn, key_a(), key_aprime());
+        let sys7 = sys6
+            .coord_recv_unlock_resp(s0)
+            .coord_recv_unlock_resp(s1)
+            .coord_recv_unlock_resp(s2);
+
+        assert(sys7.coord.unlocks_acked =~= all);
+        assert(sys7.coord.phase == CoordPhase::Done);
+        assert(!sys7.store(s0).is_locked(key_a()));
+        assert(!sys7.store(s1).is_locked(key_a()));
+        assert(sys7.no_orphaned_locks(key_a(), key_aprime()));
+    }
+
+    /// Store 0 votes no, so the coordinator aborts and unlocks both stores
+    /// before store 1 has handled its (delayed) LockReq. Store 1 has released
+    /// the txn, so the late request is refused and no lock outlives Done.
+    proof fn test_delayed_lock_req_refused_after_release() {
+        let s0: StoreId = 0;
+        let s1: StoreId = 1;
+        let txn: TxnId = 1;
+
+        let all = Set::empty().insert(s0).insert(s1);
+        let st0 = KvStoreSpec::empty().put(key_aprime(), 10u64);
+        let st1 = KvStoreSpec::empty().put(key_a(), 20u64);
+        let sys0 = SystemSpec {
+            coord: CoordinatorSpec::init(),
+            net: NetworkSpec::empty(),
+            stores: Map::empty().insert(s0, st0).insert(s1, st1),
+            all_stores: all,
+        };
+
+        let sys1 = sys0.coord_send_lock_req(s0).coord_send_lock_req(s1);
+        NetworkSpec::lemma_different_stores_distinct(s0, s1, txn);
+        let sys2 = sys1.store_handle_lock_req(s0, txn, key_a(), key_aprime());
+        assert(sys2.net.contains(lock_resp_msg(s0, false, txn)));
+        assert(sys2.net.contains(lock_req_msg(s1, txn)));
+
+        // Store 0's refusal aborts; both stores were sent a LockReq, so both are unlocked
+        let sys3 = sys2.coord_recv_lock_resp_failure(s0);
+        assert(sys3.coord.phase == CoordPhase::Cleanup);
+        assert(sys3.coord.unlock_targets() == all);
+        let sys4 = sys3.coord_send_unlock_req(s0).coord_send_unlock_req(s1);
+        let sys5 = sys4
+            .store_handle_unlock_req(s0, txn, key_a(), key_aprime())
+            .store_handle_unlock_req(s1, txn, key_a(), key_aprime());
+        let sys6 = sys5.coord_recv_unlock_resp(s0).coord_recv_unlock_resp(s1);
+        assert(sys6.coord.unlocks_acked =~= all);
+        assert(sys6.coord.phase == CoordPhase::Done);
+        assert(sys6.no_orphaned_locks(key_a(), key_aprime()));
+
+        // The overtaken LockReq is still in flight; store 1 refuses it
+        NetworkSpec::lemma_message_types_distinct(s1, txn);
+        assert(sys6.net.contains(lock_req_msg(s1, txn)));
+        assert(!sys6.store(s1).is_stale_txn_id(txn));
+        assert(sys6.store(s1).is_released(txn));
+        let sys7 = sys6.store_handle_lock_req(s1, txn, key_a(), key_aprime());
+        assert(sys7.coord.phase == CoordPhase::Done);
+        assert(sys7.net.contains(lock_resp_msg(s1, false, txn)));
+        assert(!sys7.store(s1).is_locked(key_a()));
+        assert(sys7.no_orphaned_locks(key_a(), key_aprime()));
+    }
+
+    /// A store that crashes after voting yes in the lock phase loses its locks,
+    /// takes them back on recovery, and is released by the unlock phase
+    proof fn test_store_crash_during_lock() {
+        let s0: StoreId = 0;
+        let txn: TxnId = 1;
+        let sys0 = mk_one_store_system();
+        let sys1 = sys0.coord_send_lock_req(s0);
+        assert(sys1.net.contains(lock_req_msg(s0, txn)));
+        let sys2 = sys1.store_handle_lock_req(s0, txn, key_a(), key_aprime());
+        assert(sys2.store(s0).last_seen_txn_id == txn);
+
+        // The yes vote was durably recorded and nothing has been unlocked yet
+        assert(sys2.store(s0).is_prepared(txn));
+        assert(sys2.store_prepared(s0));
+
+        let sys3 = sys2.store_crash(s0);
+        assert(!sys3.store(s0).is_locked(key_a()));
+        let sys4 = sys3.store_recover(s0, key_a(), key_aprime());
+        sys2.lemma_store_recover_restores_prepared_locks(s0, txn, key_a(), key_aprime());
+        assert(sys4.store(s0).is_locked(key_a()));
+        assert(sys4.store(s0).is_locked(key_aprime()));
+        assert(sys4.store(s0).data == sys2.store(s0).data);
+        let sys5 = sys4.store_handle_unlock_req(s0, txn, key_a(), key_aprime());
+        assert(!sys5.store(s0).is_locked(key_a()));
+        assert(!sys5.store_prepared(s0));
+    }
+
+    /// A store that crashes with a RenameReq in flight still renames once it
+    /// recovers, and keeps its locks until the unlock phase
+    proof fn test_store_crash_during_commit() {
+        let s0: StoreId = 0;
+        let txn: TxnId = 1;
+        let sys0 = mk_one_store_system();
+        let sys1 = sys0.coord_send_lock_req(s0);
+        assert(sys1.net.contains(lock_req_msg(s0, txn)));
+        let sys2 = sys1.store_handle_lock_req(s0, txn, key_a(), key_aprime());
+        assert(sys2.net.contains(lock_resp_msg(s0, true, txn)));
+        let sys3 = sys2.coord_recv_lock_resp_success(s0);
+        assert(sys3.coord.locks_acquired =~= sys3.all_stores);
+        let sys4 = sys3.coord_decide_commit();
+        assert(sys4.coord.phase == CoordPhase::Committed);
+        let sys5 = sys4.coord_send_rename_req(s0);
+        assert(sys5.net.contains(rename_req_msg(s0, txn)));
+
+        assert(sys5.store(s0).is_prepared(txn));
+        assert(sys5.store_prepared(s0));
+        assert(sys5.store(s0).contains_key(key_a()));
+        assert(!sys5.store(s0).contains_key(key_aprime()));
+
+        let sys6 = sys5.store_crash(s0).store_recover(s0, key_a(), key_aprime());
+        sys5.lemma_store_recover_restores_prepared_locks(s0, txn, key_a(), key_aprime());
+        sys5.lemma_store_crash_recover_preserve_data_accessible(s0, key_a(), key_aprime());
+        assert(sys6.net.contains(rename_req_msg(s0, txn)));
+
+        let sys7 = sys6.store_handle_rename_req(s0, txn, key_a(), key_aprime());
+        assert(sys7.store(s0).contains_key(key_aprime()));
+        assert(sys7.store(s0).get(key_aprime()) == 10u64);
+        assert(!sys7.store(s0).contains_key(key_a()));
+        assert(sys7.store(s0).is_locked(key_a()));
+    }
+
+    /// The protocol runs unchanged over non-integer payloads: a sequence value
+    /// is locked, renamed and unlocked exactly like a `u64`.
+    proof fn test_success_path_seq_values() {
+        let s0: StoreId = 0;
+        let txn: TxnId = 1;
+        let payload: Seq<char> = "hello"@;
+
+        let st0 = KvStoreSpec::<Seq<char>>::empty().put(key_a(), payload);
+        let sys0 = SystemSpec {
+            coord: CoordinatorSpec::init(),
+            net: NetworkSpec::empty(),
+            stores: Map::empty().insert(s0, st0),
+            all_stores: Set::empty().insert(s0),
+        };
+
+        let sys1 = sys0
+            .coord_send_lock_req(s0)
+            .store_handle_lock_req(s0, txn, key_a(), key_aprime())
+            .coord_recv_lock_resp_success(s0)
+            .coord_decide_commit();
+        assert(sys1.coord.phase == CoordPhase::Committed);
+        assert(sys1.store(s0).is_locked(key_a()));
+
+        let sys2 = sys1
+            .coord_send_rename_req(s0)
+            .store_handle_rename_req(s0, txn, key_a(), key_aprime());
+        assert(sys2.store(s0).contains_key(key_aprime()));
+        assert(sys2.store(s0).get(key_aprime()) == payload);
+        assert(!sys2.store(s0).contains_key(key_a()));
+    }
+
+    /// The fault-free single-store run reaches Done in ten steps
+    proof fn test_one_store_run_reaches_done() {
+        let s0: StoreId = 0;
+        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
+        let sys0 = SystemSpec::one_store_init(s0, st0);
+        assert(sys0 == mk_one_store_system());
+
+        SystemSpec::<u64>::lemma_one_store_run_reaches_done(s0, st0, key_a(), key_aprime());
+        let done = sys0.protocol_run(s0, key_a(), key_aprime(), 10);
+        assert(done.coord.phase == CoordPhase::Done);
+        assert(sys0.protocol_run(s0, key_a(), key_aprime(), 9).coord.phase != CoordPhase::Done);
+    }
+
+    /// Two crash-free runs from the one-store start that reach Done, whatever
+    /// they lose or duplicate, both hold the value 10 at A' and nothing at A
+    proof fn test_one_store_lossy_runs_same_durable(
+        b1: spec_fn(nat) -> SystemSpec<u64>,
+        b2: spec_fn(nat) -> SystemSpec<u64>,
+        i: nat,
+        j: nat,
+    )
+        requires
+            b1(0) == mk_one_store_system(),
+            b2(0) == mk_one_store_system(),
+            crash_free_behavior(b1, key_a(), key_aprime()),
+            crash_free_behavior(b2, key_a(), key_aprime()),
+            b1(i).coord.phase == CoordPhase::Done,
+            b2(j).coord.phase == CoordPhase::Done,
+    {
+        let s0: StoreId = 0;
+        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
+        assert(key_a() != key_aprime()) by {
+            assert(key_a().len() != key_aprime().len());
+        }
+        assert(mk_one_store_system() == SystemSpec::one_store_init(s0, st0));
+        lemma_one_store_lossy_runs_same_durable(b1, b2, s0, st0, key_a(), key_aprime(), i, j);
+        assert(b1(i).same_durable(b2(j)));
+        assert(b1(i).store(s0).data[key_aprime()] == 10);
+        assert(!b2(j).store(s0).data.contains_key(key_a()));
+    }
+
+    /// States a few steps from the one-store start, including a crash and a lost
+    /// message, are reachable and therefore satisfy the system invariant
+    proof fn test_one_store_reachable_states_safe() {
+        let s0: StoreId = 0;
+        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
+        let sys0 = SystemSpec::one_store_init(s0, st0);
+        assert(key_a() != key_aprime()) by {
+            assert(key_a().len() != key_aprime().len());
+        }
+        SystemSpec::<u64>::lemma_one_store_init_is_init(s0, st0, key_a(), key_aprime());
+        assert(sys0.reachable_in(key_a(), key_aprime(), 0));
+
+        let sys1 = sys0.coord_send_lock_req(s0);
+        assert(sys0.next(sys1, key_a(), key_aprime()));
+        assert(sys1.reachable_in(key_a(), key_aprime(), 1));
+
+        let sys2 = sys1.net_lose(lock_req_msg(s0, 1));
+        assert(sys1.net.contains(lock_req_msg(s0, 1)));
+        assert(sys1.next(sys2, key_a(), key_aprime()));
+        assert(sys2.reachable_in(key_a(), key_aprime(), 2));
+
+        let sys3 = sys2.coord_crash();
+        assert(sys2.next(sys3, key_a(), key_aprime()));
+        assert(sys3.reachable_in(key_a(), key_aprime(), 3));
+        assert(sys3.reachable(key_a(), key_aprime()));
+
+        sys3.lemma_reachable_system_invariant(key_a(), key_aprime());
+        assert(sys3.system_invariant(key_a(), key_aprime()));
+    }
+
+    /// Committing after the one store's lock round trip is backed by its yes vote
+    proof fn test_commit_requires_unanimity_one_store() {
+        let s0: StoreId = 0;
+        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
+        let sys0 = SystemSpec::one_store_init(s0, st0);
+        assert(key_a() != key_aprime()) by {
+            assert(key_a().len() != key_aprime().len());
+        }
+        SystemSpec::<u64>::lemma_one_store_init_is_init(s0, st0, key_a(), key_aprime());
+        assert(sys0.reachable_in(key_a(), key_aprime(), 0));
+
+        let sys1 = sys0.coord_send_lock_req(s0);
+        assert(sys0.next(sys1, key_a(), key_aprime()));
+        assert(sys1.reachable_in(key_a(), key_aprime(), 1));
+
+        let sys2 = sys1.store_handle_lock_req(s0, 1, key_a(), key_aprime());
+        assert(sys1.net.contains(lock_req_msg(s0, 1)));
+        assert(sys1.next(sys2, key_a(), key_aprime()));
+        assert(sys2.reachable_in(key_a(), key_aprime(), 2));
+
+        let sys3 = sys2.coord_recv_lock_resp_success(s0);
+        assert(sys2.net.contains(lock_resp_msg(s0, true, 1)));
+        assert(sys2.next(sys3, key_a(), key_aprime()));
+        assert(sys3.reachable_in(key_a(), key_aprime(), 3));
+        assert(sys3.reachable(key_a(), key_aprime()));
+
+        // The only store is credited, so the commit step is enabled
+        assert(sys3.coord.locks_acquired =~= sys3.all_stores);
+        let sys4 = sys3.coord_decide_commit();
+        assert(sys3.next(sys4, key_a(), key_aprime()));
+        assert(sys4.reachable_in(key_a(), key_aprime(), 4));
+        assert(sys4.reachable(key_a(), key_aprime()));
+
+        sys4.lemma_commit_requires_unanimity(key_a(), key_aprime());
+        assert(sys4.coord.wal_committed);
+        assert(sys4.net.sent.contains(lock_resp_msg(s0, true, 1)));
+        assert(sys4.unanimous_yes(1));
+    }
+
+    /// Test: the value stays available while a store handles a request
+    proof fn test_value_available_through_store_handler() {
+        let s0: StoreId = 0;
+        let st0 = KvStoreSpec::empty().put(key_a(), 10u64);
+        let sys0 = SystemSpec::one_store_init(s0, st0);
+        assert(key_a() != key_aprime()) by {
+            assert(key_a().len() != key_aprime().len());
+        }
+        SystemSpec::<u64>::lemma_one_store_init_is_init(s0, st0, key_a(), key_aprime());
+        assert(sys0.reachable_in(key_a(), key_aprime(), 0));
+
+        let sys1 = sys0.coord_send_lock_req(s0);
+        assert(sys0.next(sys1, key_a(), key_aprime()));
+        assert(sys1.reachable_in(key_a(), key_aprime(), 1));
+        sys1.lemma_reachable_in_safety_inv(key_a(), key_aprime(), 1);
+
+        assert(sys1.net.contains(lock_req_msg(s0, 1)));
+        sys1.lemma_store_handlers_preserve_value_available(s0, 1, key_a(), key_aprime());
+        let sys2 = sys1.store_handle_lock_req(s0, 1, key_a(), key_aprime());
+        assert(sys2.value_always_available(key_a(), key_aprime()));
+    }
+
+    /// A custom detection key decides lock failure instead of `key_aprime`.
+    proof fn test_lock_req_custom_detect_key() {
+        let s0: StoreId = 0;
+        let txn: TxnId = 1;
+        let detect: Seq<char> = "B"@;
+
+        let sys0 = mk_one_store_system();
+        let sys0b = sys0.with_store(s0, sys0.store(s0).put(detect, 7u64));
+        let sys1 = sys0b.coord_send_lock_req(s0);
+
+        // Default handler ignores "B" and locks successfully
+        let sys2 = sys1.store_handle_lock_req(s0, txn, key_a(), key_aprime());
+        assert(sys2.net.contains(lock_resp_msg(s0, true, txn)));
+
+        // Custom detection key "B" exists, so the lock fails
+        let sys3 = sys1.store_handle_lock_req_with_detect_key(s0, txn, key_a(), key_aprime(), detect);
+        assert(sys3.net.contains(lock_resp_msg(s0, false, txn)));
+        assert(!sys3.store(s0).is_locked(key_a()));
+    }
 }
 
 } // verus!
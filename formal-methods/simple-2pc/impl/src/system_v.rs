@@ -7,8 +7,11 @@
 //
 // This is the executable counterpart to system_s.rs (spec layer).
 
+use std::collections::BTreeSet;
+
 use vstd::prelude::*;
 
+use crate::abstract_s::Decision;
 use crate::coordinator_s::*;
 use crate::coordinator_v::*;
 use crate::kv_store_v::*;
@@ -21,6 +24,127 @@ verus! {
 // EXECUTABLE SYSTEM STATE
 // ============================================================
 
+/// A cross-store move of `key` from store `src` to store `dst`.
+///
+/// Runs on the same lock/commit/unlock skeleton as the rename: LockReq locks
+/// `key` at both stores, MigrateApply inserts at dst and RenameReq deletes at
+/// src, UnlockReq releases `key`. `value` is the coordinator's record of the
+/// value src voted with (its MigrateVote); stores only learn it from messages.
+pub struct ExecMigration {
+    pub src: u64,
+    pub dst: u64,
+    pub key: String,
+    pub value: Option<u64>,
+}
+
+/// Construction parameters for `ExecSystem::new_with_config`, gathered in one
+/// struct so new knobs do not keep widening the constructor.
+pub struct ProtocolConfig {
+    /// Number of stores (ids 0..num_stores)
+    pub num_stores: usize,
+    /// Source key name for the rename
+    pub key_a: String,
+    /// Destination key name for the rename
+    pub key_aprime: String,
+    /// Value every store starts with at `key_a`
+    pub initial_value: u64,
+    /// Per-store cap on in-flight messages (see `ExecNetwork::with_per_store_cap`);
+    /// None leaves the network unbounded
+    pub network_capacity: Option<usize>,
+}
+
+/// The `NetworkModel` installed on an `ExecSystem`, consulted on every send the
+/// protocol makes. Opaque to verification: with no model installed
+/// (`spec_reliable`) a send behaves exactly as `ExecNetwork::send`
+#[verifier::external_body]
+pub struct NetModelSlot {
+    model: Option<Box<dyn NetworkModel>>,
+}
+
+impl NetModelSlot {
+    /// No model is installed, so every send is delivered as is
+    pub uninterp spec fn spec_reliable(&self) -> bool;
+
+    /// A slot with no model installed
+    #[verifier::external_body]
+    pub fn reliable() -> (result: Self)
+        ensures
+            result.spec_reliable(),
+    {
+        NetModelSlot { model: None }
+    }
+
+    /// Send `msg` on `net`, then apply the installed model's operations for it.
+    /// Returns whether the network accepted the send (whatever the model did next)
+    #[verifier::external_body]
+    pub fn send(&mut self, net: &mut ExecNetwork, msg: ExecMessage) -> (accepted: bool)
+        ensures
+            old(self).spec_reliable() ==> accepted == old(net).spec_accepts(msg@),
+            self.spec_reliable() == old(self).spec_reliable(),
+            net.per_store_cap == old(net).per_store_cap,
+            old(net).spec_wf() ==> net.spec_wf(),
+            old(self).spec_reliable() && old(net).spec_accepts(msg@) ==> {
+                &&& net.spec_contains(msg@)
+                &&& net.messages@ == old(net).messages@.push(msg)
+                &&& net.spec_seqs() == old(net).spec_seqs().push(old(net).next_seq)
+                &&& net.next_seq == old(net).next_seq + 1
+            },
+            old(self).spec_reliable() && !old(net).spec_accepts(msg@) ==> {
+                &&& net.messages@ == old(net).messages@
+                &&& net.spec_seqs() == old(net).spec_seqs()
+                &&& net.next_seq == old(net).next_seq
+            },
+    {
+        let sent = msg.clone();
+        let before = net.ops_logged();
+        net.send(msg);
+        // A send the network refused is never logged and has nothing to fault
+        let accepted = net.ops_logged() > before;
+        if let Some(model) = self.model.as_mut() {
+            if accepted {
+                apply_model(net, model.as_mut(), &sent);
+            }
+        }
+        accepted
+    }
+}
+
+/// Every request the protocol has sent and the network accepted, so a driver
+/// can tell whether a request already went out without scanning the network
+/// log. Opaque to verification
+#[verifier::external_body]
+pub struct SentRequests {
+    sent: BTreeSet<ExecMessage>,
+}
+
+impl SentRequests {
+    /// No request sent yet
+    #[verifier::external_body]
+    pub fn new() -> (result: Self) {
+        SentRequests { sent: BTreeSet::new() }
+    }
+
+    /// Record `msg` as sent if it is a request (responses are not tracked)
+    #[verifier::external_body]
+    pub fn record(&mut self, msg: &ExecMessage) {
+        if msg.is_request() {
+            self.sent.insert(msg.clone());
+        }
+    }
+
+    /// Independent copy of the recorded requests
+    #[verifier::external_body]
+    pub fn snapshot(&self) -> (result: Self) {
+        SentRequests { sent: self.sent.iter().map(|m| m.clone()).collect() }
+    }
+
+    /// Whether both record exactly the same requests
+    #[verifier::external_body]
+    pub fn same_as(&self, other: &Self) -> bool {
+        self.sent == other.sent
+    }
+}
+
 /// Executable system state that composes all components.
 /// 
 /// This struct holds:
@@ -28,6 +152,7 @@ verus! {
 /// - A collection of KV stores (indexed by store ID)
 /// - The network (mocked message queue)
 /// - Configuration (key names for the rename operation)
+/// - The cross-store migration in progress, if any
 pub struct ExecSystem {
     /// The coordinator managing the 2PC protocol
     pub coord: Coordinator,
@@ -39,6 +164,12 @@ pub struct ExecSystem {
     pub key_a: String,
     /// Destination key name for rename operation
     pub key_aprime: String,
+    /// Cross-store migration driven by this system (None for a plain rename)
+    pub migration: Option<ExecMigration>,
+    /// Fault policy applied to every protocol send (none installed by default)
+    pub model: NetModelSlot,
+    /// Requests sent so far, consulted by `run_until_quiescent`
+    pub sent: SentRequests,
 }
 
 impl ExecSystem {
@@ -56,6 +187,45 @@ impl ExecSystem {
         (store_id as int) < self.stores@.len()
     }
 
+    /// Lemma: without added participants, the stores taking part are exactly
+    /// those SystemSpec::exclude_participants keeps out of the valid stores, so
+    /// a degraded exec run is the spec's degraded run
+    pub proof fn lemma_in_txn_matches_exclude_participants<V>(&self, sys: crate::system_s::SystemSpec<V>)
+        requires
+            self.coord.participants@ == Set::<u64>::empty(),
+            sys.all_stores == Set::new(|s: StoreId| s < self.stores@.len()),
+        ensures
+            forall|s: u64| #[trigger] self.coord.spec_in_txn(s, self.stores@.len() as nat) <==>
+                sys.exclude_participants(self.coord.spec_excluded_ids()).all_stores.contains(s as StoreId),
+    {
+        assert forall|s: u64| #[trigger] self.coord.spec_in_txn(s, self.stores@.len() as nat) <==>
+            sys.exclude_participants(self.coord.spec_excluded_ids()).all_stores.contains(s as StoreId) by {
+            if self.coord.spec_excluded_ids().contains(s as StoreId) {
+                let t = choose|t: u64| self.coord.excluded@.contains(t) && t as StoreId == s as StoreId;
+                assert(t == s);
+            }
+            if self.coord.excluded@.contains(s) {
+                assert(self.coord.spec_excluded_ids().contains(s as StoreId));
+            }
+        }
+    }
+
+    /// Sum of the stored values of the first `n` stores
+    pub open spec fn spec_value_sum_prefix(&self, n: int) -> nat
+        decreases n,
+    {
+        if n <= 0 {
+            0
+        } else {
+            self.spec_value_sum_prefix(n - 1) + self.stores@[n - 1].spec_value_sum()
+        }
+    }
+
+    /// Sum of the stored values across all stores
+    pub open spec fn spec_total_value_sum(&self) -> nat {
+        self.spec_value_sum_prefix(self.stores@.len() as int)
+    }
+
     // ============================================================
     // CONSTRUCTORS
     // ============================================================
@@ -70,28 +240,123 @@ impl ExecSystem {
             result.stores@.len() == num_stores,
             result.coord.spec_phase() == CoordPhase::Idle,
             result.net.spec_is_empty(),
+            result.net.per_store_cap is None,
+            result.model.spec_reliable(),
+    {
+        Self::new_with_config(ProtocolConfig {
+            num_stores,
+            key_a: key_a.to_owned(),
+            key_aprime: key_aprime.to_owned(),
+            initial_value,
+            network_capacity: None,
+        })
+    }
+
+    /// Create a system from a `ProtocolConfig`: `num_stores` stores each holding
+    /// key_a -> initial_value, with the network capped per store when
+    /// `network_capacity` is set
+    pub fn new_with_config(config: ProtocolConfig) -> (result: Self)
+        requires
+            config.num_stores > 0,
+            config.key_a@ != config.key_aprime@,
+        ensures
+            result.stores@.len() == config.num_stores,
+            result.coord.spec_phase() == CoordPhase::Idle,
+            result.net.spec_is_empty(),
+            result.net.per_store_cap == config.network_capacity,
+            result.key_a@ == config.key_a@,
+            result.key_aprime@ == config.key_aprime@,
+            result.model.spec_reliable(),
     {
         let mut stores: Vec<KvStore> = Vec::new();
         let mut i: usize = 0;
-        while i < num_stores
+        while i < config.num_stores
             invariant
-                0 <= i <= num_stores,
+                0 <= i <= config.num_stores,
                 stores@.len() == i,
             decreases
-                num_stores - i,
+                config.num_stores - i,
         {
             let mut store = KvStore::new();
-            store.put(key_a, initial_value);
+            store.put(config.key_a.as_str(), config.initial_value);
             stores.push(store);
             i = i + 1;
         }
 
+        let net = match config.network_capacity {
+            Some(cap) => ExecNetwork::new().with_per_store_cap(cap),
+            None => ExecNetwork::new(),
+        };
         ExecSystem {
             coord: Coordinator::new(),
             stores,
-            net: ExecNetwork::new(),
-            key_a: key_a.to_owned(),
-            key_aprime: key_aprime.to_owned(),
+            net,
+            key_a: config.key_a,
+            key_aprime: config.key_aprime,
+            migration: None,
+            model: NetModelSlot::reliable(),
+            sent: SentRequests::new(),
+        }
+    }
+
+    /// Independent deep copy of the whole system (coordinator, stores, network,
+    /// keys, migration and sent requests), e.g. to explore a branch of an execution and
+    /// return to this point. Stores are copied with `KvStore::snapshot`.
+    /// The network model is not copied: the copy delivers every send as is
+    pub fn clone_state(&self) -> (result: ExecSystem)
+        ensures
+            result.coord@ == self.coord@,
+            result.key_a@ == self.key_a@,
+            result.key_aprime@ == self.key_aprime@,
+            result.stores@.len() == self.stores@.len(),
+            forall|i: int| #![auto] 0 <= i < self.stores@.len() ==> {
+                &&& result.stores@[i].last_seen_txn_id == self.stores@[i].last_seen_txn_id
+                &&& result.stores@[i].prepared_txn == self.stores@[i].prepared_txn
+                &&& self.stores@[i].spec_key_index_wf() ==>
+                        result.stores@[i]@.data == self.stores@[i]@.data
+                &&& self.stores@[i].spec_lock_history_complete() ==>
+                        result.stores@[i]@.locked_keys == self.stores@[i]@.locked_keys
+            },
+            result.net.messages@.len() == self.net.messages@.len(),
+            forall|i: int| 0 <= i < self.net.messages@.len() ==>
+                #[trigger] result.net.messages@[i]@ == self.net.messages@[i]@,
+            result.net.spec_seqs() == self.net.spec_seqs(),
+            result.net.per_store_cap == self.net.per_store_cap,
+            result.model.spec_reliable(),
+    {
+        let mut stores: Vec<KvStore> = Vec::new();
+        let mut i: usize = 0;
+        while i < self.stores.len()
+            invariant
+                0 <= i <= self.stores.len(),
+                stores@.len() == i,
+                forall|j: int| #![auto] 0 <= j < i ==> {
+                    &&& stores@[j].last_seen_txn_id == self.stores@[j].last_seen_txn_id
+                    &&& stores@[j].prepared_txn == self.stores@[j].prepared_txn
+                    &&& self.stores@[j].spec_key_index_wf() ==>
+                            stores@[j]@.data == self.stores@[j]@.data
+                    &&& self.stores@[j].spec_lock_history_complete() ==>
+                            stores@[j]@.locked_keys == self.stores@[j]@.locked_keys
+                },
+            decreases
+                self.stores.len() - i,
+        {
+            stores.push(self.stores[i].snapshot());
+            i = i + 1;
+        }
+        let mut migration = None;
+        if let Some(m) = &self.migration {
+            migration = Some(ExecMigration { src: m.src, dst: m.dst, key: m.key.clone(), value: m.value });
+        }
+        ExecSystem {
+            coord: self.coord.snapshot(),
+            stores,
+            net: self.net.snapshot(),
+            key_a: self.key_a.clone(),
+            key_aprime: self.key_aprime.clone(),
+            migration,
+            model: NetModelSlot::reliable(),
+            sent: self.sent.snapshot(),
         }
     }
 
@@ -99,6 +364,14 @@ impl ExecSystem {
     // STORE ACCESS HELPERS
     // ============================================================
 
+    /// Check if a store ID is valid
+    pub fn is_valid_store(&self, store_id: u64) -> (result: bool)
+        ensures
+            result == self.spec_valid_store(store_id)
+    {
+        store_id < self.stores.len() as u64
+    }
+
     /// Get a store by index (immutable)
     #[verifier::truncate]
     pub fn get_store(&self, store_id: u64) -> (result: &KvStore)
@@ -108,6 +381,36 @@ impl ExecSystem {
         &self.stores[store_id as usize]
     }
 
+    /// Send a protocol message through the installed network model
+    /// (see `set_model`); with none installed this is `ExecNetwork::send`
+    fn send(&mut self, msg: ExecMessage)
+        ensures
+            self.coord == old(self).coord,
+            self.stores@ == old(self).stores@,
+            self.key_a == old(self).key_a,
+            self.key_aprime == old(self).key_aprime,
+            self.migration == old(self).migration,
+            self.model.spec_reliable() == old(self).model.spec_reliable(),
+            self.net.per_store_cap == old(self).net.per_store_cap,
+            old(self).net.spec_wf() ==> self.net.spec_wf(),
+            old(self).model.spec_reliable() && old(self).net.spec_accepts(msg@) ==> {
+                &&& self.net.spec_contains(msg@)
+                &&& self.net.messages@ == old(self).net.messages@.push(msg)
+                &&& self.net.spec_seqs() == old(self).net.spec_seqs().push(old(self).net.next_seq)
+                &&& self.net.next_seq == old(self).net.next_seq + 1
+            },
+            old(self).model.spec_reliable() && !old(self).net.spec_accepts(msg@) ==> {
+                &&& self.net.messages@ == old(self).net.messages@
+                &&& self.net.spec_seqs() == old(self).net.spec_seqs()
+                &&& self.net.next_seq == old(self).net.next_seq
+            },
+    {
+        let sent = msg.clone();
+        if self.model.send(&mut self.net, msg) {
+            self.sent.record(&sent);
+        }
+    }
+
     // ============================================================
     // COORDINATOR -> NETWORK (SEND) OPERATIONS
     // ============================================================
@@ -119,12 +422,77 @@ impl ExecSystem {
             old(self).coord.spec_phase() == CoordPhase::Idle || old(self).coord.spec_phase() == CoordPhase::Preparing,
         ensures
             self.coord.spec_phase() == CoordPhase::Preparing,
-            self.net.spec_contains(lock_req_msg(store_id as nat, self.coord.spec_txn_id())),
+            self.coord.spec_is_unlock_target(store_id),
+            self.stores@ == old(self).stores@,
+            old(self).model.spec_reliable() && old(self).net.spec_has_room(store_id as nat) ==>
+                self.net.spec_contains(lock_req_msg(store_id as nat, self.coord.spec_txn_id())),
+    {
+        self.coord.record_lock_request(store_id);
+        let txn_id = self.coord.get_txn_id();
+        let msg = ExecMessage::lock_req(store_id, txn_id);
+        self.send(msg);
+    }
+
+    /// Coordinator sends lock request to a store, retrying over a lossy network
+    /// (Re)sends the current LockReq until `attempts` copies are in flight, so a
+    /// later call tops up copies the network has lost. At most `attempts` copies
+    /// are sent per call, and the in-flight count never exceeds `attempts` unless
+    /// it already did (e.g. the network duplicated a copy). On a capped network
+    /// copies over the store's cap are dropped, so none may be in flight if the
+    /// store's queue was already full.
+    pub fn coord_send_lock_with_retry(&mut self, store_id: u64, attempts: u8)
+        requires
+            old(self).spec_valid_store(store_id),
+            old(self).coord.spec_phase() == CoordPhase::Idle || old(self).coord.spec_phase() == CoordPhase::Preparing,
+        ensures
+            self.coord.spec_phase() == CoordPhase::Preparing,
+            self.coord.spec_txn_id() == old(self).coord.spec_txn_id(),
+            self.coord.spec_is_unlock_target(store_id),
+            old(self).model.spec_reliable() ==>
+                self.net.messages@.len() <= old(self).net.messages@.len() + attempts,
+            old(self).model.spec_reliable() && attempts > 0 && old(self).net.spec_has_room(store_id as nat) ==>
+                self.net.spec_count(lock_req_msg(store_id as nat, self.coord.spec_txn_id())) > 0,
+            old(self).model.spec_reliable()
+                && old(self).net.spec_count(lock_req_msg(store_id as nat, old(self).coord.spec_txn_id())) <= attempts
+                ==> self.net.spec_count(lock_req_msg(store_id as nat, self.coord.spec_txn_id())) <= attempts,
     {
-        self.coord.start_preparing();
+        self.coord.record_lock_request(store_id);
         let txn_id = self.coord.get_txn_id();
         let msg = ExecMessage::lock_req(store_id, txn_id);
-        self.net.send(msg);
+        let ghost req = lock_req_msg(store_id as nat, txn_id as nat);
+        let ghost old_len = self.net.messages@.len();
+        let ghost old_count = self.net.spec_count(req);
+        let ghost had_room = self.net.spec_has_room(store_id as nat);
+        let ghost reliable = self.model.spec_reliable();
+
+        let mut sent: u8 = 0;
+        while sent < attempts && self.net.count(&msg) < attempts as usize
+            invariant
+                sent <= attempts,
+                msg@ == req,
+                self.coord.spec_phase() == CoordPhase::Preparing,
+                self.coord.current_txn_id == txn_id,
+                self.coord.spec_is_unlock_target(store_id),
+                self.model.spec_reliable() == reliable,
+                reliable == old(self).model.spec_reliable(),
+                self.net.per_store_cap == old(self).net.per_store_cap,
+                sent == 0 ==> self.net.spec_has_room(store_id as nat) == had_room,
+                reliable ==> self.net.messages@.len() <= old_len + sent,
+                reliable && sent > 0 && had_room ==> self.net.spec_count(req) > 0,
+                reliable && old_count <= attempts ==> self.net.spec_count(req) <= attempts,
+            decreases
+                attempts - sent,
+        {
+            let ghost before = self.net.messages@;
+            self.send(msg.clone());
+            proof {
+                if reliable && self.net.messages@.len() > before.len() {
+                    // The new copy is the last message, so the count grows by one
+                    assert(self.net.messages@.drop_last() =~= before);
+                }
+            }
+            sent = sent + 1;
+        }
     }
 
     /// Coordinator sends rename request to a store
@@ -134,25 +502,66 @@ impl ExecSystem {
             old(self).coord.spec_phase() == CoordPhase::Committed,
         ensures
             self.coord.spec_phase() == CoordPhase::Committed,
-            self.net.spec_contains(rename_req_msg(store_id as nat, self.coord.spec_txn_id())),
+            self.stores@ == old(self).stores@,
+            old(self).model.spec_reliable() && old(self).net.spec_has_room(store_id as nat) ==>
+                self.net.spec_contains(rename_req_msg(store_id as nat, self.coord.spec_txn_id())),
     {
         let txn_id = self.coord.get_txn_id();
         let msg = ExecMessage::rename_req(store_id, txn_id);
-        self.net.send(msg);
+        self.send(msg);
     }
 
     /// Coordinator sends unlock request to a store
+    /// Only unlock targets may be addressed, so a store that never granted a
+    /// lock (e.g. the one that voted no) receives no spurious UnlockReq
     pub fn coord_send_unlock_req(&mut self, store_id: u64)
         requires
             old(self).spec_valid_store(store_id),
             old(self).coord.spec_phase() == CoordPhase::Cleanup,
+            old(self).coord.spec_is_unlock_target(store_id),
         ensures
+            self.coord == old(self).coord,
             self.coord.spec_phase() == CoordPhase::Cleanup,
-            self.net.spec_contains(unlock_req_msg(store_id as nat, self.coord.spec_txn_id())),
+            self.coord.locks_acquired@ == old(self).coord.locks_acquired@,
+            self.stores@ == old(self).stores@,
+            self.key_a == old(self).key_a,
+            self.key_aprime == old(self).key_aprime,
+            self.model.spec_reliable() == old(self).model.spec_reliable(),
+            self.net.per_store_cap == old(self).net.per_store_cap,
+            old(self).model.spec_reliable() ==> self.net.next_seq <= old(self).net.next_seq + 1,
+            old(self).model.spec_reliable() && old(self).net.spec_has_room(store_id as nat) ==>
+                self.net.spec_contains(unlock_req_msg(store_id as nat, self.coord.spec_txn_id())),
     {
         let txn_id = self.coord.get_txn_id();
         let msg = ExecMessage::unlock_req(store_id, txn_id);
-        self.net.send(msg);
+        self.send(msg);
+    }
+
+    /// Coordinator sends the final decision to a store (one-phase finalization)
+    /// Stands in for the UnlockReq of the cleanup round: by then every store is
+    /// a no-op participant (a commit has renamed everywhere, an abort moves
+    /// nothing), so the store only releases the txn's locks and answers
+    /// UnlockResp. Only unlock targets may be addressed, as for UnlockReq
+    pub fn coord_send_decide(&mut self, store_id: u64)
+        requires
+            old(self).spec_valid_store(store_id),
+            old(self).coord.spec_phase() == CoordPhase::Cleanup,
+            old(self).coord.spec_is_unlock_target(store_id),
+        ensures
+            self.coord == old(self).coord,
+            self.stores@ == old(self).stores@,
+            self.key_a == old(self).key_a,
+            self.key_aprime == old(self).key_aprime,
+            self.model.spec_reliable() == old(self).model.spec_reliable(),
+            self.net.per_store_cap == old(self).net.per_store_cap,
+            old(self).model.spec_reliable() && old(self).net.spec_has_room(store_id as nat) ==>
+                self.net.spec_contains(decide_msg(store_id as nat, self.coord.spec_is_committed(),
+                    self.coord.spec_txn_id())),
+    {
+        let txn_id = self.coord.get_txn_id();
+        let commit = self.coord.is_committed();
+        let msg = ExecMessage::decide(store_id, commit, txn_id);
+        self.send(msg);
     }
 
     // ============================================================
@@ -162,17 +571,24 @@ impl ExecSystem {
     /// Coordinator receives lock response (success)
     /// Returns true if message was found and processed
     pub fn coord_recv_lock_resp_success(&mut self, store_id: u64) -> (result: bool)
-        requires
-            old(self).coord.spec_phase() == CoordPhase::Preparing,
-            !old(self).coord.spec_has_lock(store_id),
         ensures
+            result ==> old(self).spec_valid_store(store_id),
             result ==> self.coord.spec_has_lock(store_id),
             result ==> self.coord.spec_phase() == CoordPhase::Preparing,
+            !result ==> self.coord.spec_phase() == old(self).coord.spec_phase(),
+            // Only a response addressed from `store_id` can credit `store_id`
+            result ==> old(self).net.spec_contains(
+                lock_resp_msg(store_id as nat, true, old(self).coord.spec_txn_id())),
+            !result ==> self.coord.locks_acquired@ == old(self).coord.locks_acquired@,
     {
         let txn_id = self.coord.get_txn_id();
         let expected_msg = ExecMessage::lock_resp(store_id, true, txn_id);
-        
-        if self.net.lose(&expected_msg) {
+        // Runtime gate: reject a response the coordinator cannot record now
+        // (wrong txn or phase, unknown store, already credited)
+        if !self.coord.validate_response(&expected_msg, self.stores.len()) {
+            return false;
+        }
+        if self.net.receive(&expected_msg).is_some() {
             self.coord.record_lock_success(store_id);
             true
         } else {
@@ -183,15 +599,17 @@ impl ExecSystem {
     /// Coordinator receives lock response (failure)
     /// Returns true if message was found and processed
     pub fn coord_recv_lock_resp_failure(&mut self, store_id: u64) -> (result: bool)
-        requires
-            old(self).coord.spec_phase() == CoordPhase::Preparing,
         ensures
             result ==> self.coord.spec_phase() == CoordPhase::Cleanup,
     {
         let txn_id = self.coord.get_txn_id();
         let expected_msg = ExecMessage::lock_resp(store_id, false, txn_id);
-        
-        if self.net.lose(&expected_msg) {
+        // Runtime gate: reject a response the coordinator cannot record now
+        // (wrong txn or phase, unknown store, already credited)
+        if !self.coord.validate_response(&expected_msg, self.stores.len()) {
+            return false;
+        }
+        if self.net.receive(&expected_msg).is_some() {
             self.coord.handle_lock_failure();
             true
         } else {
@@ -199,30 +617,33 @@ impl ExecSystem {
         }
     }
 
-    /// Coordinator decides to commit
+    /// Coordinator decides to commit (every store must have granted its lock)
     pub fn coord_decide_commit(&mut self)
         requires
-            old(self).coord.spec_phase() == CoordPhase::Preparing,
+            old(self).coord.spec_can_commit(old(self).spec_num_stores()),
         ensures
             self.coord.spec_phase() == CoordPhase::Committed,
             self.coord.spec_is_committed(),
+            self.coord.spec_all_locks_acquired(self.spec_num_stores()),
     {
-        self.coord.decide_commit();
+        self.coord.decide_commit(Ghost(self.stores.len() as nat));
     }
 
     /// Coordinator receives rename response
     /// Returns true if message was found and processed
     pub fn coord_recv_rename_resp(&mut self, store_id: u64) -> (result: bool)
-        requires
-            old(self).coord.spec_phase() == CoordPhase::Committed,
-            !old(self).coord.spec_has_renamed(store_id),
         ensures
+            result ==> old(self).spec_valid_store(store_id),
             result ==> self.coord.spec_has_renamed(store_id),
     {
         let txn_id = self.coord.get_txn_id();
         let expected_msg = ExecMessage::rename_resp(store_id, txn_id);
-        
-        if self.net.lose(&expected_msg) {
+        // Runtime gate: reject a response the coordinator cannot record now
+        // (wrong txn or phase, unknown store, already credited)
+        if !self.coord.validate_response(&expected_msg, self.stores.len()) {
+            return false;
+        }
+        if self.net.receive(&expected_msg).is_some() {
             let num_stores = self.stores.len();
             self.coord.record_rename_done(store_id, num_stores);
             true
@@ -234,18 +655,23 @@ impl ExecSystem {
     /// Coordinator receives unlock response
     /// Returns true if message was found and processed
     pub fn coord_recv_unlock_resp(&mut self, store_id: u64) -> (result: bool)
-        requires
-            old(self).coord.spec_phase() == CoordPhase::Cleanup,
-            !old(self).coord.spec_has_unlocked(store_id),
         ensures
+            result ==> old(self).spec_valid_store(store_id),
             result ==> self.coord.spec_has_unlocked(store_id),
+            !result ==> self.coord == old(self).coord,
+            result ==> self.coord.spec_phase() == CoordPhase::Cleanup || self.coord.spec_phase() == CoordPhase::Done,
+            self.coord.wal_committed == old(self).coord.wal_committed,
+            self.stores@ == old(self).stores@,
     {
         let txn_id = self.coord.get_txn_id();
         let expected_msg = ExecMessage::unlock_resp(store_id, txn_id);
-        
-        if self.net.lose(&expected_msg) {
-            let num_stores = self.stores.len();
-            self.coord.record_unlock_acked(store_id, num_stores);
+        // Runtime gate: reject a response the coordinator cannot record now
+        // (wrong txn or phase, unknown store, already credited)
+        if !self.coord.validate_response(&expected_msg, self.stores.len()) {
+            return false;
+        }
+        if self.net.receive(&expected_msg).is_some() {
+            self.coord.record_unlock_acked(store_id);
             true
         } else {
             false
@@ -263,10 +689,29 @@ impl ExecSystem {
             old(self).spec_valid_store(store_id),
         ensures
             result ==> self.stores@.len() == old(self).stores@.len(),
+    {
+        let detect_key = self.key_aprime.clone();
+        self.store_handle_lock_req_with_detect_key(store_id, txn_id, detect_key.as_str())
+    }
+
+    /// Store handles lock request with an explicit "already renamed" detection key
+    /// The lock fails if `detect_key` already exists at the store, or if the
+    /// store already released this txn (a LockReq delayed past its abort)
+    /// Returns true if message was found and processed
+    pub fn store_handle_lock_req_with_detect_key(
+        &mut self,
+        store_id: u64,
+        txn_id: u64,
+        detect_key: &str,
+    ) -> (result: bool)
+        requires
+            old(self).spec_valid_store(store_id),
+        ensures
+            result ==> self.stores@.len() == old(self).stores@.len(),
     {
         let expected_msg = ExecMessage::lock_req(store_id, txn_id);
         
-        if !self.net.lose(&expected_msg) {
+        if self.net.receive(&expected_msg).is_none() {
             return false;
         }
 
@@ -285,21 +730,30 @@ impl ExecSystem {
         // Update txn_id
         store.update_txn_id(txn_id);
 
-        // Check if key_aprime already exists (already renamed)
-        let key_aprime_exists = store.contains_key(self.key_aprime.as_str());
-        
-        if key_aprime_exists {
-            // Lock failed - key already renamed
-            let resp = ExecMessage::lock_resp(store_id, false, txn_id);
-            self.net.send(resp);
-        } else {
-            // Lock both keys
+        if store.is_released(txn_id) {
+            // The txn's UnlockReq got here first: refuse rather than re-lock
+            let resp = expected_msg.expected_response(false).unwrap();
+            self.send(resp);
+            self.stores.insert(store_idx, store);
+            return true;
+        }
+
+        // Decide the vote; the detection key stands in for key_aprime when
+        // checking for a finished rename, the locks are those a yes vote takes
+        let vote = store.prepare_vote_with_detect_key(
+            self.key_a.as_str(), self.key_aprime.as_str(), detect_key, txn_id);
+
+        if vote == Vote::Yes {
+            // Lock both keys and durably record the yes vote
             store.lock(self.key_a.as_str());
             store.lock(self.key_aprime.as_str());
-            // Send success response
-            let resp = ExecMessage::lock_resp(store_id, true, txn_id);
-            self.net.send(resp);
+            store.prepare(txn_id);
         }
+        // else: lock failed - already renamed, frozen, or a key is held outside any txn
+
+        // Send the vote
+        let resp = expected_msg.expected_response(vote == Vote::Yes).unwrap();
+        self.send(resp);
 
         // Put the store back
         self.stores.insert(store_idx, store);
@@ -317,7 +771,7 @@ impl ExecSystem {
     {
         let expected_msg = ExecMessage::rename_req(store_id, txn_id);
         
-        if !self.net.lose(&expected_msg) {
+        if self.net.receive(&expected_msg).is_none() {
             return false;
         }
 
@@ -343,16 +797,25 @@ impl ExecSystem {
         
         if key_aprime_exists {
             // Already renamed - send success (idempotent)
-            let resp = ExecMessage::rename_resp(store_id, txn_id);
-            self.net.send(resp);
+            let resp = expected_msg.expected_response(true).unwrap();
+            self.send(resp);
         } else if key_a_locked && key_aprime_locked && key_a_exists {
-            // Perform rename
-            store.rename(self.key_a.as_str(), self.key_aprime.as_str());
-            // Send success response
-            let resp = ExecMessage::rename_resp(store_id, txn_id);
-            self.net.send(resp);
+            // Perform rename; key_aprime is free, so it only moves a value.
+            // A frozen store moves nothing and must not ack
+            let ghost pre = store;
+            if store.rename(self.key_a.as_str(), self.key_aprime.as_str()).is_some() {
+                proof {
+                    if pre.spec_key_index_wf() {
+                        KvStore::lemma_rename_conserves_value_sum(pre, store, self.key_a@, self.key_aprime@);
+                    }
+                }
+                assert(pre.spec_key_index_wf() ==> store.spec_value_sum() == pre.spec_value_sum());
+                // Send success response
+                let resp = expected_msg.expected_response(true).unwrap();
+                self.send(resp);
+            }
         }
-        // else: preconditions not met, no response
+        // else: preconditions not met (or the store is frozen), no response
 
         // Put the store back
         self.stores.insert(store_idx, store);
@@ -366,11 +829,28 @@ impl ExecSystem {
         requires
             old(self).spec_valid_store(store_id),
         ensures
-            result ==> self.stores@.len() == old(self).stores@.len(),
+            result == old(self).net.spec_contains(unlock_req_msg(store_id as nat, txn_id as nat)),
+            self.stores@.len() == old(self).stores@.len(),
+            self.coord == old(self).coord,
+            self.key_a == old(self).key_a,
+            self.key_aprime == old(self).key_aprime,
+            self.model.spec_reliable() == old(self).model.spec_reliable(),
+            self.net.per_store_cap == old(self).net.per_store_cap,
+            old(self).model.spec_reliable() ==> self.net.next_seq <= old(self).net.next_seq + 1,
+            // Only the addressed store changes, and never its data
+            forall|j: int| 0 <= j < self.stores@.len() && j != store_id as int
+                ==> #[trigger] self.stores@[j] == old(self).stores@[j],
+            self.stores@[store_id as int].data@ == old(self).stores@[store_id as int].data@,
+            // A current request releases both keys (a frozen store keeps them)
+            result && !old(self).stores@[store_id as int].spec_is_stale_txn_id(txn_id as nat)
+                && !old(self).stores@[store_id as int].frozen ==> {
+                &&& !self.stores@[store_id as int].spec_is_locked(self.key_a@)
+                &&& !self.stores@[store_id as int].spec_is_locked(self.key_aprime@)
+            },
     {
         let expected_msg = ExecMessage::unlock_req(store_id, txn_id);
         
-        if !self.net.lose(&expected_msg) {
+        if self.net.receive(&expected_msg).is_none() {
             return false;
         }
 
@@ -388,16 +868,107 @@ impl ExecSystem {
         // Update txn_id
         store.update_txn_id(txn_id);
 
-        // Unlock both keys
+        // Unlock both keys; the decision has been applied. A frozen store
+        // keeps its locks, so it acks only if neither key is still held
+        // (e.g. it voted no and never took them)
         store.unlock(self.key_a.as_str());
         store.unlock(self.key_aprime.as_str());
+        if !store.is_locked(self.key_a.as_str()) && !store.is_locked(self.key_aprime.as_str()) {
+            store.clear_prepared();
+            store.release(txn_id);
+
+            // Send success response
+            let resp = expected_msg.expected_response(true).unwrap();
+            self.send(resp);
+        }
+
+        // Put the store back
+        self.stores.insert(store_idx, store);
+        proof {
+            assert(self.stores@ =~= old(self).stores@.update(store_idx as int, self.stores@[store_idx as int]));
+        }
 
-        // Send success response
-        let resp = ExecMessage::unlock_resp(store_id, txn_id);
-        self.net.send(resp);
+        true
+    }
+
+    /// Store handles a decide message (one-phase finalization)
+    /// Only a no-op participant acts on it: a commit that still finds `key_a`
+    /// has a rename pending, so the message is consumed with no response and
+    /// the locks stay held until the rename round reaches the store. Otherwise
+    /// the store releases the locks `txn_id` owns (a foreign lock is kept),
+    /// drops its prepared vote for `txn_id` and answers a single UnlockResp.
+    /// Returns true if message was found and processed
+    pub fn store_handle_decide(&mut self, store_id: u64, commit: bool, txn_id: u64) -> (result: bool)
+        requires
+            old(self).spec_valid_store(store_id),
+        ensures
+            result == old(self).net.spec_contains(decide_msg(store_id as nat, commit, txn_id as nat)),
+            self.stores@.len() == old(self).stores@.len(),
+            self.coord == old(self).coord,
+            self.key_a == old(self).key_a,
+            self.key_aprime == old(self).key_aprime,
+            self.model.spec_reliable() == old(self).model.spec_reliable(),
+            self.net.per_store_cap == old(self).net.per_store_cap,
+            old(self).model.spec_reliable() ==> self.net.next_seq <= old(self).net.next_seq + 1,
+            // Only the addressed store changes, and never its data
+            forall|j: int| 0 <= j < self.stores@.len() && j != store_id as int
+                ==> #[trigger] self.stores@[j] == old(self).stores@[j],
+            self.stores@[store_id as int].data@ == old(self).stores@[store_id as int].data@,
+            // A lock owned by another txn survives
+            forall|k: Seq<char>| #![auto] old(self).stores@[store_id as int].spec_is_locked(k)
+                && old(self).stores@[store_id as int].spec_lock_owner(k) != txn_id
+                ==> self.stores@[store_id as int].spec_is_locked(k),
+    {
+        let expected_msg = ExecMessage::decide(store_id, commit, txn_id);
+
+        if self.net.receive(&expected_msg).is_none() {
+            return false;
+        }
+
+        let store_idx = store_id as usize;
+
+        // Check for stale transaction using immutable borrow
+        let is_stale = self.stores[store_idx].is_stale_txn_id(txn_id);
+        if is_stale {
+            return true; // Message consumed but ignored (stale)
+        }
+
+        // Get a mutable reference by removing and re-inserting
+        let mut store = self.stores.remove(store_idx);
+
+        // Update txn_id
+        store.update_txn_id(txn_id);
+
+        // A commit with the rename still pending is not a no-op: leave it to
+        // the rename round (no response, locks stay held)
+        if !(commit && store.contains_key(self.key_a.as_str())) {
+            // Release only the locks this txn owns
+            let owns_a = match store.lock_owner(self.key_a.as_str()) {
+                Some(owner) => owner == txn_id,
+                None => false,
+            };
+            if owns_a {
+                store.unlock(self.key_a.as_str());
+            }
+            let owns_aprime = match store.lock_owner(self.key_aprime.as_str()) {
+                Some(owner) => owner == txn_id,
+                None => false,
+            };
+            if owns_aprime {
+                store.unlock(self.key_aprime.as_str());
+            }
+            if store.is_prepared(txn_id) {
+                store.clear_prepared();
+            }
+            store.release(txn_id);
+            self.send(expected_msg.expected_response(commit).unwrap());
+        }
 
         // Put the store back
         self.stores.insert(store_idx, store);
+        proof {
+            assert(self.stores@ =~= old(self).stores@.update(store_idx as int, self.stores@[store_idx as int]));
+        }
 
         true
     }
@@ -417,12 +988,138 @@ impl ExecSystem {
     /// Duplicate a message in the network
     pub fn net_duplicate(&mut self, msg: &ExecMessage) -> (result: bool)
         ensures
-            result == old(self).net.spec_contains(msg@),
+            result == (old(self).net.spec_contains(msg@) && old(self).net.next_seq < u64::MAX),
             result ==> self.net.spec_contains(msg@),
     {
         self.net.duplicate(msg)
     }
 
+    /// Adversarial: replace an in-flight successful LockResp from `expected` with
+    /// one claiming to come from `responder` (a store answering for another).
+    /// Returns true if a response from `expected` was found and swapped
+    pub fn net_swap_response(&mut self, expected: u64, responder: u64) -> (result: bool)
+        ensures
+            result == old(self).net.spec_contains(
+                lock_resp_msg(expected as nat, true, old(self).coord.spec_txn_id())),
+            result && old(self).net.spec_accepts_next(1) ==> self.net.spec_contains(
+                lock_resp_msg(responder as nat, true, old(self).coord.spec_txn_id())),
+            self.coord == old(self).coord,
+            self.stores@ == old(self).stores@,
+    {
+        let txn_id = self.coord.get_txn_id();
+        let original = ExecMessage::lock_resp(expected, true, txn_id);
+        if self.net.lose(&original) {
+            self.net.send(ExecMessage::lock_resp(responder, true, txn_id));
+            true
+        } else {
+            false
+        }
+    }
+
+    // ============================================================
+    // MESSAGE PUMP
+    // ============================================================
+
+    /// Deliver and process exactly one pending message for a store.
+    ///
+    /// The oldest message for `store_id` is picked: requests are handled by the
+    /// store, responses are consumed by the coordinator. Responses the coordinator
+    /// cannot use (stale txn id, wrong phase, already recorded) are dropped.
+    /// During a migration, requests and responses go to the migration handlers.
+    /// Returns false if no message for the store is pending.
+    pub fn pump(&mut self, store_id: u64) -> (result: bool)
+        requires
+            old(self).spec_valid_store(store_id),
+        ensures
+            !result ==> self.net.messages@ == old(self).net.messages@,
+    {
+        let msg = match self.net.first_for_store(store_id) {
+            Some(m) => m,
+            None => return false,
+        };
+        let current_txn = self.coord.get_txn_id();
+        let migrating = self.migration.is_some();
+
+        match msg {
+            ExecMessage::LockReq { txn_id, .. } => {
+                if migrating {
+                    self.store_handle_migration_lock_req(store_id, txn_id);
+                } else {
+                    self.store_handle_lock_req(store_id, txn_id);
+                }
+            },
+            ExecMessage::RenameReq { txn_id, .. } => {
+                if migrating {
+                    self.store_handle_migration_apply_req(store_id, txn_id);
+                } else {
+                    self.store_handle_rename_req(store_id, txn_id);
+                }
+            },
+            ExecMessage::UnlockReq { txn_id, .. } => {
+                if migrating {
+                    self.store_handle_migration_unlock_req(store_id, txn_id);
+                } else {
+                    self.store_handle_unlock_req(store_id, txn_id);
+                }
+            },
+            ExecMessage::Decide { commit, txn_id, .. } => {
+                self.store_handle_decide(store_id, commit, txn_id);
+            },
+            ExecMessage::MigrateApply { txn_id, .. } => {
+                if !self.store_handle_migration_apply_req(store_id, txn_id) {
+                    self.net.receive(&msg);
+                }
+            },
+            ExecMessage::MigrateVote { txn_id, .. } => {
+                let consumed = txn_id == current_txn
+                    && self.coord.get_phase() == CoordPhase::Preparing
+                    && !self.coord.has_lock(store_id)
+                    && self.coord_recv_migration_vote(store_id);
+                if !consumed {
+                    self.net.receive(&msg);
+                }
+            },
+            // The recv handlers reject a wrong phase or an already recorded
+            // response; a stale txn id is checked here so they never consume
+            // a different message than the oldest one
+            ExecMessage::LockResp { success, txn_id, .. } => {
+                let consumed = txn_id == current_txn && if success {
+                    self.coord_recv_lock_resp_success(store_id)
+                } else {
+                    self.coord_recv_lock_resp_failure(store_id)
+                };
+                if !consumed {
+                    self.net.receive(&msg);
+                }
+            },
+            ExecMessage::RenameResp { txn_id, .. } => {
+                let consumed = txn_id == current_txn && if migrating {
+                    self.coord.get_phase() == CoordPhase::Committed
+                        && !self.coord.has_renamed(store_id)
+                        && self.coord_recv_migration_apply_resp(store_id)
+                } else {
+                    self.coord_recv_rename_resp(store_id)
+                };
+                if !consumed {
+                    self.net.receive(&msg);
+                }
+            },
+            ExecMessage::UnlockResp { txn_id, .. } => {
+                let consumed = txn_id == current_txn && if migrating {
+                    self.coord.get_phase() == CoordPhase::Cleanup
+                        && !self.coord.has_unlocked(store_id)
+                        && self.coord_recv_migration_unlock_resp(store_id)
+                } else {
+                    self.coord_recv_unlock_resp(store_id)
+                };
+                if !consumed {
+                    self.net.receive(&msg);
+                }
+            },
+        }
+        true
+    }
+
     // ============================================================
     // COORDINATOR CRASH/RECOVERY
     // ============================================================
@@ -446,36 +1143,576 @@ impl ExecSystem {
             self.coord.spec_txn_id() == old(self).coord.spec_txn_id() + 1,
             old(self).coord.spec_is_committed() ==> self.coord.spec_phase() == CoordPhase::Committed,
             !old(self).coord.spec_is_committed() ==> self.coord.spec_phase() == CoordPhase::Cleanup,
+            self.coord.lock_requested@ == old(self).coord.lock_requested@,
     {
         self.coord.recover();
     }
 
-    // ============================================================
-    // QUERY OPERATIONS
-    // ============================================================
-
-    /// Get the current phase of the coordinator
-    pub fn get_coord_phase(&self) -> (result: CoordPhase)
+    /// Re-drive the phase the coordinator is in (typically just resumed by
+    /// recovery): broadcast a RenameReq (Committed) or UnlockReq (Cleanup) at the
+    /// current txn id to every store that still owes a response. Excluded stores
+    /// are skipped, and in Cleanup only unlock targets are addressed
+    pub fn coord_resend_phase(&mut self)
+        requires
+            old(self).coord.spec_phase() == CoordPhase::Committed
+                || old(self).coord.spec_phase() == CoordPhase::Cleanup,
         ensures
-            result == self.coord.spec_phase()
+            self.coord == old(self).coord,
+            self.stores@ == old(self).stores@,
+            forall|s: u64| #![auto] old(self).model.spec_reliable()
+                && old(self).net.spec_accepts_next(self.spec_num_stores())
+                && (s as nat) < self.spec_num_stores() && !self.coord.excluded@.contains(s) ==> {
+                &&& self.coord.spec_phase() == CoordPhase::Committed ==>
+                    self.net.spec_contains(rename_req_msg(s as nat, self.coord.spec_txn_id()))
+                &&& self.coord.spec_phase() == CoordPhase::Cleanup && self.coord.spec_is_unlock_target(s) ==>
+                    self.net.spec_contains(unlock_req_msg(s as nat, self.coord.spec_txn_id()))
+            },
     {
-        self.coord.get_phase()
+        let committed = self.coord.get_phase() == CoordPhase::Committed;
+        let txn_id = self.coord.get_txn_id();
+        let n = self.stores.len();
+        let mut i: usize = 0;
+        while i < n
+            invariant
+                0 <= i <= n,
+                n == self.stores@.len(),
+                self.coord == old(self).coord,
+                self.stores@ == old(self).stores@,
+                committed == (self.coord.spec_phase() == CoordPhase::Committed),
+                txn_id as nat == self.coord.spec_txn_id(),
+                self.model.spec_reliable() == old(self).model.spec_reliable(),
+                self.net.per_store_cap == old(self).net.per_store_cap,
+                old(self).model.spec_reliable() ==> self.net.next_seq <= old(self).net.next_seq + i,
+                forall|s: u64| #![auto] old(self).model.spec_reliable() && old(self).net.spec_accepts_next(n as nat)
+                    && (s as int) < i && !self.coord.excluded@.contains(s) ==> {
+                    &&& committed ==> self.net.spec_contains(rename_req_msg(s as nat, txn_id as nat))
+                    &&& !committed && self.coord.spec_is_unlock_target(s) ==>
+                        self.net.spec_contains(unlock_req_msg(s as nat, txn_id as nat))
+                },
+            decreases
+                n - i,
+        {
+            let store = i as u64;
+            if !self.coord.is_excluded(store) && (committed || self.coord.is_unlock_target(store)) {
+                let msg = if committed {
+                    ExecMessage::rename_req(store, txn_id)
+                } else {
+                    ExecMessage::unlock_req(store, txn_id)
+                };
+                let ghost before = self.net.messages@;
+                self.send(msg);
+                proof {
+                    // Earlier requests keep their positions in the queue
+                    if old(self).model.spec_reliable() && old(self).net.spec_accepts_next(n as nat) {
+                        assert forall|m: Message| #![auto] (exists|k: int| 0 <= k < before.len() && before[k]@ == m)
+                            implies self.net.spec_contains(m) by {
+                            let k = choose|k: int| 0 <= k < before.len() && before[k]@ == m;
+                            assert(self.net.messages@[k]@ == m);
+                        }
+                    }
+                }
+            }
+            i = i + 1;
+        }
     }
 
-    /// Get the current transaction ID
-    pub fn get_txn_id(&self) -> (result: u64)
-        ensures
-            result as nat == self.coord.spec_txn_id()
-    {
-        self.coord.get_txn_id()
-    }
+    // ============================================================
+    // TRANSACTION ABORT
+    // ============================================================
 
-    /// Check if coordinator is committed
-    pub fn is_committed(&self) -> (result: bool)
+    /// Abort the current (uncommitted) transaction and run cleanup to completion.
+    /// A crashed coordinator is recovered first; then every store sent a LockReq
+    /// (an unlock target) is sent and handles an UnlockReq before the acks are
+    /// collected. A LockReq still in flight stays in the network: the store has
+    /// released the txn by then and refuses it when it lands. Ends in Done, or in
+    /// Cleanup if an ack went missing. Data is never touched, so every store
+    /// keeps its value at `key_a`; no store is left holding a lock provided no
+    /// network model is installed, the network accepts every send (uncapped,
+    /// sequence numbers to spare), no
+    /// store is frozen, none has seen a newer transaction and only stores sent
+    /// a LockReq hold one
+    pub fn abort_transaction(&mut self)
+        requires
+            !old(self).coord.spec_is_committed(),
+            old(self).coord.spec_phase() != CoordPhase::Idle,
+            !old(self).coord.spec_phase().spec_is_terminal(),
+            old(self).coord.spec_phase() != CoordPhase::Committed,
+            old(self).coord.spec_phase() == CoordPhase::Crashed ==>
+                old(self).coord.spec_txn_id() < u64::MAX as nat,
         ensures
-            result == self.coord.spec_is_committed()
+            self.coord.spec_phase() == CoordPhase::Cleanup || self.coord.spec_phase() == CoordPhase::Done,
+            !self.coord.spec_is_committed(),
+            self.stores@.len() == old(self).stores@.len(),
+            forall|i: int| 0 <= i < self.stores@.len()
+                ==> #[trigger] self.stores@[i].data@ == old(self).stores@[i].data@,
+            old(self).model.spec_reliable()
+                && old(self).net.spec_accepts_next(2 * old(self).stores@.len())
+                && (forall|i: int| 0 <= i < old(self).stores@.len()
+                    ==> #[trigger] old(self).stores@[i].last_seen_txn_id <= old(self).coord.current_txn_id)
+                && (forall|i: int| 0 <= i < old(self).stores@.len() ==> !#[trigger] old(self).stores@[i].frozen)
+                && (forall|i: int| 0 <= i < old(self).stores@.len()
+                    && !old(self).coord.spec_is_unlock_target(i as u64) ==> {
+                    &&& !#[trigger] old(self).stores@[i].spec_is_locked(old(self).key_a@)
+                    &&& !old(self).stores@[i].spec_is_locked(old(self).key_aprime@)
+                })
+                ==> forall|i: int| 0 <= i < self.stores@.len() ==> {
+                    &&& !#[trigger] self.stores@[i].spec_is_locked(self.key_a@)
+                    &&& !self.stores@[i].spec_is_locked(self.key_aprime@)
+                },
     {
-        self.coord.is_committed()
+        if self.coord.get_phase() == CoordPhase::Crashed {
+            self.coord_recover();
+        }
+        // Forget any recorded grant; the stores sent a LockReq stay unlock targets
+        self.coord.force_abort();
+
+        let ghost clean = old(self).model.spec_reliable()
+            && old(self).net.spec_accepts_next(2 * old(self).stores@.len())
+            && (forall|i: int| 0 <= i < old(self).stores@.len()
+                ==> #[trigger] old(self).stores@[i].last_seen_txn_id <= old(self).coord.current_txn_id)
+            && (forall|i: int| 0 <= i < old(self).stores@.len() ==> !#[trigger] old(self).stores@[i].frozen)
+            && (forall|i: int| 0 <= i < old(self).stores@.len()
+                && !old(self).coord.spec_is_unlock_target(i as u64) ==> {
+                &&& !#[trigger] old(self).stores@[i].spec_is_locked(old(self).key_a@)
+                &&& !old(self).stores@[i].spec_is_locked(old(self).key_aprime@)
+            });
+        let txn_id = self.coord.get_txn_id();
+        let n = self.stores.len();
+        let mut i: usize = 0;
+        while i < n
+            invariant
+                0 <= i <= n,
+                n == self.stores@.len(),
+                n == old(self).stores@.len(),
+                self.coord.spec_phase() == CoordPhase::Cleanup,
+                self.coord.locks_acquired@ == Set::<u64>::empty(),
+                self.coord.lock_requested@ == old(self).coord.lock_requested@,
+                !self.coord.spec_is_committed(),
+                self.coord.current_txn_id == txn_id,
+                txn_id >= old(self).coord.current_txn_id,
+                self.key_a == old(self).key_a,
+                self.key_aprime == old(self).key_aprime,
+                self.model.spec_reliable() == old(self).model.spec_reliable(),
+                self.net.per_store_cap == old(self).net.per_store_cap,
+                // Each store costs at most an UnlockReq and its response
+                old(self).model.spec_reliable() ==> self.net.next_seq <= old(self).net.next_seq + 2 * i,
+                forall|j: int| 0 <= j < n
+                    ==> #[trigger] self.stores@[j].data@ == old(self).stores@[j].data@,
+                forall|j: int| i <= j < n ==> #[trigger] self.stores@[j] == old(self).stores@[j],
+                clean ==> forall|j: int| 0 <= j < i ==> {
+                    &&& !#[trigger] self.stores@[j].spec_is_locked(self.key_a@)
+                    &&& !self.stores@[j].spec_is_locked(self.key_aprime@)
+                },
+            decreases
+                n - i,
+        {
+            let store = i as u64;
+            if self.coord.is_unlock_target(store) {
+                self.coord_send_unlock_req(store);
+                self.store_handle_unlock_req(store, txn_id);
+            }
+            i = i + 1;
+        }
+
+        let mut i: usize = 0;
+        while i < n
+            invariant
+                0 <= i <= n,
+                n == self.stores@.len(),
+                self.coord.spec_phase() == CoordPhase::Cleanup || self.coord.spec_phase() == CoordPhase::Done,
+                !self.coord.spec_is_committed(),
+                self.key_a == old(self).key_a,
+                self.key_aprime == old(self).key_aprime,
+                forall|j: int| 0 <= j < n
+                    ==> #[trigger] self.stores@[j].data@ == old(self).stores@[j].data@,
+                clean ==> forall|j: int| 0 <= j < n ==> {
+                    &&& !#[trigger] self.stores@[j].spec_is_locked(self.key_a@)
+                    &&& !self.stores@[j].spec_is_locked(self.key_aprime@)
+                },
+            decreases
+                n - i,
+        {
+            if self.coord.get_phase() != CoordPhase::Cleanup {
+                break;
+            }
+            if self.coord.is_unlock_target(i as u64) && !self.coord.has_unlocked(i as u64)
+                && !self.coord_recv_unlock_resp(i as u64) {
+                break;
+            }
+            i = i + 1;
+        }
+    }
+
+    // ============================================================
+    // STORE CRASH/RECOVERY
+    // ============================================================
+
+    /// Store crashes and recovers
+    /// The lock table is lost; a store that had voted yes (prepared) re-locks both
+    /// keys and keeps awaiting the coordinator's decision instead of aborting
+    pub fn store_crash_recover(&mut self, store_id: u64)
+        requires
+            old(self).spec_valid_store(store_id),
+        ensures
+            self.stores@.len() == old(self).stores@.len(),
+    {
+        let store_idx = store_id as usize;
+        let mut store = self.stores.remove(store_idx);
+
+        store.crash();
+        if store.prepared_txn.is_some() {
+            match &self.migration {
+                Some(m) => {
+                    store.lock(m.key.as_str());
+                },
+                None => {
+                    store.lock(self.key_a.as_str());
+                    store.lock(self.key_aprime.as_str());
+                },
+            }
+        }
+
+        self.stores.insert(store_idx, store);
+    }
+
+    // ============================================================
+    // CROSS-STORE MIGRATION
+    // ============================================================
+    //
+    // Moves `key` from `src` to `dst`. Safety: once `key` is unlocked at both
+    // stores, its value is at exactly one of them (see migration_exactly_one
+    // and lemma_migration_exactly_one). The transaction's participant set is
+    // {src, dst}, so coord_decide_commit, completion and cleanup cover only
+    // those two stores. The value moves in messages: src votes with a
+    // MigrateVote carrying it, and the coordinator hands it to dst in a
+    // MigrateApply.
+
+    /// Start migrating `key` from `src_store` to `dst_store`:
+    /// record the migration and send LockReq to both stores
+    pub fn coord_start_migration(&mut self, src_store: u64, dst_store: u64, key: &str)
+        requires
+            old(self).spec_valid_store(src_store),
+            old(self).spec_valid_store(dst_store),
+            src_store != dst_store,
+            old(self).coord.spec_phase() == CoordPhase::Idle,
+        ensures
+            self.coord.spec_phase() == CoordPhase::Preparing,
+            self.migration.is_some(),
+            self.coord.participants@ == old(self).coord.participants@.insert(src_store).insert(dst_store),
+            self.stores@.len() == old(self).stores@.len(),
+    {
+        self.migration = Some(ExecMigration {
+            src: src_store,
+            dst: dst_store,
+            key: key.to_owned(),
+            value: None,
+        });
+        self.coord.add_participant(src_store);
+        self.coord.add_participant(dst_store);
+        self.coord_send_lock_req(src_store);
+        self.coord_send_lock_req(dst_store);
+    }
+
+    /// Store handles a migration LockReq
+    /// src votes yes if it holds `key`, dst votes yes if `key` is absent there;
+    /// a yes vote locks `key`. As for a rename, a frozen store, a `key` held
+    /// outside this txn or a txn already released votes no. src votes with a
+    /// MigrateVote carrying the value it will hand over, dst (and any no
+    /// vote) with a LockResp
+    /// Returns true if message was found and processed
+    pub fn store_handle_migration_lock_req(&mut self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            old(self).spec_valid_store(store_id),
+        ensures
+            self.stores@.len() == old(self).stores@.len(),
+            self.migration == old(self).migration,
+    {
+        let (src, dst, key) = match &self.migration {
+            Some(m) => (m.src, m.dst, m.key.clone()),
+            None => return false,
+        };
+        let expected_msg = ExecMessage::lock_req(store_id, txn_id);
+        if self.net.receive(&expected_msg).is_none() {
+            return false;
+        }
+
+        let store_idx = store_id as usize;
+        if self.stores[store_idx].is_stale_txn_id(txn_id) {
+            return true; // Message consumed but ignored (stale)
+        }
+
+        let mut store = self.stores.remove(store_idx);
+        store.update_txn_id(txn_id);
+
+        if store.is_released(txn_id) {
+            // The txn's UnlockReq got here first: refuse rather than re-lock
+            self.send(expected_msg.expected_response(false).unwrap());
+            self.stores.insert(store_idx, store);
+            return true;
+        }
+
+        let current = store.get(key.as_str());
+        let wanted = if store_id == src {
+            current.is_some()
+        } else {
+            store_id == dst && current.is_none()
+        };
+        let held_by_other = store.is_locked(key.as_str()) && !store.is_prepared(txn_id);
+        // The lock fails on a frozen store, which then votes no
+        let vote = wanted && !held_by_other && store.lock(key.as_str());
+        if vote {
+            store.prepare(txn_id);
+        }
+        match current {
+            Some(v) if vote && store_id == src => self.send(ExecMessage::migrate_vote(store_id, txn_id, v)),
+            _ => self.send(expected_msg.expected_response(vote).unwrap()),
+        }
+
+        self.stores.insert(store_idx, store);
+        true
+    }
+
+    /// Coordinator receives src's yes vote (MigrateVote): records the lock and
+    /// the value src will hand over
+    /// Returns true if message was found and processed
+    pub fn coord_recv_migration_vote(&mut self, store_id: u64) -> (result: bool)
+        requires
+            old(self).coord.spec_phase() == CoordPhase::Preparing,
+            !old(self).coord.spec_has_lock(store_id),
+        ensures
+            result ==> self.coord.spec_has_lock(store_id),
+            result ==> self.migration is Some && self.migration->Some_0.value is Some,
+            self.coord.spec_phase() == CoordPhase::Preparing,
+            self.stores@ == old(self).stores@,
+    {
+        let (src, dst, key) = match &self.migration {
+            Some(m) => (m.src, m.dst, m.key.clone()),
+            None => return false,
+        };
+        if store_id != src {
+            return false;
+        }
+        let txn_id = self.coord.get_txn_id();
+        let vote = match self.net.first_of_kind(MsgKind::MigrateVote, store_id, txn_id) {
+            Some(m) => m,
+            None => return false,
+        };
+        if self.net.receive(&vote).is_none() {
+            return false;
+        }
+        self.migration = Some(ExecMigration { src, dst, key, value: Some(vote.value()) });
+        self.coord.record_lock_success(store_id);
+        true
+    }
+
+    /// Coordinator sends the committed migration: MigrateApply with the value
+    /// src reported to dst, RenameReq (delete) to src
+    /// Returns false, sending nothing, if no value has been recorded
+    pub fn coord_send_migration_apply(&mut self) -> (result: bool)
+        requires
+            old(self).coord.spec_phase() == CoordPhase::Committed,
+        ensures
+            self.coord == old(self).coord,
+            self.stores@ == old(self).stores@,
+            result == (old(self).migration matches Some(m) && m.value is Some),
+    {
+        let (src, dst, value) = match &self.migration {
+            Some(m) => match m.value {
+                Some(v) => (m.src, m.dst, v),
+                None => return false,
+            },
+            None => return false,
+        };
+        let txn_id = self.coord.get_txn_id();
+        self.send(ExecMessage::migrate_apply(dst, txn_id, value));
+        self.send(ExecMessage::rename_req(src, txn_id));
+        true
+    }
+
+    /// Store applies a committed migration: dst inserts the value its
+    /// MigrateApply carries, src deletes `key` on RenameReq. Both write under
+    /// the transaction's own lock and are idempotent on re-delivery
+    /// Returns true if message was found and processed
+    pub fn store_handle_migration_apply_req(&mut self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            old(self).spec_valid_store(store_id),
+        ensures
+            self.stores@.len() == old(self).stores@.len(),
+    {
+        let (src, dst, key) = match &self.migration {
+            Some(m) => (m.src, m.dst, m.key.clone()),
+            None => return false,
+        };
+        let req = if store_id == dst {
+            match self.net.first_of_kind(MsgKind::MigrateApply, store_id, txn_id) {
+                Some(m) => m,
+                None => return false,
+            }
+        } else if store_id == src {
+            ExecMessage::rename_req(store_id, txn_id)
+        } else {
+            return false;
+        };
+        if self.net.receive(&req).is_none() {
+            return false;
+        }
+        let value = req.value();
+
+        let store_idx = store_id as usize;
+        if self.stores[store_idx].is_stale_txn_id(txn_id) {
+            return true; // Message consumed but ignored (stale)
+        }
+
+        let mut store = self.stores.remove(store_idx);
+        store.update_txn_id(txn_id);
+
+        let key_locked = store.is_locked(key.as_str());
+        let applied = if store_id == dst {
+            // Re-delivery rewrites the same value
+            store.multi_put_locked(vec![(key.clone(), value)])
+        } else if !store.contains_key(key.as_str()) {
+            true // Already deleted (idempotent)
+        } else {
+            key_locked && store.delete_locked(key.as_str())
+        };
+        if applied {
+            self.send(req.expected_response(true).unwrap());
+        }
+        // else: preconditions not met, no response
+
+        self.stores.insert(store_idx, store);
+        true
+    }
+
+    /// Store handles a migration UnlockReq: release `key`, acking only once
+    /// it is free (a frozen store keeps its lock)
+    /// Returns true if message was found and processed
+    pub fn store_handle_migration_unlock_req(&mut self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            old(self).spec_valid_store(store_id),
+        ensures
+            self.stores@.len() == old(self).stores@.len(),
+    {
+        let key = match &self.migration {
+            Some(m) => m.key.clone(),
+            None => return false,
+        };
+        let expected_msg = ExecMessage::unlock_req(store_id, txn_id);
+        if self.net.receive(&expected_msg).is_none() {
+            return false;
+        }
+
+        let store_idx = store_id as usize;
+        if self.stores[store_idx].is_stale_txn_id(txn_id) {
+            return true; // Message consumed but ignored (stale)
+        }
+
+        let mut store = self.stores.remove(store_idx);
+        store.update_txn_id(txn_id);
+        store.unlock(key.as_str());
+        if !store.is_locked(key.as_str()) {
+            store.clear_prepared();
+            store.release(txn_id);
+            self.send(expected_msg.expected_response(true).unwrap());
+        }
+
+        self.stores.insert(store_idx, store);
+        true
+    }
+
+    /// Coordinator receives a migration apply response (RenameResp);
+    /// moves to Cleanup once both participants, src and dst, have applied
+    /// Returns true if message was found and processed
+    pub fn coord_recv_migration_apply_resp(&mut self, store_id: u64) -> (result: bool)
+        requires
+            old(self).coord.spec_phase() == CoordPhase::Committed,
+            !old(self).coord.spec_has_renamed(store_id),
+        ensures
+            result ==> self.coord.spec_has_renamed(store_id),
+    {
+        let txn_id = self.coord.get_txn_id();
+        if self.net.receive(&ExecMessage::rename_resp(store_id, txn_id)).is_some() {
+            let num_stores = self.stores.len();
+            self.coord.record_rename_done(store_id, num_stores);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Coordinator receives a migration unlock response;
+    /// moves to Done once every unlock target among src and dst has acknowledged
+    /// Returns true if message was found and processed
+    pub fn coord_recv_migration_unlock_resp(&mut self, store_id: u64) -> (result: bool)
+        requires
+            old(self).coord.spec_phase() == CoordPhase::Cleanup,
+            !old(self).coord.spec_has_unlocked(store_id),
+        ensures
+            result ==> self.coord.spec_has_unlocked(store_id),
+    {
+        let txn_id = self.coord.get_txn_id();
+        if self.net.receive(&ExecMessage::unlock_resp(store_id, txn_id)).is_some() {
+            self.coord.record_unlock_acked(store_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Migration safety: the migrated key is held by exactly one of src and dst
+    /// (meaningful once neither store holds the key locked). False if no migration.
+    /// A runtime cross-check of lemma_migration_exactly_one, which proves it
+    /// for both the commit and the abort outcome
+    pub fn migration_exactly_one(&self) -> (result: bool) {
+        match &self.migration {
+            Some(m) => {
+                if m.src >= self.stores.len() as u64 || m.dst >= self.stores.len() as u64 {
+                    return false;
+                }
+                let at_src = self.stores[m.src as usize].contains_key(m.key.as_str());
+                let at_dst = self.stores[m.dst as usize].contains_key(m.key.as_str());
+                at_src != at_dst
+            },
+            None => false,
+        }
+    }
+
+    // ============================================================
+    // QUERY OPERATIONS
+    // ============================================================
+
+    /// Get the current phase of the coordinator
+    pub fn get_coord_phase(&self) -> (result: CoordPhase)
+        ensures
+            result == self.coord.spec_phase()
+    {
+        self.coord.get_phase()
+    }
+
+    /// Get the current transaction ID
+    pub fn get_txn_id(&self) -> (result: u64)
+        ensures
+            result as nat == self.coord.spec_txn_id()
+    {
+        self.coord.get_txn_id()
+    }
+
+    /// Check if coordinator is committed
+    pub fn is_committed(&self) -> (result: bool)
+        ensures
+            result == self.coord.spec_is_committed()
+    {
+        self.coord.is_committed()
+    }
+
+    /// The authoritative transaction outcome, as decided by the coordinator's
+    /// WAL and phase (see `Coordinator::decision`)
+    pub fn decision(&self) -> (result: Decision)
+        ensures
+            result.spec_phase() == self.coord@.decision(),
+            (result == Decision::Commit) == self.coord.spec_is_committed(),
+    {
+        self.coord.decision()
     }
 
     /// Get the number of stores
@@ -486,6 +1723,132 @@ impl ExecSystem {
         self.stores.len()
     }
 
+    /// Store ids taking part in the transaction (see `in_txn`), in id order:
+    /// every store not excluded, or only src and dst during a migration
+    pub fn participants(&self) -> (result: Vec<u64>)
+        ensures
+            forall|i: int| #![auto] 0 <= i < result@.len() ==> self.spec_valid_store(result@[i])
+                && self.coord.spec_in_txn(result@[i], self.stores@.len() as nat),
+            forall|s: u64| #![auto] self.spec_valid_store(s) && self.coord.spec_in_txn(s, self.stores@.len() as nat)
+                ==> result@.contains(s),
+            forall|i: int, j: int| 0 <= i < j < result@.len() ==> result@[i] < result@[j],
+    {
+        let num_stores = self.stores.len();
+        let mut result: Vec<u64> = Vec::new();
+        let mut i: usize = 0;
+        while i < num_stores
+            invariant
+                0 <= i <= num_stores,
+                num_stores == self.stores@.len(),
+                forall|j: int| #![auto] 0 <= j < result@.len() ==> result@[j] < i as u64
+                    && self.coord.spec_in_txn(result@[j], num_stores as nat),
+                forall|s: u64| #![auto] s < i as u64 && self.coord.spec_in_txn(s, num_stores as nat)
+                    ==> result@.contains(s),
+                forall|j: int, l: int| 0 <= j < l < result@.len() ==> result@[j] < result@[l],
+            decreases
+                num_stores - i,
+        {
+            if self.coord.in_txn(i as u64, num_stores) {
+                result.push(i as u64);
+            }
+            i = i + 1;
+        }
+        result
+    }
+
+    /// Participants that still owe the coordinator a response in the current phase:
+    /// no lock grant yet (Preparing) or no rename ack (Committed) from a store
+    /// taking part in the transaction, or no unlock ack from an unlock target
+    /// (Cleanup). Empty in Idle, Done and Crashed; never an excluded store.
+    /// Before cleanup only stores in `participants()` are pending.
+    /// These are the stores a driver should resend to.
+    pub fn pending_participants(&self) -> (result: Vec<u64>)
+        ensures
+            forall|i: int| #![auto] 0 <= i < result@.len() ==> self.spec_valid_store(result@[i]),
+            forall|i: int| #![auto] 0 <= i < result@.len() ==> !self.coord.excluded@.contains(result@[i]),
+            self.coord.spec_phase() != CoordPhase::Cleanup ==> forall|i: int| #![auto] 0 <= i < result@.len()
+                ==> self.coord.spec_in_txn(result@[i], self.stores@.len() as nat),
+    {
+        let phase = self.coord.get_phase();
+        let mut result: Vec<u64> = Vec::new();
+        let mut i: usize = 0;
+        while i < self.stores.len()
+            invariant
+                0 <= i <= self.stores.len(),
+                forall|j: int| #![auto] 0 <= j < result@.len() ==> self.spec_valid_store(result@[j]),
+                forall|j: int| #![auto] 0 <= j < result@.len() ==> !self.coord.excluded@.contains(result@[j]),
+                phase == self.coord.spec_phase(),
+                phase != CoordPhase::Cleanup ==> forall|j: int| #![auto] 0 <= j < result@.len()
+                    ==> self.coord.spec_in_txn(result@[j], self.stores@.len() as nat),
+            decreases
+                self.stores.len() - i,
+        {
+            let store = i as u64;
+            let excluded = self.coord.is_excluded(store);
+            let in_txn = self.coord.in_txn(store, self.stores.len());
+            let pending = !excluded && match phase {
+                CoordPhase::Preparing => in_txn && !self.coord.has_lock(store),
+                CoordPhase::Committed => in_txn && !self.coord.has_renamed(store),
+                CoordPhase::Cleanup =>
+                    self.coord.is_unlock_target(store) && !self.coord.has_unlocked(store),
+                _ => false,
+            };
+            if pending {
+                result.push(store);
+            }
+            i = i + 1;
+        }
+        result
+    }
+
+    /// Number of messages currently in flight
+    pub fn num_inflight(&self) -> (result: usize)
+        ensures
+            result == self.net.messages@.len(),
+    {
+        self.net.len()
+    }
+
+    /// Sum of all stored values across every store, saturating at `u64::MAX`.
+    /// The rename phase only moves values between keys, so the happy path leaves
+    /// this unchanged.
+    pub fn total_value_sum(&self) -> (result: u64)
+        ensures
+            result as nat == if self.spec_total_value_sum() <= u64::MAX as nat {
+                self.spec_total_value_sum()
+            } else {
+                u64::MAX as nat
+            },
+    {
+        let mut sum: u64 = 0;
+        let mut i: usize = 0;
+        while i < self.stores.len()
+            invariant
+                0 <= i <= self.stores.len(),
+                sum as nat == if self.spec_value_sum_prefix(i as int) <= u64::MAX as nat {
+                    self.spec_value_sum_prefix(i as int)
+                } else {
+                    u64::MAX as nat
+                },
+            decreases
+                self.stores.len() - i,
+        {
+            let v = self.stores[i].value_sum();
+            sum = if v <= u64::MAX - sum { sum + v } else { u64::MAX };
+            i = i + 1;
+        }
+        sum
+    }
+
+    /// Messages the protocol should have in flight once every pending participant
+    /// has been sent the current phase's request: exactly one per pending
+    /// participant (its request or its response). At such points a different
+    /// `num_inflight` means a message was lost, duplicated or leaked, e.g.
+    /// `debug_assert_eq!(sys.num_inflight(), sys.expected_inflight_for_phase())`.
+    pub fn expected_inflight_for_phase(&self) -> (result: usize) {
+        self.pending_participants().len()
+    }
+
     /// Check if a store has the source key
     pub fn store_has_key_a(&self, store_id: u64) -> (result: bool)
         requires
@@ -494,6 +1857,14 @@ impl ExecSystem {
         self.stores[store_id as usize].contains_key(self.key_a.as_str())
     }
 
+    /// Check if a store has voted yes for `txn_id` and awaits its decision
+    pub fn store_is_prepared(&self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            self.spec_valid_store(store_id),
+    {
+        self.stores[store_id as usize].is_prepared(txn_id)
+    }
+
     /// Check if a store has the destination key
     pub fn store_has_key_aprime(&self, store_id: u64) -> (result: bool)
         requires
@@ -526,7 +1897,17 @@ impl ExecSystem {
         self.net.is_empty()
     }
 
+    /// Check if a transaction has no messages left in flight
+    pub fn net_is_settled_for_txn(&self, txn_id: u64) -> (result: bool)
+        ensures
+            result == (self.net.spec_count_txn(txn_id as nat) == 0)
+    {
+        self.net.is_settled_for_txn(txn_id)
+    }
+
     /// Directly put a value into a store (for testing)
+    /// Unguarded: bypasses all protocol checks, so it can build states the protocol
+    /// never reaches. Prefer `inject_store_value_guarded` for fault injection.
     pub fn store_put(&mut self, store_id: u64, key: &str, value: u64)
         requires
             old(self).spec_valid_store(store_id),
@@ -539,6 +1920,65 @@ impl ExecSystem {
         self.stores.insert(store_idx, store);
     }
 
+    /// Fault injection: put a value into a store only if it has already seen `min_txn`
+    /// Keeps injected writes causally after `min_txn`, respecting the txn-id
+    /// monotonicity the proofs assume. Returns true if the write happened
+    /// (the guard passed and the key was not locked).
+    pub fn inject_store_value_guarded(
+        &mut self,
+        store_id: u64,
+        key: &str,
+        value: u64,
+        min_txn: u64,
+    ) -> (result: bool)
+        requires
+            old(self).spec_valid_store(store_id),
+        ensures
+            self.stores@.len() == old(self).stores@.len(),
+            result ==> old(self).stores@[store_id as int].last_seen_txn_id >= min_txn,
+            old(self).stores@[store_id as int].last_seen_txn_id < min_txn ==> self.stores@ == old(self).stores@,
+    {
+        let store_idx = store_id as usize;
+        if self.stores[store_idx].get_last_seen_txn_id() < min_txn {
+            return false;
+        }
+        let mut store = self.stores.remove(store_idx);
+        let written = store.put(key, value);
+        self.stores.insert(store_idx, store);
+        written
+    }
+
+    /// Fault injection (for testing): replace the network contents wholesale
+    /// Bypasses the protocol entirely, so the result may hold messages no run
+    /// would produce. Messages get fresh sequence numbers in `msgs` order.
+    pub fn set_network(&mut self, msgs: Vec<ExecMessage>)
+        ensures
+            self.net.messages@.len() == msgs@.len(),
+            forall|i: int| #![auto] 0 <= i < msgs@.len() ==> self.net.messages@[i]@ == msgs@[i]@,
+            self.net.spec_wf(),
+            self.net.per_store_cap is None,
+            self.coord == old(self).coord,
+            self.stores@ == old(self).stores@,
+    {
+        let mut net = ExecNetwork::new();
+        let mut i: usize = 0;
+        while i < msgs.len()
+            invariant
+                0 <= i <= msgs.len(),
+                net.messages@.len() == i,
+                forall|j: int| #![auto] 0 <= j < i ==> net.messages@[j]@ == msgs@[j]@,
+                net.spec_wf(),
+                net.per_store_cap is None,
+                net.next_seq == i,
+            decreases
+                msgs.len() - i,
+        {
+            net.send(msgs[i].clone());
+            i = i + 1;
+        }
+        self.net = net;
+    }
+
     /// Update txn_id for a store (for testing)
     pub fn store_update_txn_id(&mut self, store_id: u64, txn_id: u64)
         requires
@@ -559,8 +1999,493 @@ impl ExecSystem {
     {
         self.stores[store_id as usize].is_stale_txn_id(txn_id)
     }
+
+    // ============================================================
+    // INVARIANT CHECKING
+    // ============================================================
+
+    /// Runtime check of the key system invariants, for debug_assert! and tests.
+    ///
+    /// Checks that:
+    /// - every store holds exactly one of key_a / key_aprime (data_accessible)
+    /// - no store holds a lock on either key once the coordinator is Done
+    /// - the phase agrees with the WAL: Idle/Preparing before the commit point,
+    ///   Committed only after it
+    ///
+    /// Returns a description of the first violation found. Trusted (external_body)
+    /// since it only builds diagnostic strings.
+    #[verifier::external_body]
+    pub fn check_invariant(&self) -> Result<(), String> {
+        for (i, store) in self.stores.iter().enumerate() {
+            let has_a = store.contains_key(&self.key_a);
+            let has_aprime = store.contains_key(&self.key_aprime);
+            if has_a == has_aprime {
+                return Err(format!(
+                    "store {}: data not accessible (has {}: {}, has {}: {})",
+                    i, self.key_a, has_a, self.key_aprime, has_aprime
+                ));
+            }
+            if self.coord.phase == CoordPhase::Done
+                && (store.is_locked(&self.key_a) || store.is_locked(&self.key_aprime))
+            {
+                return Err(format!("store {}: still locked after coordinator reached Done", i));
+            }
+        }
+        match self.coord.phase {
+            CoordPhase::Idle | CoordPhase::Preparing if self.coord.wal_committed => Err(format!(
+                "coordinator in {:?} but COMMIT is already in the WAL",
+                self.coord.phase
+            )),
+            CoordPhase::Committed if !self.coord.wal_committed => {
+                Err("coordinator in Committed without COMMIT in the WAL".to_owned())
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+// ============================================================
+// CLIENT API
+// ============================================================
+
+/// Error returned by `client_rename` when the rename does not commit
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ProtocolError {
+    /// A store refused the lock; the coordinator aborted and released the locks it held
+    Aborted,
+    /// The protocol could not reach Done (e.g., an expected response never arrived)
+    Stuck,
+}
+
+/// Report `Stuck` from `client_rename`, first releasing the transaction's locks
+/// if it has not committed: the coordinator aborts and unlocks every store it
+/// asked to lock. After a commit the locks stay, since the pending renames
+/// still need them
+fn give_up(sys: &mut ExecSystem) -> (result: Result<(), ProtocolError>)
+    ensures
+        result == Err::<(), ProtocolError>(ProtocolError::Stuck),
+{
+    let phase = sys.get_coord_phase();
+    let can_abort = !sys.is_committed()
+        && phase != CoordPhase::Idle
+        && phase != CoordPhase::Done
+        && phase != CoordPhase::Committed
+        && (phase != CoordPhase::Crashed || sys.get_txn_id() < u64::MAX);
+    if can_abort {
+        sys.abort_transaction();
+    }
+    Err(ProtocolError::Stuck)
+}
+
+/// Run the full rename protocol on `sys` to completion.
+///
+/// Drives every phase in order (lock, commit, rename, unlock) using the
+/// step functions of `ExecSystem`, delivering each message exactly once.
+/// Returns `Ok(())` if the coordinator reaches `Done` with the rename committed,
+/// `Err(ProtocolError::Aborted)` if a store refused the lock, and
+/// `Err(ProtocolError::Stuck)` if the protocol could not make progress.
+pub fn client_rename(sys: &mut ExecSystem) -> (result: Result<(), ProtocolError>)
+    requires
+        old(sys).coord.spec_phase() == CoordPhase::Idle,
+    ensures
+        result is Ok ==> sys.coord.spec_phase() == CoordPhase::Done,
+        result is Ok ==> sys.coord.spec_is_committed(),
+{
+    let n = sys.num_stores() as u64;
+    let txn_id = sys.get_txn_id();
+
+    // Phase 1: send LockReq to every store and let it vote
+    let mut i: u64 = 0;
+    while i < n
+        decreases n - i,
+    {
+        match sys.get_coord_phase() {
+            CoordPhase::Idle | CoordPhase::Preparing => {},
+            _ => return give_up(sys),
+        }
+        if !sys.is_valid_store(i) {
+            return give_up(sys);
+        }
+        sys.coord_send_lock_req(i);
+        sys.store_handle_lock_req(i, txn_id);
+        i = i + 1;
+    }
+
+    // Collect votes; every "yes" is recorded before acting on a "no" so that
+    // cleanup knows exactly which stores hold locks
+    let mut refused_by: Option<u64> = None;
+    let mut i: u64 = 0;
+    while i < n
+        decreases n - i,
+    {
+        match sys.get_coord_phase() {
+            CoordPhase::Preparing => {},
+            _ => return give_up(sys),
+        }
+        if !sys.coord.has_lock(i) && !sys.coord_recv_lock_resp_success(i) {
+            if !sys.net.contains(&ExecMessage::lock_resp(i, false, txn_id)) {
+                return give_up(sys);
+            }
+            if refused_by.is_none() {
+                refused_by = Some(i);
+            }
+        }
+        i = i + 1;
+    }
+
+    // A single "no" aborts the transaction
+    let aborted = refused_by.is_some();
+    if let Some(refusing) = refused_by {
+        match sys.get_coord_phase() {
+            CoordPhase::Preparing => {
+                if !sys.coord_recv_lock_resp_failure(refusing) {
+                    return give_up(sys);
+                }
+            },
+            _ => return give_up(sys),
+        }
+    }
+
+    if !aborted {
+        match sys.get_coord_phase() {
+            CoordPhase::Preparing if sys.coord.can_commit(sys.num_stores()) => sys.coord_decide_commit(),
+            _ => return give_up(sys),
+        }
+
+        // Phase 2: rename at every store
+        let mut i: u64 = 0;
+        while i < n
+            decreases n - i,
+        {
+            match sys.get_coord_phase() {
+                CoordPhase::Committed => {},
+                _ => return give_up(sys),
+            }
+            if !sys.is_valid_store(i) {
+                return give_up(sys);
+            }
+            sys.coord_send_rename_req(i);
+            sys.store_handle_rename_req(i, txn_id);
+            i = i + 1;
+        }
+
+        let mut i: u64 = 0;
+        while i < n
+            decreases n - i,
+        {
+            match sys.get_coord_phase() {
+                CoordPhase::Committed => {},
+                _ => return give_up(sys),
+            }
+            if !sys.coord.has_renamed(i) && !sys.coord_recv_rename_resp(i) {
+                return give_up(sys);
+            }
+            i = i + 1;
+        }
+    }
+
+    // Cleanup: release locks at every store sent a LockReq
+    let mut i: u64 = 0;
+    while i < n
+        decreases n - i,
+    {
+        match sys.get_coord_phase() {
+            CoordPhase::Cleanup => {},
+            _ => return give_up(sys),
+        }
+        if !sys.coord.is_unlock_target(i) {
+            i = i + 1;
+            continue;
+        }
+        if !sys.is_valid_store(i) {
+            return give_up(sys);
+        }
+        sys.coord_send_unlock_req(i);
+        sys.store_handle_unlock_req(i, txn_id);
+        i = i + 1;
+    }
+
+    let mut i: u64 = 0;
+    while i < n
+        decreases n - i,
+    {
+        // The last target's ack moves the coordinator to Done, so only
+        // check the phase when there is an ack left to receive
+        if sys.coord.is_unlock_target(i) && !sys.coord.has_unlocked(i) {
+            match sys.get_coord_phase() {
+                CoordPhase::Cleanup => {},
+                _ => return give_up(sys),
+            }
+            if !sys.coord_recv_unlock_resp(i) {
+                return give_up(sys);
+            }
+        }
+        i = i + 1;
+    }
+
+    match sys.get_coord_phase() {
+        CoordPhase::Done => {
+            if sys.is_committed() {
+                Ok(())
+            } else {
+                Err(ProtocolError::Aborted)
+            }
+        },
+        _ => give_up(sys),
+    }
 }
 
+} // verus!
+
+// ============================================================
+// POLICY-DRIVEN SIMULATION (plain Rust, outside verus!)
+// ============================================================
+
+impl Default for SentRequests {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Error returned by `ExecSystem::run_until_quiescent`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum QuiescenceError {
+    /// Progress actions were still enabled after `max_steps` steps
+    StepLimit,
+    /// No progress action is enabled, yet the coordinator is not Done
+    Deadlock,
+}
+
+/// Likely cause of a stalled protocol, from `ExecSystem::detect_deadlock`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DeadlockKind {
+    /// The coordinator awaits a response, but nothing is in flight and every
+    /// request it could send has already gone out once, so one was lost
+    WaitingOnLostMessage,
+    /// The coordinator is Done (committed or aborted), yet some store still
+    /// holds a lock on either key: an orphan lock nobody will release
+    AllStoresLocked,
+}
+
+impl ExecSystem {
+    /// Install `model` as the network's fault policy: from now on every message
+    /// the protocol sends (coordinator requests and store responses alike, by
+    /// hand or from a driver) is passed to it and its operations are applied
+    /// immediately. Returns the model it replaces, if any
+    pub fn set_model(&mut self, model: Box<dyn NetworkModel>) -> Option<Box<dyn NetworkModel>> {
+        self.model.model.replace(model)
+    }
+
+    /// Remove the installed model, so sends are delivered as is again
+    pub fn clear_model(&mut self) -> Option<Box<dyn NetworkModel>> {
+        self.model.model.take()
+    }
+
+    /// Run the rename protocol to completion over a network whose faults are
+    /// chosen by `model` rather than by hand-placed `net_lose`/`net_duplicate` calls.
+    ///
+    /// Each round re-sends the current phase's request to every store that still
+    /// owes a response, then pumps every store until no message is pending.
+    /// `model` is installed for the run (see `set_model`), so it sees every send,
+    /// and the previously installed model is restored afterwards.
+    /// Requests are idempotent at the stores, so lost messages are recovered by
+    /// the next round's retry. Returns as `client_rename` does, or
+    /// `Err(ProtocolError::Stuck)` if the protocol is not Done after `max_rounds`.
+    pub fn run_with_model(
+        &mut self,
+        model: Box<dyn NetworkModel>,
+        max_rounds: usize,
+    ) -> Result<(), ProtocolError> {
+        let previous = self.set_model(model);
+        let n = self.num_stores() as u64;
+        for _ in 0..max_rounds {
+            match self.get_coord_phase() {
+                CoordPhase::Done | CoordPhase::Crashed => break,
+                CoordPhase::Preparing if self.coord.can_commit(self.num_stores()) => {
+                    self.coord_decide_commit();
+                    continue;
+                },
+                CoordPhase::Idle | CoordPhase::Preparing => {
+                    for s in 0..n {
+                        if !self.coord.is_excluded(s) && !self.coord.has_lock(s) {
+                            self.coord_send_lock_req(s);
+                        }
+                    }
+                },
+                CoordPhase::Committed => {
+                    for s in 0..n {
+                        if !self.coord.is_excluded(s) && !self.coord.has_renamed(s) {
+                            self.coord_send_rename_req(s);
+                        }
+                    }
+                },
+                CoordPhase::Cleanup => {
+                    for s in 0..n {
+                        if self.coord.is_unlock_target(s) && !self.coord.has_unlocked(s) {
+                            self.coord_send_unlock_req(s);
+                        }
+                    }
+                },
+            }
+
+            // Deliver everything in flight
+            let mut progressed = true;
+            while progressed {
+                progressed = false;
+                for s in 0..n {
+                    progressed |= self.pump(s);
+                }
+            }
+        }
+        self.model.model = previous;
+
+        match self.get_coord_phase() {
+            CoordPhase::Done if self.is_committed() => Ok(()),
+            CoordPhase::Done => Err(ProtocolError::Aborted),
+            _ => Err(ProtocolError::Stuck),
+        }
+    }
+
+    /// Drive the protocol one progress action at a time until none is enabled.
+    ///
+    /// Only progress actions are taken, never faults: deliver the oldest pending
+    /// message of some store, decide commit once every lock is held, or send the
+    /// current phase's request to a store that still owes a response and has
+    /// never been sent one at this txn id. Requests are not retried, so a lost
+    /// message leaves the protocol without an enabled action. Returns the final
+    /// phase once quiescent in Done, `Err(QuiescenceError::Deadlock)` if quiescent
+    /// in any other phase, and `Err(QuiescenceError::StepLimit)` if an action is
+    /// still enabled after `max_steps` steps.
+    pub fn run_until_quiescent(&mut self, max_steps: usize) -> Result<CoordPhase, QuiescenceError> {
+        for _ in 0..max_steps {
+            if !self.progress_step() {
+                return match self.get_coord_phase() {
+                    CoordPhase::Done => Ok(CoordPhase::Done),
+                    _ => Err(QuiescenceError::Deadlock),
+                };
+            }
+        }
+        if self.clone_state().progress_step() {
+            Err(QuiescenceError::StepLimit)
+        } else if self.get_coord_phase() == CoordPhase::Done {
+            Ok(CoordPhase::Done)
+        } else {
+            Err(QuiescenceError::Deadlock)
+        }
+    }
+
+    /// Drive the protocol with progress actions (as `run_until_quiescent` does),
+    /// crashing the coordinator the first time it is in `crash_phase` and
+    /// recovering it at once; the recovered coordinator re-drives its resumed
+    /// phase at the new txn id. A phase that cannot crash is never crashed in.
+    /// Returns the phase once no progress action is enabled (Done unless a
+    /// message was lost)
+    pub fn run_with_crash_at(&mut self, crash_phase: CoordPhase) -> CoordPhase {
+        let mut crashed = !crash_phase.can_crash();
+        loop {
+            if !crashed && self.get_coord_phase() == crash_phase {
+                self.coord_crash();
+                self.coord_recover();
+                crashed = true;
+            }
+            if !self.progress_step() {
+                return self.get_coord_phase();
+            }
+        }
+    }
+
+    /// Apply the first enabled progress action; returns false if none is enabled
+    fn progress_step(&mut self) -> bool {
+        let n = self.num_stores() as u64;
+        for s in 0..n {
+            if self.pump(s) {
+                return true;
+            }
+        }
+
+        let txn_id = self.get_txn_id();
+        match self.get_coord_phase() {
+            CoordPhase::Preparing if self.coord.can_commit(self.num_stores()) => {
+                self.coord_decide_commit();
+                return true;
+            },
+            CoordPhase::Idle | CoordPhase::Preparing => {
+                for s in 0..n {
+                    if !self.coord.is_excluded(s) && !self.coord.has_lock(s)
+                        && !self.was_sent(&ExecMessage::lock_req(s, txn_id)) {
+                        self.coord_send_lock_req(s);
+                        return true;
+                    }
+                }
+            },
+            CoordPhase::Committed => {
+                for s in 0..n {
+                    if !self.coord.is_excluded(s) && !self.coord.has_renamed(s)
+                        && !self.was_sent(&ExecMessage::rename_req(s, txn_id)) {
+                        self.coord_send_rename_req(s);
+                        return true;
+                    }
+                }
+            },
+            CoordPhase::Cleanup => {
+                for s in 0..n {
+                    if self.coord.is_unlock_target(s) && !self.coord.has_unlocked(s)
+                        && !self.was_sent(&ExecMessage::unlock_req(s, txn_id)) {
+                        self.coord_send_unlock_req(s);
+                        return true;
+                    }
+                }
+            },
+            CoordPhase::Done | CoordPhase::Crashed => {},
+        }
+        false
+    }
+
+    /// Diagnose a hung protocol: None while some progress action (as taken by
+    /// `run_until_quiescent`) is enabled, after a clean finish, or while the
+    /// coordinator is crashed and awaiting recovery; otherwise the likely cause
+    pub fn detect_deadlock(&self) -> Option<DeadlockKind> {
+        if self.clone_state().progress_step() {
+            return None;
+        }
+        match self.get_coord_phase() {
+            CoordPhase::Done => {
+                let orphaned = self.stores.iter().any(|store| {
+                    store.is_locked(&self.key_a) || store.is_locked(&self.key_aprime)
+                });
+                orphaned.then_some(DeadlockKind::AllStoresLocked)
+            },
+            CoordPhase::Crashed => None,
+            _ => Some(DeadlockKind::WaitingOnLostMessage),
+        }
+    }
+
+    /// Whether the request `msg` has been sent (see `SentRequests`)
+    fn was_sent(&self, msg: &ExecMessage) -> bool {
+        self.sent.sent.contains(msg)
+    }
+}
+
+/// Apply `model`'s operations for `msg`, just sent on `net`
+fn apply_model(net: &mut ExecNetwork, model: &mut dyn NetworkModel, msg: &ExecMessage) {
+    for op in model.on_send(msg) {
+        match op {
+            NetOp::Send(m) => net.send(m),
+            NetOp::Deliver(m) => {
+                net.receive(&m);
+            },
+            NetOp::Lose(m) => {
+                net.lose(&m);
+            },
+            NetOp::Duplicate(m) => {
+                net.duplicate(&m);
+            },
+        }
+    }
+}
+
+verus! {
+
 // ============================================================
 // UNIT TESTS
 // ============================================================
@@ -659,6 +2584,176 @@ mod tests {
         assert(sys.get_coord_phase() == CoordPhase::Cleanup);
     }
 
+    /// Test: Lock failure decided by a custom detection key
+    fn test_lock_failure_custom_detect_key() {
+        let mut sys = ExecSystem::new(1, "A", "A'", 42);
+        let txn_id = sys.get_txn_id();
+
+        // "B" exists, but A' does not
+        sys.store_put(0, "B", 7);
+
+        sys.coord_send_lock_req(0);
+
+        // Store treats "B" as the "already renamed" marker - lock fails
+        assert(sys.store_handle_lock_req_with_detect_key(0, txn_id, "B"));
+        assert(sys.coord_recv_lock_resp_failure(0));
+        assert(sys.get_coord_phase() == CoordPhase::Cleanup);
+    }
+
+    /// Test: Response from a non-participant store is never credited
+    fn test_recv_from_out_of_range_store() {
+        let mut sys = ExecSystem::new(2, "A", "A'", 42);
+        sys.coord_send_lock_req(0);
+        let txn_id = sys.get_txn_id();
+
+        // Inject a success response from store 5, which does not exist
+        sys.net.send(ExecMessage::lock_resp(5, true, txn_id));
+
+        assert(!sys.coord_recv_lock_resp_success(5));
+        assert(!sys.coord.has_lock(5));
+    }
+
+    /// Test: client_rename runs the happy path to completion
+    fn test_client_rename_success() {
+        let mut sys = ExecSystem::new(3, "A", "A'", 42);
+        let result = client_rename(&mut sys);
+        assert(result is Ok);
+        assert(sys.get_coord_phase() == CoordPhase::Done);
+        assert(sys.is_committed());
+    }
+
+    /// Test: client_rename reports an abort when a store refuses the lock
+    fn test_client_rename_aborted() {
+        let mut sys = ExecSystem::new(2, "A", "A'", 42);
+        sys.store_put(1, "A'", 99);
+        let result = client_rename(&mut sys);
+        assert(result == Err::<(), ProtocolError>(ProtocolError::Aborted));
+    }
+
+    /// Test: Abort unlocks every store sent a LockReq, even one whose grant
+    /// was not yet received
+    fn test_abort_unlocks_late_grant() {
+        let mut sys = ExecSystem::new(3, "A", "A'", 42);
+        let txn_id = sys.get_txn_id();
+        sys.store_put(2, "A'", 99);
+
+        sys.coord_send_lock_req(0);
+        sys.coord_send_lock_req(1);
+        sys.coord_send_lock_req(2);
+        sys.store_handle_lock_req(0, txn_id);
+        sys.store_handle_lock_req(1, txn_id);
+        sys.store_handle_lock_req(2, txn_id);
+
+        assert(sys.coord_recv_lock_resp_success(0));
+        assert(sys.coord_recv_lock_resp_failure(2));
+
+        // Store 1's grant is still in flight, yet it is an unlock target
+        assert(!sys.coord.has_lock(1));
+        assert(sys.coord.is_unlock_target(0));
+        assert(sys.coord.is_unlock_target(1));
+        assert(sys.coord.is_unlock_target(2));
+
+        sys.coord_send_unlock_req(1);
+        assert(sys.net.contains(&ExecMessage::unlock_req(1, txn_id)));
+    }
+
+    /// Test: A lost LockReq is replaced by a retry and the lock still succeeds
+    fn test_lock_retry_after_loss() {
+        let mut sys = ExecSystem::new(1, "A", "A'", 42);
+        let txn_id = sys.get_txn_id();
+        let req = ExecMessage::lock_req(0, txn_id);
+
+        sys.coord_send_lock_with_retry(0, 1);
+        assert(sys.net_lose(&req));
+        assert(!sys.store_handle_lock_req(0, txn_id));
+
+        // Retry tops the request back up
+        sys.coord_send_lock_with_retry(0, 1);
+        assert(sys.store_handle_lock_req(0, txn_id));
+        assert(sys.coord_recv_lock_resp_success(0));
+    }
+
+    /// Test: A prepared store that crashes keeps its locks and can still commit
+    fn test_prepared_store_crash_recovery() {
+        let mut sys = ExecSystem::new(1, "A", "A'", 42);
+        let txn_id = sys.get_txn_id();
+
+        sys.coord_send_lock_req(0);
+        sys.store_handle_lock_req(0, txn_id);
+        assert(sys.store_is_prepared(0, txn_id));
+
+        sys.store_crash_recover(0);
+        assert(sys.store_is_prepared(0, txn_id));
+        assert(sys.get_store(0).is_locked("A"));
+    }
+
+    /// Test: Migrate a key from store 0 to store 1
+    fn test_migration() {
+        let mut sys = ExecSystem::new(2, "A", "A'", 42);
+        sys.store_put(0, "K", 7);
+        let txn_id = sys.get_txn_id();
+
+        sys.coord_start_migration(0, 1, "K");
+        assert(sys.store_handle_migration_lock_req(0, txn_id));
+        assert(sys.store_handle_migration_lock_req(1, txn_id));
+        assert(sys.coord_recv_migration_vote(0));
+        assert(sys.coord_recv_lock_resp_success(1));
+        sys.coord_decide_commit();
+
+        assert(sys.coord_send_migration_apply());
+        assert(sys.store_handle_migration_apply_req(1, txn_id));
+        assert(sys.store_handle_migration_apply_req(0, txn_id));
+        assert(sys.migration_exactly_one());
+    }
+
+    /// Test: set_network replaces whatever was in flight
+    fn test_set_network() {
+        let mut sys = ExecSystem::new(2, "A", "A'", 42);
+        sys.coord_send_lock_req(0);
+        let mut msgs: Vec<ExecMessage> = Vec::new();
+        msgs.push(ExecMessage::lock_resp(0, true, 1));
+        msgs.push(ExecMessage::lock_resp(1, true, 1));
+        sys.set_network(msgs);
+        assert(sys.net.messages@.len() == 2);
+        assert(sys.net.messages@[1]@ == ExecMessage::lock_resp(1, true, 1)@);
+    }
+
+    /// Test: Guarded injection only writes to stores that have seen the txn
+    fn test_inject_store_value_guarded() {
+        let mut sys = ExecSystem::new(1, "A", "A'", 42);
+        assert(!sys.inject_store_value_guarded(0, "B", 7, 1));
+
+        sys.store_update_txn_id(0, 1);
+        assert(sys.inject_store_value_guarded(0, "B", 7, 1));
+    }
+
+    /// Test: A response swapped to another store id is not credited to the expected store
+    fn test_swapped_response_not_credited() {
+        let mut sys = ExecSystem::new(2, "A", "A'", 42);
+        sys.coord_send_lock_req(1);
+        let txn_id = sys.get_txn_id();
+        sys.store_handle_lock_req(1, txn_id);
+
+        // Store 0 answers in place of store 1
+        assert(sys.net_swap_response(1, 0));
+
+        assert(!sys.coord_recv_lock_resp_success(1));
+        assert(!sys.coord.has_lock(1));
+        assert(!sys.coord.has_lock(0));
+    }
+
+    /// Test: Pump delivers one message at a time
+    fn test_pump_single_step() {
+        let mut sys = ExecSystem::new(1, "A", "A'", 42);
+        assert(!sys.pump(0));
+
+        sys.coord_send_lock_req(0);
+        assert(sys.pump(0)); // store handles LockReq
+        assert(sys.pump(0)); // coordinator consumes LockResp
+        assert(sys.coord.has_lock(0));
+        assert(!sys.pump(0));
+    }
+
     /// Test: Crash and recovery (committed)
     fn test_crash_recovery_committed() {
         let mut sys = ExecSystem::new(1, "A", "A'", 42);
@@ -684,6 +2779,40 @@ mod tests {
         assert(sys.get_coord_phase() == CoordPhase::Committed); // Resume commit
     }
 
+    /// Test: Aborting mid-Preparing runs cleanup without committing
+    fn test_abort_transaction_mid_preparing() {
+        let mut sys = ExecSystem::new(3, "A", "A'", 42);
+        let txn_id = sys.get_txn_id();
+        sys.coord_send_lock_req(0);
+        sys.coord_send_lock_req(1);
+        sys.store_handle_lock_req(0, txn_id);
+        sys.coord_recv_lock_resp_success(0);
+
+        sys.abort_transaction();
+        assert(!sys.is_committed());
+        let phase = sys.get_coord_phase();
+        assert(phase == CoordPhase::Cleanup || phase == CoordPhase::Done);
+    }
+
+    /// Test: After recovery, the resumed phase's requests go out at the new txn id
+    fn test_coord_resend_phase_after_recovery() {
+        let mut sys = ExecSystem::new(2, "A", "A'", 42);
+        let txn_id = sys.get_txn_id();
+        sys.coord_send_lock_req(0);
+        sys.coord_send_lock_req(1);
+        sys.store_handle_lock_req(0, txn_id);
+        sys.store_handle_lock_req(1, txn_id);
+        sys.coord_recv_lock_resp_success(0);
+        sys.coord_recv_lock_resp_success(1);
+        sys.coord_decide_commit();
+
+        sys.coord_crash();
+        sys.coord_recover();
+        sys.coord_resend_phase();
+        assert(sys.net.contains(&ExecMessage::rename_req(0, txn_id + 1)));
+        assert(sys.net.contains(&ExecMessage::rename_req(1, txn_id + 1)));
+    }
+
     /// Test: Crash and recovery (not committed)
     fn test_crash_recovery_not_committed() {
         let mut sys = ExecSystem::new(1, "A", "A'", 42);
@@ -705,6 +2834,22 @@ mod tests {
         assert(sys.get_coord_phase() == CoordPhase::Cleanup); // Go to cleanup
     }
 
+    /// Test: Pending participants are valid store ids
+    fn test_pending_participants() {
+        let mut sys = ExecSystem::new(2, "A", "A'", 42);
+        let txn_id = sys.get_txn_id();
+        let parts = sys.participants();
+        assert(parts@.contains(0u64) && parts@.contains(1u64));
+
+        sys.coord_send_lock_req(0);
+        sys.coord_send_lock_req(1);
+        sys.store_handle_lock_req(0, txn_id);
+        sys.coord_recv_lock_resp_success(0);
+
+        let pending = sys.pending_participants();
+        assert(forall|i: int| #![auto] 0 <= i < pending@.len() ==> pending@[i] < 2);
+    }
+
     /// Test: Network duplication
     fn test_network_duplication() {
         let mut sys = ExecSystem::new(1, "A", "A'", 42);
@@ -0,0 +1,994 @@
+// trace_v.rs - Trace recording and replay for ExecSystem
+//
+// This file contains:
+// - TraceAction: one ExecSystem operation together with its arguments
+// - TraceEvent: a recorded action and the coordinator phase it left behind
+// - TracedSystem: wrapper that records every operation applied to an ExecSystem
+// - replay: deterministically rebuild an ExecSystem from a recorded trace
+// - ExecSystem::run_schedule: apply a hand-written list of actions in order
+// - run_twice_identical: check that two systems built from one config reach
+//   the same full state under one schedule
+// - format_trace: render a recorded run and its network operations as a timeline
+//
+// This is testing tooling (e.g. for shrinking a failing fuzz run to a minimal
+// reproducer). Each wrapper is verified against the ExecSystem operation it
+// forwards to; only the trace push itself (`record`) is external_body.
+
+use vstd::prelude::*;
+
+use crate::coordinator_s::*;
+use crate::coordinator_v::*;
+use crate::kv_store_v::*;
+use crate::network_s::*;
+use crate::network_v::*;
+use crate::system_v::*;
+
+verus! {
+
+// ============================================================
+// TRACE EVENTS
+// ============================================================
+
+/// A single ExecSystem operation with its arguments
+#[derive(Debug)]
+pub enum TraceAction {
+    New { num_stores: usize, key_a: String, key_aprime: String, initial_value: u64 },
+    CoordSendLockReq { store_id: u64 },
+    CoordSendLockWithRetry { store_id: u64, attempts: u8 },
+    CoordSendRenameReq { store_id: u64 },
+    CoordSendUnlockReq { store_id: u64 },
+    CoordRecvLockRespSuccess { store_id: u64 },
+    CoordRecvLockRespFailure { store_id: u64 },
+    CoordDecideCommit,
+    CoordRecvRenameResp { store_id: u64 },
+    CoordRecvUnlockResp { store_id: u64 },
+    StoreHandleLockReq { store_id: u64, txn_id: u64 },
+    StoreHandleLockReqWithDetectKey { store_id: u64, txn_id: u64, detect_key: String },
+    StoreHandleRenameReq { store_id: u64, txn_id: u64 },
+    StoreHandleUnlockReq { store_id: u64, txn_id: u64 },
+    NetLose { msg: ExecMessage },
+    NetDuplicate { msg: ExecMessage },
+    NetSwapResponse { expected: u64, responder: u64 },
+    Pump { store_id: u64 },
+    CoordCrash,
+    CoordRecover,
+    StoreCrashRecover { store_id: u64 },
+    StorePut { store_id: u64, key: String, value: u64 },
+    InjectStoreValueGuarded { store_id: u64, key: String, value: u64, min_txn: u64 },
+    StoreUpdateTxnId { store_id: u64, txn_id: u64 },
+    ClientRename,
+    CoordStartMigration { src_store: u64, dst_store: u64, key: String },
+    StoreHandleMigrationLockReq { store_id: u64, txn_id: u64 },
+    CoordRecvMigrationVote { store_id: u64 },
+    CoordSendMigrationApply,
+    StoreHandleMigrationApplyReq { store_id: u64, txn_id: u64 },
+    StoreHandleMigrationUnlockReq { store_id: u64, txn_id: u64 },
+    CoordRecvMigrationApplyResp { store_id: u64 },
+    CoordRecvMigrationUnlockResp { store_id: u64 },
+}
+
+/// A recorded operation and the coordinator phase after it ran
+pub struct TraceEvent {
+    pub action: TraceAction,
+    pub phase: CoordPhase,
+    /// Whether the operation was enabled (its bool result; true for operations
+    /// that return none), so replay can tell when it diverges from the run
+    pub ok: bool,
+    /// Operations the network had logged after it ran (`ops_logged`), used to
+    /// line the event up with the `NetOp`s it caused (see `format_trace`)
+    pub net_ops: u64,
+}
+
+// ============================================================
+// REPLAY
+// ============================================================
+
+/// Apply one recorded action to `sys`.
+/// Returns false if the action was not enabled: a precondition of the
+/// operation does not hold (nothing is run), or the operation reported that it
+/// found nothing to do (e.g. the message it consumes is not in flight)
+#[verifier::external_body]
+fn apply(sys: &mut ExecSystem, action: &TraceAction) -> bool {
+    let phase = sys.get_coord_phase();
+    match action {
+        TraceAction::New { num_stores, key_a, key_aprime, initial_value } => {
+            if *num_stores == 0 || key_a == key_aprime {
+                return false;
+            }
+            *sys = ExecSystem::new(*num_stores, key_a, key_aprime, *initial_value);
+            true
+        },
+        TraceAction::CoordSendLockReq { store_id } => {
+            if !sys.is_valid_store(*store_id)
+                || (phase != CoordPhase::Idle && phase != CoordPhase::Preparing) {
+                return false;
+            }
+            sys.coord_send_lock_req(*store_id);
+            true
+        },
+        TraceAction::CoordSendLockWithRetry { store_id, attempts } => {
+            if !sys.is_valid_store(*store_id)
+                || (phase != CoordPhase::Idle && phase != CoordPhase::Preparing) {
+                return false;
+            }
+            sys.coord_send_lock_with_retry(*store_id, *attempts);
+            true
+        },
+        TraceAction::CoordSendRenameReq { store_id } => {
+            if !sys.is_valid_store(*store_id) || phase != CoordPhase::Committed {
+                return false;
+            }
+            sys.coord_send_rename_req(*store_id);
+            true
+        },
+        TraceAction::CoordSendUnlockReq { store_id } => {
+            if !sys.is_valid_store(*store_id) || phase != CoordPhase::Cleanup
+                || !sys.coord.is_unlock_target(*store_id) {
+                return false;
+            }
+            sys.coord_send_unlock_req(*store_id);
+            true
+        },
+        TraceAction::CoordRecvLockRespSuccess { store_id } => {
+            sys.coord_recv_lock_resp_success(*store_id)
+        },
+        TraceAction::CoordRecvLockRespFailure { store_id } => {
+            sys.coord_recv_lock_resp_failure(*store_id)
+        },
+        TraceAction::CoordDecideCommit => {
+            if !sys.coord.can_commit(sys.num_stores()) {
+                return false;
+            }
+            sys.coord_decide_commit();
+            true
+        },
+        TraceAction::CoordRecvRenameResp { store_id } => {
+            sys.coord_recv_rename_resp(*store_id)
+        },
+        TraceAction::CoordRecvUnlockResp { store_id } => {
+            sys.coord_recv_unlock_resp(*store_id)
+        },
+        TraceAction::StoreHandleLockReq { store_id, txn_id } => {
+            sys.is_valid_store(*store_id) && sys.store_handle_lock_req(*store_id, *txn_id)
+        },
+        TraceAction::StoreHandleLockReqWithDetectKey { store_id, txn_id, detect_key } => {
+            sys.is_valid_store(*store_id)
+                && sys.store_handle_lock_req_with_detect_key(*store_id, *txn_id, detect_key)
+        },
+        TraceAction::StoreHandleRenameReq { store_id, txn_id } => {
+            sys.is_valid_store(*store_id) && sys.store_handle_rename_req(*store_id, *txn_id)
+        },
+        TraceAction::StoreHandleUnlockReq { store_id, txn_id } => {
+            sys.is_valid_store(*store_id) && sys.store_handle_unlock_req(*store_id, *txn_id)
+        },
+        TraceAction::NetLose { msg } => sys.net_lose(msg),
+        TraceAction::NetDuplicate { msg } => sys.net_duplicate(msg),
+        TraceAction::NetSwapResponse { expected, responder } => {
+            sys.net_swap_response(*expected, *responder)
+        },
+        TraceAction::Pump { store_id } => sys.is_valid_store(*store_id) && sys.pump(*store_id),
+        TraceAction::CoordCrash => {
+            if !phase.can_crash() {
+                return false;
+            }
+            sys.coord_crash();
+            true
+        },
+        TraceAction::CoordRecover => {
+            if phase != CoordPhase::Crashed || sys.get_txn_id() == u64::MAX {
+                return false;
+            }
+            sys.coord_recover();
+            true
+        },
+        TraceAction::StoreCrashRecover { store_id } => {
+            if !sys.is_valid_store(*store_id) {
+                return false;
+            }
+            sys.store_crash_recover(*store_id);
+            true
+        },
+        TraceAction::StorePut { store_id, key, value } => {
+            if !sys.is_valid_store(*store_id) {
+                return false;
+            }
+            sys.store_put(*store_id, key, *value);
+            true
+        },
+        TraceAction::InjectStoreValueGuarded { store_id, key, value, min_txn } => {
+            sys.is_valid_store(*store_id)
+                && sys.inject_store_value_guarded(*store_id, key, *value, *min_txn)
+        },
+        TraceAction::StoreUpdateTxnId { store_id, txn_id } => {
+            if !sys.is_valid_store(*store_id) {
+                return false;
+            }
+            sys.store_update_txn_id(*store_id, *txn_id);
+            true
+        },
+        TraceAction::ClientRename => {
+            if phase != CoordPhase::Idle {
+                return false;
+            }
+            let _ = client_rename(sys);
+            true
+        },
+        TraceAction::CoordStartMigration { src_store, dst_store, key } => {
+            if !sys.is_valid_store(*src_store) || !sys.is_valid_store(*dst_store)
+                || src_store == dst_store || phase != CoordPhase::Idle {
+                return false;
+            }
+            sys.coord_start_migration(*src_store, *dst_store, key);
+            true
+        },
+        TraceAction::StoreHandleMigrationLockReq { store_id, txn_id } => {
+            sys.is_valid_store(*store_id) && sys.store_handle_migration_lock_req(*store_id, *txn_id)
+        },
+        TraceAction::CoordRecvMigrationVote { store_id } => {
+            phase == CoordPhase::Preparing && !sys.coord.has_lock(*store_id)
+                && sys.coord_recv_migration_vote(*store_id)
+        },
+        TraceAction::CoordSendMigrationApply => {
+            phase == CoordPhase::Committed && sys.coord_send_migration_apply()
+        },
+        TraceAction::StoreHandleMigrationApplyReq { store_id, txn_id } => {
+            sys.is_valid_store(*store_id) && sys.store_handle_migration_apply_req(*store_id, *txn_id)
+        },
+        TraceAction::StoreHandleMigrationUnlockReq { store_id, txn_id } => {
+            sys.is_valid_store(*store_id) && sys.store_handle_migration_unlock_req(*store_id, *txn_id)
+        },
+        TraceAction::CoordRecvMigrationApplyResp { store_id } => {
+            phase == CoordPhase::Committed && !sys.coord.has_renamed(*store_id)
+                && sys.coord_recv_migration_apply_resp(*store_id)
+        },
+        TraceAction::CoordRecvMigrationUnlockResp { store_id } => {
+            phase == CoordPhase::Cleanup && !sys.coord.has_unlocked(*store_id)
+                && sys.coord_recv_migration_unlock_resp(*store_id)
+        },
+    }
+}
+
+/// Rebuild an ExecSystem by re-running a recorded trace.
+/// Every operation is deterministic, so the result matches the recorded run.
+/// The trace must start with the `New` event recorded by `TracedSystem::new`.
+/// Returns the index of the first event whose replayed outcome differs from
+/// the recorded one (e.g. a step that ran when recorded is not enabled on
+/// replay), so a diverging replay is never passed off as the recorded run.
+#[verifier::external_body]
+pub fn replay(trace: &[TraceEvent]) -> (result: Result<ExecSystem, usize>)
+    requires
+        trace@.len() > 0,
+        trace@[0].action is New,
+    ensures
+        result is Err ==> 0 < result->Err_0 < trace@.len(),
+{
+    let mut sys = match &trace[0].action {
+        TraceAction::New { num_stores, key_a, key_aprime, initial_value } =>
+            ExecSystem::new(*num_stores, key_a, key_aprime, *initial_value),
+        _ => panic!("trace must start with a New event"),
+    };
+    for (i, event) in trace.iter().enumerate().skip(1) {
+        if apply(&mut sys, &event.action) != event.ok {
+            return Err(i);
+        }
+    }
+    Ok(sys)
+}
+
+/// Whether two coordinators are in the same state, including the order in
+/// which each store set was filled
+#[verifier::external_body]
+fn same_coordinator(a: &Coordinator, b: &Coordinator) -> bool {
+    a.phase == b.phase
+        && a.current_txn_id == b.current_txn_id
+        && a.wal_committed == b.wal_committed
+        && a.commit_txn == b.commit_txn
+        && a.abort_reason == b.abort_reason
+        && a.lock_requested.to_vec() == b.lock_requested.to_vec()
+        && a.locks_acquired.to_vec() == b.locks_acquired.to_vec()
+        && a.renames_done.to_vec() == b.renames_done.to_vec()
+        && a.unlocks_acked.to_vec() == b.unlocks_acked.to_vec()
+        && a.participants.to_vec() == b.participants.to_vec()
+        && a.excluded.to_vec() == b.excluded.to_vec()
+}
+
+/// Whether two stores are in the same state: every key they have indexed
+/// (data, provenance, locks, lock generations, leases), the txn bookkeeping,
+/// the epoch and the op log
+#[verifier::external_body]
+fn same_store(a: &KvStore, b: &KvStore) -> bool {
+    a.last_seen_txn_id == b.last_seen_txn_id
+        && a.prepared_txn == b.prepared_txn
+        && a.released_txn == b.released_txn
+        && a.frozen == b.frozen
+        && a.epoch == b.epoch
+        && a.key_index == b.key_index
+        && a.key_index.iter().all(|k| a.get(k) == b.get(k) && a.modified_by.get(k) == b.modified_by.get(k))
+        && a.lock_history == b.lock_history
+        && a.lock_history.iter().all(|k| a.lock_owner(k) == b.lock_owner(k))
+        && a.lock_gen_keys == b.lock_gen_keys
+        && a.lock_gen_keys.iter().all(|k| a.lock_generation(k) == b.lock_generation(k))
+        && a.leased_keys == b.leased_keys
+        && a.leased_keys.iter().all(|k| a.leases.get(k) == b.leases.get(k))
+        && a.recent_ops().len() == b.recent_ops().len()
+        && a.recent_ops().iter().zip(b.recent_ops()).all(|(oa, ob)| same_kv_op(oa, ob))
+}
+
+#[verifier::external_body]
+fn same_kv_op(a: &KvOp, b: &KvOp) -> bool {
+    match (a, b) {
+        (KvOp::Put { key: ka, txn_id: ta }, KvOp::Put { key: kb, txn_id: tb })
+        | (KvOp::Delete { key: ka, txn_id: ta }, KvOp::Delete { key: kb, txn_id: tb })
+        | (KvOp::Lock { key: ka, txn_id: ta }, KvOp::Lock { key: kb, txn_id: tb })
+        | (KvOp::Unlock { key: ka, txn_id: ta }, KvOp::Unlock { key: kb, txn_id: tb }) =>
+            ka == kb && ta == tb,
+        (KvOp::Rename { old: oa, new: na, txn_id: ta }, KvOp::Rename { old: ob, new: nb, txn_id: tb }) =>
+            oa == ob && na == nb && ta == tb,
+        _ => false,
+    }
+}
+
+/// Whether two networks are in the same state: the in-flight messages with
+/// their sequence numbers and delivery times, the cap and the operation log
+#[verifier::external_body]
+fn same_network(a: &ExecNetwork, b: &ExecNetwork) -> bool {
+    a.messages.len() == b.messages.len()
+        && a.messages.iter().zip(&b.messages).all(|(ma, mb)| ma.eq(mb))
+        && a.seqs() == b.seqs()
+        && a.delays == b.delays
+        && a.per_store_cap == b.per_store_cap
+        && a.ops_logged() == b.ops_logged()
+        && a.log().len() == b.log().len()
+        && a.log().iter().zip(b.log()).all(|(oa, ob)| same_net_op(oa, ob))
+}
+
+#[verifier::external_body]
+fn same_net_op(a: &NetOp, b: &NetOp) -> bool {
+    match (a, b) {
+        (NetOp::Send(ma), NetOp::Send(mb))
+        | (NetOp::Deliver(ma), NetOp::Deliver(mb))
+        | (NetOp::Lose(ma), NetOp::Lose(mb))
+        | (NetOp::Duplicate(ma), NetOp::Duplicate(mb)) => ma.eq(mb),
+        _ => false,
+    }
+}
+
+/// Whether two systems are in the same state: keys, coordinator, every
+/// store, the network (including its log), the migration and the requests
+/// sent so far
+#[verifier::external_body]
+fn same_state(a: &ExecSystem, b: &ExecSystem) -> bool {
+    let same_migration = match (&a.migration, &b.migration) {
+        (Some(ma), Some(mb)) =>
+            ma.src == mb.src && ma.dst == mb.dst && ma.key == mb.key && ma.value == mb.value,
+        (None, None) => true,
+        _ => false,
+    };
+    a.key_a == b.key_a
+        && a.key_aprime == b.key_aprime
+        && same_coordinator(&a.coord, &b.coord)
+        && a.stores.len() == b.stores.len()
+        && a.stores.iter().zip(&b.stores).all(|(sa, sb)| same_store(sa, sb))
+        && same_network(&a.net, &b.net)
+        && same_migration
+        && a.sent.same_as(&b.sent)
+}
+
+/// A fresh copy of `config`
+#[verifier::external_body]
+fn copy_config(config: &ProtocolConfig) -> (result: ProtocolConfig)
+    ensures
+        result.num_stores == config.num_stores,
+        result.key_a@ == config.key_a@,
+        result.key_aprime@ == config.key_aprime@,
+{
+    ProtocolConfig {
+        num_stores: config.num_stores,
+        key_a: config.key_a.clone(),
+        key_aprime: config.key_aprime.clone(),
+        initial_value: config.initial_value,
+        network_capacity: config.network_capacity,
+    }
+}
+
+/// Determinism check: build two systems from `config`, run `schedule` on
+/// both and report whether they stopped at the same action and reached the
+/// same state, down to the network log. A false result means something
+/// besides the config and the schedule (e.g. hash-map iteration order)
+/// leaked into construction or the run
+#[verifier::external_body]
+pub fn run_twice_identical(config: &ProtocolConfig, schedule: &[TraceAction]) -> (result: bool)
+    requires
+        config.num_stores > 0,
+        config.key_a@ != config.key_aprime@,
+{
+    let mut first = ExecSystem::new_with_config(copy_config(config));
+    let mut second = ExecSystem::new_with_config(copy_config(config));
+    let first_result = first.run_schedule(schedule);
+    let second_result = second.run_schedule(schedule);
+    first_result == second_result && same_state(&first, &second)
+}
+
+// ============================================================
+// FORMATTING
+// ============================================================
+
+/// Render a recorded run as a readable timeline, e.g. for a bug report: one
+/// line per event with the phase it left behind, each followed by the network
+/// operations it caused, taken from the log of `net`, the network the events
+/// ran on. Operations the log has already discarded are left out; any past
+/// the last event's mark are listed at the end.
+#[verifier::external_body]
+pub fn format_trace(events: &[TraceEvent], net: &ExecNetwork) -> String {
+    let mut out = String::new();
+    let mut next_op = net.ops_logged() - net.log().len() as u64;
+    for (i, event) in events.iter().enumerate() {
+        out.push_str(&format!("{:>3}  {:?} -> {:?}\n", i, event.action, event.phase));
+        let ops = net.log_since(next_op);
+        let end = event.net_ops.saturating_sub(next_op).min(ops.len() as u64) as usize;
+        for op in &ops[..end] {
+            out.push_str(&format!("       net {:?}\n", op));
+        }
+        next_op += end as u64;
+    }
+    for op in net.log_since(next_op) {
+        out.push_str(&format!("       net {:?}\n", op));
+    }
+    out
+}
+
+impl ExecSystem {
+    /// Run a pre-built schedule of actions in order (the deterministic
+    /// counterpart of a randomized run: an interleaving encoded as data).
+    /// Returns the final coordinator phase, or the index of the first action
+    /// that was not enabled; actions before it have been applied.
+    #[verifier::external_body]
+    pub fn run_schedule(&mut self, actions: &[TraceAction]) -> (result: Result<CoordPhase, usize>)
+        ensures
+            result is Ok ==> result->Ok_0 == self.coord.spec_phase(),
+            result is Err ==> result->Err_0 < actions@.len(),
+    {
+        for (i, action) in actions.iter().enumerate() {
+            if !apply(self, action) {
+                return Err(i);
+            }
+        }
+        Ok(self.get_coord_phase())
+    }
+}
+
+// ============================================================
+// TRACED SYSTEM
+// ============================================================
+
+/// ExecSystem wrapper that records every operation into a trace
+pub struct TracedSystem {
+    /// The wrapped system
+    pub sys: ExecSystem,
+    /// Every operation applied so far, starting with `New`
+    pub trace: Vec<TraceEvent>,
+}
+
+impl TracedSystem {
+    /// Append an event for `action` (with its outcome `ok`) and the current
+    /// coordinator phase
+    #[verifier::external_body]
+    fn record(&mut self, action: TraceAction, ok: bool)
+        ensures
+            self.sys == old(self).sys,
+            self.trace@.len() == old(self).trace@.len() + 1,
+    {
+        let phase = self.sys.get_coord_phase();
+        let net_ops = self.sys.net.ops_logged();
+        self.trace.push(TraceEvent { action, phase, ok, net_ops });
+    }
+
+    /// Create a traced system; the trace starts with the `New` event
+    pub fn new(num_stores: usize, key_a: &str, key_aprime: &str, initial_value: u64) -> (result: Self)
+        requires
+            num_stores > 0,
+            key_a@ != key_aprime@,
+        ensures
+            result.sys.stores@.len() == num_stores,
+            result.sys.coord.spec_phase() == CoordPhase::Idle,
+            result.sys.net.spec_is_empty(),
+            result.trace@.len() == 1,
+    {
+        let mut traced = TracedSystem {
+            sys: ExecSystem::new(num_stores, key_a, key_aprime, initial_value),
+            trace: Vec::new(),
+        };
+        traced.record(TraceAction::New {
+            num_stores,
+            key_a: key_a.to_owned(),
+            key_aprime: key_aprime.to_owned(),
+            initial_value,
+        }, true);
+        traced
+    }
+
+    /// The wrapped system, for queries
+    pub fn sys(&self) -> (result: &ExecSystem)
+        ensures
+            result == &self.sys,
+    {
+        &self.sys
+    }
+
+    /// The recorded trace
+    pub fn trace(&self) -> (result: &[TraceEvent])
+        ensures
+            result@ == self.trace@,
+    {
+        self.trace.as_slice()
+    }
+
+    // ============================================================
+    // COORDINATOR OPERATIONS
+    // ============================================================
+
+    /// Traced `ExecSystem::coord_send_lock_req`
+    pub fn coord_send_lock_req(&mut self, store_id: u64)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+            old(self).sys.coord.spec_phase() == CoordPhase::Idle || old(self).sys.coord.spec_phase() == CoordPhase::Preparing,
+        ensures
+            self.sys.coord.spec_phase() == CoordPhase::Preparing,
+            old(self).sys.net.spec_has_room(store_id as nat) ==>
+                self.sys.net.spec_contains(lock_req_msg(store_id as nat, self.sys.coord.spec_txn_id())),
+    {
+        self.sys.coord_send_lock_req(store_id);
+        self.record(TraceAction::CoordSendLockReq { store_id }, true);
+    }
+
+    /// Traced `ExecSystem::coord_send_lock_with_retry`
+    pub fn coord_send_lock_with_retry(&mut self, store_id: u64, attempts: u8)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+            old(self).sys.coord.spec_phase() == CoordPhase::Idle || old(self).sys.coord.spec_phase() == CoordPhase::Preparing,
+        ensures
+            self.sys.coord.spec_phase() == CoordPhase::Preparing,
+            self.sys.coord.spec_txn_id() == old(self).sys.coord.spec_txn_id(),
+    {
+        self.sys.coord_send_lock_with_retry(store_id, attempts);
+        self.record(TraceAction::CoordSendLockWithRetry { store_id, attempts }, true);
+    }
+
+    /// Traced `ExecSystem::coord_send_rename_req`
+    pub fn coord_send_rename_req(&mut self, store_id: u64)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+            old(self).sys.coord.spec_phase() == CoordPhase::Committed,
+        ensures
+            self.sys.coord.spec_phase() == CoordPhase::Committed,
+            old(self).sys.net.spec_has_room(store_id as nat) ==>
+                self.sys.net.spec_contains(rename_req_msg(store_id as nat, self.sys.coord.spec_txn_id())),
+    {
+        self.sys.coord_send_rename_req(store_id);
+        self.record(TraceAction::CoordSendRenameReq { store_id }, true);
+    }
+
+    /// Traced `ExecSystem::coord_send_unlock_req`
+    pub fn coord_send_unlock_req(&mut self, store_id: u64)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+            old(self).sys.coord.spec_phase() == CoordPhase::Cleanup,
+            old(self).sys.coord.spec_is_unlock_target(store_id),
+        ensures
+            self.sys.coord.spec_phase() == CoordPhase::Cleanup,
+            self.sys.coord.locks_acquired@ == old(self).sys.coord.locks_acquired@,
+            old(self).sys.net.spec_has_room(store_id as nat) ==>
+                self.sys.net.spec_contains(unlock_req_msg(store_id as nat, self.sys.coord.spec_txn_id())),
+    {
+        self.sys.coord_send_unlock_req(store_id);
+        self.record(TraceAction::CoordSendUnlockReq { store_id }, true);
+    }
+
+    /// Traced `ExecSystem::coord_recv_lock_resp_success`
+    pub fn coord_recv_lock_resp_success(&mut self, store_id: u64) -> (result: bool)
+        ensures
+            result ==> old(self).sys.spec_valid_store(store_id),
+            result ==> self.sys.coord.spec_has_lock(store_id),
+            result ==> self.sys.coord.spec_phase() == CoordPhase::Preparing,
+            !result ==> self.sys.coord.spec_phase() == old(self).sys.coord.spec_phase(),
+    {
+        let result = self.sys.coord_recv_lock_resp_success(store_id);
+        self.record(TraceAction::CoordRecvLockRespSuccess { store_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::coord_recv_lock_resp_failure`
+    pub fn coord_recv_lock_resp_failure(&mut self, store_id: u64) -> (result: bool)
+        ensures
+            result ==> self.sys.coord.spec_phase() == CoordPhase::Cleanup,
+    {
+        let result = self.sys.coord_recv_lock_resp_failure(store_id);
+        self.record(TraceAction::CoordRecvLockRespFailure { store_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::coord_decide_commit`
+    pub fn coord_decide_commit(&mut self)
+        requires
+            old(self).sys.coord.spec_can_commit(old(self).sys.spec_num_stores()),
+        ensures
+            self.sys.coord.spec_phase() == CoordPhase::Committed,
+            self.sys.coord.spec_is_committed(),
+    {
+        self.sys.coord_decide_commit();
+        self.record(TraceAction::CoordDecideCommit, true);
+    }
+
+    /// Traced `ExecSystem::coord_recv_rename_resp`
+    pub fn coord_recv_rename_resp(&mut self, store_id: u64) -> (result: bool)
+        ensures
+            result ==> old(self).sys.spec_valid_store(store_id),
+            result ==> self.sys.coord.spec_has_renamed(store_id),
+    {
+        let result = self.sys.coord_recv_rename_resp(store_id);
+        self.record(TraceAction::CoordRecvRenameResp { store_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::coord_recv_unlock_resp`
+    pub fn coord_recv_unlock_resp(&mut self, store_id: u64) -> (result: bool)
+        ensures
+            result ==> old(self).sys.spec_valid_store(store_id),
+            result ==> self.sys.coord.spec_has_unlocked(store_id),
+    {
+        let result = self.sys.coord_recv_unlock_resp(store_id);
+        self.record(TraceAction::CoordRecvUnlockResp { store_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::coord_crash`
+    pub fn coord_crash(&mut self)
+        requires
+            old(self).sys.coord.spec_phase().spec_can_crash(),
+        ensures
+            self.sys.coord.spec_phase() == CoordPhase::Crashed,
+    {
+        self.sys.coord_crash();
+        self.record(TraceAction::CoordCrash, true);
+    }
+
+    /// Traced `ExecSystem::coord_recover`
+    pub fn coord_recover(&mut self)
+        requires
+            old(self).sys.coord.spec_phase() == CoordPhase::Crashed,
+            old(self).sys.coord.spec_txn_id() < u64::MAX as nat,
+        ensures
+            self.sys.coord.spec_txn_id() == old(self).sys.coord.spec_txn_id() + 1,
+            old(self).sys.coord.spec_is_committed() ==> self.sys.coord.spec_phase() == CoordPhase::Committed,
+            !old(self).sys.coord.spec_is_committed() ==> self.sys.coord.spec_phase() == CoordPhase::Cleanup,
+    {
+        self.sys.coord_recover();
+        self.record(TraceAction::CoordRecover, true);
+    }
+
+    // ============================================================
+    // STORE OPERATIONS
+    // ============================================================
+
+    /// Traced `ExecSystem::store_handle_lock_req`
+    pub fn store_handle_lock_req(&mut self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            result ==> self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        let result = self.sys.store_handle_lock_req(store_id, txn_id);
+        self.record(TraceAction::StoreHandleLockReq { store_id, txn_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::store_handle_lock_req_with_detect_key`
+    pub fn store_handle_lock_req_with_detect_key(
+        &mut self,
+        store_id: u64,
+        txn_id: u64,
+        detect_key: &str,
+    ) -> (result: bool)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            result ==> self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        let result = self.sys.store_handle_lock_req_with_detect_key(store_id, txn_id, detect_key);
+        self.record(TraceAction::StoreHandleLockReqWithDetectKey {
+            store_id,
+            txn_id,
+            detect_key: detect_key.to_owned(),
+        }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::store_handle_rename_req`
+    pub fn store_handle_rename_req(&mut self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            result ==> self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        let result = self.sys.store_handle_rename_req(store_id, txn_id);
+        self.record(TraceAction::StoreHandleRenameReq { store_id, txn_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::store_handle_unlock_req`
+    pub fn store_handle_unlock_req(&mut self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            result ==> self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        let result = self.sys.store_handle_unlock_req(store_id, txn_id);
+        self.record(TraceAction::StoreHandleUnlockReq { store_id, txn_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::store_crash_recover`
+    pub fn store_crash_recover(&mut self, store_id: u64)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        self.sys.store_crash_recover(store_id);
+        self.record(TraceAction::StoreCrashRecover { store_id }, true);
+    }
+
+    // ============================================================
+    // NETWORK OPERATIONS
+    // ============================================================
+
+    /// Traced `ExecSystem::net_lose`
+    pub fn net_lose(&mut self, msg: &ExecMessage) -> (result: bool)
+        ensures
+            result == old(self).sys.net.spec_contains(msg@),
+    {
+        let result = self.sys.net_lose(msg);
+        self.record(TraceAction::NetLose { msg: msg.clone() }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::net_duplicate`
+    pub fn net_duplicate(&mut self, msg: &ExecMessage) -> (result: bool)
+        ensures
+            result == (old(self).sys.net.spec_contains(msg@) && old(self).sys.net.next_seq < u64::MAX),
+            result ==> self.sys.net.spec_contains(msg@),
+    {
+        let result = self.sys.net_duplicate(msg);
+        self.record(TraceAction::NetDuplicate { msg: msg.clone() }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::net_swap_response`
+    pub fn net_swap_response(&mut self, expected: u64, responder: u64) -> (result: bool)
+        ensures
+            self.sys.coord == old(self).sys.coord,
+            self.sys.stores@ == old(self).sys.stores@,
+    {
+        let result = self.sys.net_swap_response(expected, responder);
+        self.record(TraceAction::NetSwapResponse { expected, responder }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::pump`
+    pub fn pump(&mut self, store_id: u64) -> (result: bool)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            !result ==> self.sys.net.messages@ == old(self).sys.net.messages@,
+    {
+        let result = self.sys.pump(store_id);
+        self.record(TraceAction::Pump { store_id }, result);
+        result
+    }
+
+    // ============================================================
+    // FAULT INJECTION
+    // ============================================================
+
+    /// Traced `ExecSystem::store_put`
+    pub fn store_put(&mut self, store_id: u64, key: &str, value: u64)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        self.sys.store_put(store_id, key, value);
+        self.record(TraceAction::StorePut { store_id, key: key.to_owned(), value }, true);
+    }
+
+    /// Traced `ExecSystem::inject_store_value_guarded`
+    pub fn inject_store_value_guarded(
+        &mut self,
+        store_id: u64,
+        key: &str,
+        value: u64,
+        min_txn: u64,
+    ) -> (result: bool)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            self.sys.stores@.len() == old(self).sys.stores@.len(),
+            result ==> old(self).sys.stores@[store_id as int].last_seen_txn_id >= min_txn,
+    {
+        let result = self.sys.inject_store_value_guarded(store_id, key, value, min_txn);
+        self.record(TraceAction::InjectStoreValueGuarded {
+            store_id,
+            key: key.to_owned(),
+            value,
+            min_txn,
+        }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::store_update_txn_id`
+    pub fn store_update_txn_id(&mut self, store_id: u64, txn_id: u64)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        self.sys.store_update_txn_id(store_id, txn_id);
+        self.record(TraceAction::StoreUpdateTxnId { store_id, txn_id }, true);
+    }
+
+    // ============================================================
+    // CROSS-STORE MIGRATION
+    // ============================================================
+
+    /// Traced `ExecSystem::coord_start_migration`
+    pub fn coord_start_migration(&mut self, src_store: u64, dst_store: u64, key: &str)
+        requires
+            old(self).sys.spec_valid_store(src_store),
+            old(self).sys.spec_valid_store(dst_store),
+            src_store != dst_store,
+            old(self).sys.coord.spec_phase() == CoordPhase::Idle,
+        ensures
+            self.sys.coord.spec_phase() == CoordPhase::Preparing,
+            self.sys.migration.is_some(),
+            self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        self.sys.coord_start_migration(src_store, dst_store, key);
+        self.record(TraceAction::CoordStartMigration { src_store, dst_store, key: key.to_owned() }, true);
+    }
+
+    /// Traced `ExecSystem::store_handle_migration_lock_req`
+    pub fn store_handle_migration_lock_req(&mut self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        let result = self.sys.store_handle_migration_lock_req(store_id, txn_id);
+        self.record(TraceAction::StoreHandleMigrationLockReq { store_id, txn_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::coord_recv_migration_vote`
+    pub fn coord_recv_migration_vote(&mut self, store_id: u64) -> (result: bool)
+        requires
+            old(self).sys.coord.spec_phase() == CoordPhase::Preparing,
+            !old(self).sys.coord.spec_has_lock(store_id),
+        ensures
+            result ==> self.sys.coord.spec_has_lock(store_id),
+            self.sys.coord.spec_phase() == CoordPhase::Preparing,
+    {
+        let result = self.sys.coord_recv_migration_vote(store_id);
+        self.record(TraceAction::CoordRecvMigrationVote { store_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::coord_send_migration_apply`
+    pub fn coord_send_migration_apply(&mut self) -> (result: bool)
+        requires
+            old(self).sys.coord.spec_phase() == CoordPhase::Committed,
+        ensures
+            self.sys.coord.spec_phase() == CoordPhase::Committed,
+    {
+        let result = self.sys.coord_send_migration_apply();
+        self.record(TraceAction::CoordSendMigrationApply, result);
+        result
+    }
+
+    /// Traced `ExecSystem::store_handle_migration_apply_req`
+    pub fn store_handle_migration_apply_req(&mut self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        let result = self.sys.store_handle_migration_apply_req(store_id, txn_id);
+        self.record(TraceAction::StoreHandleMigrationApplyReq { store_id, txn_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::store_handle_migration_unlock_req`
+    pub fn store_handle_migration_unlock_req(&mut self, store_id: u64, txn_id: u64) -> (result: bool)
+        requires
+            old(self).sys.spec_valid_store(store_id),
+        ensures
+            self.sys.stores@.len() == old(self).sys.stores@.len(),
+    {
+        let result = self.sys.store_handle_migration_unlock_req(store_id, txn_id);
+        self.record(TraceAction::StoreHandleMigrationUnlockReq { store_id, txn_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::coord_recv_migration_apply_resp`
+    pub fn coord_recv_migration_apply_resp(&mut self, store_id: u64) -> (result: bool)
+        requires
+            old(self).sys.coord.spec_phase() == CoordPhase::Committed,
+            !old(self).sys.coord.spec_has_renamed(store_id),
+        ensures
+            result ==> self.sys.coord.spec_has_renamed(store_id),
+    {
+        let result = self.sys.coord_recv_migration_apply_resp(store_id);
+        self.record(TraceAction::CoordRecvMigrationApplyResp { store_id }, result);
+        result
+    }
+
+    /// Traced `ExecSystem::coord_recv_migration_unlock_resp`
+    pub fn coord_recv_migration_unlock_resp(&mut self, store_id: u64) -> (result: bool)
+        requires
+            old(self).sys.coord.spec_phase() == CoordPhase::Cleanup,
+            !old(self).sys.coord.spec_has_unlocked(store_id),
+        ensures
+            result ==> self.sys.coord.spec_has_unlocked(store_id),
+    {
+        let result = self.sys.coord_recv_migration_unlock_resp(store_id);
+        self.record(TraceAction::CoordRecvMigrationUnlockResp { store_id }, result);
+        result
+    }
+
+    // ============================================================
+    // CLIENT
+    // ============================================================
+
+    /// Run `client_rename` as a single traced step
+    pub fn client_rename(&mut self) -> (result: Result<(), ProtocolError>)
+        requires
+            old(self).sys.coord.spec_phase() == CoordPhase::Idle,
+        ensures
+            result is Ok ==> self.sys.coord.spec_phase() == CoordPhase::Done,
+            result is Ok ==> self.sys.coord.spec_is_committed(),
+    {
+        let result = client_rename(&mut self.sys);
+        self.record(TraceAction::ClientRename, true);
+        result
+    }
+}
+
+// ============================================================
+// UNIT TESTS
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test: Record a happy-path run and replay it to the same final state
+    fn test_record_replay_happy_path() {
+        let mut traced = TracedSystem::new(2, "A", "A'", 42);
+        let txn_id = traced.sys().get_txn_id();
+
+        traced.coord_send_lock_req(0);
+        traced.coord_send_lock_req(1);
+        traced.store_handle_lock_req(0, txn_id);
+        traced.store_handle_lock_req(1, txn_id);
+        traced.coord_recv_lock_resp_success(0);
+        traced.coord_recv_lock_resp_success(1);
+        traced.coord_decide_commit();
+
+        let replayed = replay(traced.trace());
+        assert(replayed is Ok);
+        let replayed = replayed.unwrap();
+        assert(replayed.get_coord_phase() == CoordPhase::Committed);
+        assert(replayed.is_committed());
+    }
+}
+
+} // verus!
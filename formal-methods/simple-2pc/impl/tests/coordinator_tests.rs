@@ -1,7 +1,15 @@
 // Runtime tests for the executable Coordinator implementation.
 // These mirror the verified tests in src/coordinator_v.rs but run under `cargo test`.
 
-use kv_store::{Coordinator, CoordPhase};
+use kv_store::coordinator_v::SimpleSet;
+use kv_store::{AbortReason, Coordinator, CoordPhase, ExecMessage, SubVote};
+use vstd::prelude::{nat, Ghost};
+
+/// The store count `decide_commit` is verified against; it is ghost, so at
+/// runtime there is nothing to pass
+fn stores(_n: u64) -> Ghost<nat> {
+    Ghost::assume_new()
+}
 
 #[test]
 fn test_new() {
@@ -36,12 +44,33 @@ fn test_record_lock_success() {
 #[test]
 fn test_handle_lock_failure() {
     let mut coord = Coordinator::new();
-    coord.start_preparing();
+    coord.record_lock_request(0);
+    coord.record_lock_request(1);
     coord.record_lock_success(0);
 
     coord.handle_lock_failure();
     assert_eq!(coord.get_phase(), CoordPhase::Cleanup);
-    assert!(!coord.has_lock(0)); // Locks cleared
+    assert!(coord.has_lock(0));
+    // Store 1's grant may still be in flight, so it is unlocked as well
+    assert!(coord.is_unlock_target(0));
+    assert!(coord.is_unlock_target(1));
+    assert!(!coord.is_unlock_target(2));
+}
+
+#[test]
+fn test_handle_lock_failure_clears_credit() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+
+    coord.handle_lock_failure();
+    for store in 0..3 {
+        assert!(!coord.has_renamed(store));
+        assert!(!coord.has_unlocked(store));
+    }
+    // Only the store that granted a lock is still credited with one
+    assert!(coord.has_lock(0));
+    assert!(!coord.has_lock(1));
 }
 
 #[test]
@@ -51,16 +80,39 @@ fn test_decide_commit() {
     coord.record_lock_success(0);
     coord.record_lock_success(1);
 
-    coord.decide_commit();
+    coord.decide_commit(stores(2));
     assert!(coord.is_committed());
     assert_eq!(coord.get_phase(), CoordPhase::Committed);
 }
 
+#[test]
+fn test_can_commit_requires_every_lock() {
+    let mut coord = Coordinator::new();
+    assert!(!coord.can_commit(0)); // not Preparing yet
+    coord.start_preparing();
+    assert!(coord.can_commit(0));
+    coord.record_lock_success(0);
+    coord.record_lock_success(2);
+    // Store 1 is missing, so decide_commit(3) is outside its contract
+    assert!(!coord.can_commit(3));
+    assert!(coord.can_commit(1));
+    coord.record_lock_success(1);
+    assert!(coord.can_commit(3));
+
+    coord.decide_commit(stores(3));
+    assert!(!coord.can_commit(3)); // already committed
+    for store in 0..3 {
+        assert!(coord.has_lock(store));
+    }
+}
+
 #[test]
 fn test_record_rename_done() {
     let mut coord = Coordinator::new();
     coord.start_preparing();
-    coord.decide_commit();
+    coord.record_lock_success(0);
+    coord.record_lock_success(1);
+    coord.decide_commit(stores(2));
 
     let all_done = coord.record_rename_done(0, 2);
     assert!(!all_done);
@@ -76,27 +128,110 @@ fn test_record_rename_done() {
 #[test]
 fn test_record_unlock_acked() {
     let mut coord = Coordinator::new();
-    coord.start_preparing();
-    coord.decide_commit();
+    coord.record_lock_request(0);
+    coord.record_lock_request(1);
+    coord.record_lock_success(0);
+    coord.record_lock_success(1);
+    coord.decide_commit(stores(2));
     coord.record_rename_done(0, 2);
     coord.record_rename_done(1, 2);
 
-    let all_done = coord.record_unlock_acked(0, 2);
+    let all_done = coord.record_unlock_acked(0);
     assert!(!all_done);
     assert!(coord.has_unlocked(0));
     assert_eq!(coord.get_phase(), CoordPhase::Cleanup);
 
-    let all_done = coord.record_unlock_acked(1, 2);
+    let all_done = coord.record_unlock_acked(1);
     assert!(all_done);
     assert!(coord.has_unlocked(1));
     assert_eq!(coord.get_phase(), CoordPhase::Done);
 }
 
+#[test]
+fn test_record_unlock_acked_after_abort() {
+    let mut coord = Coordinator::new();
+    for s in 0..3 {
+        coord.record_lock_request(s);
+    }
+    coord.record_lock_success(0);
+    coord.record_lock_success(1);
+    coord.handle_lock_failure();
+
+    // Store 2 voted no, but it was sent a LockReq, so its ack is waited on too
+    assert!(!coord.record_unlock_acked(0));
+    assert!(!coord.record_unlock_acked(1));
+    assert!(coord.record_unlock_acked(2));
+    assert_eq!(coord.get_phase(), CoordPhase::Done);
+}
+
+#[test]
+fn test_unlock_targets_survive_recovery() {
+    // The stores sent a LockReq are durable, so recovery still knows them
+    let mut coord = Coordinator::new();
+    coord.record_lock_request(0);
+    coord.record_lock_request(7);
+    coord.crash();
+    coord.recover();
+    assert_eq!(coord.get_phase(), CoordPhase::Cleanup);
+    assert!(coord.is_unlock_target(0));
+    assert!(coord.is_unlock_target(7));
+    assert!(!coord.is_unlock_target(1));
+}
+
+#[test]
+fn test_add_participant_after_locks() {
+    let mut coord = Coordinator::new();
+    coord.add_participant(0);
+    coord.add_participant(1);
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    coord.record_lock_success(1);
+    assert!(coord.all_votes_in());
+
+    // A third store discovered late: not complete until it votes too
+    coord.add_participant(2);
+    assert_eq!(coord.get_phase(), CoordPhase::Preparing);
+    assert!(!coord.all_votes_in());
+    assert!(!coord.can_commit(3));
+
+    coord.record_lock_success(2);
+    assert!(coord.all_votes_in());
+    assert!(coord.can_commit(3));
+}
+
+#[test]
+fn test_participants_scope_commit() {
+    // Stores 0 and 2 take part out of 4; store 1 never votes
+    let mut coord = Coordinator::new();
+    coord.add_participant(0);
+    coord.add_participant(2);
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    assert!(!coord.can_commit(4));
+    coord.record_lock_success(2);
+    assert!(coord.in_txn(2, 4));
+    assert!(!coord.in_txn(1, 4));
+    assert!(coord.can_commit(4));
+}
+
+#[test]
+fn test_add_participant_that_already_voted() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+
+    // Adding a store whose lock is already recorded does not block
+    coord.add_participant(0);
+    assert!(coord.all_votes_in());
+}
+
 #[test]
 fn test_crash_recover_committed() {
     let mut coord = Coordinator::new();
     coord.start_preparing();
-    coord.decide_commit();
+    coord.record_lock_success(0);
+    coord.record_lock_success(1);
+    coord.decide_commit(stores(2));
     coord.record_rename_done(0, 2);
 
     // Crash
@@ -131,33 +266,552 @@ fn test_crash_recover_not_committed() {
     assert_eq!(coord.get_phase(), CoordPhase::Cleanup); // Go to cleanup
 }
 
+#[test]
+fn test_rebuild_locks_from_store_reports() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    coord.record_lock_success(1);
+    coord.record_lock_success(2);
+    coord.decide_commit(stores(3));
+    coord.crash();
+    coord.recover();
+    assert_eq!(coord.acquired_stores(), Vec::<u64>::new());
+
+    // Stores 0 and 2 report still holding the locks; store 1's report is lost
+    coord.rebuild_locks(&[0, 2]);
+    assert!(coord.has_lock(0));
+    assert!(!coord.has_lock(1));
+    assert!(coord.has_lock(2));
+    assert_eq!(coord.get_phase(), CoordPhase::Committed);
+    assert_eq!(coord.get_txn_id(), 2);
+    assert!(coord.is_committed());
+}
+
+#[test]
+fn test_committed_txn_id_survives_recovery() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    assert_eq!(coord.committed_txn_id(), None);
+    coord.decide_commit(stores(1));
+    assert_eq!(coord.committed_txn_id(), Some(1));
+
+    // Recovery moves the live id on; the commit point stays at txn 1
+    coord.crash();
+    coord.recover();
+    assert_eq!(coord.get_txn_id(), 2);
+    assert_eq!(coord.committed_txn_id(), Some(1));
+
+    coord.crash();
+    coord.recover();
+    assert_eq!(coord.get_txn_id(), 3);
+    assert_eq!(coord.committed_txn_id(), Some(1));
+}
+
+#[test]
+fn test_double_crash_recover() {
+    for commit in [false, true] {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        coord.record_lock_success(0);
+        coord.record_lock_success(1);
+        if commit {
+            coord.decide_commit(stores(2));
+        }
+        let txn_id = coord.get_txn_id();
+
+        coord.crash();
+        coord.recover();
+        let resumed = coord.get_phase();
+        assert!(resumed.can_crash(), "{:?}", resumed);
+
+        // Crash again before the resumed phase finishes, then recover again
+        coord.crash();
+        coord.recover();
+        assert_eq!(coord.get_txn_id(), txn_id + 2);
+        assert_eq!(coord.is_committed(), commit);
+        assert_eq!(coord.get_phase(), resumed);
+    }
+}
+
+#[test]
+fn test_phase_all() {
+    use CoordPhase::*;
+    let all = CoordPhase::all();
+    assert_eq!(all, vec![Idle, Preparing, Committed, Cleanup, Done, Crashed]);
+    // Every successor is itself a listed phase
+    for phase in &all {
+        for next in phase.successors() {
+            assert!(all.contains(&next), "{:?} -> {:?}", phase, next);
+        }
+    }
+}
+
 #[test]
 fn test_phase_can_crash() {
-    assert!(!CoordPhase::Idle.can_crash());
-    assert!(CoordPhase::Preparing.can_crash());
-    assert!(CoordPhase::Committed.can_crash());
-    assert!(CoordPhase::Cleanup.can_crash());
-    assert!(!CoordPhase::Done.can_crash());
-    assert!(!CoordPhase::Crashed.can_crash());
+    // (phase, can_crash), one row per entry of CoordPhase::all()
+    let expected = [false, true, true, true, false, false];
+    for (phase, want) in CoordPhase::all().into_iter().zip(expected) {
+        assert_eq!(phase.can_crash(), want, "{:?}", phase);
+    }
 }
 
 #[test]
 fn test_phase_is_terminal() {
-    assert!(!CoordPhase::Idle.is_terminal());
-    assert!(!CoordPhase::Preparing.is_terminal());
-    assert!(!CoordPhase::Committed.is_terminal());
-    assert!(!CoordPhase::Cleanup.is_terminal());
-    assert!(CoordPhase::Done.is_terminal());
-    assert!(!CoordPhase::Crashed.is_terminal());
+    let expected = [false, false, false, false, true, false];
+    for (phase, want) in CoordPhase::all().into_iter().zip(expected) {
+        assert_eq!(phase.is_terminal(), want, "{:?}", phase);
+    }
 }
 
 #[test]
 fn test_phase_is_active() {
-    assert!(CoordPhase::Idle.is_active());
-    assert!(CoordPhase::Preparing.is_active());
-    assert!(CoordPhase::Committed.is_active());
-    assert!(CoordPhase::Cleanup.is_active());
-    assert!(!CoordPhase::Done.is_active());
-    assert!(!CoordPhase::Crashed.is_active());
+    let expected = [true, true, true, true, false, false];
+    for (phase, want) in CoordPhase::all().into_iter().zip(expected) {
+        assert_eq!(phase.is_active(), want, "{:?}", phase);
+    }
+}
+
+#[test]
+fn test_phase_categorized_exactly_once() {
+    // Each phase is exactly one of active, terminal, or crashed
+    for phase in CoordPhase::all() {
+        let groups = [phase.is_active(), phase.is_terminal(), phase == CoordPhase::Crashed];
+        assert_eq!(groups.iter().filter(|&&g| g).count(), 1, "{:?}", phase);
+        // Only active phases past Idle may crash
+        assert_eq!(phase.can_crash(), phase.is_active() && phase != CoordPhase::Idle, "{:?}", phase);
+    }
+}
+
+#[test]
+fn test_phase_resume_phase() {
+    assert_eq!(CoordPhase::resume_phase(true), CoordPhase::Committed);
+    assert_eq!(CoordPhase::resume_phase(false), CoordPhase::Cleanup);
+
+    // recover() lands in the same phase
+    for committed in [true, false] {
+        let mut coord = Coordinator::new();
+        coord.start_preparing();
+        if committed {
+            coord.record_lock_success(0);
+            coord.decide_commit(stores(1));
+        }
+        coord.crash();
+        coord.recover();
+        assert_eq!(coord.get_phase(), CoordPhase::resume_phase(committed));
+    }
 }
 
+#[test]
+fn test_phase_successors() {
+    use CoordPhase::*;
+    assert_eq!(Idle.successors(), vec![Preparing]);
+    assert_eq!(Preparing.successors(), vec![Committed, Cleanup, Crashed]);
+    assert_eq!(Committed.successors(), vec![Cleanup, Crashed]);
+    assert_eq!(Cleanup.successors(), vec![Done, Crashed]);
+    assert_eq!(Done.successors(), Vec::<CoordPhase>::new());
+    assert_eq!(Crashed.successors(), vec![Committed, Cleanup]);
+}
+
+#[test]
+fn test_force_abort_mid_preparing() {
+    let mut coord = Coordinator::new();
+    coord.record_lock_request(0);
+    coord.record_lock_request(1);
+    // Store 0 granted its lock; store 1's response was lost
+    coord.record_lock_success(0);
+
+    coord.force_abort();
+    assert_eq!(coord.get_phase(), CoordPhase::Cleanup);
+    assert!(!coord.is_committed());
+    assert!(!coord.has_lock(0));
+
+    // Cleanup runs against every store sent a LockReq and completes
+    assert!(coord.is_unlock_target(0));
+    assert!(coord.is_unlock_target(1));
+    assert!(!coord.record_unlock_acked(0));
+    assert!(coord.record_unlock_acked(1));
+    assert_eq!(coord.get_phase(), CoordPhase::Done);
+}
+
+#[test]
+fn test_force_abort_from_crashed() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.crash();
+
+    coord.force_abort();
+    assert_eq!(coord.get_phase(), CoordPhase::Cleanup);
+    assert_eq!(coord.get_txn_id(), 1);
+}
+
+#[test]
+fn test_lock_failure_records_abort_reason() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    assert_eq!(coord.abort_reason(), None);
+
+    // Store 1 refuses its lock
+    coord.handle_lock_failure();
+    assert_eq!(coord.abort_reason(), Some(AbortReason::LockFailure));
+}
+
+#[test]
+fn test_forced_abort_records_abort_reason() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.force_abort();
+    assert_eq!(coord.abort_reason(), Some(AbortReason::ForcedByOperator));
+}
+
+#[test]
+fn test_timeout_and_recovery_abort_reasons() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.handle_vote_timeout();
+    assert_eq!(coord.get_phase(), CoordPhase::Cleanup);
+    assert_eq!(coord.abort_reason(), Some(AbortReason::Timeout));
+
+    // Recovering without a COMMIT record aborts
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.crash();
+    coord.recover();
+    assert_eq!(coord.abort_reason(), Some(AbortReason::RecoveredUncommitted));
+
+    // A committed transaction resumes with no abort recorded
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    coord.decide_commit(stores(1));
+    coord.crash();
+    coord.recover();
+    assert_eq!(coord.get_phase(), CoordPhase::Committed);
+    assert_eq!(coord.abort_reason(), None);
+}
+
+#[test]
+fn test_many_stores_spill_past_inline_sets() {
+    // More participants than the sets keep inline
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    for store in 0..6 {
+        coord.record_lock_success(store);
+        coord.record_lock_success(store); // duplicate grant is not double counted
+    }
+    for store in 0..6 {
+        assert!(coord.has_lock(store));
+    }
+    assert!(!coord.has_lock(6));
+
+    coord.decide_commit(stores(6));
+    for store in 0..5 {
+        assert!(!coord.record_rename_done(store, 6));
+    }
+    assert!(coord.record_rename_done(5, 6));
+    assert_eq!(coord.get_phase(), CoordPhase::Cleanup);
+
+    coord.crash();
+    assert!(!coord.has_lock(0));
+    assert!(!coord.has_lock(5));
+}
+
+#[test]
+fn test_reconcile_wal_agreeing() {
+    assert_eq!(Coordinator::reconcile_wal((3, true), (3, true)), (3, true));
+    assert_eq!(Coordinator::reconcile_wal((3, false), (3, false)), (3, false));
+}
+
+#[test]
+fn test_reconcile_wal_conflicting() {
+    // Same txn, one copy lost the COMMIT record: commit wins, in either order
+    assert_eq!(Coordinator::reconcile_wal((5, false), (5, true)), (5, true));
+    assert_eq!(Coordinator::reconcile_wal((5, true), (5, false)), (5, true));
+    // Different txns: the newer record wins regardless of commit state
+    assert_eq!(Coordinator::reconcile_wal((2, true), (4, false)), (4, false));
+    assert_eq!(Coordinator::reconcile_wal((7, false), (6, true)), (7, false));
+}
+
+#[test]
+fn test_commit_with_one_store_excluded() {
+    let mut coord = Coordinator::new();
+    assert!(!coord.is_degraded());
+    // Store 1 is known dead before the transaction starts
+    coord.exclude_participant(1, stores(3));
+    assert!(coord.is_degraded());
+    assert!(coord.is_excluded(1));
+    assert!(!coord.is_excluded(0));
+
+    coord.record_lock_request(0);
+    coord.record_lock_request(2);
+    coord.record_lock_success(0);
+    coord.record_lock_success(2);
+    assert!(coord.can_commit(3));
+    coord.decide_commit(stores(3));
+
+    // Only the two survivors need to rename and unlock
+    assert!(!coord.record_rename_done(0, 3));
+    assert!(coord.record_rename_done(2, 3));
+    assert_eq!(coord.get_phase(), CoordPhase::Cleanup);
+    assert!(!coord.record_unlock_acked(0));
+    assert!(coord.record_unlock_acked(2));
+    assert_eq!(coord.get_phase(), CoordPhase::Done);
+
+    // Degraded mode is configuration: it stays set for the whole run
+    assert!(coord.is_degraded());
+}
+
+#[test]
+fn test_excluded_store_vote_still_required_for_others() {
+    let mut coord = Coordinator::new();
+    coord.exclude_participant(0, stores(2));
+    coord.start_preparing();
+    // Excluding store 0 does not excuse store 1
+    assert!(!coord.can_commit(2));
+    coord.record_lock_success(1);
+    assert!(coord.can_commit(2));
+}
+
+#[test]
+fn test_simple_set_to_vec_spilled() {
+    let mut set = SimpleSet::new();
+    for x in [7, 3, 7, 1, 9, 4, 3] {
+        set.insert(x);
+    }
+    // Insertion order, without duplicates, past the inline capacity
+    assert_eq!(set.to_vec(), vec![7, 3, 1, 9, 4]);
+    set.clear();
+    assert!(set.to_vec().is_empty());
+}
+
+#[test]
+fn test_store_snapshots() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(2);
+    coord.record_lock_success(0);
+
+    let mut locked = coord.acquired_stores();
+    locked.sort();
+    assert_eq!(format!("stores {:?} have locked", locked), "stores [0, 2] have locked");
+    assert!(coord.renamed_stores().is_empty());
+
+    coord.record_lock_success(1);
+    coord.decide_commit(stores(3));
+    coord.record_rename_done(0, 3);
+    assert_eq!(coord.renamed_stores(), vec![0]);
+    assert!(coord.unlocked_stores().is_empty());
+}
+
+#[test]
+fn test_vote_summary() {
+    let mut coord = Coordinator::new();
+    assert_eq!(coord.vote_summary(3), "Idle: 0/3 locked");
+
+    for s in 0..3 {
+        coord.record_lock_request(s);
+    }
+    coord.record_lock_success(0);
+    coord.record_lock_success(2);
+    assert_eq!(coord.vote_summary(3), "Preparing: 2/3 locked");
+
+    coord.record_lock_success(1);
+    coord.decide_commit(stores(3));
+    coord.record_rename_done(1, 3);
+    assert_eq!(coord.vote_summary(3), "Committed: 1/3 renamed");
+
+    coord.record_rename_done(0, 3);
+    coord.record_rename_done(2, 3);
+    assert_eq!(coord.vote_summary(3), "Cleanup: 0/3 unlocked");
+    for s in 0..3 {
+        coord.record_unlock_acked(s);
+    }
+    assert_eq!(coord.vote_summary(3), "Done: committed");
+}
+
+#[test]
+fn test_vote_summary_degraded() {
+    // Counts cover the included stores only, whatever the excluded id
+    let mut coord = Coordinator::new();
+    coord.exclude_participant(1, stores(3));
+    assert_eq!(coord.vote_summary(3), "Idle: 0/2 locked");
+
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    coord.record_lock_success(2);
+    coord.decide_commit(stores(3));
+    assert!(!coord.record_rename_done(2, 3));
+    assert_eq!(coord.vote_summary(3), "Committed: 1/2 renamed");
+    assert!(close_to(coord.progress(3), 1.0 / 2.0));
+    // Store 0 is still owed: completion checks membership, not counts
+    assert!(coord.record_rename_done(0, 3));
+}
+
+fn close_to(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn test_progress() {
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    let mut coord = Coordinator::new();
+    assert_eq!(coord.progress(3), 0.0);
+
+    coord.start_preparing();
+    for s in 0..3 {
+        coord.record_lock_success(s);
+    }
+    assert!(close(coord.progress(3), 1.0 / 3.0));
+
+    // Mid-rename: one of three stores renamed
+    coord.decide_commit(stores(3));
+    coord.record_rename_done(0, 3);
+    assert!(close(coord.progress(3), 4.0 / 9.0));
+
+    coord.record_rename_done(1, 3);
+    coord.record_rename_done(2, 3);
+    assert!(close(coord.progress(3), 2.0 / 3.0));
+    for s in 0..3 {
+        coord.record_unlock_acked(s);
+    }
+    assert_eq!(coord.progress(3), 1.0);
+}
+
+#[test]
+fn test_is_near_overflow() {
+    let mut coord = Coordinator::new();
+    assert!(!coord.is_near_overflow(1000));
+
+    // A coordinator that has crashed and recovered close to the ceiling
+    coord.current_txn_id = u64::MAX - 10;
+    coord.crash();
+    coord.recover();
+    assert_eq!(coord.txn_id_headroom(), 9);
+    assert!(coord.is_near_overflow(16));
+    assert!(!coord.is_near_overflow(8));
+}
+
+#[test]
+fn test_verify_after_recovery() {
+    // Uncommitted: recovery aborts into Cleanup
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    let old_txn = coord.get_txn_id();
+    coord.crash();
+    coord.recover();
+    assert!(coord.verify_after_recovery(old_txn + 1));
+    assert!(!coord.verify_after_recovery(old_txn));
+
+    // Committed: recovery resumes the commit phase
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    coord.decide_commit(stores(1));
+    let old_txn = coord.get_txn_id();
+    coord.crash();
+    coord.recover();
+    assert_eq!(coord.get_phase(), CoordPhase::Committed);
+    assert!(coord.verify_after_recovery(old_txn + 1));
+
+    // Progress after recovery makes the check fail
+    coord.record_rename_done(0, 1);
+    assert!(!coord.verify_after_recovery(old_txn + 1));
+}
+
+#[test]
+fn test_check_durable() {
+    let mut coord = Coordinator::new();
+    assert!(coord.check_durable());
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    assert!(coord.check_durable());
+    coord.decide_commit(stores(1));
+    assert!(coord.check_durable());
+    coord.crash();
+    assert!(coord.check_durable());
+    coord.recover();
+    assert!(coord.check_durable());
+    coord.record_rename_done(0, 1);
+    coord.record_unlock_acked(0);
+    assert_eq!(coord.get_phase(), CoordPhase::Done);
+    assert!(coord.check_durable());
+
+    // Committed without the WAL record
+    let mut corrupt = Coordinator::new();
+    corrupt.phase = CoordPhase::Committed;
+    assert!(!corrupt.check_durable());
+
+    // WAL committed but back in Preparing
+    let mut corrupt = Coordinator::new();
+    corrupt.wal_committed = true;
+    corrupt.phase = CoordPhase::Preparing;
+    assert!(!corrupt.check_durable());
+}
+
+#[test]
+fn test_as_vote_for_parent_coordinator() {
+    // Every lock granted: the sub-protocol can commit, so it votes Yes
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    // A lock is still outstanding: the child is healthy, just not done voting
+    assert_eq!(coord.as_vote(2), SubVote::Pending);
+    coord.record_lock_success(1);
+    assert_eq!(coord.as_vote(2), SubVote::Yes);
+    coord.decide_commit(stores(2));
+    assert_eq!(coord.as_vote(2), SubVote::Yes);
+
+    // A refused lock moves the sub-protocol to Cleanup, and it votes No
+    let mut aborted = Coordinator::new();
+    aborted.start_preparing();
+    aborted.record_lock_success(0);
+    aborted.handle_lock_failure();
+    assert_eq!(aborted.get_phase(), CoordPhase::Cleanup);
+    assert_eq!(aborted.as_vote(1), SubVote::No);
+}
+
+#[test]
+fn test_validate_response_rejects_wrong_txn() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    assert!(coord.validate_response(&ExecMessage::lock_resp(0, true, 1), 1));
+    assert!(!coord.validate_response(&ExecMessage::lock_resp(0, true, 0), 1));
+    assert!(!coord.validate_response(&ExecMessage::lock_resp(0, false, 2), 1));
+    assert!(!coord.validate_response(&ExecMessage::lock_resp(1, true, 1), 1));
+}
+
+#[test]
+fn test_validate_response_rejects_wrong_phase() {
+    let mut coord = Coordinator::new();
+    assert!(!coord.validate_response(&ExecMessage::lock_resp(0, true, 1), 1));
+    coord.start_preparing();
+    assert!(!coord.validate_response(&ExecMessage::rename_resp(0, 1), 1));
+    assert!(!coord.validate_response(&ExecMessage::unlock_resp(0, 1), 1));
+    coord.record_lock_success(0);
+    coord.decide_commit(stores(1));
+    assert!(coord.validate_response(&ExecMessage::rename_resp(0, 1), 1));
+    assert!(!coord.validate_response(&ExecMessage::lock_resp(0, false, 1), 1));
+    assert!(!coord.validate_response(&ExecMessage::unlock_resp(0, 1), 1));
+}
+
+#[test]
+fn test_validate_response_rejects_duplicate() {
+    let mut coord = Coordinator::new();
+    coord.start_preparing();
+    coord.record_lock_success(0);
+    assert!(!coord.validate_response(&ExecMessage::lock_resp(0, true, 1), 2));
+    // A failure is never credited, so a repeat is still acceptable
+    assert!(coord.validate_response(&ExecMessage::lock_resp(0, false, 1), 2));
+    coord.record_lock_success(1);
+    coord.decide_commit(stores(2));
+    coord.record_rename_done(0, 2);
+    assert!(!coord.validate_response(&ExecMessage::rename_resp(0, 1), 2));
+    coord.record_rename_done(1, 2);
+    assert_eq!(coord.get_phase(), CoordPhase::Cleanup);
+    assert!(coord.validate_response(&ExecMessage::unlock_resp(1, 1), 2));
+    coord.record_unlock_acked(1);
+    assert!(!coord.validate_response(&ExecMessage::unlock_resp(1, 1), 2));
+}
@@ -1,7 +1,7 @@
 // Runtime tests for the executable KvStore implementation.
 // These mirror the verified tests in src/lib.rs but run under `cargo test`.
 
-use kv_store::KvStore;
+use kv_store::{AssocMap, KeyObservation, KeyStatus, KvDelta, KvOp, KvStore, RenameProof, RenameResult, Vote, OP_LOG_CAP};
 
 #[test]
 fn test_new() {
@@ -10,6 +10,28 @@ fn test_new() {
     assert!(!store.is_locked("any_key"));
 }
 
+#[test]
+fn test_with_capacity_behaves_like_new() {
+    let mut sized = KvStore::with_capacity(1000);
+    let mut plain = KvStore::new();
+    assert!(!sized.contains_key("k0"));
+    assert_eq!(sized.get_last_seen_txn_id(), plain.get_last_seen_txn_id());
+    assert_eq!(sized.prepared_txn, plain.prepared_txn);
+
+    for i in 0..1000u64 {
+        let key = format!("k{}", i);
+        assert_eq!(sized.put(&key, i), plain.put(&key, i));
+    }
+    sized.lock("k1");
+    plain.lock("k1");
+    for i in 0..1000u64 {
+        let key = format!("k{}", i);
+        assert_eq!(sized.get(&key), plain.get(&key));
+        assert_eq!(sized.is_locked(&key), plain.is_locked(&key));
+    }
+    assert_eq!(sized.value_sum(), plain.value_sum());
+}
+
 #[test]
 fn test_put_get() {
     let mut store = KvStore::new();
@@ -95,6 +117,190 @@ fn test_rename_nonexistent() {
     assert_eq!(result, None);
 }
 
+#[test]
+fn test_rename_overwrite_existing_destination() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.put("B", 2);
+    store.lock("A");
+    store.lock("B");
+
+    // Destination pre-exists: overwritten value is returned
+    assert_eq!(store.rename_overwrite("A", "B"), Some(2u64));
+    assert!(!store.contains_key("A"));
+    assert_eq!(store.get("B"), Some(1u64));
+    assert!(store.is_locked("A"));
+    assert!(store.is_locked("B"));
+}
+
+#[test]
+fn test_rename_overwrite_empty_destination() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.lock("A");
+    store.lock("B");
+
+    // Destination empty: moved value is returned
+    assert_eq!(store.rename_overwrite("A", "B"), Some(1u64));
+    assert!(!store.contains_key("A"));
+    assert_eq!(store.get("B"), Some(1u64));
+}
+
+#[test]
+fn test_rename_overwrite_missing_source() {
+    let mut store = KvStore::new();
+    store.put("B", 2);
+    store.lock("A");
+    store.lock("B");
+
+    assert_eq!(store.rename_overwrite("A", "B"), None);
+    assert_eq!(store.get("B"), Some(2u64));
+}
+
+#[test]
+fn test_clear_unlocked_store() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.put("B", 2);
+    store.update_txn_id(7);
+
+    assert!(store.clear());
+    assert!(!store.contains_key("A"));
+    assert!(!store.contains_key("B"));
+    assert_eq!(store.get_last_seen_txn_id(), 7);
+
+    // Store is reusable afterwards
+    assert!(store.put("A", 3));
+    assert_eq!(store.get("A"), Some(3u64));
+}
+
+#[test]
+fn test_clear_refuses_while_locked() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.lock("B");
+
+    assert!(!store.clear());
+    assert_eq!(store.get("A"), Some(1u64));
+    assert!(store.is_locked("B"));
+
+    store.unlock("B");
+    assert!(store.clear());
+    assert!(!store.contains_key("A"));
+}
+
+#[test]
+fn test_get_many_present_and_absent() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.put("C", 3);
+
+    let keys = vec!["A".to_owned(), "B".to_owned(), "C".to_owned(), "A".to_owned()];
+    assert_eq!(store.get_many(&keys), vec![Some(1), None, Some(3), Some(1)]);
+    assert_eq!(store.get_many(&Vec::new()), Vec::<Option<u64>>::new());
+}
+
+#[test]
+fn test_get_ref_matches_get() {
+    let mut store = KvStore::new();
+    store.put("A", 42);
+    assert_eq!(store.get_ref("A").copied(), store.get("A"));
+    assert_eq!(store.get_ref("A"), Some(&42));
+    assert_eq!(store.get_ref("B"), None);
+}
+
+#[test]
+fn test_get_or_present_and_absent() {
+    let mut store = KvStore::new();
+    store.put("A", 42);
+    assert_eq!(store.get_or("A", 7), 42);
+    assert_eq!(store.get_or("B", 7), 7);
+
+    // The default is never written back
+    assert_eq!(store.get("B"), None);
+    assert_eq!(store.get_or("B", 9), 9);
+}
+
+#[test]
+fn test_get_or_insert_paths() {
+    let mut store = KvStore::new();
+    store.put("A", 42);
+
+    // Present: existing value wins
+    assert_eq!(store.get_or_insert("A", 7), Some(42));
+    assert_eq!(store.get("A"), Some(42));
+
+    // Absent and unlocked: default is inserted once
+    assert_eq!(store.get_or_insert("B", 7), Some(7));
+    assert_eq!(store.get_or_insert("B", 9), Some(7));
+    assert_eq!(store.get("B"), Some(7));
+
+    // Absent and locked: refused without inserting
+    store.lock("A'");
+    assert_eq!(store.get_or_insert("A'", 7), None);
+    assert!(!store.contains_key("A'"));
+    store.unlock("A'");
+    assert_eq!(store.get_or_insert("A'", 7), Some(7));
+}
+
+#[test]
+fn test_lock_if_value_mismatch() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+
+    // Value differs: no lock
+    assert!(!store.lock_if_value("A", Some(2)));
+    assert!(!store.is_locked("A"));
+
+    // Expected absent but present: no lock
+    assert!(!store.lock_if_value("A", None));
+    assert!(!store.is_locked("A"));
+
+    // Expected a value but absent: no lock
+    assert!(!store.lock_if_value("A'", Some(1)));
+    assert!(!store.is_locked("A'"));
+
+    // Data untouched by the failed attempts
+    assert_eq!(store.get("A"), Some(1));
+}
+
+#[test]
+fn test_lock_if_value_match() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+
+    // Only lock A' while it is still absent
+    assert!(store.lock_if_value("A'", None));
+    assert!(store.is_locked("A'"));
+    assert!(!store.contains_key("A'"));
+
+    assert!(store.lock_if_value("A", Some(1)));
+    assert!(store.is_locked("A"));
+}
+
+#[test]
+fn test_prepared_survives_crash() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.update_txn_id(4);
+    assert!(!store.is_prepared(4));
+
+    store.lock("A");
+    store.prepare(4);
+    assert!(store.is_prepared(4));
+    assert!(!store.is_prepared(3));
+
+    // Crash loses the lock table but keeps data, txn id and the vote
+    store.crash();
+    assert!(!store.is_locked("A"));
+    assert_eq!(store.get("A"), Some(1u64));
+    assert_eq!(store.get_last_seen_txn_id(), 4);
+    assert!(store.is_prepared(4));
+
+    store.clear_prepared();
+    assert!(!store.is_prepared(4));
+}
+
 #[test]
 fn test_multiple_keys_independent() {
     let mut store = KvStore::new();
@@ -114,3 +320,1195 @@ fn test_multiple_keys_independent() {
     assert_eq!(store.get("key3"), Some(33u64));
 }
 
+#[test]
+fn test_lock_implies_seen_txn() {
+    let mut store = KvStore::new();
+
+    // Lock taken while processing txn 3
+    store.update_txn_id(3);
+    store.lock("A");
+    assert_eq!(store.lock_owner("A"), Some(3));
+    assert!(store.lock_implies_seen_txn("A"));
+
+    // Later txns only strengthen the link
+    store.update_txn_id(5);
+    assert!(store.lock_implies_seen_txn("A"));
+
+    // A store that forgot the owner's txn is caught
+    store.last_seen_txn_id = 2;
+    assert!(!store.lock_implies_seen_txn("A"));
+    store.last_seen_txn_id = 5;
+
+    // Unlocked keys trivially satisfy the invariant
+    assert!(store.lock_implies_seen_txn("B"));
+    store.unlock("A");
+    assert!(store.lock_implies_seen_txn("A"));
+}
+
+#[test]
+fn test_update_txn_id_non_monotonic() {
+    let mut store = KvStore::new();
+
+    // Interleaved messages from several txns arrive out of order
+    store.update_txn_id(5);
+    assert_eq!(store.get_last_seen_txn_id(), 5);
+    store.update_txn_id(3);
+    assert_eq!(store.get_last_seen_txn_id(), 5);
+    store.update_txn_id(8);
+    assert_eq!(store.get_last_seen_txn_id(), 8);
+}
+
+#[test]
+fn test_with_locks_unlocks_after_closure() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    let keys = vec!["A".to_owned(), "A'".to_owned()];
+
+    let moved = store.with_locks(&keys, |s| {
+        assert!(s.is_locked("A"));
+        assert!(s.is_locked("A'"));
+        // Locked keys reject plain writes but can be renamed
+        assert!(!s.put("A", 2));
+        s.rename("A", "A'")
+    });
+
+    assert_eq!(moved, Some(1));
+    assert!(!store.is_locked("A"));
+    assert!(!store.is_locked("A'"));
+    assert_eq!(store.get("A'"), Some(1));
+}
+
+#[test]
+fn test_with_locks_early_return() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    let keys = vec!["A".to_owned()];
+
+    let found = store.with_locks(&keys, |s| {
+        if s.contains_key("A") {
+            return true;
+        }
+        s.put("A", 0);
+        false
+    });
+
+    assert!(found);
+    assert!(!store.is_locked("A"));
+    assert!(store.put("A", 5));
+}
+
+#[test]
+fn test_with_locks_keeps_preexisting_lock() {
+    let mut store = KvStore::new();
+    store.lock("A");
+    let keys = vec!["A".to_owned(), "B".to_owned()];
+
+    store.with_locks(&keys, |_| ());
+
+    // The caller's lock on A is not released; B was only held for the closure
+    assert!(store.is_locked("A"));
+    assert!(!store.is_locked("B"));
+}
+
+#[test]
+fn test_lock_all_reporting_mixed_keys() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 1));
+    store.lock("A");
+    let keys = vec!["A".to_owned(), "B".to_owned(), "C".to_owned(), "B".to_owned()];
+
+    let fresh = store.lock_all_reporting(&keys);
+
+    assert_eq!(fresh, vec![false, true, true, false]);
+    assert!(keys.iter().all(|k| store.is_locked(k)));
+    assert_eq!(store.get("A"), Some(1));
+
+    // Releasing only the newly taken locks leaves the caller's lock on A
+    for (key, newly) in keys.iter().zip(&fresh) {
+        if *newly {
+            store.unlock(key);
+        }
+    }
+    assert!(store.is_locked("A"));
+    assert!(!store.is_locked("B"));
+    assert!(!store.is_locked("C"));
+}
+
+#[test]
+fn test_release_txn_locks_only_releases_owner() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 1));
+    store.update_txn_id(1);
+    store.lock("A");
+    store.lock("B");
+    store.update_txn_id(2);
+    store.lock("C");
+    assert!(store.lock_with_lease("D", 2, 100));
+    assert_eq!(store.lock_owner("A"), Some(1));
+    assert_eq!(store.lock_owner("D"), Some(2));
+    assert_eq!(store.lock_owner("E"), None);
+
+    assert_eq!(store.release_txn_locks(1), 2);
+    assert!(!store.is_locked("A"));
+    assert!(!store.is_locked("B"));
+    assert!(store.is_locked("C"));
+    assert!(store.is_locked("D"));
+    assert_eq!(store.get("A"), Some(1));
+
+    // Nothing left for txn 1; releasing txn 2 clears the rest
+    assert_eq!(store.release_txn_locks(1), 0);
+    assert_eq!(store.release_txn_locks(2), 2);
+    assert!(store.locked_keys().is_empty());
+}
+
+#[test]
+fn test_relock_hands_ownership_to_current_txn() {
+    let mut store = KvStore::new();
+    store.update_txn_id(1);
+    store.lock("A");
+    store.update_txn_id(2);
+    store.lock("A");
+    assert_eq!(store.lock_owner("A"), Some(2));
+    assert_eq!(store.release_txn_locks(1), 0);
+    assert!(store.is_locked("A"));
+}
+
+#[test]
+fn test_last_writer_tracks_put_and_rename() {
+    let mut store = KvStore::new();
+    assert_eq!(store.last_writer("A"), None);
+
+    store.update_txn_id(3);
+    assert!(store.put("A", 10));
+    assert_eq!(store.last_writer("A"), Some(3));
+
+    // Reads and locks do not change provenance
+    store.update_txn_id(7);
+    assert_eq!(store.get("A"), Some(10));
+    store.lock("A");
+    store.lock("B");
+    assert_eq!(store.last_writer("A"), Some(3));
+
+    // Rename attributes the destination to the current txn
+    assert_eq!(store.rename("A", "B"), Some(10));
+    assert_eq!(store.last_writer("B"), Some(7));
+}
+
+#[test]
+fn test_touch_updates_provenance_only() {
+    let mut store = KvStore::new();
+    store.update_txn_id(2);
+    store.put("A", 10);
+
+    assert!(store.touch("A", 9));
+    assert_eq!(store.get("A"), Some(10));
+    assert_eq!(store.last_writer("A"), Some(9));
+    // touch does not advance the store's txn high-water mark
+    assert_eq!(store.get_last_seen_txn_id(), 2);
+
+    // Absent or locked keys are left alone
+    assert!(!store.touch("B", 9));
+    assert_eq!(store.last_writer("B"), None);
+    store.lock("A");
+    assert!(!store.touch("A", 11));
+    assert_eq!(store.last_writer("A"), Some(9));
+}
+
+#[test]
+fn test_last_writer_survives_crash() {
+    let mut store = KvStore::new();
+    store.update_txn_id(2);
+    assert!(store.put("A", 1));
+    store.crash();
+    assert_eq!(store.last_writer("A"), Some(2));
+}
+
+#[test]
+fn test_rollback_to_restores_contents() {
+    let mut store = KvStore::new();
+    store.update_txn_id(1);
+    assert!(store.put("A", 10));
+    assert!(store.put("B", 20));
+
+    // Save a copy of the current contents
+    let snapshot = store.snapshot();
+
+    // Speculative writes under a later txn
+    store.update_txn_id(5);
+    assert!(store.put("A", 11));
+    assert!(store.delete("B"));
+    assert!(store.put("C", 30));
+
+    assert!(store.rollback_to(&snapshot));
+    assert_eq!(store.get("A"), Some(10));
+    assert_eq!(store.get("B"), Some(20));
+    assert_eq!(store.get("C"), None);
+    assert_eq!(store.last_writer("A"), Some(1));
+    // txn_id is monotonic and survives the rollback
+    assert_eq!(store.get_last_seen_txn_id(), 5);
+
+    // The snapshot is borrowed, so it can be rolled back to again
+    assert!(store.put("A", 12));
+    assert!(store.rollback_to(&snapshot));
+    assert_eq!(store.get("A"), Some(10));
+}
+
+#[test]
+fn test_snapshot_is_independent() {
+    let mut store = KvStore::new();
+    store.update_txn_id(2);
+    assert!(store.put("A", 10));
+    store.lock("A");
+    store.lock("B");
+    store.unlock("B");
+    store.lock("B");
+    store.prepare(2);
+
+    let mut copy = store.snapshot();
+    assert_eq!(copy.get("A"), Some(10));
+    assert!(copy.is_locked("A"));
+    assert_eq!(copy.lock_generation("B"), store.lock_generation("B"));
+    assert_eq!(copy.last_writer("A"), Some(2));
+    assert_eq!(copy.prepared_txn, Some(2));
+
+    // Changes to either side stay on that side
+    store.unlock("A");
+    assert!(store.put("C", 1));
+    assert!(copy.is_locked("A"));
+    assert!(!copy.contains_key("C"));
+    copy.unlock("A");
+    assert!(copy.delete("A"));
+    assert_eq!(store.get("A"), Some(10));
+}
+
+#[test]
+fn test_snapshot_copies_maps_in_full() {
+    let mut store = KvStore::new();
+    store.lock("X");
+    store.crash();
+    assert!(store.lock_with_lease("L", 1, 50));
+
+    // X was never written and is no longer in the lock history
+    let mut copy = store.snapshot();
+    assert_eq!(copy.lock_generation("X"), 1);
+    assert_eq!(copy.lock_generation("L"), 1);
+
+    // Generations keep counting up in the fork, and the lease still expires
+    copy.lock("X");
+    assert_eq!(copy.lock_generation("X"), 2);
+    assert_eq!(copy.tick(100), 1);
+    assert!(!copy.is_locked("L"));
+}
+
+#[test]
+fn test_multi_put_locked_rejects_whole_batch() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 1));
+    store.lock("A");
+    store.lock("B");
+
+    // C is not locked, so nothing in the batch is applied
+    let entries = vec![("A".to_owned(), 10), ("B".to_owned(), 20), ("C".to_owned(), 30)];
+    assert!(!store.multi_put_locked(entries));
+    assert_eq!(store.get("A"), Some(1));
+    assert_eq!(store.get("B"), None);
+    assert_eq!(store.get("C"), None);
+}
+
+#[test]
+fn test_multi_put_locked_applies_all() {
+    let mut store = KvStore::new();
+    store.update_txn_id(4);
+    store.lock("A");
+    store.lock("B");
+
+    // Repeated key: the last entry wins
+    let entries = vec![("A".to_owned(), 10), ("B".to_owned(), 20), ("A".to_owned(), 11)];
+    assert!(store.multi_put_locked(entries));
+    assert_eq!(store.get("A"), Some(11));
+    assert_eq!(store.get("B"), Some(20));
+    assert_eq!(store.last_writer("B"), Some(4));
+    // Locks are left to the caller
+    assert!(store.is_locked("A"));
+    assert!(store.is_locked("B"));
+}
+
+#[test]
+fn test_lease_expiry_releases_lock() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 1));
+    assert!(store.lock_with_lease("A", 1, 10));
+    store.lock("B"); // no lease: never auto-released
+    assert!(!store.put("A", 2));
+
+    assert_eq!(store.expired_locks(9), 0);
+    assert_eq!(store.tick(9), 0);
+    assert!(store.is_locked("A"));
+
+    assert_eq!(store.expired_locks(10), 1);
+    assert_eq!(store.tick(10), 1);
+    assert!(!store.is_locked("A"));
+    assert!(store.is_locked("B"));
+    assert_eq!(store.expired_locks(100), 0);
+
+    // Data untouched by expiry; the key is writable again
+    assert_eq!(store.get("A"), Some(1));
+    assert!(store.put("A", 2));
+}
+
+#[test]
+fn test_lease_dropped_on_unlock() {
+    let mut store = KvStore::new();
+    assert!(store.lock_with_lease("A", 1, 10));
+    store.unlock("A");
+    // Re-locked without a lease: tick must not release it
+    store.lock("A");
+    assert_eq!(store.tick(20), 0);
+    assert!(store.is_locked("A"));
+}
+
+#[test]
+fn test_lock_with_lease_never_steals() {
+    let mut store = KvStore::new();
+    store.update_txn_id(1);
+    store.lock("A");
+    // Held by txn 1: txn 2 cannot take it over
+    assert!(!store.lock_with_lease("A", 2, 10));
+    assert_eq!(store.lock_owner("A"), Some(1));
+    assert_eq!(store.get_last_seen_txn_id(), 1);
+    assert_eq!(store.tick(20), 0);
+    assert!(store.is_locked("A"));
+
+    // The owner may renew its own lock with a lease
+    assert!(store.lock_with_lease("B", 1, 10));
+    assert!(store.lock_with_lease("B", 1, 30));
+    assert_eq!(store.tick(20), 0);
+    assert_eq!(store.tick(30), 1);
+    assert!(!store.is_locked("B"));
+}
+
+#[test]
+fn test_lease_dropped_on_lock_and_rename() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 1));
+    assert!(store.lock_with_lease("A", 1, 10));
+    assert!(store.lock_with_lease("A'", 1, 10));
+    assert!(store.lock_with_lease("B", 1, 10));
+
+    // A plain lock holds the key without a lease
+    store.lock("B");
+    // Once renamed, both keys stay locked until an explicit unlock
+    assert_eq!(store.rename("A", "A'"), Some(1));
+    assert_eq!(store.tick(20), 0);
+    assert!(store.is_locked("A"));
+    assert!(store.is_locked("A'"));
+    assert!(store.is_locked("B"));
+}
+
+#[test]
+fn test_lock_with_lease_rejects_stale_txn() {
+    let mut store = KvStore::new();
+    store.update_txn_id(5);
+    assert!(!store.lock_with_lease("A", 4, 10));
+    assert!(!store.is_locked("A"));
+}
+
+#[test]
+fn test_try_rename_not_locked() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 7));
+    store.lock("A");
+    // Destination not locked: rejected, nothing moves
+    assert_eq!(store.try_rename("A", "A'"), RenameResult::NotLocked);
+    assert_eq!(store.get("A"), Some(7));
+    assert_eq!(store.get("A'"), None);
+}
+
+#[test]
+fn test_try_rename_source_absent() {
+    let mut store = KvStore::new();
+    store.lock("A");
+    store.lock("A'");
+    assert_eq!(store.try_rename("A", "A'"), RenameResult::SourceAbsent);
+    assert!(!store.contains_key("A'"));
+}
+
+#[test]
+fn test_try_rename_done() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 7));
+    store.lock("A");
+    store.lock("A'");
+    assert_eq!(store.try_rename("A", "A'"), RenameResult::Done(7));
+    assert_eq!(store.get("A"), None);
+    assert_eq!(store.get("A'"), Some(7));
+    // Locks are left to the caller
+    assert!(store.is_locked("A"));
+    assert!(store.is_locked("A'"));
+}
+
+#[test]
+fn test_locked_keys_during_lock_phase() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.put("B", 2);
+    assert!(store.locked_keys().is_empty());
+
+    store.lock("A");
+    store.lock("A'");
+    store.lock("A");
+    let mut keys = store.locked_keys();
+    keys.sort();
+    assert_eq!(keys, vec!["A".to_string(), "A'".to_string()]);
+
+    // Unlocked keys drop out; relocking does not duplicate
+    store.unlock("A");
+    assert_eq!(store.locked_keys(), vec!["A'".to_string()]);
+    store.lock("A");
+    assert_eq!(store.locked_keys().len(), 2);
+
+    // A crash clears the lock table and the history with it
+    store.crash();
+    assert!(store.locked_keys().is_empty());
+}
+
+#[test]
+fn test_locked_keys_includes_leased_locks() {
+    let mut store = KvStore::new();
+    assert!(store.lock_with_lease("A", 1, 10));
+    assert!(store.lock_if_value("B", None));
+    let mut keys = store.locked_keys();
+    keys.sort();
+    assert_eq!(keys, vec!["A".to_string(), "B".to_string()]);
+
+    store.tick(10);
+    assert_eq!(store.locked_keys(), vec!["B".to_string()]);
+}
+
+#[test]
+fn test_count_value() {
+    let mut store = KvStore::new();
+    assert_eq!(store.count_value(42), 0);
+
+    store.put("A", 42);
+    store.put("B", 7);
+    store.put("C", 42);
+    assert_eq!(store.count_value(42), 2);
+    assert_eq!(store.count_value(7), 1);
+    assert_eq!(store.count_value(0), 0);
+
+    // Overwrites and deletes are reflected; re-putting a key is not double counted
+    store.put("C", 7);
+    store.put("C", 7);
+    store.delete("B");
+    assert_eq!(store.count_value(42), 1);
+    assert_eq!(store.count_value(7), 1);
+}
+
+#[test]
+fn test_scan_prefix() {
+    let mut store = KvStore::new();
+    store.put("user:alice", 1);
+    store.put("order:7", 70);
+    store.put("user:bob", 2);
+    store.put("user", 3);
+    store.put("user:carol", 4);
+    store.delete("user:carol");
+
+    assert_eq!(
+        store.scan_prefix("user:"),
+        vec![("user:alice".to_string(), 1), ("user:bob".to_string(), 2)]
+    );
+    assert_eq!(store.scan_prefix("order:"), vec![("order:7".to_string(), 70)]);
+    assert!(store.scan_prefix("admin:").is_empty());
+    assert_eq!(store.scan_prefix("").len(), 4);
+}
+
+#[test]
+fn test_sorted_entries() {
+    let mut store = KvStore::new();
+    store.put("C", 3);
+    store.put("A", 1);
+    store.put("B", 2);
+    store.delete("B");
+    assert_eq!(store.sorted_entries(), vec![("A".to_owned(), 1), ("C".to_owned(), 3)]);
+}
+
+#[test]
+fn test_json_round_trip() {
+    let mut store = KvStore::new();
+    store.put("B", u64::MAX);
+    store.put("A", 0);
+    store.put("quote\"back\\slash\n", 7);
+    store.lock("A");
+
+    let json = store.export_json();
+    assert!(json.starts_with("{\"A\":0,\"B\":18446744073709551615,"));
+    let loaded = KvStore::import_json(&json).unwrap();
+    assert_eq!(loaded.compare(&store), std::cmp::Ordering::Equal);
+    assert_eq!(loaded.get("quote\"back\\slash\n"), Some(7));
+    // Locks are not part of the fixture
+    assert!(!loaded.is_locked("A"));
+
+    assert_eq!(KvStore::new().export_json(), "{}");
+    assert!(KvStore::import_json(" { } ").unwrap().sorted_entries().is_empty());
+}
+
+#[test]
+fn test_import_json_fixture_with_whitespace() {
+    let store = KvStore::import_json("{\n  \"A\": 42,\n  \"caf\\u00e9\": 1\n}\n").unwrap();
+    assert_eq!(store.get("A"), Some(42));
+    assert_eq!(store.get("café"), Some(1));
+}
+
+#[test]
+fn test_import_json_rejects_malformed() {
+    for bad in [
+        "",
+        "[]",
+        "{\"A\": 1,}",
+        "{\"A\" 1}",
+        "{\"A\": 1} x",
+        "{A: 1}",
+        "{\"A\": -1}",
+        "{\"A\": 1.5}",
+        "{\"A\": 1e3}",
+        "{\"A\": 01}",
+        "{\"A\": \"1\"}",
+        "{\"A\": true}",
+        "{\"A\": 18446744073709551616}",
+        "{\"A\": 1, \"A\": 2}",
+        "{\"A\": 1",
+    ] {
+        assert!(KvStore::import_json(bad).is_none(), "accepted {:?}", bad);
+    }
+}
+
+#[test]
+fn test_compare_sorts_stores_consistently() {
+    use std::cmp::Ordering;
+
+    fn build(entries: &[(&str, u64)]) -> KvStore {
+        let mut store = KvStore::new();
+        for (key, value) in entries {
+            store.put(key, *value);
+        }
+        store
+    }
+
+    let forward = [
+        build(&[("A", 1), ("B", 2)]),
+        build(&[("A", 1), ("B", 3)]),
+        build(&[("B", 0)]),
+    ];
+    // Same contents, built in a different order (with a transient key)
+    let mut backward = [
+        build(&[("B", 0)]),
+        build(&[("B", 3), ("A", 1)]),
+        build(&[("B", 2), ("X", 9), ("A", 1)]),
+    ];
+    backward[2].delete("X");
+
+    for (i, a) in forward.iter().enumerate() {
+        for (j, b) in forward.iter().enumerate() {
+            assert_eq!(a.compare(b), i.cmp(&j), "stores {} and {}", i, j);
+        }
+    }
+
+    let mut sorted: Vec<&KvStore> = backward.iter().collect();
+    sorted.sort_by(|a, b| a.compare(b));
+    for (a, b) in sorted.iter().zip(forward.iter()) {
+        assert_eq!(a.compare(b), Ordering::Equal);
+    }
+}
+
+#[test]
+fn test_status_counts_through_rename() {
+    let mut store = KvStore::new();
+    assert_eq!(store.status_counts(), (0, 0, 0, 0));
+
+    store.put("A", 42);
+    store.put("B", 7);
+    assert_eq!(store.status_counts(), (2, 0, 0, 0));
+
+    // Lock phase: A' is locked before it has a value
+    store.lock("A");
+    store.lock("A'");
+    assert_eq!(store.status_counts(), (1, 1, 1, 2));
+
+    // Rename moves the value, so now A is the locked absent key
+    assert_eq!(store.rename("A", "A'"), Some(42));
+    assert_eq!(store.status_counts(), (1, 1, 1, 2));
+
+    store.unlock("A");
+    store.unlock("A'");
+    assert_eq!(store.status_counts(), (2, 0, 0, 0));
+}
+
+#[test]
+fn test_count_value_after_rename() {
+    let mut store = KvStore::new();
+    store.put("A", 42);
+    store.lock("A");
+    store.lock("A'");
+    assert_eq!(store.rename("A", "A'"), Some(42));
+    // The migrated value appears exactly once
+    assert_eq!(store.count_value(42), 1);
+    assert_eq!(store.get("A'"), Some(42));
+}
+
+#[test]
+fn test_assoc_map_matches_map_semantics() {
+    let mut map: AssocMap<u64> = AssocMap::new();
+    assert!(map.is_empty());
+    assert_eq!(map.get("A"), None);
+
+    map.insert("A".to_owned(), 1);
+    map.insert("B".to_owned(), 2);
+    map.insert("A".to_owned(), 3);
+    assert_eq!(map.get("A"), Some(&3));
+    assert_eq!(map.get("B"), Some(&2));
+
+    map.remove("A");
+    assert!(!map.contains_key("A"));
+    assert!(map.contains_key("B"));
+    map.remove("missing");
+
+    map.clear();
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_rekey_three_keys() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.put("B", 2);
+    store.put("C", 3);
+    store.put("Z", 26);
+    for key in ["A", "B", "C", "A'", "B'", "C'"] {
+        store.lock(key);
+    }
+
+    let mapping = vec![
+        ("A".to_owned(), "A'".to_owned()),
+        ("B".to_owned(), "B'".to_owned()),
+        ("C".to_owned(), "C'".to_owned()),
+    ];
+    assert!(store.rekey(mapping));
+    assert_eq!(store.get("A'"), Some(1));
+    assert_eq!(store.get("B'"), Some(2));
+    assert_eq!(store.get("C'"), Some(3));
+    for key in ["A", "B", "C"] {
+        assert!(!store.contains_key(key));
+    }
+    assert_eq!(store.get("Z"), Some(26));
+}
+
+#[test]
+fn test_rekey_rejects_overlap_and_missing_source() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.put("B", 2);
+    for key in ["A", "B", "C", "D"] {
+        store.lock(key);
+    }
+
+    // A chain (A -> B, B -> C) reuses B as both destination and source
+    let chain = vec![("A".to_owned(), "B".to_owned()), ("B".to_owned(), "C".to_owned())];
+    assert!(!store.rekey(chain));
+    // Two sources into one destination
+    let merge = vec![("A".to_owned(), "C".to_owned()), ("B".to_owned(), "C".to_owned())];
+    assert!(!store.rekey(merge));
+    // Self-rename
+    assert!(!store.rekey(vec![("A".to_owned(), "A".to_owned())]));
+    // Missing source: nothing moves, not even the valid pair
+    let missing = vec![("A".to_owned(), "C".to_owned()), ("X".to_owned(), "D".to_owned())];
+    assert!(!store.rekey(missing));
+
+    assert_eq!(store.get("A"), Some(1));
+    assert_eq!(store.get("B"), Some(2));
+    assert!(!store.contains_key("C"));
+    assert!(store.rekey(Vec::new()));
+}
+
+#[test]
+fn test_rename_atomic_rolls_back_on_missing_source() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.put("C", 3);
+    store.put("Z", 26);
+    for key in ["A", "B", "C", "X", "A'", "C'", "X'"] {
+        store.lock(key);
+    }
+    let before = store.sorted_entries();
+
+    // The second source is missing, so the first rename is undone
+    let pairs = vec![
+        ("A".to_owned(), "A'".to_owned()),
+        ("X".to_owned(), "X'".to_owned()),
+        ("C".to_owned(), "C'".to_owned()),
+    ];
+    assert!(!store.rename_atomic(pairs));
+    assert_eq!(store.sorted_entries(), before);
+    assert!(store.is_locked("A'"));
+
+    // Chains are allowed: each source only needs to exist at its turn
+    let chain = vec![("A".to_owned(), "B".to_owned()), ("B".to_owned(), "A'".to_owned())];
+    assert!(store.rename_atomic(chain));
+    assert_eq!(store.get("A'"), Some(1));
+    assert!(!store.contains_key("A"));
+    assert!(!store.contains_key("B"));
+    assert_eq!(store.get("Z"), Some(26));
+}
+
+#[test]
+fn test_observe_locked_and_present_key() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 7));
+    store.update_txn_id(3);
+    store.lock("A");
+
+    assert_eq!(
+        store.observe("A"),
+        KeyObservation { present: true, value: Some(7), locked: true, lock_owner: Some(3) }
+    );
+    assert_eq!(
+        store.observe("A'"),
+        KeyObservation { present: false, value: None, locked: false, lock_owner: None }
+    );
+}
+
+#[test]
+fn test_lock_status_before_rename() {
+    let mut store = KvStore::new();
+    store.put("A", 7);
+    assert_eq!(store.lock_status("A"), KeyStatus::Unlocked);
+    assert_eq!(store.lock_status("A'"), KeyStatus::Unlocked);
+
+    // The prepare phase locks both keys; A' does not exist yet
+    store.lock("A");
+    store.lock("A'");
+    assert_eq!(store.lock_status("A"), KeyStatus::LockedAndPresent);
+    assert_eq!(store.lock_status("A'"), KeyStatus::LockedAndAbsent);
+
+    assert_eq!(store.rename("A", "A'"), Some(7));
+    assert_eq!(store.lock_status("A"), KeyStatus::LockedAndAbsent);
+    assert_eq!(store.lock_status("A'"), KeyStatus::LockedAndPresent);
+}
+
+#[test]
+fn test_diff_apply_delta_round_trip() {
+    let mut primary = KvStore::new();
+    primary.put("A", 1);
+    primary.put("B", 2);
+    primary.put("C", 3);
+    let mut replica = KvStore::new();
+    replica.put("B", 5);
+    replica.put("C", 3);
+    replica.put("D", 9);
+
+    let deltas = primary.diff(&replica);
+    // Only the differences are shipped: drop D, add A, update B (C already matches)
+    assert_eq!(deltas.len(), 3);
+    assert!(replica.apply_delta(&deltas));
+    for key in ["A", "B", "C", "D"] {
+        assert_eq!(replica.get(key), primary.get(key), "{}", key);
+    }
+    // Equal stores have an empty diff
+    assert!(primary.diff(&replica).is_empty());
+}
+
+#[test]
+fn test_apply_delta_rejects_locked_key() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.put("B", 2);
+    store.lock("B");
+
+    let deltas = vec![KvDelta::Put("A".to_owned(), 10), KvDelta::Remove("B".to_owned())];
+    assert!(!store.apply_delta(&deltas));
+    // Nothing was applied, not even the change to the unlocked key
+    assert_eq!(store.get("A"), Some(1));
+    assert_eq!(store.get("B"), Some(2));
+
+    store.unlock("B");
+    assert!(store.apply_delta(&deltas));
+    assert_eq!(store.get("A"), Some(10));
+    assert!(!store.contains_key("B"));
+}
+
+#[test]
+fn test_lock_generation_detects_aba() {
+    let mut store = KvStore::new();
+    store.put("A", 1);
+    store.lock("A");
+    let seen = store.lock_generation("A");
+
+    // Another txn sneaks in: unlock, then re-lock
+    store.unlock("A");
+    store.lock("A");
+
+    // The boolean lock state is back where it was, but the generation moved
+    assert!(store.is_locked("A"));
+    assert_eq!(store.lock_generation("A"), seen + 1);
+    // Other keys are untouched
+    assert_eq!(store.lock_generation("B"), 0);
+}
+
+#[test]
+fn test_lock_generation_survives_crash() {
+    let mut store = KvStore::new();
+    store.lock("A");
+    store.crash();
+    assert!(!store.is_locked("A"));
+    assert_eq!(store.lock_generation("A"), 1);
+    store.lock_with_lease("A", 1, 10);
+    assert_eq!(store.lock_generation("A"), 2);
+}
+
+#[test]
+fn test_put_with_txn_updates_value_and_provenance() {
+    let mut store = KvStore::new();
+    store.update_txn_id(3);
+    assert!(store.put_with_txn("A", 10, 9));
+    assert_eq!(store.get("A"), Some(10));
+    // Stamped with the given txn, not the last seen one
+    assert_eq!(store.last_writer("A"), Some(9));
+    assert_eq!(store.get_last_seen_txn_id(), 3);
+
+    // Plain put still stamps with the last seen txn
+    assert!(store.put("A", 11));
+    assert_eq!(store.last_writer("A"), Some(3));
+
+    // Locked: neither field changes
+    store.lock("A");
+    assert!(!store.put_with_txn("A", 12, 10));
+    assert_eq!(store.get("A"), Some(11));
+    assert_eq!(store.last_writer("A"), Some(3));
+}
+
+#[test]
+fn test_rename_checked_matches_store_state() {
+    let mut store = KvStore::new();
+    store.put("A", 7);
+    store.lock("A");
+    store.lock("B");
+
+    let report = store.rename_checked("A", "B");
+    assert_eq!(
+        report,
+        RenameProof { moved: Some(7), old_was_present: true, new_is_present: true }
+    );
+    assert!(!store.contains_key("A"));
+    assert_eq!(store.get("B"), Some(7));
+
+    // Source gone: nothing moves and the report says so
+    store.lock("C");
+    let report = store.rename_checked("A", "C");
+    assert_eq!(
+        report,
+        RenameProof { moved: None, old_was_present: false, new_is_present: false }
+    );
+    assert!(!store.contains_key("C"));
+}
+
+#[test]
+fn test_value_sum() {
+    let mut store = KvStore::new();
+    assert_eq!(store.value_sum(), 0);
+    store.put("A", 40);
+    store.put("B", 2);
+    assert_eq!(store.value_sum(), 42);
+
+    // A deleted key still sits in the index but adds nothing
+    store.delete("B");
+    assert_eq!(store.value_sum(), 40);
+
+    store.put("C", u64::MAX);
+    assert_eq!(store.value_sum(), u64::MAX);
+}
+
+#[test]
+fn test_rename_keeps_value_sum() {
+    let mut store = KvStore::new();
+    store.put("A", 40);
+    store.put("B", 2);
+    store.lock("A");
+    store.lock("A'");
+
+    // The value moves to a key that had none: the sum is unchanged
+    assert_eq!(store.rename("A", "A'"), Some(40));
+    assert_eq!(store.value_sum(), 42);
+}
+
+#[test]
+fn test_check_data_accessible() {
+    let mut store = KvStore::new();
+    store.put("A", 123);
+    assert!(store.check_data_accessible("A", "A'"));
+
+    store.lock("A");
+    store.lock("A'");
+    store.rename("A", "A'");
+    assert!(store.check_data_accessible("A", "A'"));
+
+    // Corrupted: both keys present
+    store.unlock("A");
+    store.put("A", 7);
+    assert!(!store.check_data_accessible("A", "A'"));
+
+    // Neither key present
+    assert!(!KvStore::new().check_data_accessible("A", "A'"));
+}
+
+#[test]
+fn test_prepare_vote() {
+    let mut store = KvStore::new();
+    store.put("A", 42);
+    assert_eq!(store.prepare_vote("A", "A'", 1), Vote::Yes);
+
+    // A lease on the destination belongs to someone else
+    assert!(store.lock_with_lease("A'", 0, 100));
+    assert_eq!(store.prepare_vote("A", "A'", 1), Vote::NoLockedByOther);
+    store.unlock("A'");
+
+    // Locks taken for the store's own prepared txn do not count against it
+    store.lock("A");
+    store.lock("A'");
+    store.prepare(1);
+    assert_eq!(store.prepare_vote("A", "A'", 1), Vote::Yes);
+
+    // The same locks belong to another txn as far as txn 2 is concerned
+    assert_eq!(store.prepare_vote("A", "A'", 2), Vote::NoLockedByOther);
+
+    // A detection key stands in for the destination, but locks are still
+    // checked on the destination itself
+    assert_eq!(store.prepare_vote_with_detect_key("A", "A'", "B", 2), Vote::NoLockedByOther);
+    store.put("B", 1);
+    assert_eq!(store.prepare_vote_with_detect_key("A", "A'", "B", 1), Vote::NoAlreadyRenamed);
+    store.delete("B");
+
+    // Already renamed wins over any lock state
+    store.rename("A", "A'");
+    assert_eq!(store.prepare_vote("A", "A'", 1), Vote::NoAlreadyRenamed);
+}
+
+#[test]
+fn test_for_each_locked_matches_locked_keys() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 1));
+    assert!(store.put("C", 3));
+    store.lock("A");
+    store.lock("B");
+
+    let mut seen: Vec<(String, Option<u64>)> = Vec::new();
+    store.for_each_locked(|key, value| seen.push((key.to_string(), value)));
+
+    let keys: Vec<String> = seen.iter().map(|(key, _)| key.clone()).collect();
+    assert_eq!(keys, store.locked_keys());
+    seen.sort();
+    assert_eq!(seen, vec![("A".to_string(), Some(1)), ("B".to_string(), None)]);
+}
+
+#[test]
+fn test_freeze_blocks_all_mutators_until_unfreeze() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 1));
+    assert!(store.put("C", 3));
+    store.lock("B");
+
+    store.freeze();
+    assert!(store.is_frozen());
+    assert!(!store.put("A", 2));
+    assert!(!store.put("D", 4));
+    assert!(!store.delete("C"));
+    assert!(!store.lock("A"));
+    assert!(!store.is_locked("A"));
+    assert_eq!(store.rename("A", "A'"), None);
+    assert!(!store.unlock("B"));
+    assert!(store.is_locked("B"));
+    // A frozen store cannot take the locks a yes vote needs
+    assert_eq!(store.prepare_vote("A", "A'", 1), Vote::NoFrozen);
+    assert_eq!(store.try_rename("B", "C"), RenameResult::Frozen);
+    assert_eq!(store.get("A"), Some(1));
+    assert_eq!(store.get("C"), Some(3));
+    assert!(!store.contains_key("D"));
+
+    store.unfreeze();
+    assert!(!store.is_frozen());
+    assert!(store.put("A", 2));
+    assert!(store.delete("C"));
+    assert!(store.lock("A"));
+    assert!(store.lock("A'"));
+    assert_eq!(store.rename("A", "A'"), Some(2));
+    assert!(store.unlock("B"));
+    assert!(!store.is_locked("B"));
+}
+
+#[test]
+fn test_freeze_blocks_batch_and_txn_mutators() {
+    let mut store = KvStore::new();
+    store.update_txn_id(1);
+    assert!(store.put("A", 1));
+    let snapshot = store.snapshot();
+    assert!(store.put("C", 3));
+    store.lock("B");
+
+    store.freeze();
+    assert!(!store.put_with_txn("D", 4, 2));
+    assert!(!store.touch("A", 2));
+    assert!(!store.lock_with_lease("E", 2, 10));
+    assert!(!store.is_locked("E"));
+    assert!(!store.multi_put_locked(vec![("B".to_string(), 2)]));
+    assert!(!store.clear());
+    assert!(!store.rollback_to(&snapshot));
+    assert!(!store.rename_atomic(vec![]));
+    assert!(!store.contains_key("B"));
+    assert!(!store.contains_key("D"));
+    assert_eq!(store.get("C"), Some(3));
+    assert_eq!(store.last_writer("A"), Some(1));
+
+    store.unfreeze();
+    assert!(store.touch("A", 2));
+    assert!(store.multi_put_locked(vec![("B".to_string(), 2)]));
+    assert!(store.rollback_to(&snapshot));
+    assert!(!store.contains_key("C"));
+}
+
+#[test]
+fn test_epoch_tracks_successful_mutations() {
+    let mut store = KvStore::new();
+    assert_eq!(store.epoch(), 0);
+    assert!(store.put("A", 1));
+    assert_eq!(store.epoch(), 1);
+
+    // Reads leave the epoch alone, so a reader can validate its snapshot
+    let seen = store.epoch();
+    assert_eq!(store.get("A"), Some(1));
+    assert!(store.contains_key("A"));
+    assert_eq!(store.epoch(), seen);
+
+    store.lock("A");
+    store.lock("A'");
+    assert_eq!(store.epoch(), 3);
+    // A rejected write is not a modification
+    assert!(!store.put("A", 2));
+    assert_eq!(store.epoch(), 3);
+    assert_eq!(store.rename("A", "A'"), Some(1));
+    assert_eq!(store.epoch(), 4);
+    store.unlock("A");
+    store.unlock("A'");
+    assert!(store.delete("A'"));
+    assert_eq!(store.epoch(), 7);
+
+    store.freeze();
+    assert!(!store.put("B", 2));
+    store.lock("B");
+    assert_eq!(store.epoch(), 7);
+}
+
+#[test]
+fn test_epoch_advances_on_batch_and_lease_mutations() {
+    let mut store = KvStore::new();
+    assert!(store.put("A", 1));
+    let snapshot = store.snapshot();
+    let mut seen = store.epoch();
+
+    assert!(store.touch("A", 3));
+    assert!(store.epoch() > seen);
+    seen = store.epoch();
+
+    assert!(store.lock_with_lease("B", 4, 10));
+    assert!(store.epoch() > seen);
+    seen = store.epoch();
+
+    assert!(store.multi_put_locked(vec![("B".to_string(), 2)]));
+    assert!(store.epoch() > seen);
+    seen = store.epoch();
+
+    // Nothing expired yet: no release, no bump
+    assert_eq!(store.tick(5), 0);
+    assert_eq!(store.epoch(), seen);
+    assert_eq!(store.tick(10), 1);
+    assert!(store.epoch() > seen);
+    seen = store.epoch();
+
+    // Locked under txn 4, the last txn seen
+    store.lock("C");
+    seen = store.epoch();
+    assert_eq!(store.release_txn_locks(4), 1);
+    assert!(store.epoch() > seen);
+    seen = store.epoch();
+
+    assert!(store.rollback_to(&snapshot));
+    assert!(store.epoch() > seen);
+    seen = store.epoch();
+
+    // A rejected clear is not a modification
+    store.lock("D");
+    seen = store.epoch();
+    assert!(!store.clear());
+    assert_eq!(store.epoch(), seen);
+    store.unlock("D");
+    seen = store.epoch();
+    assert!(store.clear());
+    assert!(store.epoch() > seen);
+}
+
+#[test]
+fn test_recent_ops_in_order() {
+    let mut store = KvStore::new();
+    store.update_txn_id(4);
+    assert!(store.put("A", 1));
+    store.lock("A");
+    store.lock("A'");
+    assert!(!store.put("A", 2)); // rejected: not logged
+    assert_eq!(store.rename("A", "A'"), Some(1));
+    store.unlock("A");
+    store.unlock("A'");
+    assert!(store.delete("A'"));
+
+    let ops = store.recent_ops();
+    assert_eq!(ops.len(), 7);
+    assert!(matches!(&ops[0], KvOp::Put { key, txn_id: 4 } if key == "A"));
+    assert!(matches!(&ops[1], KvOp::Lock { key, txn_id: 4 } if key == "A"));
+    assert!(matches!(&ops[2], KvOp::Lock { key, txn_id: 4 } if key == "A'"));
+    assert!(matches!(&ops[3], KvOp::Rename { old, new, txn_id: 4 } if old == "A" && new == "A'"));
+    assert!(matches!(&ops[4], KvOp::Unlock { key, txn_id: 4 } if key == "A"));
+    assert!(matches!(&ops[5], KvOp::Unlock { key, txn_id: 4 } if key == "A'"));
+    assert!(matches!(&ops[6], KvOp::Delete { key, txn_id: 4 } if key == "A'"));
+}
+
+#[test]
+fn test_recent_ops_evicts_oldest() {
+    let mut store = KvStore::new();
+    for i in 0..(OP_LOG_CAP as u64 + 3) {
+        assert!(store.put_with_txn("A", i, i));
+    }
+    let ops = store.recent_ops();
+    assert_eq!(ops.len(), OP_LOG_CAP);
+    assert!(matches!(&ops[0], KvOp::Put { txn_id: 3, .. }));
+    assert!(matches!(ops.last(), Some(KvOp::Put { txn_id, .. }) if *txn_id == OP_LOG_CAP as u64 + 2));
+}
+
+#[cfg(feature = "hashmap")]
+#[test]
+fn kv_store_with_u64_keys() {
+    // A routing table keyed by store id, using the same lock/rename rules
+    let mut routes: KvStore<u64, u64> = KvStore::empty();
+    assert!(routes.put(&7, 70));
+    assert_eq!(routes.get(&7), Some(70));
+    assert!(routes.lock(&7));
+    assert!(routes.lock(&8));
+    assert!(!routes.put(&7, 1));
+    assert!(!routes.delete(&7));
+    assert_eq!(routes.rename(&7, &8), Some(70));
+    assert!(!routes.contains_key(&7));
+    assert_eq!(routes.get(&8), Some(70));
+    routes.unlock(&7);
+    routes.unlock(&8);
+    assert!(!routes.is_locked(&8));
+    assert!(routes.delete(&8));
+    assert_eq!(routes.get(&8), None);
+    assert!(matches!(routes.recent_ops()[3], KvOp::Rename { old: 7, new: 8, .. }));
+}
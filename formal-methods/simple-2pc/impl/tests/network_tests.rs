@@ -1,7 +1,10 @@
 // Runtime tests for the executable ExecNetwork and ExecMessage implementation.
 // These mirror the verified tests in src/network_v.rs but run under `cargo test`.
 
-use kv_store::{ExecMessage, ExecNetwork};
+use kv_store::{
+    CoordPhase, ExecMessage, ExecNetwork, MsgKind, NetOp, WireError, WireMessage, MAX_MSG_BYTES, NET_LOG_CAP,
+};
+use std::cmp::Ordering;
 
 #[test]
 fn test_new_network() {
@@ -51,6 +54,29 @@ fn test_receive() {
     assert!(net.is_empty());
 }
 
+#[test]
+fn test_receive_removes_first_copy() {
+    let a = ExecMessage::lock_req(0, 1);
+    let b = ExecMessage::lock_req(1, 1);
+    let build = || {
+        let mut net = ExecNetwork::new();
+        net.send(a.clone());
+        net.send(b.clone());
+        net.send(a.clone());
+        net
+    };
+    let mut net1 = build();
+    let mut net2 = build();
+
+    assert!(net1.receive(&a).is_some());
+    assert!(net2.receive(&a).is_some());
+    // The lowest-index copy (seq 0) is gone; the later copy keeps seq 2
+    assert!(net1.messages == vec![b.clone(), a.clone()]);
+    assert_eq!(net1.seqs(), [1, 2]);
+    assert!(net1.messages == net2.messages);
+    assert_eq!(net1.seqs(), net2.seqs());
+}
+
 #[test]
 fn test_receive_not_found() {
     let mut net = ExecNetwork::new();
@@ -126,6 +152,24 @@ fn test_different_message_types() {
     assert!(net.contains(&unlock_resp));
 }
 
+#[test]
+fn test_matches_store_txn_across_kinds() {
+    let msgs = [
+        ExecMessage::lock_req(2, 7),
+        ExecMessage::lock_resp(2, true, 7),
+        ExecMessage::lock_resp(2, false, 7),
+        ExecMessage::RenameReq { store: 2, txn_id: 7 },
+        ExecMessage::RenameResp { store: 2, txn_id: 7 },
+        ExecMessage::UnlockReq { store: 2, txn_id: 7 },
+        ExecMessage::UnlockResp { store: 2, txn_id: 7 },
+    ];
+    for msg in &msgs {
+        assert!(msg.matches_store_txn(2, 7));
+        assert!(!msg.matches_store_txn(3, 7));
+        assert!(!msg.matches_store_txn(2, 6));
+    }
+}
+
 #[test]
 fn test_message_accessors() {
     let msg = ExecMessage::lock_req(5, 42);
@@ -169,6 +213,96 @@ fn test_message_clone() {
     assert_eq!(msg.get_txn_id(), cloned.get_txn_id());
 }
 
+#[test]
+fn test_is_settled_for_txn() {
+    let mut net = ExecNetwork::new();
+    assert!(net.is_settled_for_txn(1));
+
+    let old_req = ExecMessage::lock_req(0, 1);
+    let old_resp = ExecMessage::lock_resp(0, true, 1);
+    net.send(old_req.clone());
+    net.send(ExecMessage::lock_req(0, 2));
+    net.send(old_resp.clone());
+    assert!(!net.is_settled_for_txn(1));
+
+    // Txn 1 drains while txn 2 traffic remains in flight
+    assert!(net.lose(&old_req));
+    assert!(!net.is_settled_for_txn(1));
+    assert!(net.lose(&old_resp));
+    assert!(net.is_settled_for_txn(1));
+    assert!(!net.is_settled_for_txn(2));
+    assert!(!net.is_empty());
+}
+
+#[test]
+fn test_message_with_txn() {
+    // Each variant keeps its kind, store and payload; only the txn changes
+    let cases = [
+        (ExecMessage::lock_req(2, 1), ExecMessage::lock_req(2, 9)),
+        (ExecMessage::lock_resp(2, true, 1), ExecMessage::lock_resp(2, true, 9)),
+        (ExecMessage::lock_resp(2, false, 1), ExecMessage::lock_resp(2, false, 9)),
+        (ExecMessage::rename_req(2, 1), ExecMessage::rename_req(2, 9)),
+        (ExecMessage::rename_resp(2, 1), ExecMessage::rename_resp(2, 9)),
+        (ExecMessage::unlock_req(2, 1), ExecMessage::unlock_req(2, 9)),
+        (ExecMessage::unlock_resp(2, 1), ExecMessage::unlock_resp(2, 9)),
+    ];
+    for (msg, expected) in cases {
+        let original = msg.clone();
+        let bumped = msg.with_txn(9);
+        assert!(bumped.eq(&expected));
+        assert_eq!(bumped.get_store(), original.get_store());
+        assert_eq!(bumped.is_request(), original.is_request());
+        assert!(!bumped.eq(&original));
+    }
+}
+
+#[test]
+fn test_valid_in_phase() {
+    let phases = [
+        CoordPhase::Idle,
+        CoordPhase::Preparing,
+        CoordPhase::Committed,
+        CoordPhase::Cleanup,
+        CoordPhase::Done,
+        CoordPhase::Crashed,
+    ];
+    let cases = [
+        (ExecMessage::lock_req(1, 1), None),
+        (ExecMessage::lock_resp(1, true, 1), Some(CoordPhase::Preparing)),
+        (ExecMessage::lock_resp(1, false, 1), Some(CoordPhase::Preparing)),
+        (ExecMessage::rename_req(1, 1), None),
+        (ExecMessage::rename_resp(1, 1), Some(CoordPhase::Committed)),
+        (ExecMessage::unlock_req(1, 1), None),
+        (ExecMessage::unlock_resp(1, 1), Some(CoordPhase::Cleanup)),
+    ];
+    for (msg, accepting) in cases {
+        for phase in phases {
+            assert_eq!(msg.valid_in_phase(phase), accepting == Some(phase), "{:?} in {:?}", msg, phase);
+        }
+    }
+}
+
+#[test]
+fn test_expected_response() {
+    let cases = [
+        (ExecMessage::lock_req(2, 5), true, Some(ExecMessage::lock_resp(2, true, 5))),
+        (ExecMessage::lock_req(2, 5), false, Some(ExecMessage::lock_resp(2, false, 5))),
+        (ExecMessage::rename_req(2, 5), true, Some(ExecMessage::rename_resp(2, 5))),
+        (ExecMessage::unlock_req(2, 5), false, Some(ExecMessage::unlock_resp(2, 5))),
+        (ExecMessage::lock_resp(2, true, 5), true, None),
+        (ExecMessage::rename_resp(2, 5), true, None),
+        (ExecMessage::unlock_resp(2, 5), true, None),
+    ];
+    for (msg, success, expected) in cases {
+        let resp = msg.expected_response(success);
+        assert_eq!(resp, expected, "{:?}", msg);
+        if let Some(resp) = resp {
+            assert!(resp.is_response());
+            assert!(resp.matches_store_txn(msg.get_store(), msg.get_txn_id()));
+        }
+    }
+}
+
 #[test]
 fn test_duplication_then_loss() {
     let mut net = ExecNetwork::new();
@@ -190,3 +324,617 @@ fn test_duplication_then_loss() {
     assert!(net.contains(&msg));
 }
 
+#[test]
+fn test_first_for_store() {
+    let mut net = ExecNetwork::new();
+    net.send(ExecMessage::lock_req(0, 1));
+    net.send(ExecMessage::lock_resp(1, true, 1));
+    net.send(ExecMessage::rename_req(1, 1));
+
+    // Oldest message for store 1 is returned, and it stays in the network
+    let found = net.first_for_store(1).unwrap();
+    assert!(found.eq(&ExecMessage::lock_resp(1, true, 1)));
+    assert_eq!(net.len(), 3);
+
+    assert!(net.first_for_store(2).is_none());
+}
+
+#[test]
+fn test_receive_in_order() {
+    let mut net = ExecNetwork::new();
+    net.send(ExecMessage::UnlockReq { store: 2, txn_id: 1 });
+    net.send(ExecMessage::LockReq { store: 0, txn_id: 1 });
+    net.send(ExecMessage::RenameReq { store: 1, txn_id: 1 });
+    assert_eq!(net.seqs(), [0, 1, 2]);
+
+    // Strictly in send order, regardless of message kind or store
+    assert!(matches!(net.receive_in_order(), Some(ExecMessage::UnlockReq { store: 2, txn_id: 1 })));
+    assert!(matches!(net.receive_in_order(), Some(ExecMessage::LockReq { store: 0, txn_id: 1 })));
+    assert!(matches!(net.receive_in_order(), Some(ExecMessage::RenameReq { store: 1, txn_id: 1 })));
+    assert!(net.receive_in_order().is_none());
+}
+
+#[test]
+fn test_send_drops_when_sequence_numbers_run_out() {
+    let mut net = ExecNetwork::new();
+    net.next_seq = u64::MAX - 1;
+    let msg = ExecMessage::LockReq { store: 0, txn_id: 1 };
+    net.send(msg.clone());
+    assert_eq!(net.seqs(), [u64::MAX - 1]);
+    assert_eq!(net.next_seq, u64::MAX);
+
+    // No number is left to give a further copy, so it is dropped, not reused
+    assert!(!net.duplicate(&msg));
+    net.send(ExecMessage::RenameReq { store: 1, txn_id: 1 });
+    assert_eq!(net.len(), 1);
+    assert_eq!(net.seqs(), [u64::MAX - 1]);
+}
+
+#[test]
+fn test_dedup() {
+    let mut net = ExecNetwork::new();
+    let msg = ExecMessage::LockReq { store: 0, txn_id: 1 };
+    let other = ExecMessage::RenameReq { store: 1, txn_id: 1 };
+    net.send(msg.clone());
+    net.send(other.clone());
+    net.send(msg.clone());
+    net.send(msg.clone());
+
+    assert_eq!(net.dedup(), 2);
+    assert_eq!(net.count(&msg), 1);
+    assert_eq!(net.count(&other), 1);
+    // First copies survive with their original sequence numbers
+    assert_eq!(net.seqs(), [0, 1]);
+    assert_eq!(net.dedup(), 0);
+}
+
+#[test]
+fn test_pop_oldest() {
+    let mut net = ExecNetwork::new();
+    net.send(ExecMessage::RenameReq { store: 1, txn_id: 1 });
+    net.send(ExecMessage::LockReq { store: 0, txn_id: 1 });
+    net.send(ExecMessage::UnlockResp { store: 2, txn_id: 1 });
+
+    // Front to back, whatever the kind or store
+    assert!(matches!(net.pop_oldest(), Some(ExecMessage::RenameReq { store: 1, txn_id: 1 })));
+    assert_eq!(net.seqs(), [1, 2]);
+    assert!(matches!(net.pop_oldest(), Some(ExecMessage::LockReq { store: 0, txn_id: 1 })));
+    assert!(matches!(net.pop_oldest(), Some(ExecMessage::UnlockResp { store: 2, txn_id: 1 })));
+    assert!(net.pop_oldest().is_none());
+    assert!(net.is_empty());
+
+    // Each pop is logged as a delivery, not a loss
+    let log = net.log();
+    assert!(log[3..].iter().all(|op| matches!(op, NetOp::Deliver(_))));
+    assert!(!log.iter().any(|op| matches!(op, NetOp::Lose(_))));
+}
+
+#[test]
+fn test_receive_and_lose_logged_apart() {
+    let msg = ExecMessage::lock_req(0, 1);
+    let mut net = ExecNetwork::new();
+    net.send(msg.clone());
+    net.send(msg.clone());
+
+    assert!(net.receive(&msg).is_some());
+    assert!(net.lose(&msg));
+    assert!(matches!(net.log_since(2), [NetOp::Deliver(a), NetOp::Lose(b)] if a.eq(&msg) && b.eq(&msg)));
+}
+
+#[test]
+fn test_drain_all_in_arrival_order() {
+    let mut net = ExecNetwork::new();
+    let sent = vec![
+        ExecMessage::LockReq { store: 0, txn_id: 1 },
+        ExecMessage::LockResp { store: 1, success: true, txn_id: 1 },
+        ExecMessage::RenameReq { store: 2, txn_id: 1 },
+        ExecMessage::UnlockReq { store: 0, txn_id: 1 },
+        ExecMessage::UnlockResp { store: 1, txn_id: 1 },
+    ];
+    for msg in &sent[..4] {
+        net.send(msg.clone());
+    }
+    net.send_delayed(sent[4].clone(), 10);
+
+    let drained = net.drain_all();
+    assert_eq!(drained.len(), 5);
+    for (got, want) in drained.iter().zip(&sent) {
+        assert!(got.eq(want));
+    }
+    assert!(net.is_empty());
+    assert!(net.seqs().is_empty());
+    assert!(net.delays.is_empty());
+    // Each drained message is logged as a delivery, not a loss
+    let log = net.log_since(5);
+    assert_eq!(log.len(), 5);
+    assert!(log.iter().all(|op| matches!(op, NetOp::Deliver(_))));
+    assert!(net.drain_all().is_empty());
+}
+
+#[test]
+fn test_receive_in_order_after_receive_and_duplicate() {
+    let mut net = ExecNetwork::new();
+    let lock = ExecMessage::LockReq { store: 0, txn_id: 1 };
+    net.send(lock.clone());
+    net.send(ExecMessage::RenameReq { store: 1, txn_id: 1 });
+    net.send(ExecMessage::UnlockReq { store: 2, txn_id: 1 });
+
+    // The duplicate is a new copy on the wire, so it is ordered after everything sent so far
+    assert!(net.duplicate(&lock));
+    assert!(net.receive(&ExecMessage::RenameReq { store: 1, txn_id: 1 }).is_some());
+    assert_eq!(net.seqs(), [0, 2, 3]);
+
+    assert!(matches!(net.receive_in_order(), Some(ExecMessage::LockReq { store: 0, txn_id: 1 })));
+    assert!(matches!(net.receive_in_order(), Some(ExecMessage::UnlockReq { store: 2, txn_id: 1 })));
+    assert!(matches!(net.receive_in_order(), Some(ExecMessage::LockReq { store: 0, txn_id: 1 })));
+    assert!(net.is_empty());
+}
+
+#[test]
+fn test_wire_message_round_trip() {
+    let msgs = vec![
+        ExecMessage::LockReq { store: 0, txn_id: 1 },
+        ExecMessage::LockResp { store: 1, success: true, txn_id: 2 },
+        ExecMessage::LockResp { store: 2, success: false, txn_id: 3 },
+        ExecMessage::RenameReq { store: 3, txn_id: 4 },
+        ExecMessage::RenameResp { store: 4, txn_id: 5 },
+        ExecMessage::UnlockReq { store: 5, txn_id: 6 },
+        ExecMessage::UnlockResp { store: 6, txn_id: 7 },
+        ExecMessage::Decide { store: 7, commit: true, txn_id: 8 },
+        ExecMessage::Decide { store: 8, commit: false, txn_id: 9 },
+        ExecMessage::MigrateVote { store: 9, txn_id: 10, value: 11 },
+        ExecMessage::MigrateApply { store: 10, txn_id: 11, value: 12 },
+    ];
+    for msg in &msgs {
+        let wire = WireMessage::from(msg);
+        assert_eq!(wire.store, msg.get_store());
+        assert_eq!(wire.txn_id, msg.get_txn_id());
+        let back = ExecMessage::try_from(wire).unwrap();
+        assert!(back.eq(msg));
+    }
+}
+
+#[test]
+fn test_wire_message_rejects_malformed() {
+    let wire = |kind: &str, success| WireMessage { kind: kind.to_owned(), store: 0, txn_id: 1, success, value: None };
+
+    assert_eq!(
+        ExecMessage::try_from(wire("Bogus", None)).err(),
+        Some(WireError::UnknownKind("Bogus".to_owned()))
+    );
+    assert_eq!(ExecMessage::try_from(wire("LockResp", None)).err(), Some(WireError::MissingSuccess));
+    assert_eq!(ExecMessage::try_from(wire("Decide", None)).err(), Some(WireError::MissingSuccess));
+    assert_eq!(
+        ExecMessage::try_from(wire("LockReq", Some(true))).err(),
+        Some(WireError::UnexpectedSuccess)
+    );
+    assert_eq!(ExecMessage::try_from(wire("MigrateApply", None)).err(), Some(WireError::MissingValue));
+    let with_value = |kind: &str| WireMessage { value: Some(5), ..wire(kind, None) };
+    assert_eq!(ExecMessage::try_from(with_value("RenameReq")).err(), Some(WireError::UnexpectedValue));
+    assert_eq!(
+        ExecMessage::try_from(with_value("MigrateVote")).ok(),
+        Some(ExecMessage::migrate_vote(0, 1, 5))
+    );
+}
+
+#[test]
+fn test_kind_histogram() {
+    let mut net = ExecNetwork::new();
+    assert_eq!(net.kind_histogram(), [0; 9]);
+
+    net.send(ExecMessage::LockReq { store: 0, txn_id: 1 });
+    net.send(ExecMessage::LockReq { store: 1, txn_id: 1 });
+    net.send(ExecMessage::LockReq { store: 2, txn_id: 1 });
+    net.send(ExecMessage::LockResp { store: 0, success: false, txn_id: 1 });
+    net.send(ExecMessage::RenameResp { store: 1, txn_id: 1 });
+    net.send(ExecMessage::UnlockReq { store: 2, txn_id: 1 });
+    net.send(ExecMessage::UnlockReq { store: 2, txn_id: 1 });
+
+    let hist = net.kind_histogram();
+    assert_eq!(hist, [3, 1, 0, 1, 2, 0, 0, 0, 0]);
+    assert_eq!(hist[MsgKind::LockReq.index()], 3);
+    assert_eq!(hist[MsgKind::UnlockReq.index()], 2);
+    assert_eq!(hist.iter().sum::<usize>(), net.len());
+}
+
+#[test]
+fn test_message_kind() {
+    assert_eq!(ExecMessage::LockReq { store: 0, txn_id: 1 }.kind(), MsgKind::LockReq);
+    assert_eq!(ExecMessage::LockResp { store: 0, success: true, txn_id: 1 }.kind(), MsgKind::LockResp);
+    assert_eq!(ExecMessage::RenameReq { store: 0, txn_id: 1 }.kind(), MsgKind::RenameReq);
+    assert_eq!(ExecMessage::RenameResp { store: 0, txn_id: 1 }.kind(), MsgKind::RenameResp);
+    assert_eq!(ExecMessage::UnlockReq { store: 0, txn_id: 1 }.kind(), MsgKind::UnlockReq);
+    assert_eq!(ExecMessage::UnlockResp { store: 0, txn_id: 1 }.kind(), MsgKind::UnlockResp);
+    assert_eq!(ExecMessage::decide(0, true, 1).kind(), MsgKind::Decide);
+}
+
+#[test]
+fn test_merge_partitions() {
+    let a = ExecMessage::LockReq { store: 0, txn_id: 1 };
+    let b = ExecMessage::LockResp { store: 1, success: true, txn_id: 1 };
+    let c = ExecMessage::UnlockReq { store: 2, txn_id: 1 };
+
+    // Messages buffered on each side of a partition
+    let mut left = ExecNetwork::new();
+    left.send(a.clone());
+    left.send(b.clone());
+    let mut right = ExecNetwork::new();
+    right.send(a.clone());
+    right.send(c.clone());
+    right.send(c.clone());
+
+    left.merge(right);
+    assert_eq!(left.len(), 5);
+    assert_eq!(left.count(&a), 2);
+    assert_eq!(left.count(&b), 1);
+    assert_eq!(left.count(&c), 2);
+
+    // Merged messages keep arriving after the ones already queued
+    assert!(left.receive_in_order().unwrap().eq(&a));
+    assert!(left.receive_in_order().unwrap().eq(&b));
+    assert!(left.receive_in_order().unwrap().eq(&a));
+}
+
+#[test]
+fn test_merge_empty() {
+    let mut net = ExecNetwork::new();
+    net.send(ExecMessage::RenameReq { store: 0, txn_id: 3 });
+    net.merge(ExecNetwork::new());
+    assert_eq!(net.len(), 1);
+}
+
+#[test]
+fn test_next_delivery_time_is_earliest_delay() {
+    let mut net = ExecNetwork::new();
+    assert_eq!(net.next_delivery_time(), None);
+
+    net.send_delayed(ExecMessage::lock_req(0, 1), 30);
+    net.send_delayed(ExecMessage::lock_req(1, 1), 10);
+    net.send_delayed(ExecMessage::lock_req(2, 1), 20);
+    net.send(ExecMessage::lock_req(3, 1));
+
+    assert_eq!(net.next_delivery_time(), Some(10));
+    assert_eq!(net.pending_delayed(0), 3);
+    assert_eq!(net.pending_delayed(10), 2);
+    assert_eq!(net.pending_delayed(25), 1);
+    assert_eq!(net.pending_delayed(30), 0);
+
+    // Once the earliest message is delivered, the next event is the following one
+    assert!(net.receive(&ExecMessage::lock_req(1, 1)).is_some());
+    assert_eq!(net.next_delivery_time(), Some(20));
+    assert_eq!(net.pending_delayed(0), 2);
+}
+
+#[test]
+fn test_delays_survive_split_and_merge() {
+    let mut net = ExecNetwork::new();
+    net.send_delayed(ExecMessage::lock_req(0, 1), 5);
+    net.send_delayed(ExecMessage::lock_req(1, 1), 7);
+
+    let mut other = net.split(&[1]);
+    assert_eq!(net.next_delivery_time(), Some(5));
+    assert_eq!(other.next_delivery_time(), Some(7));
+
+    // Healing the partition keeps the delay of the message that was split off
+    other.send(ExecMessage::lock_req(1, 2));
+    net.merge(other);
+    assert_eq!(net.len(), 3);
+    assert_eq!(net.pending_delayed(5), 1);
+    assert_eq!(net.next_delivery_time(), Some(5));
+}
+
+#[test]
+fn test_split_isolates_store_traffic() {
+    let a0 = ExecMessage::LockReq { store: 0, txn_id: 1 };
+    let b0 = ExecMessage::RenameReq { store: 0, txn_id: 1 };
+    let a1 = ExecMessage::LockReq { store: 1, txn_id: 1 };
+    let r1 = ExecMessage::LockResp { store: 1, success: true, txn_id: 1 };
+
+    let mut net = ExecNetwork::new();
+    net.send(a0.clone());
+    net.send(a1.clone());
+    net.send(b0.clone());
+    net.send(r1.clone());
+    net.send(a1.clone());
+
+    let isolated = net.split(&[1]);
+    assert_eq!(net.len(), 2);
+    assert_eq!(net.count(&a0), 1);
+    assert_eq!(net.count(&b0), 1);
+    assert_eq!(net.count_for_store(1), 0);
+    assert_eq!(isolated.len(), 3);
+    assert_eq!(isolated.count(&a1), 2);
+    assert_eq!(isolated.count(&r1), 1);
+    assert_eq!(isolated.count_for_store(0), 0);
+
+    // Both sides keep the original send order
+    let mut isolated = isolated;
+    assert!(isolated.receive_in_order().unwrap().eq(&a1));
+    assert!(isolated.receive_in_order().unwrap().eq(&r1));
+    assert!(net.receive_in_order().unwrap().eq(&a0));
+
+    // Merging heals the partition
+    net.merge(isolated);
+    assert_eq!(net.count(&b0), 1);
+    assert_eq!(net.count(&a1), 1);
+}
+
+#[test]
+fn test_split_by_no_stores_keeps_everything() {
+    let mut net = ExecNetwork::new();
+    net.send(ExecMessage::UnlockReq { store: 0, txn_id: 2 });
+    let other = net.split(&[]);
+    assert_eq!(net.len(), 1);
+    assert!(other.is_empty());
+}
+
+#[test]
+fn test_message_ordering_across_variants() {
+    let mut msgs = vec![
+        ExecMessage::UnlockResp { store: 0, txn_id: 1 },
+        ExecMessage::LockResp { store: 0, success: true, txn_id: 1 },
+        ExecMessage::RenameReq { store: 0, txn_id: 1 },
+        ExecMessage::LockResp { store: 0, success: false, txn_id: 1 },
+        ExecMessage::LockReq { store: 1, txn_id: 1 },
+        ExecMessage::LockReq { store: 0, txn_id: 2 },
+        ExecMessage::LockReq { store: 0, txn_id: 1 },
+    ];
+    msgs.sort();
+    let expected = [
+        ExecMessage::LockReq { store: 0, txn_id: 1 },
+        ExecMessage::LockReq { store: 0, txn_id: 2 },
+        ExecMessage::LockReq { store: 1, txn_id: 1 },
+        ExecMessage::LockResp { store: 0, success: false, txn_id: 1 },
+        ExecMessage::LockResp { store: 0, success: true, txn_id: 1 },
+        ExecMessage::RenameReq { store: 0, txn_id: 1 },
+        ExecMessage::UnlockResp { store: 0, txn_id: 1 },
+    ];
+    assert!(msgs.iter().zip(expected.iter()).all(|(a, b)| a == b));
+
+    // Kind dominates store and txn
+    let a = ExecMessage::LockReq { store: 9, txn_id: 9 };
+    let b = ExecMessage::RenameResp { store: 0, txn_id: 0 };
+    assert_eq!(a.cmp(&b), Ordering::Less);
+    assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+}
+
+#[test]
+fn test_receive_min_independent_of_send_order() {
+    let msgs = [
+        ExecMessage::UnlockReq { store: 1, txn_id: 1 },
+        ExecMessage::LockReq { store: 2, txn_id: 1 },
+        ExecMessage::LockReq { store: 0, txn_id: 1 },
+    ];
+
+    let mut forward = ExecNetwork::new();
+    let mut backward = ExecNetwork::new();
+    for m in msgs.iter() {
+        forward.send(m.clone());
+    }
+    for m in msgs.iter().rev() {
+        backward.send(m.clone());
+    }
+
+    for _ in 0..msgs.len() {
+        let f = forward.receive_min().unwrap();
+        let b = backward.receive_min().unwrap();
+        assert!(f == b);
+    }
+    assert!(forward.receive_min().is_none());
+    assert!(backward.receive_min().is_none());
+}
+
+#[test]
+fn test_replay_trace_rebuilds_contents() {
+    let a = ExecMessage::LockReq { store: 0, txn_id: 1 };
+    let b = ExecMessage::LockResp { store: 0, success: true, txn_id: 1 };
+    let c = ExecMessage::RenameReq { store: 1, txn_id: 1 };
+
+    let mut net = ExecNetwork::new();
+    net.send(a.clone());
+    net.send(b.clone());
+    net.duplicate(&a);
+    net.lose(&b);
+    net.send(c.clone());
+    net.receive_in_order();
+
+    let trace = net.record_trace();
+    assert_eq!(trace.len(), 6);
+    assert!(matches!(&trace[3], NetOp::Lose(m) if m.eq(&b)));
+    assert!(matches!(&trace[5], NetOp::Deliver(m) if m.eq(&a)));
+
+    let rebuilt = ExecNetwork::from_trace(&trace);
+    assert_eq!(rebuilt.len(), net.len());
+    for m in [&a, &b, &c] {
+        assert_eq!(rebuilt.count(m), net.count(m));
+    }
+    // Replaying preserves arrival order too
+    assert_eq!(rebuilt.seqs(), net.seqs());
+    assert_eq!(rebuilt.record_trace().len(), trace.len());
+}
+
+#[test]
+fn test_log_is_capped() {
+    let mut net = ExecNetwork::new();
+    for txn in 0..NET_LOG_CAP as u64 {
+        net.send(ExecMessage::lock_req(0, txn));
+    }
+    assert!(net.log_complete());
+    assert_eq!(net.log().len(), NET_LOG_CAP);
+
+    // The next operation discards the older half of the log
+    let last = ExecMessage::unlock_req(1, 1);
+    net.send(last.clone());
+    assert!(net.log().len() <= NET_LOG_CAP);
+    assert!(!net.log_complete());
+    assert_eq!(net.ops_logged(), NET_LOG_CAP as u64 + 1);
+    assert!(matches!(net.log().last(), Some(NetOp::Send(m)) if m.eq(&last)));
+
+    // Positions are absolute, so they survive the discard
+    let since = net.ops_logged();
+    net.lose(&last);
+    assert!(matches!(net.log_since(since), [NetOp::Lose(m)] if m.eq(&last)));
+    assert_eq!(net.log_since(0).len(), net.log().len());
+}
+
+#[test]
+fn test_replay_empty_trace() {
+    let net = ExecNetwork::from_trace(&[]);
+    assert!(net.is_empty());
+    assert!(net.record_trace().is_empty());
+}
+
+#[test]
+fn test_per_store_cap_rejects_full_store_only() {
+    let mut net = ExecNetwork::new().with_per_store_cap(2);
+    net.send(ExecMessage::lock_req(0, 1));
+    net.send(ExecMessage::lock_req(0, 2));
+    // Store 0 is full: the third message is dropped
+    net.send(ExecMessage::lock_req(0, 3));
+    assert_eq!(net.count_for_store(0), 2);
+    assert!(!net.contains(&ExecMessage::lock_req(0, 3)));
+
+    // Store 1 still accepts up to its own cap
+    net.send(ExecMessage::lock_req(1, 1));
+    net.send(ExecMessage::lock_req(1, 2));
+    assert_eq!(net.count_for_store(1), 2);
+    assert_eq!(net.len(), 4);
+
+    // Draining store 0 frees room for it again
+    assert!(net.receive(&ExecMessage::lock_req(0, 1)).is_some());
+    net.send(ExecMessage::lock_req(0, 3));
+    assert!(net.contains(&ExecMessage::lock_req(0, 3)));
+    assert_eq!(net.count_for_store(0), 2);
+}
+
+#[test]
+fn test_per_store_cap_counts_only_requests_to_the_store() {
+    let mut net = ExecNetwork::new().with_per_store_cap(1);
+    // Responses from store 0 do not fill its inbox
+    net.send(ExecMessage::lock_resp(0, true, 1));
+    net.send(ExecMessage::rename_resp(0, 1));
+    assert_eq!(net.count_for_store(0), 0);
+    net.send(ExecMessage::lock_req(0, 2));
+    assert!(net.contains(&ExecMessage::lock_req(0, 2)));
+    assert_eq!(net.count_for_store(0), 1);
+
+    // A full inbox still lets the store's responses through
+    net.send(ExecMessage::unlock_req(0, 2));
+    assert!(!net.contains(&ExecMessage::unlock_req(0, 2)));
+    net.send(ExecMessage::lock_resp(0, false, 2));
+    assert!(net.contains(&ExecMessage::lock_resp(0, false, 2)));
+    assert_eq!(net.len(), 4);
+}
+
+#[test]
+fn test_uncapped_network_accepts_everything() {
+    let mut net = ExecNetwork::new();
+    for txn in 0..10 {
+        net.send(ExecMessage::lock_req(0, txn));
+    }
+    assert_eq!(net.count_for_store(0), 10);
+}
+
+#[test]
+fn test_total_bytes_n_messages() {
+    let size = ExecMessage::lock_req(0, 0).byte_size();
+    let mut net = ExecNetwork::new();
+    for txn in 0..10 {
+        net.send(ExecMessage::lock_req(txn % 3, txn));
+    }
+    assert_eq!(net.total_bytes(), 10 * size);
+
+    // LockResp carries the extra success flag, a migration message its 8-byte
+    // value (the largest message)
+    assert_eq!(ExecMessage::lock_resp(0, true, 1).byte_size(), size + 1);
+    let resp = ExecMessage::migrate_vote(0, 1, 7);
+    assert_eq!(resp.byte_size(), MAX_MSG_BYTES);
+    net.send(resp);
+    assert_eq!(net.total_bytes(), 10 * size + MAX_MSG_BYTES);
+
+    // Delivering a message frees its bytes
+    net.receive(&ExecMessage::lock_req(0, 0));
+    assert_eq!(net.total_bytes(), 9 * size + MAX_MSG_BYTES);
+}
+
+#[test]
+fn test_is_well_formed() {
+    let stores = [0, 1, 2];
+    let mut net = ExecNetwork::new();
+    assert!(net.is_well_formed(&stores, 0));
+
+    net.send(ExecMessage::lock_req(0, 3));
+    net.send(ExecMessage::unlock_resp(2, 1));
+    assert!(net.is_well_formed(&stores, 3));
+
+    // A txn id newer than the coordinator's is rejected
+    assert!(!net.is_well_formed(&stores, 2));
+
+    // So is a message for a store outside the system
+    net.send(ExecMessage::rename_req(7, 1));
+    assert!(!net.is_well_formed(&stores, 3));
+    net.receive(&ExecMessage::rename_req(7, 1));
+    assert!(net.is_well_formed(&stores, 3));
+}
+
+#[test]
+fn test_log_summary_redacts_txn() {
+    assert_eq!(ExecMessage::lock_req(0, 7).log_summary(false), "<LockReq store=0 txn=7>");
+    assert_eq!(ExecMessage::lock_req(0, 7).log_summary(true), "<LockReq store=0>");
+    assert_eq!(
+        ExecMessage::lock_resp(2, false, 7).log_summary(false),
+        "<LockResp store=2 txn=7 success=false>"
+    );
+    assert_eq!(ExecMessage::lock_resp(2, false, 7).log_summary(true), "<LockResp store=2 success=false>");
+    assert_eq!(ExecMessage::decide(1, true, 3).log_summary(true), "<Decide store=1 commit=true>");
+}
+
+#[test]
+fn test_apply_loss_schedule_loses_scheduled_messages() {
+    let mut net = ExecNetwork::new();
+    net.send(ExecMessage::lock_req(0, 1));
+    net.send(ExecMessage::lock_req(1, 1));
+    net.send(ExecMessage::lock_req(2, 1));
+
+    // Store 3's request was never sent, so only two of the three are lost
+    let schedule = vec![
+        ExecMessage::lock_req(2, 1),
+        ExecMessage::lock_req(3, 1),
+        ExecMessage::lock_req(0, 1),
+    ];
+    assert_eq!(net.apply_loss_schedule(&schedule), 2);
+    assert_eq!(net.len(), 1);
+    assert!(net.contains(&ExecMessage::lock_req(1, 1)));
+    let trace = net.record_trace();
+    assert_eq!(trace.len(), 5);
+    assert!(matches!(&trace[3], NetOp::Lose(m) if m.eq(&ExecMessage::lock_req(2, 1))));
+    assert!(matches!(&trace[4], NetOp::Lose(m) if m.eq(&ExecMessage::lock_req(0, 1))));
+
+    // Replaying the schedule finds nothing left to lose
+    assert_eq!(net.apply_loss_schedule(&schedule), 0);
+    assert_eq!(net.len(), 1);
+}
+
+#[test]
+fn test_tag_round_trip() {
+    let msgs = [
+        ExecMessage::lock_req(1, 2),
+        ExecMessage::lock_resp(1, false, 2),
+        ExecMessage::rename_req(1, 2),
+        ExecMessage::rename_resp(1, 2),
+        ExecMessage::unlock_req(1, 2),
+        ExecMessage::unlock_resp(1, 2),
+        ExecMessage::decide(1, true, 2),
+        ExecMessage::migrate_vote(1, 2, 3),
+        ExecMessage::migrate_apply(1, 2, 3),
+    ];
+    for (i, m) in msgs.iter().enumerate() {
+        assert_eq!(m.tag() as usize, i);
+        assert_eq!(m.tag() as usize, m.kind().index());
+        let rebuilt = ExecMessage::from_tag(m.tag(), m.get_store(), m.get_txn_id(), m.flag(), m.value());
+        assert_eq!(rebuilt.as_ref(), Some(m));
+    }
+    assert_eq!(ExecMessage::from_tag(9, 1, 2, false, 0), None);
+    // The flag only matters for LockResp and Decide, the value for the migration kinds
+    assert_eq!(ExecMessage::from_tag(0, 1, 2, true, 5), Some(ExecMessage::lock_req(1, 2)));
+}
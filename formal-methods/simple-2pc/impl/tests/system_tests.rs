@@ -1,7 +1,11 @@
 // Runtime tests for the executable ExecSystem implementation.
 // These mirror the verified tests in src/system_v.rs but run under `cargo test`.
 
-use kv_store::{CoordPhase, ExecMessage, ExecSystem};
+use kv_store::{
+    client_rename, CoordPhase, DeadlockKind, Decision, DuplicatingUniform, ExecMessage, ExecSystem, KvStore, LossyUniform, NET_LOG_CAP,
+    ProtocolConfig, ProtocolError, QuiescenceError, Reliable,
+};
+use vstd::prelude::Ghost;
 
 #[test]
 fn test_new_system() {
@@ -93,6 +97,62 @@ fn test_lock_failure() {
     assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
 }
 
+#[test]
+fn test_lock_failure_custom_detect_key() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+
+    // "B" exists, but A' does not
+    sys.store_put(0, "B", 7);
+
+    sys.coord_send_lock_req(0);
+
+    // Store treats "B" as the "already renamed" marker - lock fails
+    assert!(sys.store_handle_lock_req_with_detect_key(0, txn_id, "B"));
+    assert!(sys.coord_recv_lock_resp_failure(0));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+    assert!(!sys.get_store(0).is_locked("A"));
+}
+
+#[test]
+fn test_recv_from_out_of_range_store() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.coord_send_lock_req(0);
+    let txn_id = sys.get_txn_id();
+
+    // Inject a success response from store 5, which does not exist
+    let resp = ExecMessage::lock_resp(5, true, txn_id);
+    sys.net.send(resp.clone());
+
+    assert!(!sys.coord_recv_lock_resp_success(5));
+    assert!(!sys.coord.has_lock(5));
+    // Message is left untouched in the network
+    assert!(sys.net.contains(&resp));
+}
+
+#[test]
+fn test_recv_rejects_wrong_phase_and_duplicate() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.coord_send_lock_req(0);
+    let txn_id = sys.get_txn_id();
+
+    // A RenameResp while still preparing is not recorded and stays queued
+    let early = ExecMessage::rename_resp(0, txn_id);
+    sys.net.send(early.clone());
+    assert!(!sys.coord_recv_rename_resp(0));
+    assert!(!sys.coord.has_renamed(0));
+    assert!(sys.net.contains(&early));
+
+    // A second grant from a store already credited is not consumed
+    let grant = ExecMessage::lock_resp(0, true, txn_id);
+    sys.net.send(grant.clone());
+    assert!(sys.coord_recv_lock_resp_success(0));
+    sys.net.send(grant.clone());
+    assert!(!sys.coord_recv_lock_resp_success(0));
+    assert!(sys.net.contains(&grant));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Preparing);
+}
+
 #[test]
 fn test_crash_recovery_committed() {
     let mut sys = ExecSystem::new(1, "A", "A'", 42);
@@ -118,6 +178,39 @@ fn test_crash_recovery_committed() {
     assert_eq!(sys.get_coord_phase(), CoordPhase::Committed); // Resume commit
 }
 
+#[test]
+fn test_decision_is_final_across_crash() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    assert_eq!(sys.decision(), Decision::Undecided);
+
+    sys.coord_send_lock_req(0);
+    let txn_id = sys.get_txn_id();
+    sys.store_handle_lock_req(0, txn_id);
+    sys.coord_recv_lock_resp_success(0);
+    assert_eq!(sys.decision(), Decision::Undecided);
+
+    sys.coord_decide_commit();
+    assert_eq!(sys.decision(), Decision::Commit);
+
+    sys.coord_crash();
+    assert_eq!(sys.decision(), Decision::Commit);
+    sys.coord_recover();
+    assert_eq!(sys.decision(), Decision::Commit);
+}
+
+#[test]
+fn test_decision_abort_after_uncommitted_crash() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    sys.coord_send_lock_req(0);
+    assert_eq!(sys.decision(), Decision::Undecided);
+
+    // A crash without a WAL commit always recovers into abort
+    sys.coord_crash();
+    assert_eq!(sys.decision(), Decision::Abort);
+    sys.coord_recover();
+    assert_eq!(sys.decision(), Decision::Abort);
+}
+
 #[test]
 fn test_crash_recovery_not_committed() {
     let mut sys = ExecSystem::new(1, "A", "A'", 42);
@@ -182,6 +275,38 @@ fn test_stale_message_rejection() {
     assert!(sys.store_is_stale_txn_id(0, old_txn_id));
 }
 
+#[test]
+fn test_stale_rename_harmless_after_recovery() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    let old_txn = sys.get_txn_id();
+    sys.coord_send_lock_req(0);
+    sys.store_handle_lock_req(0, old_txn);
+    sys.coord_recv_lock_resp_success(0);
+    sys.coord_decide_commit();
+
+    // The first RenameReq is delayed in the network across a coordinator restart
+    sys.coord_send_rename_req(0);
+    sys.coord_crash();
+    sys.coord_recover();
+    let new_txn = sys.get_txn_id();
+    sys.coord_resend_phase();
+
+    // The store renames under the new txn
+    assert!(sys.store_handle_rename_req(0, new_txn));
+    assert_eq!(sys.store_get_key_a(0), None);
+    assert_eq!(sys.store_get_key_aprime(0), Some(42));
+    let resps = sys.net.count(&ExecMessage::rename_resp(0, new_txn));
+
+    // The delayed old-txn request is consumed but rejected as stale
+    assert!(sys.store_handle_rename_req(0, old_txn));
+    assert!(!sys.net.contains(&ExecMessage::rename_req(0, old_txn)));
+    assert_eq!(sys.store_get_key_a(0), None);
+    assert_eq!(sys.store_get_key_aprime(0), Some(42));
+    assert_eq!(sys.get_store(0).get_last_seen_txn_id(), new_txn);
+    assert!(!sys.net.contains(&ExecMessage::rename_resp(0, old_txn)));
+    assert_eq!(sys.net.count(&ExecMessage::rename_resp(0, new_txn)), resps);
+}
+
 #[test]
 fn test_single_store_full_protocol() {
     let mut sys = ExecSystem::new(1, "A", "A'", 123);
@@ -246,3 +371,1232 @@ fn test_message_not_found() {
     assert!(!sys.store_handle_unlock_req(0, txn_id));
 }
 
+#[test]
+fn test_client_rename_success() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+
+    assert_eq!(client_rename(&mut sys), Ok(()));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    assert!(sys.is_committed());
+    for s in 0..3 {
+        assert!(!sys.store_has_key_a(s));
+        assert_eq!(sys.store_get_key_aprime(s), Some(42));
+        assert!(!sys.get_store(s).is_locked("A"));
+        assert!(!sys.get_store(s).is_locked("A'"));
+    }
+    assert!(sys.net_is_empty());
+}
+
+#[test]
+fn test_client_rename_aborted() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+
+    // Store 1 already has A', so it votes no
+    sys.store_put(1, "A'", 99);
+
+    assert_eq!(client_rename(&mut sys), Err(ProtocolError::Aborted));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    assert!(!sys.is_committed());
+    assert_eq!(sys.store_get_key_a(0), Some(42));
+    assert!(!sys.get_store(0).is_locked("A"));
+    assert!(!sys.get_store(0).is_locked("A'"));
+}
+
+#[test]
+fn test_net_settled_for_txn_across_recovery() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    let old_txn = sys.get_txn_id();
+
+    // A LockReq from the first attempt is still in flight when the coordinator restarts
+    sys.coord_send_lock_req(0);
+    sys.coord_crash();
+    sys.coord_recover();
+    let new_txn = sys.get_txn_id();
+    sys.coord_send_unlock_req(0);
+
+    assert!(!sys.net_is_settled_for_txn(old_txn));
+    assert!(!sys.net_is_settled_for_txn(new_txn));
+
+    // The stale request is consumed and ignored; only the new txn remains
+    assert!(sys.store_handle_unlock_req(0, new_txn));
+    assert!(sys.store_handle_lock_req(0, old_txn));
+    assert!(sys.net_is_settled_for_txn(old_txn));
+    assert!(!sys.net_is_settled_for_txn(new_txn));
+    assert!(!sys.net_is_empty());
+}
+
+#[test]
+fn test_prepared_store_crash_recovery() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+
+    sys.coord_send_lock_req(0);
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    assert!(sys.store_is_prepared(0, txn_id));
+    assert!(sys.coord_recv_lock_resp_success(0));
+
+    // Store crashes after voting yes: it remembers the vote and re-locks
+    sys.store_crash_recover(0);
+    assert!(sys.store_is_prepared(0, txn_id));
+    assert!(sys.get_store(0).is_locked("A"));
+    assert!(sys.get_store(0).is_locked("A'"));
+
+    // The coordinator's commit decision still applies
+    sys.coord_decide_commit();
+    sys.coord_send_rename_req(0);
+    assert!(sys.store_handle_rename_req(0, txn_id));
+    assert!(sys.coord_recv_rename_resp(0));
+    assert_eq!(sys.store_get_key_aprime(0), Some(42));
+
+    // Unlock applies the decision and clears the vote
+    sys.coord_send_unlock_req(0);
+    assert!(sys.store_handle_unlock_req(0, txn_id));
+    assert!(!sys.store_is_prepared(0, txn_id));
+    assert!(sys.coord_recv_unlock_resp(0));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+}
+
+#[test]
+fn test_unprepared_store_crash_drops_locks() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+
+    // Store votes no: nothing prepared, nothing to hold after a crash
+    sys.store_put(0, "A'", 7);
+    sys.coord_send_lock_req(0);
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    assert!(!sys.store_is_prepared(0, txn_id));
+
+    sys.store_crash_recover(0);
+    assert!(!sys.get_store(0).is_locked("A"));
+    assert!(!sys.get_store(0).is_locked("A'"));
+    assert_eq!(sys.store_get_key_a(0), Some(42));
+}
+
+#[test]
+fn test_inject_store_value_guarded() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+
+    // Store has seen nothing yet: injecting "after txn 1" is refused
+    assert!(!sys.inject_store_value_guarded(0, "A'", 7, 1));
+    assert!(!sys.store_has_key_aprime(0));
+
+    // Once the store has processed txn 1, the write goes through
+    let txn_id = sys.get_txn_id();
+    sys.coord_send_lock_req(0);
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    assert!(sys.inject_store_value_guarded(0, "B", 7, txn_id));
+    assert!(sys.get_store(0).contains_key("B"));
+
+    // Guard passes but the key is locked by the protocol: no write
+    assert!(!sys.inject_store_value_guarded(0, "A", 9, txn_id));
+    assert_eq!(sys.store_get_key_a(0), Some(42));
+}
+
+#[test]
+fn test_abort_unlocks_late_grant() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+
+    // Store 2 already has A', so it votes no
+    sys.store_put(2, "A'", 99);
+
+    for s in 0..3 {
+        sys.coord_send_lock_req(s);
+        assert!(sys.store_handle_lock_req(s, txn_id));
+    }
+    // Store 1's grant is still in flight when store 2's refusal aborts
+    assert!(sys.coord_recv_lock_resp_success(0));
+    assert!(sys.coord_recv_lock_resp_failure(2));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+    assert!(!sys.coord.has_lock(1));
+    assert!(sys.get_store(1).is_locked("A"));
+
+    // Cleanup addresses every store sent a LockReq, granted or not
+    for s in 0..3 {
+        assert!(sys.coord.is_unlock_target(s));
+        sys.coord_send_unlock_req(s);
+    }
+    for s in 0..3 {
+        assert!(sys.store_handle_unlock_req(s, txn_id));
+        assert!(sys.coord_recv_unlock_resp(s));
+    }
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    for s in 0..3 {
+        assert!(!sys.get_store(s).is_locked("A"));
+        assert!(!sys.get_store(s).is_locked("A'"));
+    }
+}
+
+#[test]
+fn test_abort_transaction_mid_preparing() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+
+    // Stores 0 and 1 lock, but only store 0's grant reaches the coordinator;
+    // store 2's LockReq is still in flight
+    for s in 0..3 {
+        sys.coord_send_lock_req(s);
+    }
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    assert!(sys.store_handle_lock_req(1, txn_id));
+    assert!(sys.coord_recv_lock_resp_success(0));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Preparing);
+
+    sys.abort_transaction();
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    assert!(!sys.is_committed());
+
+    // Store 2's LockReq lands after the abort, twice; both copies are refused
+    let late = ExecMessage::lock_req(2, txn_id);
+    assert!(sys.net.contains(&late));
+    assert!(sys.net_duplicate(&late));
+    for _ in 0..2 {
+        assert!(sys.store_handle_lock_req(2, txn_id));
+    }
+    assert_eq!(sys.net.count(&ExecMessage::lock_resp(2, false, txn_id)), 2);
+    for s in 0..3 {
+        assert!(!sys.get_store(s).is_locked("A"), "store {}", s);
+        assert!(!sys.get_store(s).is_locked("A'"), "store {}", s);
+        assert_eq!(sys.store_get_key_a(s), Some(42));
+        assert!(!sys.store_has_key_aprime(s));
+    }
+    assert!(sys.check_invariant().is_ok());
+}
+
+#[test]
+fn test_client_rename_stuck_releases_locks() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+
+    // Store 1 has seen a newer txn, so it ignores the LockReq and never votes
+    sys.store_update_txn_id(1, 5);
+
+    assert_eq!(client_rename(&mut sys), Err(ProtocolError::Stuck));
+    assert!(!sys.is_committed());
+    // Store 0 granted its lock before the run got stuck; it is released
+    assert!(!sys.get_store(0).is_locked("A"));
+    assert!(!sys.get_store(0).is_locked("A'"));
+    assert_eq!(sys.store_get_key_a(0), Some(42));
+}
+
+#[test]
+fn test_client_rename_abort_releases_later_grants() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+
+    // Store 0 votes no; stores 1 and 2 vote yes after it
+    sys.store_put(0, "A'", 99);
+
+    assert_eq!(client_rename(&mut sys), Err(ProtocolError::Aborted));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    for s in 1..3 {
+        assert!(!sys.get_store(s).is_locked("A"));
+        assert!(!sys.get_store(s).is_locked("A'"));
+        assert_eq!(sys.store_get_key_a(s), Some(42));
+    }
+    assert!(sys.net_is_empty());
+}
+
+#[test]
+fn test_lock_retry_after_loss() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    let req = ExecMessage::lock_req(0, txn_id);
+
+    // First LockReq to store 0 is lost in the network
+    sys.coord_send_lock_with_retry(0, 1);
+    assert_eq!(sys.net.count(&req), 1);
+    assert!(sys.net_lose(&req));
+    assert!(!sys.store_handle_lock_req(0, txn_id));
+
+    // Retry resends it; a second call with nothing lost sends nothing more
+    sys.coord_send_lock_with_retry(0, 1);
+    sys.coord_send_lock_with_retry(0, 1);
+    assert_eq!(sys.net.count(&req), 1);
+
+    sys.coord_send_lock_with_retry(1, 1);
+    for s in 0..2 {
+        assert!(sys.store_handle_lock_req(s, txn_id));
+        assert!(sys.coord_recv_lock_resp_success(s));
+    }
+
+    // Protocol proceeds to commit
+    sys.coord_decide_commit();
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Committed);
+}
+
+#[test]
+fn test_lock_retry_bounds_in_flight_copies() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    let req = ExecMessage::lock_req(0, txn_id);
+
+    sys.coord_send_lock_with_retry(0, 3);
+    assert_eq!(sys.net.count(&req), 3);
+
+    // One copy lost, one duplicated: no top-up needed
+    assert!(sys.net_lose(&req));
+    assert!(sys.net_duplicate(&req));
+    sys.coord_send_lock_with_retry(0, 3);
+    assert_eq!(sys.net.count(&req), 3);
+
+    // Every copy is handled; the coordinator credits the store once and
+    // the surplus responses are dropped by the pump
+    pump_until_quiescent(&mut sys);
+    assert!(sys.coord.has_lock(0));
+    assert!(sys.net_is_empty());
+
+    // No attempts, no send
+    let before = sys.net.len();
+    sys.coord_send_lock_with_retry(0, 0);
+    assert_eq!(sys.net.len(), before);
+}
+
+/// Round-robin `pump` over all stores until no store has pending messages
+fn pump_until_quiescent(sys: &mut ExecSystem) {
+    loop {
+        let mut progressed = false;
+        for s in 0..sys.num_stores() as u64 {
+            progressed |= sys.pump(s);
+        }
+        if !progressed {
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_pump_drives_happy_path() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+
+    for s in 0..3 {
+        sys.coord_send_lock_req(s);
+    }
+    pump_until_quiescent(&mut sys);
+    for s in 0..3 {
+        assert!(sys.coord.has_lock(s));
+    }
+
+    sys.coord_decide_commit();
+    for s in 0..3 {
+        sys.coord_send_rename_req(s);
+    }
+    pump_until_quiescent(&mut sys);
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+
+    for s in 0..3 {
+        sys.coord_send_unlock_req(s);
+    }
+    pump_until_quiescent(&mut sys);
+
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    assert!(sys.net_is_empty());
+    for s in 0..3 {
+        assert_eq!(sys.store_get_key_aprime(s), Some(42));
+    }
+}
+
+#[test]
+fn test_pump_drops_stale_response() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    sys.coord_send_lock_req(0);
+
+    // Response from an older transaction is dropped, not credited
+    sys.net.send(ExecMessage::lock_resp(0, true, 0));
+    assert!(sys.pump(0)); // LockReq handled
+    assert!(sys.pump(0)); // stale LockResp dropped
+    assert!(!sys.coord.has_lock(0));
+    assert!(sys.pump(0)); // current LockResp consumed
+    assert!(sys.coord.has_lock(0));
+    assert!(sys.net_is_empty());
+    assert!(!sys.pump(0));
+}
+
+#[test]
+fn test_swapped_response_not_credited() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.coord_send_lock_req(1);
+    let txn_id = sys.get_txn_id();
+    assert!(sys.store_handle_lock_req(1, txn_id));
+
+    // Store 0 answers in place of store 1
+    assert!(sys.net_swap_response(1, 0));
+    assert!(!sys.net.contains(&ExecMessage::lock_resp(1, true, txn_id)));
+    assert!(sys.net.contains(&ExecMessage::lock_resp(0, true, txn_id)));
+
+    // Coordinator expecting store 1 does not credit it
+    assert!(!sys.coord_recv_lock_resp_success(1));
+    assert!(!sys.coord.has_lock(1));
+    assert!(!sys.coord.has_lock(0));
+
+    // Nothing left to swap
+    assert!(!sys.net_swap_response(1, 0));
+}
+
+#[test]
+fn test_check_invariant_happy_path() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    assert_eq!(sys.check_invariant(), Ok(()));
+
+    for s in 0..2 {
+        sys.coord_send_lock_req(s);
+        assert!(sys.store_handle_lock_req(s, txn_id));
+        assert!(sys.coord_recv_lock_resp_success(s));
+        assert_eq!(sys.check_invariant(), Ok(()));
+    }
+    sys.coord_decide_commit();
+    assert_eq!(sys.check_invariant(), Ok(()));
+
+    for s in 0..2 {
+        sys.coord_send_rename_req(s);
+        assert!(sys.store_handle_rename_req(s, txn_id));
+        assert!(sys.coord_recv_rename_resp(s));
+        assert_eq!(sys.check_invariant(), Ok(()));
+    }
+    for s in 0..2 {
+        sys.coord_send_unlock_req(s);
+        assert!(sys.store_handle_unlock_req(s, txn_id));
+        assert!(sys.coord_recv_unlock_resp(s));
+        assert_eq!(sys.check_invariant(), Ok(()));
+    }
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+}
+
+#[test]
+fn test_check_invariant_catches_corruption() {
+    // Both keys present at a store
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.store_put(1, "A'", 7);
+    let err = sys.check_invariant().unwrap_err();
+    assert!(err.contains("store 1"), "{}", err);
+
+    // Lock left behind after Done
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    assert_eq!(client_rename(&mut sys), Ok(()));
+    assert_eq!(sys.check_invariant(), Ok(()));
+    sys.stores[0].lock("A'");
+    assert!(sys.check_invariant().unwrap_err().contains("Done"));
+
+    // Phase disagrees with the WAL
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    sys.coord_send_lock_req(0);
+    sys.coord.wal_committed = true;
+    assert!(sys.check_invariant().unwrap_err().contains("WAL"));
+}
+
+#[test]
+fn test_migration_happy_path() {
+    // Three stores; only 0 and 2 take part
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    sys.store_put(0, "K", 7);
+    let txn_id = sys.get_txn_id();
+
+    sys.coord_start_migration(0, 2, "K");
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Preparing);
+    assert_eq!(sys.pending_participants(), vec![0, 2]);
+    assert!(sys.store_handle_migration_lock_req(0, txn_id));
+    assert!(sys.store_handle_migration_lock_req(2, txn_id));
+    assert!(sys.get_store(0).is_locked("K"));
+    assert!(sys.get_store(2).is_locked("K"));
+
+    // src votes with the value it hands over; nothing else carries it
+    assert!(sys.net.contains(&ExecMessage::migrate_vote(0, txn_id, 7)));
+    assert!(sys.coord_recv_migration_vote(0));
+    assert!(!sys.coord.all_votes_in());
+    assert!(sys.coord_recv_lock_resp_success(2));
+    assert!(sys.coord.all_votes_in());
+    sys.coord_decide_commit();
+
+    assert!(sys.coord_send_migration_apply());
+    assert!(sys.net.contains(&ExecMessage::migrate_apply(2, txn_id, 7)));
+    for s in [0, 2] {
+        assert!(sys.store_handle_migration_apply_req(s, txn_id));
+        assert!(sys.coord_recv_migration_apply_resp(s));
+    }
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+
+    for s in [0, 2] {
+        sys.coord_send_unlock_req(s);
+        assert!(sys.store_handle_migration_unlock_req(s, txn_id));
+        assert!(sys.coord_recv_migration_unlock_resp(s));
+    }
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+
+    // Value moved intact and exists at exactly one store
+    assert!(sys.migration_exactly_one());
+    assert!(!sys.get_store(0).contains_key("K"));
+    assert_eq!(sys.get_store(2).get("K"), Some(7));
+    assert!(!sys.get_store(0).is_locked("K"));
+    assert!(!sys.get_store(2).is_locked("K"));
+    // Store 1 was never involved
+    assert!(!sys.get_store(1).contains_key("K"));
+}
+
+#[test]
+fn test_migration_aborts_when_destination_has_key() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.store_put(0, "K", 7);
+    sys.store_put(1, "K", 9);
+    let txn_id = sys.get_txn_id();
+
+    sys.coord_start_migration(0, 1, "K");
+    assert!(sys.store_handle_migration_lock_req(0, txn_id));
+    assert!(sys.store_handle_migration_lock_req(1, txn_id));
+    assert!(!sys.get_store(1).is_locked("K"));
+
+    assert!(sys.coord_recv_migration_vote(0));
+    assert!(sys.coord_recv_lock_resp_failure(1));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+
+    // Both stores were sent a LockReq, so both are unlocked
+    for s in 0..2 {
+        assert!(sys.coord.is_unlock_target(s));
+        sys.coord_send_unlock_req(s);
+        assert!(sys.store_handle_migration_unlock_req(s, txn_id));
+    }
+    assert!(sys.coord_recv_migration_unlock_resp(0));
+    assert!(sys.coord_recv_migration_unlock_resp(1));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+
+    assert!(!sys.get_store(0).is_locked("K"));
+    assert_eq!(sys.get_store(0).get("K"), Some(7));
+    assert_eq!(sys.get_store(1).get("K"), Some(9));
+}
+
+#[test]
+fn test_migration_apply_is_idempotent() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.store_put(0, "K", 7);
+    let txn_id = sys.get_txn_id();
+
+    sys.coord_start_migration(0, 1, "K");
+    assert!(sys.store_handle_migration_lock_req(0, txn_id));
+    assert!(sys.store_handle_migration_lock_req(1, txn_id));
+    assert!(sys.coord_recv_migration_vote(0));
+    assert!(sys.coord_recv_lock_resp_success(1));
+    sys.coord_decide_commit();
+
+    // Duplicated apply requests are answered again without changing the data
+    assert!(sys.coord_send_migration_apply());
+    assert!(sys.net_duplicate(&ExecMessage::rename_req(0, txn_id)));
+    assert!(sys.net_duplicate(&ExecMessage::migrate_apply(1, txn_id, 7)));
+    for s in [0, 1] {
+        assert!(sys.store_handle_migration_apply_req(s, txn_id));
+        assert!(sys.store_handle_migration_apply_req(s, txn_id));
+    }
+    assert!(sys.migration_exactly_one());
+    assert_eq!(sys.get_store(1).get("K"), Some(7));
+    assert!(sys.coord_recv_migration_apply_resp(0));
+    assert!(sys.coord_recv_migration_apply_resp(1));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+}
+
+#[test]
+fn test_pump_drives_migration() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    sys.store_put(0, "K", 7);
+    let txn_id = sys.get_txn_id();
+
+    sys.coord_start_migration(0, 2, "K");
+    pump_until_quiescent(&mut sys);
+    assert!(sys.coord.all_votes_in());
+    sys.coord_decide_commit();
+
+    assert!(sys.coord_send_migration_apply());
+    pump_until_quiescent(&mut sys);
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+
+    for s in [0, 2] {
+        sys.coord_send_unlock_req(s);
+    }
+    pump_until_quiescent(&mut sys);
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    assert!(sys.net_is_empty());
+
+    assert!(sys.migration_exactly_one());
+    assert_eq!(sys.get_store(2).get("K"), Some(7));
+    for s in [0, 2] {
+        assert!(!sys.get_store(s).is_locked("K"));
+        assert!(sys.get_store(s).is_released(txn_id));
+    }
+}
+
+#[test]
+fn test_migration_lock_votes_no_when_lock_fails() {
+    // Held outside the txn at src
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.store_put(0, "K", 7);
+    let txn_id = sys.get_txn_id();
+    sys.stores[0].lock("K");
+    sys.coord_start_migration(0, 1, "K");
+    assert!(sys.store_handle_migration_lock_req(0, txn_id));
+    assert!(sys.net.contains(&ExecMessage::lock_resp(0, false, txn_id)));
+    assert!(!sys.get_store(0).is_prepared(txn_id));
+
+    // Frozen dst
+    sys.stores[1].freeze();
+    assert!(sys.store_handle_migration_lock_req(1, txn_id));
+    assert!(sys.net.contains(&ExecMessage::lock_resp(1, false, txn_id)));
+    assert!(!sys.get_store(1).is_locked("K"));
+}
+
+#[test]
+fn test_migration_lock_refused_after_release() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.store_put(0, "K", 7);
+    let txn_id = sys.get_txn_id();
+    sys.coord_start_migration(0, 1, "K");
+
+    // The abort's UnlockReq overtakes dst's LockReq
+    sys.coord_send_unlock_req(1);
+    assert!(sys.store_handle_migration_unlock_req(1, txn_id));
+    assert!(sys.get_store(1).is_released(txn_id));
+    assert!(sys.store_handle_migration_lock_req(1, txn_id));
+    assert!(!sys.get_store(1).is_locked("K"));
+    assert!(sys.net.contains(&ExecMessage::lock_resp(1, false, txn_id)));
+}
+
+#[test]
+fn test_pending_participants_mid_preparing() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    assert_eq!(sys.participants(), vec![0, 1]);
+    assert!(sys.pending_participants().is_empty());
+
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(1);
+    assert_eq!(sys.pending_participants(), vec![0, 1]);
+
+    // Store 0 locks and its grant arrives; store 1 has not answered
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    assert!(sys.coord_recv_lock_resp_success(0));
+    assert_eq!(sys.pending_participants(), vec![1]);
+
+    // Once both have voted, the commit phase waits on every rename ack
+    assert!(sys.store_handle_lock_req(1, txn_id));
+    assert!(sys.coord_recv_lock_resp_success(1));
+    assert!(sys.pending_participants().is_empty());
+    sys.coord_decide_commit();
+    assert_eq!(sys.pending_participants(), vec![0, 1]);
+
+    sys.coord_send_rename_req(1);
+    assert!(sys.store_handle_rename_req(1, txn_id));
+    assert!(sys.coord_recv_rename_resp(1));
+    assert_eq!(sys.pending_participants(), vec![0]);
+}
+
+#[test]
+fn test_pending_participants_skip_excluded() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    sys.coord.exclude_participant(1, Ghost::assume_new());
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(2);
+    assert_eq!(sys.pending_participants(), vec![0, 2]);
+    assert_eq!(sys.expected_inflight_for_phase(), 2);
+}
+
+#[test]
+fn test_participants_follow_in_txn() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    sys.coord.exclude_participant(1, Ghost::assume_new());
+    assert_eq!(sys.participants(), vec![0, 2]);
+
+    // A migration involves only src and dst
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    sys.store_put(0, "K", 7);
+    sys.coord_start_migration(0, 2, "K");
+    assert_eq!(sys.participants(), vec![0, 2]);
+}
+
+#[test]
+fn test_expected_inflight_along_happy_path() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    let check = |sys: &ExecSystem| assert_eq!(sys.num_inflight(), sys.expected_inflight_for_phase());
+    check(&sys);
+
+    // Prepare: one request or response per store that has not voted
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(1);
+    check(&sys);
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    check(&sys);
+    assert!(sys.coord_recv_lock_resp_success(0));
+    check(&sys);
+    assert!(sys.store_handle_lock_req(1, txn_id));
+    assert!(sys.coord_recv_lock_resp_success(1));
+    check(&sys);
+
+    // Commit
+    sys.coord_decide_commit();
+    sys.coord_send_rename_req(0);
+    sys.coord_send_rename_req(1);
+    check(&sys);
+    assert!(sys.store_handle_rename_req(0, txn_id));
+    assert!(sys.coord_recv_rename_resp(0));
+    check(&sys);
+    assert!(sys.store_handle_rename_req(1, txn_id));
+    assert!(sys.coord_recv_rename_resp(1));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+
+    // Cleanup
+    sys.coord_send_unlock_req(0);
+    sys.coord_send_unlock_req(1);
+    check(&sys);
+    assert!(sys.store_handle_unlock_req(0, txn_id));
+    assert!(sys.coord_recv_unlock_resp(0));
+    check(&sys);
+    assert!(sys.store_handle_unlock_req(1, txn_id));
+    assert!(sys.coord_recv_unlock_resp(1));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    check(&sys);
+    assert_eq!(sys.num_inflight(), 0);
+}
+
+#[test]
+fn test_expected_inflight_detects_lost_message() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(1);
+    assert!(sys.net_lose(&ExecMessage::lock_req(1, txn_id)));
+    assert_eq!(sys.num_inflight(), 1);
+    assert_eq!(sys.expected_inflight_for_phase(), 2);
+}
+
+#[test]
+fn test_set_network_sets_up_scenario() {
+    // Coordinator is Preparing with both lock responses waiting
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(1);
+    let txn_id = sys.get_txn_id();
+    let msgs = vec![
+        ExecMessage::lock_resp(0, true, txn_id),
+        ExecMessage::lock_resp(1, true, txn_id),
+    ];
+    let expected: Vec<ExecMessage> = msgs.iter().map(|m| m.clone()).collect();
+    sys.set_network(msgs);
+
+    assert!(sys.net.messages == expected);
+    assert_eq!(sys.net.seqs(), [0, 1]);
+    assert_eq!(sys.num_inflight(), 2);
+
+    // The scripted responses drive the coordinator as if the stores had sent them
+    assert!(sys.coord_recv_lock_resp_success(0));
+    assert!(sys.coord_recv_lock_resp_success(1));
+    sys.coord_decide_commit();
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Committed);
+}
+
+#[test]
+fn test_coord_resend_phase_after_recovery() {
+    for commit in [true, false] {
+        let mut sys = ExecSystem::new(3, "A", "A'", 42);
+        let txn_id = sys.get_txn_id();
+        for s in 0..3 {
+            sys.coord_send_lock_req(s);
+            sys.store_handle_lock_req(s, txn_id);
+            assert!(sys.coord_recv_lock_resp_success(s));
+        }
+        if commit {
+            sys.coord_decide_commit();
+        }
+
+        sys.coord_crash();
+        sys.coord_recover();
+        let new_txn = sys.get_txn_id();
+        assert_eq!(new_txn, txn_id + 1);
+        sys.coord_resend_phase();
+
+        // Fresh requests of the resumed phase's kind, at the new txn id only
+        for s in 0..3 {
+            let rename = ExecMessage::rename_req(s, new_txn);
+            let unlock = ExecMessage::unlock_req(s, new_txn);
+            assert_eq!(sys.net.contains(&rename), commit, "store {}", s);
+            assert_eq!(sys.net.contains(&unlock), !commit, "store {}", s);
+            assert!(!sys.net.contains(&ExecMessage::rename_req(s, txn_id)));
+        }
+    }
+}
+
+#[test]
+fn test_total_value_sum_conserved_by_rename() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    sys.store_put(1, "other", 8);
+    assert_eq!(sys.total_value_sum(), 3 * 42 + 8);
+
+    assert_eq!(client_rename(&mut sys), Ok(()));
+    assert_eq!(sys.total_value_sum(), 3 * 42 + 8);
+
+    // An empty store adds nothing
+    assert_eq!(KvStore::new().value_sum(), 0);
+
+    // Saturates instead of overflowing
+    let mut big = ExecSystem::new(2, "A", "A'", u64::MAX);
+    assert_eq!(big.total_value_sum(), u64::MAX);
+    assert_eq!(client_rename(&mut big), Ok(()));
+    assert_eq!(big.total_value_sum(), u64::MAX);
+}
+
+#[test]
+fn test_run_with_model_reliable() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    assert_eq!(sys.run_with_model(Box::new(Reliable), 10), Ok(()));
+    for s in 0..3 {
+        assert_eq!(sys.store_get_key_aprime(s), Some(42));
+        assert!(!sys.get_store(s).is_locked("A'"));
+    }
+    assert!(sys.net_is_empty());
+}
+
+#[test]
+fn test_run_with_model_lossy_completes_after_retries() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    assert_eq!(sys.run_with_model(Box::new(LossyUniform::new(0.3, 7)), 100), Ok(()));
+
+    // Some messages were lost, so at least one request had to be re-sent
+    let lock_reqs = sys
+        .net
+        .log()
+        .iter()
+        .filter(|op| matches!(op, kv_store::NetOp::Send(ExecMessage::LockReq { .. })))
+        .count();
+    assert!(lock_reqs > 3);
+    for s in 0..3 {
+        assert!(!sys.store_has_key_a(s));
+        assert_eq!(sys.store_get_key_aprime(s), Some(42));
+        assert!(!sys.get_store(s).is_locked("A"));
+        assert!(!sys.get_store(s).is_locked("A'"));
+    }
+}
+
+#[test]
+fn test_run_with_model_duplicating() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    assert_eq!(sys.run_with_model(Box::new(DuplicatingUniform::new(0.5, 7)), 10), Ok(()));
+    for s in 0..3 {
+        assert_eq!(sys.store_get_key_aprime(s), Some(42));
+    }
+    assert!(sys.net_is_empty());
+}
+
+#[test]
+fn test_run_with_model_gives_up_after_max_rounds() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    assert_eq!(sys.run_with_model(Box::new(LossyUniform::new(1.0, 0)), 5), Err(ProtocolError::Stuck));
+    assert!(!sys.is_committed());
+}
+
+#[test]
+fn test_installed_model_sees_hand_driven_sends() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    assert!(sys.set_model(Box::new(LossyUniform::new(1.0, 0))).is_none());
+
+    // Both the coordinator's request and the store's response are lost
+    sys.coord_send_lock_req(0);
+    assert!(sys.net_is_empty());
+    sys.net.send(ExecMessage::lock_req(0, sys.get_txn_id()));
+    assert!(sys.store_handle_lock_req(0, sys.get_txn_id()));
+    assert!(sys.net_is_empty());
+
+    // With the model removed, sends are delivered again
+    assert!(sys.clear_model().is_some());
+    sys.coord_send_lock_req(1);
+    assert!(!sys.net_is_empty());
+}
+
+#[test]
+fn test_run_with_model_restores_installed_model() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.set_model(Box::new(DuplicatingUniform::new(1.0, 0)));
+    assert_eq!(sys.run_with_model(Box::new(Reliable), 10), Ok(()));
+
+    // The duplicating model is back in place once the run is over
+    let mut next = ExecSystem::new(1, "B", "B'", 1);
+    next.set_model(sys.clear_model().unwrap());
+    next.coord_send_lock_req(0);
+    assert_eq!(next.num_inflight(), 2);
+}
+
+#[test]
+fn test_clone_state_is_independent() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(1);
+    assert!(sys.pump(0));
+
+    let mut fork = sys.clone_state();
+    assert_eq!(fork.num_stores(), 2);
+    assert_eq!(fork.get_txn_id(), sys.get_txn_id());
+    assert_eq!(fork.get_coord_phase(), sys.get_coord_phase());
+    assert_eq!(fork.num_inflight(), sys.num_inflight());
+    assert!(fork.get_store(0).is_locked("A"));
+    assert_eq!(fork.net.log().len(), sys.net.log().len());
+
+    // Finishing the protocol on the fork leaves the original mid-flight
+    assert_eq!(client_rename(&mut fork), Ok(()));
+    assert_eq!(fork.store_get_key_aprime(1), Some(42));
+    assert!(sys.store_has_key_a(1));
+    assert!(!sys.store_has_key_aprime(1));
+    assert!(sys.get_store(0).is_locked("A"));
+    assert!(!sys.is_committed());
+
+    // ...and driving the original does not touch the fork
+    let fork_txn = fork.get_txn_id();
+    sys.store_put(1, "other", 7);
+    sys.coord_crash();
+    assert_eq!(fork.get_store(1).get("other"), None);
+    assert_eq!(fork.get_txn_id(), fork_txn);
+    assert!(fork.is_committed());
+}
+
+#[test]
+fn test_lock_req_refused_when_key_held_outside_txn() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.stores[1].lock("A");
+
+    assert_eq!(client_rename(&mut sys), Err(ProtocolError::Aborted));
+    assert_eq!(sys.store_get_key_a(0), Some(42));
+    assert_eq!(sys.store_get_key_a(1), Some(42));
+    assert!(!sys.get_store(0).is_locked("A"));
+}
+
+#[test]
+fn test_run_until_quiescent_happy_path() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+
+    // 3 lock sends + 3 lock deliveries + 3 votes + commit, then 6 each for rename and unlock
+    assert_eq!(sys.run_until_quiescent(100), Ok(CoordPhase::Done));
+    assert!(sys.is_committed());
+    assert!(sys.net_is_empty());
+    for s in 0..3 {
+        assert_eq!(sys.store_get_key_aprime(s), Some(42));
+        assert!(!sys.store_has_key_a(s));
+    }
+    assert_eq!(sys.check_invariant(), Ok(()));
+
+    // A too-small budget stops with work still pending
+    let mut short = ExecSystem::new(3, "A", "A'", 42);
+    assert_eq!(short.run_until_quiescent(5), Err(QuiescenceError::StepLimit));
+    assert_eq!(short.run_until_quiescent(100), Ok(CoordPhase::Done));
+}
+
+#[test]
+fn test_frozen_store_votes_no_and_the_txn_aborts() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    sys.stores[1].freeze();
+
+    // The frozen store cannot take the locks, so it refuses instead of
+    // granting a vote it could not honour at rename time
+    assert_eq!(sys.run_until_quiescent(100), Ok(CoordPhase::Done));
+    assert!(!sys.is_committed());
+    for s in 0..2 {
+        assert_eq!(sys.store_get_key_a(s), Some(42));
+        assert!(!sys.store_has_key_aprime(s));
+    }
+    assert!(!sys.get_store(0).is_locked("A"));
+}
+
+#[test]
+fn test_frozen_store_does_not_ack_rename_or_unlock() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    for s in 0..2 {
+        sys.coord_send_lock_req(s);
+        assert!(sys.store_handle_lock_req(s, txn_id));
+        assert!(sys.coord_recv_lock_resp_success(s));
+    }
+    sys.coord_decide_commit();
+    sys.coord_send_rename_req(0);
+    sys.coord_send_rename_req(1);
+
+    // Frozen after voting yes: the rename is consumed but not performed,
+    // so no ack reaches the coordinator
+    sys.stores[1].freeze();
+    assert!(sys.store_handle_rename_req(1, txn_id));
+    assert_eq!(sys.store_get_key_a(1), Some(42));
+    assert!(!sys.coord_recv_rename_resp(1));
+
+    // Unfrozen, the resent rename goes through
+    sys.stores[1].unfreeze();
+    assert!(sys.store_handle_rename_req(0, txn_id));
+    assert!(sys.coord_recv_rename_resp(0));
+    sys.coord_send_rename_req(1);
+    assert!(sys.store_handle_rename_req(1, txn_id));
+    assert!(sys.coord_recv_rename_resp(1));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+
+    // A frozen store keeps its locks and does not ack the unlock
+    sys.coord_send_unlock_req(1);
+    sys.stores[1].freeze();
+    assert!(sys.store_handle_unlock_req(1, txn_id));
+    assert!(sys.get_store(1).is_locked("A"));
+    assert!(!sys.coord_recv_unlock_resp(1));
+}
+
+#[test]
+fn test_run_until_quiescent_reports_deadlock_when_all_messages_lost() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    for s in 0..3 {
+        sys.coord_send_lock_req(s);
+    }
+    for s in 0..3 {
+        assert!(sys.net_lose(&ExecMessage::lock_req(s, txn_id)));
+    }
+
+    // Every request was already sent once, so nothing is left to do
+    assert_eq!(sys.run_until_quiescent(100), Err(QuiescenceError::Deadlock));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Preparing);
+    assert!(!sys.is_committed());
+}
+
+#[test]
+fn test_export_lock_state_after_partial_lock_phase() {
+    let mut sys = ExecSystem::new(3, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    for s in 0..3 {
+        sys.coord_send_lock_req(s);
+    }
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    assert!(sys.store_handle_lock_req(1, txn_id));
+
+    // A recovering coordinator learns which stores granted it a lock
+    for s in 0..2 {
+        let mut exported = sys.get_store(s).export_lock_state();
+        exported.sort();
+        assert_eq!(exported, vec![("A".to_string(), Some(txn_id)), ("A'".to_string(), Some(txn_id))]);
+    }
+    assert!(sys.get_store(2).export_lock_state().is_empty());
+}
+
+#[test]
+fn test_one_phase_decide_commit() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    for s in 0..2 {
+        sys.coord_send_lock_req(s);
+        assert!(sys.store_handle_lock_req(s, txn_id));
+    }
+    for s in 0..2 {
+        assert!(sys.coord_recv_lock_resp_success(s));
+    }
+    sys.coord_decide_commit();
+    for s in 0..2 {
+        sys.coord_send_rename_req(s);
+        assert!(sys.store_handle_rename_req(s, txn_id));
+    }
+    for s in 0..2 {
+        assert!(sys.coord_recv_rename_resp(s));
+    }
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+
+    // Every store has renamed, so each is a no-op participant: the Decide
+    // only releases its locks, answered by a single UnlockResp
+    for s in 0..2 {
+        sys.coord_send_decide(s);
+        assert!(sys.store_handle_decide(s, true, txn_id));
+        assert!(!sys.get_store(s).is_locked("A"));
+        assert!(!sys.get_store(s).is_locked("A'"));
+        assert!(!sys.net.contains(&ExecMessage::rename_resp(s, txn_id)));
+    }
+    for s in 0..2 {
+        assert!(sys.coord_recv_unlock_resp(s));
+    }
+
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    assert!(sys.is_committed());
+    assert!(sys.net_is_empty());
+    for s in 0..2 {
+        assert_eq!(sys.store_get_key_aprime(s), Some(42));
+        assert!(!sys.store_has_key_a(s));
+    }
+}
+
+#[test]
+fn test_one_phase_decide_commit_waits_for_rename() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    sys.coord_send_lock_req(0);
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    assert!(sys.coord_recv_lock_resp_success(0));
+    sys.coord_decide_commit();
+
+    // A commit Decide that reaches a store before its rename is dropped: the
+    // store still holds A, so it is not a no-op participant
+    sys.net.send(ExecMessage::decide(0, true, txn_id));
+    assert!(sys.store_handle_decide(0, true, txn_id));
+    assert!(sys.net_is_empty());
+    assert!(sys.get_store(0).is_locked("A"));
+    assert!(sys.get_store(0).is_locked("A'"));
+    assert_eq!(sys.store_get_key_a(0), Some(42));
+}
+
+#[test]
+fn test_one_phase_decide_abort_releases_locks() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    sys.coord_send_lock_req(0);
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    assert!(sys.coord_recv_lock_resp_success(0));
+    sys.stores[1].lock("A");
+    sys.coord_send_lock_req(1);
+    assert!(sys.store_handle_lock_req(1, txn_id));
+    assert!(sys.coord_recv_lock_resp_failure(1));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Cleanup);
+
+    sys.coord_send_decide(0);
+    assert!(sys.net.contains(&ExecMessage::decide(0, false, txn_id)));
+    assert!(!sys.net.contains(&ExecMessage::decide(1, false, txn_id)));
+    assert!(sys.store_handle_decide(0, false, txn_id));
+    assert!(!sys.get_store(0).is_locked("A"));
+    assert_eq!(sys.store_get_key_a(0), Some(42));
+    assert!(sys.coord_recv_unlock_resp(0));
+
+    // Store 1 refused, but it was sent a LockReq, so it is told the outcome too
+    sys.coord_send_decide(1);
+    assert!(sys.store_handle_decide(1, false, txn_id));
+    // The foreign lock on A is not the txn's to release
+    assert!(sys.get_store(1).is_locked("A"));
+    assert!(sys.coord_recv_unlock_resp(1));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Done);
+    assert!(!sys.is_committed());
+}
+
+#[test]
+fn test_orphan_locks_resolved_by_done() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    sys.coord_send_lock_req(0);
+    assert!(sys.store_handle_lock_req(0, txn_id));
+
+    // A' is locked before the rename gives it a value
+    assert_eq!(sys.get_store(0).orphan_locks(), vec!["A'".to_string()]);
+    assert!(sys.get_store(1).orphan_locks().is_empty());
+
+    assert_eq!(sys.run_until_quiescent(100), Ok(CoordPhase::Done));
+    for s in 0..2 {
+        assert!(sys.get_store(s).orphan_locks().is_empty());
+    }
+}
+
+#[test]
+fn test_new_with_config_bounds_network() {
+    let mut sys = ExecSystem::new_with_config(ProtocolConfig {
+        num_stores: 2,
+        key_a: "A".to_string(),
+        key_aprime: "A'".to_string(),
+        initial_value: 42,
+        network_capacity: Some(1),
+    });
+    assert_eq!(sys.num_stores(), 2);
+    assert_eq!(sys.store_get_key_a(1), Some(42));
+
+    // A second in-flight message for store 0 is dropped; store 1 still has room
+    let txn_id = sys.get_txn_id();
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(1);
+    assert_eq!(sys.net.len(), 2);
+    assert_eq!(sys.net.count(&ExecMessage::lock_req(0, txn_id)), 1);
+}
+
+#[test]
+fn test_detect_deadlock_after_lost_lock_resp() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(1);
+    assert!(sys.store_handle_lock_req(0, txn_id));
+    assert_eq!(sys.detect_deadlock(), None);
+
+    // Store 0's vote is lost; the coordinator waits on it forever
+    assert!(sys.net_lose(&ExecMessage::lock_resp(0, true, txn_id)));
+    assert_eq!(sys.run_until_quiescent(100), Err(QuiescenceError::Deadlock));
+    assert_eq!(sys.detect_deadlock(), Some(DeadlockKind::WaitingOnLostMessage));
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Preparing);
+}
+
+#[test]
+fn test_lost_request_remembered_past_log_cap() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    sys.coord_send_lock_req(0);
+    sys.coord_send_lock_req(1);
+    assert!(sys.net_lose(&ExecMessage::lock_req(0, txn_id)));
+
+    // Push the original sends out of the bounded network log
+    let other = ExecMessage::lock_req(1, txn_id);
+    for _ in 0..NET_LOG_CAP {
+        assert!(sys.net_duplicate(&other));
+        assert!(sys.net_lose(&other));
+    }
+
+    // The LockReq to store 0 still counts as sent, so it is not sent again
+    assert_eq!(sys.run_until_quiescent(100), Err(QuiescenceError::Deadlock));
+    assert_eq!(sys.detect_deadlock(), Some(DeadlockKind::WaitingOnLostMessage));
+}
+
+#[test]
+fn test_detect_deadlock_reports_orphan_lock_after_done() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    assert_eq!(sys.run_until_quiescent(100), Ok(CoordPhase::Done));
+    assert_eq!(sys.detect_deadlock(), None);
+
+    sys.stores[1].lock("A'");
+    assert_eq!(sys.detect_deadlock(), Some(DeadlockKind::AllStoresLocked));
+}
+
+/// Locks on either key left at any store
+fn any_store_locked(sys: &ExecSystem) -> bool {
+    sys.stores.iter().any(|st| st.is_locked("A") || st.is_locked("A'"))
+}
+
+#[test]
+fn test_run_with_crash_at_preparing_aborts_cleanly() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    assert_eq!(sys.run_with_crash_at(CoordPhase::Preparing), CoordPhase::Done);
+    // No commit was logged before the crash, so recovery aborts
+    assert!(!sys.is_committed());
+    assert_eq!(sys.get_txn_id(), 2);
+    for st in &sys.stores {
+        assert_eq!(st.get("A"), Some(42));
+        assert!(!st.contains_key("A'"));
+    }
+    assert!(!any_store_locked(&sys));
+}
+
+#[test]
+fn test_run_with_crash_at_committed_completes_rename() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    assert_eq!(sys.run_with_crash_at(CoordPhase::Committed), CoordPhase::Done);
+    assert!(sys.is_committed());
+    assert_eq!(sys.get_txn_id(), 2);
+    for st in &sys.stores {
+        assert!(!st.contains_key("A"));
+        assert_eq!(st.get("A'"), Some(42));
+    }
+    assert!(!any_store_locked(&sys));
+}
+
+#[test]
+fn test_run_with_crash_at_cleanup_completes_rename() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    assert_eq!(sys.run_with_crash_at(CoordPhase::Cleanup), CoordPhase::Done);
+    // The commit is durable, so recovery resumes Committed and re-drives it
+    assert!(sys.is_committed());
+    assert_eq!(sys.get_txn_id(), 2);
+    for st in &sys.stores {
+        assert_eq!(st.get("A'"), Some(42));
+    }
+    assert!(!any_store_locked(&sys));
+}
+
+#[test]
+fn test_run_with_crash_at_uncrashable_phase_runs_normally() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    assert_eq!(sys.run_with_crash_at(CoordPhase::Done), CoordPhase::Done);
+    assert!(sys.is_committed());
+    assert_eq!(sys.get_txn_id(), 1);
+}
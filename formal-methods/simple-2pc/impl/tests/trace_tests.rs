@@ -0,0 +1,237 @@
+// Runtime tests for trace recording and replay.
+// These mirror the verified tests in src/trace_v.rs but run under `cargo test`.
+
+use kv_store::{
+    format_trace, replay, run_twice_identical, CoordPhase, ExecMessage, ExecSystem, ProtocolConfig,
+    TraceAction, TracedSystem,
+};
+
+/// Compare the observable state of two systems
+fn assert_same_state(a: &ExecSystem, b: &ExecSystem) {
+    assert_eq!(a.get_coord_phase(), b.get_coord_phase());
+    assert_eq!(a.get_txn_id(), b.get_txn_id());
+    assert_eq!(a.is_committed(), b.is_committed());
+    assert_eq!(a.num_stores(), b.num_stores());
+    assert_eq!(a.net.len(), b.net.len());
+    for s in 0..a.num_stores() as u64 {
+        assert_eq!(a.store_get_key_a(s), b.store_get_key_a(s));
+        assert_eq!(a.store_get_key_aprime(s), b.store_get_key_aprime(s));
+    }
+}
+
+#[test]
+fn test_record_replay_happy_path() {
+    let mut traced = TracedSystem::new(2, "A", "A'", 42);
+    let txn_id = traced.sys().get_txn_id();
+
+    traced.coord_send_lock_req(0);
+    traced.coord_send_lock_req(1);
+    assert!(traced.store_handle_lock_req(0, txn_id));
+    assert!(traced.store_handle_lock_req(1, txn_id));
+    assert!(traced.coord_recv_lock_resp_success(0));
+    assert!(traced.coord_recv_lock_resp_success(1));
+    traced.coord_decide_commit();
+    traced.coord_send_rename_req(0);
+    traced.coord_send_rename_req(1);
+    assert!(traced.store_handle_rename_req(0, txn_id));
+    assert!(traced.store_handle_rename_req(1, txn_id));
+    assert!(traced.coord_recv_rename_resp(0));
+    assert!(traced.coord_recv_rename_resp(1));
+    traced.coord_send_unlock_req(0);
+    traced.coord_send_unlock_req(1);
+    assert!(traced.store_handle_unlock_req(0, txn_id));
+    assert!(traced.store_handle_unlock_req(1, txn_id));
+    assert!(traced.coord_recv_unlock_resp(0));
+    assert!(traced.coord_recv_unlock_resp(1));
+    assert_eq!(traced.sys().get_coord_phase(), CoordPhase::Done);
+
+    // One event for `new` plus one per operation, each tagged with its phase
+    let trace = traced.trace();
+    assert_eq!(trace.len(), 20);
+    assert!(matches!(trace[0].action, TraceAction::New { num_stores: 2, .. }));
+    assert_eq!(trace[0].phase, CoordPhase::Idle);
+    assert!(matches!(trace[7].action, TraceAction::CoordDecideCommit));
+    assert_eq!(trace[7].phase, CoordPhase::Committed);
+    assert_eq!(trace[19].phase, CoordPhase::Done);
+
+    let replayed = replay(trace).unwrap();
+    assert_same_state(traced.sys(), &replayed);
+    assert_eq!(replayed.store_get_key_aprime(0), Some(42));
+}
+
+#[test]
+fn test_format_trace_happy_path() {
+    let mut traced = TracedSystem::new(1, "A", "A'", 42);
+    let txn_id = traced.sys().get_txn_id();
+    traced.coord_send_lock_req(0);
+    assert!(traced.store_handle_lock_req(0, txn_id));
+    assert!(traced.coord_recv_lock_resp_success(0));
+    traced.coord_decide_commit();
+    traced.coord_send_rename_req(0);
+    assert!(traced.store_handle_rename_req(0, txn_id));
+    assert!(traced.coord_recv_rename_resp(0));
+    traced.coord_send_unlock_req(0);
+    assert!(traced.store_handle_unlock_req(0, txn_id));
+    assert!(traced.coord_recv_unlock_resp(0));
+
+    let text = format_trace(traced.trace(), &traced.sys().net);
+
+    // One line per event plus one per network operation
+    assert_eq!(text.lines().count(), traced.trace().len() + traced.sys().net.log().len());
+    // Phases appear in protocol order
+    let pos = |needle: &str| text.find(needle).unwrap_or_else(|| panic!("{} missing:\n{}", needle, text));
+    assert!(pos("-> Idle") < pos("-> Preparing"));
+    assert!(pos("-> Preparing") < pos("-> Committed"));
+    assert!(pos("-> Committed") < pos("-> Cleanup"));
+    assert!(pos("-> Cleanup") < pos("-> Done"));
+    // Each network operation sits under the event that caused it
+    assert!(pos("CoordSendLockReq") < pos("net Send(LockReq"));
+    assert!(pos("net Send(LockReq") < pos("StoreHandleLockReq"));
+}
+
+#[test]
+fn test_replay_prefix_with_faults() {
+    let mut traced = TracedSystem::new(2, "A", "A'", 42);
+    traced.coord_send_lock_req(0);
+    traced.coord_send_lock_req(1);
+    traced.pump(0);
+    traced.coord_crash();
+    traced.coord_recover();
+    assert_eq!(traced.sys().get_coord_phase(), CoordPhase::Cleanup);
+
+    // Replaying a prefix stops at the recorded intermediate state
+    let prefix = replay(&traced.trace()[..4]).unwrap();
+    assert_eq!(prefix.get_coord_phase(), traced.trace()[3].phase);
+    assert_eq!(prefix.get_coord_phase(), CoordPhase::Preparing);
+
+    let replayed = replay(traced.trace()).unwrap();
+    assert_same_state(traced.sys(), &replayed);
+}
+
+#[test]
+fn test_replay_reports_divergent_step() {
+    let mut traced = TracedSystem::new(1, "A", "A'", 42);
+    let txn_id = traced.sys().get_txn_id();
+    traced.coord_send_lock_req(0);
+    assert!(traced.store_handle_lock_req(0, txn_id));
+    // Nothing left to handle: recorded as not enabled, which replays fine
+    assert!(!traced.store_handle_lock_req(0, txn_id));
+    assert!(replay(traced.trace()).is_ok());
+
+    // Claiming the idle step ran makes replay fail at that event
+    traced.trace[3].ok = true;
+    assert_eq!(replay(traced.trace()).err(), Some(3));
+}
+
+#[test]
+fn test_replay_migration() {
+    let mut traced = TracedSystem::new(2, "A", "A'", 42);
+    traced.store_put(0, "K", 7);
+    let txn_id = traced.sys().get_txn_id();
+
+    traced.coord_start_migration(0, 1, "K");
+    assert!(traced.store_handle_migration_lock_req(0, txn_id));
+    assert!(traced.store_handle_migration_lock_req(1, txn_id));
+    assert!(traced.coord_recv_migration_vote(0));
+    assert!(traced.coord_recv_lock_resp_success(1));
+    traced.coord_decide_commit();
+    assert!(traced.coord_send_migration_apply());
+    assert!(traced.store_handle_migration_apply_req(1, txn_id));
+
+    let replayed = replay(traced.trace()).unwrap();
+    assert_same_state(traced.sys(), &replayed);
+    assert_eq!(replayed.get_store(1).get("K"), Some(7));
+    assert_eq!(replayed.get_store(0).get("K"), Some(7));
+}
+
+#[test]
+fn test_run_schedule_abort_to_cleanup() {
+    // Store 1 already holds A', so it refuses the lock and the coordinator aborts
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    let schedule = vec![
+        TraceAction::StorePut { store_id: 1, key: "A'".to_owned(), value: 7 },
+        TraceAction::CoordSendLockReq { store_id: 0 },
+        TraceAction::CoordSendLockReq { store_id: 1 },
+        TraceAction::StoreHandleLockReq { store_id: 0, txn_id },
+        TraceAction::StoreHandleLockReq { store_id: 1, txn_id },
+        TraceAction::CoordRecvLockRespSuccess { store_id: 0 },
+        TraceAction::CoordRecvLockRespFailure { store_id: 1 },
+    ];
+    assert_eq!(sys.run_schedule(&schedule), Ok(CoordPhase::Cleanup));
+    assert!(!sys.is_committed());
+    assert!(sys.store_has_key_a(0));
+}
+
+#[test]
+fn test_run_schedule_lose_then_retry() {
+    let mut sys = ExecSystem::new(1, "A", "A'", 42);
+    let txn_id = sys.get_txn_id();
+    let resp = ExecMessage::LockResp { store: 0, success: true, txn_id };
+    let schedule = vec![
+        TraceAction::CoordSendLockReq { store_id: 0 },
+        TraceAction::StoreHandleLockReq { store_id: 0, txn_id },
+        TraceAction::NetLose { msg: resp },
+        // Retry: resend, and the already-locked store answers again
+        TraceAction::CoordSendLockReq { store_id: 0 },
+        TraceAction::StoreHandleLockReq { store_id: 0, txn_id },
+        TraceAction::CoordRecvLockRespSuccess { store_id: 0 },
+        TraceAction::CoordDecideCommit,
+    ];
+    assert_eq!(sys.run_schedule(&schedule), Ok(CoordPhase::Committed));
+}
+
+#[test]
+fn test_run_twice_identical_happy_path() {
+    let config = ProtocolConfig {
+        num_stores: 2,
+        key_a: "A".to_owned(),
+        key_aprime: "A'".to_owned(),
+        initial_value: 42,
+        network_capacity: None,
+    };
+    let txn_id = ExecSystem::new(2, "A", "A'", 42).get_txn_id();
+    let mut schedule = Vec::new();
+    for store_id in 0..2 {
+        schedule.push(TraceAction::CoordSendLockReq { store_id });
+        schedule.push(TraceAction::StoreHandleLockReq { store_id, txn_id });
+        schedule.push(TraceAction::CoordRecvLockRespSuccess { store_id });
+    }
+    schedule.push(TraceAction::CoordDecideCommit);
+    for store_id in 0..2 {
+        schedule.push(TraceAction::CoordSendRenameReq { store_id });
+        schedule.push(TraceAction::StoreHandleRenameReq { store_id, txn_id });
+        schedule.push(TraceAction::CoordRecvRenameResp { store_id });
+    }
+    for store_id in 0..2 {
+        schedule.push(TraceAction::CoordSendUnlockReq { store_id });
+        schedule.push(TraceAction::StoreHandleUnlockReq { store_id, txn_id });
+        schedule.push(TraceAction::CoordRecvUnlockResp { store_id });
+    }
+
+    // The schedule really runs to completion, so both runs cover the whole protocol
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    assert_eq!(sys.run_schedule(&schedule), Ok(CoordPhase::Done));
+    assert!(run_twice_identical(&config, &schedule));
+    // A schedule that stops early is compared just the same
+    assert!(run_twice_identical(&config, &schedule[..4]));
+}
+
+#[test]
+fn test_run_schedule_reports_first_disabled_action() {
+    let mut sys = ExecSystem::new(2, "A", "A'", 42);
+    let schedule = vec![
+        TraceAction::CoordSendLockReq { store_id: 0 },
+        // No LockResp is in flight yet
+        TraceAction::CoordRecvLockRespSuccess { store_id: 0 },
+        TraceAction::CoordDecideCommit,
+    ];
+    assert_eq!(sys.run_schedule(&schedule), Err(1));
+    // Actions before the failure were applied
+    assert_eq!(sys.get_coord_phase(), CoordPhase::Preparing);
+
+    // Committing from Idle is not enabled
+    let mut idle = ExecSystem::new(2, "A", "A'", 42);
+    assert_eq!(idle.run_schedule(&[TraceAction::CoordDecideCommit]), Err(0));
+    assert_eq!(idle.run_schedule(&[]), Ok(CoordPhase::Idle));
+}